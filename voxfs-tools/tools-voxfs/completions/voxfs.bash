@@ -0,0 +1,90 @@
+# Completions for the voxfs tools, generated by `voxfs completions bash`.
+
+# Prints the names in the image that start with a prefix: _voxfs_names <files|tags> <image> <volume> <prefix>
+_voxfs_names() {
+    local volume_args=()
+
+    if [[ -n "$3" ]]; then
+        volume_args=(--volume "$3")
+    fi
+
+    voxfs __complete-names "$2" "$4" --kind "$1" "${volume_args[@]}" 2>/dev/null
+}
+
+_voxfs_tools() {
+    local tool="${COMP_WORDS[0]##*/}"
+    local current="${COMP_WORDS[COMP_CWORD]}"
+    local previous="${COMP_WORDS[COMP_CWORD-1]}"
+    local image="" volume="" positional=0 flag="" flag_position=0
+    local i
+
+    # Find the image, the volume and how many positional arguments come before the word being completed
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            --volume) ((i++)); volume="${COMP_WORDS[i]}" ;;
+            -a|--apply|-r|--remove|-c|--create|-d|--delete|--archive|--restore|--rename|-f|-n|--name|-q|--query|--sort)
+                flag="${COMP_WORDS[i]}"; flag_position=$i ;;
+            -*) ;;
+            *)
+                if [[ -z "$image" ]]; then
+                    image="${COMP_WORDS[i]}"
+                fi
+                ((positional++))
+                ;;
+        esac
+    done
+
+    local flags
+    case "$tool" in
+        add-voxfs) flags="-n --name --volume -y --yes" ;;
+        rm-voxfs) flags="--volume -y --yes" ;;
+        read-voxfs) flags="-r --raw --hide-header --no-format --volume --tolerant" ;;
+        ls-voxfs) flags="-f -l --volume --sort --tolerant" ;;
+        tag-voxfs) flags="-c --create -d --delete -l --list -a --apply -r --remove -q --query --archive --restore --rename --archived --export-manifest --apply-manifest --volume --json --porcelain" ;;
+    esac
+
+    if [[ "$current" == -* ]]; then
+        COMPREPLY=($(compgen -W "$flags" -- "$current"))
+        return
+    fi
+
+    if [[ "$previous" == "--volume" ]]; then
+        return
+    fi
+
+    if [[ "$previous" == "--apply-manifest" ]]; then
+        COMPREPLY=($(compgen -f -- "$current"))
+        return
+    fi
+
+    if [[ -z "$image" ]]; then
+        COMPREPLY=($(compgen -f -- "$current"))
+        return
+    fi
+
+    local kind=""
+    case "$tool:$flag:$((COMP_CWORD - flag_position))" in
+        tag-voxfs:-d:1|tag-voxfs:--delete:1|tag-voxfs:--archive:1|tag-voxfs:--restore:1|tag-voxfs:--rename:1|tag-voxfs:-a:1|tag-voxfs:--apply:1|tag-voxfs:-r:1|tag-voxfs:--remove:1|ls-voxfs:-f:*)
+            kind=tags ;;
+        tag-voxfs:-a:2|tag-voxfs:--apply:2|tag-voxfs:-r:2|tag-voxfs:--remove:2)
+            kind=files ;;
+        rm-voxfs:*|read-voxfs:*)
+            if [[ $positional -eq 1 ]]; then
+                kind=files
+            fi
+            ;;
+        add-voxfs:*)
+            if [[ $positional -eq 1 ]]; then
+                COMPREPLY=($(compgen -f -- "$current"))
+            fi
+            return
+            ;;
+    esac
+
+    if [[ -n "$kind" ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(_voxfs_names "$kind" "$image" "$volume" "$current"))
+    fi
+}
+
+complete -o filenames -F _voxfs_tools add-voxfs rm-voxfs read-voxfs ls-voxfs tag-voxfs
@@ -0,0 +1,71 @@
+# Completions for the voxfs tools, generated by `voxfs completions fish`.
+
+# The image is the first argument that isn't a flag
+function __voxfs_image
+    set -l words (commandline -opc)
+    for word in $words[2..-1]
+        if not string match -q -- '-*' $word
+            echo $word
+            return
+        end
+    end
+end
+
+function __voxfs_volume
+    set -l words (commandline -opc)
+    set -l index (contains -i -- --volume $words)
+    and echo $words[(math $index + 1)]
+end
+
+function __voxfs_has_image
+    set -l image (__voxfs_image)
+    test -n "$image"
+end
+
+# Prints the names of a kind in the image that start with the token being completed
+function __voxfs_names
+    set -l image (__voxfs_image)
+    test -n "$image"; or return
+    set -l volume (__voxfs_volume)
+    if test -n "$volume"
+        voxfs __complete-names $image (commandline -ct) --kind $argv[1] --volume $volume 2>/dev/null
+    else
+        voxfs __complete-names $image (commandline -ct) --kind $argv[1] 2>/dev/null
+    end
+end
+
+for tool in add-voxfs rm-voxfs read-voxfs ls-voxfs tag-voxfs
+    complete -c $tool -l volume -x -d 'The volume to use in an image with a volume table'
+end
+
+complete -c add-voxfs -s n -l name -x -d 'The name to give the file in the image'
+complete -c add-voxfs -s y -l yes -d 'Do not ask for confirmation'
+
+complete -c rm-voxfs -n __voxfs_has_image -x -a '(__voxfs_names files)'
+complete -c rm-voxfs -s y -l yes -d 'Do not ask for confirmation'
+
+complete -c read-voxfs -n __voxfs_has_image -x -a '(__voxfs_names files)'
+complete -c read-voxfs -s r -l raw -d 'Print the raw bytes'
+complete -c read-voxfs -l hide-header -d 'Do not print the header'
+complete -c read-voxfs -l no-format -d 'Do not format the output'
+complete -c read-voxfs -l tolerant -d 'Skip unreadable tags and files'
+
+complete -c ls-voxfs -s f -x -a '(__voxfs_names tags)' -d 'Filter by tag'
+complete -c ls-voxfs -s l -d 'List the files with their metadata'
+complete -c ls-voxfs -l sort -x -a 'index name size mtime'
+complete -c ls-voxfs -l tolerant -d 'Skip unreadable tags and files'
+
+complete -c tag-voxfs -s c -l create -x -d 'Create a new tag'
+complete -c tag-voxfs -s d -l delete -x -a '(__voxfs_names tags)' -d 'Delete a tag'
+complete -c tag-voxfs -s a -l apply -x -a '(__voxfs_names tags) (__voxfs_names files)' -d 'Apply a tag to a file'
+complete -c tag-voxfs -s r -l remove -x -a '(__voxfs_names tags) (__voxfs_names files)' -d 'Remove a tag from a file'
+complete -c tag-voxfs -l archive -x -a '(__voxfs_names tags)' -d 'Hide a tag without deleting it'
+complete -c tag-voxfs -l restore -x -a '(__voxfs_names tags)' -d 'Bring back an archived tag'
+complete -c tag-voxfs -l rename -x -a '(__voxfs_names tags)' -d 'Rename a tag'
+complete -c tag-voxfs -s q -l query -x -d 'List the files matching a tag query'
+complete -c tag-voxfs -s l -l list -d 'List the tags'
+complete -c tag-voxfs -l archived -d 'List the archived tags instead'
+complete -c tag-voxfs -l json -d 'List the tags as JSON'
+complete -c tag-voxfs -l porcelain -d 'List the tags in a stable format for scripts'
+complete -c tag-voxfs -l export-manifest -d 'Print the tags and their files as a JSON manifest'
+complete -c tag-voxfs -l apply-manifest -r -F -d 'Apply the tags in a JSON manifest'
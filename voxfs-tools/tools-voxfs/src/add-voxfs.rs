@@ -1,10 +1,11 @@
 use clap::{App, Arg};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::Path;
-use std::process::exit;
-use voxfs::{Disk, INodeFlags};
-use voxfs_tool_lib::{Handler, Manager};
+use voxfs::INodeFlags;
+use voxfs_tool_lib::{
+    confirm, describe_error, fail, open_image, u64_to_sized_string, ExitCode, OpenMode,
+};
 
 const BUFFER_SIZE: usize = 4000;
 
@@ -31,139 +32,167 @@ fn main() {
                 .takes_value(true)
                 .help("The name of the file as it should be stored in the voxfs image."),
         )
+        .arg(
+            Arg::with_name("tag")
+                .short("t")
+                .long("tag")
+                .takes_value(true)
+                .value_name("TAG")
+                .help("A tag to apply to the file, its blocks are placed after those of the tag's other files."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Add the file without asking for confirmation."),
+        )
         .get_matches();
 
     let path = match arguments.value_of("image") {
         Some(p) => p,
-        None => {
-            eprintln!("An image is required.");
-            exit(1);
-        }
+        None => fail("An image is required.", ExitCode::Usage),
     };
 
-    let mut manager = Manager::new();
-    let mut handler = match Handler::new(path.to_string()) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(1);
-        }
-    };
+    let mut image = open_image(path, OpenMode::Strict);
 
-    let mut disk = match Disk::open_disk(&mut handler, &mut manager) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Disk opening error: {:?}", e);
-            exit(1);
-        }
-    };
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let mut disk = image.disk();
 
     let file_path = match arguments.value_of("file") {
         Some(f) => f.to_string(),
-        None => {
-            eprintln!("A file to add is required.");
-            exit(1);
-        }
+        None => fail("A file to add is required.", ExitCode::Usage),
     };
 
     let name = match arguments.value_of("name") {
         Some(n) => n.to_string(),
-        None => match Path::new(&file_path).file_name() {
-            Some(n) => match n.to_str() {
-                Some(n) => n.to_string(),
-                None => {
-                    eprintln!("Could not determine a file name to use for the image.");
-                    exit(1);
-                }
-            },
-            None => {
-                eprintln!("Could not determine a file name to use for the image.");
-                exit(1);
-            }
+        None => match Path::new(&file_path).file_name().and_then(|n| n.to_str()) {
+            Some(n) => n.to_string(),
+            None => fail(
+                "Could not determine a file name to use for the image.",
+                ExitCode::Usage,
+            ),
         },
     };
 
-    print!(
-        "Are you sure you wish to copy \"{}\" into the image as \"{}\": (y/n) ",
-        file_path, name
-    );
-
-    match std::io::stdout().flush() {
-        Ok(_) => (),
-        Err(_) => (),
-    }
-
-    let mut input = String::new();
-    match std::io::stdin().read_line(&mut input) {
-        Ok(_) => (),
-        Err(_) => {
-            eprintln!("Failed to read response.");
-            exit(1);
-        }
-    }
+    let tag_index = arguments
+        .value_of("tag")
+        .map(|tag| match disk.tag_with_name(tag) {
+            Some(i) => i,
+            None => fail(format!("No tag named {} exists.", tag), ExitCode::NotFound),
+        });
+
+    let file_size = match std::fs::metadata(&file_path) {
+        Ok(m) => m.len(),
+        Err(e) => fail(
+            format!("Could not open file due to error: {}", e),
+            ExitCode::Io,
+        ),
+    };
 
-    input = input.strip_suffix("\n").unwrap_or("").to_string();
-    if input.ends_with('\r') {
-        input = input.strip_suffix("\r").unwrap_or("").to_string();
+    let estimate = disk.estimate_required_blocks(&[file_size]);
+
+    if !estimate.fits() {
+        fail(
+            format!(
+                "The file needs {} but only {} is free.",
+                u64_to_sized_string(estimate.required_bytes()),
+                u64_to_sized_string(estimate.free_bytes())
+            ),
+            ExitCode::Failure,
+        );
     }
 
-    if input != "y" && input != "Y" {
+    if !confirm(
+        &format!(
+            "Are you sure you wish to copy \"{}\" into the image as \"{}\"?",
+            file_path, name
+        ),
+        arguments.is_present("yes"),
+    ) {
         println!("Will not add file.");
-        exit(0);
+        ExitCode::Success.exit();
     }
 
     let mut file = match File::open(file_path) {
         Ok(f) => f,
-        Err(e) => {
-            eprintln!("Could not open file due to error: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(
+            format!("Could not open file due to error: {}", e),
+            ExitCode::Io,
+        ),
     };
 
     let mut buffer = vec![0u8; BUFFER_SIZE];
 
     let mut amount_read = match file.read(&mut buffer) {
         Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error while reading: {}", e);
-            exit(1);
+        Err(e) => fail(format!("Error while reading: {}", e), ExitCode::Io),
+    };
+
+    let contents = buffer[..amount_read].to_vec();
+    let created = match tag_index {
+        Some(tag_index) => {
+            disk.create_new_file_in_tag(&name, INodeFlags::default(), contents, tag_index)
         }
+        None => disk.create_new_file(&name, INodeFlags::default(), contents),
     };
 
-    let file_index =
-        match disk.create_new_file(&name, INodeFlags::default(), buffer[..amount_read].to_vec()) {
-            Ok(i) => i.index(),
-            Err(e) => {
-                eprintln!("Error: {}", e);
-                exit(1);
+    let file_index = match created {
+        Ok(i) => {
+            if i.name() != name {
+                println!("A file named {} exists, adding as {}.", name, i.name());
             }
-        };
+
+            i.index()
+        }
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
+    };
 
     amount_read = match file.read(&mut buffer) {
         Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error while reading: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(format!("Error while reading: {}", e), ExitCode::Io),
     };
 
     while amount_read > 0 {
         match disk.append_file_bytes(file_index, &buffer[..amount_read].to_vec()) {
             Ok(_) => (),
-            Err(e) => {
-                eprintln!("Error: {}", e);
-                exit(1);
-            }
+            Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
         }
 
         amount_read = match file.read(&mut buffer) {
             Ok(s) => s,
-            Err(e) => {
-                eprintln!("Error while reading: {}", e);
-                exit(1);
-            }
+            Err(e) => fail(format!("Error while reading: {}", e), ExitCode::Io),
         };
     }
 
+    match disk.close() {
+        Ok(_) => (),
+        Err(e) => fail(
+            format!(
+                "Could not write the changes to the image: {}",
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
+    }
+
     println!("Successfully added file!");
 }
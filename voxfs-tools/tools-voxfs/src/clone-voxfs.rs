@@ -0,0 +1,116 @@
+use clap::{App, Arg};
+use std::path::Path;
+use voxfs::{probe, DiskHandler};
+use voxfs_tool_lib::{confirm, fail, u64_to_sized_string, ExitCode, Handler};
+
+fn main() {
+    let arguments = App::new("clone-voxfs")
+        .version("0.1.0")
+        .about("This program creates a delta image that records only the changes made to a parent image, or flattens a delta and its parents into a standalone image.")
+        .arg(
+            Arg::with_name("input")
+                .required(true)
+                .takes_value(true)
+                .help("The parent image to clone, or the delta to flatten"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the delta or flattened image to create"),
+        )
+        .arg(
+            Arg::with_name("flatten")
+                .long("flatten")
+                .help("Merge the input and every image it is a delta of into the output."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Replace an existing file at the output path without asking for confirmation."),
+        )
+        .get_matches();
+
+    let input = match arguments.value_of("input") {
+        Some(p) => p,
+        None => fail("An input image is required.", ExitCode::Usage),
+    };
+
+    let output = match arguments.value_of("output") {
+        Some(p) => p,
+        None => fail("An output path is required.", ExitCode::Usage),
+    };
+
+    if Path::new(input) == Path::new(output) {
+        fail(
+            "The output must be a different file to the input.",
+            ExitCode::Usage,
+        );
+    }
+
+    if Path::new(output).exists()
+        && !confirm(
+            &format!("A file already exists at {}, replace it?", output),
+            arguments.is_present("yes"),
+        )
+    {
+        println!("Did not create {}.", output);
+        ExitCode::Success.exit();
+    }
+
+    if arguments.is_present("flatten") {
+        flatten(input, output);
+    } else {
+        clone(input, output);
+    }
+}
+
+/// Creates a delta of the parent image at the output path.
+fn clone(parent: &str, output: &str) {
+    let handler = match Handler::new(parent.to_string()) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::NoImage),
+    };
+
+    if probe(&handler).is_none() {
+        fail(
+            format!("{} does not contain a voxfs filesystem.", parent),
+            ExitCode::NoImage,
+        );
+    }
+
+    drop(handler);
+
+    match Handler::new_delta(output.to_string(), parent.to_string()) {
+        Ok(_) => println!("Created {}, a delta of {}", output, parent),
+        Err(e) => fail(format!("Could not create the delta: {}", e), ExitCode::Io),
+    }
+}
+
+/// Copies the delta and its parents into a standalone image at the output path.
+fn flatten(input: &str, output: &str) {
+    let handler = match Handler::new(input.to_string()) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::NoImage),
+    };
+
+    if handler.parent_path().is_none() {
+        fail(format!("{} is not a delta image.", input), ExitCode::Usage);
+    }
+
+    let size = match handler.disk_size() {
+        Ok(s) => s,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+
+    match handler.flatten(output.to_string()) {
+        Ok(_) => println!(
+            "Flattened {} into {} ({})",
+            input,
+            output,
+            u64_to_sized_string(size)
+        ),
+        Err(e) => fail(format!("Could not flatten the image: {}", e), ExitCode::Io),
+    }
+}
@@ -0,0 +1,185 @@
+use clap::{App, Arg};
+use std::path::Path;
+use voxfs::{Disk, OpContext, VoxFSError, DEFAULT_BYTES_PER_INODE};
+use voxfs_tool_lib::{
+    confirm, copy_filesystem, describe_error, fail, format_options_like, inodes_per_tag,
+    is_out_of_space, open_image, print_progress, u64_to_sized_string, Allocation, ExitCode,
+    Handler, MKImageError, Manager, OpenMode,
+};
+
+/// The smallest image mkfs-voxfs will create.
+const MINIMUM_SIZE: u64 = 40_960;
+
+fn main() {
+    let arguments = App::new("compact-voxfs")
+        .version("0.1.0")
+        .about("This program copies the files and tags of a voxfs image into a new image that is only as large as they need.")
+        .arg(
+            Arg::with_name("input")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image to compact"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the compacted image to create"),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to compact in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Replace an existing file at the output path without asking for confirmation."),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help("Show how many of the files and tags have been copied."),
+        )
+        .get_matches();
+
+    let input = match arguments.value_of("input") {
+        Some(p) => p,
+        None => fail("An input image is required.", ExitCode::Usage),
+    };
+
+    let output = match arguments.value_of("output") {
+        Some(p) => p,
+        None => fail("An output path is required.", ExitCode::Usage),
+    };
+
+    if Path::new(input) == Path::new(output) {
+        fail(
+            "The output must be a different file to the input.",
+            ExitCode::Usage,
+        );
+    }
+
+    if Path::new(output).exists()
+        && !confirm(
+            &format!("A file already exists at {}, replace it?", output),
+            arguments.is_present("yes"),
+        )
+    {
+        println!("Did not compact the image.");
+        ExitCode::Success.exit();
+    }
+
+    let context = match arguments.is_present("progress") {
+        true => OpContext::new().with_progress(&print_progress),
+        false => OpContext::new(),
+    };
+
+    let mut image = open_image(input, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let source = image.disk();
+    let mut size = estimate_size(&source);
+
+    // The estimate can fall short of the space the copy needs, e.g. for indirect blocks, so grow until it fits
+    loop {
+        match compact_into(&source, output, size, &context) {
+            Ok(_) => break,
+            Err(e) if is_out_of_space(&e) => {
+                size += std::cmp::max(size / 16, 16 * source.block_size());
+                size = round_up(size, source.block_size());
+            }
+            Err(e) => fail(
+                format!("Could not compact the image: {}", describe_error(&e)),
+                ExitCode::Failure,
+            ),
+        }
+    }
+
+    println!(
+        "Compacted {} files and {} tags into {} ({})",
+        source.number_of_files(),
+        source.number_of_tags(),
+        output,
+        u64_to_sized_string(size)
+    );
+}
+
+/// Creates an image of the size at the path and copies the source into it.
+fn compact_into(
+    source: &Disk<MKImageError>,
+    output: &str,
+    size: u64,
+    context: &OpContext,
+) -> Result<(), VoxFSError<MKImageError>> {
+    let mut handler = match Handler::new_create_with_allocation(
+        output.to_string(),
+        size as usize,
+        Allocation::Zeroed,
+    ) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+    let mut manager = Manager::new();
+
+    let mut destination = Disk::make_new_filesystem_with_options(
+        &mut handler,
+        &mut manager,
+        format_options_like(source),
+    )?;
+
+    copy_filesystem(source, &mut destination, context)?;
+
+    return destination.close();
+}
+
+/// Estimates the smallest image that holds the source's files and tags, following how a format divides an image.
+/// There is a tag or inode slot for every `DEFAULT_BYTES_PER_INODE` bytes of the image, divided in the source's ratio.
+fn estimate_size(source: &Disk<MKImageError>) -> u64 {
+    let block_size = source.block_size();
+    let mut data_size = 0;
+
+    for inode in source.list_inodes() {
+        data_size += round_up(inode.file_size(), block_size);
+    }
+
+    // The super block, bitmaps and boot area
+    let overhead = source.boot_area_size() + 4 * block_size;
+
+    // The tag and inode tables take an eighth of the image, twice that with a mirror
+    let by_data = if source.has_metadata_mirror() {
+        (data_size + overhead) * 4 / 3
+    } else {
+        (data_size + overhead) * 8 / 7
+    };
+
+    let ratio = inodes_per_tag(source);
+    let by_inodes = source.number_of_files() as u64 * DEFAULT_BYTES_PER_INODE * (ratio + 1) / ratio;
+    let by_tags = source.number_of_tags() as u64 * DEFAULT_BYTES_PER_INODE * (ratio + 1);
+
+    let size = std::cmp::max(by_data, std::cmp::max(by_inodes, by_tags));
+
+    return round_up(std::cmp::max(size, MINIMUM_SIZE), block_size);
+}
+
+fn round_up(n: u64, alignment: u64) -> u64 {
+    return match n % alignment {
+        0 => n,
+        r => n + alignment - r,
+    };
+}
@@ -0,0 +1,185 @@
+use clap::{App, Arg};
+use std::path::Path;
+use voxfs::{Disk, OpContext, VoxFSError, MIN_BLOCK_SIZE};
+use voxfs_tool_lib::{
+    confirm, copy_filesystem, describe_error, fail, format_options_like, is_out_of_space,
+    open_image, print_progress, sized_string_to_u64, u64_to_sized_string, Allocation, ExitCode,
+    Handler, MKImageError, Manager, OpenMode,
+};
+
+fn main() {
+    let arguments = App::new("convert-voxfs")
+        .version("0.1.0")
+        .about("This program copies the files and tags of a voxfs image into a new image with a different block size.")
+        .arg(
+            Arg::with_name("input")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image to convert"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the converted image to create"),
+        )
+        .arg(
+            Arg::with_name("block-size")
+                .long("block-size")
+                .required(true)
+                .takes_value(true)
+                .value_name("SIZE")
+                .help("The block size of the new image, a power of two of at least 512 bytes."),
+        )
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .takes_value(true)
+                .value_name("SIZE")
+                .help("The size of the new image, the same size as the input if not given."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to convert in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Replace an existing file at the output path without asking for confirmation."),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help("Show how many of the files and tags have been copied."),
+        )
+        .get_matches();
+
+    let input = match arguments.value_of("input") {
+        Some(p) => p,
+        None => fail("An input image is required.", ExitCode::Usage),
+    };
+
+    let output = match arguments.value_of("output") {
+        Some(p) => p,
+        None => fail("An output path is required.", ExitCode::Usage),
+    };
+
+    if Path::new(input) == Path::new(output) {
+        fail(
+            "The output must be a different file to the input.",
+            ExitCode::Usage,
+        );
+    }
+
+    let block_size = match arguments
+        .value_of("block-size")
+        .and_then(sized_string_to_u64)
+    {
+        Some(s) if s >= MIN_BLOCK_SIZE && s.is_power_of_two() => s,
+        _ => fail(
+            format!(
+                "The block size must be a power of two of at least {} bytes.",
+                MIN_BLOCK_SIZE
+            ),
+            ExitCode::Usage,
+        ),
+    };
+
+    let requested_size = match arguments.value_of("size") {
+        Some(s) => match sized_string_to_u64(s) {
+            Some(s) => Some(s),
+            None => fail("A valid integer size is required.", ExitCode::Usage),
+        },
+        None => None,
+    };
+
+    if Path::new(output).exists()
+        && !confirm(
+            &format!("A file already exists at {}, replace it?", output),
+            arguments.is_present("yes"),
+        )
+    {
+        println!("Did not convert the image.");
+        ExitCode::Success.exit();
+    }
+
+    let context = match arguments.is_present("progress") {
+        true => OpContext::new().with_progress(&print_progress),
+        false => OpContext::new(),
+    };
+
+    let mut image = open_image(input, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let size = requested_size.unwrap_or_else(|| image.size());
+    let source = image.disk();
+
+    match convert_into(&source, output, size, block_size, &context) {
+        Ok(_) => (),
+        Err(e) if is_out_of_space(&e) => fail(
+            format!(
+                "The files and tags do not fit in {} with a block size of {}, use --size to make the image larger.",
+                u64_to_sized_string(size),
+                u64_to_sized_string(block_size)
+            ),
+            ExitCode::Failure,
+        ),
+        Err(e) => fail(
+            format!("Could not convert the image: {}", describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    }
+
+    println!(
+        "Converted {} files and {} tags into {} with a block size of {}",
+        source.number_of_files(),
+        source.number_of_tags(),
+        output,
+        u64_to_sized_string(block_size)
+    );
+}
+
+/// Creates an image of the size at the path, formats it with the block size and copies the source into it.
+fn convert_into(
+    source: &Disk<MKImageError>,
+    output: &str,
+    size: u64,
+    block_size: u64,
+    context: &OpContext,
+) -> Result<(), VoxFSError<MKImageError>> {
+    let mut handler = match Handler::new_create_with_allocation(
+        output.to_string(),
+        size as usize,
+        Allocation::Zeroed,
+    ) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+    let mut manager = Manager::new();
+
+    let mut destination = Disk::make_new_filesystem_with_options(
+        &mut handler,
+        &mut manager,
+        format_options_like(source).with_block_size(block_size),
+    )?;
+
+    copy_filesystem(source, &mut destination, context)?;
+
+    return destination.close();
+}
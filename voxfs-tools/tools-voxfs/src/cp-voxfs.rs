@@ -0,0 +1,180 @@
+use clap::{App, Arg};
+use std::path::Path;
+use voxfs::OpContext;
+use voxfs_tool_lib::{
+    copy_files, describe_error, fail, open_image, print_progress, ExitCode, OpenMode,
+};
+
+fn main() {
+    let arguments = App::new("cp-voxfs")
+        .version("0.1.0")
+        .about("This program copies files, and the tags applied to them, from one voxfs image to another keeping their timestamps and flags.")
+        .arg(
+            Arg::with_name("source")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image to copy from"),
+        )
+        .arg(
+            Arg::with_name("destination")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image to copy into"),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .long("tag")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("TAG")
+                .help("Copy every file with the tag, can be given more than once."),
+        )
+        .arg(
+            Arg::with_name("file")
+                .long("file")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("NAME")
+                .help("Copy the file with the name, can be given more than once."),
+        )
+        .arg(
+            Arg::with_name("source-volume")
+                .long("source-volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to copy from in a source image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("destination-volume")
+                .long("destination-volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to copy into in a destination image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help("Print the progress of the copy to stderr."),
+        )
+        .get_matches();
+
+    let source_path = match arguments.value_of("source") {
+        Some(p) => p,
+        None => fail("A source image is required.", ExitCode::Usage),
+    };
+
+    let destination_path = match arguments.value_of("destination") {
+        Some(p) => p,
+        None => fail("A destination image is required.", ExitCode::Usage),
+    };
+
+    let tags: Vec<&str> = arguments
+        .values_of("tag")
+        .map_or(Vec::new(), |v| v.collect());
+    let files: Vec<&str> = arguments
+        .values_of("file")
+        .map_or(Vec::new(), |v| v.collect());
+
+    if tags.is_empty() && files.is_empty() {
+        fail(
+            "Give the files to copy with --tag or --file.",
+            ExitCode::Usage,
+        );
+    }
+
+    if Path::new(source_path) == Path::new(destination_path) {
+        fail(
+            "The destination must be a different image to the source.",
+            ExitCode::Usage,
+        );
+    }
+
+    let context = match arguments.is_present("progress") {
+        true => OpContext::new().with_progress(&print_progress),
+        false => OpContext::new(),
+    };
+
+    let mut source_image = open_image(source_path, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("source-volume") {
+        source_image.select_volume(volume);
+    }
+
+    let mut destination_image = open_image(destination_path, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("destination-volume") {
+        destination_image.select_volume(volume);
+    }
+
+    let source = source_image.disk();
+    let mut destination = destination_image.disk();
+
+    let mut selected: Vec<u64> = Vec::new();
+
+    for name in &files {
+        match source.inode_with_name(name) {
+            Some(i) if !selected.contains(&i) => selected.push(i),
+            Some(_) => (),
+            None => fail(
+                format!("Could not find file with name {}", name),
+                ExitCode::NotFound,
+            ),
+        }
+    }
+
+    for name in &tags {
+        let tag_index = match source.tag_with_name(name) {
+            Some(i) => i,
+            None => fail(
+                format!("Could not find tag with name {}", name),
+                ExitCode::NotFound,
+            ),
+        };
+
+        let members = match source.list_nodes_with_tag(tag_index) {
+            Ok(m) => m,
+            Err(e) => fail(
+                format!("Could not read the tag {}: {}", name, describe_error(&e)),
+                ExitCode::Failure,
+            ),
+        };
+
+        for member in members {
+            if !selected.contains(&member.index()) {
+                selected.push(member.index());
+            }
+        }
+    }
+
+    let copies = match copy_files(&source, &mut destination, &selected, &context) {
+        Ok(c) => c,
+        Err(e) => fail(
+            format!("Could not copy the files: {}", describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    };
+
+    match destination.close() {
+        Ok(_) => (),
+        Err(e) => fail(
+            format!(
+                "Could not write the changes to the image: {}",
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
+    }
+
+    for copy in &copies {
+        println!("{}", copy.name());
+    }
+
+    println!(
+        "Copied {} files from {} to {}",
+        copies.len(),
+        source_path,
+        destination_path
+    );
+}
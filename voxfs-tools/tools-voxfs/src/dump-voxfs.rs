@@ -0,0 +1,159 @@
+use clap::{App, Arg};
+use voxfs::{ByteSerializable, DiskHandler, INode, SuperBlock, TagBlock};
+use voxfs_tool_lib::{fail, sized_string_to_u64, ExitCode, Handler, HexDump};
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Parses an offset or length, either in hex with a 0x prefix or as a size with optional units.
+fn parse_amount(value: &str) -> Option<u64> {
+    if let Some(hex) = value.strip_prefix("0x") {
+        return u64::from_str_radix(hex, 16).ok();
+    }
+
+    return sized_string_to_u64(value);
+}
+
+fn main() {
+    let arguments = App::new("dump-voxfs")
+        .version("0.1.0")
+        .about("This program prints the bytes of a voxfs image as a hex dump, or decodes the structure at an offset.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image"),
+        )
+        .arg(
+            Arg::with_name("offset")
+                .long("offset")
+                .takes_value(true)
+                .value_name("OFFSET")
+                .help("Where to start, in bytes or in hex with a 0x prefix. Defaults to the start of the image."),
+        )
+        .arg(
+            Arg::with_name("length")
+                .long("length")
+                .takes_value(true)
+                .value_name("LENGTH")
+                .conflicts_with("decode")
+                .help("How many bytes to print. Defaults to the rest of the image."),
+        )
+        .arg(
+            Arg::with_name("decode")
+                .long("decode")
+                .takes_value(true)
+                .possible_values(&["inode", "tag", "superblock"])
+                .help("Decode the structure at the offset instead of printing a hex dump."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Offsets are from the start of this volume in an image with a volume table."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    let offset = match arguments.value_of("offset") {
+        Some(o) => match parse_amount(o) {
+            Some(o) => o,
+            None => fail(format!("{} is not a valid offset.", o), ExitCode::Usage),
+        },
+        None => 0,
+    };
+
+    let mut handler = match Handler::new(path.to_string()) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::NoImage),
+    };
+
+    if let Some(volume) = arguments.value_of("volume") {
+        match handler.select_volume(volume) {
+            Ok(_) => (),
+            Err(e) => fail(e, ExitCode::NoImage),
+        }
+    }
+
+    let size = match handler.disk_size() {
+        Ok(s) => s,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+
+    if offset > size {
+        fail(
+            format!("The offset {} is past the end of the image.", offset),
+            ExitCode::Usage,
+        );
+    }
+
+    match arguments.value_of("decode") {
+        Some("superblock") => decode(&handler, offset, SuperBlock::size(), |b| {
+            SuperBlock::from_bytes(b).map(|s| format!("{:#?}", s))
+        }),
+        Some("inode") => decode(&handler, offset, INode::size(), |b| {
+            INode::from_bytes(b).map(|i| format!("{:#?}", i))
+        }),
+        Some("tag") => decode(&handler, offset, TagBlock::size(), |b| {
+            TagBlock::from_bytes(b).map(|t| format!("{:#?}", t))
+        }),
+        _ => {
+            let length = match arguments.value_of("length") {
+                Some(l) => match parse_amount(l) {
+                    Some(l) => std::cmp::min(l, size - offset),
+                    None => fail(format!("{} is not a valid length.", l), ExitCode::Usage),
+                },
+                None => size - offset,
+            };
+
+            dump(&handler, offset, length);
+        }
+    }
+}
+
+/// Prints a hex dump of the bytes in the range, reading them in pieces.
+fn dump(handler: &Handler, offset: u64, length: u64) {
+    let mut hex_dump = HexDump::new();
+    let end = offset + length;
+    let mut address = offset;
+
+    while address < end {
+        let bytes = read(handler, address, std::cmp::min(CHUNK_SIZE, end - address));
+
+        for line in hex_dump.lines(address, &bytes) {
+            println!("{}", line);
+        }
+
+        address += bytes.len() as u64;
+    }
+
+    println!("{}", hex_dump.end(end));
+}
+
+/// Prints the structure of the given size at the offset, or its bytes if it can not be decoded.
+fn decode<F: Fn(&[u8]) -> Option<String>>(handler: &Handler, offset: u64, size: u64, decoder: F) {
+    let bytes = read(handler, offset, size);
+
+    match decoder(&bytes) {
+        Some(decoded) => println!("{}", decoded),
+        None => {
+            eprintln!(
+                "The {} bytes at {:#x} are not a valid structure, it failed its checksum or has invalid fields.",
+                size, offset
+            );
+            dump(handler, offset, size);
+            ExitCode::Failure.exit();
+        }
+    }
+}
+
+fn read(handler: &Handler, address: u64, amount: u64) -> Vec<u8> {
+    return match handler.read_bytes(address, amount) {
+        Ok(b) => b,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+}
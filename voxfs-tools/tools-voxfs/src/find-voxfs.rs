@@ -0,0 +1,176 @@
+use clap::{App, Arg};
+use voxfs::{INode, NamePattern, SortOrder, TagQuery, VoxFSError};
+use voxfs_tool_lib::{
+    fail, fail_error, json_string, open_image, parse_date, sized_string_to_u64, ExitCode, OpenMode,
+};
+
+fn main() {
+    let arguments = App::new("find-voxfs")
+        .version("0.1.0")
+        .about("This program searches a voxfs image for files by name, tags, size and modification time.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image"),
+        )
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .value_name("PATTERN")
+                .help("Only files whose names match a pattern using *, ? and [...], e.g. \"*.log\"."),
+        )
+        .arg(
+            Arg::with_name("tags")
+                .long("tags")
+                .takes_value(true)
+                .value_name("QUERY")
+                .help("Only files whose tags match a query using &, | and !, e.g. \"work & !archived\"."),
+        )
+        .arg(
+            Arg::with_name("larger-than")
+                .long("larger-than")
+                .takes_value(true)
+                .value_name("SIZE")
+                .help("Only files larger than a size, with optional units (KiB, MiB, GiB)."),
+        )
+        .arg(
+            Arg::with_name("smaller-than")
+                .long("smaller-than")
+                .takes_value(true)
+                .value_name("SIZE")
+                .help("Only files smaller than a size, with optional units (KiB, MiB, GiB)."),
+        )
+        .arg(
+            Arg::with_name("newer-than")
+                .long("newer-than")
+                .takes_value(true)
+                .value_name("DATE")
+                .help("Only files modified after a date, either YYYY-MM-DD or an RFC 3339 time."),
+        )
+        .arg(
+            Arg::with_name("older-than")
+                .long("older-than")
+                .takes_value(true)
+                .value_name("DATE")
+                .help("Only files modified before a date, either YYYY-MM-DD or an RFC 3339 time."),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the files found as a JSON array."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to search in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    let pattern = match arguments.value_of("name").map(NamePattern::parse) {
+        Some(Ok(p)) => Some(p),
+        Some(Err(e)) => fail(format!("Invalid name pattern: {}", e), ExitCode::Usage),
+        None => None,
+    };
+
+    let query = match arguments.value_of("tags").map(TagQuery::parse) {
+        Some(Ok(q)) => Some(q),
+        Some(Err(e)) => fail(format!("Invalid query: {}", e), ExitCode::Usage),
+        None => None,
+    };
+
+    let size_argument = |name: &str| match arguments.value_of(name) {
+        Some(s) => match sized_string_to_u64(s) {
+            Some(s) => Some(s),
+            None => fail(format!("{} is not a valid size.", s), ExitCode::Usage),
+        },
+        None => None,
+    };
+
+    let date_argument = |name: &str| match arguments.value_of(name) {
+        Some(d) => match parse_date(d) {
+            Some(d) => Some(d),
+            None => fail(format!("{} is not a valid date.", d), ExitCode::Usage),
+        },
+        None => None,
+    };
+
+    let larger_than = size_argument("larger-than");
+    let smaller_than = size_argument("smaller-than");
+    let newer_than = date_argument("newer-than");
+    let older_than = date_argument("older-than");
+
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let mut disk = image.disk();
+
+    // The tag query narrows the files the most cheaply, so it picks the candidates when there is one
+    let candidates = match &query {
+        Some(query) => match disk.query_tags(query) {
+            Ok(i) => i,
+            Err(VoxFSError::NoTagsWithNames(names)) => fail(
+                format!("No tags with names: {}", names.join(", ")),
+                ExitCode::NotFound,
+            ),
+            Err(e) => fail_error("Error", &e, arguments.is_present("json"), ExitCode::Failure),
+        },
+        None => match &pattern {
+            Some(pattern) => disk.find_inodes(pattern),
+            None => disk.list_inodes_sorted(SortOrder::Index),
+        },
+    };
+
+    let found: Vec<INode> = candidates
+        .into_iter()
+        .filter(|inode| match &pattern {
+            Some(pattern) => pattern.matches(&inode.name()),
+            None => true,
+        })
+        .filter(|inode| larger_than.iter().all(|s| inode.file_size() > *s))
+        .filter(|inode| smaller_than.iter().all(|s| inode.file_size() < *s))
+        .filter(|inode| newer_than.iter().all(|d| inode.modified_time() > *d))
+        .filter(|inode| older_than.iter().all(|d| inode.modified_time() < *d))
+        .collect();
+
+    if arguments.is_present("json") {
+        let entries: Vec<String> = found
+            .iter()
+            .map(|inode| {
+                format!(
+                    "{{\"index\":{},\"name\":{},\"size\":{},\"modified\":{}}}",
+                    inode.index(),
+                    json_string(&inode.name()),
+                    inode.file_size(),
+                    json_string(&inode.modified_time().to_rfc3339())
+                )
+            })
+            .collect();
+
+        println!("[{}]", entries.join(","));
+    } else {
+        for inode in found.iter() {
+            println!("{}", inode.name());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use clap::{App, Arg};
+use voxfs::HistoryRecord;
+use voxfs_tool_lib::{fail, fail_error, json_string, open_image, ExitCode, OpenMode};
+
+fn main() {
+    let arguments = App::new("history-voxfs")
+        .version("0.1.0")
+        .about("This program prints the changes recorded in a voxfs image's history, oldest first.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the changes as a JSON array."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to read in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
+
+    if !disk.has_history() {
+        fail(
+            "The image has no history, it can be created with mkfs-voxfs --history.",
+            ExitCode::NotFound,
+        );
+    }
+
+    let records = match disk.history() {
+        Ok(r) => r,
+        Err(e) => fail_error("Error", &e, arguments.is_present("json"), ExitCode::Failure),
+    };
+
+    if arguments.is_present("json") {
+        let entries: Vec<String> = records.iter().map(json_record).collect();
+
+        println!("[{}]", entries.join(","));
+    } else {
+        for record in records.iter() {
+            println!("{}", describe(record));
+        }
+    }
+}
+
+/// A line like a commit log entry: sequence, time, user, operation and what it changed.
+fn describe(record: &HistoryRecord) -> String {
+    let user = match record.user_name().is_empty() {
+        true => record.user_id().to_string(),
+        false => format!("{} ({})", record.user_name(), record.user_id()),
+    };
+
+    let target = match record.target() {
+        Some(t) => format!(" -> file {}", t),
+        None => String::new(),
+    };
+
+    return format!(
+        "{:>6}  {}  {}  {} {:?} [{}{}]",
+        record.sequence(),
+        record.time().to_rfc3339(),
+        user,
+        record.operation(),
+        record.name(),
+        record.subject(),
+        target
+    );
+}
+
+fn json_record(record: &HistoryRecord) -> String {
+    let target = match record.target() {
+        Some(t) => t.to_string(),
+        None => "null".to_string(),
+    };
+
+    return format!(
+        "{{\"sequence\":{},\"time\":{},\"user_id\":{},\"user\":{},\"operation\":{},\"subject\":{},\"target\":{},\"name\":{}}}",
+        record.sequence(),
+        json_string(&record.time().to_rfc3339()),
+        record.user_id(),
+        json_string(&record.user_name()),
+        json_string(&record.operation().to_string()),
+        record.subject(),
+        target,
+        json_string(&record.name())
+    );
+}
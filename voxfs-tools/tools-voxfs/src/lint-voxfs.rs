@@ -0,0 +1,198 @@
+use clap::{App, Arg};
+use voxfs::{DuplicateName, NameIssue, NameReport};
+use voxfs_tool_lib::{fail, json_string, open_image, ExitCode, OpenMode};
+
+const SPACER: &str = "    ";
+
+fn main() {
+    let arguments = App::new("lint-voxfs")
+        .version("0.1.0")
+        .about("This program lists the file names in a voxfs image that are duplicated, only differ in case or have characters that could cause trouble when the image is mounted or served.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the report as JSON."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
+    let report = disk.name_report();
+
+    if arguments.is_present("json") {
+        print!("{}", json_report(&report));
+    } else {
+        print_report(&report);
+    }
+
+    if !report.is_clean() {
+        ExitCode::Failure.exit();
+    }
+}
+
+/// Prints the names quoted and escaped so whitespace and control characters can be seen.
+fn print_report(report: &NameReport) {
+    println!(
+        "{} files, names are {} characters long on average",
+        report.files(),
+        report.average_length()
+    );
+
+    print_duplicates("Duplicate names", report.duplicates());
+    print_duplicates("Names that only differ in case", report.case_collisions());
+
+    if !report.odd_names().is_empty() {
+        println!("\nNames with odd characters:");
+
+        for odd in report.odd_names() {
+            let issues: Vec<&str> = odd.issues().iter().map(|i| issue_string(*i)).collect();
+
+            println!(
+                "{}{:<8}{}{:?}{}{}",
+                SPACER,
+                odd.index(),
+                SPACER,
+                odd.name(),
+                SPACER,
+                issues.join(", ")
+            );
+        }
+    }
+
+    if !report.longest().is_empty() {
+        println!("\nLongest names:");
+
+        for (index, name) in report.longest() {
+            println!(
+                "{}{:<8}{}{:>4}{}{:?}",
+                SPACER,
+                index,
+                SPACER,
+                name.chars().count(),
+                SPACER,
+                name
+            );
+        }
+    }
+
+    if report.is_clean() {
+        println!("\nNo problems found.");
+    }
+}
+
+fn print_duplicates(title: &str, duplicates: &[DuplicateName]) {
+    if duplicates.is_empty() {
+        return;
+    }
+
+    println!("\n{}:", title);
+
+    for duplicate in duplicates {
+        let inodes: Vec<String> = duplicate.inodes().iter().map(|i| i.to_string()).collect();
+
+        println!(
+            "{}{:?}{}files {}",
+            SPACER,
+            duplicate.name(),
+            SPACER,
+            inodes.join(", ")
+        );
+    }
+}
+
+fn issue_string(issue: NameIssue) -> &'static str {
+    return match issue {
+        NameIssue::NonUtf8 => "not UTF-8",
+        NameIssue::ControlCharacter => "control character",
+        NameIssue::ForbiddenCharacter => "forbidden character",
+        NameIssue::SurroundingWhitespace => "surrounding whitespace",
+        NameIssue::Reserved => "reserved name",
+    };
+}
+
+fn json_report(report: &NameReport) -> String {
+    let duplicates = |list: &[DuplicateName]| -> String {
+        let entries: Vec<String> = list
+            .iter()
+            .map(|d| {
+                let inodes: Vec<String> = d.inodes().iter().map(|i| i.to_string()).collect();
+
+                format!(
+                    "{{\"name\":{},\"inodes\":[{}]}}",
+                    json_string(d.name()),
+                    inodes.join(",")
+                )
+            })
+            .collect();
+
+        return format!("[{}]", entries.join(","));
+    };
+
+    let odd_names: Vec<String> = report
+        .odd_names()
+        .iter()
+        .map(|odd| {
+            let issues: Vec<String> = odd
+                .issues()
+                .iter()
+                .map(|i| json_string(&format!("{:?}", i)))
+                .collect();
+
+            format!(
+                "{{\"index\":{},\"name\":{},\"issues\":[{}]}}",
+                odd.index(),
+                json_string(odd.name()),
+                issues.join(",")
+            )
+        })
+        .collect();
+
+    let longest: Vec<String> = report
+        .longest()
+        .iter()
+        .map(|(index, name)| format!("{{\"index\":{},\"name\":{}}}", index, json_string(name)))
+        .collect();
+
+    return format!(
+        "{{\"clean\":{},\"files\":{},\"average_length\":{},\"duplicates\":{},\"case_collisions\":{},\"odd_names\":[{}],\"longest\":[{}]}}\n",
+        report.is_clean(),
+        report.files(),
+        report.average_length(),
+        duplicates(report.duplicates()),
+        duplicates(report.case_collisions()),
+        odd_names.join(","),
+        longest.join(",")
+    );
+}
@@ -1,7 +1,6 @@
 use clap::{App, Arg};
-use std::process::exit;
-use voxfs::{Disk, VoxFSError};
-use voxfs_tool_lib::{u64_to_sized_string, Handler, Manager};
+use voxfs::{SortOrder, VoxFSError};
+use voxfs_tool_lib::{describe_error, fail, open_image, u64_to_sized_string, ExitCode, OpenMode};
 
 const SPACER: &str = "    ";
 
@@ -30,31 +29,62 @@ fn main() {
                 .takes_value(false)
                 .help("List the files with their metadata."),
         )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .takes_value(true)
+                .possible_values(&["index", "name", "size", "mtime"])
+                .default_value("name")
+                .help("The order to list the files in."),
+        )
+        .arg(
+            Arg::with_name("tolerant")
+                .long("tolerant")
+                .takes_value(false)
+                .help("Skip unreadable tags and files instead of failing, listing them on stderr."),
+        )
         .get_matches();
 
     let path = match arguments.value_of("image") {
         Some(p) => p,
-        None => {
-            eprintln!("An image is required.");
-            exit(1);
-        }
+        None => fail("An image is required.", ExitCode::Usage),
     };
 
-    let mut manager = Manager::new();
-    let mut handler = match Handler::new(path.to_string()) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(1);
-        }
+    let mode = if arguments.is_present("tolerant") {
+        OpenMode::Tolerant
+    } else {
+        OpenMode::Strict
     };
 
-    let disk = match Disk::open_disk(&mut handler, &mut manager) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("An error occurred: {:?}", e);
-            exit(1);
-        }
+    let mut image = open_image(path, mode);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
+
+    let order = match arguments.value_of("sort") {
+        Some("index") => SortOrder::Index,
+        Some("size") => SortOrder::Size,
+        Some("mtime") => SortOrder::ModifiedTime,
+        _ => SortOrder::Name,
     };
 
     if arguments.is_present("filter-tags") {
@@ -75,24 +105,26 @@ fn main() {
                     VoxFSError::MoreNamesThanTagsProvided => {
                         eprintln!("There are less than {} tags.", tags_len);
                     }
-                    _ => eprintln!("Unexpected error: \"{}\"", e),
+                    _ => eprintln!("Unexpected error: \"{}\"", describe_error(&e)),
                 }
 
-                exit(1);
+                ExitCode::NotFound.exit();
             }
         };
 
-        let inodes = match disk.list_nodes_with_tags(indices) {
+        let mut inodes = match disk.list_nodes_with_tags(indices) {
             Ok(i) => i,
             Err(e) => {
                 match e {
-                    _ => eprintln!("An unexpected error occurred: \"{}\"", e),
+                    _ => eprintln!("An unexpected error occurred: \"{}\"", describe_error(&e)),
                 }
 
-                exit(1);
+                ExitCode::Failure.exit();
             }
         };
 
+        order.sort(&mut inodes);
+
         if arguments.is_present("list") {
             if inodes.len() == 0 {
                 println!("No files were found that satisfied the criteria.");
@@ -122,7 +154,7 @@ fn main() {
             }
         }
     } else if arguments.is_present("list") {
-        let inodes = disk.list_inodes();
+        let inodes = disk.list_inodes_sorted(order);
 
         for i in 0..inodes.len() {
             println!(
@@ -135,7 +167,7 @@ fn main() {
             );
         }
     } else {
-        let inodes = disk.list_inodes();
+        let inodes = disk.list_inodes_sorted(order);
 
         for i in 0..inodes.len() {
             if i != 0 && (i + 1) % 3 == 0 {
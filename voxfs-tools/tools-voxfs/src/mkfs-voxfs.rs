@@ -1,9 +1,101 @@
 use clap::{App, Arg};
-use std::io::Write;
 use std::path::Path;
-use std::process::exit;
-use voxfs::Disk;
-use voxfs_tool_lib::{sized_string_to_u64, Handler, Manager};
+use voxfs::volumes::VolumeTable;
+use voxfs::{Disk, FormatOptions, INode, NamePolicy, TagBlock, MAX_LABEL_LENGTH, MIN_BLOCK_SIZE};
+use voxfs_tool_lib::{
+    confirm, describe_error, detect_signatures, fail, is_block_device, path_size,
+    sized_string_to_u64, u64_to_sized_string, Allocation, ExitCode, Handler, Manager,
+};
+
+/// Parses a volume argument of the form NAME=SIZE.
+fn parse_volume(value: &str) -> Option<(String, u64)> {
+    let (name, size) = value.split_once('=')?;
+
+    return Some((name.to_string(), sized_string_to_u64(size)?));
+}
+
+/// Formats the disk the handler points to, exiting on failure.
+fn format(
+    handler: &mut Handler,
+    manager: &mut Manager,
+    options: &FormatOptions,
+    boot_image: &Option<Vec<u8>>,
+) {
+    let mut options = options.clone();
+
+    if let Some(boot_image) = boot_image {
+        options = options.with_boot_area_size(boot_image.len() as u64);
+    }
+
+    let mut disk = match Disk::make_new_filesystem_with_options(handler, manager, options) {
+        Ok(d) => d,
+        Err(e) => fail(describe_error(&e), ExitCode::Failure),
+    };
+
+    if let Some(boot_image) = boot_image {
+        match disk.write_boot_area(boot_image) {
+            Ok(_) => (),
+            Err(e) => fail(describe_error(&e), ExitCode::Failure),
+        }
+    }
+
+    match disk.close() {
+        Ok(_) => (),
+        Err(e) => fail(describe_error(&e), ExitCode::Failure),
+    }
+}
+
+/// Describes what is at the path and what formatting it would destroy, exiting unless it is a plain file with no
+/// filesystem or `--force` was given. Returns whether the path is a block device.
+fn check_existing(path: &str, force: bool) -> bool {
+    let metadata = match std::fs::metadata(path) {
+        Ok(m) => m,
+        Err(_) => return false,
+    };
+
+    let device = is_block_device(&metadata);
+
+    let size = match path_size(path) {
+        Ok(s) => s,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+
+    let signatures = match detect_signatures(path) {
+        Ok(s) => s,
+        Err(e) => fail(e, ExitCode::Io),
+    };
+
+    match device {
+        true => println!(
+            "{} is a block device of {}",
+            path,
+            u64_to_sized_string(size)
+        ),
+        false => println!(
+            "A file of {} already exists at {}",
+            u64_to_sized_string(size),
+            path
+        ),
+    }
+
+    for signature in signatures.iter() {
+        println!("It holds {}, which will be destroyed.", signature);
+    }
+
+    if !device && signatures.is_empty() {
+        println!("It will be replaced.");
+        return false;
+    }
+
+    if !force {
+        fail(
+            format!("Refusing to format over {}, give --force to do so.", path),
+            ExitCode::Usage,
+        );
+    }
+
+    return device;
+}
 
 fn main() {
     let arguments = App::new("mkfs-voxfs")
@@ -21,124 +113,307 @@ fn main() {
                 .takes_value(true)
                 .help("The size of the image with optional (KB, MB, GB)."),
         )
+        .arg(
+            Arg::with_name("block-size")
+                .long("block-size")
+                .takes_value(true)
+                .value_name("SIZE")
+                .default_value("4KiB")
+                .help("The size of each block, a power of two of at least 512 bytes."),
+        )
+        .arg(
+            Arg::with_name("boot-image")
+                .long("boot-image")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("A file to store in the boot area, the boot area is sized to fit it."),
+        )
+        .arg(
+            Arg::with_name("mirror-metadata")
+                .long("mirror-metadata")
+                .help(
+                    "Keeps a second copy of the tag and inode tables to recover corrupted records.",
+                ),
+        )
+        .arg(
+            Arg::with_name("content-hashes")
+                .long("content-hashes")
+                .help("Keeps a SHA-256 hash of each file's contents, updated as the file is written."),
+        )
+        .arg(
+            Arg::with_name("bytes-per-inode")
+                .long("bytes-per-inode")
+                .takes_value(true)
+                .value_name("SIZE")
+                .default_value("2KiB")
+                .help("The space for each tag or inode, larger values leave more room for data when storing a few big files."),
+        )
+        .arg(
+            Arg::with_name("inodes-per-tag")
+                .long("inodes-per-tag")
+                .takes_value(true)
+                .value_name("N")
+                .default_value("3")
+                .help("How many inodes to create for each tag."),
+        )
+        .arg(
+            Arg::with_name("history")
+                .long("history")
+                .takes_value(true)
+                .value_name("SIZE")
+                .help("Reserves space to record each change made to the filesystem, 128 bytes per change. Once full the oldest changes are forgotten."),
+        )
+        .arg(
+            Arg::with_name("label")
+                .long("label")
+                .takes_value(true)
+                .value_name("LABEL")
+                .help("A name for the filesystem, up to 16 bytes."),
+        )
+        .arg(
+            Arg::with_name("allocation")
+                .long("allocation")
+                .takes_value(true)
+                .possible_values(&["zeroed", "sparse", "preallocate"])
+                .default_value("zeroed")
+                .help("How to allocate the image's space, preallocate reserves it without writing zeroes where supported."),
+        )
+        .arg(
+            Arg::with_name("duplicate-names")
+                .long("duplicate-names")
+                .takes_value(true)
+                .possible_values(&["reject", "allow", "suffix"])
+                .default_value("reject")
+                .help("What to do when a file is added with the name of an existing file."),
+        )
+        .arg(
+            Arg::with_name("max-name-length")
+                .long("max-name-length")
+                .takes_value(true)
+                .value_name("LENGTH")
+                .help("Refuses file and tag names longer than this many characters, for compatibility with other tools. Otherwise long names are cut short to fit."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("NAME=SIZE")
+                .help(
+                    "Adds a volume table and formats a volume of this size in it. Can be repeated.",
+                ),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Create the image, replacing any existing file, without asking for confirmation."),
+        )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Allow formatting a block device, or a file that already holds a filesystem."),
+        )
         .get_matches();
 
     let path = match arguments.value_of("path") {
         Some(p) => p,
-        None => {
-            eprintln!("A path is required.");
-            exit(1);
-        }
+        None => fail("A path is required.", ExitCode::Usage),
     };
 
     let size_str = match arguments.value_of("size") {
         Some(p) => p,
-        None => {
-            eprintln!("A size is required.");
-            exit(1);
-        }
+        None => fail("A size is required.", ExitCode::Usage),
     };
 
     let size = match sized_string_to_u64(size_str) {
         Some(s) => s,
-        None => {
-            eprintln!("A valid integer size is required.");
-            exit(1);
-        }
+        None => fail("A valid integer size is required.", ExitCode::Usage),
     };
 
     if size < 40_960 {
-        eprintln!("Image size must be atleast 40KB.");
-        exit(1);
+        fail("Image size must be atleast 40KB.", ExitCode::Usage);
     }
 
-    println!("Create image of size {} bytes at {}", size, path);
-    print!("Confirm (y/N) ");
+    let block_size = match arguments
+        .value_of("block-size")
+        .and_then(sized_string_to_u64)
+    {
+        Some(s) if s >= MIN_BLOCK_SIZE && s.is_power_of_two() => s,
+        _ => fail(
+            format!(
+                "The block size must be a power of two of at least {} bytes.",
+                MIN_BLOCK_SIZE
+            ),
+            ExitCode::Usage,
+        ),
+    };
 
-    match std::io::stdout().flush() {
-        Ok(_) => (),
-        Err(_) => (),
-    }
+    let boot_image = match arguments.value_of("boot-image") {
+        Some(boot_path) => match std::fs::read(boot_path) {
+            Ok(b) => Some(b),
+            Err(e) => fail(
+                format!("Failed to read the boot image {}. Error: {}", boot_path, e),
+                ExitCode::Io,
+            ),
+        },
+        None => None,
+    };
 
-    let mut input = String::new();
-    match std::io::stdin().read_line(&mut input) {
-        Ok(_) => (),
-        Err(_) => {
-            eprintln!("Failed to read response.");
-            exit(1);
-        }
-    }
+    let mirror_metadata = arguments.is_present("mirror-metadata");
+    let content_hashes = arguments.is_present("content-hashes");
 
-    // strip the new line character
-    input = input.strip_suffix("\n").unwrap_or("").to_string();
-    if input.ends_with('\r') {
-        input = input.strip_suffix("\r").unwrap_or("").to_string();
-    }
+    let bytes_per_inode = match arguments
+        .value_of("bytes-per-inode")
+        .and_then(sized_string_to_u64)
+    {
+        Some(s) if s >= INode::size() => s,
+        _ => fail(
+            format!(
+                "The bytes per inode must be at least {} bytes.",
+                INode::size()
+            ),
+            ExitCode::Usage,
+        ),
+    };
 
-    if input != "y" && input != "Y" {
-        println!("Did not create image.");
-        exit(0);
+    let inodes_per_tag = match arguments
+        .value_of("inodes-per-tag")
+        .map(|n| n.parse::<u64>())
+    {
+        Some(Ok(n)) if n > 0 => n,
+        _ => fail(
+            "The inodes per tag must be a whole number of at least 1.",
+            ExitCode::Usage,
+        ),
+    };
+
+    let history_size = match arguments.value_of("history") {
+        Some(s) => match sized_string_to_u64(s) {
+            Some(s) => s,
+            None => fail(
+                format!("{} is not a valid history size.", s),
+                ExitCode::Usage,
+            ),
+        },
+        None => 0,
+    };
+
+    let label = arguments.value_of("label").unwrap_or("");
+
+    if label.len() > MAX_LABEL_LENGTH || label.contains('\0') {
+        fail(
+            format!(
+                "The label must be at most {} bytes without null characters.",
+                MAX_LABEL_LENGTH
+            ),
+            ExitCode::Usage,
+        );
     }
 
-    // Check if file already exists.
-    let path_struct = Path::new(path);
+    let allocation = match arguments.value_of("allocation") {
+        Some("sparse") => Allocation::Sparse,
+        Some("preallocate") => Allocation::Preallocated,
+        _ => Allocation::Zeroed,
+    };
 
-    if path_struct.exists() {
-        println!("A file already exists at {}", &path);
-        print!("Delete file (y/N) ");
+    let name_policy = match arguments.value_of("duplicate-names") {
+        Some("allow") => NamePolicy::Allow,
+        Some("suffix") => NamePolicy::AutoSuffix,
+        _ => NamePolicy::Reject,
+    };
 
-        match std::io::stdout().flush() {
-            Ok(_) => (),
-            Err(_) => (),
-        }
+    let max_name_length = match arguments
+        .value_of("max-name-length")
+        .map(|n| n.parse::<usize>())
+    {
+        Some(Ok(n)) if n > 0 && n <= TagBlock::MAX_NAME_LENGTH => Some(n),
+        Some(_) => fail(
+            format!(
+                "The maximum name length must be between 1 and {}.",
+                TagBlock::MAX_NAME_LENGTH
+            ),
+            ExitCode::Usage,
+        ),
+        None => None,
+    };
 
-        let mut input = String::new();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => (),
-            Err(_) => {
-                eprintln!("Failed to read response.");
-                exit(1);
+    let options = FormatOptions::new()
+        .with_block_size(block_size)
+        .with_bytes_per_inode(bytes_per_inode)
+        .with_inodes_per_tag(inodes_per_tag)
+        .with_metadata_mirror(mirror_metadata)
+        .with_content_hashes(content_hashes)
+        .with_history_size(history_size)
+        .with_label(label)
+        .with_name_policy(name_policy)
+        .with_max_name_length(max_name_length);
+
+    let mut volumes = Vec::new();
+
+    if let Some(values) = arguments.values_of("volume") {
+        for value in values {
+            match parse_volume(value) {
+                Some(v) => volumes.push(v),
+                None => fail(
+                    format!("Volumes must be given as NAME=SIZE, not {}.", value),
+                    ExitCode::Usage,
+                ),
             }
         }
+    }
 
-        // strip the new line character
-        input = input.strip_suffix("\n").unwrap_or("").to_string();
-        if input.ends_with('\r') {
-            input = input.strip_suffix("\r").unwrap_or("").to_string();
-        }
+    let assume_yes = arguments.is_present("yes");
+    let device = check_existing(path, arguments.is_present("force"));
 
-        if input != "y" && input != "Y" {
-            println!("Did not create image.");
-            exit(0);
-        }
+    println!("Create image of size {} bytes at {}", size, path);
 
-        // Delete the file
-        match std::fs::remove_file(path) {
-            Ok(_) => (),
-            Err(_) => {
-                eprintln!("Could not delete the old file.");
-                exit(1);
+    if !confirm("Confirm", assume_yes) {
+        println!("Did not create image.");
+        ExitCode::Success.exit();
+    }
+
+    let handler = if device {
+        // The device node is formatted in place rather than replaced by a file
+        Handler::new_device(path.to_string(), size, allocation)
+    } else {
+        if Path::new(path).exists() {
+            match std::fs::remove_file(path) {
+                Ok(_) => (),
+                Err(_) => fail("Could not delete the old file.", ExitCode::Io),
             }
         }
-    }
 
-    let mut handler = match Handler::new_create(path.to_string(), size as usize) {
+        Handler::new_create_with_allocation(path.to_string(), size as usize, allocation)
+    };
+
+    let mut handler = match handler {
         Ok(h) => h,
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(1);
-        }
+        Err(e) => fail(e, ExitCode::Io),
     };
 
     let mut manager = Manager::new();
 
-    match Disk::make_new_filesystem(&mut handler, &mut manager) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("{:?}", e);
-            exit(1);
+    if volumes.is_empty() {
+        format(&mut handler, &mut manager, &options, &boot_image);
+    } else {
+        let specs: Vec<(&str, u64)> = volumes.iter().map(|(n, s)| (n.as_str(), *s)).collect();
+
+        match VolumeTable::create(&mut handler, &specs) {
+            Ok(_) => (),
+            Err(e) => fail(e, ExitCode::Failure),
         }
-    };
+
+        for (name, _) in volumes.iter() {
+            match handler.select_volume(name) {
+                Ok(_) => (),
+                Err(e) => fail(e, ExitCode::Failure),
+            }
+
+            format(&mut handler, &mut manager, &options, &boot_image);
+        }
+    }
 
     println!("Successfully created image at {}", path);
 }
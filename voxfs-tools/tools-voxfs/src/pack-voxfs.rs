@@ -0,0 +1,161 @@
+use clap::{App, Arg};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use voxfs::{probe, Disk, MountOptions};
+use voxfs_tool_lib::{
+    confirm, describe_error, fail, pack, u64_to_sized_string, ExitCode, Handler, Manager,
+    CONTAINER_EXTENSION,
+};
+
+fn main() {
+    let arguments = App::new("pack-voxfs")
+        .version("0.1.0")
+        .about("This program packs an image into a .voxz file holding only its allocated blocks, to be restored with unpack-voxfs.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image to pack"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .takes_value(true)
+                .help("The path of the packed image, the image's path with a .voxz extension by default"),
+        )
+        .arg(
+            Arg::with_name("all-blocks")
+                .long("all-blocks")
+                .help("Keep the contents of unallocated data blocks, only blocks of zeroes are left out."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Replace an existing file at the output path without asking for confirmation."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    let output = match arguments.value_of("output") {
+        Some(o) => o.to_string(),
+        None => Path::new(path)
+            .with_extension(CONTAINER_EXTENSION)
+            .to_string_lossy()
+            .to_string(),
+    };
+
+    if Path::new(path) == Path::new(&output) {
+        fail(
+            "The output must be a different file to the image.",
+            ExitCode::Usage,
+        );
+    }
+
+    if Path::new(&output).exists()
+        && !confirm(
+            &format!("A file already exists at {}, replace it?", output),
+            arguments.is_present("yes"),
+        )
+    {
+        println!("Did not create {}.", output);
+        ExitCode::Success.exit();
+    }
+
+    let mut handler = match Handler::new(path.to_string()) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::NoImage),
+    };
+
+    if probe(&handler).is_none() {
+        fail(
+            format!("{} does not contain a voxfs filesystem.", path),
+            ExitCode::NoImage,
+        );
+    }
+
+    let (block_size, data_start, allocated) = allocation(&mut handler);
+    let all_blocks = arguments.is_present("all-blocks");
+
+    // Every block before the data region holds metadata so only data blocks are checked in the bitmap
+    let keep = |block: u64| -> bool {
+        let address = block * block_size;
+
+        if all_blocks || address < data_start {
+            return true;
+        }
+
+        return match allocated.get(((address - data_start) / block_size) as usize) {
+            Some(a) => *a,
+            None => true,
+        };
+    };
+
+    let file = match File::create(&output) {
+        Ok(f) => f,
+        Err(e) => fail(
+            format!("Failed to create {}. Error: {}", output, e),
+            ExitCode::Io,
+        ),
+    };
+
+    let mut writer = BufWriter::new(file);
+
+    let summary = match pack(&handler, block_size, &keep, &mut writer) {
+        Ok(s) => s,
+        Err(e) => fail(format!("Failed to pack the image: {}", e), ExitCode::Io),
+    };
+
+    let file = match writer.into_inner() {
+        Ok(f) => f,
+        Err(e) => fail(
+            format!("Failed to write {}. Error: {}", output, e),
+            ExitCode::Io,
+        ),
+    };
+
+    if let Err(e) = file.sync_all() {
+        fail(
+            format!("Failed to write {}. Error: {}", output, e),
+            ExitCode::Io,
+        );
+    }
+
+    println!(
+        "Packed {} ({}) into {} ({}), storing {} of {} blocks",
+        path,
+        u64_to_sized_string(summary.disk_size),
+        output,
+        u64_to_sized_string(summary.packed_size),
+        summary.stored_blocks,
+        summary.total_blocks
+    );
+}
+
+/// Reads the block size, where the data blocks start and which of them are allocated from the filesystem.
+fn allocation(handler: &mut Handler) -> (u64, u64, Vec<bool>) {
+    let mut manager = Manager::new();
+
+    let disk = match Disk::open_with_options(
+        handler,
+        &mut manager,
+        MountOptions::new().with_read_only(true),
+    ) {
+        Ok(d) => d,
+        Err(e) => fail(
+            format!("Disk opening error: {}", describe_error(&e)),
+            ExitCode::NoImage,
+        ),
+    };
+
+    let geometry = disk.geometry();
+    let allocated = (0..geometry.block_count())
+        .map(|i| disk.is_data_block_allocated(i))
+        .collect();
+
+    return (geometry.block_size(), geometry.data_start(), allocated);
+}
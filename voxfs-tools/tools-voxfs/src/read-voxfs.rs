@@ -1,7 +1,5 @@
 use clap::{App, Arg};
-use std::process::exit;
-use voxfs::Disk;
-use voxfs_tool_lib::{Handler, Manager};
+use voxfs_tool_lib::{describe_error, fail, open_image, ExitCode, OpenMode};
 
 const SEPARATOR: &str = "  ";
 
@@ -43,55 +41,71 @@ fn main() {
                 .requires("hide_header")
                 .help("Disable any formatting of raw output."),
         )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("tolerant")
+                .long("tolerant")
+                .takes_value(false)
+                .help("Skip unreadable tags and files instead of failing, listing them on stderr."),
+        )
         .get_matches();
 
     let path = match arguments.value_of("image") {
         Some(p) => p,
-        None => {
-            eprintln!("An image is required.");
-            exit(1);
-        }
+        None => fail("An image is required.", ExitCode::Usage),
     };
 
-    let mut manager = Manager::new();
-    let mut handler = match Handler::new(path.to_string()) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(1);
-        }
+    let mode = if arguments.is_present("tolerant") {
+        OpenMode::Tolerant
+    } else {
+        OpenMode::Strict
     };
 
-    let disk = match Disk::open_disk(&mut handler, &mut manager) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Disk opening error: {:?}", e);
-            exit(1);
-        }
-    };
+    let mut image = open_image(path, mode);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
 
     let file_name = match arguments.value_of("file") {
         Some(f) => f,
-        None => {
-            eprintln!("A file name is required to read.");
-            exit(1);
-        }
+        None => fail("A file name is required to read.", ExitCode::Usage),
     };
 
     let index = match disk.inode_with_name(file_name) {
         Some(i) => i,
-        None => {
-            eprintln!("No file exists with name \"{}\"", file_name);
-            exit(1);
-        }
+        None => fail(
+            format!("No file exists with name \"{}\"", file_name),
+            ExitCode::NotFound,
+        ),
     };
 
     let contents = match disk.read_file(index) {
         Ok(c) => c,
-        Err(e) => {
-            eprintln!("An error occurred while reading file contents: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(
+            format!(
+                "An error occurred while reading file contents: {}",
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
     };
 
     if arguments.is_present("raw") {
@@ -121,10 +135,10 @@ fn main() {
             Ok(s) => {
                 print!("{}", s);
             }
-            Err(_) => {
-                eprintln!("Could not create UTF-8 text from file contents.");
-                exit(1);
-            }
+            Err(_) => fail(
+                "Could not create UTF-8 text from file contents.",
+                ExitCode::Failure,
+            ),
         };
     }
 }
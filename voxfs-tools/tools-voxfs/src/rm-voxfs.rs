@@ -1,8 +1,5 @@
 use clap::{App, Arg};
-use std::io::Write;
-use std::process::exit;
-use voxfs::Disk;
-use voxfs_tool_lib::{Handler, Manager};
+use voxfs_tool_lib::{confirm, describe_error, fail, open_image, ExitCode, OpenMode};
 
 fn main() {
     let arguments = App::new("rm-voxfs")
@@ -20,84 +17,84 @@ fn main() {
                 .takes_value(true)
                 .help("The path of the file to remove"),
         )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Remove the file without asking for confirmation."),
+        )
         .get_matches();
 
     let path = match arguments.value_of("image") {
         Some(p) => p,
-        None => {
-            eprintln!("An image is required.");
-            exit(1);
-        }
+        None => fail("An image is required.", ExitCode::Usage),
     };
 
-    let mut manager = Manager::new();
-    let mut handler = match Handler::new(path.to_string()) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(1);
-        }
-    };
+    let mut image = open_image(path, OpenMode::Strict);
 
-    let mut disk = match Disk::open_disk(&mut handler, &mut manager) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Disk opening error: {:?}", e);
-            exit(1);
-        }
-    };
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let mut disk = image.disk();
 
     let file_name = match arguments.value_of("file") {
         Some(f) => f,
-        None => {
-            eprintln!("A file to remove is required.");
-            exit(1);
-        }
+        None => fail("A file to remove is required.", ExitCode::Usage),
     };
 
-    print!(
-        "Are you sure you wish to remove \"{}\" from the image: (y/n) ",
-        file_name
-    );
-
-    match std::io::stdout().flush() {
-        Ok(_) => (),
-        Err(_) => (),
-    }
-
-    let mut input = String::new();
-    match std::io::stdin().read_line(&mut input) {
-        Ok(_) => (),
-        Err(_) => {
-            eprintln!("Failed to read response.");
-            exit(1);
-        }
-    }
-
-    input = input.strip_suffix("\n").unwrap_or("").to_string();
-    if input.ends_with('\r') {
-        input = input.strip_suffix("\r").unwrap_or("").to_string();
-    }
-
-    if input != "y" && input != "Y" {
+    if !confirm(
+        &format!(
+            "Are you sure you wish to remove \"{}\" from the image?",
+            file_name
+        ),
+        arguments.is_present("yes"),
+    ) {
         println!("Will not remove file.");
-        exit(0);
+        ExitCode::Success.exit();
     }
 
     let index = match disk.inode_with_name(file_name) {
         Some(i) => i,
-        None => {
-            eprintln!("Could not find file with name {}", file_name);
-            exit(1);
-        }
+        None => fail(
+            format!("Could not find file with name {}", file_name),
+            ExitCode::NotFound,
+        ),
     };
 
     match disk.delete_file(index) {
         Ok(_) => (),
-        Err(e) => {
-            eprintln!("Could not remove file due to error: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(
+            format!("Could not remove file due to error: {}", describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    }
+
+    match disk.close() {
+        Ok(_) => (),
+        Err(e) => fail(
+            format!(
+                "Could not write the changes to the image: {}",
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
     }
 
     println!("Successfully removed file!");
@@ -0,0 +1,153 @@
+use clap::{App, Arg};
+use voxfs::{OpContext, ScrubRegion, ScrubReport};
+use voxfs_tool_lib::{
+    describe_error, fail, json_string, open_image, print_progress, ExitCode, MKImageError, OpenMode,
+};
+
+const SPACER: &str = "    ";
+
+fn main() {
+    let arguments = App::new("scrub-voxfs")
+        .version("0.1.0")
+        .about("This program verifies the checksums of every metadata record in a voxfs image.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Also write the report to a file as JSON."),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help("Show how many of the tags and inodes have been checked."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    // Damaged records are reported by the scrub, so they shouldn't stop the disk opening
+    let mut image = open_image(path, OpenMode::Tolerant);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
+
+    let context = match arguments.is_present("progress") {
+        true => OpContext::new().with_progress(&print_progress),
+        false => OpContext::new(),
+    };
+
+    let report = match disk.scrub_with_context(&context) {
+        Ok(r) => r,
+        Err(e) => fail(
+            format!("The scrub failed: {}", describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    };
+
+    println!(
+        "{:<16}{}{:>8}{}{:>8}",
+        "Region", SPACER, "Checked", SPACER, "Failed"
+    );
+
+    for region in ScrubRegion::ALL.iter() {
+        println!(
+            "{:<16}{}{:>8}{}{:>8}",
+            format!("{:?}", region),
+            SPACER,
+            report.checked(*region),
+            SPACER,
+            report.failed(*region)
+        );
+    }
+
+    println!("{:<16}{}not checksummed", "Data", SPACER);
+
+    for failure in report.failures() {
+        eprintln!(
+            "{:?} record {} at address {}: {}",
+            failure.region(),
+            failure.index(),
+            failure.address(),
+            describe_error(failure.reason())
+        );
+    }
+
+    if let Some(json_path) = arguments.value_of("json") {
+        match std::fs::write(json_path, json_report(&report)) {
+            Ok(_) => (),
+            Err(e) => fail(
+                format!("Could not write the report to {}: {}", json_path, e),
+                ExitCode::Io,
+            ),
+        }
+    }
+
+    if !report.is_clean() {
+        ExitCode::Failure.exit();
+    }
+}
+
+fn json_report(report: &ScrubReport<MKImageError>) -> String {
+    let regions: Vec<String> = ScrubRegion::ALL
+        .iter()
+        .map(|region| {
+            format!(
+                "{{\"region\":{},\"checked\":{},\"failed\":{}}}",
+                json_string(&format!("{:?}", region)),
+                report.checked(*region),
+                report.failed(*region)
+            )
+        })
+        .collect();
+
+    let failures: Vec<String> = report
+        .failures()
+        .iter()
+        .map(|failure| {
+            format!(
+                "{{\"region\":{},\"index\":{},\"address\":{},\"code\":{},\"reason\":{}}}",
+                json_string(&format!("{:?}", failure.region())),
+                failure.index(),
+                failure.address(),
+                json_string(failure.reason().code()),
+                json_string(&failure.reason().to_string())
+            )
+        })
+        .collect();
+
+    return format!(
+        "{{\"clean\":{},\"regions\":[{}],\"failures\":[{}]}}\n",
+        report.is_clean(),
+        regions.join(","),
+        failures.join(",")
+    );
+}
@@ -1,10 +1,21 @@
 use clap::{App, Arg};
-use std::process::exit;
-use voxfs::{Disk, TagFlags};
-use voxfs_tool_lib::{Handler, MKImageError, Manager};
+use voxfs::{Disk, Manifest, TagBlock, TagFlags, TagQuery, VoxFSError};
+use voxfs_tool_lib::{
+    describe_error, fail, json_string, open_image, ExitCode, MKImageError, OpenMode,
+};
 
 const SEPARATOR: &str = "    ";
 
+/// How the tag list is printed.
+enum ListFormat {
+    /// Names in three columns.
+    Human,
+    /// A JSON array with an object for each tag.
+    Json,
+    /// A tab separated line for each tag.
+    Porcelain,
+}
+
 fn main() {
     let arguments = App::new("tag-voxfs")
         .version("0.1.0")
@@ -22,7 +33,7 @@ fn main() {
                 .takes_value(true)
                 .max_values(1)
                 .value_name("tag_name")
-                .conflicts_with_all(&["delete", "list", "apply", "remove"])
+                .conflicts_with_all(&["delete", "list", "apply", "remove", "query"])
                 .help("Create a new tag"),
         )
         .arg(
@@ -31,16 +42,47 @@ fn main() {
                 .long("delete")
                 .takes_value(true)
                 .max_values(1)
-                .conflicts_with_all(&["create", "list", "apply", "remove"])
+                .conflicts_with_all(&["create", "list", "apply", "remove", "query"])
+                .value_name("tag_name")
+                .help("Delete a tag, printing the files it was applied to"),
+        )
+        .arg(
+            Arg::with_name("rename")
+                .long("rename")
+                .takes_value(true)
+                .value_names(&["tag_name", "new_name"])
+                .max_values(2)
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove", "query"])
+                .help("Rename a tag, keeping the files it is applied to"),
+        )
+        .arg(
+            Arg::with_name("archive")
+                .long("archive")
+                .takes_value(true)
+                .value_name("tag_name")
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove", "query"])
+                .help("Hide a tag without deleting it, keeping the files it is applied to"),
+        )
+        .arg(
+            Arg::with_name("restore")
+                .long("restore")
+                .takes_value(true)
                 .value_name("tag_name")
-                .help("Delete a tag"),
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove", "query", "archive"])
+                .help("Bring back an archived tag"),
         )
         .arg(
             Arg::with_name("list")
                 .short("l")
                 .long("list")
-                .conflicts_with_all(&["create", "delete", "apply", "remove"])
-                .help("List all tags"),
+                .conflicts_with_all(&["create", "delete", "apply", "remove", "query"])
+                .help("List all tags that aren't archived"),
+        )
+        .arg(
+            Arg::with_name("archived")
+                .long("archived")
+                .requires("list")
+                .help("List the archived tags instead."),
         )
         .arg(
             Arg::with_name("apply")
@@ -49,7 +91,7 @@ fn main() {
                 .takes_value(true)
                 .value_names(&["tag_name", "file_name"])
                 .max_values(2)
-                .conflicts_with_all(&["create", "delete", "list", "remove"])
+                .conflicts_with_all(&["create", "delete", "list", "remove", "query"])
                 .help("Apply tag to file"),
         )
         .arg(
@@ -59,46 +101,97 @@ fn main() {
                 .takes_value(true)
                 .value_names(&["tag_name", "file_name"])
                 .max_values(2)
-                .conflicts_with_all(&["create", "delete", "list", "apply"])
+                .conflicts_with_all(&["create", "delete", "list", "apply", "query"])
                 .help("Remove a tag from a file"),
         )
+        .arg(
+            Arg::with_name("query")
+                .short("q")
+                .long("query")
+                .takes_value(true)
+                .value_name("expression")
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove"])
+                .help("List the files matching a tag query, e.g. \"work & !(archived | old)\""),
+        )
+        .arg(
+            Arg::with_name("untagged")
+                .long("untagged")
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove", "query"])
+                .help("List the files that have no tags"),
+        )
+        .arg(
+            Arg::with_name("export-manifest")
+                .long("export-manifest")
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove", "query"])
+                .help("Print the tags and the files they are applied to as a JSON manifest."),
+        )
+        .arg(
+            Arg::with_name("apply-manifest")
+                .long("apply-manifest")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with_all(&["create", "delete", "list", "apply", "remove", "query", "export-manifest"])
+                .help("Create the tags in a manifest and apply them to the files named, keeping the existing tags."),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .requires("list")
+                .conflicts_with("porcelain")
+                .help("List the tags as a JSON array."),
+        )
+        .arg(
+            Arg::with_name("porcelain")
+                .long("porcelain")
+                .requires("list")
+                .help("List the tags as tab separated lines of index, name, members, flags and creation time."),
+        )
         .get_matches();
 
     let path = match arguments.value_of("image") {
         Some(p) => p,
-        None => {
-            eprintln!("An image is required.");
-            exit(1);
-        }
+        None => fail("An image is required.", ExitCode::Usage),
     };
 
-    let mut manager = Manager::new();
-    let mut handler = match Handler::new(path.to_string()) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("{}", e);
-            exit(1);
-        }
-    };
+    let mut image = open_image(path, OpenMode::Strict);
 
-    let disk = match Disk::open_disk(&mut handler, &mut manager) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Disk opening error: {:?}", e);
-            exit(1);
-        }
-    };
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
 
     if arguments.is_present("list") {
-        list_tags(disk);
+        let format = if arguments.is_present("json") {
+            ListFormat::Json
+        } else if arguments.is_present("porcelain") {
+            ListFormat::Porcelain
+        } else {
+            ListFormat::Human
+        };
+
+        list_tags(disk, format, arguments.is_present("archived"));
         return;
     } else if arguments.is_present("create") {
         let tag_name = match arguments.value_of("create") {
             Some(n) => n,
-            None => {
-                eprintln!("Error: A name is required for a new tag.");
-                exit(1);
-            }
+            None => fail("Error: A name is required for a new tag.", ExitCode::Usage),
         };
 
         create_new_tag(disk, tag_name);
@@ -106,33 +199,66 @@ fn main() {
     } else if arguments.is_present("delete") {
         let tag_name = match arguments.value_of("delete") {
             Some(n) => n,
-            None => {
-                eprintln!("Error: A name is required to delete a tag.");
-                exit(1);
-            }
+            None => fail(
+                "Error: A name is required to delete a tag.",
+                ExitCode::Usage,
+            ),
         };
 
         delete_tag(disk, tag_name);
         return;
+    } else if arguments.is_present("rename") {
+        let (tag_name, new_name) = match arguments.values_of("rename") {
+            Some(vals) => {
+                let vals: Vec<&str> = vals.collect();
+
+                if vals.len() != 2 {
+                    fail(
+                        format!(
+                            "Expected only 2 values instead {} were provided",
+                            vals.len()
+                        ),
+                        ExitCode::Usage,
+                    );
+                }
+
+                (vals[0], vals[1])
+            }
+            None => fail(
+                "Error: A tag and a new name are required to rename a tag.",
+                ExitCode::Usage,
+            ),
+        };
+
+        rename_tag(disk, tag_name, new_name);
+        return;
+    } else if let Some(tag_name) = arguments.value_of("archive") {
+        set_archived(disk, tag_name, true);
+        return;
+    } else if let Some(tag_name) = arguments.value_of("restore") {
+        set_archived(disk, tag_name, false);
+        return;
     } else if arguments.is_present("apply") {
         let (tag_name, file_name) = match arguments.values_of("apply") {
             Some(vals) => {
                 let vals: Vec<&str> = vals.collect();
 
                 if vals.len() != 2 {
-                    eprintln!(
-                        "Expected only 2 values instead {} were provided",
-                        vals.len()
+                    fail(
+                        format!(
+                            "Expected only 2 values instead {} were provided",
+                            vals.len()
+                        ),
+                        ExitCode::Usage,
                     );
-                    exit(1);
                 }
 
                 (vals[0], vals[1])
             }
-            None => {
-                eprintln!("Error: A tag and file name is required to apply a tag.");
-                exit(1);
-            }
+            None => fail(
+                "Error: A tag and file name is required to apply a tag.",
+                ExitCode::Usage,
+            ),
         };
 
         apply_tag(disk, tag_name, file_name);
@@ -143,89 +269,289 @@ fn main() {
                 let vals: Vec<&str> = vals.collect();
 
                 if vals.len() != 2 {
-                    eprintln!(
-                        "Expected only 2 values instead {} were provided",
-                        vals.len()
+                    fail(
+                        format!(
+                            "Expected only 2 values instead {} were provided",
+                            vals.len()
+                        ),
+                        ExitCode::Usage,
                     );
-                    exit(1);
                 }
 
                 (vals[0], vals[1])
             }
-            None => {
-                eprintln!("Error: A tag and file name is required to remove a tag.");
-                exit(1);
-            }
+            None => fail(
+                "Error: A tag and file name is required to remove a tag.",
+                ExitCode::Usage,
+            ),
         };
 
         remove_tag(disk, tag_name, file_name);
         return;
+    } else if arguments.is_present("query") {
+        let query = match arguments.value_of("query") {
+            Some(q) => q,
+            None => fail("Error: A query is required.", ExitCode::Usage),
+        };
+
+        query_tags(disk, query);
+        return;
+    } else if arguments.is_present("untagged") {
+        list_untagged(disk);
+        return;
+    } else if arguments.is_present("export-manifest") {
+        export_manifest(disk);
+        return;
+    } else if let Some(manifest_path) = arguments.value_of("apply-manifest") {
+        apply_manifest(disk, manifest_path);
+        return;
     }
 }
 
-fn list_tags(disk: Disk<MKImageError>) {
-    let tags = disk.list_tags();
+fn export_manifest(disk: Disk<MKImageError>) {
+    let manifest = match disk.export_manifest() {
+        Ok(m) => m,
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
+    };
 
-    if tags.len() == 0 {
-        println!("No tags on this disk!");
+    match serde_json::to_string_pretty(&manifest) {
+        Ok(json) => println!("{}", json),
+        Err(e) => fail(format!("Error: {}", e), ExitCode::Failure),
     }
+}
 
-    for i in 0..tags.len() {
-        if (i + 1) % 3 != 0 {
-            print!("{}{}", tags[i].name_string(), SEPARATOR);
-        } else {
-            println!("{}", tags[i].name_string());
+fn apply_manifest(mut disk: Disk<MKImageError>, manifest_path: &str) {
+    let contents = match std::fs::read_to_string(manifest_path) {
+        Ok(c) => c,
+        Err(e) => fail(
+            format!(
+                "Failed to read the manifest {}. Error: {}",
+                manifest_path, e
+            ),
+            ExitCode::Io,
+        ),
+    };
+
+    let manifest: Manifest = match serde_json::from_str(&contents) {
+        Ok(m) => m,
+        Err(e) => fail(format!("Invalid manifest: {}", e), ExitCode::Usage),
+    };
+
+    let report = match disk.apply_manifest(&manifest) {
+        Ok(r) => r,
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
+    };
+
+    for name in report.created_tags().iter() {
+        println!("Created new tag with name: \"{}\"", name);
+    }
+
+    println!("Applied {} tags to files", report.applied());
+
+    for name in report.missing_files().iter() {
+        eprintln!(
+            "No file with name: \"{}\" found, its tags were skipped.",
+            name
+        );
+    }
+}
+
+fn list_tags(mut disk: Disk<MKImageError>, format: ListFormat, archived: bool) {
+    let tags: Vec<TagBlock> = disk
+        .list_tags()
+        .into_iter()
+        .filter(|tag| tag.flags().archived() == archived)
+        .collect();
+
+    match format {
+        ListFormat::Human => {
+            if tags.len() == 0 {
+                println!("No tags on this disk!");
+            }
+
+            for i in 0..tags.len() {
+                if (i + 1) % 3 != 0 {
+                    print!("{}{}", tags[i].name_string(), SEPARATOR);
+                } else {
+                    println!("{}", tags[i].name_string());
+                }
+            }
+        }
+        ListFormat::Json => {
+            let entries: Vec<String> = tags
+                .iter()
+                .map(|tag| {
+                    format!(
+                        "{{\"index\":{},\"name\":{},\"members\":{},\"flags\":{},\"created\":{},\"color\":{},\"icon\":{}}}",
+                        tag.index(),
+                        json_string(&tag.name_string()),
+                        member_count(&mut disk, tag),
+                        json_string(&tag.flags().to_string()),
+                        json_string(&tag.creation_time().to_rfc3339()),
+                        tag.color()
+                            .map(|c| json_string(&format!("#{:06x}", c)))
+                            .unwrap_or_else(|| String::from("null")),
+                        tag.icon()
+                            .map(|i| json_string(&i.to_string()))
+                            .unwrap_or_else(|| String::from("null"))
+                    )
+                })
+                .collect();
+
+            println!("[{}]", entries.join(","));
+        }
+        ListFormat::Porcelain => {
+            for tag in tags.iter() {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    tag.index(),
+                    tag.name_string(),
+                    member_count(&mut disk, tag),
+                    tag.flags(),
+                    tag.creation_time().to_rfc3339()
+                );
+            }
         }
     }
 }
 
+fn query_tags(mut disk: Disk<MKImageError>, query: &str) {
+    let query = match TagQuery::parse(query) {
+        Ok(q) => q,
+        Err(e) => fail(format!("Invalid query: {}", e), ExitCode::Usage),
+    };
+
+    let inodes = match disk.query_tags(&query) {
+        Ok(i) => i,
+        Err(VoxFSError::NoTagsWithNames(names)) => fail(
+            format!("No tags with names: {}", names.join(", ")),
+            ExitCode::NotFound,
+        ),
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
+    };
+
+    for inode in inodes.iter() {
+        println!("{}{}{}", inode.index(), SEPARATOR, inode.name());
+    }
+}
+
+fn list_untagged(mut disk: Disk<MKImageError>) {
+    let inodes = match disk.list_untagged_inodes() {
+        Ok(i) => i,
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
+    };
+
+    for inode in inodes.iter() {
+        println!("{}{}{}", inode.index(), SEPARATOR, inode.name());
+    }
+}
+
+fn member_count(disk: &mut Disk<MKImageError>, tag: &TagBlock) -> u64 {
+    return match disk.tag_member_count(tag.index()) {
+        Ok(n) => n,
+        Err(e) => fail(
+            format!(
+                "Could not read the members of \"{}\": {}",
+                tag.name_string(),
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
+    };
+}
+
 fn create_new_tag(mut disk: Disk<MKImageError>, tag_name: &str) {
     match disk.create_new_tag(tag_name, TagFlags::default()) {
         Ok(t) => {
             println!("Created new tag with name: \"{}\"", t.name_string());
         }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
     }
 }
 
 fn delete_tag(mut disk: Disk<MKImageError>, tag_name: &str) {
     let tag_index = match disk.tag_with_name(tag_name) {
         Some(t) => t,
-        None => {
-            eprintln!("No tag exists with the name: \"{}\"", tag_name);
-            exit(1);
-        }
+        None => fail(
+            format!("No tag exists with the name: \"{}\"", tag_name),
+            ExitCode::NotFound,
+        ),
     };
 
-    match disk.delete_tag(tag_index) {
-        Ok(_) => {
-            println!("Successfully deleted the tag \"{}\"", tag_name);
-        }
-        Err(e) => {
-            eprintln!("Error whilst deleting tag: {}", e);
-            exit(1);
-        }
+    let members = match disk.delete_tag_returning_members(tag_index) {
+        Ok(m) => m,
+        Err(e) => fail(
+            format!("Error whilst deleting tag: {}", describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    };
+
+    println!("Successfully deleted the tag \"{}\"", tag_name);
+
+    if !members.is_empty() {
+        let inodes = disk.list_inodes();
+        let names: Vec<String> = members
+            .iter()
+            .filter_map(|index| inodes.iter().find(|inode| inode.index() == *index))
+            .map(|inode| inode.name())
+            .collect();
+
+        println!("It was applied to: {}", names.join(", "));
+    }
+}
+
+fn rename_tag(mut disk: Disk<MKImageError>, tag_name: &str, new_name: &str) {
+    let tag_index = match disk.tag_with_name(tag_name) {
+        Some(t) => t,
+        None => fail(
+            format!("No tag exists with the name: \"{}\"", tag_name),
+            ExitCode::NotFound,
+        ),
+    };
+
+    match disk.rename_tag(tag_index, new_name) {
+        Ok(_) => println!("Renamed the tag \"{}\" to \"{}\"", tag_name, new_name),
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
+    }
+}
+
+fn set_archived(mut disk: Disk<MKImageError>, tag_name: &str, archived: bool) {
+    let tag_index = match disk.tag_with_name(tag_name) {
+        Some(t) => t,
+        None => fail(
+            format!("No tag exists with the name: \"{}\"", tag_name),
+            ExitCode::NotFound,
+        ),
+    };
+
+    let result = if archived {
+        disk.archive_tag(tag_index)
+    } else {
+        disk.restore_tag(tag_index)
+    };
+
+    match result {
+        Ok(_) if archived => println!("Archived the tag \"{}\"", tag_name),
+        Ok(_) => println!("Restored the tag \"{}\"", tag_name),
+        Err(e) => fail(format!("Error: {}", describe_error(&e)), ExitCode::Failure),
     }
 }
 
 fn apply_tag(mut disk: Disk<MKImageError>, tag_name: &str, file_name: &str) {
     let tag_index = match disk.tag_with_name(tag_name) {
         Some(t) => t,
-        None => {
-            eprintln!("No tag with name: \"{}\" found.", tag_name);
-            exit(1);
-        }
+        None => fail(
+            format!("No tag with name: \"{}\" found.", tag_name),
+            ExitCode::NotFound,
+        ),
     };
 
     let file_index = match disk.inode_with_name(file_name) {
         Some(t) => t,
-        None => {
-            eprintln!("No file with name: \"{}\" found.", file_name);
-            exit(1);
-        }
+        None => fail(
+            format!("No file with name: \"{}\" found.", file_name),
+            ExitCode::NotFound,
+        ),
     };
 
     match disk.apply_tag(tag_index, file_index) {
@@ -233,28 +559,31 @@ fn apply_tag(mut disk: Disk<MKImageError>, tag_name: &str, file_name: &str) {
             println!("Applied tag \"{}\" to \"{}\"", tag_name, file_name);
             return;
         }
-        Err(e) => {
-            eprintln!("An error occurred while applying a tag: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(
+            format!(
+                "An error occurred while applying a tag: {}",
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
     }
 }
 
 fn remove_tag(mut disk: Disk<MKImageError>, tag_name: &str, file_name: &str) {
     let tag_index = match disk.tag_with_name(tag_name) {
         Some(t) => t,
-        None => {
-            eprintln!("No tag with name: \"{}\" found.", tag_name);
-            exit(1);
-        }
+        None => fail(
+            format!("No tag with name: \"{}\" found.", tag_name),
+            ExitCode::NotFound,
+        ),
     };
 
     let file_index = match disk.inode_with_name(file_name) {
         Some(t) => t,
-        None => {
-            eprintln!("No file with name: \"{}\" found.", file_name);
-            exit(1);
-        }
+        None => fail(
+            format!("No file with name: \"{}\" found.", file_name),
+            ExitCode::NotFound,
+        ),
     };
 
     match disk.remove_tag_from_inode(tag_index, file_index) {
@@ -262,9 +591,12 @@ fn remove_tag(mut disk: Disk<MKImageError>, tag_name: &str, file_name: &str) {
             println!("Removed tag \"{}\" from \"{}\"", tag_name, file_name);
             return;
         }
-        Err(e) => {
-            eprintln!("An error occurred while removing the tag: {}", e);
-            exit(1);
-        }
+        Err(e) => fail(
+            format!(
+                "An error occurred while removing the tag: {}",
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
     }
-}
\ No newline at end of file
+}
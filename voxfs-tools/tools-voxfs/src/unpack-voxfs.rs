@@ -0,0 +1,117 @@
+use clap::{App, Arg};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use voxfs::probe;
+use voxfs_tool_lib::{
+    confirm, fail, u64_to_sized_string, Allocation, ContainerReader, ExitCode, Handler,
+};
+
+fn main() {
+    let arguments = App::new("unpack-voxfs")
+        .version("0.1.0")
+        .about("This program restores an image from a .voxz file created by pack-voxfs.")
+        .arg(
+            Arg::with_name("packed")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the packed image"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .takes_value(true)
+                .help("The path of the image to create, the packed image's path without the .voxz extension by default"),
+        )
+        .arg(
+            Arg::with_name("allocation")
+                .long("allocation")
+                .takes_value(true)
+                .possible_values(&["zeroed", "sparse", "preallocate"])
+                .default_value("zeroed")
+                .help("How to allocate the image's space, preallocate reserves it without writing zeroes where supported."),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Replace an existing file at the output path without asking for confirmation."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("packed") {
+        Some(p) => p,
+        None => fail("A packed image is required.", ExitCode::Usage),
+    };
+
+    let output = match arguments.value_of("output") {
+        Some(o) => o.to_string(),
+        None => Path::new(path)
+            .with_extension("")
+            .to_string_lossy()
+            .to_string(),
+    };
+
+    if Path::new(path) == Path::new(&output) {
+        fail(
+            "The output must be a different file to the packed image, give it as the second argument.",
+            ExitCode::Usage,
+        );
+    }
+
+    let allocation = match arguments.value_of("allocation") {
+        Some("sparse") => Allocation::Sparse,
+        Some("preallocate") => Allocation::Preallocated,
+        _ => Allocation::Zeroed,
+    };
+
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => fail(
+            format!("Failed to open {}. Error: {}", path, e),
+            ExitCode::NoImage,
+        ),
+    };
+
+    let reader = match ContainerReader::new(BufReader::new(file)) {
+        Ok(r) => r,
+        Err(e) => fail(e, ExitCode::NoImage),
+    };
+
+    if Path::new(&output).exists()
+        && !confirm(
+            &format!("A file already exists at {}, replace it?", output),
+            arguments.is_present("yes"),
+        )
+    {
+        println!("Did not create {}.", output);
+        ExitCode::Success.exit();
+    }
+
+    let disk_size = reader.disk_size();
+
+    let mut handler =
+        match Handler::new_create_with_allocation(output.clone(), disk_size as usize, allocation) {
+            Ok(h) => h,
+            Err(e) => fail(e, ExitCode::Io),
+        };
+
+    let blocks = match reader.unpack(&mut handler) {
+        Ok(b) => b,
+        Err(e) => fail(
+            format!("Failed to unpack the image: {}", e),
+            ExitCode::Failure,
+        ),
+    };
+
+    if probe(&handler).is_none() {
+        eprintln!("Warning: {} does not contain a voxfs filesystem.", output);
+    }
+
+    println!(
+        "Unpacked {} into {} ({}), writing {} blocks",
+        path,
+        output,
+        u64_to_sized_string(disk_size),
+        blocks
+    );
+}
@@ -0,0 +1,257 @@
+use clap::{App, Arg};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use voxfs::Disk;
+use voxfs_tool_lib::{describe_error, fail, open_image, Crc32, ExitCode, MKImageError, OpenMode};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+const SEPARATOR: &str = "    ";
+
+/// The result of comparing one host file with the image.
+enum Outcome {
+    Match,
+    Mismatch { host: (u64, u32), image: (u64, u32) },
+    Missing,
+}
+
+fn main() {
+    let arguments = App::new("verify-voxfs")
+        .version("0.1.0")
+        .about("This program compares files on the host with the files of the same name in a voxfs image.")
+        .arg(
+            Arg::with_name("image")
+                .required(true)
+                .takes_value(true)
+                .help("The path of the image"),
+        )
+        .arg(
+            Arg::with_name("path")
+                .required(true)
+                .takes_value(true)
+                .help("A file, or a directory of files, to compare with the image"),
+        )
+        .arg(
+            Arg::with_name("volume")
+                .long("volume")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("The volume to use in an image with a volume table."),
+        )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .required(false)
+                .takes_value(false)
+                .help("Print a dump of the filesystem's structures to stderr once it is opened."),
+        )
+        .arg(
+            Arg::with_name("tolerant")
+                .long("tolerant")
+                .takes_value(false)
+                .help("Skip unreadable tags and files instead of failing, listing them on stderr."),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("Only report files that do not match."),
+        )
+        .get_matches();
+
+    let path = match arguments.value_of("image") {
+        Some(p) => p,
+        None => fail("An image is required.", ExitCode::Usage),
+    };
+
+    let host_path = match arguments.value_of("path") {
+        Some(p) => Path::new(p),
+        None => fail(
+            "A file or directory to compare is required.",
+            ExitCode::Usage,
+        ),
+    };
+
+    let host_files = list_host_files(host_path);
+
+    let mode = if arguments.is_present("tolerant") {
+        OpenMode::Tolerant
+    } else {
+        OpenMode::Strict
+    };
+
+    let mut image = open_image(path, mode);
+
+    if let Some(volume) = arguments.value_of("volume") {
+        image.select_volume(volume);
+    }
+
+    image.set_dump(arguments.is_present("dump"));
+
+    let disk = image.disk();
+    let quiet = arguments.is_present("quiet");
+    let mut problems = 0;
+
+    for file in host_files.iter() {
+        let name = match file.file_name().and_then(|n| n.to_str()) {
+            Some(n) => n,
+            None => {
+                eprintln!("Skipping {}, its name is not valid UTF-8.", file.display());
+                continue;
+            }
+        };
+
+        match compare(&disk, file, name) {
+            Outcome::Match => {
+                if !quiet {
+                    println!("OK{}{}", SEPARATOR, name);
+                }
+            }
+            Outcome::Mismatch { host, image } => {
+                problems += 1;
+                println!(
+                    "MISMATCH{}{} (host: {} bytes, crc32 {:08x}; image: {} bytes, crc32 {:08x})",
+                    SEPARATOR, name, host.0, host.1, image.0, image.1
+                );
+            }
+            Outcome::Missing => {
+                problems += 1;
+                println!("MISSING{}{}", SEPARATOR, name);
+            }
+        }
+    }
+
+    println!(
+        "{} files checked, {} did not match.",
+        host_files.len(),
+        problems
+    );
+
+    if problems > 0 {
+        ExitCode::Failure.exit();
+    }
+}
+
+/// The files to compare, either the file itself or the files directly inside a directory, sorted by path.
+fn list_host_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+
+    let entries = match std::fs::read_dir(path) {
+        Ok(e) => e,
+        Err(e) => fail(
+            format!("Could not read the directory {}: {}", path.display(), e),
+            ExitCode::Io,
+        ),
+    };
+
+    let mut files = Vec::new();
+
+    for entry in entries {
+        match entry {
+            Ok(e) => {
+                if e.path().is_file() {
+                    files.push(e.path());
+                }
+            }
+            Err(e) => fail(
+                format!("Could not read the directory {}: {}", path.display(), e),
+                ExitCode::Io,
+            ),
+        }
+    }
+
+    files.sort();
+
+    return files;
+}
+
+fn compare(disk: &Disk<MKImageError>, file: &Path, name: &str) -> Outcome {
+    let index = match disk.inode_with_name(name) {
+        Some(i) => i,
+        None => return Outcome::Missing,
+    };
+
+    let host = host_checksum(file);
+    let image = image_checksum(disk, index, name);
+
+    if host == image {
+        return Outcome::Match;
+    }
+
+    return Outcome::Mismatch { host, image };
+}
+
+/// The size and checksum of a host file, read in pieces.
+fn host_checksum(path: &Path) -> (u64, u32) {
+    let mut file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => fail(
+            format!("Could not open {}: {}", path.display(), e),
+            ExitCode::Io,
+        ),
+    };
+
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut crc = Crc32::new();
+    let mut size = 0;
+
+    loop {
+        let amount_read = match file.read(&mut buffer) {
+            Ok(s) => s,
+            Err(e) => fail(
+                format!("Error while reading {}: {}", path.display(), e),
+                ExitCode::Io,
+            ),
+        };
+
+        if amount_read == 0 {
+            return (size, crc.finish());
+        }
+
+        crc.update(&buffer[..amount_read]);
+        size += amount_read as u64;
+    }
+}
+
+/// The size and checksum of a file in the image, read in pieces.
+fn image_checksum(disk: &Disk<MKImageError>, index: u64, name: &str) -> (u64, u32) {
+    let mut handle = match disk.open_file(index) {
+        Ok(h) => h,
+        Err(e) => fail(
+            format!(
+                "Could not open \"{}\" in the image: {}",
+                name,
+                describe_error(&e)
+            ),
+            ExitCode::Failure,
+        ),
+    };
+
+    let mut crc = Crc32::new();
+    let mut size = 0;
+
+    loop {
+        let bytes = match disk.read_file_range(&mut handle, size, BUFFER_SIZE as u64) {
+            Ok(b) => b,
+            Err(e) => fail(
+                format!(
+                    "Could not read \"{}\" from the image: {}",
+                    name,
+                    describe_error(&e)
+                ),
+                ExitCode::Failure,
+            ),
+        };
+
+        if bytes.is_empty() {
+            disk.close_file(handle);
+
+            return (size, crc.finish());
+        }
+
+        crc.update(&bytes);
+        size += bytes.len() as u64;
+    }
+}
@@ -0,0 +1,488 @@
+use clap::{App, AppSettings, Arg, SubCommand};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use voxfs::{probe, Disk, TagQuery, VoxFSError};
+use voxfs_tool_lib::{
+    confirm, describe_error, export_zip, fail, open_image, ExitCode, Handler, Manager, OpenMode,
+    ZipTagLayout, ZIP_METADATA_NAME,
+};
+
+const BASH_COMPLETIONS: &str = include_str!("../completions/voxfs.bash");
+const FISH_COMPLETIONS: &str = include_str!("../completions/voxfs.fish");
+
+fn main() {
+    let arguments = App::new("voxfs")
+        .version("0.1.0")
+        .about("This program provides helpers shared by the voxfs tools.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about(
+                    "Prints a script that completes the tools' flags and the names inside images.",
+                )
+                .arg(
+                    Arg::with_name("shell")
+                        .required(true)
+                        .possible_values(&["bash", "zsh", "fish"])
+                        .help("The shell to print the script for"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Converts an image formatted by an older version of voxfs to the current format.")
+                .arg(
+                    Arg::with_name("image")
+                        .required(true)
+                        .help("The path of the image to migrate"),
+                )
+                .arg(
+                    Arg::with_name("volume")
+                        .long("volume")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("The volume to migrate in an image with a volume table."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("capacity")
+                .about("Prints how many inodes, tags and data blocks are used out of the number the image has.")
+                .arg(
+                    Arg::with_name("image")
+                        .required(true)
+                        .help("The path of the image"),
+                )
+                .arg(
+                    Arg::with_name("volume")
+                        .long("volume")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("The volume to use in an image with a volume table."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("hash")
+                .about("Prints the stored content hash of files in an image formatted with content hashes.")
+                .arg(
+                    Arg::with_name("image")
+                        .required(true)
+                        .help("The path of the image"),
+                )
+                .arg(
+                    Arg::with_name("files")
+                        .required(true)
+                        .multiple(true)
+                        .help("The names of the files"),
+                )
+                .arg(
+                    Arg::with_name("volume")
+                        .long("volume")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("The volume to use in an image with a volume table."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("truncate")
+                .about("Sets the size of a file in an image, freeing the blocks past the new end or appending zeroes.")
+                .arg(
+                    Arg::with_name("image")
+                        .required(true)
+                        .help("The path of the image"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .help("The name of the file"),
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .required(true)
+                        .help("The new size of the file in bytes"),
+                )
+                .arg(
+                    Arg::with_name("volume")
+                        .long("volume")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("The volume to use in an image with a volume table."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-zip")
+                .about("Writes the files in an image to a zip archive, keeping their tags as folders or in a metadata file.")
+                .arg(
+                    Arg::with_name("image")
+                        .required(true)
+                        .help("The path of the image"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .required(true)
+                        .help("The path of the zip archive"),
+                )
+                .arg(
+                    Arg::with_name("query")
+                        .short("q")
+                        .long("query")
+                        .takes_value(true)
+                        .value_name("expression")
+                        .help("Only export the files matching a tag query, e.g. \"work & !old\""),
+                )
+                .arg(
+                    Arg::with_name("metadata")
+                        .long("metadata")
+                        .help("Store each file once and list the tags in voxfs-tags.json, instead of a folder for each tag."),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .short("y")
+                        .long("yes")
+                        .help("Replace an existing file at the output path without asking for confirmation."),
+                )
+                .arg(
+                    Arg::with_name("volume")
+                        .long("volume")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("The volume to use in an image with a volume table."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("__complete-names")
+                .setting(AppSettings::Hidden)
+                .arg(Arg::with_name("image").required(true))
+                .arg(Arg::with_name("prefix").default_value(""))
+                .arg(
+                    Arg::with_name("kind")
+                        .long("kind")
+                        .takes_value(true)
+                        .possible_values(&["files", "tags"])
+                        .default_value("files"),
+                )
+                .arg(Arg::with_name("volume").long("volume").takes_value(true)),
+        )
+        .get_matches();
+
+    match arguments.subcommand() {
+        ("completions", Some(arguments)) => match arguments.value_of("shell") {
+            Some("bash") => print!("{}", BASH_COMPLETIONS),
+            // zsh can run the bash script through its compatibility layer
+            Some("zsh") => print!(
+                "autoload -U +X bashcompinit && bashcompinit\n{}",
+                BASH_COMPLETIONS
+            ),
+            Some("fish") => print!("{}", FISH_COMPLETIONS),
+            _ => fail("A shell is required.", ExitCode::Usage),
+        },
+        ("migrate", Some(arguments)) => migrate(
+            arguments.value_of("image").unwrap_or(""),
+            arguments.value_of("volume"),
+        ),
+        ("capacity", Some(arguments)) => capacity(
+            arguments.value_of("image").unwrap_or(""),
+            arguments.value_of("volume"),
+        ),
+        ("hash", Some(arguments)) => hash(
+            arguments.value_of("image").unwrap_or(""),
+            &arguments
+                .values_of("files")
+                .map_or(Vec::new(), |v| v.collect()),
+            arguments.value_of("volume"),
+        ),
+        ("truncate", Some(arguments)) => truncate(
+            arguments.value_of("image").unwrap_or(""),
+            arguments.value_of("file").unwrap_or(""),
+            arguments.value_of("size").unwrap_or(""),
+            arguments.value_of("volume"),
+        ),
+        ("export-zip", Some(arguments)) => export(
+            arguments.value_of("image").unwrap_or(""),
+            arguments.value_of("output").unwrap_or(""),
+            arguments.value_of("query"),
+            arguments.is_present("metadata"),
+            arguments.is_present("yes"),
+            arguments.value_of("volume"),
+        ),
+        ("__complete-names", Some(arguments)) => complete_names(
+            arguments.value_of("image").unwrap_or(""),
+            arguments.value_of("prefix").unwrap_or(""),
+            arguments.value_of("kind") == Some("tags"),
+            arguments.value_of("volume"),
+        ),
+        _ => fail("A subcommand is required.", ExitCode::Usage),
+    }
+}
+
+/// Rewrites the image in the current format if it was formatted by an older version.
+fn migrate(path: &str, volume: Option<&str>) {
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = volume {
+        image.select_volume(volume);
+    }
+
+    let mut disk = image.disk();
+
+    if !disk.needs_migration() {
+        println!("{} is already in the current format.", path);
+        return;
+    }
+
+    match disk.migrate().and_then(|_| disk.close()) {
+        Ok(_) => println!("Migrated {} to the current format.", path),
+        Err(e) => fail(
+            format!("Failed to migrate: {}", describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    }
+}
+
+/// Prints the used and total slots of the inode and tag tables and the data blocks.
+fn capacity(path: &str, volume: Option<&str>) {
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = volume {
+        image.select_volume(volume);
+    }
+
+    let report = image.disk().capacity_report();
+
+    for (name, usage) in [
+        ("inodes", report.inodes()),
+        ("tags", report.tags()),
+        ("blocks", report.blocks()),
+    ]
+    .iter()
+    {
+        println!(
+            "{:<8}{:>10} / {:<10}{:>4}%",
+            name,
+            usage.used(),
+            usage.total(),
+            usage.percent_used()
+        );
+    }
+}
+
+/// Prints the hash of each file followed by its name, like sha256sum, or a dash for a file without a hash.
+fn hash(path: &str, files: &[&str], volume: Option<&str>) {
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = volume {
+        image.select_volume(volume);
+    }
+
+    let disk = image.disk();
+
+    if !disk.has_content_hashes() {
+        fail(
+            format!("{} was not formatted with content hashes.", path),
+            ExitCode::Failure,
+        );
+    }
+
+    for name in files {
+        let inode_index = match disk.inode_with_name(name) {
+            Some(i) => i,
+            None => fail(
+                format!("Could not find file with name {}", name),
+                ExitCode::NotFound,
+            ),
+        };
+
+        match disk.file_hash(inode_index) {
+            Ok(Some(hash)) => {
+                let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+                println!("{}  {}", hex, name);
+            }
+            Ok(None) => println!("{:<64}  {}", "-", name),
+            Err(e) => fail(
+                format!(
+                    "Could not read the hash of {}: {}",
+                    name,
+                    describe_error(&e)
+                ),
+                ExitCode::Failure,
+            ),
+        }
+    }
+}
+
+/// Sets the size of a file, like truncate(1).
+fn truncate(path: &str, name: &str, size: &str, volume: Option<&str>) {
+    let size = match size.parse::<u64>() {
+        Ok(s) => s,
+        Err(_) => fail(
+            format!("The size {} is not a number of bytes.", size),
+            ExitCode::Usage,
+        ),
+    };
+
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = volume {
+        image.select_volume(volume);
+    }
+
+    let mut disk = image.disk();
+
+    let inode_index = match disk.inode_with_name(name) {
+        Some(i) => i,
+        None => fail(
+            format!("Could not find file with name {}", name),
+            ExitCode::NotFound,
+        ),
+    };
+
+    match disk
+        .truncate_file(inode_index, size)
+        .and_then(|_| disk.close())
+    {
+        Ok(_) => println!("Set the size of {} to {} bytes.", name, size),
+        Err(e) => fail(
+            format!("Failed to truncate {}: {}", name, describe_error(&e)),
+            ExitCode::Failure,
+        ),
+    }
+}
+
+/// Writes the files matching a query, or all of them, to a zip archive.
+fn export(
+    path: &str,
+    output: &str,
+    query: Option<&str>,
+    metadata: bool,
+    yes: bool,
+    volume: Option<&str>,
+) {
+    let query = match query.map(TagQuery::parse) {
+        Some(Ok(q)) => Some(q),
+        Some(Err(e)) => fail(format!("Invalid query: {}", e), ExitCode::Usage),
+        None => None,
+    };
+
+    let layout = match metadata {
+        true => ZipTagLayout::Metadata,
+        false => ZipTagLayout::Folders,
+    };
+
+    if Path::new(output).exists()
+        && !confirm(
+            &format!("A file already exists at {}, replace it?", output),
+            yes,
+        )
+    {
+        println!("Did not create {}.", output);
+        ExitCode::Success.exit();
+    }
+
+    let mut image = open_image(path, OpenMode::Strict);
+
+    if let Some(volume) = volume {
+        image.select_volume(volume);
+    }
+
+    let mut disk = image.disk();
+
+    let file = match File::create(output) {
+        Ok(f) => f,
+        Err(e) => fail(
+            format!("Failed to create {}. Error: {}", output, e),
+            ExitCode::Io,
+        ),
+    };
+
+    let mut writer = BufWriter::new(file);
+
+    let summary = match export_zip(&mut disk, query.as_ref(), layout, &mut writer) {
+        Ok(s) => s,
+        Err(e) => {
+            // Leave no partly written archive behind
+            drop(writer);
+            let _ = std::fs::remove_file(output);
+
+            match e {
+                VoxFSError::NoTagsWithNames(names) => fail(
+                    format!("No tags with names: {}", names.join(", ")),
+                    ExitCode::NotFound,
+                ),
+                e => fail(
+                    format!("Failed to export the zip: {}", describe_error(&e)),
+                    ExitCode::Failure,
+                ),
+            }
+        }
+    };
+
+    let file = match writer.into_inner() {
+        Ok(f) => f,
+        Err(e) => fail(
+            format!("Failed to write {}. Error: {}", output, e),
+            ExitCode::Io,
+        ),
+    };
+
+    if let Err(e) = file.sync_all() {
+        fail(
+            format!("Failed to write {}. Error: {}", output, e),
+            ExitCode::Io,
+        );
+    }
+
+    for name in summary.skipped.iter() {
+        eprintln!("Skipped {} as it has no contents.", name);
+    }
+
+    if metadata {
+        println!(
+            "Exported {} files to {}, with their tags in {}.",
+            summary.files, output, ZIP_METADATA_NAME
+        );
+    } else {
+        println!(
+            "Exported {} files to {} as {} entries.",
+            summary.files, output, summary.entries
+        );
+    }
+}
+
+/// Prints the file or tag names in the image that start with the prefix, one per line.
+/// This runs while the user is typing so it exits quietly if the image can't be read.
+fn complete_names(path: &str, prefix: &str, tags: bool, volume: Option<&str>) {
+    let mut handler = match Handler::new(path.to_string()) {
+        Ok(h) => h,
+        Err(_) => ExitCode::NoImage.exit(),
+    };
+
+    if let Some(volume) = volume {
+        if handler.select_volume(volume).is_err() {
+            ExitCode::NoImage.exit();
+        }
+    }
+
+    if probe(&handler).is_none() {
+        ExitCode::NoImage.exit();
+    }
+
+    let mut manager = Manager::new();
+
+    let disk = match Disk::open_disk(&mut handler, &mut manager) {
+        Ok(d) => d,
+        Err(_) => ExitCode::NoImage.exit(),
+    };
+
+    let mut names: Vec<String> = if tags {
+        disk.list_tags().iter().map(|t| t.name_string()).collect()
+    } else {
+        disk.list_inodes().iter().map(|i| i.name()).collect()
+    };
+
+    names.retain(|n| n.starts_with(prefix));
+    names.sort();
+    names.dedup();
+
+    for name in names.iter() {
+        println!("{}", name);
+    }
+}
@@ -1,14 +1,68 @@
+use crate::check::{group_by_severity, Finding, Severity};
+use crate::config::{Action, Config, KeyBindings};
+use crate::heatmap::HeatmapGrid;
+use crate::help::{HelpOverlay, Screen};
+use crate::read_ahead::{ImageStamp, ReadAhead};
+use crate::regions::{geometry_regions, guess_regions, region_at, Region};
 use crate::{VisualiserError, UI};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use std::path::Path;
+use std::time::Duration;
 use voxfs::{Disk, DiskHandler, FORBIDDEN_CHARACTERS};
-use voxfs_tool_lib::{Handler, MKImageError, Manager};
+use voxfs_tool_lib::{describe_error, Handler, MKImageError, Manager};
+
+/// The number of bytes read from the start of the image to guess the disk regions.
+const REGION_PROBE_SIZE: u64 = 4096;
+
+/// The number of blocks the filesystem check reads between looking for key presses.
+const CHECK_BUDGET_BLOCKS: u64 = 64;
 
 enum CurrentMenu {
     Main,
     RawDiskRoot,
     DiskInfo,
+    Regions,
+    Heatmap,
+    Check,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum MenuOption {
+    DiskInformation,
+    DiskRegions,
+    AccessHeatmap,
+    FilesystemCheck,
+    RawDisk,
+    Quit,
+}
+
+impl MenuOption {
+    fn label(&self) -> &'static str {
+        return match self {
+            MenuOption::DiskInformation => "Disk Information",
+            MenuOption::DiskRegions => "Disk Regions",
+            MenuOption::AccessHeatmap => "Block Access Heat Map",
+            MenuOption::FilesystemCheck => "Filesystem Check",
+            MenuOption::RawDisk => "View Raw Disk",
+            MenuOption::Quit => "Quit",
+        };
+    }
+}
+
+/// The access we have to the image. If the disk could not be opened we fall back to reading the raw bytes.
+enum ImageAccess<'a, 'b> {
+    Disk(Box<Disk<'a, 'b, MKImageError>>),
+    Raw(&'a mut Handler),
+}
+
+impl<'a, 'b> ImageAccess<'a, 'b> {
+    fn read_bytes(&mut self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+        return match self {
+            ImageAccess::Disk(disk) => disk.handler().read_bytes(location, amount),
+            ImageAccess::Raw(handler) => handler.read_bytes(location, amount),
+        };
+    }
 }
 
 pub struct Application {
@@ -17,10 +71,20 @@ pub struct Application {
     current_menu: CurrentMenu,
     quit: bool,
     ui: UI,
+    regions: Vec<Region>,
+    /// Set when the disk could not be opened, the application then runs in a degraded raw only mode.
+    open_error: Option<String>,
+    /// An address the raw disk view should start at the next time it is opened.
+    raw_start_address: Option<u64>,
+    /// Set when the terminal was resized since the last frame, the next frame must be redrawn from scratch.
+    resized: bool,
+    keys: KeyBindings,
+    /// The bytes around the raw view's window, kept between frames.
+    read_ahead: ReadAhead,
 }
 
 impl Application {
-    pub fn new(image_path: String) -> Result<Self, VisualiserError> {
+    pub fn new(image_path: String, config: Config) -> Result<Self, VisualiserError> {
         if !Path::new(&image_path).exists() {
             return Err(VisualiserError::new(&format!(
                 "No image file found with path: {}",
@@ -33,7 +97,13 @@ impl Application {
             disk_size: None,
             current_menu: CurrentMenu::Main,
             quit: false,
-            ui: UI::new()?,
+            ui: UI::new(&config)?,
+            regions: Vec::new(),
+            open_error: None,
+            raw_start_address: None,
+            resized: false,
+            keys: config.keys,
+            read_ahead: ReadAhead::new(config.view.read_ahead_pages),
         });
     }
 
@@ -41,22 +111,49 @@ impl Application {
         let mut handler = match Handler::new(self.path.clone()) {
             Ok(h) => h,
             Err(e) => {
-                return Err(VisualiserError::new(&e.get_message()));
+                return Err(VisualiserError::new(&e.to_string()));
             }
         };
 
-        match handler.disk_size() {
-            Ok(sz) => {
-                self.disk_size = Some(sz);
-            }
+        let disk_size = match handler.disk_size() {
+            Ok(sz) => sz,
             Err(_) => return Err(VisualiserError::new("Failed to retrieve disk size.")),
-        }
+        };
+
+        self.disk_size = Some(disk_size);
+
+        // The regions are guessed from the raw bytes so they are available even if the disk is damaged.
+        let first_block = handler
+            .read_bytes(0, REGION_PROBE_SIZE.min(disk_size))
+            .unwrap_or_default();
+        self.regions = guess_regions(&first_block, disk_size);
 
         let mut manager = Manager::new();
 
         let disk = match Disk::open_disk(&mut handler, &mut manager) {
-            Ok(d) => d,
-            Err(_) => return Err(VisualiserError::new_internal("Failed to open disk.")),
+            Ok(d) => {
+                // The disk's own geometry replaces the guess when it can be opened
+                self.regions = geometry_regions(&d.geometry(), disk_size);
+
+                Some(Box::new(d))
+            }
+            Err(e) => {
+                self.open_error = Some(format!(
+                    "The disk could not be opened ({}), only raw access is available.",
+                    e
+                ));
+
+                None
+            }
+        };
+
+        let access = match disk {
+            Some(d) => ImageAccess::Disk(d),
+            None => {
+                // Disk implements Drop, so the empty option must be dropped to end its borrow of the handler.
+                drop(disk);
+                ImageAccess::Raw(&mut handler)
+            }
         };
 
         match enable_raw_mode() {
@@ -68,7 +165,7 @@ impl Application {
             }
         }
 
-        let res = self.main_loop(disk);
+        let res = self.main_loop(access);
         self.ui.try_clear();
         ignore_result!(disable_raw_mode());
         self.ui.show_cursor();
@@ -76,27 +173,60 @@ impl Application {
         return res;
     }
 
-    fn main_loop(&mut self, mut disk: Disk<MKImageError>) -> Result<(), VisualiserError> {
+    fn main_loop(&mut self, mut access: ImageAccess) -> Result<(), VisualiserError> {
         while !self.quit {
             match self.current_menu {
                 CurrentMenu::Main => self.main_menu()?,
-                CurrentMenu::RawDiskRoot => self.raw_disk_root(&mut disk)?,
-                CurrentMenu::DiskInfo => self.disk_info(&mut disk)?,
+                CurrentMenu::RawDiskRoot => self.raw_disk_root(&mut access)?,
+                CurrentMenu::DiskInfo => match access {
+                    ImageAccess::Disk(ref mut disk) => self.disk_info(disk)?,
+                    ImageAccess::Raw(_) => self.current_menu = CurrentMenu::Main,
+                },
+                CurrentMenu::Regions => self.disk_regions()?,
+                CurrentMenu::Heatmap => match access {
+                    ImageAccess::Disk(ref disk) => self.access_heatmap(disk)?,
+                    ImageAccess::Raw(_) => self.current_menu = CurrentMenu::Main,
+                },
+                CurrentMenu::Check => match access {
+                    ImageAccess::Disk(ref mut disk) => self.filesystem_check(disk)?,
+                    ImageAccess::Raw(_) => self.current_menu = CurrentMenu::Main,
+                },
             }
         }
 
         return Ok(());
     }
 
-    fn raw_disk_root(&mut self, disk: &mut Disk<MKImageError>) -> Result<(), VisualiserError> {
+    /// The options available in the main menu, the disk information requires a successfully opened disk.
+    fn menu_options(&self) -> Vec<MenuOption> {
+        let mut options = Vec::new();
+
+        if self.open_error.is_none() {
+            options.push(MenuOption::DiskInformation);
+        }
+
+        options.push(MenuOption::DiskRegions);
+
+        if self.open_error.is_none() {
+            options.push(MenuOption::AccessHeatmap);
+            options.push(MenuOption::FilesystemCheck);
+        }
+
+        options.push(MenuOption::RawDisk);
+        options.push(MenuOption::Quit);
+
+        return options;
+    }
+
+    fn raw_disk_root(&mut self, access: &mut ImageAccess) -> Result<(), VisualiserError> {
         // NOTE: We can assume disk_size is not None because we must have a disk size to call this method before hand
         let mut cont = true;
-        let mut starting_address = 0;
+        let mut starting_address = self.raw_start_address.take().unwrap_or(0) as usize;
         let mut selected_row = 0;
         let mut force_redraw = true;
 
         while cont {
-            let (_, max_rows) = match UI::get_size() {
+            let (max_columns, max_rows) = match UI::get_size() {
                 Some(p) => p,
                 None => {
                     return Err(VisualiserError::new_internal(
@@ -105,58 +235,75 @@ impl Application {
                 }
             };
 
-            let table_rows = max_rows - 7;
+            let table_rows = max_rows.saturating_sub(7).max(1);
+            let bytes_per_row = UI::bytes_per_row(max_columns);
 
             if selected_row >= table_rows {
                 selected_row = table_rows - 1;
             }
 
-            let bytes_per_render = (table_rows as usize) * 16;
+            // Keep the first row aligned when the number of columns changes
+            starting_address -= starting_address % bytes_per_row;
+
+            let bytes_per_render = (table_rows as usize) * bytes_per_row;
 
             let mut start = starting_address;
             let mut end = starting_address + bytes_per_render;
 
             if end > self.disk_size.unwrap() as usize {
                 end = self.disk_size.unwrap() as usize;
-                start = end - bytes_per_render;
+                start = end.saturating_sub(bytes_per_render);
+                start -= start % bytes_per_row;
             }
 
-            let bytes = match disk
-                .handler()
-                .read_bytes(start as u64, (end - start) as u64)
-            {
+            let bytes = match self.read_ahead.read(
+                start as u64,
+                (end - start) as u64,
+                self.disk_size.unwrap(),
+                ImageStamp::of(&self.path),
+                &mut |location, amount| access.read_bytes(location, amount),
+            ) {
                 Ok(b) => b,
                 Err(_) => return Err(VisualiserError::new("Could not read the file.")),
             };
 
+            let selected_address = (start + selected_row as usize * bytes_per_row) as u64;
+
             self.ui.render_raw_disk_ui(
                 &bytes,
                 start as u64,
                 selected_row as usize,
+                region_at(&self.regions, selected_address),
+                bytes_per_row,
                 force_redraw,
             )?;
-            force_redraw = false;
 
             let key = self.blocking_read_key()?;
+            force_redraw = self.take_resized();
 
             match key {
                 Some(k) => {
-                    if k.code == KeyCode::Esc {
+                    if self.keys.action(&k) == Some(Action::Back) {
                         cont = false;
-                    } else if k.code == KeyCode::Down {
+                    } else if self.keys.action(&k) == Some(Action::Down) {
                         if selected_row < table_rows - 1 {
                             selected_row += 1;
                         } else {
-                            starting_address += 0x10;
+                            starting_address += bytes_per_row;
                             if starting_address + bytes_per_render
                                 > self.disk_size.unwrap() as usize
                             {
-                                starting_address = ((self.disk_size.unwrap() as usize
-                                    - bytes_per_render)
-                                    & (usize::MAX - 0xf))
-                                    as usize;
+                                starting_address = (self.disk_size.unwrap() as usize)
+                                    .saturating_sub(bytes_per_render);
+                                starting_address -= starting_address % bytes_per_row;
                             }
                         }
+                    } else if self.keys.action(&k) == Some(Action::Up) {
+                        if selected_row > 0 {
+                            selected_row -= 1;
+                        } else {
+                            starting_address = start.saturating_sub(bytes_per_row);
+                        }
                     }
                 }
                 None => (),
@@ -171,31 +318,36 @@ impl Application {
     fn main_menu(&mut self) -> Result<(), VisualiserError> {
         let mut cont = true;
         let mut selected_index = 0; // Quit is the last index
-        let number_of_options = 3;
+        let options = self.menu_options();
+        let labels: Vec<&str> = options.iter().map(|o| o.label()).collect();
+        let number_of_options = options.len();
         let mut force_redraw = true;
 
         while cont {
-            self.ui.render_main_menu(selected_index, force_redraw)?;
-            force_redraw = false;
+            self.ui
+                .render_main_menu(&labels, selected_index, &self.open_error, force_redraw)?;
             let event = self.blocking_read_key()?;
+            force_redraw = self.take_resized();
 
             match event {
                 Some(k) => {
-                    if k.code == KeyCode::Enter {
-                        if selected_index == number_of_options - 1 {
-                            self.quit = true;
-                            cont = false; // Time to quit
-                        } else if selected_index == 1 {
-                            self.current_menu = CurrentMenu::RawDiskRoot;
-                            cont = false;
-                        } else if selected_index == 0 {
-                            self.current_menu = CurrentMenu::DiskInfo;
-                            cont = false;
+                    if self.keys.action(&k) == Some(Action::Select) {
+                        match options[selected_index] {
+                            MenuOption::Quit => self.quit = true, // Time to quit
+                            MenuOption::RawDisk => self.current_menu = CurrentMenu::RawDiskRoot,
+                            MenuOption::DiskInformation => {
+                                self.current_menu = CurrentMenu::DiskInfo
+                            }
+                            MenuOption::DiskRegions => self.current_menu = CurrentMenu::Regions,
+                            MenuOption::AccessHeatmap => self.current_menu = CurrentMenu::Heatmap,
+                            MenuOption::FilesystemCheck => self.current_menu = CurrentMenu::Check,
                         }
-                    } else if k.code == KeyCode::Down {
+
+                        cont = false;
+                    } else if self.keys.action(&k) == Some(Action::Down) {
                         selected_index += 1;
                         selected_index %= number_of_options;
-                    } else if k.code == KeyCode::Up {
+                    } else if self.keys.action(&k) == Some(Action::Up) {
                         if selected_index == 0 {
                             selected_index = number_of_options - 1;
                         } else {
@@ -210,19 +362,64 @@ impl Application {
         return Ok(());
     }
 
+    fn disk_regions(&mut self) -> Result<(), VisualiserError> {
+        let mut cont = true;
+        let mut selected_index = 0;
+        let mut force_redraw = true;
+
+        while cont {
+            self.ui
+                .render_regions(&self.regions, selected_index, force_redraw)?;
+
+            let input = self.blocking_read_key()?;
+            force_redraw = self.take_resized();
+            match input {
+                Some(k) => {
+                    if self.keys.action(&k) == Some(Action::Back) {
+                        cont = false;
+                    } else if self.keys.action(&k) == Some(Action::Down) {
+                        if selected_index + 1 < self.regions.len() {
+                            selected_index += 1;
+                        }
+                    } else if self.keys.action(&k) == Some(Action::Up) {
+                        selected_index = selected_index.saturating_sub(1);
+                    } else if self.keys.action(&k) == Some(Action::Select)
+                        && selected_index < self.regions.len()
+                    {
+                        // Open the raw view at the start of the region, aligned to a row.
+                        self.raw_start_address = Some(self.regions[selected_index].start & !0xf);
+                        cont = false;
+                    }
+                }
+                None => (),
+            }
+        }
+
+        if self.raw_start_address.is_some() {
+            self.current_menu = CurrentMenu::RawDiskRoot;
+        } else {
+            self.current_menu = CurrentMenu::Main;
+        }
+
+        return Ok(());
+    }
+
     fn disk_info(&mut self, disk: &mut Disk<MKImageError>) -> Result<(), VisualiserError> {
         let disk_info = disk.disk_info();
+        // Summarised on the screen, a chain that can't be read leaves it out rather than closing the screen
+        let chain_stats = disk.chain_stats().ok();
         let mut force_redraw = true;
         let mut cont = true;
 
         while cont {
-            self.ui.render_disk_info(&disk_info, force_redraw)?;
-            force_redraw = false;
+            self.ui
+                .render_disk_info(&disk_info, chain_stats.as_ref(), force_redraw)?;
 
             let input = self.blocking_read_key()?;
+            force_redraw = self.take_resized();
             match input {
                 Some(k) => {
-                    if k.code == KeyCode::Char('q') {
+                    if self.keys.action(&k) == Some(Action::Back) {
                         cont = false;
                     }
                 }
@@ -235,6 +432,162 @@ impl Application {
         return Ok(());
     }
 
+    /// Shows which blocks have been read and written since the disk was opened, to find the hot metadata areas.
+    fn access_heatmap(&mut self, disk: &Disk<MKImageError>) -> Result<(), VisualiserError> {
+        let heatmap = disk.access_heatmap();
+        let mut force_redraw = true;
+        let mut cont = true;
+
+        // Where the most accessed block is, to explain the hottest cell
+        let status = match heatmap.iter().max_by_key(|(_, access)| access.total()) {
+            Some((block, access)) => {
+                let region = match region_at(&self.regions, block * heatmap.block_size()) {
+                    Some(r) => r.name,
+                    None => "Unknown",
+                };
+
+                format!(
+                    "Hottest block: {} in {} ({} reads, {} writes)",
+                    block, region, access.reads, access.writes
+                )
+            }
+            None => "No blocks have been accessed.".to_string(),
+        };
+
+        while cont {
+            let (max_columns, max_rows) = match UI::get_size() {
+                Some(p) => p,
+                None => {
+                    return Err(VisualiserError::new_internal(
+                        "Couldn't determine the terminal's size",
+                    ))
+                }
+            };
+
+            // The grid is inside a border and above the four rows of the footer
+            let row_width = max_columns.saturating_sub(2).max(1) as usize;
+            let rows = max_rows.saturating_sub(6).max(1) as usize;
+            let grid = HeatmapGrid::new(&heatmap, self.disk_size.unwrap(), row_width * rows);
+
+            self.ui
+                .render_heatmap(&grid, row_width, &status, force_redraw)?;
+
+            let input = self.blocking_read_key()?;
+            force_redraw = self.take_resized();
+
+            if let Some(k) = input {
+                cont = self.keys.action(&k) != Some(Action::Back);
+            }
+        }
+
+        self.current_menu = CurrentMenu::Main;
+
+        return Ok(());
+    }
+
+    /// Checks every record on the disk a slice at a time, listing what failed as it goes so the check can be
+    /// stopped early. Once finished a finding can be viewed in the raw disk or repaired.
+    fn filesystem_check(&mut self, disk: &mut Disk<MKImageError>) -> Result<(), VisualiserError> {
+        let mirrored = disk.has_metadata_mirror();
+        let mut findings: Vec<Finding> = Vec::new();
+        let mut status = String::new();
+        let mut selected_index = 0;
+        let mut force_redraw = true;
+        let mut running = true;
+        let mut cont = true;
+
+        disk.set_check_cursor(0);
+
+        while cont {
+            if running {
+                match disk.check_incremental(CHECK_BUDGET_BLOCKS) {
+                    Ok(report) => {
+                        let finished = report.reached_end();
+                        findings.extend(
+                            report
+                                .into_failures()
+                                .into_iter()
+                                .map(|f| Finding::new(f, mirrored)),
+                        );
+                        group_by_severity(&mut findings);
+                        running = !finished;
+
+                        status = if running {
+                            format!(
+                                "Checking... {}% ({} found)",
+                                disk.check_cursor() * 100 / disk.check_length(),
+                                findings.len()
+                            )
+                        } else {
+                            check_summary(&findings)
+                        };
+                    }
+                    Err(e) => {
+                        running = false;
+                        status = format!("The check failed: {}", describe_error(&e));
+                    }
+                }
+            }
+
+            self.ui
+                .render_check(&findings, selected_index, &status, force_redraw)?;
+
+            // While checking, only keys that are already waiting are read so the check carries on
+            let input = if running {
+                self.poll_key()?
+            } else {
+                self.blocking_read_key()?
+            };
+            force_redraw = self.take_resized();
+
+            let k = match input {
+                Some(k) => k,
+                None => continue,
+            };
+
+            match self.keys.action(&k) {
+                Some(Action::Back) => cont = false,
+                Some(Action::Down) if selected_index + 1 < findings.len() => selected_index += 1,
+                Some(Action::Up) => selected_index = selected_index.saturating_sub(1),
+                Some(Action::Select) if selected_index < findings.len() => {
+                    // Open the raw view at the damaged record, aligned to a row.
+                    self.raw_start_address =
+                        Some(findings[selected_index].failure.address() & !0xf);
+                    cont = false;
+                }
+                Some(Action::Repair) if !running && selected_index < findings.len() => {
+                    let finding = &mut findings[selected_index];
+
+                    status = if finding.repaired {
+                        "The record has already been repaired.".to_string()
+                    } else {
+                        match disk.repair_scrub_failure(&finding.failure) {
+                            Ok(_) => {
+                                finding.repaired = true;
+                                self.read_ahead.invalidate();
+                                format!(
+                                    "Rewrote {:?} record {}.",
+                                    finding.failure.region(),
+                                    finding.failure.index()
+                                )
+                            }
+                            Err(e) => format!("Could not repair: {}", describe_error(&e)),
+                        }
+                    };
+                }
+                _ => (),
+            }
+        }
+
+        if self.raw_start_address.is_some() {
+            self.current_menu = CurrentMenu::RawDiskRoot;
+        } else {
+            self.current_menu = CurrentMenu::Main;
+        }
+
+        return Ok(());
+    }
+
     /// This runs a prompt for a file name and returns a suitable file name. It's currently unused but could be in future developments.
     #[allow(dead_code)]
     fn prompt_file_name(&mut self) -> Result<Option<String>, VisualiserError> {
@@ -247,9 +600,9 @@ impl Application {
         while !cancel && !save {
             self.ui
                 .render_file_name_prompt(&file_name, &error_message, force_redraw)?;
-            force_redraw = false;
 
             let input = self.blocking_read_key()?;
+            force_redraw = self.take_resized();
             match input {
                 Some(k) => {
                     if k.code == KeyCode::Esc {
@@ -292,6 +645,23 @@ impl Application {
         }
     }
 
+    /// Returns true if the terminal was resized since this was last called.
+    fn take_resized(&mut self) -> bool {
+        let resized = self.resized;
+        self.resized = false;
+
+        return resized;
+    }
+
+    /// Returns the next key if one is already waiting, without blocking.
+    fn poll_key(&mut self) -> Result<Option<KeyEvent>, VisualiserError> {
+        return match crossterm::event::poll(Duration::from_millis(0)) {
+            Ok(true) => self.blocking_read_key(),
+            Ok(false) => Ok(None),
+            Err(e) => Err(VisualiserError::new(&format!("{}", e))),
+        };
+    }
+
     fn blocking_read_key(&mut self) -> Result<Option<KeyEvent>, VisualiserError> {
         let event = match crossterm::event::read() {
             Ok(e) => e,
@@ -300,13 +670,79 @@ impl Application {
 
         let key = match event {
             Event::Key(kv) => kv,
+            Event::Resize(_, _) => {
+                self.resized = true;
+                return Ok(None);
+            }
             _ => return Ok(None),
         };
 
         if key == KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL) {
             return Err(VisualiserError::new("SIGINT was found"));
+        }
+
+        // While the help overlay is open it receives every key
+        if self.ui.help_mut().is_some() {
+            self.help_input(key);
+            return Ok(None);
+        } else if self.keys.action(&key) == Some(Action::Help) {
+            let overlay = HelpOverlay::new(&self.keys, self.current_screen());
+            self.ui.open_help(overlay);
+            return Ok(None);
         } else {
             return Ok(Some(key));
         }
     }
+
+    /// Handles a key while the help overlay is open, typed characters are added to the search.
+    fn help_input(&mut self, key: KeyEvent) {
+        let action = self.keys.action(&key);
+
+        if key.code == KeyCode::Esc || action == Some(Action::Help) {
+            self.ui.close_help();
+            return;
+        }
+
+        let overlay = match self.ui.help_mut() {
+            Some(o) => o,
+            None => return,
+        };
+
+        if action == Some(Action::Up) {
+            overlay.select_previous();
+        } else if action == Some(Action::Down) {
+            overlay.select_next();
+        } else if key.code == KeyCode::Backspace {
+            overlay.pop_search();
+        } else if let KeyCode::Char(ch) = key.code {
+            overlay.push_search(ch);
+        }
+    }
+
+    fn current_screen(&self) -> Screen {
+        return match self.current_menu {
+            CurrentMenu::Main => Screen::MainMenu,
+            CurrentMenu::RawDiskRoot => Screen::RawDisk,
+            CurrentMenu::DiskInfo => Screen::DiskInfo,
+            CurrentMenu::Regions => Screen::Regions,
+            CurrentMenu::Heatmap => Screen::Heatmap,
+            CurrentMenu::Check => Screen::Check,
+        };
+    }
+}
+
+/// Counts the findings of each severity for the status line once the check has finished.
+fn check_summary(findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return "The check finished, no problems were found.".to_string();
+    }
+
+    let count = |severity: Severity| findings.iter().filter(|f| f.severity == severity).count();
+
+    return format!(
+        "The check finished: {} critical, {} errors, {} warnings.",
+        count(Severity::Critical),
+        count(Severity::Error),
+        count(Severity::Warning)
+    );
 }
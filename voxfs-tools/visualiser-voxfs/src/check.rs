@@ -0,0 +1,68 @@
+use voxfs::{ScrubFailure, ScrubRegion};
+use voxfs_tool_lib::{describe_error, MKImageError};
+
+/// How serious a finding of the filesystem check is, the most serious first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The damaged record has no other copy, its contents are lost.
+    Critical,
+    /// A record without a mirror is damaged but the copy loaded when the disk was opened can replace it.
+    Error,
+    /// One copy of a mirrored record is damaged, the other is intact.
+    Warning,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        return match self {
+            Severity::Critical => "Critical",
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+    }
+}
+
+/// A record that failed the filesystem check.
+pub struct Finding {
+    pub failure: ScrubFailure<MKImageError>,
+    pub severity: Severity,
+    pub repaired: bool,
+}
+
+impl Finding {
+    /// `mirrored` is whether the disk keeps a second copy of its tag and inode tables.
+    pub fn new(failure: ScrubFailure<MKImageError>, mirrored: bool) -> Self {
+        let severity = match failure.region() {
+            ScrubRegion::SuperBlock => Severity::Critical,
+            ScrubRegion::IndirectTags | ScrubRegion::IndirectINodes => Severity::Critical,
+            ScrubRegion::TagMirror | ScrubRegion::INodeMirror => Severity::Warning,
+            ScrubRegion::TagTable | ScrubRegion::INodeTable if mirrored => Severity::Warning,
+            ScrubRegion::TagTable | ScrubRegion::INodeTable => Severity::Error,
+        };
+
+        return Self {
+            failure,
+            severity,
+            repaired: false,
+        };
+    }
+
+    pub fn description(&self) -> String {
+        return describe_error(self.failure.reason());
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.repaired {
+            return "repaired";
+        } else if self.failure.repairable() {
+            return "repairable";
+        } else {
+            return "";
+        }
+    }
+}
+
+/// Sorts the findings so those of the same severity are listed together, the most serious first.
+pub fn group_by_severity(findings: &mut [Finding]) {
+    findings.sort_by_key(|f| (f.severity, f.failure.address()));
+}
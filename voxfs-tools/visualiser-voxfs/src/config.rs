@@ -0,0 +1,398 @@
+use crate::VisualiserError;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::style::{Color, Modifier, Style};
+
+/// An action that can be bound to one or more keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Select,
+    Back,
+    Help,
+    Repair,
+}
+
+impl Action {
+    /// The name used for this action in the config file.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Select => "select",
+            Action::Back => "back",
+            Action::Help => "help",
+            Action::Repair => "repair",
+        };
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "up" => Some(Action::Up),
+            "down" => Some(Action::Down),
+            "select" => Some(Action::Select),
+            "back" => Some(Action::Back),
+            "help" => Some(Action::Help),
+            "repair" => Some(Action::Repair),
+            _ => None,
+        };
+    }
+}
+
+/// The table of keys bound to each action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl KeyBindings {
+    /// Returns the action bound to a key if there is one.
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        for (action, keys) in &self.bindings {
+            if keys.contains(&key.code) {
+                return Some(*action);
+            }
+        }
+
+        return None;
+    }
+
+    /// Returns the keys bound to an action.
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        for (a, keys) in &self.bindings {
+            if *a == action {
+                return keys;
+            }
+        }
+
+        return &[];
+    }
+
+    /// Returns the keys bound to an action joined for display.
+    pub fn describe(&self, action: Action) -> String {
+        let names: Vec<String> = self.keys(action).iter().map(key_name).collect();
+
+        return names.join("/");
+    }
+
+    fn set(&mut self, action: Action, keys: Vec<KeyCode>) {
+        for (a, k) in self.bindings.iter_mut() {
+            if *a == action {
+                *k = keys;
+                return;
+            }
+        }
+
+        self.bindings.push((action, keys));
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        return Self {
+            bindings: vec![
+                (Action::Up, vec![KeyCode::Up]),
+                (Action::Down, vec![KeyCode::Down]),
+                (Action::Select, vec![KeyCode::Enter]),
+                (Action::Back, vec![KeyCode::Esc, KeyCode::Char('q')]),
+                (Action::Help, vec![KeyCode::Char('?')]),
+                (Action::Repair, vec![KeyCode::Char('r')]),
+            ],
+        };
+    }
+}
+
+/// The styles used by the user interface.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub default_style: Style,
+    pub highlight_style: Style,
+    pub error_style: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        return Self {
+            default_style: Style::default().fg(Color::White),
+            highlight_style: Style::default()
+                .add_modifier(Modifier::ITALIC)
+                .add_modifier(Modifier::REVERSED),
+            error_style: Style::default()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::SLOW_BLINK)
+                .fg(Color::Red),
+        };
+    }
+}
+
+/// How the views read the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ViewOptions {
+    /// How many screens of bytes the raw view reads at once and keeps in memory while scrolling.
+    pub read_ahead_pages: u64,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        return Self {
+            read_ahead_pages: 8,
+        };
+    }
+}
+
+/// The visualiser's configuration. The config file has a `[keys]` section mapping action names to a comma
+/// separated list of keys, a `[theme]` section mapping style names to colours and a `[view]` section, for example:
+///
+/// ```text
+/// [keys]
+/// up = up, k
+/// down = down, j
+///
+/// [theme]
+/// foreground = black
+/// error_foreground = #aa0000
+///
+/// [view]
+/// read_ahead_pages = 16
+/// ```
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Config {
+    pub keys: KeyBindings,
+    pub theme: Theme,
+    pub view: ViewOptions,
+}
+
+enum Section {
+    None,
+    Keys,
+    Theme,
+    View,
+}
+
+impl Config {
+    /// Reads and parses a config file.
+    pub fn load(path: &str) -> Result<Self, VisualiserError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(VisualiserError::new(&format!(
+                    "Failed to read the config file {}. Error: {}",
+                    path, e
+                )))
+            }
+        };
+
+        return Self::parse(&contents);
+    }
+
+    /// Parses the contents of a config file, any option not present keeps its default value.
+    pub fn parse(contents: &str) -> Result<Self, VisualiserError> {
+        let mut config = Self::default();
+        let mut section = Section::None;
+
+        for (index, raw_line) in contents.lines().enumerate() {
+            let line_number = index + 1;
+            let line = raw_line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = match &line[1..line.len() - 1] {
+                    "keys" => Section::Keys,
+                    "theme" => Section::Theme,
+                    "view" => Section::View,
+                    other => {
+                        return Err(VisualiserError::new(&format!(
+                            "Unknown section '{}' on line {} of the config file.",
+                            other, line_number
+                        )))
+                    }
+                };
+
+                continue;
+            }
+
+            let (name, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => {
+                    return Err(VisualiserError::new(&format!(
+                        "Expected 'name = value' on line {} of the config file.",
+                        line_number
+                    )))
+                }
+            };
+
+            match section {
+                Section::Keys => {
+                    let action = match Action::from_name(name) {
+                        Some(a) => a,
+                        None => {
+                            return Err(VisualiserError::new(&format!(
+                                "Unknown action '{}' on line {} of the config file.",
+                                name, line_number
+                            )))
+                        }
+                    };
+
+                    let mut keys = Vec::new();
+
+                    for key_name in value.split(',') {
+                        match parse_key(key_name.trim()) {
+                            Some(k) => keys.push(k),
+                            None => {
+                                return Err(VisualiserError::new(&format!(
+                                    "Unknown key '{}' on line {} of the config file.",
+                                    key_name.trim(),
+                                    line_number
+                                )))
+                            }
+                        }
+                    }
+
+                    config.keys.set(action, keys);
+                }
+                Section::Theme => {
+                    let color = match parse_color(value) {
+                        Some(c) => c,
+                        None => {
+                            return Err(VisualiserError::new(&format!(
+                                "Unknown colour '{}' on line {} of the config file.",
+                                value, line_number
+                            )))
+                        }
+                    };
+
+                    let theme = &mut config.theme;
+
+                    match name {
+                        "foreground" => theme.default_style = theme.default_style.fg(color),
+                        "background" => theme.default_style = theme.default_style.bg(color),
+                        "highlight_foreground" => {
+                            theme.highlight_style = theme.highlight_style.fg(color)
+                        }
+                        "highlight_background" => {
+                            // An explicit background replaces the reversed colours
+                            theme.highlight_style = theme
+                                .highlight_style
+                                .remove_modifier(Modifier::REVERSED)
+                                .bg(color)
+                        }
+                        "error_foreground" => theme.error_style = theme.error_style.fg(color),
+                        _ => {
+                            return Err(VisualiserError::new(&format!(
+                                "Unknown theme option '{}' on line {} of the config file.",
+                                name, line_number
+                            )))
+                        }
+                    }
+                }
+                Section::View => match name {
+                    "read_ahead_pages" => match value.parse::<u64>() {
+                        Ok(pages) if pages > 0 => config.view.read_ahead_pages = pages,
+                        _ => {
+                            return Err(VisualiserError::new(&format!(
+                                "Expected a number of pages of at least 1 on line {} of the config file.",
+                                line_number
+                            )))
+                        }
+                    },
+                    _ => {
+                        return Err(VisualiserError::new(&format!(
+                            "Unknown view option '{}' on line {} of the config file.",
+                            name, line_number
+                        )))
+                    }
+                },
+                Section::None => {
+                    return Err(VisualiserError::new(&format!(
+                        "Option '{}' on line {} of the config file is not in a section.",
+                        name, line_number
+                    )))
+                }
+            }
+        }
+
+        return Ok(config);
+    }
+}
+
+/// Parses a key name, either a single character or one of the named keys.
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(ch));
+    }
+
+    return match name.to_lowercase().as_str() {
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        "enter" => Some(KeyCode::Enter),
+        "esc" => Some(KeyCode::Esc),
+        "backspace" => Some(KeyCode::Backspace),
+        "tab" => Some(KeyCode::Tab),
+        "space" => Some(KeyCode::Char(' ')),
+        "home" => Some(KeyCode::Home),
+        "end" => Some(KeyCode::End),
+        "pageup" => Some(KeyCode::PageUp),
+        "pagedown" => Some(KeyCode::PageDown),
+        _ => None,
+    };
+}
+
+/// Returns a readable name for a key, the inverse of `parse_key`.
+fn key_name(key: &KeyCode) -> String {
+    return match key {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(ch) => ch.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        _ => "?".to_string(),
+    };
+}
+
+/// Parses a colour name or a `#rrggbb` hex colour.
+fn parse_color(name: &str) -> Option<Color> {
+    if name.starts_with('#') && name.len() == 7 {
+        let r = u8::from_str_radix(&name[1..3], 16).ok()?;
+        let g = u8::from_str_radix(&name[3..5], 16).ok()?;
+        let b = u8::from_str_radix(&name[5..7], 16).ok()?;
+
+        return Some(Color::Rgb(r, g, b));
+    }
+
+    return match name.to_lowercase().as_str() {
+        "reset" => Some(Color::Reset),
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "magenta" => Some(Color::Magenta),
+        "cyan" => Some(Color::Cyan),
+        "gray" => Some(Color::Gray),
+        "darkgray" => Some(Color::DarkGray),
+        "lightred" => Some(Color::LightRed),
+        "lightgreen" => Some(Color::LightGreen),
+        "lightyellow" => Some(Color::LightYellow),
+        "lightblue" => Some(Color::LightBlue),
+        "lightmagenta" => Some(Color::LightMagenta),
+        "lightcyan" => Some(Color::LightCyan),
+        "white" => Some(Color::White),
+        _ => None,
+    };
+}
@@ -0,0 +1,50 @@
+use voxfs::AccessHeatmap;
+
+/// The number of colours used for blocks that were accessed, from the coolest to the hottest.
+pub const HEAT_LEVELS: usize = 3;
+
+/// The heat map divided into a fixed number of cells, each covering the same number of blocks so the whole
+/// disk fits on the screen.
+pub struct HeatmapGrid {
+    pub blocks_per_cell: u64,
+    /// The total accesses of the blocks in each cell.
+    pub cells: Vec<u64>,
+    pub max: u64,
+}
+
+impl HeatmapGrid {
+    pub fn new(heatmap: &AccessHeatmap, disk_size: u64, cell_count: usize) -> Self {
+        let block_count = disk_size.div_ceil(heatmap.block_size()).max(1);
+        let blocks_per_cell = block_count.div_ceil(cell_count.max(1) as u64).max(1);
+        let used_cells = block_count.div_ceil(blocks_per_cell);
+
+        let cells: Vec<u64> = (0..used_cells)
+            .map(|cell| {
+                let start = cell * blocks_per_cell;
+
+                heatmap
+                    .range_accesses(start, start + blocks_per_cell - 1)
+                    .total()
+            })
+            .collect();
+
+        let max = cells.iter().copied().max().unwrap_or(0);
+
+        return Self {
+            blocks_per_cell,
+            cells,
+            max,
+        };
+    }
+
+    /// How hot a cell is, 0 when none of its blocks were accessed and up to `HEAT_LEVELS` for the hottest.
+    pub fn level(&self, cell: usize) -> usize {
+        let accesses = self.cells[cell];
+
+        if accesses == 0 || self.max == 0 {
+            return 0;
+        }
+
+        return 1 + ((accesses - 1) * HEAT_LEVELS as u64 / self.max) as usize;
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::{Action, KeyBindings};
+
+/// The screens of the visualiser, used to group the key bindings shown in the help overlay.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Screen {
+    MainMenu,
+    RawDisk,
+    DiskInfo,
+    Regions,
+    Heatmap,
+    Check,
+}
+
+const SCREENS: [Screen; 6] = [
+    Screen::MainMenu,
+    Screen::RawDisk,
+    Screen::DiskInfo,
+    Screen::Regions,
+    Screen::Heatmap,
+    Screen::Check,
+];
+
+impl Screen {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Screen::MainMenu => "Main Menu",
+            Screen::RawDisk => "Raw Disk",
+            Screen::DiskInfo => "Disk Information",
+            Screen::Regions => "Disk Regions",
+            Screen::Heatmap => "Block Access Heat Map",
+            Screen::Check => "Filesystem Check",
+        };
+    }
+
+    /// The actions available on this screen and what they do.
+    fn actions(&self) -> &'static [(Action, &'static str)] {
+        return match self {
+            Screen::MainMenu => &[
+                (Action::Up, "Previous option"),
+                (Action::Down, "Next option"),
+                (Action::Select, "Open the selected option"),
+                (Action::Help, "Show this help"),
+            ],
+            Screen::RawDisk => &[
+                (Action::Up, "Move the cursor up"),
+                (Action::Down, "Move the cursor down"),
+                (Action::Back, "Return to the main menu"),
+                (Action::Help, "Show this help"),
+            ],
+            Screen::DiskInfo => &[
+                (Action::Back, "Return to the main menu"),
+                (Action::Help, "Show this help"),
+            ],
+            Screen::Regions => &[
+                (Action::Up, "Previous region"),
+                (Action::Down, "Next region"),
+                (Action::Select, "View the region in the raw disk"),
+                (Action::Back, "Return to the main menu"),
+                (Action::Help, "Show this help"),
+            ],
+            Screen::Heatmap => &[
+                (Action::Back, "Return to the main menu"),
+                (Action::Help, "Show this help"),
+            ],
+            Screen::Check => &[
+                (Action::Up, "Previous finding"),
+                (Action::Down, "Next finding"),
+                (Action::Select, "View the damaged record in the raw disk"),
+                (Action::Repair, "Rewrite the record from the copy in memory"),
+                (Action::Back, "Stop the check or return to the main menu"),
+                (Action::Help, "Show this help"),
+            ],
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HelpEntry {
+    pub screen: &'static str,
+    pub keys: String,
+    pub description: &'static str,
+}
+
+impl HelpEntry {
+    fn matches(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+
+        return self.screen.to_lowercase().contains(&search)
+            || self.keys.to_lowercase().contains(&search)
+            || self.description.to_lowercase().contains(&search);
+    }
+}
+
+/// The state of the help overlay, a searchable list of the key bindings of every screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HelpOverlay {
+    entries: Vec<HelpEntry>,
+    search: String,
+    selected_index: usize,
+}
+
+impl HelpOverlay {
+    /// Builds the overlay from the key binding table, the bindings of the current screen are listed first.
+    pub fn new(keys: &KeyBindings, current: Screen) -> Self {
+        let mut entries = Vec::new();
+        let mut screens = vec![current];
+        screens.extend(SCREENS.iter().filter(|s| **s != current));
+
+        for screen in screens {
+            for (action, description) in screen.actions() {
+                entries.push(HelpEntry {
+                    screen: screen.name(),
+                    keys: keys.describe(*action),
+                    description,
+                });
+            }
+        }
+
+        return Self {
+            entries,
+            search: String::new(),
+            selected_index: 0,
+        };
+    }
+
+    /// The entries matching the current search.
+    pub fn filtered(&self) -> Vec<&HelpEntry> {
+        return self
+            .entries
+            .iter()
+            .filter(|e| e.matches(&self.search))
+            .collect();
+    }
+
+    pub fn search(&self) -> &str {
+        return &self.search;
+    }
+
+    pub fn selected_index(&self) -> usize {
+        return self.selected_index;
+    }
+
+    pub fn push_search(&mut self, ch: char) {
+        self.search.push(ch);
+        self.selected_index = 0;
+    }
+
+    pub fn pop_search(&mut self) {
+        self.search.pop();
+        self.selected_index = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected_index + 1 < self.filtered().len() {
+            self.selected_index += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+}
@@ -1,9 +1,16 @@
 #[macro_use]
 mod macros;
 mod application;
+mod check;
+mod config;
 mod error;
+mod heatmap;
+mod help;
+mod read_ahead;
+mod regions;
 mod user_interface;
 
 pub use application::Application;
+pub use config::Config;
 use error::VisualiserError;
 use user_interface::UI;
@@ -1,6 +1,6 @@
 use clap::{App, Arg};
 use std::process::exit;
-use visualiser_voxfs::Application;
+use visualiser_voxfs::{Application, Config};
 
 fn main() {
     let arguments = App::new("visualiser-voxfs")
@@ -12,6 +12,13 @@ fn main() {
                 .takes_value(true)
                 .help("The path of the image to open"),
         )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("A config file defining key bindings and a colour theme"),
+        )
         .get_matches();
 
     let path = match arguments.value_of("path") {
@@ -22,7 +29,18 @@ fn main() {
         }
     };
 
-    let mut application = match Application::new(path) {
+    let config = match arguments.value_of("config") {
+        Some(config_path) => match Config::load(config_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+
+    let mut application = match Application::new(path, config) {
         Ok(a) => a,
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -0,0 +1,79 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Identifies a version of the image file, so a window read before the file was written is not shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageStamp {
+    modified: Option<SystemTime>,
+    length: u64,
+}
+
+impl ImageStamp {
+    /// Reads the modification time and length of the image, None if they can't be read.
+    pub fn of(path: &str) -> Option<Self> {
+        let metadata = std::fs::metadata(Path::new(path)).ok()?;
+
+        return Some(Self {
+            modified: metadata.modified().ok(),
+            length: metadata.len(),
+        });
+    }
+}
+
+/// A window of the image kept in memory around the bytes on screen, so scrolling through the raw view only
+/// reads from the image when it moves out of the window.
+pub struct ReadAhead {
+    /// The size of the window as a number of screens of bytes.
+    pages: u64,
+    start: u64,
+    bytes: Vec<u8>,
+    stamp: Option<ImageStamp>,
+}
+
+impl ReadAhead {
+    pub fn new(pages: u64) -> Self {
+        return Self {
+            pages: pages.max(1),
+            start: 0,
+            bytes: Vec::new(),
+            stamp: None,
+        };
+    }
+
+    /// Returns `amount` bytes from `location`, first reading a window of about `pages` times the amount centred
+    /// on them if they aren't all held or the image has changed since the window was read.
+    pub fn read<E>(
+        &mut self,
+        location: u64,
+        amount: u64,
+        disk_size: u64,
+        stamp: Option<ImageStamp>,
+        read_bytes: &mut dyn FnMut(u64, u64) -> Result<Vec<u8>, E>,
+    ) -> Result<Vec<u8>, E> {
+        let held = location >= self.start
+            && location + amount <= self.start + self.bytes.len() as u64
+            && stamp.is_some()
+            && stamp == self.stamp;
+
+        if !held {
+            let extra = amount * (self.pages - 1) / 2;
+            let start = location.saturating_sub(extra);
+            let end = (location + amount + extra).min(disk_size);
+
+            self.bytes = read_bytes(start, end.saturating_sub(start))?;
+            self.start = start;
+            self.stamp = stamp;
+        }
+
+        let offset = (location - self.start) as usize;
+        let end = (offset + amount as usize).min(self.bytes.len());
+
+        return Ok(self.bytes[offset.min(end)..end].to_vec());
+    }
+
+    /// Drops the window, e.g. after the visualiser has written to the image.
+    pub fn invalidate(&mut self) {
+        self.bytes.clear();
+        self.stamp = None;
+    }
+}
@@ -0,0 +1,285 @@
+use voxfs::DiskGeometry;
+
+/// The block size voxfs uses when formatting a new image, used when the super block is unreadable.
+const FALLBACK_BLOCK_SIZE: u64 = 4096;
+
+/// A named range of bytes within the image. The start is inclusive and the end is exclusive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: &'static str,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Region {
+    fn new(name: &'static str, start: u64, end: u64) -> Self {
+        return Self { name, start, end };
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        return address >= self.start && address < self.end;
+    }
+
+    pub fn len(&self) -> u64 {
+        return self.end - self.start;
+    }
+}
+
+/// Guesses the layout of an image from the raw bytes of its first block.
+/// The super block fields are read without verifying the checksum so that a damaged image can still be
+/// partially described. If the fields are not plausible only the super block region is reported.
+pub fn guess_regions(first_block: &[u8], disk_size: u64) -> Vec<Region> {
+    let mut regions = Vec::new();
+
+    match read_layout(first_block, disk_size) {
+        Some(layout) => {
+            let block_size = layout.block_size;
+            let bitmap_start = layout.bitmap_start();
+            let tag_map_end = bitmap_start + bitmap_length(layout.tag_count, block_size);
+            let inode_map_end = tag_map_end + bitmap_length(layout.inode_count, block_size);
+            let block_map_end = inode_map_end + bitmap_length(layout.block_count, block_size);
+            let data_end = layout.data_start + layout.block_count * block_size;
+
+            regions.push(Region::new("Super block", 0, block_size));
+
+            if bitmap_start > block_size {
+                regions.push(Region::new("Boot area", block_size, bitmap_start));
+            }
+
+            regions.push(Region::new("Tag bitmap", bitmap_start, tag_map_end));
+            regions.push(Region::new("INode bitmap", tag_map_end, inode_map_end));
+            regions.push(Region::new("Block bitmap", inode_map_end, block_map_end));
+            regions.push(Region::new(
+                "Tag table",
+                layout.tag_start,
+                layout.inode_start,
+            ));
+
+            if layout.mirror_start != 0 {
+                regions.push(Region::new(
+                    "INode table",
+                    layout.inode_start,
+                    layout.mirror_start,
+                ));
+                regions.push(Region::new(
+                    "Tag table mirror",
+                    layout.mirror_start,
+                    layout.mirror_start + (layout.inode_start - layout.tag_start),
+                ));
+                regions.push(Region::new(
+                    "INode table mirror",
+                    layout.mirror_start + (layout.inode_start - layout.tag_start),
+                    layout.data_start,
+                ));
+            } else {
+                regions.push(Region::new(
+                    "INode table",
+                    layout.inode_start,
+                    layout.data_start,
+                ));
+            }
+
+            regions.push(Region::new(
+                "Data blocks",
+                layout.data_start,
+                data_end.min(disk_size),
+            ));
+
+            if data_end < disk_size {
+                regions.push(Region::new("Unused", data_end, disk_size));
+            }
+        }
+        None => {
+            let super_block_end = FALLBACK_BLOCK_SIZE.min(disk_size);
+
+            regions.push(Region::new("Super block (unreadable)", 0, super_block_end));
+
+            if super_block_end < disk_size {
+                regions.push(Region::new("Unknown", super_block_end, disk_size));
+            }
+        }
+    }
+
+    return regions;
+}
+
+/// Describes the layout of a disk that was opened successfully, from the geometry it reports.
+pub fn geometry_regions(geometry: &DiskGeometry, disk_size: u64) -> Vec<Region> {
+    let block_size = geometry.block_size();
+    let mut regions = vec![Region::new("Super block", 0, block_size)];
+
+    if geometry.boot_area_blocks() > 0 {
+        regions.push(Region::new(
+            "Boot area",
+            block_size,
+            geometry.tag_bitmap_start(),
+        ));
+    }
+
+    regions.push(Region::new(
+        "Tag bitmap",
+        geometry.tag_bitmap_start(),
+        geometry.inode_bitmap_start(),
+    ));
+    regions.push(Region::new(
+        "INode bitmap",
+        geometry.inode_bitmap_start(),
+        geometry.block_bitmap_start(),
+    ));
+    regions.push(Region::new(
+        "Block bitmap",
+        geometry.block_bitmap_start(),
+        geometry.bitmaps_end(),
+    ));
+    regions.push(Region::new(
+        "Tag table",
+        geometry.tag_table_start(),
+        geometry.inode_table_start(),
+    ));
+
+    // Each region ends where the next present one starts
+    let mut inode_table_end = geometry.data_start();
+
+    if let Some(start) = geometry.history_start() {
+        inode_table_end = start;
+    }
+
+    if let Some(start) = geometry.content_hash_start() {
+        inode_table_end = start;
+    }
+
+    if let (Some(tags), Some(inodes)) = (
+        geometry.mirror_tag_table_start(),
+        geometry.mirror_inode_table_start(),
+    ) {
+        regions.push(Region::new(
+            "INode table",
+            geometry.inode_table_start(),
+            tags,
+        ));
+        regions.push(Region::new("Tag table mirror", tags, inodes));
+        regions.push(Region::new("INode table mirror", inodes, inode_table_end));
+    } else {
+        regions.push(Region::new(
+            "INode table",
+            geometry.inode_table_start(),
+            inode_table_end,
+        ));
+    }
+
+    if let Some(start) = geometry.content_hash_start() {
+        regions.push(Region::new(
+            "Content hashes",
+            start,
+            geometry.history_start().unwrap_or(geometry.data_start()),
+        ));
+    }
+
+    if let Some(start) = geometry.history_start() {
+        regions.push(Region::new("History", start, geometry.data_start()));
+    }
+
+    regions.push(Region::new(
+        "Data blocks",
+        geometry.data_start(),
+        geometry.data_end().min(disk_size),
+    ));
+
+    if geometry.data_end() < disk_size {
+        regions.push(Region::new("Unused", geometry.data_end(), disk_size));
+    }
+
+    return regions;
+}
+
+/// Returns the region that contains an address if there is one.
+pub fn region_at(regions: &[Region], address: u64) -> Option<&Region> {
+    return regions.iter().find(|r| r.contains(address));
+}
+
+struct RawLayout {
+    block_size: u64,
+    tag_count: u64,
+    inode_count: u64,
+    block_count: u64,
+    tag_start: u64,
+    inode_start: u64,
+    data_start: u64,
+    boot_area_blocks: u64,
+    // Zero when the tag and inode tables are not mirrored
+    mirror_start: u64,
+}
+
+impl RawLayout {
+    /// The bitmaps follow the super block's block and the boot area.
+    fn bitmap_start(&self) -> u64 {
+        return self.block_size.saturating_mul(1 + self.boot_area_blocks);
+    }
+}
+
+/// Reads the super block fields at their documented offsets and checks that they describe a layout that fits the disk.
+fn read_layout(bytes: &[u8], disk_size: u64) -> Option<RawLayout> {
+    if bytes.len() < 63 {
+        return None;
+    }
+
+    let read_u64 = |offset: usize| {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(&bytes[offset..offset + 8]);
+        u64::from_le_bytes(b)
+    };
+
+    let layout = RawLayout {
+        block_size: read_u64(4),
+        tag_count: read_u64(12),
+        inode_count: read_u64(20),
+        block_count: read_u64(28),
+        tag_start: read_u64(36),
+        inode_start: read_u64(44),
+        data_start: read_u64(52),
+        boot_area_blocks: u16::from_le_bytes([bytes[61], bytes[62]]) as u64,
+        mirror_start: if bytes.len() >= 84 { read_u64(76) } else { 0 },
+    };
+
+    if layout.block_size < 512 || layout.block_size % 64 != 0 || layout.block_size > disk_size {
+        return None;
+    }
+
+    if layout.tag_start < layout.bitmap_start()
+        || layout.inode_start < layout.tag_start
+        || layout.data_start < layout.inode_start
+        || layout.data_start > disk_size
+    {
+        return None;
+    }
+
+    // The mirror holds a copy of both tables between the inode table and the data blocks
+    if layout.mirror_start != 0
+        && (layout.mirror_start < layout.inode_start
+            || layout.mirror_start > layout.data_start
+            || layout.inode_start - layout.tag_start > layout.data_start - layout.mirror_start)
+    {
+        return None;
+    }
+
+    // The counts must be small enough that the bitmaps fit before the tag table.
+    let maps = bitmap_length(layout.tag_count, layout.block_size)
+        .checked_add(bitmap_length(layout.inode_count, layout.block_size))?
+        .checked_add(bitmap_length(layout.block_count, layout.block_size))?;
+
+    if layout.bitmap_start().checked_add(maps)? > layout.tag_start {
+        return None;
+    }
+
+    layout.block_count.checked_mul(layout.block_size)?;
+
+    return Some(layout);
+}
+
+/// The length in bytes of a bitmap with a number of bits, rounded to the block size.
+fn bitmap_length(bits: u64, block_size: u64) -> u64 {
+    let bits_per_block = block_size * 8;
+    let blocks = bits / bits_per_block + if bits % bits_per_block != 0 { 1 } else { 0 };
+
+    return blocks.saturating_mul(block_size);
+}
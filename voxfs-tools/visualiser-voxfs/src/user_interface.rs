@@ -1,13 +1,20 @@
+use crate::check::{Finding, Severity};
+use crate::config::{Action, Config, KeyBindings};
 use crate::error::VisualiserError;
+use crate::heatmap::{HeatmapGrid, HEAT_LEVELS};
+use crate::help::HelpOverlay;
+use crate::regions::Region;
 use std::io;
 use std::io::Stdout;
 use tui::backend::CrosstermBackend;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
-use tui::style::{Color, Modifier, Style};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Style};
 use tui::text::{Span, Spans, Text};
-use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState};
-use tui::Terminal;
-use voxfs::DiskInfo;
+use tui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+};
+use tui::{Frame, Terminal};
+use voxfs::{ChainStats, DiskInfo, Exhaustion};
 use voxfs_tool_lib::u64_to_sized_string;
 
 type TerminalBackend = CrosstermBackend<Stdout>;
@@ -17,10 +24,12 @@ pub struct UI {
     highlight_style: Style,
     default_style: Style,
     error_style: Style,
+    keys: KeyBindings,
+    help: Option<HelpOverlay>,
 }
 
 impl UI {
-    pub fn new() -> Result<Self, VisualiserError> {
+    pub fn new(config: &Config) -> Result<Self, VisualiserError> {
         let stdout = io::stdout();
         let backend = CrosstermBackend::new(stdout);
         let terminal = match Terminal::new(backend) {
@@ -35,46 +44,87 @@ impl UI {
 
         return Ok(Self {
             terminal,
-            default_style: Style::default().fg(Color::White),
-            highlight_style: Style::default()
-                .add_modifier(Modifier::ITALIC)
-                .add_modifier(Modifier::REVERSED),
-            error_style: Style::default()
-                .add_modifier(Modifier::BOLD)
-                .add_modifier(Modifier::SLOW_BLINK)
-                .fg(Color::Red),
+            default_style: config.theme.default_style,
+            highlight_style: config.theme.highlight_style,
+            error_style: config.theme.error_style,
+            keys: config.keys.clone(),
+            help: None,
         });
     }
 
+    /// Returns the footer hint for opening the help overlay.
+    fn help_hint(&self) -> String {
+        return format!("{} - Help", self.keys.describe(Action::Help));
+    }
+
+    /// Shows the help overlay on top of the following frames until it is closed.
+    pub fn open_help(&mut self, overlay: HelpOverlay) {
+        self.help = Some(overlay);
+    }
+
+    pub fn close_help(&mut self) {
+        self.help = None;
+    }
+
+    /// Returns the help overlay if it is open.
+    pub fn help_mut(&mut self) -> Option<&mut HelpOverlay> {
+        return self.help.as_mut();
+    }
+
+    /// Renders the main menu. If a status message is provided it is shown below the menu, this is used
+    /// to explain why options are missing when the disk could not be opened.
     pub fn render_main_menu(
         &mut self,
+        options: &[&str],
         selected_index: usize,
+        status_message: &Option<String>,
         force_redraw: bool,
     ) -> Result<(), VisualiserError> {
         let default_style = self.default_style;
         let highlight_style = self.highlight_style;
+        let error_style = self.error_style;
+        let title = format!("Main Menu ({})", self.help_hint());
+        let help = self.help.clone();
 
         if force_redraw {
             ignore_result!(self.force_redraw_next_frame());
         }
 
         match self.terminal.draw(|f| {
-            let items = [
-                ListItem::new("Disk Information"),
-                ListItem::new("View Raw Disk"),
-                ListItem::new("Quit"),
-            ];
+            let items: Vec<ListItem> = options.iter().map(|o| ListItem::new(*o)).collect();
 
             let mut state = ListState::default();
             state.select(Some(selected_index));
 
             let block = List::new(items)
-                .block(Block::default().title("Main Menu").borders(Borders::ALL))
+                .block(Block::default().title(title.as_str()).borders(Borders::ALL))
                 .style(default_style)
                 .highlight_style(highlight_style)
                 .highlight_symbol(">> ");
 
-            f.render_stateful_widget(block, f.size(), &mut state);
+            match status_message {
+                Some(message) => {
+                    let rects = Layout::default()
+                        .constraints([Constraint::Min(5), Constraint::Length(3)])
+                        .direction(Direction::Vertical)
+                        .split(f.size());
+
+                    let status_block = Paragraph::new(Text::from(Spans::from(vec![Span::styled(
+                        message.as_str(),
+                        error_style,
+                    )])))
+                    .block(Block::default().title("Status").borders(Borders::ALL))
+                    .style(default_style);
+
+                    f.render_stateful_widget(block, rects[0], &mut state);
+                    f.render_widget(status_block, rects[1]);
+                }
+                None => f.render_stateful_widget(block, f.size(), &mut state),
+            }
+
+            if let Some(overlay) = &help {
+                render_help_overlay(f, overlay, default_style, highlight_style);
+            }
         }) {
             Ok(_) => (),
             Err(e) => {
@@ -93,10 +143,14 @@ impl UI {
         bytes: &Vec<u8>,
         current_offset: u64,
         selected_row: usize,
+        selected_region: Option<&Region>,
+        bytes_per_row: usize,
         force_redraw: bool,
     ) -> Result<(), VisualiserError> {
         let default_style = self.default_style;
         let highlight_style = self.highlight_style;
+        let help_hint = self.help_hint();
+        let help = self.help.clone();
 
         if force_redraw {
             ignore_result!(self.force_redraw_next_frame());
@@ -109,6 +163,7 @@ impl UI {
                 .split(f.size());
 
             // We want to render an interface like this (NOTE this won't allow addresses over 32 bits)
+            // The number of byte columns depends on the width of the terminal.
             //
             // Offset      00  01  02  03  04  05  06  07  08  09  0a  0b  0c  0d  0e  0f
             // 00000000    xx  xx  xx  xx  xx  xx  xx  xx  xx  xx  xx  xx  xx  xx  xx  xx
@@ -117,35 +172,21 @@ impl UI {
             let mut state = TableState::default();
             state.select(Some(selected_row));
 
-            let header = [
-                "Offset    ",
-                "00",
-                "01",
-                "02",
-                "03",
-                "04",
-                "05",
-                "06",
-                "07",
-                "08",
-                "09",
-                "0a",
-                "0b",
-                "0c",
-                "0d",
-                "0e",
-                "0f",
-            ];
+            let row_length = bytes_per_row + 1;
+            let mut header = vec!["Offset    ".to_string()];
+
+            for column in 0..bytes_per_row {
+                header.push(format!("{:02x}", column));
+            }
 
             let mut rows = Vec::new();
             let mut iteration_offset = current_offset;
             let mut current_row = vec![format!("{:08x}", iteration_offset)];
 
             for byte in bytes {
-                //Row::Data(["Row41", "ff", "ff", "ef"].into_iter());
-                if current_row.len() >= 17 {
+                if current_row.len() >= row_length {
                     rows.push(Row::Data(current_row.into_iter()));
-                    iteration_offset += 0x10;
+                    iteration_offset += bytes_per_row as u64;
                     current_row = vec![
                         format!("{:08x}", iteration_offset),
                         format!("{:02x}", *byte),
@@ -155,17 +196,17 @@ impl UI {
                 }
             }
 
-            if current_row.len() >= 17 {
+            if current_row.len() >= row_length {
                 rows.push(Row::Data(current_row.into_iter()));
             } else {
-                while current_row.len() < 17 {
+                while current_row.len() < row_length {
                     current_row.push(format!("  "));
                 }
 
                 rows.push(Row::Data(current_row.into_iter()));
             }
 
-            let mut widths = [Constraint::Length(2); 17];
+            let mut widths = vec![Constraint::Length(2); row_length];
             widths[0] = Constraint::Length(10);
 
             let block = Table::new(header.iter(), rows.into_iter())
@@ -179,10 +220,15 @@ impl UI {
                 .highlight_style(highlight_style)
                 .widths(&widths);
 
+            let region_text = match selected_region {
+                Some(r) => format!("Region: {}", r.name),
+                None => "Region: Unknown".to_string(),
+            };
+
             let footer_text = vec![Spans::from(vec![
-                Span::raw("esc - Back"),
+                Span::raw(help_hint),
                 Span::raw("    "),
-                Span::raw("↑,↓,←,→ - Move Cursor"),
+                Span::raw(region_text),
             ])];
 
             let footer_block = Paragraph::new(footer_text)
@@ -191,6 +237,10 @@ impl UI {
 
             f.render_widget(footer_block, rects[1]);
             f.render_stateful_widget(block, rects[0], &mut state);
+
+            if let Some(overlay) = &help {
+                render_help_overlay(f, overlay, default_style, highlight_style);
+            }
         }) {
             Ok(_) => (),
             Err(e) => {
@@ -207,9 +257,13 @@ impl UI {
     pub fn render_disk_info(
         &mut self,
         disk_info: &DiskInfo,
+        chain_stats: Option<&ChainStats>,
         force_redraw: bool,
     ) -> Result<(), VisualiserError> {
         let default_style = self.default_style;
+        let highlight_style = self.highlight_style;
+        let help_hint = self.help_hint();
+        let help = self.help.clone();
 
         if force_redraw {
             ignore_result!(self.force_redraw_next_frame());
@@ -217,11 +271,274 @@ impl UI {
 
         match self.terminal.draw(|f| {
             let splits = Layout::default().constraints(vec![Constraint::Min(10), Constraint::Length(3)]).direction(Direction::Vertical).split(f.size());
-            let body = Paragraph::new(Text::raw(format!("Tags: {}\nNumber of Free Tags: {}\nFiles: {}\nFree File spaces: {}\nBlock Size: {}\nFree Blocks: {}\n Free space: {}", disk_info.number_of_tags(), disk_info.free_tag_slots(), disk_info.number_of_files(), disk_info.free_file_slots(), disk_info.block_size(), disk_info.free_block_count(), u64_to_sized_string(disk_info.free_block_space())))).block(Block::default().title("Disk Information").borders(Borders::ALL).style(default_style)).alignment(Alignment::Center);
-            let command_bar = Paragraph::new(Text::raw("q - return to menu")).block(Block::default().borders(Borders::ALL).style(default_style)).alignment(Alignment::Center);
+            let body = Paragraph::new(Text::raw(format!("Tags: {}\nNumber of Free Tags: {}\nFiles: {}\nFree File spaces: {}\nBlock Size: {}\nMax name length: {}\nFree Blocks: {}\n Free space: {}\nRuns out first: {}\nChecksum failures: {}\nClosed cleanly: {}\n{}", disk_info.number_of_tags(), disk_info.free_tag_slots(), disk_info.number_of_files(), disk_info.free_file_slots(), disk_info.block_size(), name_length_string(disk_info.max_name_length()), disk_info.free_block_count(), u64_to_sized_string(disk_info.free_block_space()), exhaustion_string(disk_info.projected_exhaustion()), disk_info.checksum_failures(), !disk_info.opened_dirty(), chain_stats_string(chain_stats)))).block(Block::default().title("Disk Information").borders(Borders::ALL).style(default_style)).alignment(Alignment::Center);
+            let command_bar = Paragraph::new(Text::raw(help_hint)).block(Block::default().borders(Borders::ALL).style(default_style)).alignment(Alignment::Center);
 
             f.render_widget(body,splits[0]);
             f.render_widget(command_bar, splits[1]);
+
+            if let Some(overlay) = &help {
+                render_help_overlay(f, overlay, default_style, highlight_style);
+            }
+        }) {
+            Ok(_) => (),
+            Err(e) => {
+                return Err(VisualiserError::new_internal(&format!(
+                    "Failed to render menu. Error: {}",
+                    e
+                )))
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn render_regions(
+        &mut self,
+        regions: &[Region],
+        selected_index: usize,
+        force_redraw: bool,
+    ) -> Result<(), VisualiserError> {
+        let default_style = self.default_style;
+        let highlight_style = self.highlight_style;
+        let help_hint = self.help_hint();
+        let help = self.help.clone();
+
+        if force_redraw {
+            ignore_result!(self.force_redraw_next_frame());
+        }
+
+        match self.terminal.draw(|f| {
+            let rects = Layout::default()
+                .constraints([Constraint::Min(5), Constraint::Length(3)])
+                .direction(Direction::Vertical)
+                .split(f.size());
+
+            let mut state = TableState::default();
+            state.select(Some(selected_index));
+
+            let rows = regions.iter().map(|r| {
+                Row::Data(
+                    vec![
+                        r.name.to_string(),
+                        format!("{:08x}", r.start),
+                        format!("{:08x}", r.end),
+                        u64_to_sized_string(r.len()),
+                    ]
+                    .into_iter(),
+                )
+            });
+
+            let widths = [
+                Constraint::Length(24),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(12),
+            ];
+
+            let table = Table::new(["Region", "Start", "End", "Size"].iter(), rows)
+                .column_spacing(2)
+                .block(Block::default().title("Disk Regions").borders(Borders::ALL))
+                .style(default_style)
+                .highlight_style(highlight_style)
+                .widths(&widths);
+
+            let command_bar = Paragraph::new(Text::raw(help_hint))
+                .block(Block::default().borders(Borders::ALL).style(default_style))
+                .alignment(Alignment::Center);
+
+            f.render_stateful_widget(table, rects[0], &mut state);
+            f.render_widget(command_bar, rects[1]);
+
+            if let Some(overlay) = &help {
+                render_help_overlay(f, overlay, default_style, highlight_style);
+            }
+        }) {
+            Ok(_) => (),
+            Err(e) => {
+                return Err(VisualiserError::new_internal(&format!(
+                    "Failed to render menu. Error: {}",
+                    e
+                )))
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Renders the findings of the filesystem check, grouped by severity, above a status line.
+    pub fn render_check(
+        &mut self,
+        findings: &[Finding],
+        selected_index: usize,
+        status: &str,
+        force_redraw: bool,
+    ) -> Result<(), VisualiserError> {
+        let default_style = self.default_style;
+        let highlight_style = self.highlight_style;
+        let hints = format!(
+            "{} - View bytes  {} - Repair  {}",
+            self.keys.describe(Action::Select),
+            self.keys.describe(Action::Repair),
+            self.help_hint()
+        );
+        let help = self.help.clone();
+
+        if force_redraw {
+            ignore_result!(self.force_redraw_next_frame());
+        }
+
+        match self.terminal.draw(|f| {
+            let rects = Layout::default()
+                .constraints([Constraint::Min(5), Constraint::Length(4)])
+                .direction(Direction::Vertical)
+                .split(f.size());
+
+            let mut state = TableState::default();
+
+            if !findings.is_empty() {
+                state.select(Some(selected_index));
+            }
+
+            let rows = findings.iter().map(|finding| {
+                let colour = match finding.severity {
+                    Severity::Critical => Color::Red,
+                    Severity::Error => Color::LightRed,
+                    Severity::Warning => Color::Yellow,
+                };
+
+                Row::StyledData(
+                    vec![
+                        finding.severity.label().to_string(),
+                        format!("{:?}", finding.failure.region()),
+                        finding.failure.index().to_string(),
+                        format!("{:08x}", finding.failure.address()),
+                        finding.description(),
+                        finding.status().to_string(),
+                    ]
+                    .into_iter(),
+                    default_style.fg(colour),
+                )
+            });
+
+            let widths = [
+                Constraint::Length(8),
+                Constraint::Length(14),
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Min(20),
+                Constraint::Length(10),
+            ];
+
+            let table = Table::new(
+                [
+                    "Severity", "Region", "Record", "Address", "Problem", "Status",
+                ]
+                .iter(),
+                rows,
+            )
+            .column_spacing(2)
+            .block(
+                Block::default()
+                    .title("Filesystem Check")
+                    .borders(Borders::ALL),
+            )
+            .style(default_style)
+            .highlight_style(highlight_style)
+            .widths(&widths);
+
+            let footer = Paragraph::new(vec![Spans::from(status), Spans::from(hints.as_str())])
+                .style(default_style)
+                .block(Block::default().borders(Borders::ALL));
+
+            f.render_stateful_widget(table, rects[0], &mut state);
+            f.render_widget(footer, rects[1]);
+
+            if let Some(overlay) = &help {
+                render_help_overlay(f, overlay, default_style, highlight_style);
+            }
+        }) {
+            Ok(_) => (),
+            Err(e) => {
+                return Err(VisualiserError::new_internal(&format!(
+                    "Failed to render menu. Error: {}",
+                    e
+                )))
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Renders the heat map as a grid of cells, one character each, filling rows of the width given.
+    pub fn render_heatmap(
+        &mut self,
+        grid: &HeatmapGrid,
+        row_width: usize,
+        status: &str,
+        force_redraw: bool,
+    ) -> Result<(), VisualiserError> {
+        let default_style = self.default_style;
+        let highlight_style = self.highlight_style;
+        let help_hint = self.help_hint();
+        let help = self.help.clone();
+        let colours: [Color; HEAT_LEVELS] = [Color::Blue, Color::Yellow, Color::Red];
+
+        if force_redraw {
+            ignore_result!(self.force_redraw_next_frame());
+        }
+
+        match self.terminal.draw(|f| {
+            let rects = Layout::default()
+                .constraints([Constraint::Min(5), Constraint::Length(4)])
+                .direction(Direction::Vertical)
+                .split(f.size());
+
+            let lines: Vec<Spans> = (0..grid.cells.len())
+                .collect::<Vec<usize>>()
+                .chunks(row_width.max(1))
+                .map(|row| {
+                    Spans::from(
+                        row.iter()
+                            .map(|cell| match grid.level(*cell) {
+                                0 => Span::styled("·", default_style),
+                                level => Span::styled("█", default_style.fg(colours[level - 1])),
+                            })
+                            .collect::<Vec<Span>>(),
+                    )
+                })
+                .collect();
+
+            let title = format!(
+                "Block Access Heat Map ({} blocks per cell)",
+                grid.blocks_per_cell
+            );
+            let body = Paragraph::new(Text::from(lines))
+                .block(Block::default().title(title.as_str()).borders(Borders::ALL))
+                .style(default_style);
+
+            let mut legend = vec![Span::raw("· none  ")];
+
+            for (i, colour) in colours.iter().enumerate() {
+                legend.push(Span::styled("█", default_style.fg(*colour)));
+                legend.push(Span::raw(format!(
+                    " up to {}  ",
+                    grid.max * (i as u64 + 1) / HEAT_LEVELS as u64
+                )));
+            }
+
+            legend.push(Span::raw(help_hint));
+
+            let footer = Paragraph::new(vec![Spans::from(legend), Spans::from(status)])
+                .style(default_style)
+                .block(Block::default().borders(Borders::ALL));
+
+            f.render_widget(body, rects[0]);
+            f.render_widget(footer, rects[1]);
+
+            if let Some(overlay) = &help {
+                render_help_overlay(f, overlay, default_style, highlight_style);
+            }
         }) {
             Ok(_) => (),
             Err(e) => {
@@ -335,6 +652,18 @@ impl UI {
         return Ok(());
     }
 
+    /// Returns the number of bytes shown in each row of the raw disk view for a terminal width.
+    /// Each byte column takes 4 characters and the offset column and borders take 14.
+    pub fn bytes_per_row(columns: u16) -> usize {
+        if columns >= 32 * 4 + 14 {
+            return 32;
+        } else if columns >= 16 * 4 + 14 {
+            return 16;
+        } else {
+            return 8;
+        }
+    }
+
     /// Causes the next frame to be redrawn completely from scratch
     fn force_redraw_next_frame(&mut self) -> std::io::Result<()> {
         return self.terminal.resize(self.terminal.size()?);
@@ -365,3 +694,95 @@ impl UI {
         ignore_result!(self.terminal.hide_cursor());
     }
 }
+
+/// Renders the help overlay in the centre of the frame, on top of the current screen.
+fn render_help_overlay(
+    f: &mut Frame<TerminalBackend>,
+    overlay: &HelpOverlay,
+    default_style: Style,
+    highlight_style: Style,
+) {
+    let size = f.size();
+    let width = size.width * 4 / 5;
+    let height = size.height * 4 / 5;
+    let area = Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    );
+
+    let rects = Layout::default()
+        .constraints([Constraint::Length(3), Constraint::Min(3)])
+        .direction(Direction::Vertical)
+        .split(area);
+
+    let search_block = Paragraph::new(Text::raw(overlay.search())).block(
+        Block::default()
+            .title("Search (esc - close)")
+            .borders(Borders::ALL)
+            .style(default_style),
+    );
+
+    let mut state = TableState::default();
+    state.select(Some(overlay.selected_index()));
+
+    let rows = overlay.filtered().into_iter().map(|e| {
+        Row::Data(
+            vec![
+                e.screen.to_string(),
+                e.keys.clone(),
+                e.description.to_string(),
+            ]
+            .into_iter(),
+        )
+    });
+
+    let widths = [
+        Constraint::Length(18),
+        Constraint::Length(12),
+        Constraint::Min(10),
+    ];
+
+    let table = Table::new(["Screen", "Keys", "Action"].iter(), rows)
+        .column_spacing(2)
+        .block(Block::default().title("Help").borders(Borders::ALL))
+        .style(default_style)
+        .highlight_style(highlight_style)
+        .widths(&widths);
+
+    f.render_widget(Clear, area);
+    f.render_widget(search_block, rects[0]);
+    f.render_stateful_widget(table, rects[1], &mut state);
+}
+
+/// Summarises the indirect chains of the files and tags, and how many blocks compacting them would free.
+fn chain_stats_string(chain_stats: Option<&ChainStats>) -> String {
+    let stats = match chain_stats {
+        Some(s) => s,
+        None => return "Indirect blocks: could not be read".to_string(),
+    };
+
+    return format!(
+        "Indirect blocks: {} ({}% full)\nCompaction would free: {} blocks from {} files and tags",
+        stats.indirect_blocks(),
+        stats.fill_percent(),
+        stats.reclaimable_blocks(),
+        stats.compactable().count()
+    );
+}
+
+fn name_length_string(max_name_length: Option<usize>) -> String {
+    return match max_name_length {
+        Some(length) => format!("{} characters", length),
+        None => "What each record can store".to_string(),
+    };
+}
+
+fn exhaustion_string(exhaustion: Option<Exhaustion>) -> &'static str {
+    return match exhaustion {
+        Some(Exhaustion::INodes) => "File slots, before the data blocks",
+        Some(Exhaustion::DataBlocks) => "Data blocks",
+        None => "Unknown until files are added",
+    };
+}
@@ -0,0 +1,243 @@
+use crate::{
+    error_json, print_open_report, Handler, MKImageError, Manager, RetryPolicy, RetryStats,
+    RetryingHandler,
+};
+use std::fmt::Display;
+use std::io::Write;
+use std::process::exit;
+use voxfs::{probe, Disk, DiskHandler, VoxFSError};
+
+/// The exit codes shared by the tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// The tool completed, or the user declined to continue.
+    Success = 0,
+    /// The filesystem reported an error.
+    Failure = 1,
+    /// The arguments were missing or invalid.
+    Usage = 2,
+    /// The image could not be opened or does not contain a voxfs filesystem.
+    NoImage = 3,
+    /// A named file or tag does not exist in the image.
+    NotFound = 4,
+    /// A file outside of the image could not be read or written.
+    Io = 5,
+}
+
+impl ExitCode {
+    pub fn exit(self) -> ! {
+        exit(self as i32);
+    }
+}
+
+/// Prints the message to stderr and exits with the code.
+pub fn fail<T: Display>(message: T, code: ExitCode) -> ! {
+    eprintln!("{}", message);
+    code.exit();
+}
+
+/// Describes a filesystem error followed by its code, e.g. "FileBusy [E0047]", so the failure can be looked up
+/// whatever the wording.
+pub fn describe_error<E: Display>(error: &VoxFSError<E>) -> String {
+    return format!("{} [{}]", error, error.code());
+}
+
+/// Exits with the code after printing the context and a filesystem error with its code to stderr. A tool printing
+/// JSON also prints the error to stdout as `{"error":{"code":...,"message":...}}`, so scripts reading it see why.
+pub fn fail_error<E: Display>(
+    context: &str,
+    error: &VoxFSError<E>,
+    json: bool,
+    code: ExitCode,
+) -> ! {
+    if json {
+        println!("{{\"error\":{}}}", error_json(error));
+    }
+
+    fail(format!("{}: {}", context, describe_error(error)), code);
+}
+
+/// How the filesystem in an image is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Fail if any tag or file can not be read.
+    Strict,
+    /// Skip unreadable tags and files, listing them on stderr.
+    Tolerant,
+}
+
+/// An image that should contain a voxfs filesystem. Accesses failing with a transient error are retried with the
+/// default `RetryPolicy`.
+pub struct Image {
+    path: String,
+    handler: RetryingHandler<Handler>,
+    manager: Manager,
+    mode: OpenMode,
+    dump: bool,
+}
+
+/// An environment variable naming a file to log every write made to an image opened with `open_image`.
+pub const WRITE_LOG_VARIABLE: &str = "VOXFS_WRITE_LOG";
+/// An environment variable that, when set, refuses every write to an image opened with `open_image`. Used to check
+/// that a tool which should only read never writes.
+pub const WRITE_PROTECT_VARIABLE: &str = "VOXFS_WRITE_PROTECT";
+
+/// Opens the image at the path, exiting if it can not be opened.
+pub fn open_image(path: &str, mode: OpenMode) -> Image {
+    let mut handler = match Handler::new(path.to_string()) {
+        Ok(h) => h,
+        Err(e) => fail(e, ExitCode::NoImage),
+    };
+
+    if let Some(log) = std::env::var_os(WRITE_LOG_VARIABLE) {
+        match handler.log_writes(&log.to_string_lossy()) {
+            Ok(_) => (),
+            Err(e) => fail(e, ExitCode::Io),
+        }
+    }
+
+    if std::env::var_os(WRITE_PROTECT_VARIABLE).is_some() {
+        handler.set_writable_regions(Some(Vec::new()));
+    }
+
+    return Image {
+        path: path.to_string(),
+        handler: RetryingHandler::new(handler, RetryPolicy::default()),
+        manager: Manager::new(),
+        mode,
+        dump: false,
+    };
+}
+
+impl Image {
+    /// Selects a volume from the image's volume table, exiting if there is no such volume.
+    pub fn select_volume(&mut self, name: &str) {
+        match self.handler.inner_mut().select_volume(name) {
+            Ok(_) => (),
+            Err(e) => fail(e, ExitCode::NoImage),
+        }
+    }
+
+    /// Whether to print `Disk::debug_dump` to stderr once the filesystem is opened.
+    pub fn set_dump(&mut self, dump: bool) {
+        self.dump = dump;
+    }
+
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.handler.set_policy(policy);
+    }
+
+    /// The retries and failures of the accesses made so far.
+    pub fn retry_stats(&self) -> RetryStats {
+        return self.handler.stats();
+    }
+
+    /// The size in bytes of the image, or of the selected volume.
+    pub fn size(&self) -> u64 {
+        return match self.handler.disk_size() {
+            Ok(s) => s,
+            Err(e) => fail(e, ExitCode::Io),
+        };
+    }
+
+    /// Opens the filesystem, exiting if there isn't one and warning if it was not closed cleanly.
+    pub fn disk(&mut self) -> Disk<'_, '_, MKImageError> {
+        if probe(&self.handler).is_none() {
+            fail(
+                format!("{} does not contain a voxfs filesystem.", self.path),
+                ExitCode::NoImage,
+            );
+        }
+
+        let opened = match self.mode {
+            OpenMode::Strict => Disk::open_disk(&mut self.handler, &mut self.manager),
+            OpenMode::Tolerant => Disk::open_disk_tolerant(&mut self.handler, &mut self.manager)
+                .map(|(disk, report)| {
+                    print_open_report(&report);
+                    disk
+                }),
+        };
+
+        let disk = match opened {
+            Ok(d) => d,
+            Err(e) => fail(
+                format!("Disk opening error: {}", describe_error(&e)),
+                ExitCode::NoImage,
+            ),
+        };
+
+        if disk.opened_dirty() {
+            eprintln!("Warning: the filesystem was not closed cleanly and may be inconsistent.");
+        }
+
+        if disk.needs_migration() {
+            eprintln!("Warning: the filesystem uses an older format and can't be modified until it is converted with `voxfs migrate`.");
+        }
+
+        if self.dump {
+            let mut dump = String::new();
+
+            if disk.debug_dump(&mut dump).is_ok() {
+                eprint!("{}", dump);
+            }
+        }
+
+        return disk;
+    }
+}
+
+/// Asks a yes or no question, anything other than y is taken as no.
+pub fn confirm(prompt: &str, assume_yes: bool) -> bool {
+    if assume_yes {
+        return true;
+    }
+
+    print!("{} (y/N) ", prompt);
+
+    let _ = std::io::stdout().flush();
+
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(_) => (),
+        Err(_) => fail("Failed to read response.", ExitCode::Io),
+    }
+
+    return is_yes(&input);
+}
+
+/// Shows a percentage on stderr, rewriting the line each time. Used as an `OpContext` progress callback.
+pub fn print_progress(done: u64, total: u64) {
+    let percent = match total {
+        0 => 100,
+        _ => done * 100 / total,
+    };
+
+    eprint!("\r{:>3}% ({}/{})", percent, done, total);
+
+    if done >= total {
+        eprintln!();
+    }
+
+    let _ = std::io::stderr().flush();
+}
+
+fn is_yes(response: &str) -> bool {
+    let response = response.trim_end_matches(&['\r', '\n'][..]);
+
+    return response == "y" || response == "Y";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_yes;
+
+    #[test]
+    fn test_is_yes() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes("Y\r\n"));
+        assert!(is_yes("y"));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("yes\n"));
+        assert!(!is_yes("n\n"));
+    }
+}
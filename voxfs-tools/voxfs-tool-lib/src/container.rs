@@ -0,0 +1,380 @@
+// Packed image (.voxz) layout:
+// header (CONTAINER_HEADER_SIZE), index with a bit for each block of the image, the stored blocks in order ...
+// Only the blocks set in the index are stored, the rest of the image is zeroes when it is unpacked, so the file
+// is a fraction of the image's size without the host supporting sparse files.
+
+use crate::{Crc32, MKImageError};
+use std::convert::TryInto;
+use std::io::{Read, Seek, SeekFrom, Write};
+use voxfs::DiskHandler;
+
+const CONTAINER_MAGIC: [u8; 8] = *b"VOXZIMG\0";
+const CONTAINER_VERSION: u8 = 1;
+/// The size in bytes of the header at the start of a packed image.
+const CONTAINER_HEADER_SIZE: usize = 64;
+const CHECKSUM_START: usize = CONTAINER_HEADER_SIZE - 4;
+/// The extension given to packed images.
+pub const CONTAINER_EXTENSION: &str = "voxz";
+
+/// The header of a packed image, describing the image it was made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ContainerHeader {
+    disk_size: u64,
+    block_size: u64,
+    stored_blocks: u64,
+    /// The checksum of the stored blocks.
+    data_checksum: u32,
+}
+
+impl ContainerHeader {
+    fn to_bytes(&self) -> [u8; CONTAINER_HEADER_SIZE] {
+        let mut bytes = [0u8; CONTAINER_HEADER_SIZE];
+
+        bytes[..8].copy_from_slice(&CONTAINER_MAGIC);
+        bytes[8] = CONTAINER_VERSION;
+        bytes[16..24].copy_from_slice(&self.disk_size.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.block_size.to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.stored_blocks.to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.data_checksum.to_le_bytes());
+
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..CHECKSUM_START]);
+        bytes[CHECKSUM_START..].copy_from_slice(&crc.finish().to_le_bytes());
+
+        return bytes;
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, MKImageError> {
+        if !is_container(bytes) || bytes.len() < CONTAINER_HEADER_SIZE {
+            return Err(invalid("the header is missing"));
+        }
+
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..CHECKSUM_START]);
+
+        if crc.finish().to_le_bytes() != bytes[CHECKSUM_START..CONTAINER_HEADER_SIZE] {
+            return Err(invalid("the header's checksum does not match"));
+        }
+
+        if bytes[8] != CONTAINER_VERSION {
+            return Err(invalid(&format!("version {} is not supported", bytes[8])));
+        }
+
+        let header = Self {
+            disk_size: u64::from_le_bytes(bytes[16..24].try_into().unwrap()),
+            block_size: u64::from_le_bytes(bytes[24..32].try_into().unwrap()),
+            stored_blocks: u64::from_le_bytes(bytes[32..40].try_into().unwrap()),
+            data_checksum: u32::from_le_bytes(bytes[40..44].try_into().unwrap()),
+        };
+
+        if header.block_size == 0 || header.stored_blocks > header.block_count() {
+            return Err(invalid("the header is corrupted"));
+        }
+
+        return Ok(header);
+    }
+
+    /// The number of blocks in the image, the last may be shorter than the block size.
+    fn block_count(&self) -> u64 {
+        return self.disk_size.div_ceil(self.block_size);
+    }
+
+    fn index_size(&self) -> usize {
+        return self.block_count().div_ceil(8) as usize;
+    }
+
+    /// The size of a block of the image, only the last can be short.
+    fn block_length(&self, block: u64) -> u64 {
+        return core::cmp::min(self.block_size, self.disk_size - block * self.block_size);
+    }
+}
+
+/// What was stored when an image was packed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackSummary {
+    pub disk_size: u64,
+    pub total_blocks: u64,
+    pub stored_blocks: u64,
+    /// The size in bytes of the packed image.
+    pub packed_size: u64,
+}
+
+/// Whether the bytes start with the magic of a packed image.
+pub fn is_container(bytes: &[u8]) -> bool {
+    return bytes.len() >= CONTAINER_MAGIC.len()
+        && bytes[..CONTAINER_MAGIC.len()] == CONTAINER_MAGIC;
+}
+
+/// Writes a packed copy of the image to the writer. Blocks are only stored if `keep` returns true for their
+/// index and they are not all zeroes, so unallocated blocks can be left out by checking the filesystem's bitmap.
+pub fn pack<W: Write + Seek>(
+    handler: &dyn DiskHandler<MKImageError>,
+    block_size: u64,
+    keep: &dyn Fn(u64) -> bool,
+    writer: &mut W,
+) -> Result<PackSummary, MKImageError> {
+    if block_size == 0 {
+        return Err(invalid("the block size must not be 0"));
+    }
+
+    let mut header = ContainerHeader {
+        disk_size: handler.disk_size()?,
+        block_size,
+        stored_blocks: 0,
+        data_checksum: 0,
+    };
+
+    let mut index = vec![0u8; header.index_size()];
+
+    // The header and index are written again once the stored blocks are known
+    let start = tell(writer)?;
+    write(writer, &header.to_bytes())?;
+    write(writer, &index)?;
+
+    let mut crc = Crc32::new();
+
+    for block in 0..header.block_count() {
+        if !keep(block) {
+            continue;
+        }
+
+        let bytes = handler.read_bytes(block * block_size, header.block_length(block))?;
+
+        if bytes.iter().all(|b| *b == 0) {
+            continue;
+        }
+
+        index[block as usize / 8] |= 1 << (block % 8);
+        header.stored_blocks += 1;
+
+        crc.update(&bytes);
+        write(writer, &bytes)?;
+    }
+
+    header.data_checksum = crc.finish();
+
+    let end = tell(writer)?;
+    seek(writer, start)?;
+    write(writer, &header.to_bytes())?;
+    write(writer, &index)?;
+    seek(writer, end)?;
+
+    return Ok(PackSummary {
+        disk_size: header.disk_size,
+        total_blocks: header.block_count(),
+        stored_blocks: header.stored_blocks,
+        packed_size: end - start,
+    });
+}
+
+/// Reads a packed image, its header and index are read when it is opened and the blocks when it is unpacked.
+pub struct ContainerReader<R: Read> {
+    reader: R,
+    header: ContainerHeader,
+    index: Vec<u8>,
+}
+
+impl<R: Read> ContainerReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, MKImageError> {
+        let mut bytes = [0u8; CONTAINER_HEADER_SIZE];
+        read(&mut reader, &mut bytes)?;
+
+        let header = ContainerHeader::from_bytes(&bytes)?;
+
+        let mut index = vec![0u8; header.index_size()];
+        read(&mut reader, &mut index)?;
+
+        let set: u64 = index.iter().map(|b| b.count_ones() as u64).sum();
+
+        if set != header.stored_blocks {
+            return Err(invalid("the index does not match the header"));
+        }
+
+        return Ok(Self {
+            reader,
+            header,
+            index,
+        });
+    }
+
+    /// The size in bytes of the unpacked image.
+    pub fn disk_size(&self) -> u64 {
+        return self.header.disk_size;
+    }
+
+    pub fn stored_blocks(&self) -> u64 {
+        return self.header.stored_blocks;
+    }
+
+    /// Writes the stored blocks to the handler, which should be at least `disk_size` bytes and zeroed.
+    /// Returns the number of blocks written.
+    pub fn unpack(
+        mut self,
+        handler: &mut dyn DiskHandler<MKImageError>,
+    ) -> Result<u64, MKImageError> {
+        if handler.disk_size()? < self.header.disk_size {
+            return Err(invalid("the image to unpack into is too small"));
+        }
+
+        let mut crc = Crc32::new();
+        let mut written = 0;
+
+        for block in 0..self.header.block_count() {
+            if self.index[block as usize / 8] & (1 << (block % 8)) == 0 {
+                continue;
+            }
+
+            let mut bytes = vec![0u8; self.header.block_length(block) as usize];
+            read(&mut self.reader, &mut bytes)?;
+
+            crc.update(&bytes);
+            handler.write_bytes(&bytes, block * self.header.block_size)?;
+            written += 1;
+        }
+
+        if crc.finish() != self.header.data_checksum {
+            return Err(invalid("the stored blocks' checksum does not match"));
+        }
+
+        handler.flush()?;
+
+        return Ok(written);
+    }
+}
+
+fn read<R: Read>(reader: &mut R, bytes: &mut [u8]) -> Result<(), MKImageError> {
+    return reader.read_exact(bytes).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => invalid("the file ends early"),
+        _ => MKImageError::io("read the packed image", e),
+    });
+}
+
+fn write<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), MKImageError> {
+    return writer
+        .write_all(bytes)
+        .map_err(|e| MKImageError::io("write the packed image", e));
+}
+
+fn seek<W: Seek>(writer: &mut W, position: u64) -> Result<(), MKImageError> {
+    return writer
+        .seek(SeekFrom::Start(position))
+        .map(|_| ())
+        .map_err(|e| MKImageError::io("seek in the packed image", e));
+}
+
+fn tell<W: Seek>(writer: &mut W) -> Result<u64, MKImageError> {
+    return writer
+        .stream_position()
+        .map_err(|e| MKImageError::io("seek in the packed image", e));
+}
+
+fn invalid(reason: &str) -> MKImageError {
+    return MKImageError::InvalidContainer(reason.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use voxfs::{MemoryDiskError, MemoryDiskHandler};
+
+    /// Lets a memory disk stand in for an image in the tests.
+    struct Image(MemoryDiskHandler);
+
+    impl DiskHandler<MKImageError> for Image {
+        fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MKImageError> {
+            return self.0.write_bytes(bytes, location).map_err(convert);
+        }
+
+        fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+            return self.0.read_bytes(location, amount).map_err(convert);
+        }
+
+        fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MKImageError> {
+            return self.0.zero_range(start, end).map_err(convert);
+        }
+
+        fn disk_size(&self) -> Result<u64, MKImageError> {
+            return self.0.disk_size().map_err(convert);
+        }
+    }
+
+    fn convert(_: MemoryDiskError) -> MKImageError {
+        return MKImageError::OutOfBounds {
+            location: 0,
+            amount: 0,
+        };
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Ten and a half blocks with data in blocks 0, 3 and the short last block
+        let size = 512 * 10 + 256;
+        let mut image = Image(MemoryDiskHandler::new(size));
+        image.write_bytes(&vec![1; 512], 0).unwrap();
+        image.write_bytes(&vec![2; 10], 512 * 3 + 7).unwrap();
+        image.write_bytes(&vec![3; 10], 512 * 6).unwrap();
+        image.write_bytes(&vec![4; 256], 512 * 10).unwrap();
+
+        // Block 6 is left out as if it were unallocated
+        let mut packed = Cursor::new(Vec::new());
+        let summary = pack(&image, 512, &|block| block != 6, &mut packed).unwrap();
+        assert_eq!(summary.total_blocks, 11);
+        assert_eq!(summary.stored_blocks, 3);
+        assert_eq!(summary.packed_size, 64 + 2 + 512 * 2 + 256);
+        assert!(is_container(packed.get_ref()));
+
+        let reader = ContainerReader::new(Cursor::new(packed.get_ref().clone())).unwrap();
+        assert_eq!(reader.disk_size(), size as u64);
+
+        let mut unpacked = Image(MemoryDiskHandler::new(size));
+        assert_eq!(reader.unpack(&mut unpacked).unwrap(), 3);
+
+        image.write_bytes(&vec![0; 10], 512 * 6).unwrap();
+        assert_eq!(
+            unpacked.read_bytes(0, size as u64).unwrap(),
+            image.read_bytes(0, size as u64).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_corruption() {
+        let mut image = Image(MemoryDiskHandler::new(4096));
+        image.write_bytes(&vec![1; 100], 1000).unwrap();
+
+        let mut packed = Cursor::new(Vec::new());
+        pack(&image, 512, &|_| true, &mut packed).unwrap();
+        let packed = packed.into_inner();
+
+        let mut header = packed.clone();
+        header[20] ^= 0xff;
+        assert!(matches!(
+            ContainerReader::new(Cursor::new(header)).err(),
+            Some(MKImageError::InvalidContainer(_))
+        ));
+
+        let mut data = packed.clone();
+        *data.last_mut().unwrap() ^= 0xff;
+        let reader = ContainerReader::new(Cursor::new(data)).unwrap();
+        assert!(matches!(
+            reader
+                .unpack(&mut Image(MemoryDiskHandler::new(4096)))
+                .err(),
+            Some(MKImageError::InvalidContainer(_))
+        ));
+
+        let truncated = packed[..packed.len() - 1].to_vec();
+        let reader = ContainerReader::new(Cursor::new(truncated)).unwrap();
+        assert!(matches!(
+            reader
+                .unpack(&mut Image(MemoryDiskHandler::new(4096)))
+                .err(),
+            Some(MKImageError::InvalidContainer(_))
+        ));
+
+        let reader = ContainerReader::new(Cursor::new(packed)).unwrap();
+        assert!(reader
+            .unpack(&mut Image(MemoryDiskHandler::new(1024)))
+            .is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::MKImageError;
+use std::collections::BTreeMap;
+use voxfs::{
+    Disk, FileType, FormatOptions, INode, NamePolicy, OpContext, SortOrder, TagBlock, VoxFSError,
+};
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The options to format a disk with so it matches the source, apart from its size.
+/// The block size can be replaced afterwards with `with_block_size` to change the geometry.
+/// Duplicate names are allowed so every file can be copied, `copy_filesystem` restores the source's policy after.
+pub fn format_options_like(source: &Disk<MKImageError>) -> FormatOptions {
+    return FormatOptions::new()
+        .with_block_size(source.block_size())
+        .with_boot_area_size(source.boot_area_size())
+        .with_metadata_mirror(source.has_metadata_mirror())
+        .with_history_size(source.history_size())
+        .with_content_hashes(source.has_content_hashes())
+        .with_inodes_per_tag(inodes_per_tag(source))
+        .with_uuid(source.uuid())
+        .with_label(&source.label())
+        .with_name_policy(NamePolicy::Allow)
+        .with_max_name_length(source.max_name_length());
+}
+
+/// The ratio of inodes to tags the disk was formatted with, rounded down.
+pub fn inodes_per_tag(disk: &Disk<MKImageError>) -> u64 {
+    let inodes = (disk.number_of_files() + disk.free_file_slots()) as u64;
+    let tags = (disk.number_of_tags() + disk.free_tag_slots()) as u64;
+
+    return std::cmp::max(inodes / std::cmp::max(tags, 1), 1);
+}
+
+/// Copies the boot area, files and tags of one disk into a newly formatted disk, keeping names, flags,
+/// timestamps and tag membership. The files are read and written in pieces so they are never fully in memory.
+/// Progress is reported after each file and tag is copied.
+pub fn copy_filesystem(
+    source: &Disk<MKImageError>,
+    destination: &mut Disk<MKImageError>,
+    context: &OpContext,
+) -> Result<(), VoxFSError<MKImageError>> {
+    if source.boot_area_size() > 0 {
+        destination.write_boot_area(&source.read_boot_area()?)?;
+    }
+
+    let total = (source.number_of_files() + source.number_of_tags()) as u64;
+    let mut done = 0;
+
+    // The index of each file in the source mapped to its index in the destination
+    let mut indices = BTreeMap::new();
+
+    for inode in source.list_inodes_sorted(SortOrder::Index) {
+        let copy = copy_file(source, destination, &inode)?;
+        indices.insert(inode.index(), copy.index());
+
+        done += 1;
+        context.step(done, total)?;
+    }
+
+    for tag in source.list_tags() {
+        let copy_index = copy_tag(source, destination, &tag, &indices)?;
+        destination.set_tag_creation_time(copy_index, tag.creation_time())?;
+
+        done += 1;
+        context.step(done, total)?;
+    }
+
+    destination.set_name_policy(source.name_policy())?;
+
+    return Ok(());
+}
+
+/// Copies files from one disk to another, keeping their flags and timestamps, and applies the tags they have in the
+/// source to the copies, creating any tags the destination doesn't have. Files are named as they are in the source
+/// so the destination's name policy decides what happens to names it already has.
+/// Progress is reported after each file is copied. Returns the copies in the order of the indices given.
+pub fn copy_files(
+    source: &Disk<MKImageError>,
+    destination: &mut Disk<MKImageError>,
+    inode_indices: &[u64],
+    context: &OpContext,
+) -> Result<Vec<INode>, VoxFSError<MKImageError>> {
+    let inodes = source.list_inodes();
+    let mut indices = BTreeMap::new();
+    let mut copies = Vec::new();
+
+    for (i, index) in inode_indices.iter().enumerate() {
+        let inode = match inodes.iter().find(|n| n.index() == *index) {
+            Some(n) => n,
+            None => return Err(VoxFSError::CouldNotFindINode),
+        };
+
+        let copy = copy_file(source, destination, inode)?;
+        indices.insert(inode.index(), copy.index());
+        copies.push(copy);
+
+        context.step(i as u64 + 1, inode_indices.len() as u64)?;
+    }
+
+    // Only the tags applied to a copied file are needed
+    for tag in source.list_tags() {
+        let members = source.list_nodes_with_tag(tag.index())?;
+
+        if members.iter().any(|m| indices.contains_key(&m.index())) {
+            copy_tag(source, destination, &tag, &indices)?;
+        }
+    }
+
+    return Ok(copies);
+}
+
+/// Writes a copy of a file to the destination, reading its contents in pieces.
+fn copy_file(
+    source: &Disk<MKImageError>,
+    destination: &mut Disk<MKImageError>,
+    inode: &INode,
+) -> Result<INode, VoxFSError<MKImageError>> {
+    let file_type = inode.flags().file_type();
+
+    // Protection flags are applied once the contents have been written
+    let writable_flags = inode.flags().with_append_only(false).with_immutable(false);
+
+    let copy = if file_type.has_contents() {
+        let mut handle = source.open_file(inode.index())?;
+        let first = source.read_file_range(&mut handle, 0, CHUNK_SIZE)?;
+        let mut offset = first.len() as u64;
+        let copy = destination.create_new_file(&inode.name(), writable_flags, first)?;
+
+        loop {
+            let bytes = source.read_file_range(&mut handle, offset, CHUNK_SIZE)?;
+
+            if bytes.is_empty() {
+                break;
+            }
+
+            offset += bytes.len() as u64;
+            destination.append_file_bytes(copy.index(), &bytes)?;
+        }
+
+        source.close_file(handle);
+
+        copy
+    } else {
+        let rdev = match file_type {
+            FileType::Device => inode.rdev().unwrap_or(0),
+            _ => 0,
+        };
+
+        destination.create_special(&inode.name(), file_type, rdev)?
+    };
+
+    destination.set_file_times(
+        copy.index(),
+        inode.creation_time(),
+        inode.modified_time(),
+        inode.access_time(),
+    )?;
+    destination.set_file_flags(copy.index(), inode.flags())?;
+
+    return Ok(copy);
+}
+
+/// Applies a tag to the copies of its members, given by the map from each file's index in the source to its index
+/// in the destination. The tag is created with the source's flags, creation time and appearance if the destination
+/// has no tag with its name. Returns the index of the tag in the destination.
+fn copy_tag(
+    source: &Disk<MKImageError>,
+    destination: &mut Disk<MKImageError>,
+    tag: &TagBlock,
+    indices: &BTreeMap<u64, u64>,
+) -> Result<u64, VoxFSError<MKImageError>> {
+    let name = tag.name_string();
+
+    // A freshly formatted disk already has the root tag
+    let copy_index = match destination.tag_with_name(&name) {
+        Some(i) => i,
+        None => {
+            let index = destination.create_new_tag(&name, tag.flags())?.index();
+            destination.set_tag_creation_time(index, tag.creation_time())?;
+
+            if tag.color().is_some() || tag.icon().is_some() {
+                destination.set_tag_color(index, tag.color())?;
+                destination.set_tag_icon(index, tag.icon())?;
+            }
+
+            index
+        }
+    };
+
+    for member in source.list_nodes_with_tag(tag.index())? {
+        if let Some(index) = indices.get(&member.index()) {
+            destination.apply_tag(copy_index, *index)?;
+        }
+    }
+
+    return Ok(copy_index);
+}
+
+/// True if the error means the destination was too small for the copy, or to be formatted at all.
+pub fn is_out_of_space(error: &VoxFSError<MKImageError>) -> bool {
+    return matches!(
+        error,
+        VoxFSError::NotEnoughFreeDataBlocks
+            | VoxFSError::NoFreeInode
+            | VoxFSError::NoFreeTag
+            | VoxFSError::InvalidBootAreaSize
+            | VoxFSError::DiskError(MKImageError::OutOfBounds { .. })
+    );
+}
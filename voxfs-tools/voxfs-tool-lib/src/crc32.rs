@@ -0,0 +1,58 @@
+/// A CRC-32 (IEEE) checksum that can be computed over data read in pieces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32 {
+    value: u32,
+}
+
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+impl Crc32 {
+    pub fn new() -> Self {
+        return Self { value: 0xFFFF_FFFF };
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.value ^= *byte as u32;
+
+            for _ in 0..8 {
+                let mask = (self.value & 1).wrapping_neg();
+                self.value = (self.value >> 1) ^ (POLYNOMIAL & mask);
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        return !self.value;
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Crc32;
+
+    #[test]
+    fn test_check_value() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_pieces() {
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"");
+        crc.update(b"56789");
+
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+        assert_eq!(Crc32::new().finish(), 0);
+    }
+}
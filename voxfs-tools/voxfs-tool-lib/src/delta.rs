@@ -0,0 +1,148 @@
+// Delta image layout:
+// header (DELTA_HEADER_SIZE), bitmap of the blocks held by the delta (a whole number of headers), blocks ...
+// Block n of the disk is stored at the same offset from the start of the blocks, so the file is only as large
+// on the host as the blocks that have been written when the host supports sparse files.
+
+use crate::{Crc32, MKImageError};
+use std::convert::TryInto;
+
+const DELTA_MAGIC: [u8; 8] = *b"VOXDELTA";
+const DELTA_VERSION: u8 = 1;
+/// The size in bytes of the header at the start of a delta image.
+pub(crate) const DELTA_HEADER_SIZE: u64 = 4_096;
+/// The size in bytes of the blocks copied from the parent when they are first written.
+pub(crate) const DELTA_BLOCK_SIZE: u64 = 4_096;
+const PATH_START: usize = 34;
+const CHECKSUM_START: usize = DELTA_HEADER_SIZE as usize - 4;
+/// The longest parent path in bytes that fits in the header.
+pub const MAX_PARENT_PATH_LENGTH: usize = CHECKSUM_START - PATH_START;
+
+/// The header of a delta image, naming the image it records the changes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DeltaHeader {
+    pub disk_size: u64,
+    pub block_size: u64,
+    pub parent: String,
+}
+
+impl DeltaHeader {
+    pub fn new(disk_size: u64, parent: &str) -> Result<Self, MKImageError> {
+        if parent.len() > MAX_PARENT_PATH_LENGTH {
+            return Err(MKImageError::InvalidDelta(format!(
+                "the parent's path is longer than {} bytes",
+                MAX_PARENT_PATH_LENGTH
+            )));
+        }
+
+        return Ok(Self {
+            disk_size,
+            block_size: DELTA_BLOCK_SIZE,
+            parent: parent.to_string(),
+        });
+    }
+
+    /// Whether the bytes start with the magic of a delta header.
+    pub fn is_delta(bytes: &[u8]) -> bool {
+        return bytes.len() >= DELTA_MAGIC.len() && bytes[..DELTA_MAGIC.len()] == DELTA_MAGIC;
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; DELTA_HEADER_SIZE as usize];
+
+        bytes[..8].copy_from_slice(&DELTA_MAGIC);
+        bytes[8] = DELTA_VERSION;
+        bytes[16..24].copy_from_slice(&self.disk_size.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.block_size.to_le_bytes());
+        bytes[32..34].copy_from_slice(&(self.parent.len() as u16).to_le_bytes());
+        bytes[PATH_START..PATH_START + self.parent.len()].copy_from_slice(self.parent.as_bytes());
+
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..CHECKSUM_START]);
+        bytes[CHECKSUM_START..].copy_from_slice(&crc.finish().to_le_bytes());
+
+        return bytes;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MKImageError> {
+        if bytes.len() < DELTA_HEADER_SIZE as usize || !Self::is_delta(bytes) {
+            return Err(invalid("the header is missing"));
+        }
+
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..CHECKSUM_START]);
+
+        if crc.finish().to_le_bytes() != bytes[CHECKSUM_START..DELTA_HEADER_SIZE as usize] {
+            return Err(invalid("the header's checksum does not match"));
+        }
+
+        if bytes[8] != DELTA_VERSION {
+            return Err(invalid(&format!("version {} is not supported", bytes[8])));
+        }
+
+        let disk_size = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        let block_size = u64::from_le_bytes(bytes[24..32].try_into().unwrap());
+        let length = u16::from_le_bytes(bytes[32..34].try_into().unwrap()) as usize;
+
+        if block_size == 0 || length > MAX_PARENT_PATH_LENGTH {
+            return Err(invalid("the header is corrupted"));
+        }
+
+        let parent = match std::str::from_utf8(&bytes[PATH_START..PATH_START + length]) {
+            Ok(p) => p.to_string(),
+            Err(_) => return Err(invalid("the parent's path is not valid UTF-8")),
+        };
+
+        return Ok(Self {
+            disk_size,
+            block_size,
+            parent,
+        });
+    }
+
+    /// The number of blocks in the disk, the last may be shorter than the block size.
+    pub fn block_count(&self) -> u64 {
+        return self.disk_size.div_ceil(self.block_size);
+    }
+
+    /// The size in bytes of the bitmap, rounded up to a whole number of headers.
+    pub fn bitmap_size(&self) -> u64 {
+        let bytes = self.block_count().div_ceil(8);
+
+        return bytes.div_ceil(DELTA_HEADER_SIZE) * DELTA_HEADER_SIZE;
+    }
+
+    /// The offset in the file of the first block.
+    pub fn data_offset(&self) -> u64 {
+        return DELTA_HEADER_SIZE + self.bitmap_size();
+    }
+}
+
+fn invalid(reason: &str) -> MKImageError {
+    return MKImageError::InvalidDelta(reason.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = DeltaHeader::new(4096 * 8 * 4096 + 1, "/images/base.img").unwrap();
+        let bytes = header.to_bytes();
+
+        assert!(DeltaHeader::is_delta(&bytes));
+        assert_eq!(DeltaHeader::from_bytes(&bytes).unwrap(), header);
+        assert_eq!(header.block_count(), 4096 * 8 + 1);
+        assert_eq!(header.bitmap_size(), 8192);
+        assert_eq!(header.data_offset(), 4096 * 3);
+
+        let mut corrupted = bytes.clone();
+        corrupted[40] ^= 0xff;
+        assert!(matches!(
+            DeltaHeader::from_bytes(&corrupted),
+            Err(MKImageError::InvalidDelta(_))
+        ));
+
+        assert!(DeltaHeader::new(0, &"a".repeat(MAX_PARENT_PATH_LENGTH + 1)).is_err());
+    }
+}
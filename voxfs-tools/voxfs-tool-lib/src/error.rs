@@ -1,20 +1,56 @@
 use std::fmt::Formatter;
+use std::io;
 use voxfs::VoxFSErrorConvertible;
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct MKImageError {
-    message: String,
+/// The errors produced when creating, opening or accessing an image.
+#[derive(Debug)]
+pub enum MKImageError {
+    /// The image does not exist.
+    NotFound { path: String, source: io::Error },
+    /// The image can not be opened for reading and writing.
+    PermissionDenied { path: String, source: io::Error },
+    /// Any other I/O failure, with a description of what was being done.
+    Io {
+        operation: String,
+        source: io::Error,
+    },
+    /// The image ended before the requested bytes could be read, it may have been truncated.
+    ShortRead { location: u64, amount: u64 },
+    /// An access extended past the end of the image or volume.
+    OutOfBounds { location: u64, amount: u64 },
+    /// The image has no volume with the name.
+    NoSuchVolume(String),
+    /// The image's volume table could not be read.
+    InvalidVolumeTable(String),
+    /// The image is a delta that can't be used, or its parent can't.
+    InvalidDelta(String),
+    /// A packed image container is corrupted or in an unsupported version.
+    InvalidContainer(String),
+    /// A write fell outside the regions the handler was allowed to write to.
+    WriteProtected { location: u64, amount: u64 },
 }
 
 impl MKImageError {
-    pub fn new(message: &str) -> Self {
-        return MKImageError {
-            message: String::from(message),
+    /// Classifies an error from opening or creating the image at a path.
+    pub fn open(path: &str, source: io::Error) -> Self {
+        return match source.kind() {
+            io::ErrorKind::NotFound => MKImageError::NotFound {
+                path: path.to_string(),
+                source,
+            },
+            io::ErrorKind::PermissionDenied => MKImageError::PermissionDenied {
+                path: path.to_string(),
+                source,
+            },
+            _ => MKImageError::io(&format!("open {}", path), source),
         };
     }
 
-    pub fn get_message(&self) -> String {
-        return self.message.clone();
+    pub fn io(operation: &str, source: io::Error) -> Self {
+        return MKImageError::Io {
+            operation: operation.to_string(),
+            source,
+        };
     }
 }
 
@@ -22,6 +58,68 @@ impl VoxFSErrorConvertible for MKImageError {}
 
 impl std::fmt::Display for MKImageError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        return write!(f, "{:?}", self);
+        return match self {
+            MKImageError::NotFound { path, .. } => write!(f, "{} does not exist", path),
+            MKImageError::PermissionDenied { path, .. } => {
+                write!(f, "Permission denied opening {}", path)
+            }
+            MKImageError::Io { operation, source } => {
+                write!(f, "Failed to {}. Error: {}", operation, source)
+            }
+            MKImageError::ShortRead { location, amount } => write!(
+                f,
+                "The image ended before {} bytes could be read from {}",
+                amount, location
+            ),
+            MKImageError::OutOfBounds { location, amount } => write!(
+                f,
+                "Access of {} bytes at {} is past the end of the image",
+                amount, location
+            ),
+            MKImageError::NoSuchVolume(name) => write!(f, "The image has no volume named {}", name),
+            MKImageError::InvalidVolumeTable(e) => {
+                write!(f, "Failed to read the volume table. Error: {}", e)
+            }
+            MKImageError::InvalidDelta(reason) => write!(f, "Invalid delta image: {}", reason),
+            MKImageError::InvalidContainer(reason) => {
+                write!(f, "Invalid packed image: {}", reason)
+            }
+            MKImageError::WriteProtected { location, amount } => write!(
+                f,
+                "Refused to write {} bytes at {}, outside the writable regions",
+                amount, location
+            ),
+        };
+    }
+}
+
+impl std::error::Error for MKImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            MKImageError::NotFound { source, .. }
+            | MKImageError::PermissionDenied { source, .. }
+            | MKImageError::Io { source, .. } => Some(source),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_open_classification() {
+        let error = MKImageError::open("image", io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(error, MKImageError::NotFound { .. }));
+        assert_eq!(error.to_string(), "image does not exist");
+
+        let error = MKImageError::open("image", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(error, MKImageError::PermissionDenied { .. }));
+        assert!(error.source().is_some());
+
+        let error = MKImageError::open("image", io::Error::from(io::ErrorKind::Other));
+        assert!(matches!(error, MKImageError::Io { .. }));
     }
 }
@@ -1,171 +1,808 @@
+use crate::delta::{DeltaHeader, DELTA_BLOCK_SIZE, DELTA_HEADER_SIZE};
 use crate::error::MKImageError;
 use std::cell::RefCell;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use voxfs::volumes::VolumeTable;
 use voxfs::DiskHandler;
 
+/// How the space of a new image is allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allocation {
+    /// Write zeroes over the whole image, slow for large images but supported everywhere.
+    Zeroed,
+    /// Only set the file's length, the space is allocated as it is written so the disk may fill up later.
+    Sparse,
+    /// Reserve the space without writing it, falling back to writing zeroes where this isn't supported.
+    Preallocated,
+}
+
+/// The most images a chain of deltas may have, which stops a delta that is its own ancestor being opened forever.
+const MAX_DELTA_CHAIN: usize = 32;
+
 pub struct Handler {
     file: RefCell<File>,
+    // The region of the file used as the disk, set when a volume is selected
+    start: u64,
+    size: Option<u64>,
+    // Set when the image is a delta holding the changes to a parent image
+    delta: Option<Delta>,
+    audit: WriteAudit,
+}
+
+/// Where the writes made through a handler are logged and the regions they are allowed in.
+#[derive(Default)]
+struct WriteAudit {
+    log: Option<File>,
+    // None allows writes anywhere
+    writable: Option<Vec<Range<u64>>>,
+}
+
+impl WriteAudit {
+    fn allows(&self, location: u64, amount: u64) -> bool {
+        return match &self.writable {
+            Some(regions) => regions
+                .iter()
+                .any(|r| location >= r.start && location + amount <= r.end),
+            None => true,
+        };
+    }
+
+    /// Appends a line with the location and length of the write, followed by "refused" if it wasn't allowed.
+    fn record(&mut self, location: u64, amount: u64, allowed: bool) -> Result<(), MKImageError> {
+        let log = match &mut self.log {
+            Some(l) => l,
+            None => return Ok(()),
+        };
+
+        let line = match allowed {
+            true => format!("{} {}\n", location, amount),
+            false => format!("{} {} refused\n", location, amount),
+        };
+
+        return log
+            .write_all(line.as_bytes())
+            .map_err(|e| MKImageError::io("write to the write log", e));
+    }
+}
+
+/// The image a delta records the changes to and which of its blocks the delta holds.
+struct Delta {
+    header: DeltaHeader,
+    parent: Box<Handler>,
+    bitmap: Vec<u8>,
+}
+
+impl Delta {
+    fn holds(&self, block: u64) -> bool {
+        return self.bitmap[(block / 8) as usize] & (1 << (block % 8)) != 0;
+    }
 }
 
 impl Handler {
-    /// This will create a new file of the specified size. It will overwrite any existing file
+    /// This will create a new file of the specified size filled with zeroes. It will overwrite any existing file
     pub fn new_create(path: String, size: usize) -> Result<Self, MKImageError> {
+        return Self::new_create_with_allocation(path, size, Allocation::Zeroed);
+    }
+
+    /// Creates a new file of the specified size, allocating its space in the given way.
+    /// It will overwrite any existing file
+    pub fn new_create_with_allocation(
+        path: String,
+        size: usize,
+        allocation: Allocation,
+    ) -> Result<Self, MKImageError> {
         // Create the file.
         let mut file = match OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(path)
+            .truncate(true)
+            .open(&path)
         {
             Ok(f) => f,
-            Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to create. Error: {}",
-                    e
-                )))
-            }
+            Err(e) => return Err(MKImageError::open(&path, e)),
+        };
+
+        let zero_fill = match allocation {
+            Allocation::Zeroed => true,
+            Allocation::Sparse => match file.set_len(size as u64) {
+                Ok(_) => false,
+                Err(e) => return Err(MKImageError::io("set the file size", e)),
+            },
+            // Fall back to writing zeroes where the platform can't preallocate
+            Allocation::Preallocated => match preallocate(&file, size as u64) {
+                Ok(preallocated) => !preallocated,
+                Err(e) => return Err(MKImageError::io("preallocate", e)),
+            },
+        };
+
+        if zero_fill {
+            write_zeroes(&mut file, size)?;
+        }
+
+        return Ok(Self {
+            file: RefCell::new(file),
+            start: 0,
+            size: None,
+            delta: None,
+            audit: WriteAudit::default(),
+        });
+    }
+
+    /// Opens an existing block device, or file, to use its first `size` bytes as a new image without replacing
+    /// it. The old contents are only cleared with `Allocation::Zeroed`, the other allocations leave them as they
+    /// are since the space is already allocated.
+    pub fn new_device(
+        path: String,
+        size: u64,
+        allocation: Allocation,
+    ) -> Result<Self, MKImageError> {
+        let file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(false)
+            .open(&path)
+        {
+            Ok(f) => f,
+            Err(e) => return Err(MKImageError::open(&path, e)),
         };
 
-        // Make the file up to the size
-        let mut remaining = size;
+        let mut handler = Self {
+            file: RefCell::new(file),
+            start: 0,
+            size: None,
+            delta: None,
+            audit: WriteAudit::default(),
+        };
+
+        if handler.file_size()? < size {
+            return Err(MKImageError::OutOfBounds {
+                location: 0,
+                amount: size,
+            });
+        }
+
+        handler.size = Some(size);
+
+        if allocation == Allocation::Zeroed {
+            write_zeroes(handler.file.get_mut(), size as usize)?;
+        }
+
+        return Ok(handler);
+    }
+
+    // Opens a file, along with its parents if it is a delta
+    pub fn new(path: String) -> Result<Self, MKImageError> {
+        return Self::open(&path, true, 0);
+    }
 
-        while remaining > 100 * 1024 * 1024 {
-            match file.write_all(&vec![0u8; 100 * 1024 * 1024]) {
-                Ok(_) => (),
-                Err(e) => {
-                    return Err(MKImageError::new(&format!(
-                        "Failed to write null bytes. Error: {}",
-                        e
+    /// Creates a delta image at the path that starts out the same as the parent image and records only the
+    /// blocks written to it, leaving the parent untouched. The parent is found by its absolute path, so it must
+    /// not be moved or changed while the delta is in use.
+    /// It will overwrite any existing file
+    pub fn new_delta(path: String, parent: String) -> Result<Self, MKImageError> {
+        let parent_path = match std::fs::canonicalize(&parent) {
+            Ok(p) => match p.to_str() {
+                Some(p) => p.to_string(),
+                None => {
+                    return Err(MKImageError::InvalidDelta(format!(
+                        "the path of {} is not valid UTF-8",
+                        parent
                     )))
                 }
-            }
+            },
+            Err(e) => return Err(MKImageError::open(&parent, e)),
+        };
 
-            remaining -= 100 * 1024 * 1024;
-        }
+        let parent_handler = Self::open(&parent_path, false, 1)?;
+        let header = DeltaHeader::new(parent_handler.disk_size()?, &parent_path)?;
 
-        match file.write_all(&vec![0u8; remaining]) {
+        let file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+        {
+            Ok(f) => f,
+            Err(e) => return Err(MKImageError::open(&path, e)),
+        };
+
+        // The bitmap and blocks are left as holes that read as zeroes
+        match file.set_len(header.data_offset() + header.disk_size) {
             Ok(_) => (),
-            Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to write null bytes. Error: {}",
-                    e
-                )))
-            }
+            Err(e) => return Err(MKImageError::io("set the file size", e)),
         }
 
-        return Ok(Self {
+        let handler = Self {
             file: RefCell::new(file),
-        });
+            start: 0,
+            size: None,
+            delta: Some(Delta {
+                bitmap: vec![0u8; header.bitmap_size() as usize],
+                header: header.clone(),
+                parent: Box::new(parent_handler),
+            }),
+            audit: WriteAudit::default(),
+        };
+
+        handler.write_file(0, &header.to_bytes(), 0)?;
+
+        return Ok(handler);
     }
 
-    // Opens a file
-    pub fn new(path: String) -> Result<Self, MKImageError> {
+    /// Opens a file, following the chain of parents to a depth if it is a delta.
+    fn open(path: &str, writable: bool, depth: usize) -> Result<Self, MKImageError> {
         let file = match OpenOptions::new()
             .read(true)
-            .write(true)
+            .write(writable)
             .create(false)
-            .open(path.clone())
+            .open(path)
         {
             Ok(f) => f,
-            Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to open file {}. Error: {}",
-                    path, e
-                )))
-            }
+            Err(e) => return Err(MKImageError::open(path, e)),
         };
 
-        return Ok(Self {
+        let mut handler = Self {
             file: RefCell::new(file),
+            start: 0,
+            size: None,
+            delta: None,
+            audit: WriteAudit::default(),
+        };
+
+        if handler.file_size()? < DELTA_HEADER_SIZE {
+            return Ok(handler);
+        }
+
+        let bytes = handler.read_file(0, DELTA_HEADER_SIZE, 0)?;
+
+        if !DeltaHeader::is_delta(&bytes) {
+            return Ok(handler);
+        }
+
+        if depth >= MAX_DELTA_CHAIN {
+            return Err(MKImageError::InvalidDelta(format!(
+                "{} has more than {} parents",
+                path, MAX_DELTA_CHAIN
+            )));
+        }
+
+        let header = DeltaHeader::from_bytes(&bytes)?;
+        let parent = Self::open(&header.parent, false, depth + 1)?;
+
+        if parent.disk_size()? != header.disk_size {
+            return Err(MKImageError::InvalidDelta(format!(
+                "its parent {} has changed size",
+                header.parent
+            )));
+        }
+
+        let bitmap = handler.read_file(DELTA_HEADER_SIZE, header.bitmap_size(), 0)?;
+        handler.delta = Some(Delta {
+            header,
+            parent: Box::new(parent),
+            bitmap,
         });
+
+        return Ok(handler);
+    }
+
+    /// Appends the location and length of every write made through the handler to a log file, one per line.
+    /// Locations are in the disk, or the selected volume, as passed to `write_bytes`.
+    pub fn log_writes(&mut self, path: &str) -> Result<(), MKImageError> {
+        let log = match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(f) => f,
+            Err(e) => return Err(MKImageError::open(path, e)),
+        };
+
+        self.audit.log = Some(log);
+
+        return Ok(());
+    }
+
+    /// Refuses writes that don't lie within one of the regions with `MKImageError::WriteProtected`, an empty list
+    /// refuses every write. None allows writes anywhere again.
+    pub fn set_writable_regions(&mut self, regions: Option<Vec<Range<u64>>>) {
+        self.audit.writable = regions;
+    }
+
+    /// The path of the image this is a delta of, if it is one.
+    pub fn parent_path(&self) -> Option<&str> {
+        return self.delta.as_ref().map(|d| d.header.parent.as_str());
+    }
+
+    /// Copies the whole image, through every parent if it is a delta, into a new image at the path that stands on
+    /// its own. Blocks of zeroes are left as holes. It will overwrite any existing file
+    pub fn flatten(&self, path: String) -> Result<Self, MKImageError> {
+        let size = match &self.delta {
+            Some(delta) => delta.header.disk_size,
+            None => self.file_size()?,
+        };
+
+        let mut handler =
+            Self::new_create_with_allocation(path, size as usize, Allocation::Sparse)?;
+        let mut address = 0;
+
+        while address < size {
+            let amount = std::cmp::min(size - address, FLATTEN_CHUNK_SIZE);
+            let bytes = self.read_at(address, amount)?;
+
+            for (i, block) in bytes.chunks(DELTA_BLOCK_SIZE as usize).enumerate() {
+                if block.iter().any(|b| *b != 0) {
+                    let block_address = address + i as u64 * DELTA_BLOCK_SIZE;
+                    handler.write_file(block_address, block, block_address)?;
+                }
+            }
+
+            address += amount;
+        }
+
+        handler.flush()?;
+
+        return Ok(handler);
+    }
+
+    /// Restricts the handler to the volume with a name from the image's volume table.
+    pub fn select_volume(&mut self, name: &str) -> Result<(), MKImageError> {
+        self.start = 0;
+        self.size = None;
+
+        let volume = match VolumeTable::read(self) {
+            Ok(table) => match table.volume(name) {
+                Some(v) => v.clone(),
+                None => return Err(MKImageError::NoSuchVolume(name.to_string())),
+            },
+            Err(e) => return Err(MKImageError::InvalidVolumeTable(e.to_string())),
+        };
+
+        self.start = volume.start();
+        self.size = Some(volume.size());
+
+        return Ok(());
     }
 }
 
-impl DiskHandler<MKImageError> for Handler {
-    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MKImageError> {
-        if self.disk_size()? < location + bytes.len() as u64 {
-            return Err(MKImageError::new(&format!(
-                "File is not large enough to write to address: {}",
-                location
-            )));
+/// The most bytes read at once when flattening an image.
+const FLATTEN_CHUNK_SIZE: u64 = 1_048_576;
+
+/// Writes zeroes from the start of the file, in chunks of at most 100MiB.
+fn write_zeroes(file: &mut File, size: usize) -> Result<(), MKImageError> {
+    let mut remaining = size;
+
+    while remaining > 0 {
+        let amount = std::cmp::min(remaining, 100 * 1024 * 1024);
+
+        match file.write_all(&vec![0u8; amount]) {
+            Ok(_) => (),
+            Err(e) => return Err(MKImageError::io("write null bytes", e)),
         }
 
+        remaining -= amount;
+    }
+
+    return Ok(());
+}
+
+/// The size of the file or block device at a path.
+pub fn path_size(path: &str) -> Result<u64, MKImageError> {
+    return match File::open(path) {
+        Ok(mut f) => size_of_file(&mut f),
+        Err(e) => Err(MKImageError::open(path, e)),
+    };
+}
+
+fn size_of_file(file: &mut File) -> Result<u64, MKImageError> {
+    let metadata = match file.metadata() {
+        Ok(m) => m,
+        Err(e) => return Err(MKImageError::io("determine the file size", e)),
+    };
+
+    // A block device's metadata has no length, its size is found by seeking to the end
+    if is_block_device(&metadata) {
+        return file
+            .seek(SeekFrom::End(0))
+            .map_err(|e| MKImageError::io("determine the device size", e));
+    }
+
+    return Ok(metadata.len());
+}
+
+/// Whether the file is a block device, such as a disk or partition.
+#[cfg(unix)]
+pub fn is_block_device(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    return metadata.file_type().is_block_device();
+}
+
+/// Whether the file is a block device, such as a disk or partition.
+#[cfg(not(unix))]
+pub fn is_block_device(_metadata: &std::fs::Metadata) -> bool {
+    return false;
+}
+
+/// Allocates the space of an empty file, returning false if the platform or filesystem can't.
+#[cfg(target_os = "linux")]
+fn preallocate(file: &File, size: u64) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    if size == 0 {
+        return Ok(true);
+    }
+
+    // Space allocated by fallocate reads as zeroes.
+    let result = unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, size as libc::off_t) };
+
+    if result == 0 {
+        return Ok(true);
+    }
+
+    let error = std::io::Error::last_os_error();
+
+    return match error.raw_os_error() {
+        Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => Ok(false),
+        _ => Err(error),
+    };
+}
+
+/// Allocates the space of an empty file, returning false if the platform or filesystem can't.
+#[cfg(windows)]
+fn preallocate(file: &File, size: u64) -> std::io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+
+    // Extending the file allocates its clusters, which Windows zeroes lazily as they are written.
+    file.set_len(size)?;
+
+    // Marking the data as valid skips the zeroing, but needs the SE_MANAGE_VOLUME_NAME privilege.
+    // Without it the file is still allocated so the failure is ignored.
+    unsafe {
+        winapi::um::fileapi::SetFileValidData(file.as_raw_handle() as _, size as i64);
+    }
+
+    return Ok(true);
+}
+
+/// Allocates the space of an empty file, returning false if the platform or filesystem can't.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn preallocate(_file: &File, _size: u64) -> std::io::Result<bool> {
+    return Ok(false);
+}
+
+impl Handler {
+    /// The length of the file on the host.
+    fn file_size(&self) -> Result<u64, MKImageError> {
+        return size_of_file(&mut self.file.borrow_mut());
+    }
+
+    /// Reads bytes from an offset in the file, ignoring any volume or delta. The location is used in errors.
+    fn read_file(&self, offset: u64, amount: u64, location: u64) -> Result<Vec<u8>, MKImageError> {
         let mut file = self.file.borrow_mut();
 
-        match file.seek(SeekFrom::Start(location)) {
+        match file.seek(SeekFrom::Start(offset)) {
             Ok(_) => (),
             Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to seek to location: {}. Error: {}",
-                    location, e
-                )))
+                return Err(MKImageError::io(
+                    &format!("seek to location {}", location),
+                    e,
+                ))
             }
         }
 
-        match file.write_all(bytes) {
+        let mut result = vec![0u8; amount as usize];
+        match file.read_exact(&mut result) {
             Ok(_) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                return Err(MKImageError::ShortRead { location, amount })
+            }
             Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to write to location: {}. Error: {}",
-                    location, e
-                )))
+                return Err(MKImageError::io(
+                    &format!("read from location {}", location),
+                    e,
+                ))
             }
         }
 
-        return Ok(());
+        return Ok(result);
     }
 
-    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
-        if self.disk_size()? < location + amount {
-            return Err(MKImageError::new(&format!(
-                "File is not large enough to read address: {}",
-                location + amount
-            )));
-        }
-
+    /// Writes bytes to an offset in the file, ignoring any volume or delta. The location is used in errors.
+    fn write_file(&self, offset: u64, bytes: &[u8], location: u64) -> Result<(), MKImageError> {
         let mut file = self.file.borrow_mut();
 
-        match file.seek(SeekFrom::Start(location)) {
+        match file.seek(SeekFrom::Start(offset)) {
             Ok(_) => (),
             Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to seek to location: {}. Error: {}",
-                    location, e
-                )))
+                return Err(MKImageError::io(
+                    &format!("seek to location {}", location),
+                    e,
+                ))
             }
         }
 
-        let mut result = vec![0u8; amount as usize];
-        match file.read_exact(&mut result) {
+        match file.write_all(bytes) {
             Ok(_) => (),
             Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Failed to read bytes. Error: {}",
-                    e
-                )))
+                return Err(MKImageError::io(
+                    &format!("write to location {}", location),
+                    e,
+                ))
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Reads from an address in the whole image, taking each block from the delta or its parent.
+    fn read_at(&self, address: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+        let delta = match &self.delta {
+            Some(d) => d,
+            None => return self.read_file(address, amount, address),
+        };
+
+        let block_size = delta.header.block_size;
+        let end = address + amount;
+        let mut result = Vec::with_capacity(amount as usize);
+        let mut position = address;
+
+        while position < end {
+            // Read every following block that comes from the same place at once
+            let held = delta.holds(position / block_size);
+            let mut run_end = (position / block_size + 1) * block_size;
+
+            while run_end < end && delta.holds(run_end / block_size) == held {
+                run_end += block_size;
             }
+
+            let run_end = std::cmp::min(run_end, end);
+
+            if held {
+                result.extend(self.read_file(
+                    delta.header.data_offset() + position,
+                    run_end - position,
+                    position,
+                )?);
+            } else {
+                result.extend(delta.parent.read_bytes(position, run_end - position)?);
+            }
+
+            position = run_end;
         }
 
         return Ok(result);
     }
 
+    /// Writes to an address in the whole image. A delta first copies in the rest of any block it doesn't hold
+    /// that is only partly written, then marks the blocks as held once they are written.
+    fn write_at(&mut self, address: u64, bytes: &[u8]) -> Result<(), MKImageError> {
+        let (block_size, disk_size, data_offset) = match &self.delta {
+            Some(d) => (
+                d.header.block_size,
+                d.header.disk_size,
+                d.header.data_offset(),
+            ),
+            None => return self.write_file(address, bytes, address),
+        };
+
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let end = address + bytes.len() as u64;
+        let first = address / block_size;
+        let last = (end - 1) / block_size;
+
+        let edges = match first == last {
+            true => vec![first],
+            false => vec![first, last],
+        };
+
+        for block in edges.iter() {
+            let block_start = block * block_size;
+            let block_end = std::cmp::min(block_start + block_size, disk_size);
+            let delta = self.delta.as_ref().unwrap();
+
+            if !delta.holds(*block) && (address > block_start || end < block_end) {
+                let contents = delta
+                    .parent
+                    .read_bytes(block_start, block_end - block_start)?;
+                self.write_file(data_offset + block_start, &contents, block_start)?;
+            }
+        }
+
+        self.write_file(data_offset + address, bytes, address)?;
+
+        let delta = self.delta.as_mut().unwrap();
+
+        for block in first..=last {
+            delta.bitmap[(block / 8) as usize] |= 1 << (block % 8);
+        }
+
+        let changed = delta.bitmap[(first / 8) as usize..=(last / 8) as usize].to_vec();
+
+        return self.write_file(DELTA_HEADER_SIZE + first / 8, &changed, address);
+    }
+}
+
+impl DiskHandler<MKImageError> for Handler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MKImageError> {
+        let amount = bytes.len() as u64;
+
+        if self.disk_size()? < location + amount {
+            return Err(MKImageError::OutOfBounds { location, amount });
+        }
+
+        let allowed = self.audit.allows(location, amount);
+        self.audit.record(location, amount, allowed)?;
+
+        if !allowed {
+            return Err(MKImageError::WriteProtected { location, amount });
+        }
+
+        return self.write_at(self.start + location, bytes);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+        if self.disk_size()? < location + amount {
+            return Err(MKImageError::OutOfBounds { location, amount });
+        }
+
+        return self.read_at(self.start + location, amount);
+    }
+
     fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MKImageError> {
         return self.write_bytes(&vec![0u8; (end - start) as usize], start);
     }
 
     fn disk_size(&self) -> Result<u64, MKImageError> {
-        let b = self.file.borrow();
-        let metadata = match b.metadata() {
-            Ok(m) => m,
-            Err(e) => {
-                return Err(MKImageError::new(&format!(
-                    "Could not determine file size. Error: {}",
-                    e
-                )))
-            }
+        if let Some(size) = self.size {
+            return Ok(size);
+        }
+
+        if let Some(delta) = &self.delta {
+            return Ok(delta.header.disk_size);
+        }
+
+        return self.file_size();
+    }
+
+    fn flush(&mut self) -> Result<(), MKImageError> {
+        let mut file = self.file.borrow_mut();
+
+        match file.flush().and_then(|_| file.sync_data()) {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(MKImageError::io("flush the image", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocation() {
+        let path = std::env::temp_dir().join(format!("voxfs-allocation-{}", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+
+        for allocation in [
+            Allocation::Zeroed,
+            Allocation::Sparse,
+            Allocation::Preallocated,
+        ]
+        .iter()
+        {
+            // Creating over a larger file truncates it
+            std::fs::write(&path, vec![0xffu8; 8192]).unwrap();
+
+            let handler =
+                Handler::new_create_with_allocation(path.clone(), 4096, *allocation).unwrap();
+            assert_eq!(handler.disk_size().unwrap(), 4096);
+            assert_eq!(handler.read_bytes(0, 4096).unwrap(), vec![0u8; 4096]);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_audit() {
+        let directory = std::env::temp_dir();
+        let image = directory.join(format!("voxfs-audit-{}", std::process::id()));
+        let log = directory.join(format!("voxfs-audit-log-{}", std::process::id()));
+        let image = image.to_str().unwrap().to_string();
+        let log = log.to_str().unwrap().to_string();
+
+        let mut handler =
+            Handler::new_create_with_allocation(image.clone(), 8192, Allocation::Sparse).unwrap();
+        handler.log_writes(&log).unwrap();
+        handler.write_bytes(&vec![1u8; 10], 100).unwrap();
+
+        handler.set_writable_regions(Some(vec![0..50, 4096..8192]));
+        handler.zero_range(4096, 8192).unwrap();
+        assert!(matches!(
+            handler.write_bytes(&vec![2u8; 10], 4090),
+            Err(MKImageError::WriteProtected {
+                location: 4090,
+                amount: 10
+            })
+        ));
+
+        // Nothing may be written once every write is refused
+        handler.set_writable_regions(Some(Vec::new()));
+        assert!(handler.write_bytes(&vec![3u8; 1], 5000).is_err());
+        assert_eq!(handler.read_bytes(4090, 10).unwrap(), vec![0u8; 10]);
+
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "100 10\n4096 4096\n4090 10 refused\n5000 1 refused\n"
+        );
+
+        std::fs::remove_file(&image).unwrap();
+        std::fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn test_delta() {
+        let directory = std::env::temp_dir();
+        let path = |name: &str| {
+            return directory
+                .join(format!("voxfs-delta-{}-{}", name, std::process::id()))
+                .to_str()
+                .unwrap()
+                .to_string();
         };
 
-        return Ok(metadata.len());
+        // The parent doesn't end on a block boundary
+        let original: Vec<u8> = (0..4096 * 3 + 100).map(|i| (i % 251) as u8).collect();
+        std::fs::write(path("base"), &original).unwrap();
+
+        let mut expected = original.clone();
+        {
+            let mut child = Handler::new_delta(path("child"), path("base")).unwrap();
+            assert_eq!(child.disk_size().unwrap(), original.len() as u64);
+            assert_eq!(child.read_bytes(0, 4096 * 3 + 100).unwrap(), original);
+
+            child.write_bytes(&vec![1u8; 10], 4090).unwrap();
+            child.zero_range(4096 * 3 + 50, 4096 * 3 + 100).unwrap();
+            expected[4090..4100].copy_from_slice(&[1u8; 10]);
+            expected[4096 * 3 + 50..].copy_from_slice(&[0u8; 50]);
+
+            assert_eq!(
+                child.read_bytes(0, expected.len() as u64).unwrap(),
+                expected
+            );
+        }
+
+        assert_eq!(std::fs::read(path("base")).unwrap(), original);
+
+        // A delta can be the parent of another
+        let mut grandchild = Handler::new_delta(path("grandchild"), path("child")).unwrap();
+        assert_eq!(grandchild.parent_path(), Some(path("child").as_str()));
+        grandchild.write_bytes(&vec![2u8; 5000], 100).unwrap();
+        expected[100..5100].copy_from_slice(&[2u8; 5000]);
+        drop(grandchild);
+
+        let grandchild = Handler::new(path("grandchild")).unwrap();
+        assert_eq!(
+            grandchild.read_bytes(0, expected.len() as u64).unwrap(),
+            expected
+        );
+
+        let flattened = grandchild.flatten(path("flat")).unwrap();
+        assert!(flattened.parent_path().is_none());
+        assert_eq!(std::fs::read(path("flat")).unwrap(), expected);
+
+        // The parent must keep its size
+        std::fs::write(path("base"), &original[..4096]).unwrap();
+        assert!(matches!(
+            Handler::new(path("child")),
+            Err(MKImageError::InvalidDelta(_))
+        ));
+
+        for name in ["base", "child", "grandchild", "flat"].iter() {
+            std::fs::remove_file(path(name)).unwrap();
+        }
     }
 }
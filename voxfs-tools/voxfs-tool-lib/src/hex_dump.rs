@@ -0,0 +1,104 @@
+/// The number of bytes shown on each line.
+const LINE_LENGTH: usize = 16;
+
+/// Formats bytes read in pieces as a canonical hex dump, an address, sixteen bytes in hex and their printable
+/// characters. Runs of identical lines are collapsed into a single `*` line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HexDump {
+    previous: Option<Vec<u8>>,
+    squeezing: bool,
+}
+
+impl HexDump {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// The lines for the bytes at the address. Every piece but the last should be a multiple of 16 bytes long.
+    pub fn lines(&mut self, address: u64, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for (i, line) in bytes.chunks(LINE_LENGTH).enumerate() {
+            if self.previous.as_deref() == Some(line) {
+                if !self.squeezing {
+                    self.squeezing = true;
+                    lines.push(String::from("*"));
+                }
+
+                continue;
+            }
+
+            self.squeezing = false;
+            self.previous = Some(line.to_vec());
+            lines.push(format_line(address + (i * LINE_LENGTH) as u64, line));
+        }
+
+        return lines;
+    }
+
+    /// The final line, the address just past the end of the dump.
+    pub fn end(&self, address: u64) -> String {
+        return format!("{:08x}", address);
+    }
+}
+
+fn format_line(address: u64, bytes: &[u8]) -> String {
+    let mut line = format!("{:08x} ", address);
+
+    for i in 0..LINE_LENGTH {
+        if i % 8 == 0 {
+            line.push(' ');
+        }
+
+        match bytes.get(i) {
+            Some(b) => line.push_str(&format!("{:02x} ", b)),
+            None => line.push_str("   "),
+        }
+    }
+
+    line.push_str(" |");
+
+    for b in bytes {
+        if b.is_ascii_graphic() || *b == b' ' {
+            line.push(*b as char);
+        } else {
+            line.push('.');
+        }
+    }
+
+    line.push('|');
+
+    return line;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HexDump;
+
+    #[test]
+    fn test_lines() {
+        let mut dump = HexDump::new();
+        let lines = dump.lines(0x10, b"voxfs\x00\x01 image!");
+
+        assert_eq!(
+            lines,
+            vec![String::from(
+                "00000010  76 6f 78 66 73 00 01 20  69 6d 61 67 65 21        |voxfs.. image!|"
+            )]
+        );
+        assert_eq!(dump.end(0x1e), "0000001e");
+    }
+
+    #[test]
+    fn test_squeeze() {
+        let mut dump = HexDump::new();
+        let mut lines = dump.lines(0, &[0u8; 32]);
+        lines.extend(dump.lines(32, &[0u8; 32]));
+        lines.extend(dump.lines(64, &[1u8; 16]));
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("00000000  00 00"));
+        assert_eq!(lines[1], "*");
+        assert!(lines[2].starts_with("00000040  01 01"));
+    }
+}
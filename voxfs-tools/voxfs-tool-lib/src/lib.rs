@@ -1,11 +1,40 @@
+mod cli;
+mod container;
+mod copy;
+mod crc32;
+mod delta;
 mod error;
 mod handler;
+mod hex_dump;
 mod manager;
+#[cfg(feature = "object-store")]
+mod object_store_handler;
+mod retrying_handler;
+mod sha256;
+mod signature;
+mod zip;
 
 use byte_unit::Byte;
+use chrono::{DateTime, NaiveDate, Utc};
+pub use cli::{
+    confirm, describe_error, fail, fail_error, open_image, print_progress, ExitCode, Image,
+    OpenMode, WRITE_LOG_VARIABLE, WRITE_PROTECT_VARIABLE,
+};
+pub use container::{is_container, pack, ContainerReader, PackSummary, CONTAINER_EXTENSION};
+pub use copy::{copy_files, copy_filesystem, format_options_like, inodes_per_tag, is_out_of_space};
+pub use crc32::Crc32;
+pub use delta::MAX_PARENT_PATH_LENGTH;
 pub use error::MKImageError;
-pub use handler::Handler;
+pub use handler::{is_block_device, path_size, Allocation, Handler};
+pub use hex_dump::HexDump;
 pub use manager::Manager;
+#[cfg(feature = "object-store")]
+pub use object_store_handler::{DirectoryObjectStore, ObjectStore, ObjectStoreHandler};
+pub use retrying_handler::{ClassifyError, ErrorClass, RetryPolicy, RetryStats, RetryingHandler};
+pub use sha256::Sha256;
+pub use signature::{detect_signatures, signatures, Signature};
+use voxfs::{OpenReport, VoxFSError};
+pub use zip::{export_zip, ZipSummary, ZipTagLayout, ZIP_METADATA_NAME};
 
 pub fn sized_string_to_u64(string: &str) -> Option<u64> {
     return match Byte::from_str(string) {
@@ -18,9 +47,66 @@ pub fn u64_to_sized_string(n: u64) -> String {
     return Byte::from(n).get_appropriate_unit(false).to_string();
 }
 
+/// Parses a date as either YYYY-MM-DD, taken as midnight UTC, or an RFC 3339 time.
+pub fn parse_date(string: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(string, "%Y-%m-%d") {
+        return Some(DateTime::from_utc(date.and_hms(0, 0, 0), Utc));
+    }
+
+    return match DateTime::parse_from_rfc3339(string) {
+        Ok(d) => Some(d.with_timezone(&Utc)),
+        Err(_) => None,
+    };
+}
+
+/// Quotes a string for use in JSON output.
+pub fn json_string(string: &str) -> String {
+    let mut result = String::with_capacity(string.len() + 2);
+    result.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+
+    return result;
+}
+
+/// A filesystem error as a JSON object of its code and message.
+pub fn error_json<E: std::fmt::Display>(error: &VoxFSError<E>) -> String {
+    return format!(
+        "{{\"code\":{},\"message\":{}}}",
+        json_string(error.code()),
+        json_string(&error.to_string())
+    );
+}
+
+/// Prints the tags and files skipped by a tolerant open.
+pub fn print_open_report<E: std::fmt::Display>(report: &OpenReport<E>) {
+    for record in report.skipped() {
+        eprintln!(
+            "Skipped {:?} {} at address {}: {}",
+            record.kind(),
+            record.index(),
+            record.address(),
+            describe_error(record.reason())
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::sized_string_to_u64;
+    use super::{describe_error, error_json, json_string, parse_date, sized_string_to_u64};
+    use voxfs::VoxFSError;
 
     #[test]
     fn test_no_suffix() {
@@ -66,4 +152,37 @@ mod tests {
     fn test_fail_4() {
         assert!(sized_string_to_u64("G2GB").is_none())
     }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("tag"), "\"tag\"");
+        assert_eq!(json_string("a \"b\"\\\n"), "\"a \\\"b\\\"\\\\\\n\"");
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2024-01-01").unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2024-01-01T10:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-01T08:30:00+00:00"
+        );
+        assert!(parse_date("2024-13-01").is_none());
+        assert!(parse_date("yesterday").is_none());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let error: VoxFSError<String> = VoxFSError::FileBusy;
+        assert_eq!(describe_error(&error), "FileBusy [E0047]");
+        assert_eq!(
+            error_json(&VoxFSError::DiskError("out of \"space\"".to_string())),
+            "{\"code\":\"E0053\",\"message\":\"Disk error: out of \\\"space\\\"\"}"
+        );
+    }
 }
@@ -1,6 +1,7 @@
+use crate::Sha256;
 use chrono::DateTime;
 use chrono::Utc;
-use voxfs::OSManager;
+use voxfs::{ContentHasher, OSManager};
 
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Eq, Ord)]
 pub struct Manager {}
@@ -15,4 +16,19 @@ impl OSManager for Manager {
     fn current_time(&self) -> DateTime<Utc> {
         return Utc::now();
     }
+
+    #[cfg(target_os = "linux")]
+    fn user_id(&self) -> u32 {
+        return unsafe { libc::getuid() };
+    }
+
+    fn user_name(&self) -> String {
+        return std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_default();
+    }
+
+    fn content_hasher(&self) -> Option<Box<dyn ContentHasher>> {
+        return Some(Box::new(Sha256::new()));
+    }
 }
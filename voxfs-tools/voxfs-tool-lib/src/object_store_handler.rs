@@ -0,0 +1,352 @@
+// Object store layout:
+// the disk is split into objects of `object_size` bytes, object n covers the bytes from n * object_size and is
+// stored under the key "<prefix>/<n as 16 hex digits>". Objects that were never written are missing and read as
+// zeroes, so a new disk only costs the objects its filesystem touches.
+
+use crate::error::MKImageError;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use voxfs::DiskHandler;
+
+/// A store of whole objects by key, such as an S3 bucket. Objects are only ever read and written whole since
+/// most object stores can't update part of one.
+pub trait ObjectStore {
+    /// Returns None if there is no object with the key.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MKImageError>;
+
+    fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), MKImageError>;
+}
+
+/// Keeps each object in a file named by its key under a directory, for a bucket mounted on the host or to try the
+/// handler without a remote store.
+pub struct DirectoryObjectStore {
+    root: PathBuf,
+}
+
+impl DirectoryObjectStore {
+    pub fn new(root: PathBuf) -> Self {
+        return Self { root };
+    }
+}
+
+impl ObjectStore for DirectoryObjectStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MKImageError> {
+        return match std::fs::read(self.root.join(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(MKImageError::io(&format!("read the object {}", key), e)),
+        };
+    }
+
+    fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), MKImageError> {
+        let path = self.root.join(key);
+
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                return Err(MKImageError::io(
+                    &format!("create the directory for the object {}", key),
+                    e,
+                ));
+            }
+        }
+
+        return std::fs::write(&path, bytes)
+            .map_err(|e| MKImageError::io(&format!("write the object {}", key), e));
+    }
+}
+
+/// An object held in memory and whether it has changed since it was read from the store.
+struct CachedObject {
+    bytes: Vec<u8>,
+    dirty: bool,
+    last_used: u64,
+}
+
+/// The objects held in memory, with a counter used to find the least recently used.
+struct ObjectCache {
+    objects: BTreeMap<u64, CachedObject>,
+    clock: u64,
+}
+
+/// A disk on an object store. The filesystem reads and writes a few bytes at a time, so whole objects are kept in a
+/// write back cache and only written to the store when they are evicted, flushed or at a barrier. Writes that have
+/// not been flushed are lost if the handler is dropped, so `Disk::close` should always be called.
+pub struct ObjectStoreHandler<S: ObjectStore> {
+    // Reads take the handler by reference but may need to evict a dirty object to make room
+    store: RefCell<S>,
+    prefix: String,
+    disk_size: u64,
+    object_size: u64,
+    capacity: usize,
+    cache: RefCell<ObjectCache>,
+}
+
+impl<S: ObjectStore> ObjectStoreHandler<S> {
+    /// The disk is `disk_size` bytes stored as objects of `object_size` bytes under the prefix, with at most
+    /// `capacity` objects held in memory.
+    pub fn new(
+        store: S,
+        prefix: &str,
+        disk_size: u64,
+        object_size: u64,
+        capacity: usize,
+    ) -> Result<Self, MKImageError> {
+        if object_size == 0 || capacity == 0 {
+            return Err(MKImageError::io(
+                "create the object store handler",
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "the object size and cache capacity must not be 0",
+                ),
+            ));
+        }
+
+        return Ok(Self {
+            store: RefCell::new(store),
+            prefix: prefix.trim_end_matches('/').to_string(),
+            disk_size,
+            object_size,
+            capacity,
+            cache: RefCell::new(ObjectCache {
+                objects: BTreeMap::new(),
+                clock: 0,
+            }),
+        });
+    }
+
+    /// The key of the object holding the bytes from `index * object_size`.
+    pub fn key(&self, index: u64) -> String {
+        return format!("{}/{:016x}", self.prefix, index);
+    }
+
+    /// The number of objects held in memory and how many of them have unwritten changes.
+    pub fn cached_objects(&self) -> (usize, usize) {
+        let cache = self.cache.borrow();
+        let dirty = cache.objects.values().filter(|o| o.dirty).count();
+
+        return (cache.objects.len(), dirty);
+    }
+
+    pub fn into_store(self) -> S {
+        return self.store.into_inner();
+    }
+
+    /// Calls the function with an object, reading it into the cache first if needed.
+    fn with_object<T>(
+        &self,
+        index: u64,
+        f: impl FnOnce(&mut CachedObject) -> T,
+    ) -> Result<T, MKImageError> {
+        let mut cache = self.cache.borrow_mut();
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        if !cache.objects.contains_key(&index) {
+            if cache.objects.len() >= self.capacity {
+                self.evict(&mut cache)?;
+            }
+
+            let mut bytes = self
+                .store
+                .borrow()
+                .get(&self.key(index))?
+                .unwrap_or_default();
+            bytes.resize(self.object_size as usize, 0);
+
+            cache.objects.insert(
+                index,
+                CachedObject {
+                    bytes,
+                    dirty: false,
+                    last_used: clock,
+                },
+            );
+        }
+
+        let object = cache.objects.get_mut(&index).unwrap();
+        object.last_used = clock;
+
+        return Ok(f(object));
+    }
+
+    /// Removes the least recently used object, writing it to the store if it changed.
+    fn evict(&self, cache: &mut ObjectCache) -> Result<(), MKImageError> {
+        let oldest = match cache.objects.iter().min_by_key(|(_, o)| o.last_used) {
+            Some((index, _)) => *index,
+            None => return Ok(()),
+        };
+
+        if cache.objects[&oldest].dirty {
+            self.store
+                .borrow_mut()
+                .put(&self.key(oldest), &cache.objects[&oldest].bytes)?;
+        }
+
+        cache.objects.remove(&oldest);
+
+        return Ok(());
+    }
+
+    /// Splits a range of the disk into the parts of each object it covers, as the object's index, the offset in
+    /// the object, the offset in the range and the length.
+    fn pieces(&self, location: u64, amount: u64) -> Vec<(u64, usize, usize, usize)> {
+        let mut pieces = Vec::new();
+        let mut done = 0;
+
+        while done < amount {
+            let address = location + done;
+            let offset = address % self.object_size;
+            let length = std::cmp::min(self.object_size - offset, amount - done);
+
+            pieces.push((
+                address / self.object_size,
+                offset as usize,
+                done as usize,
+                length as usize,
+            ));
+            done += length;
+        }
+
+        return pieces;
+    }
+
+    fn check_bounds(&self, location: u64, amount: u64) -> Result<(), MKImageError> {
+        return match location.checked_add(amount) {
+            Some(end) if end <= self.disk_size => Ok(()),
+            _ => Err(MKImageError::OutOfBounds { location, amount }),
+        };
+    }
+}
+
+impl<S: ObjectStore> DiskHandler<MKImageError> for ObjectStoreHandler<S> {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MKImageError> {
+        self.check_bounds(location, bytes.len() as u64)?;
+
+        for (index, offset, start, length) in self.pieces(location, bytes.len() as u64) {
+            self.with_object(index, |object| {
+                object.bytes[offset..offset + length]
+                    .copy_from_slice(&bytes[start..start + length]);
+                object.dirty = true;
+            })?;
+        }
+
+        return Ok(());
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+        self.check_bounds(location, amount)?;
+
+        let mut bytes = vec![0u8; amount as usize];
+
+        for (index, offset, start, length) in self.pieces(location, amount) {
+            self.with_object(index, |object| {
+                bytes[start..start + length]
+                    .copy_from_slice(&object.bytes[offset..offset + length]);
+            })?;
+        }
+
+        return Ok(bytes);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MKImageError> {
+        return self.write_bytes(&vec![0u8; end.saturating_sub(start) as usize], start);
+    }
+
+    fn disk_size(&self) -> Result<u64, MKImageError> {
+        return Ok(self.disk_size);
+    }
+
+    /// Writes every changed object to the store, they stay in the cache.
+    fn flush(&mut self) -> Result<(), MKImageError> {
+        let mut cache = self.cache.borrow_mut();
+        let mut store = self.store.borrow_mut();
+
+        for (index, object) in cache.objects.iter_mut().filter(|(_, o)| o.dirty) {
+            store.put(&self.key(*index), &object.bytes)?;
+            object.dirty = false;
+        }
+
+        return Ok(());
+    }
+
+    /// Objects are written whole so a write after the barrier could otherwise reach the store in the same object
+    /// as one before it, the cache is flushed to keep them in order.
+    fn barrier(&mut self) -> Result<(), MKImageError> {
+        return self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Manager;
+    use voxfs::{Disk, INodeFlags};
+
+    /// Counts the requests made to it so the tests can check what reached the store.
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: BTreeMap<String, Vec<u8>>,
+        puts: usize,
+    }
+
+    impl ObjectStore for MemoryStore {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MKImageError> {
+            return Ok(self.objects.get(key).cloned());
+        }
+
+        fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), MKImageError> {
+            self.puts += 1;
+            self.objects.insert(key.to_string(), bytes.to_vec());
+
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn test_write_back() {
+        let mut handler =
+            ObjectStoreHandler::new(MemoryStore::default(), "disk/", 4096, 1024, 2).unwrap();
+        assert_eq!(handler.key(3), "disk/0000000000000003");
+
+        // Spans objects 0 and 1
+        handler.write_bytes(&vec![7; 100], 1000).unwrap();
+        assert_eq!(handler.cached_objects(), (2, 2));
+        assert_eq!(handler.read_bytes(990, 20).unwrap()[10..], [7; 10]);
+
+        // Reading object 3 evicts object 0, the least recently used
+        assert_eq!(handler.read_bytes(3072, 4).unwrap(), vec![0; 4]);
+        assert_eq!(handler.cached_objects(), (2, 1));
+
+        handler.flush().unwrap();
+        assert_eq!(handler.cached_objects(), (2, 0));
+        assert!(handler.read_bytes(4090, 10).is_err());
+
+        let store = handler.into_store();
+        assert_eq!(store.puts, 2);
+        assert_eq!(store.objects.len(), 2);
+        assert_eq!(store.objects["disk/0000000000000001"][..76], [7; 76]);
+    }
+
+    #[test]
+    fn test_filesystem() {
+        let handler = ObjectStoreHandler::new(MemoryStore::default(), "disk", 4096 * 64, 8192, 4);
+        let mut handler = handler.unwrap();
+        let mut manager = Manager::new();
+
+        let contents: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        let file = disk
+            .create_new_file("file", INodeFlags::default(), contents.clone())
+            .unwrap();
+        disk.close().unwrap();
+
+        // Every change reached the store so a new handler sees the same disk
+        let store = handler.into_store();
+        let mut handler = ObjectStoreHandler::new(store, "disk", 4096 * 64, 8192, 4).unwrap();
+
+        let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        assert_eq!(disk.read_file(file.index()).unwrap(), contents);
+    }
+}
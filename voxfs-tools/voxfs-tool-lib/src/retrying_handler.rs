@@ -0,0 +1,309 @@
+use crate::error::MKImageError;
+use std::cell::Cell;
+use std::io::ErrorKind;
+use std::time::Duration;
+use voxfs::{DiskHandler, VoxFSErrorConvertible};
+
+/// Whether an operation that failed with an error might succeed if it is tried again.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ErrorClass {
+    /// The failure may go away by itself, such as a timeout on a network filesystem or a USB device resetting.
+    Transient,
+    /// Trying again will fail the same way.
+    Permanent,
+}
+
+/// Errors that can say whether the operation that produced them is worth retrying.
+pub trait ClassifyError {
+    fn class(&self) -> ErrorClass;
+}
+
+impl ClassifyError for MKImageError {
+    fn class(&self) -> ErrorClass {
+        return match self {
+            MKImageError::Io { source, .. } => match source.kind() {
+                ErrorKind::Interrupted
+                | ErrorKind::WouldBlock
+                | ErrorKind::TimedOut
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NotConnected
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof => ErrorClass::Transient,
+                _ => ErrorClass::Permanent,
+            },
+            // A network filesystem can return fewer bytes than asked for while the file is still there
+            MKImageError::ShortRead { .. } => ErrorClass::Transient,
+            _ => ErrorClass::Permanent,
+        };
+    }
+}
+
+/// How many times to try an operation and how long to wait between the attempts. The wait doubles after each
+/// attempt up to the maximum.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Attempts is the total number of tries, so 1 never retries.
+    pub fn new(attempts: u32, initial_delay: Duration, max_delay: Duration) -> Self {
+        return Self {
+            attempts: attempts.max(1),
+            initial_delay,
+            max_delay,
+        };
+    }
+
+    /// Tries each operation once.
+    pub fn never() -> Self {
+        return Self::new(1, Duration::from_millis(0), Duration::from_millis(0));
+    }
+
+    pub fn attempts(&self) -> u32 {
+        return self.attempts;
+    }
+
+    /// The time to wait after a failed attempt, counting from 0.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+
+        return self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        return Self::new(4, Duration::from_millis(50), Duration::from_secs(2));
+    }
+}
+
+/// Counts of the failures a `RetryingHandler` has seen.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetryStats {
+    /// The number of times an operation was tried again.
+    pub retries: u64,
+    /// The operations that failed at first but then succeeded.
+    pub recovered: u64,
+    /// The operations that failed with a permanent error, or a transient error on every attempt.
+    pub failures: u64,
+}
+
+/// Wraps a handler so operations failing with a transient error are tried again after a delay, for images on
+/// network filesystems or removable media. Permanent errors are returned straight away.
+pub struct RetryingHandler<H> {
+    inner: H,
+    policy: RetryPolicy,
+    // Reads take the handler by reference so the counters need to be updated through it
+    stats: Cell<RetryStats>,
+}
+
+impl<H> RetryingHandler<H> {
+    pub fn new(inner: H, policy: RetryPolicy) -> Self {
+        return Self {
+            inner,
+            policy,
+            stats: Cell::new(RetryStats::default()),
+        };
+    }
+
+    pub fn inner(&self) -> &H {
+        return &self.inner;
+    }
+
+    pub fn inner_mut(&mut self) -> &mut H {
+        return &mut self.inner;
+    }
+
+    pub fn into_inner(self) -> H {
+        return self.inner;
+    }
+
+    pub fn policy(&self) -> RetryPolicy {
+        return self.policy;
+    }
+
+    pub fn set_policy(&mut self, policy: RetryPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn stats(&self) -> RetryStats {
+        return self.stats.get();
+    }
+}
+
+impl<E, H> DiskHandler<E> for RetryingHandler<H>
+where
+    E: VoxFSErrorConvertible + ClassifyError,
+    H: DiskHandler<E>,
+{
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), E> {
+        let inner = &mut self.inner;
+
+        return retry(self.policy, &self.stats, || {
+            inner.write_bytes(bytes, location)
+        });
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, E> {
+        return retry(self.policy, &self.stats, || {
+            self.inner.read_bytes(location, amount)
+        });
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), E> {
+        let inner = &mut self.inner;
+
+        return retry(self.policy, &self.stats, || inner.zero_range(start, end));
+    }
+
+    fn disk_size(&self) -> Result<u64, E> {
+        return retry(self.policy, &self.stats, || self.inner.disk_size());
+    }
+
+    fn flush(&mut self) -> Result<(), E> {
+        let inner = &mut self.inner;
+
+        return retry(self.policy, &self.stats, || inner.flush());
+    }
+
+    fn barrier(&mut self) -> Result<(), E> {
+        let inner = &mut self.inner;
+
+        return retry(self.policy, &self.stats, || inner.barrier());
+    }
+}
+
+/// Runs an operation until it succeeds, fails permanently or runs out of attempts, counting what happened.
+fn retry<T, E: ClassifyError>(
+    policy: RetryPolicy,
+    stats: &Cell<RetryStats>,
+    mut operation: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    let mut counts = stats.get();
+    let mut attempt = 0;
+
+    let result = loop {
+        let error = match operation() {
+            Ok(value) => {
+                if attempt > 0 {
+                    counts.recovered += 1;
+                }
+
+                break Ok(value);
+            }
+            Err(e) => e,
+        };
+
+        if error.class() == ErrorClass::Permanent || attempt + 1 >= policy.attempts() {
+            counts.failures += 1;
+
+            break Err(error);
+        }
+
+        std::thread::sleep(policy.delay(attempt));
+
+        attempt += 1;
+        counts.retries += 1;
+    };
+
+    stats.set(counts);
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// Fails the first reads with an error of the given kind.
+    struct Flaky {
+        failures: Cell<u32>,
+        kind: ErrorKind,
+    }
+
+    impl DiskHandler<MKImageError> for Flaky {
+        fn write_bytes(&mut self, _bytes: &Vec<u8>, _location: u64) -> Result<(), MKImageError> {
+            return Ok(());
+        }
+
+        fn read_bytes(&self, _location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+
+                return Err(MKImageError::io("read", io::Error::from(self.kind)));
+            }
+
+            return Ok(vec![0; amount as usize]);
+        }
+
+        fn zero_range(&mut self, _start: u64, _end: u64) -> Result<(), MKImageError> {
+            return Ok(());
+        }
+
+        fn disk_size(&self) -> Result<u64, MKImageError> {
+            return Ok(4096);
+        }
+    }
+
+    fn flaky(failures: u32, kind: ErrorKind) -> RetryingHandler<Flaky> {
+        let policy = RetryPolicy::new(3, Duration::from_millis(0), Duration::from_millis(0));
+        let flaky = Flaky {
+            failures: Cell::new(failures),
+            kind,
+        };
+
+        return RetryingHandler::new(flaky, policy);
+    }
+
+    #[test]
+    fn test_transient_errors_are_retried() {
+        let handler = flaky(2, ErrorKind::TimedOut);
+        assert_eq!(handler.read_bytes(0, 4).unwrap(), vec![0; 4]);
+        assert_eq!(
+            handler.stats(),
+            RetryStats {
+                retries: 2,
+                recovered: 1,
+                failures: 0
+            }
+        );
+
+        // Only three attempts are made
+        let handler = flaky(3, ErrorKind::Interrupted);
+        assert!(handler.read_bytes(0, 4).is_err());
+        assert_eq!(handler.stats().retries, 2);
+        assert_eq!(handler.stats().failures, 1);
+    }
+
+    #[test]
+    fn test_permanent_errors_are_not_retried() {
+        let handler = flaky(1, ErrorKind::PermissionDenied);
+        assert!(handler.read_bytes(0, 4).is_err());
+        assert_eq!(handler.stats().retries, 0);
+        assert_eq!(handler.stats().failures, 1);
+
+        assert_eq!(
+            MKImageError::NoSuchVolume(String::from("data")).class(),
+            ErrorClass::Permanent
+        );
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new(0, Duration::from_millis(50), Duration::from_millis(300));
+        assert_eq!(policy.attempts(), 1);
+        assert_eq!(policy.delay(0), Duration::from_millis(50));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(300));
+        assert_eq!(policy.delay(40), Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,250 @@
+use voxfs::ContentHasher;
+
+/// A SHA-256 hash that can be computed over data read in pieces, used for the content hashes of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_length: usize,
+    length: u64,
+}
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+impl Sha256 {
+    pub fn new() -> Self {
+        return Self {
+            state: INITIAL_STATE,
+            block: [0u8; 64],
+            block_length: 0,
+            length: 0,
+        };
+    }
+
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len() as u64);
+
+        while !bytes.is_empty() {
+            let taken = std::cmp::min(64 - self.block_length, bytes.len());
+            self.block[self.block_length..self.block_length + taken]
+                .copy_from_slice(&bytes[..taken]);
+            self.block_length += taken;
+            bytes = &bytes[taken..];
+
+            if self.block_length == 64 {
+                self.compress();
+                self.block_length = 0;
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; 32] {
+        let bit_length = self.length.wrapping_mul(8);
+
+        // A one bit, then zeroes up to the 8 bytes of the length at the end of a block
+        self.update(&[0x80]);
+
+        while self.block_length != 56 {
+            self.update(&[0]);
+        }
+
+        self.update(&bit_length.to_be_bytes());
+
+        let mut hash = [0u8; 32];
+
+        for (i, word) in self.state.iter().enumerate() {
+            hash[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+
+        return hash;
+    }
+
+    fn compress(&mut self) {
+        let mut schedule = [0u32; 64];
+
+        for (word, bytes) in schedule.iter_mut().zip(self.block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7)
+                ^ schedule[i - 15].rotate_right(18)
+                ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17)
+                ^ schedule[i - 2].rotate_right(19)
+                ^ (schedule[i - 2] >> 10);
+
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(ROUND_CONSTANTS[i])
+                .wrapping_add(schedule[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (word, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl ContentHasher for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        Sha256::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> [u8; 32] {
+        return Sha256::finish(*self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sha256;
+
+    fn hex(hash: [u8; 32]) -> String {
+        return hash.iter().map(|b| format!("{:02x}", b)).collect();
+    }
+
+    #[test]
+    fn test_vectors() {
+        assert_eq!(
+            hex(Sha256::new().finish()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        let mut abc = Sha256::new();
+        abc.update(b"abc");
+        assert_eq!(
+            hex(abc.finish()),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // Two blocks once padded
+        let mut long = Sha256::new();
+        long.update(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        assert_eq!(
+            hex(long.finish()),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_pieces() {
+        let bytes: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+
+        let mut whole = Sha256::new();
+        whole.update(&bytes);
+
+        let mut pieces = Sha256::new();
+        for chunk in bytes.chunks(37) {
+            pieces.update(chunk);
+        }
+
+        assert_eq!(whole.finish(), pieces.finish());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::container::is_container;
+use crate::error::MKImageError;
+use crate::handler::path_size;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io::Read;
+use voxfs::volumes::VolumeTable;
+use voxfs::{probe, DiskHandler, ProbeInfo};
+
+/// The bytes read from the start of a disk to look for signatures, enough for every format checked.
+const SIGNATURE_BYTES: u64 = 64 * 1024;
+
+// The ext2/3/4 super block is 1024 bytes in, its magic is 56 bytes into it
+const EXT_SUPER_BLOCK: usize = 1024;
+const EXT_MAGIC: [u8; 2] = [0x53, 0xef];
+const EXT_HAS_JOURNAL: u8 = 0x04;
+const EXT_INCOMPAT_EXTENTS: u8 = 0x40;
+
+/// A filesystem or other structure found at the start of a disk, which formatting over it would destroy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signature {
+    VoxFS(ProbeInfo),
+    /// A voxfs volume table, with the names of its volumes.
+    VolumeTable(Vec<String>),
+    /// A packed image made by pack-voxfs.
+    Container,
+    /// An ext2, ext3 or ext4 filesystem, with its version and label.
+    Ext {
+        version: u8,
+        label: String,
+    },
+    /// A FAT12, FAT16, FAT32 or exFAT filesystem, with its label if it has one.
+    Fat {
+        kind: String,
+        label: String,
+    },
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Signature::VoxFS(info) => write!(
+                f,
+                "a voxfs filesystem (version {}){}",
+                info.version(),
+                labelled(info.label())
+            ),
+            Signature::VolumeTable(names) => write!(
+                f,
+                "a voxfs volume table with {} volumes: {}",
+                names.len(),
+                names.join(", ")
+            ),
+            Signature::Container => write!(f, "a packed voxfs image"),
+            Signature::Ext { version, label } => {
+                write!(f, "an ext{} filesystem{}", version, labelled(label))
+            }
+            Signature::Fat { kind, label } => write!(f, "a {} filesystem{}", kind, labelled(label)),
+        };
+    }
+}
+
+fn labelled(label: &str) -> String {
+    return match label.is_empty() {
+        true => String::new(),
+        false => format!(" labelled {:?}", label),
+    };
+}
+
+/// Reads the start of the file or block device at the path and returns the signatures found.
+pub fn detect_signatures(path: &str) -> Result<Vec<Signature>, MKImageError> {
+    let size = path_size(path)?;
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => return Err(MKImageError::open(path, e)),
+    };
+
+    let mut bytes = Vec::new();
+
+    match file.take(SIGNATURE_BYTES).read_to_end(&mut bytes) {
+        Ok(_) => (),
+        Err(e) => return Err(MKImageError::io(&format!("read {}", path), e)),
+    }
+
+    return Ok(signatures(&bytes, size));
+}
+
+/// The signatures in the first bytes of a disk of `size` bytes.
+pub fn signatures(bytes: &[u8], size: u64) -> Vec<Signature> {
+    let mut found = Vec::new();
+    let start = StartOfDisk { bytes, size };
+
+    if let Some(info) = probe(&start) {
+        found.push(Signature::VoxFS(info));
+    }
+
+    if let Ok(table) = VolumeTable::read(&start) {
+        found.push(Signature::VolumeTable(
+            table
+                .volumes()
+                .iter()
+                .map(|v| v.name().to_string())
+                .collect(),
+        ));
+    }
+
+    if is_container(bytes) {
+        found.push(Signature::Container);
+    }
+
+    if let Some(ext) = ext_signature(bytes) {
+        found.push(ext);
+    }
+
+    if let Some(fat) = fat_signature(bytes) {
+        found.push(fat);
+    }
+
+    return found;
+}
+
+fn ext_signature(bytes: &[u8]) -> Option<Signature> {
+    let super_block = bytes.get(EXT_SUPER_BLOCK..EXT_SUPER_BLOCK + 1024)?;
+
+    if super_block[56..58] != EXT_MAGIC {
+        return None;
+    }
+
+    // ext4 added extents, ext3 a journal
+    let version = if super_block[0x60] & EXT_INCOMPAT_EXTENTS != 0 {
+        4
+    } else if super_block[0x5c] & EXT_HAS_JOURNAL != 0 {
+        3
+    } else {
+        2
+    };
+
+    return Some(Signature::Ext {
+        version,
+        label: text(&super_block[0x78..0x88]),
+    });
+}
+
+fn fat_signature(bytes: &[u8]) -> Option<Signature> {
+    let boot_sector = bytes.get(..512)?;
+
+    if &boot_sector[3..11] == b"EXFAT   " {
+        return Some(Signature::Fat {
+            kind: "exFAT".to_string(),
+            label: String::new(),
+        });
+    }
+
+    if boot_sector[510..512] != [0x55, 0xaa] {
+        return None;
+    }
+
+    // FAT12 and FAT16 keep the type and label earlier in the boot sector than FAT32
+    let (kind, label) = if boot_sector[82..87] == *b"FAT32" {
+        ("FAT32".to_string(), &boot_sector[71..82])
+    } else if boot_sector[54..57] == *b"FAT" {
+        (text(&boot_sector[54..62]), &boot_sector[43..54])
+    } else {
+        return None;
+    };
+
+    let label = match text(label) {
+        l if l == "NO NAME" => String::new(),
+        l => l,
+    };
+
+    return Some(Signature::Fat { kind, label });
+}
+
+/// A fixed width field padded with spaces or null bytes.
+fn text(bytes: &[u8]) -> String {
+    return String::from_utf8_lossy(bytes)
+        .trim_end_matches([' ', '\0'])
+        .to_string();
+}
+
+/// The bytes read from the start of a disk, which reports the size of the whole disk so the layouts found can be
+/// checked against it.
+struct StartOfDisk<'a> {
+    bytes: &'a [u8],
+    size: u64,
+}
+
+impl DiskHandler<MKImageError> for StartOfDisk<'_> {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MKImageError> {
+        return Err(MKImageError::OutOfBounds {
+            location,
+            amount: bytes.len() as u64,
+        });
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+        return match self
+            .bytes
+            .get(location as usize..location.saturating_add(amount) as usize)
+        {
+            Some(b) => Ok(b.to_vec()),
+            None => Err(MKImageError::OutOfBounds { location, amount }),
+        };
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MKImageError> {
+        return Err(MKImageError::OutOfBounds {
+            location: start,
+            amount: end.saturating_sub(start),
+        });
+    }
+
+    fn disk_size(&self) -> Result<u64, MKImageError> {
+        return Ok(self.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Manager;
+    use voxfs::{Disk, FormatOptions, MemoryDiskHandler};
+
+    #[test]
+    fn test_voxfs_signature() {
+        let mut handler = MemoryDiskHandler::new(4096 * 64);
+        let mut manager = Manager::new();
+        let options = FormatOptions::new().with_label("backups");
+
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options)
+            .unwrap()
+            .close()
+            .unwrap();
+
+        let bytes = handler.read_bytes(0, 4096).unwrap();
+        let found = signatures(&bytes, 4096 * 64);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].to_string(),
+            "a voxfs filesystem (version 2) labelled \"backups\""
+        );
+
+        // The layout doesn't fit a smaller disk
+        assert!(signatures(&bytes, 4096 * 8).is_empty());
+        assert!(signatures(&[0u8; 4096], 4096 * 64).is_empty());
+    }
+
+    #[test]
+    fn test_ext_signature() {
+        let mut bytes = vec![0u8; 4096];
+        bytes[1024 + 56..1024 + 58].copy_from_slice(&EXT_MAGIC);
+        bytes[1024 + 0x78..1024 + 0x7c].copy_from_slice(b"root");
+
+        assert_eq!(
+            signatures(&bytes, 1 << 20),
+            [Signature::Ext {
+                version: 2,
+                label: "root".to_string()
+            }]
+        );
+
+        bytes[1024 + 0x60] = EXT_INCOMPAT_EXTENTS;
+        assert_eq!(
+            signatures(&bytes, 1 << 20)[0].to_string(),
+            "an ext4 filesystem labelled \"root\""
+        );
+    }
+
+    #[test]
+    fn test_fat_signature() {
+        let mut bytes = vec![0u8; 512];
+        bytes[510] = 0x55;
+        bytes[511] = 0xaa;
+        assert!(signatures(&bytes, 1 << 20).is_empty());
+
+        bytes[71..82].copy_from_slice(b"NO NAME    ");
+        bytes[82..90].copy_from_slice(b"FAT32   ");
+        assert_eq!(
+            signatures(&bytes, 1 << 20)[0].to_string(),
+            "a FAT32 filesystem"
+        );
+
+        let mut bytes = vec![0u8; 512];
+        bytes[510] = 0x55;
+        bytes[511] = 0xaa;
+        bytes[43..54].copy_from_slice(b"CAMERA     ");
+        bytes[54..62].copy_from_slice(b"FAT16   ");
+        assert_eq!(
+            signatures(&bytes, 1 << 20),
+            [Signature::Fat {
+                kind: "FAT16".to_string(),
+                label: "CAMERA".to_string()
+            }]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use crate::{json_string, Crc32, MKImageError};
+use chrono::{Datelike, Timelike};
+use std::io::{Seek, SeekFrom, Write};
+use voxfs::{Disk, INode, TagQuery, Timestamp, VoxFSError};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// Version 1.0, enough for stored entries.
+const VERSION_NEEDED: u16 = 10;
+/// The names are UTF-8.
+const UTF8_FLAG: u16 = 0x0800;
+/// The entries are stored without compression.
+const STORED: u16 = 0;
+/// The offset of the CRC-32 in a local file header, it and the sizes after it are written once the contents
+/// have been read.
+const LOCAL_CRC_OFFSET: u64 = 14;
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The name of the entry listing the tags of each file with `ZipTagLayout::Metadata`.
+pub const ZIP_METADATA_NAME: &str = "voxfs-tags.json";
+
+/// How the tags of the exported files are kept in a zip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZipTagLayout {
+    /// Each file is stored in a folder named after each of its tags, untagged files are at the top level.
+    Folders,
+    /// Each file is stored once at the top level and `ZIP_METADATA_NAME` lists the tags of every file.
+    Metadata,
+}
+
+/// What was written by `export_zip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipSummary {
+    /// The number of files exported.
+    pub files: u64,
+    /// The number of entries in the zip, more than the files if a file has several tag folders.
+    pub entries: u64,
+    /// The files without contents, such as devices, that were left out.
+    pub skipped: Vec<String>,
+    /// The size in bytes of the zip.
+    pub size: u64,
+}
+
+/// An entry already written, kept for the central directory.
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    dos_time: u16,
+    dos_date: u16,
+    offset: u32,
+}
+
+/// Writes the files matching the query, or every file if there is none, to a zip archive that most systems can
+/// open without extra software. The files are stored uncompressed and read in pieces, and archived tags are left
+/// out. Files, archives and offsets are limited to 4 GiB and 65535 entries as zip64 isn't written.
+pub fn export_zip<W: Write + Seek>(
+    disk: &mut Disk<MKImageError>,
+    query: Option<&TagQuery>,
+    layout: ZipTagLayout,
+    writer: &mut W,
+) -> Result<ZipSummary, VoxFSError<MKImageError>> {
+    let mut inodes = match query {
+        Some(q) => disk.query_tags(q)?,
+        None => disk.list_inodes(),
+    };
+    inodes.sort_by_key(|inode| inode.name());
+
+    let start = tell(writer)?;
+    let mut entries = Vec::new();
+    let mut metadata = Vec::new();
+    let mut summary = ZipSummary {
+        files: 0,
+        entries: 0,
+        skipped: Vec::new(),
+        size: 0,
+    };
+
+    for inode in inodes.iter() {
+        if !inode.flags().file_type().has_contents() {
+            summary.skipped.push(inode.name());
+            continue;
+        }
+
+        let mut tags: Vec<String> = disk
+            .tags_for_inode(inode.index())?
+            .iter()
+            .filter(|tag| !tag.flags().archived())
+            .map(|tag| tag.name_string())
+            .collect();
+        tags.sort();
+
+        let names = match layout {
+            ZipTagLayout::Folders if !tags.is_empty() => tags
+                .iter()
+                .map(|tag| format!("{}/{}", tag, inode.name()))
+                .collect(),
+            _ => vec![inode.name()],
+        };
+
+        for name in names {
+            let offset = tell(writer)? - start;
+            entries.push(write_file(disk, inode, name, offset, writer)?);
+        }
+
+        if layout == ZipTagLayout::Metadata {
+            let tags: Vec<String> = tags.iter().map(|t| json_string(t)).collect();
+            metadata.push(format!(
+                "{{\"name\":{},\"tags\":[{}]}}",
+                json_string(&inode.name()),
+                tags.join(",")
+            ));
+        }
+
+        summary.files += 1;
+    }
+
+    if layout == ZipTagLayout::Metadata {
+        let contents = format!("{{\"files\":[{}]}}\n", metadata.join(","));
+        let offset = tell(writer)? - start;
+        let mut crc = Crc32::new();
+        crc.update(contents.as_bytes());
+
+        let (dos_time, dos_date) = dos_date_time(chrono::Utc::now());
+        let entry = Entry {
+            name: ZIP_METADATA_NAME.to_string(),
+            crc: crc.finish(),
+            size: contents.len() as u32,
+            dos_time,
+            dos_date,
+            offset: limit(offset)?,
+        };
+
+        write(writer, &local_header(&entry))?;
+        write(writer, contents.as_bytes())?;
+        entries.push(entry);
+    }
+
+    if entries.len() > u16::MAX as usize {
+        return Err(too_large("the zip would hold more than 65535 entries"));
+    }
+
+    let directory_start = tell(writer)? - start;
+
+    for entry in entries.iter() {
+        write(writer, &central_header(entry))?;
+    }
+
+    let directory_size = tell(writer)? - start - directory_start;
+
+    let mut end = Vec::with_capacity(22);
+    end.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    end.extend_from_slice(&[0; 4]); // This disk and the disk the directory starts on
+    end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    end.extend_from_slice(&limit(directory_size)?.to_le_bytes());
+    end.extend_from_slice(&limit(directory_start)?.to_le_bytes());
+    end.extend_from_slice(&[0; 2]); // No comment
+    write(writer, &end)?;
+
+    summary.entries = entries.len() as u64;
+    summary.size = tell(writer)? - start;
+
+    return Ok(summary);
+}
+
+/// Writes a file's local header and contents, filling in the CRC-32 and size once the contents have been read.
+fn write_file<W: Write + Seek>(
+    disk: &Disk<MKImageError>,
+    inode: &INode,
+    name: String,
+    offset: u64,
+    writer: &mut W,
+) -> Result<Entry, VoxFSError<MKImageError>> {
+    let (dos_time, dos_date) = dos_date_time(inode.modified_time());
+    let mut entry = Entry {
+        name,
+        crc: 0,
+        size: limit(inode.file_size())?,
+        dos_time,
+        dos_date,
+        offset: limit(offset)?,
+    };
+
+    let header_start = tell(writer)?;
+    write(writer, &local_header(&entry))?;
+
+    let mut handle = disk.open_file(inode.index())?;
+    let mut crc = Crc32::new();
+    let mut position = 0;
+
+    loop {
+        let bytes = disk.read_file_range(&mut handle, position, CHUNK_SIZE)?;
+
+        if bytes.is_empty() {
+            break;
+        }
+
+        position += bytes.len() as u64;
+        crc.update(&bytes);
+        write(writer, &bytes)?;
+    }
+
+    disk.close_file(handle);
+
+    entry.crc = crc.finish();
+    entry.size = limit(position)?;
+
+    let end = tell(writer)?;
+    seek(writer, header_start + LOCAL_CRC_OFFSET)?;
+    write(writer, &entry.crc.to_le_bytes())?;
+    write(writer, &entry.size.to_le_bytes())?;
+    write(writer, &entry.size.to_le_bytes())?;
+    seek(writer, end)?;
+
+    return Ok(entry);
+}
+
+fn local_header(entry: &Entry) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(30 + entry.name.len());
+    bytes.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+    bytes.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+    bytes.extend_from_slice(&UTF8_FLAG.to_le_bytes());
+    bytes.extend_from_slice(&STORED.to_le_bytes());
+    bytes.extend_from_slice(&entry.dos_time.to_le_bytes());
+    bytes.extend_from_slice(&entry.dos_date.to_le_bytes());
+    bytes.extend_from_slice(&entry.crc.to_le_bytes());
+    bytes.extend_from_slice(&entry.size.to_le_bytes()); // Compressed size
+    bytes.extend_from_slice(&entry.size.to_le_bytes());
+    bytes.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&[0; 2]); // No extra field
+    bytes.extend_from_slice(entry.name.as_bytes());
+
+    return bytes;
+}
+
+fn central_header(entry: &Entry) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(46 + entry.name.len());
+    bytes.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+    bytes.extend_from_slice(&VERSION_NEEDED.to_le_bytes()); // Version made by
+    bytes.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+    bytes.extend_from_slice(&UTF8_FLAG.to_le_bytes());
+    bytes.extend_from_slice(&STORED.to_le_bytes());
+    bytes.extend_from_slice(&entry.dos_time.to_le_bytes());
+    bytes.extend_from_slice(&entry.dos_date.to_le_bytes());
+    bytes.extend_from_slice(&entry.crc.to_le_bytes());
+    bytes.extend_from_slice(&entry.size.to_le_bytes()); // Compressed size
+    bytes.extend_from_slice(&entry.size.to_le_bytes());
+    bytes.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    // No extra field or comment, the first disk, and no internal or external attributes
+    bytes.extend_from_slice(&[0; 12]);
+    bytes.extend_from_slice(&entry.offset.to_le_bytes());
+    bytes.extend_from_slice(entry.name.as_bytes());
+
+    return bytes;
+}
+
+/// The time and date in the MS-DOS format zip uses, which starts in 1980 and has a resolution of 2 seconds.
+fn dos_date_time(time: Timestamp) -> (u16, u16) {
+    if time.year() < 1980 {
+        return (0, (1 << 5) | 1); // 1980-01-01
+    }
+
+    let dos_time = (time.hour() << 11) | (time.minute() << 5) | (time.second() / 2);
+    let dos_date = ((time.year() as u32 - 1980).min(127) << 9) | (time.month() << 5) | time.day();
+
+    return (dos_time as u16, dos_date as u16);
+}
+
+/// Checks a size or offset fits in the 32 bits zip stores it in.
+fn limit(n: u64) -> Result<u32, VoxFSError<MKImageError>> {
+    if n > u32::MAX as u64 {
+        return Err(too_large("the zip would be larger than 4 GiB"));
+    }
+
+    return Ok(n as u32);
+}
+
+fn too_large(reason: &str) -> VoxFSError<MKImageError> {
+    return VoxFSError::DiskError(MKImageError::io(
+        "write the zip",
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, reason),
+    ));
+}
+
+fn write<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), VoxFSError<MKImageError>> {
+    return writer
+        .write_all(bytes)
+        .map_err(|e| VoxFSError::DiskError(MKImageError::io("write the zip", e)));
+}
+
+fn seek<W: Seek>(writer: &mut W, position: u64) -> Result<(), VoxFSError<MKImageError>> {
+    return writer
+        .seek(SeekFrom::Start(position))
+        .map(|_| ())
+        .map_err(|e| VoxFSError::DiskError(MKImageError::io("seek in the zip", e)));
+}
+
+fn tell<W: Seek>(writer: &mut W) -> Result<u64, VoxFSError<MKImageError>> {
+    return writer
+        .stream_position()
+        .map_err(|e| VoxFSError::DiskError(MKImageError::io("seek in the zip", e)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Manager;
+    use std::io::Cursor;
+    use voxfs::{DiskHandler, INodeFlags, MemoryDiskError, MemoryDiskHandler, TagFlags};
+
+    /// Lets a memory disk stand in for an image in the tests.
+    struct Image(MemoryDiskHandler);
+
+    impl DiskHandler<MKImageError> for Image {
+        fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MKImageError> {
+            return self.0.write_bytes(bytes, location).map_err(convert);
+        }
+
+        fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MKImageError> {
+            return self.0.read_bytes(location, amount).map_err(convert);
+        }
+
+        fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MKImageError> {
+            return self.0.zero_range(start, end).map_err(convert);
+        }
+
+        fn disk_size(&self) -> Result<u64, MKImageError> {
+            return self.0.disk_size().map_err(convert);
+        }
+    }
+
+    fn convert(_: MemoryDiskError) -> MKImageError {
+        return MKImageError::OutOfBounds {
+            location: 0,
+            amount: 0,
+        };
+    }
+
+    /// Reads the name and contents of every entry through the central directory, checking each CRC-32.
+    fn read_entries(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let u16_at = |i: usize| u16::from_le_bytes([zip[i], zip[i + 1]]) as usize;
+        let u32_at = |i: usize| u32::from_le_bytes([zip[i], zip[i + 1], zip[i + 2], zip[i + 3]]);
+
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(end), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+
+        let mut position = u32_at(end + 16) as usize;
+        let mut entries = Vec::new();
+
+        for _ in 0..u16_at(end + 10) {
+            assert_eq!(u32_at(position), CENTRAL_HEADER_SIGNATURE);
+            let crc = u32_at(position + 16);
+            let size = u32_at(position + 24) as usize;
+            let name_length = u16_at(position + 28);
+            let offset = u32_at(position + 42) as usize;
+            let name = String::from_utf8(zip[position + 46..position + 46 + name_length].to_vec())
+                .unwrap();
+
+            assert_eq!(u32_at(offset), LOCAL_HEADER_SIGNATURE);
+            assert_eq!(u32_at(offset + 14), crc);
+            assert_eq!(u32_at(offset + 22) as usize, size);
+
+            let start = offset + 30 + u16_at(offset + 26);
+            let contents = zip[start..start + size].to_vec();
+            let mut check = Crc32::new();
+            check.update(&contents);
+            assert_eq!(check.finish(), crc);
+
+            entries.push((name, contents));
+            position += 46 + name_length;
+        }
+
+        return entries;
+    }
+
+    #[test]
+    fn test_export() {
+        let mut handler = Image(MemoryDiskHandler::new(4096 * 200));
+        let mut manager = Manager::new();
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+        let work = disk.create_new_tag("work", TagFlags::default()).unwrap();
+        let photos = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+        let hidden = disk.create_new_tag("hidden", TagFlags::default()).unwrap();
+
+        let large: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let report = disk
+            .create_new_file("report.txt", INodeFlags::default(), b"quarterly".to_vec())
+            .unwrap();
+        let scan = disk
+            .create_new_file("scan.bin", INodeFlags::default(), large.clone())
+            .unwrap();
+        disk.create_new_file("notes.txt", INodeFlags::default(), b"untagged".to_vec())
+            .unwrap();
+
+        disk.apply_tag(work.index(), report.index()).unwrap();
+        disk.apply_tag(work.index(), scan.index()).unwrap();
+        disk.apply_tag(photos.index(), scan.index()).unwrap();
+        disk.apply_tag(hidden.index(), report.index()).unwrap();
+        disk.archive_tag(hidden.index()).unwrap();
+
+        let mut zip = Cursor::new(Vec::new());
+        let summary = export_zip(&mut disk, None, ZipTagLayout::Folders, &mut zip).unwrap();
+        assert_eq!(summary.files, 3);
+        assert_eq!(summary.entries, 4);
+        assert_eq!(summary.size, zip.get_ref().len() as u64);
+
+        let entries = read_entries(zip.get_ref());
+        let names: Vec<&str> = entries.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "notes.txt",
+                "work/report.txt",
+                "photos/scan.bin",
+                "work/scan.bin"
+            ]
+        );
+        assert_eq!(entries[1].1, b"quarterly");
+        assert_eq!(entries[2].1, large);
+
+        // Only the files matching the query, with their tags listed in the metadata
+        let query = TagQuery::parse("work").unwrap();
+        let mut zip = Cursor::new(Vec::new());
+        let summary =
+            export_zip(&mut disk, Some(&query), ZipTagLayout::Metadata, &mut zip).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.entries, 3);
+
+        let entries = read_entries(zip.get_ref());
+        assert_eq!(entries[0].0, "report.txt");
+        assert_eq!(entries[1].0, "scan.bin");
+        assert_eq!(entries[2].0, ZIP_METADATA_NAME);
+        assert_eq!(
+            String::from_utf8(entries[2].1.clone()).unwrap(),
+            "{\"files\":[{\"name\":\"report.txt\",\"tags\":[\"work\"]},\
+             {\"name\":\"scan.bin\",\"tags\":[\"photos\",\"work\"]}]}\n"
+        );
+    }
+
+    #[test]
+    fn test_dos_date_time() {
+        let time = chrono::DateTime::parse_from_rfc3339("2024-03-05T13:45:31Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        assert_eq!(
+            dos_date_time(time),
+            ((13 << 11) | (45 << 5) | 15, (44 << 9) | (3 << 5) | 5)
+        );
+
+        let early = chrono::DateTime::parse_from_rfc3339("1970-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(dos_date_time(early), (0, (1 << 5) | 1));
+    }
+}
@@ -0,0 +1,211 @@
+extern crate voxfs;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::time::{Duration, Instant};
+use voxfs::{Disk, INodeFlags, TagFlags};
+
+#[path = "../tests/common.rs"]
+mod common;
+use common::*;
+
+const BLOCK_SIZE: usize = 4096;
+
+/// Formats a new image of the given size and returns its bytes.
+fn formatted_image(disk_size: usize) -> Vec<u8> {
+    let mut handler = Handler::new(disk_size);
+    let mut manager = Manager::new();
+
+    Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    return handler.dump_disk();
+}
+
+/// Formats a new image and fills it with files of the given size.
+fn image_with_files(disk_size: usize, file_count: usize, file_size: usize) -> Vec<u8> {
+    let mut handler = Handler::new(disk_size);
+    let mut manager = Manager::new();
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+        for i in 0..file_count {
+            disk.create_new_file(
+                &format!("file_{}", i),
+                INodeFlags::read_write(),
+                vec![0xab; file_size],
+            )
+            .unwrap();
+        }
+    }
+
+    return handler.dump_disk();
+}
+
+/// Builds an image where every other single block file has been deleted, leaving the free space fragmented.
+fn fragmented_image(disk_size: usize, file_count: usize) -> Vec<u8> {
+    let mut handler = Handler::new(disk_size);
+    let mut manager = Manager::new();
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        let mut indices = Vec::new();
+
+        for i in 0..file_count {
+            let inode = disk
+                .create_new_file(
+                    &format!("file_{}", i),
+                    INodeFlags::read_write(),
+                    vec![0xcd; BLOCK_SIZE],
+                )
+                .unwrap();
+
+            indices.push(inode.index());
+        }
+
+        for index in indices.into_iter().step_by(2) {
+            disk.delete_file(index).unwrap();
+        }
+    }
+
+    return handler.dump_disk();
+}
+
+fn bench_create_new_file(c: &mut Criterion) {
+    let image = formatted_image(BLOCK_SIZE * 2048);
+    let mut group = c.benchmark_group("create_new_file");
+
+    for size in [1024usize, 64 * 1024, 1024 * 1024].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            let contents = vec![0xefu8; size];
+
+            b.iter_custom(|iterations| {
+                let mut total = Duration::from_secs(0);
+
+                for _ in 0..iterations {
+                    let mut handler = Handler {
+                        disk: image.clone(),
+                    };
+                    let mut manager = Manager::new();
+                    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+                    let start = Instant::now();
+                    disk.create_new_file("bench_file", INodeFlags::read_write(), contents.clone())
+                        .unwrap();
+                    total += start.elapsed();
+                }
+
+                total
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_apply_tag(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_tag");
+    group.sample_size(10);
+
+    for file_count in [1000usize, 4000].iter() {
+        let image = image_with_files(BLOCK_SIZE * 16384, *file_count, 64);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(file_count),
+            file_count,
+            |b, _| {
+                b.iter_custom(|iterations| {
+                    let mut total = Duration::from_secs(0);
+
+                    for _ in 0..iterations {
+                        let mut handler = Handler {
+                            disk: image.clone(),
+                        };
+                        let mut manager = Manager::new();
+                        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+                        let tag = disk
+                            .create_new_tag("bench_tag", TagFlags::read_write())
+                            .unwrap();
+                        let inodes = disk.list_inodes();
+
+                        let start = Instant::now();
+                        for inode in inodes {
+                            disk.apply_tag(tag.index(), inode.index()).unwrap();
+                        }
+                        total += start.elapsed();
+                    }
+
+                    total
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_find_blocks_fragmented(c: &mut Criterion) {
+    // Blocks are found through create_new_file, a file spanning many holes exercises the extent search.
+    let image = fragmented_image(BLOCK_SIZE * 4096, 2000);
+    let contents = vec![0x12u8; BLOCK_SIZE * 64];
+
+    c.bench_function("find_blocks_fragmented", |b| {
+        b.iter_custom(|iterations| {
+            let mut total = Duration::from_secs(0);
+
+            for _ in 0..iterations {
+                let mut handler = Handler {
+                    disk: image.clone(),
+                };
+                let mut manager = Manager::new();
+                let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+                let start = Instant::now();
+                disk.create_new_file(
+                    "fragmented_file",
+                    INodeFlags::read_write(),
+                    contents.clone(),
+                )
+                .unwrap();
+                total += start.elapsed();
+            }
+
+            total
+        });
+    });
+}
+
+fn bench_open_disk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("open_disk");
+    group.sample_size(10);
+
+    for (disk_size, file_count) in
+        [(BLOCK_SIZE * 4096, 1000usize), (BLOCK_SIZE * 65536, 8000)].iter()
+    {
+        let image = image_with_files(*disk_size, *file_count, 64);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(disk_size),
+            &image,
+            |b, image| {
+                let mut handler = Handler {
+                    disk: image.clone(),
+                };
+
+                b.iter(|| {
+                    let mut manager = Manager::new();
+                    Disk::open_disk(&mut handler, &mut manager).unwrap();
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_create_new_file,
+    bench_apply_tag,
+    bench_find_blocks_fragmented,
+    bench_open_disk
+);
+criterion_main!(benches);
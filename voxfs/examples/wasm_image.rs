@@ -0,0 +1,133 @@
+//! Inspects a voxfs image from JavaScript. The image is passed in as an `ArrayBuffer` and is accessed in place
+//! through an `ArrayBufferHandler`, with timestamps taken from the JavaScript clock.
+//!
+//! Build with `cargo build --example wasm_image --target wasm32-unknown-unknown` and generate the bindings with
+//! `wasm-bindgen`. On other targets this example is empty.
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use chrono::{DateTime, TimeZone, Utc};
+    use js_sys::{ArrayBuffer, Date, Uint8Array};
+    use voxfs::{Disk, DiskHandler, OSManager, VoxFSErrorConvertible};
+    use wasm_bindgen::prelude::*;
+
+    /// An `OSManager` that reads the time from the JavaScript `Date` clock.
+    #[derive(Copy, Clone, Debug)]
+    pub struct JsManager {}
+
+    impl OSManager for JsManager {
+        fn current_time(&self) -> DateTime<Utc> {
+            return Utc.timestamp_millis(Date::now() as i64);
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ArrayBufferError {
+        OutOfBounds,
+    }
+
+    impl VoxFSErrorConvertible for ArrayBufferError {}
+
+    impl core::fmt::Display for ArrayBufferError {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            return match self {
+                ArrayBufferError::OutOfBounds => write!(f, "Out of bounds disk access"),
+            };
+        }
+    }
+
+    /// A disk handler that reads and writes a JavaScript `ArrayBuffer` without copying the whole image.
+    pub struct ArrayBufferHandler {
+        view: Uint8Array,
+    }
+
+    impl ArrayBufferHandler {
+        pub fn new(buffer: &ArrayBuffer) -> Self {
+            return Self {
+                view: Uint8Array::new(buffer),
+            };
+        }
+
+        /// Returns the view of start..end if it lies within the buffer.
+        fn checked_view(&self, start: u64, end: u64) -> Result<Uint8Array, ArrayBufferError> {
+            if start > end || end > self.view.length() as u64 {
+                return Err(ArrayBufferError::OutOfBounds);
+            }
+
+            return Ok(self.view.subarray(start as u32, end as u32));
+        }
+    }
+
+    impl DiskHandler<ArrayBufferError> for ArrayBufferHandler {
+        fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), ArrayBufferError> {
+            let end = match location.checked_add(bytes.len() as u64) {
+                Some(e) => e,
+                None => return Err(ArrayBufferError::OutOfBounds),
+            };
+
+            self.checked_view(location, end)?.copy_from(bytes);
+
+            return Ok(());
+        }
+
+        fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, ArrayBufferError> {
+            let end = match location.checked_add(amount) {
+                Some(e) => e,
+                None => return Err(ArrayBufferError::OutOfBounds),
+            };
+
+            return Ok(self.checked_view(location, end)?.to_vec());
+        }
+
+        fn zero_range(&mut self, start: u64, end: u64) -> Result<(), ArrayBufferError> {
+            self.checked_view(start, end)?
+                .fill(0, 0, (end - start) as u32);
+
+            return Ok(());
+        }
+
+        fn disk_size(&self) -> Result<u64, ArrayBufferError> {
+            return Ok(self.view.length() as u64);
+        }
+    }
+
+    /// Opens the image in a buffer and returns a description of its files and tags.
+    #[wasm_bindgen]
+    pub fn describe_image(buffer: &ArrayBuffer) -> Result<String, JsValue> {
+        let mut handler = ArrayBufferHandler::new(buffer);
+        let mut manager = JsManager {};
+
+        let disk = match Disk::open_disk(&mut handler, &mut manager) {
+            Ok(d) => d,
+            Err(e) => return Err(JsValue::from_str(&format!("{}", e))),
+        };
+
+        let info = disk.disk_info();
+        let mut description = format!(
+            "Block size: {}\nFiles: {}\nTags: {}\nFree blocks: {}\n",
+            info.block_size(),
+            info.number_of_files(),
+            info.number_of_tags(),
+            info.free_block_count()
+        );
+
+        for tag in disk.list_tags() {
+            let members = match disk.list_nodes_with_tag(tag.index()) {
+                Ok(m) => m,
+                Err(e) => return Err(JsValue::from_str(&format!("{}", e))),
+            };
+
+            description.push_str(&format!("\n[{}]\n", tag.name_string()));
+
+            for inode in members {
+                description.push_str(&format!(
+                    "  {} ({} bytes)\n",
+                    inode.name(),
+                    inode.file_size()
+                ));
+            }
+        }
+
+        return Ok(description);
+    }
+}
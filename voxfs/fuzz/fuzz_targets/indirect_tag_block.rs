@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use voxfs::{ByteSerializable, IndirectTagBlock};
+
+fuzz_target!(|data: &[u8]| {
+    // Anything that parses must serialize back to a block that parses to the same value
+    if let Some(parsed) = IndirectTagBlock::from_bytes(data) {
+        let bytes = parsed.to_bytes();
+        assert!(IndirectTagBlock::from_bytes(&bytes).is_some());
+    }
+});
@@ -0,0 +1,25 @@
+#![no_main]
+use chrono::{DateTime, Utc};
+use libfuzzer_sys::fuzz_target;
+use voxfs::{Disk, MemoryDiskHandler, OSManager};
+
+#[derive(Debug)]
+struct Manager {}
+
+impl OSManager for Manager {
+    fn current_time(&self) -> DateTime<Utc> {
+        return Utc::now();
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut handler = MemoryDiskHandler::from_bytes(data.to_vec());
+    let mut manager = Manager {};
+
+    // Opening may fail but it must never panic
+    if let Ok(disk) = Disk::open_disk(&mut handler, &mut manager) {
+        let _ = disk.list_tags();
+        let _ = disk.list_inodes();
+        let _ = disk.disk_info();
+    };
+});
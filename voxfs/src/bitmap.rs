@@ -35,12 +35,19 @@ impl BitMap {
 
     pub fn find_next_0_index_up_to(&self, index: usize) -> Option<usize> {
         for (i, val) in self.vc.iter().enumerate() {
-            if i == index {
+            if i * 64 >= index {
                 break;
             }
 
             if *val < u64::MAX {
-                return Some(i * 64 + rightmost_unset_bit(*val));
+                let found = i * 64 + rightmost_unset_bit(*val);
+
+                // Every bit before the first free one is set so there is none below the index
+                if found >= index {
+                    return None;
+                }
+
+                return Some(found);
             }
         }
 
@@ -87,7 +94,7 @@ impl BitMap {
         if value {
             self.vc[array_index] |= 1 << bit;
         } else {
-            self.vc[array_index] ^= 1 << bit;
+            self.vc[array_index] &= !(1 << bit);
         }
 
         return true;
@@ -180,7 +187,7 @@ impl BitMap {
 
     /// Count the number of zeroes up to an index but not including said index. Useful for when a size that is not a multiple of 64 is needed. Returns none if the index was greater than the length.
     pub fn count_zeros_up_to(&self, index: usize) -> Option<usize> {
-        if index >= self.len() {
+        if index > self.len() {
             return None;
         }
 
@@ -252,6 +259,32 @@ mod tests {
         assert_eq!(map.bit_at(342).unwrap(), false);
     }
 
+    #[test]
+    fn test_bit_clear_twice() {
+        let mut map = BitMap::new(1024);
+
+        assert!(map.set_bit(5, false));
+        assert_eq!(map.bit_at(5).unwrap(), false); // clearing a clear bit leaves it clear
+        assert!(map.set_bit(5, true));
+        assert!(map.set_bit(5, false));
+        assert!(map.set_bit(5, false));
+        assert_eq!(map.bit_at(5).unwrap(), false);
+    }
+
+    #[test]
+    fn test_find_next_0_up_to() {
+        let mut map = BitMap::new(1024);
+
+        for i in 0..10 {
+            assert!(map.set_bit(i, true));
+        }
+
+        assert_eq!(map.find_next_0_index_up_to(11), Some(10));
+        // The free bits are in the same word as the limit but past it
+        assert_eq!(map.find_next_0_index_up_to(10), None);
+        assert_eq!(map.find_next_0_index_up_to(0), None);
+    }
+
     #[test]
     fn test_flatten_bool() {
         let mut map = BitMap::new(1024);
@@ -448,4 +481,47 @@ mod tests {
 
         assert_eq!(map.count_zeros_up_to(512).unwrap(), 511);
     }
+
+    #[test]
+    fn test_count_zeros_up_to_len() {
+        let mut map = BitMap::new(1024);
+
+        map.set_all(false);
+        map.set_bit(443, true);
+
+        assert_eq!(map.count_zeros_up_to(1024).unwrap(), 1023);
+        assert_eq!(map.count_zeros_up_to(1025), None);
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn bitmap_round_trip(vc in proptest::collection::vec(any::<u64>(), 0..64)) {
+                let map = BitMap { vc };
+                let parsed = BitMap::from_bytes(&map.as_bytes());
+
+                prop_assert_eq!(&parsed, &map);
+                prop_assert_eq!(parsed.as_bytes(), map.as_bytes());
+                prop_assert_eq!(map.count_ones() + map.count_zeros(), map.len());
+            }
+
+            #[test]
+            fn bitmap_set_bits(size in 1..2048usize, bits in proptest::collection::vec(any::<prop::sample::Index>(), 0..64)) {
+                let mut map = BitMap::new(size);
+
+                for bit in bits.iter() {
+                    prop_assert!(map.set_bit(bit.index(size), true));
+                }
+
+                let parsed = BitMap::from_bytes(&map.as_bytes());
+
+                for bit in bits.iter() {
+                    prop_assert_eq!(parsed.bit_at(bit.index(size)), Some(true));
+                }
+            }
+        }
+    }
 }
@@ -35,3 +35,8 @@ pub trait Checksum {
         return sum == 0;
     }
 }
+
+/// A record that can be parsed without checking its checksum, for disks opened with `Integrity::None`.
+pub(crate) trait UnverifiedRecord: Sized {
+    fn from_bytes_unverified(bytes: &[u8]) -> Option<Self>;
+}
@@ -0,0 +1,124 @@
+use alloc::collections::BTreeMap;
+
+/// The number of times a block was read from and written to the disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockAccess {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+impl BlockAccess {
+    pub fn total(&self) -> u64 {
+        return self.reads + self.writes;
+    }
+}
+
+/// Counts of the reads and writes sent to the disk handler for each block since the disk was opened.
+/// Blocks are numbered from the start of the disk, so the super block is block 0 and the numbers can be
+/// compared with the layout in the super block. Reads answered by the block cache are not counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessHeatmap {
+    block_size: u64,
+    // Only the blocks accessed at least once are stored.
+    blocks: BTreeMap<u64, BlockAccess>,
+}
+
+impl AccessHeatmap {
+    pub(crate) fn new(block_size: u64) -> Self {
+        return Self {
+            block_size,
+            blocks: BTreeMap::new(),
+        };
+    }
+
+    pub fn block_size(&self) -> u64 {
+        return self.block_size;
+    }
+
+    /// The accesses of a block, zero for a block that wasn't accessed.
+    pub fn block(&self, index: u64) -> BlockAccess {
+        return self.blocks.get(&index).copied().unwrap_or_default();
+    }
+
+    /// The blocks accessed at least once in order of their numbers.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, BlockAccess)> + '_ {
+        return self.blocks.iter().map(|(index, access)| (*index, *access));
+    }
+
+    /// The largest number of accesses of any one block.
+    pub fn max_accesses(&self) -> u64 {
+        return self
+            .blocks
+            .values()
+            .map(|access| access.total())
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// The number of accesses of the blocks from start to end, INCLUSIVE at both ends.
+    pub fn range_accesses(&self, start: u64, end: u64) -> BlockAccess {
+        let mut total = BlockAccess::default();
+
+        for access in self.blocks.range(start..=end).map(|(_, a)| a) {
+            total.reads += access.reads;
+            total.writes += access.writes;
+        }
+
+        return total;
+    }
+
+    pub(crate) fn record_read(&mut self, address: u64, length: u64) {
+        for index in self.touched_blocks(address, length) {
+            self.blocks.entry(index).or_default().reads += 1;
+        }
+    }
+
+    pub(crate) fn record_write(&mut self, address: u64, length: u64) {
+        for index in self.touched_blocks(address, length) {
+            self.blocks.entry(index).or_default().writes += 1;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    fn touched_blocks(&self, address: u64, length: u64) -> core::ops::Range<u64> {
+        if length == 0 {
+            return 0..0;
+        }
+
+        return address / self.block_size..(address + length - 1) / self.block_size + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap_counts() {
+        let mut heatmap = AccessHeatmap::new(512);
+
+        heatmap.record_read(0, 512);
+        heatmap.record_read(100, 10);
+        heatmap.record_write(1000, 100); // Spans blocks 1 and 2
+        heatmap.record_write(4096, 0);
+
+        assert_eq!(
+            heatmap.block(0),
+            BlockAccess {
+                reads: 2,
+                writes: 0
+            }
+        );
+        assert_eq!(heatmap.block(2).writes, 1);
+        assert_eq!(heatmap.block(8), BlockAccess::default());
+        assert_eq!(heatmap.iter().count(), 3);
+        assert_eq!(heatmap.max_accesses(), 2);
+        assert_eq!(heatmap.range_accesses(1, 5).writes, 2);
+
+        heatmap.clear();
+        assert_eq!(heatmap.max_accesses(), 0);
+    }
+}
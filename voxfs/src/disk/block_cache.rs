@@ -0,0 +1,137 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// A least recently used cache of data blocks, keyed by data block index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockCache {
+    capacity: usize,
+    tick: u64,
+    // The block index mapped to the tick it was last used and its contents.
+    blocks: BTreeMap<u64, (u64, Vec<u8>)>,
+}
+
+impl BlockCache {
+    /// Constructs a cache that holds up to a number of blocks. A capacity of 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        return Self {
+            capacity,
+            tick: 0,
+            blocks: BTreeMap::new(),
+        };
+    }
+
+    pub fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+
+    /// Changes the capacity, evicting the least recently used blocks if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+
+        while self.blocks.len() > self.capacity {
+            self.evict();
+        }
+    }
+
+    pub fn contains(&self, index: u64) -> bool {
+        return self.blocks.contains_key(&index);
+    }
+
+    /// Returns a copy of a block's contents if it is cached, marking it as recently used.
+    pub fn get(&mut self, index: u64) -> Option<Vec<u8>> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        return match self.blocks.get_mut(&index) {
+            Some((last_used, contents)) => {
+                *last_used = tick;
+                Some(contents.clone())
+            }
+            None => None,
+        };
+    }
+
+    /// Stores a block's contents, evicting the least recently used block if the cache is full.
+    pub fn insert(&mut self, index: u64, contents: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.blocks.contains_key(&index) && self.blocks.len() >= self.capacity {
+            self.evict();
+        }
+
+        self.tick += 1;
+        self.blocks.insert(index, (self.tick, contents));
+    }
+
+    /// Removes the blocks between two indexes, INCLUSIVE at both ends.
+    pub fn invalidate_range(&mut self, start: u64, end: u64) {
+        let indexes: Vec<u64> = self.blocks.range(start..=end).map(|(i, _)| *i).collect();
+
+        for index in indexes {
+            self.blocks.remove(&index);
+        }
+    }
+
+    fn evict(&mut self) {
+        let oldest = self
+            .blocks
+            .iter()
+            .min_by_key(|(_, (last_used, _))| *last_used)
+            .map(|(i, _)| *i);
+
+        if let Some(index) = oldest {
+            self.blocks.remove(&index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = BlockCache::new(2);
+
+        cache.insert(1, vec![1]);
+        cache.insert(2, vec![2]);
+        assert_eq!(cache.get(1), Some(vec![1]));
+
+        cache.insert(3, vec![3]);
+        assert!(cache.contains(1));
+        assert!(!cache.contains(2));
+        assert!(cache.contains(3));
+    }
+
+    #[test]
+    fn test_invalidate_range() {
+        let mut cache = BlockCache::new(8);
+
+        for i in 0..5 {
+            cache.insert(i, vec![i as u8]);
+        }
+
+        cache.invalidate_range(1, 3);
+        assert_eq!(cache.get(0), Some(vec![0]));
+        assert_eq!(cache.get(2), None);
+        assert!(!cache.contains(1) && !cache.contains(3));
+        assert!(cache.contains(4));
+    }
+
+    #[test]
+    fn test_disabled_and_shrunk() {
+        let mut cache = BlockCache::new(0);
+        cache.insert(1, vec![1]);
+        assert!(!cache.contains(1));
+
+        cache.set_capacity(3);
+        cache.insert(1, vec![1]);
+        cache.insert(2, vec![2]);
+        cache.insert(3, vec![3]);
+        cache.set_capacity(1);
+        assert!(!cache.contains(1) && !cache.contains(2));
+        assert!(cache.contains(3));
+    }
+}
@@ -0,0 +1,103 @@
+/// How many slots of a table, or blocks of the data region, are in use out of the number the disk was formatted with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Usage {
+    used: u64,
+    total: u64,
+}
+
+impl Usage {
+    pub fn new(used: u64, total: u64) -> Self {
+        return Self { used, total };
+    }
+
+    #[inline]
+    pub fn used(&self) -> u64 {
+        return self.used;
+    }
+
+    #[inline]
+    pub fn total(&self) -> u64 {
+        return self.total;
+    }
+
+    #[inline]
+    pub fn free(&self) -> u64 {
+        return self.total.saturating_sub(self.used);
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        return self.used >= self.total;
+    }
+
+    /// The percentage in use rounded down, so only a full table reports 100.
+    pub fn percent_used(&self) -> u64 {
+        if self.total == 0 {
+            return 100;
+        }
+
+        return self.used * 100 / self.total;
+    }
+}
+
+/// How full each fixed size part of a disk is, since any of them filling stops new files or tags being created.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CapacityReport {
+    inodes: Usage,
+    tags: Usage,
+    blocks: Usage,
+}
+
+impl CapacityReport {
+    pub(crate) fn new(inodes: Usage, tags: Usage, blocks: Usage) -> Self {
+        return Self {
+            inodes,
+            tags,
+            blocks,
+        };
+    }
+
+    #[inline]
+    pub fn inodes(&self) -> Usage {
+        return self.inodes;
+    }
+
+    /// The root tag counts as a used slot.
+    #[inline]
+    pub fn tags(&self) -> Usage {
+        return self.tags;
+    }
+
+    /// The data blocks, including the indirect blocks of inodes and tags.
+    #[inline]
+    pub fn blocks(&self) -> Usage {
+        return self.blocks;
+    }
+
+    /// Whether any of the tables or the data region is at least the given percentage full.
+    pub fn any_above(&self, percent: u64) -> bool {
+        return [self.inodes, self.tags, self.blocks]
+            .iter()
+            .any(|usage| usage.percent_used() >= percent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage() {
+        let usage = Usage::new(199, 200);
+        assert_eq!(usage.free(), 1);
+        assert_eq!(usage.percent_used(), 99);
+        assert!(!usage.is_full());
+
+        assert!(Usage::new(200, 200).is_full());
+        assert_eq!(Usage::new(0, 0).percent_used(), 100);
+
+        let report = CapacityReport::new(Usage::new(1, 10), usage, Usage::new(0, 10));
+        assert!(report.any_above(90));
+        assert!(!report.any_above(100));
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The indirect blocks chained from a file's inode or from a tag and how full they are. The entries are the
+/// extents of a file or the members of a tag held in the indirect blocks rather than in the inode or tag itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainUsage {
+    index: u64,
+    name: String,
+    indirect_blocks: u64,
+    entries: u64,
+    entries_per_block: u64,
+}
+
+impl ChainUsage {
+    pub(crate) fn new(
+        index: u64,
+        name: String,
+        indirect_blocks: u64,
+        entries: u64,
+        entries_per_block: u64,
+    ) -> Self {
+        return Self {
+            index,
+            name,
+            indirect_blocks,
+            entries,
+            entries_per_block,
+        };
+    }
+
+    /// The index of the inode or tag.
+    #[inline]
+    pub fn index(&self) -> u64 {
+        return self.index;
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    #[inline]
+    pub fn indirect_blocks(&self) -> u64 {
+        return self.indirect_blocks;
+    }
+
+    #[inline]
+    pub fn entries(&self) -> u64 {
+        return self.entries;
+    }
+
+    /// The average percentage of each indirect block's slots in use, rounded down. A chain with no indirect
+    /// blocks has nothing to waste so reports 100.
+    pub fn fill_percent(&self) -> u64 {
+        return fill_percent(self.entries, self.indirect_blocks * self.entries_per_block);
+    }
+
+    /// The fewest indirect blocks that could hold the entries.
+    pub fn minimum_blocks(&self) -> u64 {
+        if self.entries_per_block == 0 {
+            return self.indirect_blocks;
+        }
+
+        return self.entries.div_ceil(self.entries_per_block);
+    }
+
+    /// The blocks that rewriting the chain with every block full would free.
+    pub fn reclaimable_blocks(&self) -> u64 {
+        return self.indirect_blocks.saturating_sub(self.minimum_blocks());
+    }
+}
+
+/// The indirect chains of every file and tag on a disk, in index order, to find the structures that would benefit
+/// from compaction.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChainStats {
+    inodes: Vec<ChainUsage>,
+    tags: Vec<ChainUsage>,
+}
+
+impl ChainStats {
+    pub(crate) fn new(inodes: Vec<ChainUsage>, tags: Vec<ChainUsage>) -> Self {
+        return Self { inodes, tags };
+    }
+
+    #[inline]
+    pub fn inodes(&self) -> &[ChainUsage] {
+        return &self.inodes;
+    }
+
+    #[inline]
+    pub fn tags(&self) -> &[ChainUsage] {
+        return &self.tags;
+    }
+
+    /// The indirect blocks used by every file and tag.
+    pub fn indirect_blocks(&self) -> u64 {
+        return self.all().map(|c| c.indirect_blocks).sum();
+    }
+
+    /// The percentage of the slots in every indirect block that are in use, rounded down.
+    pub fn fill_percent(&self) -> u64 {
+        let entries = self.all().map(|c| c.entries).sum();
+        let slots = self
+            .all()
+            .map(|c| c.indirect_blocks * c.entries_per_block)
+            .sum();
+
+        return fill_percent(entries, slots);
+    }
+
+    /// The blocks that compacting every chain would free.
+    pub fn reclaimable_blocks(&self) -> u64 {
+        return self.all().map(|c| c.reclaimable_blocks()).sum();
+    }
+
+    /// The files and tags whose chains would free blocks if they were compacted.
+    pub fn compactable(&self) -> impl Iterator<Item = &ChainUsage> {
+        return self.all().filter(|c| c.reclaimable_blocks() > 0);
+    }
+
+    fn all(&self) -> impl Iterator<Item = &ChainUsage> {
+        return self.inodes.iter().chain(self.tags.iter());
+    }
+}
+
+fn fill_percent(entries: u64, slots: u64) -> u64 {
+    if slots == 0 {
+        return 100;
+    }
+
+    return entries * 100 / slots;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_chain_usage() {
+        // Three blocks of 10 slots holding 12 entries could be two
+        let chain = ChainUsage::new(4, "tag".to_string(), 3, 12, 10);
+        assert_eq!(chain.fill_percent(), 40);
+        assert_eq!(chain.minimum_blocks(), 2);
+        assert_eq!(chain.reclaimable_blocks(), 1);
+
+        let direct = ChainUsage::new(5, "file".to_string(), 0, 0, 10);
+        assert_eq!(direct.fill_percent(), 100);
+        assert_eq!(direct.reclaimable_blocks(), 0);
+
+        let stats = ChainStats::new(vec![direct], vec![chain]);
+        assert_eq!(stats.indirect_blocks(), 3);
+        assert_eq!(stats.fill_percent(), 40);
+        assert_eq!(stats.reclaimable_blocks(), 1);
+        assert_eq!(
+            stats.compactable().map(|c| c.index()).collect::<Vec<u64>>(),
+            [4]
+        );
+    }
+}
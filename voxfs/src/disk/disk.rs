@@ -1,17 +1,45 @@
+#[cfg(feature = "access-stats")]
+use super::access_heatmap::AccessHeatmap;
+use super::block_cache::BlockCache;
 use super::disk_blocks::SuperBlock;
-use super::DiskHandler;
+use super::listing_cache::ListingCache;
+use super::maybe_owned::MaybeOwned;
+use super::tag_index::{TagIndex, TagMembers};
+use super::{
+    BitmapFlushPolicy, CapacityReport, ChainStats, ChainUsage, DiskHandler, EstimateReport,
+    FileHandle, Integrity, IntegrityManifest, ManifestRegion, MountOptions, NamePattern,
+    NameReport, NewFileSpec, OpContext, OpenReport, RecordKind, ScrubFailure, ScrubRegion,
+    ScrubReport, SortOrder, TagQuery, Usage,
+};
 use crate::bitmap::BitMap;
+use crate::checksum_trait::UnverifiedRecord;
 use crate::disk::disk_blocks::{
-    Extent, INode, INodeFlags, IndirectINode, IndirectTagBlock, TagBlock, TagFlags,
+    Extent, FileType, FilesystemState, HistoryOperation, HistoryRecord, INode, INodeFlags,
+    IndirectINode, IndirectTagBlock, NamePolicy, TagBlock, TagFlags, BACKUP_SUPER_BLOCK_ADDRESS,
+    CONTENT_HASH_SIZE, DEFAULT_BYTES_PER_INODE, DEFAULT_INODES_PER_TAG,
+};
+use crate::manager::{timestamp_to_disk, timestamp_to_unix};
+use crate::utils::generate_uuid;
+use crate::{
+    ByteSerializable, DiskGeometry, DiskInfo, FormatOptions, OSManager, Timestamp, VoxFSError,
+    VoxFSErrorConvertible,
 };
-use crate::{ByteSerializable, DiskInfo, OSManager, VoxFSError, VoxFSErrorConvertible};
 use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
     string::{String, ToString},
     vec,
     vec::Vec,
 };
+use core::cell::{Cell, RefCell};
+
+#[cfg(feature = "std")]
+use super::{Manifest, ManifestReport, ManifestTag};
 
 const DEFAULT_BLOCK_SIZE: u64 = 4_096; // In bytes. 4KiB.
+/// The smallest block size a disk can be formatted with, enough to hold two tags or inodes.
+pub const MIN_BLOCK_SIZE: u64 = 512;
+const DEFAULT_MAX_IO_SIZE: u64 = 1_048_576; // In bytes. 1MiB.
 pub const FORBIDDEN_CHARACTERS: [char; 21] = [
     '#', '<', '$', '+', '%', '>', '!', '`', '&', '*', '\'', '|', '{', '}', '?', '"', '=', '/', ':',
     '\\', '@',
@@ -23,10 +51,19 @@ pub struct FileSize {
 }
 
 pub struct Disk<'a, 'b, E: VoxFSErrorConvertible> {
-    handler: &'a mut dyn DiskHandler<E>,
-    manager: &'b mut dyn OSManager,
+    handler: MaybeOwned<'a, dyn DiskHandler<E> + 'a>,
+    manager: MaybeOwned<'b, dyn OSManager + 'b>,
 
     super_block: SuperBlock,
+    // Whether the super block was dirty before this disk was opened.
+    opened_dirty: bool,
+    // Whether this disk has marked the super block dirty and not yet synced.
+    dirty: bool,
+    // The indexes of records that failed their checksum when opening and were read from the mirror instead.
+    recovered_tags: Vec<u64>,
+    recovered_inodes: Vec<u64>,
+    // Whether the backup super block was used because the primary was corrupted or older.
+    recovered_super_block: bool,
 
     tag_bitmap: BitMap,
     inode_bitmap: BitMap,
@@ -42,6 +79,60 @@ pub struct Disk<'a, 'b, E: VoxFSErrorConvertible> {
     // No guarantees are made about the order of the inodes, they may not be in index order.
     tags: Vec<TagBlock>,
     inodes: Vec<INode>,
+
+    membership: TagIndex,
+
+    // Reads only need a shared reference so the caches are filled through a RefCell.
+    block_cache: RefCell<BlockCache>,
+    listing_cache: RefCell<ListingCache>,
+    // The block after the last data block of each tag's members, where new files with the tag are placed. It is
+    // worked out from the members' extents the first time it is needed and moved on as files are added to the tag.
+    placement_hints: RefCell<BTreeMap<u64, Option<u64>>>,
+    // The number of data blocks used by each inode. The inode has no room to store it so it is counted for every
+    // inode when the disk is opened and kept up to date as files change, saving a walk of the indirect blocks on
+    // every call to file_size. Files whose count can't be read, or after the caches are invalidated, are counted
+    // again when next asked for.
+    physical_blocks: RefCell<BTreeMap<u64, u64>>,
+    // The files whose contents matched their content hash when checked with `Integrity::Full`, until they change.
+    verified_files: RefCell<BTreeSet<u64>>,
+    read_ahead: bool,
+    max_io_size: u64,
+    // The most bytes a single operation may allocate for file contents, None for no limit.
+    memory_budget: Option<u64>,
+
+    bitmap_flush_policy: BitmapFlushPolicy,
+    // Whether the bitmaps in memory have changes that have not been written yet.
+    bitmaps_pending: bool,
+    operations_since_flush: u32,
+    // Whether anything has been written since the handler was last flushed.
+    unflushed_writes: bool,
+    // Whether anything has been written since the handler's last barrier.
+    unordered_writes: bool,
+    // Where the next incremental check starts.
+    check_cursor: u64,
+    // The number of records found corrupted since the disk was opened, counted by reads that only need a shared
+    // reference so they are kept in Cells.
+    checksum_failures: Cell<u64>,
+    // When a scrub or incremental check last reached the end of the disk.
+    last_check_time: Cell<Option<Timestamp>>,
+    // The sequence number the next history record will be written with.
+    next_history_sequence: u64,
+
+    // Whether modifications are refused, set by the mount options.
+    read_only: bool,
+    // Whether reads leave access times alone, set by the mount options.
+    noatime: bool,
+    // How much of what is read is checked against its checksums, set by the mount options.
+    integrity: Integrity,
+    // The access times of files read since the last sync, written when the disk syncs.
+    pending_access_times: RefCell<BTreeMap<u64, Timestamp>>,
+    // The number of handles open on each file, opening one only needs a shared reference.
+    open_files: RefCell<BTreeMap<u64, u64>>,
+    // Whether files with open handles can't be deleted, set by the mount options.
+    busy_protection: bool,
+    // The reads and writes sent to the handler for each block.
+    #[cfg(feature = "access-stats")]
+    access_heatmap: RefCell<AccessHeatmap>,
 }
 
 /// This macro unwraps an error for voxfs. We use a macro here because it takes advantage of templates.
@@ -72,7 +163,42 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         handler: &'a mut dyn DiskHandler<E>,
         manager: &'b mut dyn OSManager,
     ) -> Result<Self, VoxFSError<E>> {
-        let default_root_tag = TagBlock::new(
+        return Self::make_new_filesystem_with_options(handler, manager, FormatOptions::default());
+    }
+
+    /// Constructs a new filesystem with the specified options.
+    pub fn make_new_filesystem_with_options(
+        handler: &'a mut dyn DiskHandler<E>,
+        manager: &'b mut dyn OSManager,
+        options: FormatOptions,
+    ) -> Result<Self, VoxFSError<E>> {
+        let default_root_tag = Self::default_root_tag(manager);
+
+        return Self::format(
+            MaybeOwned::Borrowed(handler),
+            MaybeOwned::Borrowed(manager),
+            default_root_tag,
+            options,
+        );
+    }
+
+    /// Constructs a new filesystem with a specified root tag. This is primarily for testing purposes only.
+    pub fn make_new_filesystem_with_root(
+        handler: &'a mut dyn DiskHandler<E>,
+        manager: &'b mut dyn OSManager,
+        root_tag: TagBlock,
+    ) -> Result<Self, VoxFSError<E>> {
+        return Self::format(
+            MaybeOwned::Borrowed(handler),
+            MaybeOwned::Borrowed(manager),
+            root_tag,
+            FormatOptions::default(),
+        );
+    }
+
+    /// The root tag every new filesystem starts with.
+    fn default_root_tag(manager: &dyn OSManager) -> TagBlock {
+        return TagBlock::new(
             0,
             "root",
             TagFlags::new(true, true),
@@ -81,28 +207,93 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             0x0,
             [0u64; 12],
         );
-
-        return Self::make_new_filesystem_with_root(handler, manager, default_root_tag);
     }
 
-    /// Constructs a new filesystem with a specified root tag. This is primarily for testing purposes only.
-    pub fn make_new_filesystem_with_root(
-        handler: &'a mut dyn DiskHandler<E>,
-        manager: &'b mut dyn OSManager,
+    fn format(
+        mut handler: MaybeOwned<'a, dyn DiskHandler<E> + 'a>,
+        manager: MaybeOwned<'b, dyn OSManager + 'b>,
         root_tag: TagBlock,
+        options: FormatOptions,
     ) -> Result<Self, VoxFSError<E>> {
         let disk_size = unwrap_return_error_voxfs_convertible!(handler.disk_size());
-        let block_size = DEFAULT_BLOCK_SIZE;
+        let block_size = options.block_size.unwrap_or(DEFAULT_BLOCK_SIZE);
 
-        if block_size % 64 != 0 {
+        if block_size < MIN_BLOCK_SIZE || !block_size.is_power_of_two() {
             return Err(VoxFSError::InvalidBlockSize);
         }
 
-        let mut super_block = SuperBlock::new(block_size, disk_size);
-        unwrap_return_error_voxfs_convertible!(handler.zero_range(0, block_size)); // Zero the first block.
+        // The boot area is a whole number of blocks following the super block's block
+        let boot_area_blocks = match options.boot_area_size % block_size {
+            0 => options.boot_area_size / block_size,
+            _ => options.boot_area_size / block_size + 1,
+        };
+        let boot_area_size = boot_area_blocks * block_size;
+
+        if boot_area_blocks > u16::MAX as u64 || boot_area_size + block_size >= disk_size {
+            return Err(VoxFSError::InvalidBootAreaSize);
+        }
+
+        let bytes_per_inode = options.bytes_per_inode.unwrap_or(DEFAULT_BYTES_PER_INODE);
+        let inodes_per_tag = options.inodes_per_tag.unwrap_or(DEFAULT_INODES_PER_TAG);
+
+        if bytes_per_inode < INode::size() || inodes_per_tag == 0 {
+            return Err(VoxFSError::InvalidMetadataRatio);
+        }
+
+        let mut super_block = SuperBlock::with_ratios(
+            block_size,
+            disk_size - boot_area_size,
+            bytes_per_inode,
+            inodes_per_tag,
+        );
+
+        if super_block.block_count() == 0 {
+            return Err(VoxFSError::InvalidMetadataRatio);
+        }
+
+        super_block.set_boot_area_blocks(boot_area_blocks as u16);
+
+        if options.mirror_metadata {
+            super_block.reserve_metadata_mirror();
+        }
+
+        let history_blocks = match options.history_size % block_size {
+            0 => options.history_size / block_size,
+            _ => options.history_size / block_size + 1,
+        };
+
+        if history_blocks > u16::MAX as u64 {
+            return Err(VoxFSError::InvalidHistorySize);
+        }
+
+        super_block.reserve_history(history_blocks as u16);
+
+        if options.content_hashes {
+            super_block.reserve_content_hashes();
+        }
+
+        if !super_block.set_label(&options.label) {
+            return Err(VoxFSError::InvalidLabel);
+        }
+
+        let uuid = match options.uuid {
+            Some(uuid) => uuid,
+            None => generate_uuid(timestamp_to_disk(manager.current_time()) ^ disk_size),
+        };
+        super_block.set_uuid(uuid);
+        super_block.set_name_policy(options.name_policy);
+
+        if !super_block.set_max_name_length(options.max_name_length) {
+            return Err(VoxFSError::InvalidMaxNameLength);
+        }
+
+        // Zero the first block and the boot area.
+        unwrap_return_error_voxfs_convertible!(
+            handler.zero_range(0, super_block.bitmap_start_address())
+        );
 
         // The address of where we can start the data.
-        let mut offset = DEFAULT_BLOCK_SIZE;
+        let mut offset = super_block.bitmap_start_address();
 
         // Create the bit maps
         let tag_bitmap = BitMap::new(super_block.tag_count() as usize);
@@ -131,16 +322,45 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         super_block.set_inode_start_address(offset);
         offset += block_size * super_block.blocks_for_inodes();
 
+        if options.mirror_metadata {
+            super_block.set_mirror_start_address(offset);
+            offset +=
+                block_size * (super_block.blocks_for_tags() + super_block.blocks_for_inodes());
+        }
+
+        // A zeroed hash is read as no hash
+        if options.content_hashes {
+            let hash_size = super_block.content_hash_blocks() * block_size;
+            unwrap_return_error_voxfs_convertible!(handler.zero_range(offset, offset + hash_size));
+            offset += hash_size;
+        }
+
+        // The history is found by scanning for records so it must start empty
+        if history_blocks > 0 {
+            unwrap_return_error_voxfs_convertible!(
+                handler.zero_range(offset, offset + history_blocks * block_size)
+            );
+            offset += history_blocks * block_size;
+        }
+
         super_block.set_data_start_address(offset);
 
-        // Write the super block
+        // Write the super block and its backup
+        super_block.record_commit();
+        let super_block_bytes = super_block.to_bytes().to_vec();
         unwrap_return_error_voxfs_convertible!(
-            handler.write_bytes(&super_block.to_bytes().to_vec(), 0)
+            handler.write_bytes(&super_block_bytes, BACKUP_SUPER_BLOCK_ADDRESS)
         );
+        unwrap_return_error_voxfs_convertible!(handler.write_bytes(&super_block_bytes, 0));
 
         let mut new_disk = Self {
             handler,
             manager,
+            opened_dirty: false,
+            dirty: false,
+            recovered_tags: Vec::new(),
+            recovered_inodes: Vec::new(),
+            recovered_super_block: false,
             super_block,
             tag_bitmap,
             inode_bitmap,
@@ -151,20 +371,80 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             blocks_for_block_map,
             tags: vec![root_tag],
             inodes: Vec::new(),
+            membership: TagIndex::new(),
+            block_cache: RefCell::new(BlockCache::new(0)),
+            listing_cache: RefCell::new(ListingCache::new()),
+            placement_hints: RefCell::new(BTreeMap::new()),
+            physical_blocks: RefCell::new(BTreeMap::new()),
+            verified_files: RefCell::new(BTreeSet::new()),
+            read_ahead: false,
+            max_io_size: DEFAULT_MAX_IO_SIZE,
+            memory_budget: None,
+            bitmap_flush_policy: BitmapFlushPolicy::default(),
+            bitmaps_pending: false,
+            operations_since_flush: 0,
+            unflushed_writes: true,
+            unordered_writes: true,
+            check_cursor: 0,
+            checksum_failures: Cell::new(0),
+            last_check_time: Cell::new(None),
+            next_history_sequence: 1,
+            read_only: false,
+            noatime: true,
+            integrity: Integrity::Metadata,
+            pending_access_times: RefCell::new(BTreeMap::new()),
+            open_files: RefCell::new(BTreeMap::new()),
+            busy_protection: false,
+            #[cfg(feature = "access-stats")]
+            access_heatmap: RefCell::new(AccessHeatmap::new(block_size)),
         };
 
         // Write the root tag
         new_disk.store_tag_first_free(root_tag)?;
 
         // Write the bit maps
-        new_disk.write_bitmaps()?;
+        new_disk.flush_bitmaps()?;
 
         return Ok(new_disk);
     }
 
     /// Gives access to the disk handler
     pub fn handler(&mut self) -> &mut dyn DiskHandler<E> {
-        return self.handler;
+        return &mut *self.handler;
+    }
+
+    /// The size in bytes of the boot area.
+    pub fn boot_area_size(&self) -> u64 {
+        return self.super_block.boot_area_blocks() as u64 * self.block_size;
+    }
+
+    /// Reads the whole boot area.
+    pub fn read_boot_area(&self) -> Result<Vec<u8>, VoxFSError<E>> {
+        return self.read_from_address(self.block_size, self.boot_area_size());
+    }
+
+    /// Writes bytes to the start of the boot area, the rest of the area is zeroed.
+    pub fn write_boot_area(&mut self, bytes: &Vec<u8>) -> Result<(), VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let boot_area_size = self.boot_area_size();
+
+        if bytes.len() as u64 > boot_area_size {
+            return Err(VoxFSError::InvalidBootAreaSize);
+        }
+
+        self.write_to_address(self.block_size, bytes)?;
+        unwrap_return_error_voxfs_convertible!(self.handler.zero_range(
+            self.block_size + bytes.len() as u64,
+            self.block_size + boot_area_size
+        ));
+
+        return self.record_history(HistoryOperation::WriteBootArea, 0, None, "");
+    }
+
+    /// The number of data blocks, used or free.
+    pub fn data_block_count(&self) -> u64 {
+        return self.super_block.block_count();
     }
 
     /// Returns the number of available data blocks
@@ -175,10 +455,427 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             .unwrap_or(0) as u64;
     }
 
+    /// Whether a data block is used by a file or an indirect block, false for an index past the last block.
+    pub fn is_data_block_allocated(&self, index: u64) -> bool {
+        if index >= self.super_block.block_count() {
+            return false;
+        }
+
+        return self.block_bitmap.bit_at(index as usize).unwrap_or(false);
+    }
+
+    /// Reads a data block by its index, whether or not it is allocated, for tools that inspect the disk below the
+    /// level of files.
+    pub fn read_block(&self, index: u64) -> Result<Vec<u8>, VoxFSError<E>> {
+        if index >= self.super_block.block_count() {
+            return Err(VoxFSError::BlockOutOfRange(index));
+        }
+
+        return self.read_data_block(index);
+    }
+
+    /// Writes over the start of a data block, or all of it, without checking what uses the block. The checksums
+    /// and content hashes of whatever the block holds are left as they are, so a scrub may report them. The caches
+    /// are dropped in case the block held tag members.
+    #[cfg(feature = "dangerous")]
+    pub fn write_block_unchecked(&mut self, index: u64, bytes: &[u8]) -> Result<(), VoxFSError<E>> {
+        if index >= self.super_block.block_count() {
+            return Err(VoxFSError::BlockOutOfRange(index));
+        }
+
+        let address = self.data_index_to_address(index);
+
+        if bytes.len() as u64 > self.block_size {
+            return Err(VoxFSError::AddressOutOfRegion {
+                address,
+                length: bytes.len() as u64,
+            });
+        }
+
+        self.mark_dirty()?;
+        self.write_to_data_region(address, &bytes.to_vec())?;
+        self.invalidate_caches();
+
+        return Ok(());
+    }
+
+    /// Whether the filesystem was left dirty by the last program to modify it, meaning it was not synced or dropped
+    /// cleanly and may need to be checked.
+    pub fn opened_dirty(&self) -> bool {
+        return self.opened_dirty;
+    }
+
+    /// Whether this disk has modified the filesystem since it was opened or last synced.
+    pub fn is_dirty(&self) -> bool {
+        return self.dirty;
+    }
+
+    /// The number of tag and inode records found corrupted since the disk was opened, when opening it or by a
+    /// scrub or incremental check.
+    pub fn checksum_failures(&self) -> u64 {
+        return self.checksum_failures.get();
+    }
+
+    /// When a scrub or incremental check last reached the end of the disk since it was opened.
+    pub fn last_check_time(&self) -> Option<Timestamp> {
+        return self.last_check_time.get();
+    }
+
+    /// The indexes of the tags that were corrupted when the disk was opened and were recovered from the mirror.
+    /// The primary copy is repaired the next time the tag is written.
+    pub fn recovered_tags(&self) -> &Vec<u64> {
+        return &self.recovered_tags;
+    }
+
+    /// The indexes of the inodes that were corrupted when the disk was opened and were recovered from the mirror.
+    /// The primary copy is repaired the next time the inode is written.
+    pub fn recovered_inodes(&self) -> &Vec<u64> {
+        return &self.recovered_inodes;
+    }
+
+    /// Whether the super block was read from its backup when the disk was opened, because the primary copy was
+    /// corrupted or an interrupted write left it older than the backup. The primary copy is repaired the next time
+    /// the super block is written.
+    pub fn recovered_super_block(&self) -> bool {
+        return self.recovered_super_block;
+    }
+
+    /// The number of times the super block has been written since the filesystem was created.
+    pub fn commit_sequence(&self) -> u64 {
+        return self.super_block.commit_sequence();
+    }
+
+    /// The time the filesystem was last opened and modified.
+    pub fn last_mount_time(&self) -> Timestamp {
+        return self.super_block.last_mount_time();
+    }
+
+    pub fn uuid(&self) -> [u8; 16] {
+        return self.super_block.uuid();
+    }
+
+    /// The filesystem's label, empty if it has none.
+    pub fn label(&self) -> String {
+        return self.super_block.label();
+    }
+
+    /// Whether the tag and inode tables have a mirrored copy.
+    pub fn has_metadata_mirror(&self) -> bool {
+        return self.super_block.mirror_tag_start_address().is_some();
+    }
+
+    /// Whether the filesystem was formatted with a history region recording each change.
+    pub fn has_history(&self) -> bool {
+        return self.super_block.history_blocks() > 0;
+    }
+
+    /// Whether the filesystem was formatted to keep a hash of each file's contents.
+    pub fn has_content_hashes(&self) -> bool {
+        return self.super_block.has_content_hashes();
+    }
+
+    /// The size in bytes of the history region, 0 if there isn't one.
+    pub fn history_size(&self) -> u64 {
+        return self.super_block.history_blocks() as u64 * self.block_size;
+    }
+
+    /// The changes recorded in the history region from oldest to newest.
+    /// Once the region is full the oldest records are overwritten, so this only goes back so far.
+    pub fn history(&self) -> Result<Vec<HistoryRecord>, VoxFSError<E>> {
+        return self.read_history();
+    }
+
+    /// What happens when a file is created with the name of an existing file.
+    pub fn name_policy(&self) -> NamePolicy {
+        return self.super_block.name_policy();
+    }
+
+    /// The most characters a new file or tag name may have, if the disk was formatted with a limit.
+    pub fn max_name_length(&self) -> Option<usize> {
+        return self.super_block.max_name_length();
+    }
+
+    /// Changes how duplicate file names are handled and records it in the super block.
+    /// Files that already share a name are left as they are.
+    pub fn set_name_policy(&mut self, name_policy: NamePolicy) -> Result<(), VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let mut super_block = self.super_block.clone();
+        super_block.set_name_policy(name_policy);
+
+        self.write_super_block(super_block)?;
+
+        return self.record_history(HistoryOperation::SetNamePolicy, 0, None, "");
+    }
+
+    /// The number of times the filesystem has been opened and modified.
+    pub fn mount_count(&self) -> u32 {
+        return self.super_block.mount_count();
+    }
+
+    /// Whether the disk was formatted with an older version of the format and must be migrated with `migrate`
+    /// before it can be modified. Its times are converted as it is read so it can still be read without migrating.
+    pub fn needs_migration(&self) -> bool {
+        return self.super_block.needs_migration();
+    }
+
+    /// Rewrites the tags, inodes and history records of a disk formatted with an older version of the format in
+    /// the current one. Version 1 changed the encoding of times to hold those before 1970 and after 2262, version 2
+    /// moved the colour and icon of a tag out of its last member slot and shortened tag names to make room.
+    /// Does nothing if the disk is already the current version. The disk is marked dirty first so if this is
+    /// interrupted it is reported by `opened_dirty` when it is next opened, the records may then be mixed.
+    pub fn migrate(&mut self) -> Result<(), VoxFSError<E>> {
+        if self.read_only {
+            return Err(VoxFSError::ReadOnly);
+        }
+
+        if !self.needs_migration() {
+            return Ok(());
+        }
+
+        if !self.dirty {
+            let mut super_block = self.super_block.clone();
+            super_block.set_state(FilesystemState::Dirty);
+
+            self.write_super_block(super_block)?;
+            self.barrier()?;
+            self.dirty = true;
+        }
+
+        // The records in memory were converted when the disk was opened
+        for i in 0..self.tags.len() {
+            self.write_tag(self.tags[i])?;
+        }
+
+        for i in 0..self.inodes.len() {
+            self.write_inode(self.inodes[i])?;
+        }
+
+        // Only the times of history records changed before version 1
+        let history_start = if self.super_block.has_legacy_times() {
+            self.super_block.history_start_address()
+        } else {
+            None
+        };
+
+        if let Some(start) = history_start {
+            for block in 0..self.super_block.history_blocks() as u64 {
+                let address = start + block * self.block_size;
+                let mut bytes = self.read_from_address(address, self.block_size)?;
+
+                for slot in bytes.chunks_exact_mut(HistoryRecord::size() as usize) {
+                    if let Some(mut record) = HistoryRecord::from_bytes(slot) {
+                        record.convert_legacy_time();
+                        slot.copy_from_slice(&record.to_bytes());
+                    }
+                }
+
+                self.write_to_history_region(address, &bytes)?;
+            }
+        }
+
+        self.barrier()?;
+
+        let mut super_block = self.super_block.clone();
+        super_block.upgrade_version();
+        super_block.record_mount(self.manager.current_time());
+
+        return self.write_super_block(super_block);
+    }
+
+    /// Writes any pending bitmap changes and marks the filesystem as clean.
+    /// This is also done when the disk is dropped but errors are ignored there.
+    pub fn sync(&mut self) -> Result<(), VoxFSError<E>> {
+        self.write_access_times()?;
+
+        if self.bitmaps_pending {
+            self.flush_bitmaps()?;
+        }
+
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut super_block = self.super_block.clone();
+        super_block.set_state(FilesystemState::Clean);
+
+        // Everything must be on the disk before it is marked clean
+        self.barrier()?;
+        self.write_super_block(super_block)?;
+        self.dirty = false;
+
+        return Ok(());
+    }
+
+    /// Syncs the disk and flushes the handler, reporting any errors that dropping the disk would ignore.
+    pub fn close(mut self) -> Result<(), VoxFSError<E>> {
+        self.sync()?;
+
+        return self.flush_handler();
+    }
+
+    /// Asks the handler to keep everything written so far ahead of any later writes.
+    fn barrier(&mut self) -> Result<(), VoxFSError<E>> {
+        if !self.unordered_writes {
+            return Ok(());
+        }
+
+        unwrap_return_error_voxfs_convertible!(self.handler.barrier());
+        self.unordered_writes = false;
+
+        return Ok(());
+    }
+
+    /// Asks the handler to persist everything written since it was last flushed.
+    fn flush_handler(&mut self) -> Result<(), VoxFSError<E>> {
+        if !self.unflushed_writes {
+            return Ok(());
+        }
+
+        unwrap_return_error_voxfs_convertible!(self.handler.flush());
+        self.unflushed_writes = false;
+
+        return Ok(());
+    }
+
     /// Opens a disk, loading the required details
     pub fn open_disk(
         handler: &'a mut dyn DiskHandler<E>,
         manager: &'b mut dyn OSManager,
+    ) -> Result<Self, VoxFSError<E>> {
+        return Self::open(
+            MaybeOwned::Borrowed(handler),
+            MaybeOwned::Borrowed(manager),
+            None,
+            Integrity::default(),
+            false,
+        );
+    }
+
+    /// Opens a disk, skipping any tags or inodes that can not be read instead of failing.
+    /// The skipped records are listed in the report. A corrupted super block still fails the open.
+    pub fn open_disk_tolerant(
+        handler: &'a mut dyn DiskHandler<E>,
+        manager: &'b mut dyn OSManager,
+    ) -> Result<(Self, OpenReport<E>), VoxFSError<E>> {
+        let mut report = OpenReport::new();
+        let disk = Self::open(
+            MaybeOwned::Borrowed(handler),
+            MaybeOwned::Borrowed(manager),
+            Some(&mut report),
+            Integrity::default(),
+            false,
+        )?;
+
+        return Ok((disk, report));
+    }
+
+    /// Opens a disk with the behaviour described by the options. The defaults open it the same way as `open_disk`.
+    pub fn open_with_options(
+        handler: &'a mut dyn DiskHandler<E>,
+        manager: &'b mut dyn OSManager,
+        options: MountOptions,
+    ) -> Result<Self, VoxFSError<E>> {
+        return Self::open_maybe_owned(
+            MaybeOwned::Borrowed(handler),
+            MaybeOwned::Borrowed(manager),
+            options,
+        );
+    }
+
+    /// Opens a disk that owns its handler and manager, so it can be kept without borrowing them, e.g. in a
+    /// long lived struct as a `Disk<'static, 'static, E>`. They are dropped with the disk.
+    pub fn open_owned(
+        handler: Box<dyn DiskHandler<E> + 'a>,
+        manager: Box<dyn OSManager + 'b>,
+        options: MountOptions,
+    ) -> Result<Self, VoxFSError<E>> {
+        return Self::open_maybe_owned(
+            MaybeOwned::Owned(handler),
+            MaybeOwned::Owned(manager),
+            options,
+        );
+    }
+
+    /// Formats a new filesystem on a disk that owns its handler and manager, see `open_owned`.
+    pub fn make_new_filesystem_owned(
+        handler: Box<dyn DiskHandler<E> + 'a>,
+        manager: Box<dyn OSManager + 'b>,
+        options: FormatOptions,
+    ) -> Result<Self, VoxFSError<E>> {
+        let root_tag = Self::default_root_tag(&*manager);
+
+        return Self::format(
+            MaybeOwned::Owned(handler),
+            MaybeOwned::Owned(manager),
+            root_tag,
+            options,
+        );
+    }
+
+    fn open_maybe_owned(
+        handler: MaybeOwned<'a, dyn DiskHandler<E> + 'a>,
+        manager: MaybeOwned<'b, dyn OSManager + 'b>,
+        options: MountOptions,
+    ) -> Result<Self, VoxFSError<E>> {
+        let mut disk = Self::open(handler, manager, None, options.integrity, options.salvage)?;
+
+        // A salvaged disk can be missing part of its data region, so nothing may be written to it
+        disk.read_only = options.read_only || options.salvage;
+        // A read only disk, or one that must be migrated, can't write the access times
+        disk.noatime = options.noatime || disk.read_only || disk.needs_migration();
+        disk.set_cache_size(options.cache_size);
+        disk.set_memory_budget(options.memory_budget);
+        disk.busy_protection = options.busy_protection;
+
+        if !options.lazy_load {
+            for i in 0..disk.tags.len() {
+                disk.load_tag_members(i)?;
+            }
+        }
+
+        if options.check_on_open && !disk.scrub()?.is_clean() {
+            return Err(VoxFSError::FailedCheckOnOpen);
+        }
+
+        return Ok(disk);
+    }
+
+    /// Whether the disk was opened read only, refusing every modification.
+    pub fn is_read_only(&self) -> bool {
+        return self.read_only;
+    }
+
+    /// The number of reads and writes sent to the handler for each block since the disk was opened.
+    #[cfg(feature = "access-stats")]
+    pub fn access_heatmap(&self) -> AccessHeatmap {
+        return self.access_heatmap.borrow().clone();
+    }
+
+    /// Forgets the accesses counted so far, e.g. to measure a single workload.
+    #[cfg(feature = "access-stats")]
+    pub fn reset_access_heatmap(&mut self) {
+        self.access_heatmap.get_mut().clear();
+    }
+
+    /// How much of what is read is checked against its checksums.
+    pub fn integrity(&self) -> Integrity {
+        return self.integrity;
+    }
+
+    /// Whether file contents are checked against their content hashes when read, with `Integrity::Full`.
+    pub fn verifies_reads(&self) -> bool {
+        return self.integrity == Integrity::Full;
+    }
+
+    /// Opens a disk, recording unreadable records in the report if there is one rather than failing. A truncated
+    /// image fails with `VoxFSError::ImageTruncated` unless salvaging, which shrinks the data region to fit.
+    fn open(
+        handler: MaybeOwned<'a, dyn DiskHandler<E> + 'a>,
+        manager: MaybeOwned<'b, dyn OSManager + 'b>,
+        mut report: Option<&mut OpenReport<E>>,
+        integrity: Integrity,
+        salvage: bool,
     ) -> Result<Self, VoxFSError<E>> {
         // Things to do:
         // 1: Load the super block
@@ -187,11 +884,42 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         // Read the super block
         let block_size = DEFAULT_BLOCK_SIZE;
         let first_block = unwrap_return_error_voxfs_convertible!(handler.read_bytes(0, block_size));
-        let super_block = match SuperBlock::from_bytes(&first_block) {
-            Some(b) => b,
-            None => return Err(VoxFSError::CorruptedSuperBlock),
+        let disk_size = unwrap_return_error_voxfs_convertible!(handler.disk_size());
+
+        // The layout is checked against the size it claims so a truncated image can be told apart from a corrupted
+        // super block
+        let valid = |address: u64| {
+            return SuperBlock::from_bytes(&first_block[address as usize..]).filter(|b| {
+                let claimed = b
+                    .block_count()
+                    .saturating_mul(b.block_size())
+                    .saturating_add(b.data_start_address());
+
+                return b.is_layout_valid(core::cmp::max(disk_size, claimed));
+            });
         };
 
+        // The backup is written first, so a newer backup means the write to the primary was interrupted
+        let (mut super_block, recovered_super_block) =
+            match (valid(0), valid(BACKUP_SUPER_BLOCK_ADDRESS)) {
+                (Some(primary), Some(backup))
+                    if backup.commit_sequence() > primary.commit_sequence() =>
+                {
+                    (backup, true)
+                }
+                (Some(primary), _) => (primary, false),
+                (None, Some(backup)) => (backup, true),
+                (None, None) => return Err(VoxFSError::CorruptedSuperBlock),
+            };
+
+        // Without the tables there is nothing to salvage
+        if super_block.data_start_address() > disk_size {
+            return Err(VoxFSError::ImageTruncated {
+                expected: super_block.data_start_address(),
+                actual: disk_size,
+            });
+        }
+
         // Determine the block size and the number of blocks for the bitmaps
         let block_size = super_block.block_size();
         let blocks_for_tag_map = rounded_to_alignment!(super_block.tag_count(), block_size);
@@ -199,17 +927,18 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         let blocks_for_block_map = rounded_to_alignment!(super_block.block_count(), block_size);
 
         // Read the data for the bitmaps
+        let bitmap_start = super_block.bitmap_start_address();
         let tag_bitmaps_bytes = unwrap_return_error_voxfs_convertible!(
-            handler.read_bytes(block_size, blocks_for_tag_map * block_size)
+            handler.read_bytes(bitmap_start, blocks_for_tag_map * block_size)
         );
 
         let inode_bitmaps_bytes = unwrap_return_error_voxfs_convertible!(handler.read_bytes(
-            block_size + blocks_for_tag_map * block_size,
+            bitmap_start + blocks_for_tag_map * block_size,
             blocks_for_inode_map * block_size
         ));
 
         let data_bitmaps_bytes = unwrap_return_error_voxfs_convertible!(handler.read_bytes(
-            block_size + (blocks_for_tag_map + blocks_for_inode_map) * block_size,
+            bitmap_start + (blocks_for_tag_map + blocks_for_inode_map) * block_size,
             blocks_for_block_map * block_size
         ));
 
@@ -218,9 +947,39 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         let inode_bitmap = BitMap::from_bytes(&inode_bitmaps_bytes);
         let block_bitmap = BitMap::from_bytes(&data_bitmaps_bytes);
 
+        // Blocks past the end of the disk are only missed if they're in use
+        let blocks_on_disk = (disk_size - super_block.data_start_address()) / block_size;
+        let blocks_in_use = (0..super_block.block_count())
+            .rev()
+            .find(|i| block_bitmap.bit_at(*i as usize) == Some(true))
+            .map_or(0, |last| last + 1);
+
+        if blocks_in_use > blocks_on_disk && !salvage {
+            return Err(VoxFSError::ImageTruncated {
+                expected: super_block.data_start_address() + blocks_in_use * block_size,
+                actual: disk_size,
+            });
+        }
+
+        if salvage {
+            super_block.clamp_block_count(blocks_on_disk);
+        }
+
+        // Contents can only be checked against hashes that are kept, with a hasher to compare them
+        if integrity == Integrity::Full
+            && (!super_block.has_content_hashes() || manager.content_hasher().is_none())
+        {
+            return Err(VoxFSError::NoContentHasher);
+        }
+
         let mut s = Self {
             handler,
             manager,
+            opened_dirty: super_block.state() == FilesystemState::Dirty,
+            dirty: false,
+            recovered_tags: Vec::new(),
+            recovered_inodes: Vec::new(),
+            recovered_super_block,
             super_block,
             tag_bitmap,
             inode_bitmap,
@@ -231,14 +990,247 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             blocks_for_block_map,
             tags: Vec::new(),
             inodes: Vec::new(),
+            membership: TagIndex::new(),
+            block_cache: RefCell::new(BlockCache::new(0)),
+            listing_cache: RefCell::new(ListingCache::new()),
+            placement_hints: RefCell::new(BTreeMap::new()),
+            physical_blocks: RefCell::new(BTreeMap::new()),
+            verified_files: RefCell::new(BTreeSet::new()),
+            read_ahead: false,
+            max_io_size: DEFAULT_MAX_IO_SIZE,
+            memory_budget: None,
+            bitmap_flush_policy: BitmapFlushPolicy::default(),
+            bitmaps_pending: false,
+            operations_since_flush: 0,
+            unflushed_writes: false,
+            unordered_writes: false,
+            check_cursor: 0,
+            checksum_failures: Cell::new(0),
+            last_check_time: Cell::new(None),
+            next_history_sequence: 1,
+            read_only: false,
+            noatime: true,
+            integrity,
+            pending_access_times: RefCell::new(BTreeMap::new()),
+            open_files: RefCell::new(BTreeMap::new()),
+            busy_protection: false,
+            #[cfg(feature = "access-stats")]
+            access_heatmap: RefCell::new(AccessHeatmap::new(block_size)),
         };
 
+        // The super block and bitmaps were read before there was a heat map to count them in
+        #[cfg(feature = "access-stats")]
+        {
+            let heatmap = s.access_heatmap.get_mut();
+            heatmap.record_read(0, DEFAULT_BLOCK_SIZE);
+            heatmap.record_read(
+                bitmap_start,
+                (blocks_for_tag_map + blocks_for_inode_map + blocks_for_block_map) * block_size,
+            );
+        }
+
         // Load the tags and inodes into memory.
-        s.tags = s.load_tags()?;
-        s.inodes = s.load_inodes()?;
+        s.tags = s.load_tags(report.as_deref_mut())?;
+        s.inodes = s.load_inodes(report)?;
 
-        return Ok(s);
-    }
+        if s.super_block.has_legacy_times() {
+            for tag in s.tags.iter_mut() {
+                tag.convert_legacy_time();
+            }
+
+            for inode in s.inodes.iter_mut() {
+                inode.convert_legacy_times();
+            }
+        }
+
+        if s.super_block.has_legacy_tag_appearance() {
+            for tag in s.tags.iter_mut() {
+                tag.convert_legacy_appearance();
+            }
+        }
+
+        if let Some(last) = s.read_history()?.last() {
+            s.next_history_sequence = last.sequence() + 1;
+        }
+
+        return Ok(s);
+    }
+
+    /// Reads every metadata record on the disk and verifies its checksum, without using the copies in memory.
+    /// Unreadable records are listed in the report rather than failing the scrub.
+    pub fn scrub(&self) -> Result<ScrubReport<E>, VoxFSError<E>> {
+        return self.scrub_with_context(&OpContext::new());
+    }
+
+    /// Scrubs the disk, reporting progress as each tag and inode is checked and stopping with
+    /// `VoxFSError::Cancelled` if the context is cancelled.
+    pub fn scrub_with_context(&self, context: &OpContext) -> Result<ScrubReport<E>, VoxFSError<E>> {
+        let mut report = ScrubReport::new();
+
+        // Each used record is checked once in the tables and once more when its indirect blocks are followed
+        let tag_count = self.super_block.tag_count();
+        let inode_count = self.super_block.inode_count();
+        let used_records = (tag_count as usize
+            - self
+                .tag_bitmap
+                .count_zeros_up_to(tag_count as usize)
+                .unwrap_or(0))
+            + (inode_count as usize
+                - self
+                    .inode_bitmap
+                    .count_zeros_up_to(inode_count as usize)
+                    .unwrap_or(0));
+        let total = (used_records + self.tags.len() + self.inodes.len()) as u64;
+        let mut done = 0;
+
+        self.scrub_super_block(&mut report);
+
+        for i in 0..self.super_block.tag_count() {
+            if !self.tag_bitmap.bit_at(i as usize).unwrap() {
+                continue;
+            }
+
+            self.scrub_tag(
+                &mut report,
+                ScrubRegion::TagTable,
+                i,
+                self.tag_index_to_address(i),
+            );
+
+            if let Some(mirror) = self.super_block.mirror_tag_start_address() {
+                self.scrub_tag(
+                    &mut report,
+                    ScrubRegion::TagMirror,
+                    i,
+                    mirror + i * TagBlock::size(),
+                );
+            }
+
+            done += 1;
+            context.step(done, total)?;
+        }
+
+        for i in 0..self.super_block.inode_count() {
+            if !self.inode_bitmap.bit_at(i as usize).unwrap() {
+                continue;
+            }
+
+            self.scrub_inode(
+                &mut report,
+                ScrubRegion::INodeTable,
+                i,
+                self.inode_index_to_address(i),
+            );
+
+            if let Some(mirror) = self.super_block.mirror_inode_start_address() {
+                self.scrub_inode(
+                    &mut report,
+                    ScrubRegion::INodeMirror,
+                    i,
+                    mirror + i * INode::size(),
+                );
+            }
+
+            done += 1;
+            context.step(done, total)?;
+        }
+
+        for tag in &self.tags {
+            self.scrub_tag_chain(&mut report, tag);
+
+            done += 1;
+            context.step(done, total)?;
+        }
+
+        for inode in &self.inodes {
+            self.scrub_inode_chain(&mut report, inode);
+
+            done += 1;
+            context.step(done, total)?;
+        }
+
+        report.finish();
+        self.note_check(&report);
+
+        return Ok(report);
+    }
+
+    /// Scrubs the next part of the disk, stopping once about `budget_blocks` blocks have been read, and remembers
+    /// where it stopped so repeated calls scrub the whole disk without one long pause. The report covers only the
+    /// records checked by this call. After the end of the disk is reached the next call starts from the beginning.
+    /// At least one record is checked even with a budget of 0.
+    pub fn check_incremental(
+        &mut self,
+        budget_blocks: u64,
+    ) -> Result<ScrubReport<E>, VoxFSError<E>> {
+        let mut report = ScrubReport::new();
+        let end = self.check_length();
+        let mut spent = 0;
+
+        if self.check_cursor >= end {
+            self.check_cursor = 0;
+        }
+
+        while self.check_cursor < end && (spent < budget_blocks || spent == 0) {
+            spent += self.check_position(&mut report, self.check_cursor);
+            self.check_cursor += 1;
+        }
+
+        if self.check_cursor >= end {
+            report.finish();
+            self.check_cursor = 0;
+        }
+
+        self.note_check(&report);
+
+        return Ok(report);
+    }
+
+    /// Rewrites a record reported by `scrub` or `check_incremental` from the copy loaded when the disk was
+    /// opened, fixing both the table and its mirror. Indirect blocks fail with `VoxFSError::NotRepairable`.
+    pub fn repair_scrub_failure(&mut self, failure: &ScrubFailure<E>) -> Result<(), VoxFSError<E>> {
+        if !failure.repairable() {
+            return Err(VoxFSError::NotRepairable);
+        }
+
+        self.mark_dirty()?;
+
+        return match failure.region() {
+            ScrubRegion::SuperBlock => self.write_super_block(self.super_block.clone()),
+            ScrubRegion::TagTable | ScrubRegion::TagMirror => {
+                match self.tags.iter().find(|t| t.index() == failure.index()) {
+                    Some(tag) => self.write_tag(*tag),
+                    None => Err(VoxFSError::CouldNotFindTag),
+                }
+            }
+            ScrubRegion::INodeTable | ScrubRegion::INodeMirror => {
+                match self.inodes.iter().find(|i| i.index() == failure.index()) {
+                    Some(inode) => self.write_inode(*inode),
+                    None => Err(VoxFSError::CouldNotFindINode),
+                }
+            }
+            ScrubRegion::IndirectTags | ScrubRegion::IndirectINodes => {
+                Err(VoxFSError::NotRepairable)
+            }
+        };
+    }
+
+    /// The number of positions `check_incremental` steps through before it reaches the end of the disk, with
+    /// `check_cursor` this gives its progress.
+    pub fn check_length(&self) -> u64 {
+        // The super block, every tag and inode slot, then the indirect blocks of every tag and inode
+        return 1 + 2 * (self.super_block.tag_count() + self.super_block.inode_count());
+    }
+
+    /// Where the next call to `check_incremental` starts. A service can store it to carry on after restarting.
+    pub fn check_cursor(&self) -> u64 {
+        return self.check_cursor;
+    }
+
+    /// Sets where the next call to `check_incremental` starts, a cursor past the end starts from the beginning.
+    pub fn set_check_cursor(&mut self, cursor: u64) {
+        self.check_cursor = cursor;
+    }
 
     /// Creates a new tag in the first available slot.
     pub fn create_new_tag(
@@ -246,6 +1238,8 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         name: &str,
         flags: TagFlags,
     ) -> Result<TagBlock, VoxFSError<E>> {
+        self.mark_dirty()?;
+
         self.validate_name(name, VoxFSError::InvalidTagName)?;
 
         for tag in &self.tags {
@@ -268,11 +1262,138 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         let tag = self.store_tag_first_free(tag)?;
         self.tags.push(tag); // Keep track of the tag in memory
 
+        self.record_history(HistoryOperation::CreateTag, tag.index(), None, name)?;
+
+        return Ok(tag);
+    }
+
+    /// Renames a tag in place, keeping its members. Fails if another tag already has the name.
+    pub fn rename_tag(
+        &mut self,
+        tag_index: u64,
+        new_name: &str,
+    ) -> Result<TagBlock, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        self.validate_name(new_name, VoxFSError::InvalidTagName)?;
+
+        let local_index = match self.tags.iter().position(|t| t.index() == tag_index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        for tag in &self.tags {
+            if tag.index() != tag_index && tag.same_name(new_name) {
+                return Err(VoxFSError::TagExistsWithName(new_name.to_string()));
+            }
+        }
+
+        let mut tag = self.tags[local_index];
+        tag.set_name(new_name);
+
+        self.write_tag(tag)?;
+        self.tags[local_index] = tag;
+
+        self.record_history(HistoryOperation::RenameTag, tag_index, None, new_name)?;
+
+        return Ok(tag);
+    }
+
+    /// Sets when a tag was created, e.g. to keep it when copying the tag from another filesystem.
+    pub fn set_tag_creation_time(
+        &mut self,
+        tag_index: u64,
+        creation_time: Timestamp,
+    ) -> Result<TagBlock, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let local_index = match self.tags.iter().position(|t| t.index() == tag_index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let mut tag = self.tags[local_index];
+        tag.set_creation_time(creation_time);
+
+        self.write_tag(tag)?;
+        self.tags[local_index] = tag;
+
+        self.record_history(
+            HistoryOperation::SetTagTimes,
+            tag_index,
+            None,
+            &tag.name_string(),
+        )?;
+
+        return Ok(tag);
+    }
+
+    /// Sets the colour clients should show a tag in, as 0xRRGGBB, or removes it.
+    pub fn set_tag_color(
+        &mut self,
+        tag_index: u64,
+        color: Option<u32>,
+    ) -> Result<TagBlock, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let local_index = match self.tags.iter().position(|t| t.index() == tag_index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let icon = self.tags[local_index].icon();
+
+        return self.set_tag_appearance(local_index, color, icon);
+    }
+
+    /// Sets the character, usually an emoji, clients should show next to a tag, or removes it.
+    pub fn set_tag_icon(
+        &mut self,
+        tag_index: u64,
+        icon: Option<char>,
+    ) -> Result<TagBlock, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let local_index = match self.tags.iter().position(|t| t.index() == tag_index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let color = self.tags[local_index].color();
+
+        return self.set_tag_appearance(local_index, color, icon);
+    }
+
+    /// Stores a tag's colour and icon once the disk has been marked dirty.
+    fn set_tag_appearance(
+        &mut self,
+        local_index: usize,
+        color: Option<u32>,
+        icon: Option<char>,
+    ) -> Result<TagBlock, VoxFSError<E>> {
+        let mut tag = self.tags[local_index];
+
+        if !tag.set_appearance(color, icon) {
+            return Err(VoxFSError::InvalidTagColor);
+        }
+
+        self.write_tag(tag)?;
+        self.tags[local_index] = tag;
+
+        self.record_history(
+            HistoryOperation::SetTagAppearance,
+            tag.index(),
+            None,
+            &tag.name_string(),
+        )?;
+
         return Ok(tag);
     }
 
     /// Deletes a tag for the tag with the specified index
     pub fn delete_tag(&mut self, index: u64) -> Result<(), VoxFSError<E>> {
+        self.mark_dirty()?;
+
         let mut local_index = None; // The index of the tag in the vector in memory
         let mut local_tag = None;
 
@@ -329,23 +1450,111 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
 
         // Remove from the memory map
         self.tags.remove(local_index);
+        self.membership.forget(local_tag.index());
+        self.listing_cache.get_mut().forget(local_tag.index());
+        self.placement_hints.get_mut().remove(&local_tag.index());
 
         // Write the bitmaps to the disk.
         self.write_bitmaps()?;
 
-        return Ok(());
+        return self.record_history(
+            HistoryOperation::DeleteTag,
+            index,
+            None,
+            &local_tag.name_string(),
+        );
+    }
+
+    /// Deletes a tag like `delete_tag`, first collecting the indices of the inodes it was applied to so the
+    /// caller can reapply them. The indices are sorted.
+    pub fn delete_tag_returning_members(&mut self, index: u64) -> Result<Vec<u64>, VoxFSError<E>> {
+        let mut members: Vec<u64> = self
+            .list_nodes_with_tag(index)?
+            .iter()
+            .map(|inode| inode.index())
+            .collect();
+        members.sort_unstable();
+
+        self.delete_tag(index)?;
+
+        return Ok(members);
+    }
+
+    /// Hides a tag instead of deleting it. The tag keeps its members and its name, so it can be brought back
+    /// with `restore_tag`.
+    pub fn archive_tag(&mut self, index: u64) -> Result<TagBlock, VoxFSError<E>> {
+        return self.set_tag_archived(index, true, HistoryOperation::ArchiveTag);
+    }
+
+    /// Brings back a tag hidden by `archive_tag`.
+    pub fn restore_tag(&mut self, index: u64) -> Result<TagBlock, VoxFSError<E>> {
+        return self.set_tag_archived(index, false, HistoryOperation::RestoreTag);
+    }
+
+    fn set_tag_archived(
+        &mut self,
+        index: u64,
+        archived: bool,
+        operation: HistoryOperation,
+    ) -> Result<TagBlock, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let local_index = match self.tags.iter().position(|t| t.index() == index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let mut tag = self.tags[local_index];
+        tag.set_flags(tag.flags().with_archived(archived));
+
+        self.write_tag(tag)?;
+        self.tags[local_index] = tag;
+
+        self.record_history(operation, index, None, &tag.name_string())?;
+
+        return Ok(tag);
     }
 
-    /// List the tags stored on the disk, this method doesn't reload them.
+    /// List the tags stored on the disk, this method doesn't reload them. Archived tags are included, use
+    /// `tag.flags().archived()` to hide them.
     pub fn list_tags(&self) -> Vec<TagBlock> {
         return self.tags.clone();
     }
 
+    /// List the tags hidden by `archive_tag`.
+    pub fn list_archived_tags(&self) -> Vec<TagBlock> {
+        return self
+            .tags
+            .iter()
+            .filter(|tag| tag.flags().archived())
+            .copied()
+            .collect();
+    }
+
     /// List the inodes on the disk, this method doesn't reload them.
     pub fn list_inodes(&self) -> Vec<INode> {
         return self.inodes.clone();
     }
 
+    /// Lists the inodes in a given order.
+    pub fn list_inodes_sorted(&self, order: SortOrder) -> Vec<INode> {
+        let mut inodes = self.inodes.clone();
+        order.sort(&mut inodes);
+
+        return inodes;
+    }
+
+    /// Lists up to `limit` inodes, skipping the first `offset`, in the order of `list_inodes`.
+    pub fn list_inodes_page(&self, offset: usize, limit: usize) -> Vec<INode> {
+        return self
+            .inodes
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect();
+    }
+
     /// The number of tags on this disk
     pub fn number_of_tags(&self) -> usize {
         return self.tags.len();
@@ -395,166 +1604,466 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         return DiskInfo::from_disk(self);
     }
 
-    /// Add an inode to a tag
-    pub fn apply_tag(&mut self, tag_index: u64, inode_index: u64) -> Result<(), VoxFSError<E>> {
-        let inode = self.inodes[self.locate_inode(inode_index)?];
+    /// How many inode and tag slots and data blocks are used out of the number the disk was formatted with.
+    pub fn capacity_report(&self) -> CapacityReport {
+        let inode_count = self.super_block.inode_count();
+        let tag_count = self.super_block.tag_count();
+        let block_count = self.super_block.block_count();
 
-        // Locate the tag in the memory map from the disk index provided
-        let mut tag_self_index = None;
+        return CapacityReport::new(
+            Usage::new(inode_count - self.free_file_slots() as u64, inode_count),
+            Usage::new(tag_count - self.free_tag_slots() as u64, tag_count),
+            Usage::new(block_count - self.available_data_blocks(), block_count),
+        );
+    }
 
-        // Find the local index of this tag
-        for (i, tag) in self.tags.iter().enumerate() {
-            if tag.index() == tag_index {
-                tag_self_index = Some(i);
-                break;
+    /// How many blocks new files of the given sizes in bytes would use, placing them in the free blocks in the
+    /// same way writing them in order would so the indirect blocks of fragmented files are counted. Nothing is
+    /// written.
+    pub fn estimate_required_blocks(&self, file_sizes: &[u64]) -> EstimateReport {
+        let block_count = self.super_block.block_count() as usize;
+        let mut bitmap = self.block_bitmap.clone();
+        let mut data_blocks = 0;
+        let mut indirect_blocks = 0;
+        let mut placed = true;
+
+        for size in file_sizes {
+            data_blocks += size.div_ceil(self.block_size);
+
+            // Once a file doesn't fit only its contents are counted
+            if !placed {
+                continue;
             }
-        }
-
-        // Unwrap it to ensure we could find it
-        let tag_self_index = match tag_self_index {
-            Some(i) => i,
-            None => return Err(VoxFSError::CouldNotFindTag),
-        };
-
-        // This checks if we have enough space in the tag block itself to add a new member
-        // if not we create a new indirect tag block
-        if self.tags[tag_self_index].number_of_pointers() >= TagBlock::MAXIMUM_LOCAL_MEMBERS {
-            /*
-            Two things must be checked.
-            1. Has this tag been applied before to this INode.
-            2. Where is a free spot?
-             */
-
-            // Check if we have already applied this tag to the inode
-            if self.tags[tag_self_index].contains_member(&inode.index()) {
-                return Err(VoxFSError::TagAlreadyAppliedToINode);
-            }
-
-            let mut next_address = self.tags[tag_self_index].indirect_pointer();
-            let mut previous_indirect = None;
-            let mut previous_indirect_location = None;
-            let mut free_indirect_tag_address: Option<u64> = None; // This tracks the location of an available spot in an inode
-
-            // NOTE: We don't bail early after finding a spot because we need to still check
-            // every indirect inode to ensure we don't apply the tag twice.
-            while next_address.is_some() {
-                let bytes = self.read_from_address(next_address.unwrap(), self.block_size)?;
-
-                let indirect_tag = match IndirectTagBlock::from_bytes(&bytes) {
-                    Some(t) => t,
-                    None => return Err(VoxFSError::CorruptedIndirectTag),
-                };
 
-                // Check if we contain the member already
-                if indirect_tag.contains_member(&inode.index()) {
-                    return Err(VoxFSError::TagAlreadyAppliedToINode);
+            let extents = match self.find_blocks_in(&bitmap, *size, None) {
+                Some(extents) => extents,
+                None => {
+                    placed = false;
+                    continue;
                 }
+            };
 
-                // Only if we haven't found a spot yet add it to this.
-                if free_indirect_tag_address.is_none()
-                    && (indirect_tag.number_of_members() as u64)
-                        < IndirectTagBlock::max_members_for_blocksize(self.block_size)
-                {
-                    free_indirect_tag_address = next_address;
+            for (start, end) in extents.iter() {
+                for i in *start..=*end {
+                    bitmap.set_bit(i as usize, true);
                 }
-
-                let nxt = indirect_tag.next();
-                previous_indirect = Some(indirect_tag);
-                previous_indirect_location = Some(next_address.unwrap());
-                next_address = nxt;
             }
 
-            // If we didn't find a spot to add this tag... Create a new one!
-            if free_indirect_tag_address.is_none() {
-                // Create a new indirect tag
-                let indirect_tag = IndirectTagBlock::new(
-                    self.tags[tag_self_index].index(),
-                    vec![inode.index()],
-                    0,
-                    self.block_size,
-                );
-
-                // Find a spot for it
-                let index = match self.find_block() {
-                    Some(index) => index,
-                    None => return Err(VoxFSError::NotEnoughFreeDataBlocks),
-                };
-
-                let location = self.data_index_to_address(index);
+            for _ in 0..self.group_indirect_extents(&extents).len() {
+                indirect_blocks += 1;
 
-                // Mark the block as taken
-                self.block_bitmap.set_bit(index as usize, true);
+                match bitmap.find_next_0_index_up_to(block_count) {
+                    Some(b) => {
+                        bitmap.set_bit(b, true);
+                    }
+                    None => placed = false,
+                }
+            }
+        }
 
-                // Write the new indirect block
-                self.write_to_address(
-                    location,
-                    &indirect_tag.to_bytes_padded(self.block_size as usize),
-                )?;
+        return EstimateReport::new(
+            self.block_size,
+            file_sizes.len() as u64,
+            data_blocks,
+            indirect_blocks,
+            self.available_data_blocks(),
+            self.free_file_slots() as u64,
+            placed,
+        );
+    }
 
-                // Write the bitmaps
-                self.write_bitmaps()?;
+    /// The indirect blocks chained from every file and tag and how full they are, to find the ones that would
+    /// benefit from compaction. Each chain is read from the disk.
+    pub fn chain_stats(&self) -> Result<ChainStats, VoxFSError<E>> {
+        let extents_per_block = IndirectINode::max_extents_for_blocksize(self.block_size);
+        let members_per_block = IndirectTagBlock::max_members_for_blocksize(self.block_size);
 
-                // We need to point to this new block somehow...
-                // Set the previous indirect block to point to this or the root tag
-                match previous_indirect {
-                    Some(mut i) => {
-                        i.set_next(location);
-
-                        self.write_to_address(
-                            previous_indirect_location.unwrap(),
-                            &i.to_bytes_padded(self.block_size as usize),
-                        )?;
-                    }
-                    None => {
-                        self.tags[tag_self_index].set_indirect(location);
+        let mut inodes = Vec::with_capacity(self.inodes.len());
 
-                        self.write_to_address(
-                            self.tag_index_to_address(self.tags[tag_self_index].index()),
-                            &self.tags[tag_self_index].to_bytes().to_vec(),
-                        )?;
-                    }
-                }
-            } else {
-                // Otherwise place it in the free spot
+        for inode in self.list_inodes_sorted(SortOrder::Index) {
+            let mut blocks = 0;
+            let mut extents = 0;
+            let mut next = inode.indirect_pointer();
 
-                // Read the indirect block and check that it was valid
-                let mut indirect = match IndirectTagBlock::from_bytes(
-                    &self.read_from_address(free_indirect_tag_address.unwrap(), self.block_size)?,
-                ) {
+            while let Some(address) = next {
+                let bytes = self.read_from_address(address, self.block_size)?;
+                let indirect = match IndirectINode::from_bytes(&bytes) {
                     Some(i) => i,
-                    None => return Err(VoxFSError::CorruptedIndirectTag),
+                    None => return Err(VoxFSError::CorruptedIndirectINode),
                 };
 
-                // Set the blocksize so we can use the append_member function
-                indirect.set_block_size(self.block_size);
+                blocks += 1;
+                extents += indirect.extents().len() as u64;
+                next = indirect.next();
+            }
 
-                // Append a member
-                if !indirect.append_member(inode.index()) {
-                    return Err(VoxFSError::FailedIndirectTagAppend);
-                }
+            inodes.push(ChainUsage::new(
+                inode.index(),
+                inode.name(),
+                blocks,
+                extents,
+                extents_per_block,
+            ));
+        }
 
-                // rewrite the new indirect block
-                self.write_to_address(
-                    free_indirect_tag_address.unwrap(),
-                    &indirect.to_bytes_padded(self.block_size as usize),
-                )?;
-            }
-        } else {
-            // There's enough space to add it to the tag block itself
+        let mut sorted_tags = self.tags.clone();
+        sorted_tags.sort_by_key(|t| t.index());
 
-            // Check if this tag has already been applied.
-            if self.tags[tag_self_index].contains_member(&inode.index()) {
-                return Err(VoxFSError::TagAlreadyAppliedToINode);
-            }
+        let mut tags = Vec::with_capacity(sorted_tags.len());
 
-            self.tags[tag_self_index].append_member(inode.index());
+        for tag in sorted_tags {
+            let (members, chain) = self.read_tag_chain(&tag)?;
+
+            tags.push(ChainUsage::new(
+                tag.index(),
+                tag.name_string(),
+                chain.len() as u64,
+                (members.len() - tag.number_of_pointers() as usize) as u64,
+                members_per_block,
+            ));
+        }
+
+        return Ok(ChainStats::new(inodes, tags));
+    }
+
+    /// The layout the disk was formatted with, where each region starts and how large it is.
+    pub fn geometry(&self) -> DiskGeometry {
+        return DiskGeometry::new(
+            &self.super_block,
+            self.blocks_for_tag_map,
+            self.blocks_for_inode_map,
+            self.blocks_for_block_map,
+        );
+    }
+
+    /// Writes the super block, a summary of the bitmaps, every allocated inode and every tag with its
+    /// indirect blocks as text. Inodes and tags are written in index order so dumps of the same disk can be
+    /// compared. An indirect block that can't be read is written as an error line rather than ending the dump.
+    pub fn debug_dump(&self, w: &mut dyn core::fmt::Write) -> core::fmt::Result {
+        let geometry = self.geometry();
+
+        writeln!(w, "super block")?;
+        writeln!(w, "  version: {}", self.super_block.version())?;
+        write!(w, "  uuid: ")?;
+        for byte in self.super_block.uuid().iter() {
+            write!(w, "{:02x}", byte)?;
+        }
+        writeln!(w)?;
+        writeln!(w, "  label: {:?}", self.super_block.label())?;
+        writeln!(w, "  state: {:?}", self.super_block.state())?;
+        writeln!(w, "  name policy: {:?}", self.super_block.name_policy())?;
+
+        if let Some(length) = self.super_block.max_name_length() {
+            writeln!(w, "  max name length: {}", length)?;
+        }
+        writeln!(w, "  mount count: {}", self.super_block.mount_count())?;
+        writeln!(
+            w,
+            "  commit sequence: {}{}",
+            self.super_block.commit_sequence(),
+            if self.recovered_super_block {
+                " (from the backup)"
+            } else {
+                ""
+            }
+        )?;
+        writeln!(w, "  block size: {}", geometry.block_size())?;
+        writeln!(w, "  boot area blocks: {}", geometry.boot_area_blocks())?;
+        writeln!(w, "  tag bitmap: {:#x}", geometry.tag_bitmap_start())?;
+        writeln!(w, "  inode bitmap: {:#x}", geometry.inode_bitmap_start())?;
+        writeln!(w, "  block bitmap: {:#x}", geometry.block_bitmap_start())?;
+        writeln!(w, "  tag table: {:#x}", geometry.tag_table_start())?;
+        writeln!(w, "  inode table: {:#x}", geometry.inode_table_start())?;
+
+        if let (Some(tags), Some(inodes)) = (
+            geometry.mirror_tag_table_start(),
+            geometry.mirror_inode_table_start(),
+        ) {
+            writeln!(w, "  mirror: tags {:#x}, inodes {:#x}", tags, inodes)?;
+        }
 
-            self.write_to_address(
-                self.tag_index_to_address(self.tags[tag_self_index].index()),
-                &self.tags[tag_self_index].to_bytes().to_vec(),
+        if let Some(hashes) = geometry.content_hash_start() {
+            writeln!(
+                w,
+                "  content hashes: {:#x}, {} blocks",
+                hashes,
+                geometry.content_hash_blocks()
             )?;
         }
 
+        if let Some(history) = geometry.history_start() {
+            writeln!(
+                w,
+                "  history: {:#x}, {} blocks",
+                history,
+                geometry.history_blocks()
+            )?;
+        }
+
+        writeln!(w, "  data: {:#x}", geometry.data_start())?;
+
+        writeln!(w, "bitmaps")?;
+        for (name, bitmap, count) in [
+            ("tags", &self.tag_bitmap, geometry.tag_count()),
+            ("inodes", &self.inode_bitmap, geometry.inode_count()),
+            ("blocks", &self.block_bitmap, geometry.block_count()),
+        ]
+        .iter()
+        {
+            writeln!(w, "  {}: {} of {} used", name, bitmap.count_ones(), count)?;
+        }
+
+        let mut inodes: Vec<&INode> = self.inodes.iter().collect();
+        inodes.sort_by_key(|inode| inode.index());
+
+        for inode in inodes {
+            writeln!(w, "inode {}", inode.index())?;
+            writeln!(w, "  name: {:?}", inode.name())?;
+            writeln!(w, "  type: {:?}", inode.flags().file_type())?;
+            writeln!(w, "  flags: {}", inode.flags())?;
+            writeln!(w, "  size: {}", inode.file_size())?;
+            write!(w, "  times:")?;
+            for (name, time) in [
+                ("created", inode.creation_time()),
+                ("modified", inode.modified_time()),
+                ("accessed", inode.access_time()),
+            ]
+            .iter()
+            {
+                let (seconds, nanos) = timestamp_to_unix(*time);
+                write!(w, " {} {}.{:09}", name, seconds, nanos)?;
+            }
+            writeln!(w)?;
+
+            write!(w, "  extents:")?;
+            for extent in inode.blocks()[..inode.num_extents() as usize].iter() {
+                write!(w, " {}..={}", extent.start, extent.end)?;
+            }
+            writeln!(w)?;
+
+            let mut next = inode.indirect_pointer();
+
+            while let Some(address) = next {
+                next = None;
+
+                match self.read_from_address(address, self.block_size) {
+                    Ok(bytes) => match IndirectINode::from_bytes(&bytes) {
+                        Some(block) => {
+                            write!(w, "  indirect {:#x}:", address)?;
+                            for extent in block.extents() {
+                                write!(w, " {}..={}", extent.start, extent.end)?;
+                            }
+                            writeln!(w)?;
+
+                            next = block.next();
+                        }
+                        None => writeln!(w, "  indirect {:#x}: error: corrupted", address)?,
+                    },
+                    Err(e) => writeln!(w, "  indirect {:#x}: error: {:?}", address, e)?,
+                }
+            }
+        }
+
+        let mut tags: Vec<&TagBlock> = self.tags.iter().collect();
+        tags.sort_by_key(|tag| tag.index());
+
+        for tag in tags {
+            writeln!(w, "tag {}", tag.index())?;
+            writeln!(w, "  name: {:?}", tag.name_string())?;
+            writeln!(w, "  flags: {}", tag.flags())?;
+
+            if tag.flags().archived() {
+                writeln!(w, "  archived: true")?;
+            }
+
+            if let Some(color) = tag.color() {
+                writeln!(w, "  color: #{:06x}", color)?;
+            }
+
+            if let Some(icon) = tag.icon() {
+                writeln!(w, "  icon: {}", icon)?;
+            }
+
+            write!(w, "  members:")?;
+            for member in tag.members()[..tag.number_of_pointers() as usize].iter() {
+                write!(w, " {}", member)?;
+            }
+            writeln!(w)?;
+
+            let mut next = tag.indirect_pointer();
+
+            while let Some(address) = next {
+                next = None;
+
+                match self.read_from_address(address, self.block_size) {
+                    Ok(bytes) => match IndirectTagBlock::from_bytes(&bytes) {
+                        Some(block) => {
+                            write!(w, "  indirect {:#x}:", address)?;
+                            for member in block.members() {
+                                write!(w, " {}", member)?;
+                            }
+                            writeln!(w)?;
+
+                            next = block.next();
+                        }
+                        None => writeln!(w, "  indirect {:#x}: error: corrupted", address)?,
+                    },
+                    Err(e) => writeln!(w, "  indirect {:#x}: error: {:?}", address, e)?,
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Add an inode to a tag
+    pub fn apply_tag(&mut self, tag_index: u64, inode_index: u64) -> Result<(), VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let inode = self.inodes[self.locate_inode(inode_index)?];
+
+        // Locate the tag in the memory map from the disk index provided
+        let mut tag_self_index = None;
+
+        // Find the local index of this tag
+        for (i, tag) in self.tags.iter().enumerate() {
+            if tag.index() == tag_index {
+                tag_self_index = Some(i);
+                break;
+            }
+        }
+
+        // Unwrap it to ensure we could find it
+        let tag_self_index = match tag_self_index {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let tag_index = self.tags[tag_self_index].index();
+        self.load_tag_members(tag_self_index)?;
+
+        // The index holds every member so duplicates are found without reading the chain
+        let members = self.membership.get(tag_index).unwrap();
+        let tail = members.tail();
+
+        if members.contains(inode.index()) {
+            return Err(VoxFSError::TagAlreadyAppliedToINode);
+        }
+
+        self.extend_placement_hint(tag_index, &inode)?;
+
+        // This checks if we have enough space in the tag block itself to add a new member
+        // if not it is appended to the last indirect tag block, creating a new one if that is full
+        if self.tags[tag_self_index].number_of_pointers() < TagBlock::MAXIMUM_LOCAL_MEMBERS {
+            self.tags[tag_self_index].append_member(inode.index());
+
+            self.write_tag(self.tags[tag_self_index])?;
+            self.membership
+                .insert_member(tag_index, inode.index(), tail);
+            self.listing_cache.get_mut().forget(tag_index);
+
+            return self.record_tag_change(HistoryOperation::ApplyTag, tag_self_index, inode_index);
+        }
+
+        self.append_to_chain(tag_self_index, inode.index(), tail)?;
+
+        return self.record_tag_change(HistoryOperation::ApplyTag, tag_self_index, inode_index);
+    }
+
+    /// Appends a member to the last indirect block of a tag, creating a new block if that is full. The tail is
+    /// the last block of the chain and the number of members in it, from the tag's entry in the membership index.
+    fn append_to_chain(
+        &mut self,
+        tag_self_index: usize,
+        inode_index: u64,
+        tail: Option<(u64, u16)>,
+    ) -> Result<(), VoxFSError<E>> {
+        let tag_index = self.tags[tag_self_index].index();
+        let capacity = IndirectTagBlock::max_members_for_blocksize(self.block_size);
+
+        match tail {
+            Some((address, count)) if (count as u64) < capacity => {
+                // Read the indirect block and check that it was valid
+                let mut indirect = match IndirectTagBlock::from_bytes(
+                    &self.read_from_address(address, self.block_size)?,
+                ) {
+                    Some(i) => i,
+                    None => return Err(VoxFSError::CorruptedIndirectTag),
+                };
+
+                // Set the blocksize so we can use the append_member function
+                indirect.set_block_size(self.block_size);
+
+                // Append a member
+                if !indirect.append_member(inode_index) {
+                    return Err(VoxFSError::FailedIndirectTagAppend);
+                }
+
+                // rewrite the new indirect block
+                self.write_to_data_region(
+                    address,
+                    &indirect.to_bytes_padded(self.block_size as usize),
+                )?;
+                self.membership
+                    .insert_member(tag_index, inode_index, Some((address, count + 1)));
+                self.listing_cache.get_mut().forget(tag_index);
+            }
+            _ => {
+                // Create a new indirect tag
+                let indirect_tag =
+                    IndirectTagBlock::new(tag_index, vec![inode_index], 0, self.block_size);
+
+                // Find a spot for it
+                let index = match self.find_block() {
+                    Some(index) => index,
+                    None => return Err(VoxFSError::NotEnoughFreeDataBlocks),
+                };
+
+                let location = self.data_index_to_address(index);
+
+                // Mark the block as taken
+                self.block_bitmap.set_bit(index as usize, true);
+
+                // Write the new indirect block
+                self.write_to_data_region(
+                    location,
+                    &indirect_tag.to_bytes_padded(self.block_size as usize),
+                )?;
+
+                // Write the bitmaps
+                self.write_bitmaps()?;
+
+                // We need to point to this new block somehow...
+                // Set the previous last indirect block to point to this or the root tag
+                match tail {
+                    Some((address, _)) => {
+                        let mut previous = match IndirectTagBlock::from_bytes(
+                            &self.read_from_address(address, self.block_size)?,
+                        ) {
+                            Some(i) => i,
+                            None => return Err(VoxFSError::CorruptedIndirectTag),
+                        };
+
+                        previous.set_next(location);
+
+                        self.write_to_data_region(
+                            address,
+                            &previous.to_bytes_padded(self.block_size as usize),
+                        )?;
+                    }
+                    None => {
+                        self.tags[tag_self_index].set_indirect(location);
+
+                        self.write_tag(self.tags[tag_self_index])?;
+                    }
+                }
+
+                self.membership
+                    .insert_member(tag_index, inode_index, Some((location, 1)));
+                self.listing_cache.get_mut().forget(tag_index);
+            }
+        }
+
         return Ok(());
     }
 
@@ -574,9 +2083,22 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         inode_index: u64,
         prune: bool,
     ) -> Result<(), VoxFSError<E>> {
-        // Locate the inode
-        let inode = self.inodes[self.locate_inode(inode_index)?];
+        self.mark_dirty()?;
+        self.locate_inode(inode_index)?;
+
+        let tag_local_index = self.remove_member(tag_index, inode_index, prune)?;
 
+        return self.record_tag_change(HistoryOperation::RemoveTag, tag_local_index, inode_index);
+    }
+
+    /// Removes an inode from a tag's members without recording it in the history, returning where the tag is
+    /// in memory.
+    fn remove_member(
+        &mut self,
+        tag_index: u64,
+        inode_index: u64,
+        prune: bool,
+    ) -> Result<usize, VoxFSError<E>> {
         let mut tag = None;
         let mut tag_local_index = None;
 
@@ -599,17 +2121,20 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         let mut found = false;
         let members = tag.members();
 
-        // Find the index of the member within the members of this tag and remove it
-        for (i, node_index) in members.iter().enumerate() {
-            if *node_index == inode.index() {
+        // Find the index of the member within the members of this tag and remove it, the unused
+        // slots after the members are zeroed so they must be skipped
+        for (i, node_index) in members[..tag.number_of_pointers() as usize]
+            .iter()
+            .enumerate()
+        {
+            if *node_index == inode_index {
                 found = true;
                 self.tags[tag_local_index].remove_member_at(i as u16);
 
                 // Write the tag
-                self.write_to_address(
-                    self.tag_index_to_address(self.tags[tag_local_index].index()),
-                    &self.tags[tag_local_index].to_bytes().to_vec(),
-                )?;
+                self.write_tag(self.tags[tag_local_index])?;
+                self.membership.remove_member(tag_index, inode_index, None);
+                self.listing_cache.get_mut().forget(tag_index);
 
                 break;
             }
@@ -635,7 +2160,7 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
 
                 // Parse the members and remove the target member if found
                 for (i, member) in members.iter().enumerate() {
-                    if *member == inode.index() {
+                    if *member == inode_index {
                         block.remove_member_at(i as u16);
                         found = true;
                         break;
@@ -661,7 +2186,7 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                                 // Write the bitmaps
                                 self.write_bitmaps()?;
                                 // Write the parent block overtop the old one
-                                self.write_to_address(
+                                self.write_to_data_region(
                                     parent_address.unwrap(),
                                     &cp.to_bytes_padded(self.block_size as usize),
                                 )?;
@@ -678,18 +2203,22 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                                 self.write_bitmaps()?;
 
                                 // Update the tag block with the new details
-                                self.write_to_address(
-                                    self.tag_index_to_address(self.tags[tag_local_index].index()),
-                                    &self.tags[tag_local_index].to_bytes().to_vec(),
-                                )?;
+                                self.write_tag(self.tags[tag_local_index])?;
                             }
                         }
+
+                        // The end of the chain may have moved so it is read again when needed
+                        self.membership.forget(tag_index);
+                        self.listing_cache.get_mut().forget(tag_index);
                     } else {
                         // Otherwise just update this block
-                        self.write_to_address(
+                        self.write_to_data_region(
                             address,
                             &block.to_bytes_padded(self.block_size as usize),
                         )?;
+                        self.membership
+                            .remove_member(tag_index, inode_index, Some(address));
+                        self.listing_cache.get_mut().forget(tag_index);
                     }
                 } else {
                     // If we didn't find the block set the parent for the next indirect block to be the
@@ -705,86 +2234,520 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             }
         }
 
-        return Ok(());
+        return Ok(tag_local_index);
     }
 
-    /// List the inodes on the disk, that are members of a tag
-    pub fn list_nodes_with_tag(&self, tag_index: u64) -> Result<Vec<INode>, VoxFSError<E>> {
-        let mut tag = None;
+    /// Records a tag being applied to or removed from a file.
+    fn record_tag_change(
+        &mut self,
+        operation: HistoryOperation,
+        tag_local_index: usize,
+        inode_index: u64,
+    ) -> Result<(), VoxFSError<E>> {
+        let tag = self.tags[tag_local_index];
 
-        // Locate the tag in the memory map
-        for t in &self.tags {
-            if t.index() == tag_index {
-                tag = Some(t);
-                break;
-            }
-        }
+        return self.record_history(
+            operation,
+            tag.index(),
+            Some(inode_index),
+            &tag.name_string(),
+        );
+    }
 
-        // Ensure we found it
-        if tag.is_none() {
-            return Err(VoxFSError::CouldNotFindTag);
+    /// Rewrites a tag's members into as few indirect tag blocks as possible and frees the blocks no longer needed.
+    /// Removing members only frees indirect blocks that become completely empty, so this can be run occasionally
+    /// to reclaim half empty chains. Returns the number of blocks freed.
+    pub fn compact_tag(&mut self, tag_index: u64) -> Result<u64, VoxFSError<E>> {
+        let tag_local_index = match self.tags.iter().position(|t| t.index() == tag_index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let tag = self.tags[tag_local_index];
+        let (members, chain) = self.read_tag_chain(&tag)?;
+
+        let local_count = core::cmp::min(members.len(), TagBlock::MAXIMUM_LOCAL_MEMBERS as usize);
+        let capacity = IndirectTagBlock::max_members_for_blocksize(self.block_size) as usize;
+        let groups = (members.len() - local_count).div_ceil(capacity);
+
+        // Nothing to do if the members are already packed
+        if groups == chain.len() && local_count == tag.number_of_pointers() as usize {
+            return Ok(0);
         }
 
-        return self.load_nodes_with_tag(&tag.unwrap());
+        self.mark_dirty()?;
+
+        let freed = self.write_tag_chain(tag_local_index, &members, chain)?;
+
+        self.record_history(
+            HistoryOperation::CompactTag,
+            tag_index,
+            None,
+            &self.tags[tag_local_index].name_string(),
+        )?;
+
+        return Ok(freed);
     }
 
-    /// List the inodes on the disk, that are members of a tag
-    fn load_nodes_with_tag(&self, tag: &TagBlock) -> Result<Vec<INode>, VoxFSError<E>> {
-        let mut nodes = Vec::new();
+    /// Adds every member of the source tag to the destination tag then deletes the source tag.
+    /// The destination's chain is rewritten once rather than applying the tag to each member in turn.
+    /// Returns the number of members that were not already in the destination.
+    pub fn merge_tags(&mut self, src_tag: u64, dst_tag: u64) -> Result<u64, VoxFSError<E>> {
+        let moved = self.move_members(src_tag, dst_tag, &|_| true)?;
+        self.delete_tag(src_tag)?;
 
-        match tag.indirect_pointer() {
-            Some(indirect_address) => {
-                let mut number_of_pointers = tag.number_of_pointers() as usize;
-                let mut members = tag.members()[..number_of_pointers].to_vec();
+        return Ok(moved);
+    }
 
-                // Locate the member nodes and store them from the tag
-                for node in &self.inodes {
-                    // Break if we found everything
-                    if number_of_pointers == 0 {
-                        break;
-                    }
+    /// Moves the members of the source tag that the filter accepts to the destination tag, members the
+    /// destination already has are only removed from the source. Both chains are rewritten once and packed.
+    /// Returns the number of members that were not already in the destination.
+    pub fn move_members(
+        &mut self,
+        src_tag: u64,
+        dst_tag: u64,
+        filter: &dyn Fn(&INode) -> bool,
+    ) -> Result<u64, VoxFSError<E>> {
+        if src_tag == dst_tag {
+            return Err(VoxFSError::SameSourceAndDestinationTag);
+        }
 
-                    for i in 0..number_of_pointers {
-                        if node.index() == members[i] {
-                            nodes.push(*node);
-                            members.remove(i);
-                            number_of_pointers -= 1;
-                            break;
-                        }
-                    }
-                }
+        let src_local_index = match self.tags.iter().position(|t| t.index() == src_tag) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
 
-                let mut next_address = Some(indirect_address);
+        let dst_local_index = match self.tags.iter().position(|t| t.index() == dst_tag) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
 
-                // Process the indirect blocks
-                while next_address.is_some() {
-                    // Read the block and check it
-                    let bytes = self.read_from_address(next_address.unwrap(), self.block_size)?;
-                    let block = match IndirectTagBlock::from_bytes(&bytes) {
-                        Some(b) => b,
-                        None => return Err(VoxFSError::CorruptedIndirectTag),
-                    };
+        self.mark_dirty()?;
 
-                    let mut block_members = block.members();
+        let (src_members, src_chain) = self.read_tag_chain(&self.tags[src_local_index])?;
+        let (mut dst_members, dst_chain) = self.read_tag_chain(&self.tags[dst_local_index])?;
 
-                    // Process the members by locating the loaded inodes in the memory map
-                    for node in &self.inodes {
-                        if block_members.len() == 0 {
-                            break;
-                        }
+        let existing: BTreeSet<u64> = dst_members.iter().cloned().collect();
+        let mut kept = Vec::new();
+        let mut moved = 0;
 
-                        for i in 0..block_members.len() {
-                            if node.index() == block_members[i] {
-                                nodes.push(*node);
-                                block_members.remove(i);
-                                break;
-                            }
-                        }
-                    }
+        let inodes: BTreeMap<u64, &INode> = self.inodes.iter().map(|i| (i.index(), i)).collect();
 
-                    next_address = block.next();
-                }
-            }
+        for member in src_members {
+            let selected = match inodes.get(&member) {
+                Some(inode) => filter(inode),
+                None => false,
+            };
+
+            if !selected {
+                kept.push(member);
+            } else if !existing.contains(&member) {
+                dst_members.push(member);
+                moved += 1;
+            }
+        }
+
+        // The source is rewritten first so any blocks it frees can be used by the destination
+        self.write_tag_chain(src_local_index, &kept, src_chain)?;
+        self.write_tag_chain(dst_local_index, &dst_members, dst_chain)?;
+
+        self.record_history(
+            HistoryOperation::MoveTagMembers,
+            src_tag,
+            Some(dst_tag),
+            &self.tags[src_local_index].name_string(),
+        )?;
+
+        return Ok(moved);
+    }
+
+    /// Reads every member of a tag in order along with the addresses of the indirect blocks in its chain.
+    fn read_tag_chain(&self, tag: &TagBlock) -> Result<(Vec<u64>, Vec<u64>), VoxFSError<E>> {
+        let mut members = tag.members()[..tag.number_of_pointers() as usize].to_vec();
+        let mut chain = Vec::new();
+        let mut next = tag.indirect_pointer();
+
+        while let Some(address) = next {
+            let bytes = self.read_from_address(address, self.block_size)?;
+
+            let block = match self.parse_record::<IndirectTagBlock>(&bytes) {
+                Some(b) => b,
+                None => return Err(VoxFSError::CorruptedIndirectTag),
+            };
+
+            members.extend(block.members());
+            chain.push(address);
+            next = block.next();
+        }
+
+        return Ok((members, chain));
+    }
+
+    /// Replaces a tag's members, packing them into the tag and as few indirect blocks as possible. The blocks of
+    /// the old chain are reused first, more are allocated if needed and any left over are freed.
+    /// Returns the number of blocks freed.
+    fn write_tag_chain(
+        &mut self,
+        tag_local_index: usize,
+        members: &[u64],
+        mut chain: Vec<u64>,
+    ) -> Result<u64, VoxFSError<E>> {
+        let tag = self.tags[tag_local_index];
+
+        let local_count = core::cmp::min(members.len(), TagBlock::MAXIMUM_LOCAL_MEMBERS as usize);
+        let capacity = IndirectTagBlock::max_members_for_blocksize(self.block_size) as usize;
+        let groups: Vec<&[u64]> = members[local_count..].chunks(capacity).collect();
+
+        if groups.len() > chain.len() {
+            let needed = groups.len() - chain.len();
+
+            if self.free_block_count() < needed {
+                return Err(VoxFSError::NotEnoughFreeDataBlocks);
+            }
+
+            for _ in 0..needed {
+                let index = match self.find_block() {
+                    Some(index) => index,
+                    None => return Err(VoxFSError::NotEnoughFreeDataBlocks),
+                };
+
+                self.block_bitmap.set_bit(index as usize, true);
+                chain.push(self.data_index_to_address(index));
+            }
+        }
+
+        for (i, group) in groups.iter().enumerate() {
+            let next = match chain.get(i + 1) {
+                Some(address) if i + 1 < groups.len() => *address,
+                _ => 0,
+            };
+
+            let block = IndirectTagBlock::new(tag.index(), group.to_vec(), next, self.block_size);
+            self.write_to_data_region(chain[i], &block.to_bytes_padded(self.block_size as usize))?;
+        }
+
+        let mut new_tag = tag;
+
+        while new_tag.number_of_pointers() > 0 {
+            new_tag.remove_member_at(0);
+        }
+
+        for member in members[..local_count].iter() {
+            new_tag.append_member(*member);
+        }
+
+        if groups.is_empty() {
+            new_tag.set_indirect_optional(None);
+        } else {
+            new_tag.set_indirect(chain[0]);
+        }
+
+        self.write_tag(new_tag)?;
+        self.tags[tag_local_index] = new_tag;
+
+        let tail = groups
+            .last()
+            .map(|group| (chain[groups.len() - 1], group.len() as u16));
+        let members = self.split_stale_members(members.iter().cloned().collect(), tail);
+        self.membership.load(tag.index(), members);
+        self.listing_cache.get_mut().forget(tag.index());
+        self.placement_hints.get_mut().remove(&tag.index());
+
+        // Free the blocks that are no longer part of the chain
+        for address in chain[groups.len()..].iter() {
+            let index = self.address_to_data_index(*address);
+            self.block_bitmap.set_bit(index as usize, false);
+        }
+
+        self.write_bitmaps()?;
+
+        return Ok((chain.len() - groups.len()) as u64);
+    }
+
+    /// Reads a tag's members and the end of its indirect chain into the membership index if they are not there.
+    fn load_tag_members(&mut self, tag_local_index: usize) -> Result<(), VoxFSError<E>> {
+        let tag = self.tags[tag_local_index];
+
+        if self.membership.get(tag.index()).is_some() {
+            return Ok(());
+        }
+
+        let mut members: BTreeSet<u64> = tag.members()[..tag.number_of_pointers() as usize]
+            .iter()
+            .cloned()
+            .collect();
+        let mut tail = None;
+        let mut next = tag.indirect_pointer();
+
+        while let Some(address) = next {
+            let bytes = self.read_from_address(address, self.block_size)?;
+
+            let block = match self.parse_record::<IndirectTagBlock>(&bytes) {
+                Some(b) => b,
+                None => return Err(VoxFSError::CorruptedIndirectTag),
+            };
+
+            members.extend(block.members());
+            tail = Some((address, block.number_of_members()));
+            next = block.next();
+        }
+
+        let members = self.split_stale_members(members, tail);
+        self.membership.load(tag.index(), members);
+
+        return Ok(());
+    }
+
+    /// Separates the indexes a tag lists whose files no longer exist, left behind when a crash interrupts
+    /// `delete_file` after the inode is freed but before its tags are rewritten.
+    fn split_stale_members(&self, members: BTreeSet<u64>, tail: Option<(u64, u16)>) -> TagMembers {
+        let (live, stale) = members
+            .into_iter()
+            .partition(|member| self.inode_bitmap.bit_at(*member as usize).unwrap_or(false));
+
+        return TagMembers::new(live, stale, tail);
+    }
+
+    /// Removes a free inode index from every tag that still lists it, so a new file given the index doesn't
+    /// inherit the tags of the file deleted before it.
+    fn clear_stale_members(&mut self, inode_index: u64) -> Result<(), VoxFSError<E>> {
+        for i in 0..self.tags.len() {
+            self.load_tag_members(i)?;
+        }
+
+        for tag_index in self.membership.stale_tags(inode_index) {
+            self.remove_member(tag_index, inode_index, true)?;
+        }
+
+        return Ok(());
+    }
+
+    /// Returns the tags that have been applied to an inode.
+    /// The first call reads every tag's members, after which the answer comes from memory.
+    pub fn tags_for_inode(&mut self, inode_index: u64) -> Result<Vec<TagBlock>, VoxFSError<E>> {
+        self.locate_inode(inode_index)?;
+
+        for i in 0..self.tags.len() {
+            self.load_tag_members(i)?;
+        }
+
+        let indices = self.membership.tags_for(inode_index);
+
+        return Ok(self
+            .tags
+            .iter()
+            .filter(|t| indices.contains(&t.index()))
+            .cloned()
+            .collect());
+    }
+
+    /// Returns the number of files a tag has been applied to.
+    pub fn tag_member_count(&mut self, tag_index: u64) -> Result<u64, VoxFSError<E>> {
+        let tag_local_index = match self.tags.iter().position(|t| t.index() == tag_index) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        self.load_tag_members(tag_local_index)?;
+
+        return Ok(self.membership.get(tag_index).unwrap().len() as u64);
+    }
+
+    /// List the inodes on the disk, that are members of a tag
+    pub fn list_nodes_with_tag(&self, tag_index: u64) -> Result<Vec<INode>, VoxFSError<E>> {
+        let mut tag = None;
+
+        // Locate the tag in the memory map
+        for t in &self.tags {
+            if t.index() == tag_index {
+                tag = Some(t);
+                break;
+            }
+        }
+
+        // Ensure we found it
+        if tag.is_none() {
+            return Err(VoxFSError::CouldNotFindTag);
+        }
+
+        return self.load_nodes_with_tag(&tag.unwrap());
+    }
+
+    /// Lists the inodes that are members of a tag in a given order.
+    pub fn list_nodes_with_tag_sorted(
+        &self,
+        tag_index: u64,
+        order: SortOrder,
+    ) -> Result<Vec<INode>, VoxFSError<E>> {
+        let mut inodes = self.list_nodes_with_tag(tag_index)?;
+        order.sort(&mut inodes);
+
+        return Ok(inodes);
+    }
+
+    /// The names of a tag's members and their indexes in name order, for serving the tag like a directory.
+    /// The listing is kept in memory until the tag's members change. Of members sharing a name only the one with
+    /// the lowest index is listed.
+    pub fn tag_listing(&self, tag_index: u64) -> Result<Vec<(String, u64)>, VoxFSError<E>> {
+        return self.with_tag_listing(tag_index, |listing| {
+            return listing
+                .iter()
+                .map(|(name, index)| (name.clone(), *index))
+                .collect();
+        });
+    }
+
+    /// Finds the index of the member of a tag with a name using the tag's listing, see `tag_listing`.
+    pub fn lookup_in_tag(&self, tag_index: u64, name: &str) -> Result<Option<u64>, VoxFSError<E>> {
+        return self.with_tag_listing(tag_index, |listing| listing.get(name).cloned());
+    }
+
+    /// Forgets the tag listings, tag members, data blocks and file sizes kept in memory, so each is read from
+    /// the disk again when it is next needed.
+    pub fn invalidate_caches(&mut self) {
+        self.listing_cache.get_mut().clear();
+        self.placement_hints.get_mut().clear();
+        self.membership = TagIndex::new();
+        self.physical_blocks.get_mut().clear();
+        self.verified_files.get_mut().clear();
+
+        let capacity = self.block_cache.get_mut().capacity();
+        self.block_cache = RefCell::new(BlockCache::new(capacity));
+    }
+
+    /// Calls the function with a tag's listing, building it from the tag's members if it isn't cached.
+    fn with_tag_listing<T>(
+        &self,
+        tag_index: u64,
+        function: impl FnOnce(&BTreeMap<String, u64>) -> T,
+    ) -> Result<T, VoxFSError<E>> {
+        if let Some(listing) = self.listing_cache.borrow().get(tag_index) {
+            return Ok(function(listing));
+        }
+
+        let mut listing: BTreeMap<String, u64> = BTreeMap::new();
+
+        for inode in self.list_nodes_with_tag(tag_index)? {
+            let index = listing.entry(inode.name()).or_insert(inode.index());
+            *index = core::cmp::min(*index, inode.index());
+        }
+
+        let result = function(&listing);
+        self.listing_cache.borrow_mut().insert(tag_index, listing);
+
+        return Ok(result);
+    }
+
+    /// Lists up to `limit` members of a tag, skipping the first `offset`, in the order they were tagged.
+    /// Indirect tag blocks past the end of the page aren't read.
+    pub fn list_nodes_with_tag_page(
+        &self,
+        tag_index: u64,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<INode>, VoxFSError<E>> {
+        let tag = match self.tags.iter().find(|t| t.index() == tag_index) {
+            Some(t) => *t,
+            None => return Err(VoxFSError::CouldNotFindTag),
+        };
+
+        let mut nodes = Vec::new();
+        let mut skipped = 0;
+        let mut members = tag.members()[..tag.number_of_pointers() as usize].to_vec();
+        let mut next = tag.indirect_pointer();
+
+        loop {
+            for member in members {
+                if nodes.len() >= limit {
+                    return Ok(nodes);
+                }
+
+                let local_index = match self.locate_inode(member) {
+                    Ok(i) => i,
+                    Err(_) => continue,
+                };
+
+                if skipped < offset {
+                    skipped += 1;
+                } else {
+                    nodes.push(self.inodes[local_index]);
+                }
+            }
+
+            let address = match next {
+                Some(a) if nodes.len() < limit => a,
+                _ => return Ok(nodes),
+            };
+
+            let bytes = self.read_from_address(address, self.block_size)?;
+
+            let block = match self.parse_record::<IndirectTagBlock>(&bytes) {
+                Some(b) => b,
+                None => return Err(VoxFSError::CorruptedIndirectTag),
+            };
+
+            members = block.members();
+            next = block.next();
+        }
+    }
+
+    /// List the inodes on the disk, that are members of a tag
+    fn load_nodes_with_tag(&self, tag: &TagBlock) -> Result<Vec<INode>, VoxFSError<E>> {
+        let mut nodes = Vec::new();
+
+        match tag.indirect_pointer() {
+            Some(indirect_address) => {
+                let mut number_of_pointers = tag.number_of_pointers() as usize;
+                let mut members = tag.members()[..number_of_pointers].to_vec();
+
+                // Locate the member nodes and store them from the tag
+                for node in &self.inodes {
+                    // Break if we found everything
+                    if number_of_pointers == 0 {
+                        break;
+                    }
+
+                    for i in 0..number_of_pointers {
+                        if node.index() == members[i] {
+                            nodes.push(*node);
+                            members.remove(i);
+                            number_of_pointers -= 1;
+                            break;
+                        }
+                    }
+                }
+
+                let mut next_address = Some(indirect_address);
+
+                // Process the indirect blocks
+                while next_address.is_some() {
+                    // Read the block and check it
+                    let bytes = self.read_from_address(next_address.unwrap(), self.block_size)?;
+                    let block = match self.parse_record::<IndirectTagBlock>(&bytes) {
+                        Some(b) => b,
+                        None => return Err(VoxFSError::CorruptedIndirectTag),
+                    };
+
+                    let mut block_members = block.members();
+
+                    // Process the members by locating the loaded inodes in the memory map
+                    for node in &self.inodes {
+                        if block_members.len() == 0 {
+                            break;
+                        }
+
+                        for i in 0..block_members.len() {
+                            if node.index() == block_members[i] {
+                                nodes.push(*node);
+                                block_members.remove(i);
+                                break;
+                            }
+                        }
+                    }
+
+                    next_address = block.next();
+                }
+            }
             None => {
                 // If there was no indirect tag block
                 let mut number_of_pointers = tag.number_of_pointers() as usize;
@@ -863,6 +2826,77 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         return Ok(nodes);
     }
 
+    /// Lists the inodes whose tags match a query, in index order.
+    /// Every tag named in the query must exist.
+    pub fn query_tags(&mut self, query: &TagQuery) -> Result<Vec<INode>, VoxFSError<E>> {
+        let mut tag_indices = BTreeMap::new();
+        let mut missing = Vec::new();
+
+        for name in query.tag_names() {
+            match self.tags.iter().position(|t| t.same_name(name)) {
+                Some(i) => {
+                    self.load_tag_members(i)?;
+                    tag_indices.insert(name, self.tags[i].index());
+                }
+                None => missing.push(name.to_string()),
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(VoxFSError::NoTagsWithNames(missing));
+        }
+
+        let mut inodes: Vec<INode> = self
+            .inodes
+            .iter()
+            .filter(|inode| {
+                query.matches(&|name: &str| {
+                    let members = self.membership.get(tag_indices[name]).unwrap();
+                    members.contains(inode.index())
+                })
+            })
+            .cloned()
+            .collect();
+
+        SortOrder::Index.sort(&mut inodes);
+
+        return Ok(inodes);
+    }
+
+    /// Lists the inodes that no tag has been applied to, in index order.
+    /// Every tag's members are read into the membership index so each inode is a single lookup.
+    pub fn list_untagged_inodes(&mut self) -> Result<Vec<INode>, VoxFSError<E>> {
+        for i in 0..self.tags.len() {
+            self.load_tag_members(i)?;
+        }
+
+        let mut inodes: Vec<INode> = self
+            .inodes
+            .iter()
+            .filter(|inode| !self.membership.is_tagged(inode.index()))
+            .cloned()
+            .collect();
+
+        SortOrder::Index.sort(&mut inodes);
+
+        return Ok(inodes);
+    }
+
+    /// Lists the inodes whose names match a pattern, in index order.
+    pub fn find_inodes(&self, pattern: &NamePattern) -> Vec<INode> {
+        return self
+            .list_inodes_sorted(SortOrder::Index)
+            .into_iter()
+            .filter(|inode| pattern.matches(&inode.name()))
+            .collect();
+    }
+
+    /// Lists the file names that are duplicated, collide when case is ignored or have characters that could cause
+    /// trouble, with the longest names, to clean up before the files are shown through another interface.
+    pub fn name_report(&self) -> NameReport {
+        return NameReport::new(&self.list_inodes_sorted(SortOrder::Index));
+    }
+
     /// Returns the inode index with the file name.
     pub fn inode_with_name(&self, name: &str) -> Option<u64> {
         for inode in &self.inodes {
@@ -916,32 +2950,812 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         return None;
     }
 
-    /// Creates a new file in the first available index in the first available INode location.
-    /// A copy of the inode is returned but the original is stored in the disk.
-    pub fn create_new_file(
-        &mut self,
-        name: &str,
-        flags: INodeFlags,
-        contents: Vec<u8>,
-    ) -> Result<INode, VoxFSError<E>> {
-        self.validate_name(name, VoxFSError::InvalidFileName)?;
+    /// Hashes the boot area, every tag and its members, every file's metadata and the stored blocks of every
+    /// extent, so a consumer of a distributed image can check it hasn't been changed. Fails with
+    /// `VoxFSError::NoContentHasher` if the manager can't hash.
+    pub fn export_integrity_manifest(&self) -> Result<IntegrityManifest, VoxFSError<E>> {
+        let mut entries = Vec::new();
 
-        // Check if a file already exists with this name.
-        if self.inode_with_name(name).is_some() {
-            return Err(VoxFSError::FileExistsWithName(name.to_string()));
+        if self.boot_area_size() > 0 {
+            let boot_area = self.read_boot_area()?;
+            entries.push((ManifestRegion::BootArea, self.hash_parts(&[&boot_area])?));
         }
 
-        // Find a free space to store the inode on the disk
-        let inode_index = match self
-            .inode_bitmap
+        for tag in self.tags.iter() {
+            let mut members: Vec<u64> = self
+                .list_nodes_with_tag(tag.index())?
+                .iter()
+                .map(|inode| inode.index())
+                .collect();
+            members.sort();
+
+            let mut bytes: Vec<u8> = tag.name_string().as_bytes().to_vec();
+            bytes.push(0);
+            bytes.push(tag.flags().as_u8());
+
+            for member in members {
+                bytes.extend_from_slice(&member.to_le_bytes());
+            }
+
+            entries.push((
+                ManifestRegion::Tag(tag.index()),
+                self.hash_parts(&[&bytes])?,
+            ));
+        }
+
+        for inode in self.inodes.iter() {
+            let mut bytes: Vec<u8> = inode.name().as_bytes().to_vec();
+            bytes.push(0);
+            bytes.extend_from_slice(&inode.file_size().to_le_bytes());
+            bytes.push(inode.flags().to_u8());
+            bytes.extend_from_slice(&inode.rdev().unwrap_or(0).to_le_bytes());
+
+            entries.push((
+                ManifestRegion::INode(inode.index()),
+                self.hash_parts(&[&bytes])?,
+            ));
+
+            if !inode.flags().file_type().has_contents() {
+                continue;
+            }
+
+            for extent in self.file_extents(inode)? {
+                let blocks = (extent.start..=extent.end)
+                    .map(|i| self.read_data_block(i))
+                    .collect::<Result<Vec<Vec<u8>>, VoxFSError<E>>>()?;
+                let parts: Vec<&[u8]> = blocks.iter().map(|b| b.as_slice()).collect();
+
+                entries.push((
+                    ManifestRegion::Extent {
+                        inode: inode.index(),
+                        start: extent.start,
+                        end: extent.end,
+                    },
+                    self.hash_parts(&parts)?,
+                ));
+            }
+        }
+
+        return Ok(IntegrityManifest::new(entries));
+    }
+
+    /// Compares the disk against a manifest exported from the image it was distributed as, returning the regions
+    /// that changed, were removed or were added. The disk matches the manifest if none are returned.
+    pub fn verify_against_manifest(
+        &self,
+        manifest: &IntegrityManifest,
+    ) -> Result<Vec<ManifestRegion>, VoxFSError<E>> {
+        return Ok(manifest.differences(&self.export_integrity_manifest()?));
+    }
+
+    /// Hashes byte slices one after another with the manager's content hasher.
+    fn hash_parts(&self, parts: &[&[u8]]) -> Result<[u8; 32], VoxFSError<E>> {
+        let mut hasher = match self.manager.content_hasher() {
+            Some(h) => h,
+            None => return Err(VoxFSError::NoContentHasher),
+        };
+
+        for part in parts {
+            hasher.update(part);
+        }
+
+        return Ok(hasher.finish());
+    }
+
+    /// Describes the tags and the names of the files each is applied to, sorted so that the same tags always
+    /// produce the same manifest.
+    #[cfg(feature = "std")]
+    pub fn export_manifest(&self) -> Result<Manifest, VoxFSError<E>> {
+        let mut tags = Vec::new();
+
+        for tag in self.tags.iter() {
+            let mut files: Vec<String> = self
+                .list_nodes_with_tag(tag.index())?
+                .iter()
+                .map(|inode| inode.name())
+                .collect();
+            files.sort();
+
+            tags.push(ManifestTag {
+                name: tag.name_string(),
+                read: tag.flags().read(),
+                write: tag.flags().write(),
+                files,
+            });
+        }
+
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        return Ok(Manifest { tags });
+    }
+
+    /// Creates the tags in a manifest that don't exist and applies them to the files with the names listed.
+    /// Nothing is removed, so tags and assignments not in the manifest are kept. Files that can't be found are
+    /// skipped and listed in the report.
+    #[cfg(feature = "std")]
+    pub fn apply_manifest(&mut self, manifest: &Manifest) -> Result<ManifestReport, VoxFSError<E>> {
+        let mut report = ManifestReport::new();
+
+        for manifest_tag in manifest.tags.iter() {
+            let tag_index = match self.tag_with_name(&manifest_tag.name) {
+                Some(i) => i,
+                None => {
+                    let flags = TagFlags::new(manifest_tag.read, manifest_tag.write);
+                    let tag = self.create_new_tag(&manifest_tag.name, flags)?;
+                    report.tag_created(&manifest_tag.name);
+
+                    tag.index()
+                }
+            };
+
+            for file in manifest_tag.files.iter() {
+                let inode_index = match self.inode_with_name(file) {
+                    Some(i) => i,
+                    None => {
+                        report.file_missing(file);
+                        continue;
+                    }
+                };
+
+                match self.apply_tag(tag_index, inode_index) {
+                    Ok(_) => report.tag_applied(),
+                    Err(VoxFSError::TagAlreadyAppliedToINode) => (),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        return Ok(report);
+    }
+
+    /// Creates a new file in the first available index in the first available INode location.
+    /// A copy of the inode is returned but the original is stored in the disk.
+    pub fn create_new_file(
+        &mut self,
+        name: &str,
+        flags: INodeFlags,
+        contents: Vec<u8>,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        return self.create_file(name, flags, &contents, None);
+    }
+
+    /// Creates a new file and applies a tag to it. The file's blocks are placed after those of the tag's other
+    /// members where there is room, so reading every file with the tag reads the disk in order.
+    pub fn create_new_file_in_tag(
+        &mut self,
+        name: &str,
+        flags: INodeFlags,
+        contents: Vec<u8>,
+        tag_index: u64,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let near = self.tag_placement_hint(tag_index)?;
+        let inode = self.create_file(name, flags, &contents, near)?;
+
+        // The file is deleted again rather than left behind without the tag
+        if let Err(e) = self.apply_tag(tag_index, inode.index()) {
+            return Err(self.undo_batch(&[inode], e));
+        }
+
+        return Ok(inode);
+    }
+
+    /// The block after the last data block of the tag's members, where new files with the tag are placed.
+    /// Only the first call for a tag reads its members' extents.
+    fn tag_placement_hint(&self, tag_index: u64) -> Result<Option<u64>, VoxFSError<E>> {
+        if let Some(hint) = self.placement_hints.borrow().get(&tag_index) {
+            return Ok(*hint);
+        }
+
+        let mut hint = None;
+
+        for inode in self.list_nodes_with_tag(tag_index)? {
+            for extent in self.file_extents(&inode)? {
+                hint = core::cmp::max(hint, Some(extent.end + 1));
+            }
+        }
+
+        self.placement_hints.borrow_mut().insert(tag_index, hint);
+
+        return Ok(hint);
+    }
+
+    /// Moves a tag's placement hint past the blocks of a file being added to it, if the hint has been worked out.
+    fn extend_placement_hint(
+        &mut self,
+        tag_index: u64,
+        inode: &INode,
+    ) -> Result<(), VoxFSError<E>> {
+        if !self.placement_hints.get_mut().contains_key(&tag_index) {
+            return Ok(());
+        }
+
+        let end = self
+            .file_extents(inode)?
+            .iter()
+            .map(|extent| extent.end + 1)
+            .max();
+
+        if let Some(hint) = self.placement_hints.get_mut().get_mut(&tag_index) {
+            *hint = core::cmp::max(*hint, end);
+        }
+
+        return Ok(());
+    }
+
+    /// Creates a number of files, checking before anything is written that they all fit and that their names and
+    /// tags are valid so either every file is created or none are. The tags are applied once every file has
+    /// been written. If writing fails part way the files already created are deleted again.
+    /// A file with tags is placed after the members of its first tag, as with `create_new_file_in_tag`.
+    pub fn import_batch(&mut self, entries: &[NewFileSpec]) -> Result<Vec<INode>, VoxFSError<E>> {
+        // Checked before the disk is marked dirty so a batch that can't be created leaves it untouched
+        let hints = self.check_batch(entries)?;
+        self.mark_dirty()?;
+
+        let mut created = Vec::with_capacity(entries.len());
+
+        for (entry, near) in entries.iter().zip(hints) {
+            match self.create_file(&entry.name, entry.flags, &entry.contents, near) {
+                Ok(inode) => created.push(inode),
+                Err(e) => return Err(self.undo_batch(&created, e)),
+            }
+        }
+
+        for (entry, inode) in entries.iter().zip(created.iter()) {
+            for tag_index in entry.tags.iter() {
+                match self.apply_tag(*tag_index, inode.index()) {
+                    Ok(_) | Err(VoxFSError::TagAlreadyAppliedToINode) => (),
+                    Err(e) => return Err(self.undo_batch(&created, e)),
+                }
+            }
+        }
+
+        return Ok(created);
+    }
+
+    /// Checks that every file in a batch can be created, allocating their blocks on a copy of the block bitmap
+    /// in the same way writing them would. Returns where each file's blocks should be placed.
+    fn check_batch(&mut self, entries: &[NewFileSpec]) -> Result<Vec<Option<u64>>, VoxFSError<E>> {
+        let mut names: Vec<&str> = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            self.validate_name(&entry.name, VoxFSError::InvalidFileName)?;
+
+            if !entry.flags.file_type().has_contents() {
+                return Err(VoxFSError::InvalidFileType);
+            }
+
+            let taken =
+                self.inode_with_name(&entry.name).is_some() || names.contains(&entry.name.as_str());
+
+            if taken && self.super_block.name_policy() == NamePolicy::Reject {
+                return Err(VoxFSError::FileExistsWithName(entry.name.clone()));
+            }
+
+            names.push(&entry.name);
+
+            for tag_index in entry.tags.iter() {
+                if !self.tags.iter().any(|t| t.index() == *tag_index) {
+                    return Err(VoxFSError::CouldNotFindTag);
+                }
+            }
+        }
+
+        let free_inodes = self
+            .inode_bitmap
+            .count_zeros_up_to(self.super_block.inode_count() as usize)
+            .unwrap_or(0);
+
+        if free_inodes < entries.len() {
+            return Err(VoxFSError::NoFreeInode);
+        }
+
+        let mut hints = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            hints.push(match entry.tags.first() {
+                Some(tag_index) => self.tag_placement_hint(*tag_index)?,
+                None => None,
+            });
+        }
+
+        let block_bitmap = self.block_bitmap.clone();
+        let fits = self.allocate_batch(entries, &hints);
+        self.block_bitmap = block_bitmap;
+
+        if !fits {
+            return Err(VoxFSError::NotEnoughFreeDataBlocks);
+        }
+
+        return Ok(hints);
+    }
+
+    /// Marks the blocks each file in a batch would use in the block bitmap, returning false if they run out.
+    fn allocate_batch(&mut self, entries: &[NewFileSpec], hints: &[Option<u64>]) -> bool {
+        for (entry, near) in entries.iter().zip(hints) {
+            let extents = match self.find_blocks(entry.contents.len() as u64, *near) {
+                Some(extents) => extents,
+                None => return false,
+            };
+
+            for (start, end) in extents.iter() {
+                for i in *start..=*end {
+                    self.block_bitmap.set_bit(i as usize, true);
+                }
+            }
+
+            for _ in 0..self.group_indirect_extents(&extents).len() {
+                match self
+                    .block_bitmap
+                    .find_next_0_index_up_to(self.super_block.block_count() as usize)
+                {
+                    Some(b) => self.block_bitmap.set_bit(b, true),
+                    None => return false,
+                };
+            }
+        }
+
+        return true;
+    }
+
+    /// Deletes the files created by a batch that failed, returning the error that stopped it.
+    fn undo_batch(&mut self, created: &[INode], error: VoxFSError<E>) -> VoxFSError<E> {
+        for inode in created {
+            // The original error is more useful than any from cleaning up
+            let _ = self.delete_file(inode.index());
+        }
+
+        return error;
+    }
+
+    /// Creates a file once the disk has been marked dirty, with its blocks placed at or after `near` if there is room.
+    fn create_file(
+        &mut self,
+        name: &str,
+        flags: INodeFlags,
+        contents: &[u8],
+        near: Option<u64>,
+    ) -> Result<INode, VoxFSError<E>> {
+        let (inode_index, name) = self.new_file_slot(name)?;
+
+        let (inode, physical_blocks) = self.write_contents_to_new_blocks(
+            inode_index as u64,
+            &name,
+            flags,
+            contents,
+            None,
+            near,
+        )?;
+
+        self.write_content_hash(inode_index as u64, contents)?;
+        self.write_inode(inode)?;
+        self.physical_blocks
+            .get_mut()
+            .insert(inode.index(), physical_blocks);
+
+        if !self.inode_bitmap.set_bit(inode_index, true) {
+            panic!("Unexpected fail."); // This should never happen but if it does then its a developer error so panic.
+        }
+
+        self.write_bitmaps()?;
+
+        self.inodes.push(inode);
+
+        self.record_history(
+            HistoryOperation::CreateFile,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        return Ok(inode);
+    }
+
+    /// Creates a file without contents, a device or a socket. The device number is only stored for devices.
+    /// Symbolic links are created with `create_new_file`, with the path they point to as their contents.
+    pub fn create_special(
+        &mut self,
+        name: &str,
+        file_type: FileType,
+        rdev: u64,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        if file_type.has_contents() {
+            return Err(VoxFSError::InvalidFileType);
+        }
+
+        let (inode_index, name) = self.new_file_slot(name)?;
+
+        let mut blocks = [Extent::zeroed(); 5];
+
+        if file_type == FileType::Device {
+            blocks[0].start = rdev;
+        }
+
+        let current_time = self.manager.current_time();
+        let inode = INode::new(
+            inode_index as u64,
+            &name,
+            0,
+            INodeFlags::default().with_file_type(file_type),
+            current_time,
+            current_time,
+            current_time,
+            0,
+            0,
+            blocks,
+        );
+
+        self.write_content_hash(inode.index(), &[])?;
+        self.write_inode(inode)?;
+        self.physical_blocks.get_mut().insert(inode.index(), 0);
+
+        if !self.inode_bitmap.set_bit(inode_index, true) {
+            panic!("Unexpected fail."); // This should never happen but if it does then its a developer error so panic.
+        }
+
+        self.write_bitmaps()?;
+
+        self.inodes.push(inode);
+
+        self.record_history(
+            HistoryOperation::CreateFile,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        return Ok(inode);
+    }
+
+    /// Validates a new file's name, applying the name policy if it is taken, and finds a free inode for it.
+    fn new_file_slot(&mut self, name: &str) -> Result<(usize, String), VoxFSError<E>> {
+        self.validate_name(name, VoxFSError::InvalidFileName)?;
+
+        // Check if a file already exists with this name.
+        let name = if self.inode_with_name(name).is_none() {
+            name.to_string()
+        } else {
+            match self.super_block.name_policy() {
+                NamePolicy::Reject => return Err(VoxFSError::FileExistsWithName(name.to_string())),
+                NamePolicy::Allow => name.to_string(),
+                NamePolicy::AutoSuffix => {
+                    let unique = self.unique_name(name);
+
+                    // A suffix can take the name past the disk's limit
+                    self.validate_name(&unique, VoxFSError::FileExistsWithName(name.to_string()))?;
+
+                    unique
+                }
+            }
+        };
+
+        // Find a free space to store the inode on the disk
+        let inode_index = match self
+            .inode_bitmap
             .find_next_0_index_up_to(self.super_block.inode_count() as usize)
         {
             Some(index) => index,
             None => return Err(VoxFSError::NoFreeInode),
         };
 
+        self.clear_stale_members(inode_index as u64)?;
+
+        return Ok((inode_index, name));
+    }
+
+    /// Replaces the contents and flags of the file with the name, keeping its index, tags and creation time.
+    /// The new contents are written to fresh blocks before the inode is rewritten, so the file on disk is either
+    /// entirely old or entirely new if the operation is interrupted. The old blocks are freed afterwards.
+    pub fn replace_file(
+        &mut self,
+        name: &str,
+        flags: INodeFlags,
+        contents: Vec<u8>,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let inode_index = match self.inode_with_name(name) {
+            Some(i) => i,
+            None => return Err(VoxFSError::CouldNotFindINode),
+        };
+
+        let local_index = self.locate_inode(inode_index)?;
+        let old = self.inodes[local_index];
+        self.check_replaceable(&old)?;
+
+        // Read the old layout before any of its blocks could be reused.
+        let (old_extents, old_indirect_indexes) = self.file_blocks(&old)?;
+
+        let (inode, physical_blocks) = self.write_contents_to_new_blocks(
+            inode_index,
+            &old.name(),
+            flags,
+            &contents,
+            Some(old.creation_time()),
+            None,
+        )?;
+
+        // A hash written before the inode can only be wrong if the write is interrupted
+        self.write_content_hash(inode_index, &contents)?;
+
+        // This single record write is the point at which the file changes.
+        self.write_inode(inode)?;
+        self.inodes[local_index] = inode;
+        self.physical_blocks
+            .get_mut()
+            .insert(inode_index, physical_blocks);
+
+        self.free_blocks(&old_extents, &old_indirect_indexes)?;
+        self.write_bitmaps()?;
+
+        self.record_history(
+            HistoryOperation::ReplaceFile,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        return Ok(inode);
+    }
+
+    /// Copies a file into new files of at most `chunk_size` bytes each, named after the file with the number of
+    /// the chunk appended, e.g. `video.000`, `video.001`. The file itself is left unchanged. Either every chunk is
+    /// created or none are, an empty file gives a single empty chunk.
+    pub fn split_file(
+        &mut self,
+        inode_index: u64,
+        chunk_size: u64,
+    ) -> Result<Vec<INode>, VoxFSError<E>> {
+        if chunk_size == 0 {
+            return Err(VoxFSError::InvalidChunkSize);
+        }
+
+        let inode = self.inodes[self.locate_inode(inode_index)?];
+
+        if !inode.flags().file_type().has_contents() {
+            return Err(VoxFSError::InvalidFileType);
+        }
+
+        // The chunks are read straight into their entries so the contents are only held once
+        self.check_memory_budget(inode.file_size() + self.block_size)?;
+
+        // Reads made by the disk itself don't count as the file being open
+        let mut handle = FileHandle::new(inode_index);
+        let mut entries = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let chunk = self.read_file_range(&mut handle, offset, chunk_size)?;
+            offset += chunk.len() as u64;
+
+            entries.push(
+                NewFileSpec::new(&format!("{}.{:03}", inode.name(), entries.len()), chunk)
+                    .with_flags(inode.flags()),
+            );
+
+            if offset >= inode.file_size() {
+                break;
+            }
+        }
+
+        return self.import_batch(&entries);
+    }
+
+    /// Joins files, in the order given, into a new file which takes the flags of the first. The files joined are
+    /// removed. While every file before it fills its last block a file's blocks are handed to the new file as they
+    /// are, the contents of the files after the first that doesn't are copied to new blocks.
+    pub fn concat_files(
+        &mut self,
+        inode_indexes: &[u64],
+        new_name: &str,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        if inode_indexes.is_empty() {
+            return Err(VoxFSError::InvalidConcatenation);
+        }
+
+        let mut sources = Vec::with_capacity(inode_indexes.len());
+
+        for (i, inode_index) in inode_indexes.iter().enumerate() {
+            if inode_indexes[..i].contains(inode_index) {
+                return Err(VoxFSError::InvalidConcatenation);
+            }
+
+            let inode = self.inodes[self.locate_inode(*inode_index)?];
+
+            if !inode.flags().file_type().has_contents() {
+                return Err(VoxFSError::InvalidFileType);
+            }
+
+            self.check_replaceable(&inode)?;
+            sources.push(inode);
+        }
+
+        let (inode_index, name) = self.new_file_slot(new_name)?;
+
+        // The blocks of the leading files that end on a block boundary are reused, as is the last file's if
+        // every file before it does.
+        let mut reused = sources
+            .iter()
+            .take_while(|inode| inode.file_size() % self.block_size == 0)
+            .count();
+
+        if reused == sources.len() - 1 {
+            reused += 1;
+        }
+
+        let mut extents = Vec::new();
+
+        for inode in sources[..reused].iter() {
+            for extent in self.file_extents(inode)? {
+                extents.push((extent.start, extent.end));
+            }
+        }
+
+        // Each file is read whole before it is added to the copy
+        let copied_size: u64 = sources[reused..].iter().map(|i| i.file_size()).sum();
+        let largest = sources[reused..].iter().map(|i| i.file_size()).max();
+        self.check_memory_budget(copied_size + largest.unwrap_or(0) + self.block_size)?;
+
+        let mut copied = Vec::with_capacity(copied_size as usize);
+
+        for inode in sources[reused..].iter() {
+            let mut handle = FileHandle::new(inode.index());
+            copied.extend(self.read_file_range(&mut handle, 0, inode.file_size())?);
+        }
+
+        extents.extend(self.write_new_blocks(&copied, None)?);
+
+        let size = sources.iter().map(|inode| inode.file_size()).sum();
+        let inode = self.inode_for_extents(
+            inode_index as u64,
+            &name,
+            sources[0].flags(),
+            size,
+            &extents,
+            None,
+        )?;
+
+        // The new file is written before the files it was made from are removed
+        self.write_inode(inode)?;
+        self.physical_blocks.get_mut().insert(
+            inode.index(),
+            extents.iter().map(|(start, end)| end - start + 1).sum(),
+        );
+
+        if !self.inode_bitmap.set_bit(inode_index, true) {
+            panic!("Unexpected fail."); // This should never happen but if it does then its a developer error so panic.
+        }
+
+        self.write_bitmaps()?;
+        self.inodes.push(inode);
+        self.update_content_hash(inode.index())?;
+
+        self.record_history(
+            HistoryOperation::CreateFile,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        for (i, source) in sources.iter().enumerate() {
+            self.remove_file(source.index(), i >= reused)?;
+        }
+
+        return Ok(inode);
+    }
+
+    /// Sets the flags of a file, this is how the append only and immutable flags are removed.
+    pub fn set_file_flags(
+        &mut self,
+        inode_index: u64,
+        flags: INodeFlags,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let local_index = self.locate_inode(inode_index)?;
+        let mut inode = self.inodes[local_index];
+        inode.set_flags(flags);
+
+        self.write_inode(inode)?;
+        self.inodes[local_index] = inode;
+
+        self.record_history(
+            HistoryOperation::SetFileFlags,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        return Ok(inode);
+    }
+
+    /// Sets a file's timestamps, e.g. to keep them when copying the file from another filesystem.
+    pub fn set_file_times(
+        &mut self,
+        inode_index: u64,
+        creation_time: Timestamp,
+        modified_time: Timestamp,
+        access_time: Timestamp,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+
+        let local_index = self.locate_inode(inode_index)?;
+        let mut inode = self.inodes[local_index];
+        inode.set_times(creation_time, modified_time, access_time);
+
+        self.write_inode(inode)?;
+        self.inodes[local_index] = inode;
+
+        self.record_history(
+            HistoryOperation::SetFileTimes,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        return Ok(inode);
+    }
+
+    /// Checks that a file's existing contents may be discarded, which its flags can forbid.
+    fn check_replaceable(&self, inode: &INode) -> Result<(), VoxFSError<E>> {
+        if inode.flags().immutable() {
+            return Err(VoxFSError::FileIsImmutable);
+        }
+
+        if inode.flags().append_only() {
+            return Err(VoxFSError::FileIsAppendOnly);
+        }
+
+        return Ok(());
+    }
+
+    /// Allocates blocks for the contents and writes them, followed by any indirect inodes needed to hold the
+    /// extents past the first 5. Returns an inode describing them, which hasn't been written, and its number of blocks.
+    /// The creation time is the current time if it isn't given.
+    fn write_contents_to_new_blocks(
+        &mut self,
+        inode_index: u64,
+        name: &str,
+        flags: INodeFlags,
+        contents: &[u8],
+        creation_time: Option<Timestamp>,
+        near: Option<u64>,
+    ) -> Result<(INode, u64), VoxFSError<E>> {
+        if !flags.file_type().has_contents() {
+            return Err(VoxFSError::InvalidFileType);
+        }
+
+        let extents = self.write_new_blocks(contents, near)?;
+        let inode = self.inode_for_extents(
+            inode_index,
+            name,
+            flags,
+            contents.len() as u64,
+            &extents,
+            creation_time,
+        )?;
+
+        return Ok((
+            inode,
+            extents.iter().map(|(start, end)| end - start + 1).sum(),
+        ));
+    }
+
+    /// Allocates blocks for the contents and writes them, returning the extents they were written to.
+    fn write_new_blocks(
+        &mut self,
+        contents: &[u8],
+        near: Option<u64>,
+    ) -> Result<Vec<(u64, u64)>, VoxFSError<E>> {
         // Request enough blocks to cover the size of the file
-        let extents = match self.find_blocks(contents.len() as u64) {
+        let extents = match self.find_blocks(contents.len() as u64, near) {
             Some(extents) => extents,
             None => return Err(VoxFSError::NotEnoughFreeDataBlocks),
         };
@@ -962,56 +3776,41 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             // Mark each block as taken
             for i in *start..=*end {
                 self.block_bitmap.set_bit(i as usize, true);
-
-                // Write the content for this block
-                if contents_offset + self.block_size > contents.len() as u64 {
-                    self.write_to_address(
-                        self.data_index_to_address(i),
-                        &contents[contents_offset as usize..].to_vec(),
-                    )?;
-                } else {
-                    // We don't need to check if we have written all the data because we assume
-                    // that we will be given the minimum blocks required so that once this else is reached
-                    // it will be the last block
-                    self.write_to_address(
-                        self.data_index_to_address(i),
-                        &contents[contents_offset as usize
-                            ..(contents_offset + self.block_size) as usize]
-                            .to_vec(),
-                    )?;
-                }
-
-                // Increase the offset to account for the data written
-                contents_offset += self.block_size;
             }
-        }
-
-        let inode;
-
-        if extents.len() > 5 {
-            let mut inode_extents = [Extent::zeroed(); 5];
 
-            for i in 0..5 {
-                inode_extents[i].start = extents[i].0;
-                inode_extents[i].end = extents[i].1;
-            }
+            // The extent is contiguous so its content can be written in as few calls as possible.
+            // We are given the minimum blocks required so only the last extent can be partly filled.
+            let extent_end = core::cmp::min(
+                contents_offset + (end - start + 1) * self.block_size,
+                contents.len() as u64,
+            );
 
-            let mut indirects_addresses = Vec::new();
+            self.write_data_blocks(
+                *start,
+                &contents[contents_offset as usize..extent_end as usize],
+            )?;
 
-            let amount_per_indirect =
-                IndirectINode::max_extents_for_blocksize(self.block_size) as usize;
-            for mut i in 5..extents.len() {
-                let mut block_indirects = Vec::new();
+            contents_offset = extent_end;
+        }
 
-                while block_indirects.len() < amount_per_indirect && i < extents.len() {
-                    block_indirects.push(extents[i]);
-                    i += 1;
-                }
+        return Ok(extents);
+    }
 
-                indirects_addresses.push(block_indirects);
-            }
+    /// Writes the indirect inodes needed to hold the extents past the first 5 and returns an inode describing
+    /// every extent, which hasn't been written. The creation time is the current time if it isn't given.
+    fn inode_for_extents(
+        &mut self,
+        inode_index: u64,
+        name: &str,
+        flags: INodeFlags,
+        size: u64,
+        extents: &[(u64, u64)],
+        creation_time: Option<Timestamp>,
+    ) -> Result<INode, VoxFSError<E>> {
+        let mut previous_address = 0;
 
-            let mut previous_address = 0;
+        if extents.len() > 5 {
+            let indirects_addresses = self.group_indirect_extents(extents);
 
             for address_group in indirects_addresses.iter().rev() {
                 // Find a block
@@ -1036,64 +3835,52 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                     self.block_size,
                 );
 
-                self.write_to_address(address, &block.to_bytes())?;
+                self.write_to_data_region(address, &block.to_bytes())?;
                 previous_address = address;
                 self.block_bitmap.set_bit(block_index as usize, true);
             }
+        }
 
-            let current_time = self.manager.current_time();
+        let mut extent_blocks = [Extent::zeroed(); 5];
 
-            inode = INode::new(
-                inode_index as u64,
-                name,
-                contents.len() as u64,
-                flags,
-                current_time,
-                current_time,
-                current_time,
-                previous_address,
-                extents.len() as u8,
-                inode_extents,
-            );
-        } else {
-            let mut extent_blocks = [Extent::zeroed(); 5];
+        for i in 0..core::cmp::min(extents.len(), 5) {
+            extent_blocks[i] = Extent {
+                start: extents[i].0,
+                end: extents[i].1,
+            };
+        }
 
-            for i in 0..(if extents.len() < 5 { extents.len() } else { 5 }) {
-                extent_blocks[i] = Extent {
-                    start: extents[i].0,
-                    end: extents[i].1,
-                };
-            }
+        let current_time = self.manager.current_time();
 
-            let current_time = self.manager.current_time();
-            inode = INode::new(
-                inode_index as u64,
-                name,
-                contents.len() as u64,
-                flags,
-                current_time,
-                current_time,
-                current_time,
-                0,
-                extents.len() as u8,
-                extent_blocks,
-            );
-        }
+        return Ok(INode::new(
+            inode_index,
+            name,
+            size,
+            flags,
+            current_time,
+            current_time,
+            creation_time.unwrap_or(current_time),
+            previous_address,
+            core::cmp::min(extents.len(), 5) as u8,
+            extent_blocks,
+        ));
+    }
 
-        self.write_to_address(
-            self.inode_index_to_address(inode_index as u64),
-            &inode.to_bytes().to_vec(),
-        )?;
+    /// Divides the extents past the first 5 into the groups stored in each indirect inode.
+    fn group_indirect_extents(&self, extents: &[(u64, u64)]) -> Vec<Vec<(u64, u64)>> {
+        let mut indirects_addresses = Vec::new();
 
-        if !self.inode_bitmap.set_bit(inode_index, true) {
-            panic!("Unexpected fail."); // This should never happen but if it does then its a developer error so panic.
+        if extents.len() <= 5 {
+            return indirects_addresses;
         }
 
-        self.write_bitmaps()?;
-
-        self.inodes.push(inode);
+        let amount_per_indirect =
+            IndirectINode::max_extents_for_blocksize(self.block_size) as usize;
+        for group in extents[5..].chunks(amount_per_indirect) {
+            indirects_addresses.push(group.to_vec());
+        }
 
-        return Ok(inode);
+        return indirects_addresses;
     }
 
     /// Returns the approximate file size of an inode.
@@ -1118,34 +3905,75 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         let inode = self.inodes[self.locate_inode(inode_index)?];
 
         let actual_size = inode.file_size();
-        let mut physical_size = 0;
+
+        if let Some(blocks) = self.physical_blocks.borrow().get(&inode_index) {
+            return Ok(FileSize {
+                actual_size,
+                physical_size: blocks * self.block_size,
+            });
+        }
+
+        let blocks = self.count_physical_blocks(&inode)?;
+
+        self.physical_blocks
+            .borrow_mut()
+            .insert(inode_index, blocks);
+
+        return Ok(FileSize {
+            actual_size,
+            physical_size: blocks * self.block_size,
+        });
+    }
+
+    /// Counts the data blocks used by the extents of a file, reading its indirect inodes.
+    fn count_physical_blocks(&self, inode: &INode) -> Result<u64, VoxFSError<E>> {
+        let mut blocks = 0;
         let mut next = inode.indirect_pointer();
 
         // Calculate the size of each extent and add it to the overall physical size
         for i in 0..inode.num_extents() as usize {
             let extent = inode.blocks()[i];
-            physical_size += (extent.end - extent.start + 1) * self.block_size; // +1 because inclusive
+            blocks += extent.end - extent.start + 1; // +1 because inclusive
         }
 
         while next.is_some() {
             let bytes = self.read_from_address(next.unwrap(), self.block_size)?;
-            let indirect_inode = match IndirectINode::from_bytes(&bytes) {
+            let indirect_inode = match self.parse_record::<IndirectINode>(&bytes) {
                 Some(i) => i,
                 None => return Err(VoxFSError::CorruptedIndirectINode),
             };
 
             for extent in &indirect_inode.extents() {
-                physical_size += (extent.end - extent.start + 1) * self.block_size;
-                // +1 because inclusive
+                blocks += extent.end - extent.start + 1; // +1 because inclusive
             }
 
             next = indirect_inode.next();
         }
 
-        return Ok(FileSize {
-            actual_size,
-            physical_size,
-        });
+        return Ok(blocks);
+    }
+
+    /// The stored hash of a file's contents, kept up to date as the file is written so files can be compared or
+    /// checked without reading them. None if the filesystem doesn't keep content hashes or the file was last
+    /// written without a content hasher.
+    pub fn file_hash(&self, inode_index: u64) -> Result<Option<[u8; 32]>, VoxFSError<E>> {
+        self.locate_inode(inode_index)?;
+
+        let address = match self.content_hash_address(inode_index) {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+
+        let bytes = self.read_from_address(address, CONTENT_HASH_SIZE)?;
+
+        if bytes.iter().all(|b| *b == 0) {
+            return Ok(None);
+        }
+
+        let mut hash = [0u8; CONTENT_HASH_SIZE as usize];
+        hash.copy_from_slice(&bytes);
+
+        return Ok(Some(hash));
     }
 
     /// Reads an entire file from the disk
@@ -1159,85 +3987,235 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         inode_index: u64,
         num_bytes: u64,
     ) -> Result<Vec<u8>, VoxFSError<E>> {
-        // Locate the INode object in the memory map
-        let inode = self.inodes[self.locate_inode(inode_index)?];
+        // Locate the INode object in the memory map
+        let inode = self.inodes[self.locate_inode(inode_index)?];
+
+        let num_bytes = {
+            if num_bytes > inode.file_size() || num_bytes == 0 {
+                inode.file_size()
+            } else {
+                num_bytes
+            }
+        };
+
+        let extents = self.file_extents(&inode)?;
+
+        // Verifying hashes the whole file, so all of it is read before the bytes asked for are returned
+        let wanted_bytes = num_bytes;
+        let num_bytes = if self.verifies_reads() {
+            inode.file_size()
+        } else {
+            num_bytes
+        };
+
+        self.check_memory_budget(num_bytes + self.block_size)?;
+        self.note_access(inode_index);
+
+        let mut result_bytes = Vec::with_capacity(num_bytes as usize);
+
+        for extent in extents {
+            if result_bytes.len() as u64 >= num_bytes {
+                break;
+            }
+
+            // Only the blocks holding the bytes asked for are read
+            for index in extent.start..=extent.end {
+                if result_bytes.len() as u64 >= num_bytes {
+                    break;
+                }
+
+                let content = self.read_data_block(index)?;
+                let wanted = core::cmp::min(content.len(), num_bytes as usize - result_bytes.len());
+                result_bytes.extend_from_slice(&content[..wanted]);
+            }
+        }
+
+        if (result_bytes.len() as u64) < num_bytes {
+            // The extents did not cover the bytes asked for
+            return Err(VoxFSError::ExpectedIndirectNode);
+        }
+
+        if self.verifies_reads() {
+            self.check_contents(inode_index, &result_bytes)?;
+            result_bytes.truncate(wanted_bytes as usize);
+        }
+
+        return Ok(result_bytes);
+    }
+
+    /// Sets the number of data blocks kept in memory to speed up repeated and sequential reads. Defaults to 0,
+    /// which disables caching. Writes made through `handler()` bypass the cache and may leave it stale.
+    pub fn set_cache_size(&mut self, blocks: usize) {
+        self.block_cache.borrow_mut().set_capacity(blocks);
+    }
+
+    /// Enables reading the next extent into the cache when `read_file_range` detects sequential reads.
+    /// This has no effect unless the cache has a size.
+    pub fn set_read_ahead(&mut self, read_ahead: bool) {
+        self.read_ahead = read_ahead;
+    }
+
+    /// Sets the largest number of bytes sent to the handler in a single write when writing file contents.
+    /// It is rounded down to a whole number of blocks, and at least one block is always written at a time.
+    pub fn set_max_io_size(&mut self, bytes: u64) {
+        self.max_io_size = bytes;
+    }
+
+    /// Sets the most bytes a single operation may allocate to hold file contents, such as the bytes returned by
+    /// `read_file_range` or the copy made by `split_file`. An operation that would need more fails with
+    /// `VoxFSError::MemoryBudgetExceeded` before reading anything, so hosts without much memory can read large files
+    /// in pieces instead. Defaults to None, which sets no limit.
+    pub fn set_memory_budget(&mut self, bytes: Option<u64>) {
+        self.memory_budget = bytes;
+    }
+
+    pub fn memory_budget(&self) -> Option<u64> {
+        return self.memory_budget;
+    }
+
+    /// Sets when bitmap changes are written to the disk. Defaults to `BitmapFlushPolicy::Immediate`.
+    /// Bulk imports can defer the writes, at the cost of the bitmaps on disk being stale until `sync`.
+    pub fn set_bitmap_flush_policy(
+        &mut self,
+        policy: BitmapFlushPolicy,
+    ) -> Result<(), VoxFSError<E>> {
+        self.bitmap_flush_policy = policy;
+
+        // Switching back to writing immediately should not leave older changes behind
+        if policy == BitmapFlushPolicy::Immediate && self.bitmaps_pending {
+            self.flush_bitmaps()?;
+        }
+
+        return Ok(());
+    }
+
+    /// Opens a file to be read in pieces with `read_file_range`. The file counts as open until the handle is given
+    /// to `close_file`, which with `MountOptions::busy_protection` stops it being deleted.
+    pub fn open_file(&self, inode_index: u64) -> Result<FileHandle, VoxFSError<E>> {
+        self.locate_inode(inode_index)?;
+        *self.open_files.borrow_mut().entry(inode_index).or_insert(0) += 1;
+
+        return Ok(FileHandle::new(inode_index));
+    }
+
+    /// Closes a handle from `open_file`. Closing more handles than were opened has no effect.
+    pub fn close_file(&self, handle: FileHandle) {
+        let mut open_files = self.open_files.borrow_mut();
+
+        if let Some(count) = open_files.get_mut(&handle.inode_index()) {
+            *count -= 1;
+
+            if *count == 0 {
+                open_files.remove(&handle.inode_index());
+            }
+        }
+    }
+
+    /// The number of handles open on a file.
+    pub fn open_count(&self, inode_index: u64) -> u64 {
+        return self
+            .open_files
+            .borrow()
+            .get(&inode_index)
+            .copied()
+            .unwrap_or(0);
+    }
+
+    /// Fails with `VoxFSError::FileBusy` if the file has open handles and the disk was mounted to protect them.
+    fn check_not_busy(&self, inode_index: u64) -> Result<(), VoxFSError<E>> {
+        if self.busy_protection && self.open_count(inode_index) > 0 {
+            return Err(VoxFSError::FileBusy);
+        }
+
+        return Ok(());
+    }
+
+    /// Reads up to length bytes of a file starting at an offset. Fewer bytes are returned only when the end of the file
+    /// is reached. A read that starts where the handle's last read ended is sequential and, if read ahead is enabled,
+    /// the extent after the last one read is loaded into the cache.
+    /// With `Integrity::Full` the whole file is checked against its content hash before the first range is read
+    /// from it, and again after it changes.
+    pub fn read_file_range(
+        &self,
+        handle: &mut FileHandle,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, VoxFSError<E>> {
+        if self.verifies_reads() {
+            self.verify_file(handle.inode_index())?;
+        }
+
+        return self.read_range(handle, offset, length);
+    }
+
+    /// Reads a range of a file as `read_file_range` does without checking it against its content hash.
+    fn read_range(
+        &self,
+        handle: &mut FileHandle,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, VoxFSError<E>> {
+        let inode = self.inodes[self.locate_inode(handle.inode_index())?];
 
-        let mut result_bytes = Vec::new();
+        if offset >= inode.file_size() {
+            self.note_access(inode.index());
 
-        let num_bytes = {
-            if num_bytes > inode.file_size() {
-                inode.file_size()
-            } else if num_bytes == 0 {
-                inode.file_size()
-            } else {
-                num_bytes
-            }
-        };
+            return Ok(Vec::new());
+        }
 
-        // Whilst it is possible to read large chunks, for the sake of simplicity for the driver implementor,
-        // we will not read amounts larger than the block size.
+        let end = core::cmp::min(inode.file_size(), offset.saturating_add(length));
+        self.check_memory_budget(end - offset + self.block_size)?;
+        self.note_access(inode.index());
 
-        let mut amount_read = 0;
-        let blocks = inode.blocks();
+        let sequential = offset == handle.next_offset();
+        let extents = self.file_extents(&inode)?;
 
-        for extent_index in 0..inode.num_extents() as usize {
-            let extent = blocks[extent_index];
+        let mut result = Vec::with_capacity((end - offset) as usize);
+        let mut position = 0; // The offset in the file of the current extent
+        let mut last_extent = 0;
 
-            // Read the data in the extent
-            let mut content = self.read_extent(extent)?;
+        for (extent_index, extent) in extents.iter().enumerate() {
+            let extent_end = position + (extent.end - extent.start + 1) * self.block_size;
 
-            // Check if we need to add all the data from the extent or just some of it
-            if content.len() + result_bytes.len() > num_bytes as usize {
-                // We don't need all the data we read to slice it to the amount we need.
-                let bytes_to_read = num_bytes - result_bytes.len() as u64;
-                result_bytes.extend_from_slice(&content[..bytes_to_read as usize]);
-                amount_read += bytes_to_read;
-            } else {
-                // We need all the data from the extent so store it
-                let bytes_read = content.len();
-                result_bytes.append(&mut content);
-                amount_read += bytes_read as u64;
+            if extent_end > offset {
+                let from = core::cmp::max(offset, position);
+                let to = core::cmp::min(end, extent_end);
+
+                for block in
+                    (from - position) / self.block_size..=(to - 1 - position) / self.block_size
+                {
+                    let contents = self.read_data_block(extent.start + block)?;
+                    let block_offset = position + block * self.block_size;
+                    let start = core::cmp::max(from, block_offset) - block_offset;
+                    let stop = core::cmp::min(to, block_offset + self.block_size) - block_offset;
+
+                    result.extend_from_slice(&contents[start as usize..stop as usize]);
+                }
+
+                last_extent = extent_index;
             }
-        }
 
-        // Now we need to read from any indirect
-        let mut next = inode.indirect_pointer();
+            position = extent_end;
 
-        // If there is data still to read iterate over the indirect blocks
-        while amount_read < num_bytes {
-            // If we don't have a new indirect block to read then throw an error.
-            if next.is_none() {
-                return Err(VoxFSError::ExpectedIndirectNode);
+            if position >= end {
+                break;
             }
+        }
 
-            // Read the indirect inode
-            let bytes = self.read_from_address(next.unwrap(), self.block_size)?;
-            let indirect = match IndirectINode::from_bytes(&bytes) {
-                Some(i) => i,
-                None => return Err(VoxFSError::CorruptedIndirectINode),
-            };
+        if (result.len() as u64) < end - offset {
+            // The extents did not cover the file's size
+            return Err(VoxFSError::ExpectedIndirectNode);
+        }
 
-            for extent in &indirect.extents() {
-                let mut content = self.read_extent(*extent)?;
+        handle.set_next_offset(end);
 
-                // Check if we need to add all the data from the extent or just some of it
-                if content.len() + result_bytes.len() > num_bytes as usize {
-                    // We don't need all the data we read to slice it to the amount we need.
-                    let bytes_to_read = num_bytes - result_bytes.len() as u64;
-                    result_bytes.extend_from_slice(&content[..bytes_to_read as usize]);
-                    amount_read += bytes_to_read;
-                } else {
-                    // We need all the data from the extent so store it
-                    let bytes_read = content.len();
-                    result_bytes.append(&mut content);
-                    amount_read += bytes_read as u64;
-                }
+        if sequential && self.read_ahead {
+            if let Some(next) = extents.get(last_extent + 1) {
+                self.prefetch_extent(*next)?;
             }
-
-            next = indirect.next();
         }
 
-        return Ok(result_bytes);
+        return Ok(result);
     }
 
     /// Appends bytes to a file.
@@ -1246,6 +4224,8 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         inode_index: u64,
         bytes: &Vec<u8>,
     ) -> Result<(), VoxFSError<E>> {
+        self.mark_dirty()?;
+
         // locate the inode in our memory map
         let mut inode_local_index = None;
 
@@ -1264,8 +4244,19 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         let inode_local_index = inode_local_index.unwrap();
         let inode = self.inodes[inode_local_index];
 
-        // Find the last extent and how much space of that extent is available.
-        let mut last_block_extent = inode.blocks()[(inode.num_extents() - 1) as usize];
+        if inode.flags().immutable() {
+            return Err(VoxFSError::FileIsImmutable);
+        }
+
+        if !inode.flags().file_type().has_contents() {
+            return Err(VoxFSError::InvalidFileType);
+        }
+
+        // Find the last extent and how much space of that extent is available, an empty file may have none.
+        let mut last_block_extent = match inode.num_extents() {
+            0 => None,
+            n => Some(inode.blocks()[(n - 1) as usize]),
+        };
         let mut next = inode.indirect_pointer();
         let mut previous = None;
 
@@ -1280,39 +4271,43 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             // If this is the last indirect node mark the last extent so we check how much of it is left
             // when we append
             if indirect_inode.next().is_none() {
-                last_block_extent = indirect_inode.last_extent().unwrap();
+                last_block_extent = indirect_inode.last_extent();
             }
 
             previous = next;
             next = indirect_inode.next();
         }
 
-        // Check how much space is left in that last block
-        let amount_available = self.block_size - (inode.file_size() % self.block_size);
+        // Check how much space is left in that last block, a file that fills its last block has none left
+        let amount_available = match inode.file_size() % self.block_size {
+            0 => 0,
+            used => self.block_size - used,
+        };
 
         if amount_available > bytes.len() as u64 {
             // If we can fit all the required data into the space that's available just do that.
+            let last_block_extent = match last_block_extent {
+                Some(e) => e,
+                None => return Err(VoxFSError::CorruptedINode),
+            };
             let block_address = self.data_index_to_address(last_block_extent.end);
             let address = block_address + (self.block_size - amount_available);
 
             // Write as many bytes as we can
-            self.write_to_address(address, &bytes)?;
+            self.write_to_data_region(address, &bytes)?;
 
             // Update the inode to reflect the new size
             self.inodes[inode_local_index].increase_file_size(bytes.len() as u64);
-            self.write_to_address(
-                self.inode_index_to_address(self.inodes[inode_local_index].index()),
-                &self.inodes[inode_local_index].to_bytes().to_vec(),
-            )?;
+            self.write_inode(self.inodes[inode_local_index])?;
         } else {
             // This could potentially be improved by checking the already existing extents for space either side but I don't see the practical advantage in the long term to this approach.
 
             // Otherwise we need to allocate more data blocks
-            let block_address = self.data_index_to_address(last_block_extent.end);
-            let address = block_address + (self.block_size - amount_available);
-
-            // Finds data blocks
-            let pointers = match self.find_blocks(bytes.len() as u64 - amount_available) {
+            // Finds data blocks, following on from the file's last block where there is room
+            let pointers = match self.find_blocks(
+                bytes.len() as u64 - amount_available,
+                last_block_extent.map(|e| e.end + 1),
+            ) {
                 Some(p) => p,
                 None => return Err(VoxFSError::NotEnoughFreeDataBlocks),
             };
@@ -1344,6 +4339,13 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                 });
             }
 
+            if let Some(blocks) = self.physical_blocks.get_mut().get_mut(&inode_index) {
+                *blocks += pointers
+                    .iter()
+                    .map(|(start, end)| end - start + 1)
+                    .sum::<u64>();
+            }
+
             // This tracks how many extents we still need to add to a node
             let mut remaining = extents.len();
 
@@ -1387,7 +4389,7 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                 }
 
                 if changed_indirect {
-                    self.write_to_address(next.unwrap(), &indirect_inode.to_bytes())?;
+                    self.write_to_data_region(next.unwrap(), &indirect_inode.to_bytes())?;
                 }
 
                 next = indirect_inode.next();
@@ -1432,7 +4434,7 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
 
                         // Update the indirect pointer to point to this new indirect
                         previous_indirect.set_next(indirect_address);
-                        self.write_to_address(a, &previous_indirect.to_bytes())?;
+                        self.write_to_data_region(a, &previous_indirect.to_bytes())?;
                     }
                     None => {
                         // Add to the original inode a pointer to this new indirect
@@ -1442,28 +4444,34 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                 }
 
                 // Write the new indirect block
-                self.write_to_address(indirect_address, &new_indirect.to_bytes())?;
+                self.write_to_data_region(indirect_address, &new_indirect.to_bytes())?;
             }
 
             // Write as many bytes to the last block as possible
-            self.write_to_address(address, &bytes[..amount_available as usize].to_vec())?;
+            if let Some(last_block_extent) = last_block_extent {
+                let block_address = self.data_index_to_address(last_block_extent.end);
+                let address = block_address + (self.block_size - amount_available);
+
+                self.write_to_data_region(address, &bytes[..amount_available as usize].to_vec())?;
+            }
 
             // Continue on and write to each of the new extents
             let mut offset = 0;
             for extent in extents {
                 for index in extent.start..=extent.end {
                     let index_address = self.data_index_to_address(index);
-                    let bytes_end_index = amount_available + ((offset + 1) * self.block_size); // Add 1 to the offset to account for the fact we want to write block_size
+                    let bytes_start_index = amount_available + (offset * self.block_size);
+                    let bytes_end_index = bytes_start_index + self.block_size;
 
                     if bytes_end_index >= bytes.len() as u64 {
-                        self.write_to_address(
+                        self.write_to_data_region(
                             index_address,
-                            &bytes[amount_available as usize..].to_vec(),
+                            &bytes[bytes_start_index as usize..].to_vec(),
                         )?;
                     } else {
-                        self.write_to_address(
+                        self.write_to_data_region(
                             index_address,
-                            &bytes[amount_available as usize..bytes_end_index as usize].to_vec(),
+                            &bytes[bytes_start_index as usize..bytes_end_index as usize].to_vec(),
                         )?;
                     }
 
@@ -1471,21 +4479,200 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
                 }
             }
 
+            // Record the newly allocated blocks before the inode refers to them
+            self.write_bitmaps()?;
+
             self.inodes[inode_local_index].increase_file_size(bytes.len() as u64);
-            self.write_to_address(
-                self.inode_index_to_address(self.inodes[inode_local_index].index()),
-                &self.inodes[inode_local_index].to_bytes().to_vec(),
-            )?;
+            self.write_inode(self.inodes[inode_local_index])?;
         }
 
-        return Ok(());
+        self.update_content_hash(inode_index)?;
+
+        return self.record_history(
+            HistoryOperation::AppendFile,
+            inode_index,
+            None,
+            &inode.name(),
+        );
+    }
+
+    /// Sets the size of a file. Blocks past the new end and any indirect inodes no longer needed are freed, and
+    /// the rest of the last block is zeroed. A larger size appends zeroes. Fails with `VoxFSError::FileBusy` if
+    /// the file has open handles and the disk was mounted with `MountOptions::busy_protection`.
+    pub fn truncate_file(
+        &mut self,
+        inode_index: u64,
+        new_size: u64,
+    ) -> Result<INode, VoxFSError<E>> {
+        self.mark_dirty()?;
+        self.check_not_busy(inode_index)?;
+
+        let local_index = self.locate_inode(inode_index)?;
+        let old = self.inodes[local_index];
+
+        if !old.flags().file_type().has_contents() {
+            return Err(VoxFSError::InvalidFileType);
+        }
+
+        if new_size >= old.file_size() {
+            if new_size > old.file_size() {
+                self.check_memory_budget(new_size - old.file_size())?;
+                self.append_file_bytes(
+                    inode_index,
+                    &vec![0; (new_size - old.file_size()) as usize],
+                )?;
+            }
+
+            return Ok(self.inodes[self.locate_inode(inode_index)?]);
+        }
+
+        self.check_replaceable(&old)?;
+
+        // Read the old layout before any of its blocks could be reused.
+        let (_, old_indirect_indexes) = self.file_blocks(&old)?;
+        let mut remaining = new_size.div_ceil(self.block_size);
+        let mut kept = Vec::new();
+        let mut trimmed = Vec::new();
+
+        for extent in self.file_extents(&old)? {
+            let length = extent.end - extent.start + 1;
+
+            if remaining >= length {
+                kept.push((extent.start, extent.end));
+            } else if remaining > 0 {
+                kept.push((extent.start, extent.start + remaining - 1));
+                trimmed.push(Extent {
+                    start: extent.start + remaining,
+                    end: extent.end,
+                });
+            } else {
+                trimmed.push(extent);
+            }
+
+            remaining -= core::cmp::min(remaining, length);
+        }
+
+        // The new indirect inodes are written to free blocks while the old ones still hold the file. On a full disk
+        // the old ones are given up first, as the new chain never needs more blocks than they take.
+        let mut indirects_to_free = old_indirect_indexes;
+
+        if self.group_indirect_extents(&kept).len() > self.free_block_count() {
+            self.free_blocks(&[], &indirects_to_free)?;
+            indirects_to_free.clear();
+        }
+
+        let mut inode = self.inode_for_extents(
+            inode_index,
+            &old.name(),
+            old.flags(),
+            new_size,
+            &kept,
+            Some(old.creation_time()),
+        )?;
+        inode.set_times(
+            old.creation_time(),
+            inode.modified_time(),
+            old.access_time(),
+        );
+
+        // This single record write is the point at which the file changes.
+        self.write_inode(inode)?;
+        self.inodes[local_index] = inode;
+        self.physical_blocks.get_mut().insert(
+            inode_index,
+            kept.iter().map(|(start, end)| end - start + 1).sum(),
+        );
+
+        // Stale bytes past the end would otherwise reappear if the file is appended to
+        if let Some((_, end)) = kept.last() {
+            let used = new_size - (new_size - 1) / self.block_size * self.block_size;
+
+            if used < self.block_size {
+                self.write_to_data_region(
+                    self.data_index_to_address(*end) + used,
+                    &vec![0; (self.block_size - used) as usize],
+                )?;
+            }
+        }
+
+        self.free_blocks(&trimmed, &indirects_to_free)?;
+        self.write_bitmaps()?;
+
+        self.update_content_hash(inode_index)?;
+
+        self.record_history(
+            HistoryOperation::TruncateFile,
+            inode.index(),
+            None,
+            &inode.name(),
+        )?;
+
+        return Ok(inode);
     }
 
-    /// Deletes a file.
+    /// Deletes a file. Fails with `VoxFSError::FileBusy` if it has open handles and the disk was mounted with
+    /// `MountOptions::busy_protection`, otherwise the handles are forgotten.
     pub fn delete_file(&mut self, inode_index: u64) -> Result<(), VoxFSError<E>> {
+        self.mark_dirty()?;
+        self.check_not_busy(inode_index)?;
+
+        self.remove_file(inode_index, true)?;
+        self.open_files.get_mut().remove(&inode_index);
+
+        return Ok(());
+    }
+
+    /// Removes a file once the disk has been marked dirty. The blocks of its extents are left allocated unless
+    /// asked to free them, for when another file has taken them over, its indirect inodes are always freed.
+    fn remove_file(&mut self, inode_index: u64, free_extents: bool) -> Result<(), VoxFSError<E>> {
         let local_index = self.locate_inode(inode_index)?;
         let inode = self.inodes[local_index];
+        self.check_replaceable(&inode)?;
+
+        let (mut extents, indirect_indexes) = self.file_blocks(&inode)?;
+
+        if !free_extents {
+            extents.clear();
+        }
+
+        self.pending_access_times.get_mut().remove(&inode_index);
+
+        // The reverse index means only the tags that reference it are touched.
+        let tags = self.tags_for_inode(inode.index())?;
+
+        self.free_blocks(&extents, &indirect_indexes)?;
+
+        // Mark the inode as free
+        if !self.inode_bitmap.set_bit(inode.index() as usize, false) {
+            return Err(VoxFSError::FailedToFreeINode);
+        }
 
+        // Freeing the inode on disk is the point at which the file is deleted, so a crash before the tags are
+        // rewritten leaves them pointing at a free inode rather than leaving the file alive without its tags.
+        // Such stale members are skipped when tags are loaded and cleared when the index is reused.
+        self.write_bitmaps()?;
+
+        // We need to ensure this inode isn't being pointed to by any tags.
+        // Only the deletion itself is recorded in the history, not each tag removed
+        for tag in tags {
+            self.remove_member(tag.index(), inode.index(), true)?;
+        }
+
+        // Remove it from the memory map
+        self.inodes.remove(local_index);
+        self.physical_blocks.get_mut().remove(&inode.index());
+        self.write_content_hash_slot(inode.index(), [0u8; CONTENT_HASH_SIZE as usize])?;
+
+        return self.record_history(
+            HistoryOperation::DeleteFile,
+            inode.index(),
+            None,
+            &inode.name(),
+        );
+    }
+
+    /// Returns the extents of a file and the data block indexes of its indirect inodes.
+    fn file_blocks(&self, inode: &INode) -> Result<(Vec<Extent>, Vec<u64>), VoxFSError<E>> {
         let mut next = inode.indirect_pointer();
         let mut extents = Vec::new();
         let mut indirect_indexes = Vec::new();
@@ -1493,7 +4680,7 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         while next.is_some() {
             // Read each indirect in
             let bytes = self.read_from_address(next.unwrap(), self.block_size)?;
-            let indirect = match IndirectINode::from_bytes(&bytes) {
+            let indirect = match self.parse_record::<IndirectINode>(&bytes) {
                 Some(i) => i,
                 None => return Err(VoxFSError::CorruptedIndirectINode),
             };
@@ -1509,31 +4696,27 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             next = indirect.next();
         }
 
-        extents.extend_from_slice(&inode.blocks());
-
-        // We need to ensure this inode isn't being pointed to by any tags.
+        // Only the first num_extents entries are in use, the rest are zeroed
+        extents.extend_from_slice(&inode.blocks()[..inode.num_extents() as usize]);
 
-        // I don't know a more efficient method of doing this :/
-        let indices: Vec<u64> = self.tags.iter().map(|t| t.index()).collect();
-        for index in indices {
-            match self.remove_tag_from_inode(index, inode.index()) {
-                Ok(_) => (),
-                Err(e) => match e {
-                    VoxFSError::TagNotAppliedToINode => (),
-                    _ => return Err(e),
-                },
-            }
-        }
+        return Ok((extents, indirect_indexes));
+    }
 
+    /// Marks the blocks of a file's extents and indirect inodes as free in the memory bitmap.
+    fn free_blocks(
+        &mut self,
+        extents: &[Extent],
+        indirect_indexes: &[u64],
+    ) -> Result<(), VoxFSError<E>> {
         // Mark each indirect index as free
         for index in indirect_indexes {
-            if !self.block_bitmap.set_bit(index as usize, false) {
+            if !self.block_bitmap.set_bit(*index as usize, false) {
                 return Err(VoxFSError::FailedToFreeBlock);
             }
         }
 
         // Mark the blocks in each extent as free
-        for extent in &extents {
+        for extent in extents {
             for i in extent.start..=extent.end {
                 if !self.block_bitmap.set_bit(i as usize, false) {
                     return Err(VoxFSError::FailedToFreeBlock);
@@ -1541,37 +4724,49 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             }
         }
 
-        // Mark the inode as free
-        if !self.inode_bitmap.set_bit(inode.index() as usize, false) {
-            return Err(VoxFSError::FailedToFreeINode);
-        }
+        return Ok(());
+    }
 
-        // Remove it from the memory map
-        self.inodes.remove(local_index);
+    /// Writes the block availability bit maps
+    /// Writes the bitmaps now or later depending on the flush policy.
+    fn write_bitmaps(&mut self) -> Result<(), VoxFSError<E>> {
+        self.bitmaps_pending = true;
 
-        // Update the disk
-        self.write_bitmaps()?;
+        let flush = match self.bitmap_flush_policy {
+            BitmapFlushPolicy::Immediate => true,
+            BitmapFlushPolicy::OnSync => false,
+            BitmapFlushPolicy::EveryNOperations(n) => self.operations_since_flush >= n,
+        };
+
+        if flush {
+            self.flush_bitmaps()?;
+        }
 
         return Ok(());
     }
 
-    /// Writes the block availability bit maps
-    fn write_bitmaps(&mut self) -> Result<(), VoxFSError<E>> {
+    /// Writes the bitmaps to the disk regardless of the flush policy.
+    fn flush_bitmaps(&mut self) -> Result<(), VoxFSError<E>> {
+        // The bitmaps start after the superblock and the boot area
+        let bitmap_start = self.super_block.bitmap_start_address();
+
         // Write the tags bitmap
-        self.write_to_address(self.block_size, &self.tag_bitmap.as_bytes())?; // We start at blocksize because of the superblock
+        self.write_to_address(bitmap_start, &self.tag_bitmap.as_bytes())?;
 
         // Write the inodes bitmap
         self.write_to_address(
-            self.block_size + self.blocks_for_tag_map * self.block_size,
+            bitmap_start + self.blocks_for_tag_map * self.block_size,
             &self.inode_bitmap.as_bytes(),
-        )?; // We start at blocksize because of the superblock then skip the tag map
+        )?; // Skip the tag map
 
         // Write the data bitmap
         self.write_to_address(
-            self.block_size
-                + (self.blocks_for_tag_map + self.blocks_for_inode_map) * self.block_size,
+            bitmap_start + (self.blocks_for_tag_map + self.blocks_for_inode_map) * self.block_size,
             &self.block_bitmap.as_bytes(),
-        )?; // We start at blocksize because of the superblock then skip the tag map and inode map
+        )?; // Skip the tag map and inode map
+
+        self.bitmaps_pending = false;
+        self.operations_since_flush = 0;
 
         return Ok(());
     }
@@ -1584,17 +4779,14 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             .find_next_0_index_up_to(self.super_block.tag_count() as usize)
         {
             Some(index) => index,
-            None => return Err(VoxFSError::NoFreeInode),
+            None => return Err(VoxFSError::NoFreeTag),
         };
 
         // Set the index in the tag
         tag.set_index(index as u64);
 
         // Write the tag to the disk
-        self.write_to_address(
-            self.tag_index_to_address(index as u64),
-            &tag.to_bytes().to_vec(),
-        )?;
+        self.write_tag(tag)?;
 
         // Set the spot as taken
         if !self.tag_bitmap.set_bit(index, true) {
@@ -1608,52 +4800,342 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
     }
 
     /// Load a list of the tags from the disk
-    fn load_tags(&self) -> Result<Vec<TagBlock>, VoxFSError<E>> {
+    fn load_tags(
+        &mut self,
+        mut report: Option<&mut OpenReport<E>>,
+    ) -> Result<Vec<TagBlock>, VoxFSError<E>> {
         let mut tags = Vec::new();
 
         for i in 0..self.super_block.tag_count() {
             // We unwrap here because we assume the bit exists
             if self.tag_bitmap.bit_at(i as usize).unwrap() {
-                // Read the tag and add it to the memory map
                 let location = self.tag_index_to_address(i);
-                let bytes = self.read_from_address(location, TagBlock::size())?;
 
-                // Ensure the tag isn't corrupted
-                tags.push(match TagBlock::from_bytes(&bytes) {
-                    Some(tag) => tag,
-                    None => return Err(VoxFSError::CorruptedTag),
-                });
+                match self.load_tag(i) {
+                    Ok(tag) => tags.push(tag),
+                    Err(e) => match report.as_deref_mut() {
+                        Some(report) => report.skip(RecordKind::Tag, i, location, e),
+                        None => return Err(e),
+                    },
+                }
             }
         }
 
         return Ok(tags);
     }
 
+    /// Counts the corrupted records a check found and notes the time if it reached the end of the disk.
+    fn note_check(&self, report: &ScrubReport<E>) {
+        let corrupted = report
+            .failures()
+            .iter()
+            .filter(|f| !matches!(f.reason(), VoxFSError::DiskError(_)))
+            .count() as u64;
+        self.checksum_failures
+            .set(self.checksum_failures.get() + corrupted);
+
+        if report.reached_end() {
+            self.last_check_time.set(Some(self.manager.current_time()));
+        }
+    }
+
+    /// Checks one position of `check_incremental`, returning the number of blocks read.
+    fn check_position(&self, report: &mut ScrubReport<E>, position: u64) -> u64 {
+        let tag_count = self.super_block.tag_count();
+        let inode_count = self.super_block.inode_count();
+
+        if position == 0 {
+            self.scrub_super_block(report);
+
+            return 1;
+        }
+
+        let mirrored = self.super_block.mirror_tag_start_address().is_some();
+        let copies = if mirrored { 2 } else { 1 };
+        let mut i = position - 1;
+
+        if i < tag_count {
+            if !self.tag_bitmap.bit_at(i as usize).unwrap() {
+                return 0;
+            }
+
+            self.scrub_tag(
+                report,
+                ScrubRegion::TagTable,
+                i,
+                self.tag_index_to_address(i),
+            );
+
+            if let Some(mirror) = self.super_block.mirror_tag_start_address() {
+                self.scrub_tag(
+                    report,
+                    ScrubRegion::TagMirror,
+                    i,
+                    mirror + i * TagBlock::size(),
+                );
+            }
+
+            return copies;
+        }
+
+        i -= tag_count;
+
+        if i < inode_count {
+            if !self.inode_bitmap.bit_at(i as usize).unwrap() {
+                return 0;
+            }
+
+            self.scrub_inode(
+                report,
+                ScrubRegion::INodeTable,
+                i,
+                self.inode_index_to_address(i),
+            );
+
+            if let Some(mirror) = self.super_block.mirror_inode_start_address() {
+                self.scrub_inode(
+                    report,
+                    ScrubRegion::INodeMirror,
+                    i,
+                    mirror + i * INode::size(),
+                );
+            }
+
+            return copies;
+        }
+
+        i -= inode_count;
+
+        if i < tag_count {
+            return match self.tags.iter().find(|t| t.index() == i) {
+                Some(tag) => self.scrub_tag_chain(report, tag),
+                None => 0,
+            };
+        }
+
+        i -= tag_count;
+
+        return match self.inodes.iter().find(|n| n.index() == i) {
+            Some(inode) => self.scrub_inode_chain(report, inode),
+            None => 0,
+        };
+    }
+
+    /// Checks the super block for `scrub`.
+    fn scrub_super_block(&self, report: &mut ScrubReport<E>) {
+        match self.read_from_address(0, SuperBlock::size()) {
+            Ok(bytes) => match SuperBlock::from_bytes(&bytes) {
+                Some(_) => report.pass(ScrubRegion::SuperBlock),
+                None => report.fail(
+                    ScrubRegion::SuperBlock,
+                    0,
+                    0,
+                    VoxFSError::CorruptedSuperBlock,
+                ),
+            },
+            Err(e) => report.fail(ScrubRegion::SuperBlock, 0, 0, e),
+        }
+    }
+
+    /// Checks a tag's indirect blocks for `scrub`, returning the number read.
+    fn scrub_tag_chain(&self, report: &mut ScrubReport<E>, tag: &TagBlock) -> u64 {
+        let mut next = tag.indirect_pointer();
+        let mut blocks = 0;
+
+        while let Some(address) = next {
+            next = None;
+            blocks += 1;
+
+            match self.read_from_address(address, self.block_size) {
+                Ok(bytes) => match IndirectTagBlock::from_bytes(&bytes) {
+                    Some(block) => {
+                        report.pass(ScrubRegion::IndirectTags);
+                        next = block.next();
+                    }
+                    None => report.fail(
+                        ScrubRegion::IndirectTags,
+                        tag.index(),
+                        address,
+                        VoxFSError::CorruptedIndirectTag,
+                    ),
+                },
+                Err(e) => report.fail(ScrubRegion::IndirectTags, tag.index(), address, e),
+            }
+        }
+
+        return blocks;
+    }
+
+    /// Checks an inode's indirect blocks for `scrub`, returning the number read.
+    fn scrub_inode_chain(&self, report: &mut ScrubReport<E>, inode: &INode) -> u64 {
+        let mut next = inode.indirect_pointer();
+        let mut blocks = 0;
+
+        while let Some(address) = next {
+            next = None;
+            blocks += 1;
+
+            match self.read_from_address(address, self.block_size) {
+                Ok(bytes) => match IndirectINode::from_bytes(&bytes) {
+                    Some(block) => {
+                        report.pass(ScrubRegion::IndirectINodes);
+                        next = block.next();
+                    }
+                    None => report.fail(
+                        ScrubRegion::IndirectINodes,
+                        inode.index(),
+                        address,
+                        VoxFSError::CorruptedIndirectINode,
+                    ),
+                },
+                Err(e) => report.fail(ScrubRegion::IndirectINodes, inode.index(), address, e),
+            }
+        }
+
+        return blocks;
+    }
+
+    /// Checks the tag at an address for `scrub`.
+    fn scrub_tag(
+        &self,
+        report: &mut ScrubReport<E>,
+        region: ScrubRegion,
+        index: u64,
+        address: u64,
+    ) {
+        match self.read_from_address(address, TagBlock::size()) {
+            Ok(bytes) => match TagBlock::from_bytes(&bytes) {
+                Some(_) => report.pass(region),
+                None => report.fail(region, index, address, VoxFSError::CorruptedTag),
+            },
+            Err(e) => report.fail(region, index, address, e),
+        }
+    }
+
+    /// Checks the inode at an address for `scrub`.
+    fn scrub_inode(
+        &self,
+        report: &mut ScrubReport<E>,
+        region: ScrubRegion,
+        index: u64,
+        address: u64,
+    ) {
+        match self.read_from_address(address, INode::size()) {
+            Ok(bytes) => match INode::from_bytes(&bytes) {
+                Some(_) => report.pass(region),
+                None => report.fail(region, index, address, VoxFSError::CorruptedINode),
+            },
+            Err(e) => report.fail(region, index, address, e),
+        }
+    }
+
+    /// Parses a tag, inode or indirect block, checking its checksum unless the disk was opened with
+    /// `Integrity::None`.
+    fn parse_record<R: ByteSerializable + UnverifiedRecord>(&self, bytes: &[u8]) -> Option<R> {
+        return match self.integrity {
+            Integrity::None => R::from_bytes_unverified(bytes),
+            Integrity::Metadata | Integrity::Full => R::from_bytes(bytes),
+        };
+    }
+
+    /// Reads the tag at an index, falling back to the mirror if it is corrupted.
+    fn load_tag(&mut self, index: u64) -> Result<TagBlock, VoxFSError<E>> {
+        let bytes = self.read_from_address(self.tag_index_to_address(index), TagBlock::size())?;
+
+        if let Some(tag) = self.parse_record::<TagBlock>(&bytes) {
+            return Ok(tag);
+        }
+
+        self.checksum_failures.set(self.checksum_failures.get() + 1);
+
+        let mirrored = match self.super_block.mirror_tag_start_address() {
+            Some(mirror) => TagBlock::from_bytes(
+                &self.read_from_address(mirror + index * TagBlock::size(), TagBlock::size())?,
+            ),
+            None => None,
+        };
+
+        return match mirrored {
+            Some(tag) => {
+                self.recovered_tags.push(index);
+                Ok(tag)
+            }
+            None => Err(VoxFSError::CorruptedTag),
+        };
+    }
+
     /// Reads and loads all the inodes on the filesystem.
-    fn load_inodes(&mut self) -> Result<Vec<INode>, VoxFSError<E>> {
+    fn load_inodes(
+        &mut self,
+        mut report: Option<&mut OpenReport<E>>,
+    ) -> Result<Vec<INode>, VoxFSError<E>> {
         let mut inodes: Vec<INode> = Vec::new();
 
         for i in 0..self.super_block.inode_count() {
             // If this bit is marked as taken then read the inode at that location
             if self.inode_bitmap.bit_at(i as usize).unwrap() {
-                // Read the inode and add it to the memory map
                 let address = self.inode_index_to_address(i);
-                let bytes = self.read_from_address(address, INode::size())?;
 
-                // Ensure the INode was valid
-                inodes.push(match INode::from_bytes(&bytes) {
-                    Some(node) => node,
-                    None => return Err(VoxFSError::CorruptedINode),
-                });
+                match self.load_inode(i) {
+                    Ok(node) => {
+                        // A file whose indirect inodes can't be read reports the error when its size is asked for
+                        if let Ok(blocks) = self.count_physical_blocks(&node) {
+                            self.physical_blocks.get_mut().insert(i, blocks);
+                        }
+
+                        inodes.push(node);
+                    }
+                    Err(e) => match report.as_deref_mut() {
+                        Some(report) => report.skip(RecordKind::INode, i, address, e),
+                        None => return Err(e),
+                    },
+                }
             }
         }
 
         return Ok(inodes);
     }
 
+    /// Reads the inode at an index, falling back to the mirror if it is corrupted.
+    fn load_inode(&mut self, index: u64) -> Result<INode, VoxFSError<E>> {
+        let bytes = self.read_from_address(self.inode_index_to_address(index), INode::size())?;
+
+        if let Some(node) = self.parse_record::<INode>(&bytes) {
+            return Ok(node);
+        }
+
+        self.checksum_failures.set(self.checksum_failures.get() + 1);
+
+        let mirrored = match self.super_block.mirror_inode_start_address() {
+            Some(mirror) => INode::from_bytes(
+                &self.read_from_address(mirror + index * INode::size(), INode::size())?,
+            ),
+            None => None,
+        };
+
+        return match mirrored {
+            Some(node) => {
+                self.recovered_inodes.push(index);
+                Ok(node)
+            }
+            None => Err(VoxFSError::CorruptedINode),
+        };
+    }
+
     /// Locates extents and returns a vector of tuples where .0 is the start address and .1 is the end address.
     /// min_size: The minimum size needed in BYTES
-    fn find_blocks(&self, min_size: u64) -> Option<Vec<(u64, u64)>> {
+    /// near: A block to place the extents at or after when there is room, otherwise the first that fit are used
+    fn find_blocks(&self, min_size: u64, near: Option<u64>) -> Option<Vec<(u64, u64)>> {
+        return self.find_blocks_in(&self.block_bitmap, min_size, near);
+    }
+
+    /// Locates extents as `find_blocks` does, in a block bitmap other than the disk's.
+    fn find_blocks_in(
+        &self,
+        bitmap: &BitMap,
+        min_size: u64,
+        near: Option<u64>,
+    ) -> Option<Vec<(u64, u64)>> {
         // Calculate how many blocks we need for the minimum size
         let num_blocks_required = {
             if min_size % self.block_size != 0 {
@@ -1664,76 +5146,85 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         };
 
         // Check if we have enough blocks.
-        if (self
-            .block_bitmap
-            .count_zeros_up_to(self.super_block.block_count() as usize))
-        .unwrap()
+        if (bitmap.count_zeros_up_to(self.super_block.block_count() as usize)).unwrap()
             < num_blocks_required as usize
         {
             return None;
         }
 
+        // Collect every run of free blocks as an inclusive extent
+        let mut free_extents: Vec<(u64, u64)> = Vec::new();
+        let mut run_start = None;
+
+        for i in 0..self.super_block.block_count() {
+            if !bitmap.bit_at(i as usize).unwrap() {
+                if run_start.is_none() {
+                    run_start = Some(i);
+                }
+            } else if let Some(start) = run_start {
+                free_extents.push((start, i - 1));
+                run_start = None;
+            }
+        }
+
+        if let Some(start) = run_start {
+            free_extents.push((start, self.super_block.block_count() - 1));
+        }
+
         let mut res = Vec::new();
         let mut blocks_found = 0;
 
-        let mut first_index = self
-            .block_bitmap
-            .find_next_0_index_up_to(self.super_block.block_count() as usize)
-            .unwrap() as u64;
-
-        // Find the largest available extent first them work down to individual blocks.
+        // Use the first extent that fits what remains, otherwise take the largest available extent and work
+        // down to individual blocks.
         while blocks_found < num_blocks_required {
-            // Find largest available extent up to the size required
-            let mut start_index = first_index;
-            let mut end_index = start_index;
-
-            let mut largest_start_index = start_index;
-            let mut largest_end_index = end_index;
-
-            let mut i = start_index + 1;
-
-            // Find the largest available extent up to the required size
-            while i < self.super_block.block_count() {
-                // Check if we found an extent big enough
-                if (end_index - start_index + 1) >= (num_blocks_required - blocks_found) {
-                    // We add one here to account for the inclusive end of the extent
-                    largest_start_index = start_index;
-                    largest_end_index = end_index;
-                    break;
+            let remaining = num_blocks_required - blocks_found;
+
+            let after_hint = near.and_then(|near| {
+                return free_extents
+                    .iter()
+                    .position(|(start, end)| {
+                        return *end >= near && end - core::cmp::max(*start, near) >= remaining - 1;
+                    })
+                    .map(|i| (i, near));
+            });
+
+            let first_fit = free_extents
+                .iter()
+                .position(|(start, end)| end - start >= remaining - 1);
+
+            let chosen = match (after_hint, first_fit) {
+                // Split a free run the hint falls inside so the blocks before the hint stay free
+                (Some((i, near)), _) if free_extents[i].0 < near => {
+                    let (start, end) = free_extents[i];
+                    free_extents[i] = (start, near - 1);
+                    free_extents.insert(i + 1, (near, end));
+
+                    i + 1
                 }
-
-                if !self.block_bitmap.bit_at(i as usize).unwrap() {
-                    end_index += 1;
-                } else {
-                    if (end_index - start_index) > (largest_end_index - largest_start_index) {
-                        largest_start_index = start_index;
-                        largest_end_index = end_index;
+                (Some((i, _)), _) => i,
+                (None, Some(i)) => i,
+                (None, None) => {
+                    let mut largest = None;
+
+                    for (i, (start, end)) in free_extents.iter().enumerate() {
+                        match largest {
+                            Some((_, size)) if size > end - start => (),
+                            _ => largest = Some((i, end - start + 1)),
+                        }
                     }
 
-                    start_index = i + 1;
-                    end_index = i + 1;
-                }
-
-                i += 1;
-            }
-
-            blocks_found += largest_end_index - largest_start_index + 1;
-            res.push((largest_start_index, largest_end_index));
-
-            if largest_start_index == first_index {
-                let mut new_first_index = None;
-
-                for i in largest_end_index..self.super_block.block_count() {
-                    if !self.block_bitmap.bit_at(i as usize).unwrap() {
-                        new_first_index = Some(i);
+                    match largest {
+                        Some((i, _)) => i,
+                        None => return None, // Should never happen but just in case.
                     }
                 }
+            };
 
-                match new_first_index {
-                    Some(i) => first_index = i,
-                    None => return None, // Should never happen but just in case.
-                }
-            }
+            let (start, end) = free_extents.remove(chosen);
+            let end = core::cmp::min(end, start + remaining - 1);
+
+            blocks_found += end - start + 1;
+            res.push((start, end));
         }
 
         return Some(res);
@@ -1765,7 +5256,7 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
     #[inline]
     fn address_to_data_index(&self, address: u64) -> u64 {
         assert!(
-            address > self.super_block.data_start_address(),
+            address >= self.super_block.data_start_address(),
             "Invalid address conversion requested."
         );
 
@@ -1790,15 +5281,387 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         return self.super_block.tag_start_address() + index * TagBlock::size();
     }
 
+    /// Marks the super block dirty and records the mount before the first modification.
+    fn mark_dirty(&mut self) -> Result<(), VoxFSError<E>> {
+        if self.read_only {
+            return Err(VoxFSError::ReadOnly);
+        }
+
+        // Records written now would be in the current format but read back in the old one
+        if self.super_block.needs_migration() {
+            return Err(VoxFSError::MigrationRequired);
+        }
+
+        // Every modifying operation starts here so this is where they are counted
+        self.operations_since_flush = self.operations_since_flush.saturating_add(1);
+
+        if self.dirty {
+            return Ok(());
+        }
+
+        let mut super_block = self.super_block.clone();
+        super_block.set_state(FilesystemState::Dirty);
+        super_block.record_mount(self.manager.current_time());
+
+        // The disk must be marked dirty before anything it records changes
+        self.write_super_block(super_block)?;
+        self.barrier()?;
+        self.dirty = true;
+
+        return Ok(());
+    }
+
+    /// Remembers that a file was read so its access time is written on the next sync, unless mounted with noatime.
+    fn note_access(&self, inode_index: u64) {
+        if self.noatime {
+            return;
+        }
+
+        self.pending_access_times
+            .borrow_mut()
+            .insert(inode_index, self.manager.current_time());
+    }
+
+    /// Writes the access times of the files read since the last sync.
+    fn write_access_times(&mut self) -> Result<(), VoxFSError<E>> {
+        let pending = core::mem::take(self.pending_access_times.get_mut());
+
+        for (inode_index, access_time) in pending {
+            let local_index = match self.locate_inode(inode_index) {
+                Ok(i) => i,
+                Err(_) => continue,
+            };
+
+            self.mark_dirty()?;
+
+            let mut inode = self.inodes[local_index];
+            inode.set_times(inode.creation_time(), inode.modified_time(), access_time);
+
+            self.write_inode(inode)?;
+            self.inodes[local_index] = inode;
+        }
+
+        return Ok(());
+    }
+
+    /// Reads the valid records in the history region sorted by sequence, skipping empty or corrupted slots.
+    fn read_history(&self) -> Result<Vec<HistoryRecord>, VoxFSError<E>> {
+        let start = match self.super_block.history_start_address() {
+            Some(s) => s,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut records = Vec::new();
+
+        for block in 0..self.super_block.history_blocks() as u64 {
+            let bytes = self.read_from_address(start + block * self.block_size, self.block_size)?;
+
+            for slot in bytes.chunks_exact(HistoryRecord::size() as usize) {
+                if let Some(mut record) = HistoryRecord::from_bytes(slot) {
+                    if self.super_block.has_legacy_times() {
+                        record.convert_legacy_time();
+                    }
+
+                    records.push(record);
+                }
+            }
+        }
+
+        records.sort_by_key(|r| r.sequence());
+
+        return Ok(records);
+    }
+
+    /// Appends a record of a change to the history region, overwriting the oldest record once it is full.
+    /// Does nothing if the filesystem has no history region.
+    fn record_history(
+        &mut self,
+        operation: HistoryOperation,
+        subject: u64,
+        target: Option<u64>,
+        name: &str,
+    ) -> Result<(), VoxFSError<E>> {
+        let start = match self.super_block.history_start_address() {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+
+        let capacity = self.history_size() / HistoryRecord::size();
+        let sequence = self.next_history_sequence;
+
+        let record = HistoryRecord::new(
+            sequence,
+            self.manager.current_time(),
+            operation,
+            self.manager.user_id(),
+            &self.manager.user_name(),
+            subject,
+            target,
+            name,
+        );
+
+        let slot = (sequence - 1) % capacity;
+        self.write_to_history_region(
+            start + slot * HistoryRecord::size(),
+            &record.to_bytes().to_vec(),
+        )?;
+        self.next_history_sequence += 1;
+
+        return Ok(());
+    }
+
+    /// Writes the super block with the next commit sequence, only replacing the one in memory if the write succeeds.
+    /// The backup is written first so an interrupted write leaves at least one intact copy.
+    fn write_super_block(&mut self, mut super_block: SuperBlock) -> Result<(), VoxFSError<E>> {
+        super_block.record_commit();
+        let bytes = super_block.to_bytes().to_vec();
+
+        self.write_to_address(BACKUP_SUPER_BLOCK_ADDRESS, &bytes)?;
+        self.barrier()?;
+        self.write_to_address(0, &bytes)?;
+        self.super_block = super_block;
+
+        return Ok(());
+    }
+
+    /// Writes a tag to its slot in the tag table and to the mirror if there is one.
+    /// The data and indirect blocks it points to are ordered before it.
+    fn write_tag(&mut self, tag: TagBlock) -> Result<(), VoxFSError<E>> {
+        let bytes = tag.to_bytes().to_vec();
+
+        self.barrier()?;
+
+        let table_size = self.super_block.tag_count() * TagBlock::size();
+        let start = self.super_block.tag_start_address();
+        self.write_to_region(
+            start,
+            start + table_size,
+            self.tag_index_to_address(tag.index()),
+            &bytes,
+        )?;
+
+        if let Some(mirror) = self.super_block.mirror_tag_start_address() {
+            self.write_to_region(
+                mirror,
+                mirror + table_size,
+                mirror + tag.index() * TagBlock::size(),
+                &bytes,
+            )?;
+        }
+
+        return Ok(());
+    }
+
+    /// Writes an inode to its slot in the inode table and to the mirror if there is one.
+    /// The data and indirect blocks it points to are ordered before it.
+    fn write_inode(&mut self, inode: INode) -> Result<(), VoxFSError<E>> {
+        let bytes = inode.to_bytes().to_vec();
+
+        self.barrier()?;
+
+        let table_size = self.super_block.inode_count() * INode::size();
+        let start = self.super_block.inode_start_address();
+        self.write_to_region(
+            start,
+            start + table_size,
+            self.inode_index_to_address(inode.index()),
+            &bytes,
+        )?;
+
+        if let Some(mirror) = self.super_block.mirror_inode_start_address() {
+            self.write_to_region(
+                mirror,
+                mirror + table_size,
+                mirror + inode.index() * INode::size(),
+                &bytes,
+            )?;
+        }
+
+        return Ok(());
+    }
+
     /// Write data to an address on the disk
     #[inline]
     fn write_to_address(&mut self, address: u64, content: &Vec<u8>) -> Result<(), VoxFSError<E>> {
+        let data_start = self.super_block.data_start_address();
+
+        // Drop any cached data blocks that are being overwritten
+        if address + content.len() as u64 > data_start && !content.is_empty() {
+            let first = address.saturating_sub(data_start) / self.block_size;
+            let last = (address + content.len() as u64 - 1 - data_start) / self.block_size;
+
+            self.block_cache.get_mut().invalidate_range(first, last);
+        }
+
+        self.unflushed_writes = true;
+        self.unordered_writes = true;
+
+        #[cfg(feature = "access-stats")]
+        self.access_heatmap
+            .get_mut()
+            .record_write(address, content.len() as u64);
+
         match self.handler.write_bytes(content, address) {
             Ok(_) => return Ok(()),
             Err(e) => return Err(e.into_voxfs_error()),
         }
     }
 
+    /// Writes content that must lie between two addresses, failing with `VoxFSError::AddressOutOfRegion` rather
+    /// than overwriting the neighbouring region when an index or address was miscalculated.
+    fn write_to_region(
+        &mut self,
+        start: u64,
+        end: u64,
+        address: u64,
+        content: &Vec<u8>,
+    ) -> Result<(), VoxFSError<E>> {
+        let length = content.len() as u64;
+
+        match address.checked_add(length) {
+            Some(content_end) if address >= start && content_end <= end => (),
+            _ => return Err(VoxFSError::AddressOutOfRegion { address, length }),
+        }
+
+        return self.write_to_address(address, content);
+    }
+
+    /// Writes data or indirect blocks, which must lie within the data region.
+    fn write_to_data_region(
+        &mut self,
+        address: u64,
+        content: &Vec<u8>,
+    ) -> Result<(), VoxFSError<E>> {
+        let start = self.super_block.data_start_address();
+        let end = self.data_index_to_address(self.super_block.block_count());
+
+        return self.write_to_region(start, end, address, content);
+    }
+
+    /// Writes history records, which must lie within the history region.
+    fn write_to_history_region(
+        &mut self,
+        address: u64,
+        content: &Vec<u8>,
+    ) -> Result<(), VoxFSError<E>> {
+        let start = match self.super_block.history_start_address() {
+            Some(s) => s,
+            None => {
+                return Err(VoxFSError::AddressOutOfRegion {
+                    address,
+                    length: content.len() as u64,
+                })
+            }
+        };
+        let end = start + self.history_size();
+
+        return self.write_to_region(start, end, address, content);
+    }
+
+    /// The address of an inode's slot in the content hash region, if the filesystem has one.
+    fn content_hash_address(&self, inode_index: u64) -> Option<u64> {
+        return self
+            .super_block
+            .content_hash_start_address()
+            .map(|start| start + inode_index * CONTENT_HASH_SIZE);
+    }
+
+    /// Stores the hash of a file's new contents, or clears it if there is no content hasher.
+    fn write_content_hash(
+        &mut self,
+        inode_index: u64,
+        contents: &[u8],
+    ) -> Result<(), VoxFSError<E>> {
+        if !self.has_content_hashes() {
+            return Ok(());
+        }
+
+        let hash = match self.manager.content_hasher() {
+            Some(mut hasher) => {
+                hasher.update(contents);
+                hasher.finish()
+            }
+            None => [0u8; CONTENT_HASH_SIZE as usize],
+        };
+
+        return self.write_content_hash_slot(inode_index, hash);
+    }
+
+    /// Rehashes a file after it changed in place. Hashers can't carry on from a stored hash so the whole file is
+    /// read back, a piece at a time.
+    fn update_content_hash(&mut self, inode_index: u64) -> Result<(), VoxFSError<E>> {
+        if !self.has_content_hashes() {
+            return Ok(());
+        }
+
+        let mut hasher = match self.manager.content_hasher() {
+            Some(h) => h,
+            None => {
+                return self.write_content_hash_slot(inode_index, [0u8; CONTENT_HASH_SIZE as usize])
+            }
+        };
+
+        let size = self.inodes[self.locate_inode(inode_index)?].file_size();
+        let chunk_size = core::cmp::max(self.max_io_size, self.block_size);
+        let mut handle = FileHandle::new(inode_index);
+        let mut offset = 0;
+        let access_time = self
+            .pending_access_times
+            .get_mut()
+            .get(&inode_index)
+            .copied();
+
+        while offset < size {
+            hasher.update(&self.read_range(&mut handle, offset, chunk_size)?);
+            offset += chunk_size;
+        }
+
+        // Reading the file back to hash it isn't an access
+        match access_time {
+            Some(time) => self
+                .pending_access_times
+                .get_mut()
+                .insert(inode_index, time),
+            None => self.pending_access_times.get_mut().remove(&inode_index),
+        };
+
+        return self.write_content_hash_slot(inode_index, hasher.finish());
+    }
+
+    /// Writes an inode's slot in the content hash region, which must lie within the region.
+    fn write_content_hash_slot(
+        &mut self,
+        inode_index: u64,
+        hash: [u8; CONTENT_HASH_SIZE as usize],
+    ) -> Result<(), VoxFSError<E>> {
+        self.verified_files.get_mut().remove(&inode_index);
+
+        let (start, address) = match (
+            self.super_block.content_hash_start_address(),
+            self.content_hash_address(inode_index),
+        ) {
+            (Some(s), Some(a)) => (s, a),
+            _ => return Ok(()),
+        };
+        let end = start + self.super_block.content_hash_blocks() * self.block_size;
+
+        return self.write_to_region(start, end, address, &hash.to_vec());
+    }
+
+    /// Writes content to consecutive data blocks starting at an index, splitting it into writes no larger than
+    /// the maximum I/O size.
+    fn write_data_blocks(&mut self, start: u64, content: &[u8]) -> Result<(), VoxFSError<E>> {
+        let chunk_size = core::cmp::max(self.max_io_size / self.block_size, 1) * self.block_size;
+        let mut address = self.data_index_to_address(start);
+
+        for chunk in content.chunks(chunk_size as usize) {
+            self.write_to_data_region(address, &chunk.to_vec())?;
+            address += chunk.len() as u64;
+        }
+
+        return Ok(());
+    }
+
     /// Read data from an address on the disk.
     #[inline]
     fn read_from_address(
@@ -1806,28 +5669,137 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         address: u64,
         number_of_bytes: u64,
     ) -> Result<Vec<u8>, VoxFSError<E>> {
+        #[cfg(feature = "access-stats")]
+        self.access_heatmap
+            .borrow_mut()
+            .record_read(address, number_of_bytes);
+
         match self.handler.read_bytes(address, number_of_bytes) {
             Ok(b) => return Ok(b),
             Err(e) => return Err(e.into_voxfs_error()),
         }
     }
 
-    /// Read blocks between two data indexes, INCLUSIVE at both ends
-    fn read_between_range(&self, start: u64, end: u64) -> Result<Vec<u8>, VoxFSError<E>> {
-        let mut result = Vec::new();
+    /// Reads a data block through the cache.
+    fn read_data_block(&self, index: u64) -> Result<Vec<u8>, VoxFSError<E>> {
+        if let Some(contents) = self.block_cache.borrow_mut().get(index) {
+            return Ok(contents);
+        }
+
+        let contents =
+            self.read_from_address(self.data_index_to_address(index), self.block_size)?;
+        self.block_cache
+            .borrow_mut()
+            .insert(index, contents.clone());
+
+        return Ok(contents);
+    }
+
+    /// Loads the blocks of an extent that are not already cached with a single read, up to the cache's capacity.
+    fn prefetch_extent(&self, extent: Extent) -> Result<(), VoxFSError<E>> {
+        let capacity = self.block_cache.borrow().capacity() as u64;
 
-        for i in start..=end {
-            let addr = self.data_index_to_address(i);
-            let mut content = self.read_from_address(addr, self.block_size)?;
-            result.append(&mut content);
+        if capacity == 0 || extent.end < extent.start {
+            return Ok(());
         }
 
-        return Ok(result);
+        let end = core::cmp::min(extent.end, extent.start + capacity - 1);
+
+        if (extent.start..=end).all(|i| self.block_cache.borrow().contains(i)) {
+            return Ok(());
+        }
+
+        let bytes = self.read_from_address(
+            self.data_index_to_address(extent.start),
+            (end - extent.start + 1) * self.block_size,
+        )?;
+        let mut cache = self.block_cache.borrow_mut();
+
+        for (i, contents) in bytes.chunks(self.block_size as usize).enumerate() {
+            cache.insert(extent.start + i as u64, contents.to_vec());
+        }
+
+        return Ok(());
     }
 
-    /// Read data from an extent
-    fn read_extent(&self, extent: Extent) -> Result<Vec<u8>, VoxFSError<E>> {
-        return self.read_between_range(extent.start, extent.end);
+    /// Collects every extent of a file in order, including those in indirect inodes.
+    fn file_extents(&self, inode: &INode) -> Result<Vec<Extent>, VoxFSError<E>> {
+        let mut extents = inode.blocks()[..inode.num_extents() as usize].to_vec();
+        let mut next = inode.indirect_pointer();
+
+        while let Some(address) = next {
+            let bytes = self.read_from_address(address, self.block_size)?;
+            let indirect = match self.parse_record::<IndirectINode>(&bytes) {
+                Some(i) => i,
+                None => return Err(VoxFSError::CorruptedIndirectINode),
+            };
+
+            extents.extend(indirect.extents());
+            next = indirect.next();
+        }
+
+        return Ok(extents);
+    }
+
+    /// Checks a file's contents against its content hash, reading it a piece at a time, unless it has been checked
+    /// since it last changed.
+    fn verify_file(&self, inode_index: u64) -> Result<(), VoxFSError<E>> {
+        if self.verified_files.borrow().contains(&inode_index) {
+            return Ok(());
+        }
+
+        let mut hasher = match self.manager.content_hasher() {
+            Some(h) => h,
+            None => return Err(VoxFSError::NoContentHasher),
+        };
+
+        let size = self.inodes[self.locate_inode(inode_index)?].file_size();
+        let chunk_size = core::cmp::max(self.max_io_size, self.block_size);
+        let mut handle = FileHandle::new(inode_index);
+        let mut offset = 0;
+
+        while offset < size {
+            hasher.update(&self.read_range(&mut handle, offset, chunk_size)?);
+            offset += chunk_size;
+        }
+
+        return self.compare_content_hash(inode_index, hasher.finish());
+    }
+
+    /// Checks the whole contents of a file, as read, against its content hash.
+    fn check_contents(&self, inode_index: u64, contents: &[u8]) -> Result<(), VoxFSError<E>> {
+        let mut hasher = match self.manager.content_hasher() {
+            Some(h) => h,
+            None => return Err(VoxFSError::NoContentHasher),
+        };
+
+        hasher.update(contents);
+
+        return self.compare_content_hash(inode_index, hasher.finish());
+    }
+
+    /// Fails with `VoxFSError::DataChecksumMismatch` if the hash differs from the file's content hash, or the
+    /// file has none to compare against, otherwise remembers the file as checked.
+    fn compare_content_hash(
+        &self,
+        inode_index: u64,
+        hash: [u8; CONTENT_HASH_SIZE as usize],
+    ) -> Result<(), VoxFSError<E>> {
+        if self.file_hash(inode_index)? != Some(hash) {
+            return Err(VoxFSError::DataChecksumMismatch { inode: inode_index });
+        }
+
+        self.verified_files.borrow_mut().insert(inode_index);
+
+        return Ok(());
+    }
+
+    /// Fails with `VoxFSError::MemoryBudgetExceeded` if an operation needs more bytes than the memory budget.
+    fn check_memory_budget(&self, bytes: u64) -> Result<(), VoxFSError<E>> {
+        return match self.memory_budget {
+            Some(budget) if bytes > budget => Err(VoxFSError::MemoryBudgetExceeded),
+            _ => Ok(()),
+        };
     }
 
     /// Locates an inode based on an inode index, it returns the index in the memory map
@@ -1841,7 +5813,27 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
         return Err(VoxFSError::CouldNotFindINode);
     }
 
-    /// Checks if a tag/inode name contains any forbidden characters
+    /// Appends " (n)" to a name, before any extension, with the smallest n that isn't taken.
+    fn unique_name(&self, name: &str) -> String {
+        let (stem, extension) = match name.rfind('.') {
+            Some(i) if i > 0 => name.split_at(i),
+            _ => (name, ""),
+        };
+
+        let mut n = 1;
+
+        loop {
+            let candidate = format!("{} ({}){}", stem, n, extension);
+
+            if self.inode_with_name(&candidate).is_none() {
+                return candidate;
+            }
+
+            n += 1;
+        }
+    }
+
+    /// Checks if a tag/inode name contains any forbidden characters or is longer than the disk's limit
     fn validate_name(&self, name: &str, err: VoxFSError<E>) -> Result<(), VoxFSError<E>> {
         for ref ch in name.chars() {
             if FORBIDDEN_CHARACTERS.contains(ch) {
@@ -1849,6 +5841,77 @@ impl<'a, 'b, E: VoxFSErrorConvertible> Disk<'a, 'b, E> {
             }
         }
 
+        if let Some(length) = self.super_block.max_name_length() {
+            if name.chars().count() > length {
+                return Err(err);
+            }
+        }
+
         return Ok(());
     }
 }
+
+impl<'a, 'b, E: VoxFSErrorConvertible> Drop for Disk<'a, 'b, E> {
+    fn drop(&mut self) {
+        // Errors can not be reported here, call close first to handle them.
+        if self.sync().is_ok() {
+            let _ = self.flush_handler();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::timestamp_from_unix;
+    use crate::{MemoryDiskError, MemoryDiskHandler};
+
+    #[derive(Debug)]
+    struct Manager;
+
+    impl OSManager for Manager {
+        fn current_time(&self) -> Timestamp {
+            return timestamp_from_unix(0, 0);
+        }
+    }
+
+    #[test]
+    fn test_write_to_region() {
+        let mut handler = MemoryDiskHandler::new(4096 * 30);
+        let mut manager = Manager;
+        let mut disk: Disk<MemoryDiskError> =
+            Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+        let inode_start = disk.super_block.inode_start_address();
+        let record = vec![0xffu8; TagBlock::size() as usize];
+
+        // A tag record that would straddle into the inode table is refused before anything is written
+        assert_eq!(
+            disk.write_to_region(
+                disk.super_block.tag_start_address(),
+                inode_start,
+                inode_start - 100,
+                &record
+            ),
+            Err(VoxFSError::AddressOutOfRegion {
+                address: inode_start - 100,
+                length: TagBlock::size(),
+            })
+        );
+        assert_eq!(
+            disk.read_from_address(inode_start, 156).unwrap(),
+            vec![0u8; 156]
+        );
+
+        let data_start = disk.super_block.data_start_address();
+        let data_end = disk.data_index_to_address(disk.super_block.block_count());
+        disk.write_to_data_region(data_start, &vec![1u8; 4096])
+            .unwrap();
+        assert!(disk
+            .write_to_data_region(data_start - 1, &vec![1u8; 4096])
+            .is_err());
+        assert!(disk
+            .write_to_data_region(data_end - 4095, &vec![1u8; 4096])
+            .is_err());
+    }
+}
@@ -0,0 +1,396 @@
+use crate::manager::{disk_to_timestamp, legacy_time_to_disk, timestamp_to_disk, Timestamp};
+use crate::{ByteSerializable, Checksum};
+use alloc::string::String;
+use byteorder::{ByteOrder, LittleEndian};
+use core::fmt::{Display, Formatter};
+
+const MAX_USER_NAME_LENGTH: usize = 16;
+const MAX_SUBJECT_NAME_LENGTH: usize = 64;
+/// Stored in place of the second index for operations that only involve one tag or file.
+const NO_TARGET: u64 = u64::MAX;
+
+/// A kind of change recorded in the history.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HistoryOperation {
+    WriteBootArea,
+    SetNamePolicy,
+    CreateTag,
+    DeleteTag,
+    ApplyTag,
+    RemoveTag,
+    CompactTag,
+    SetTagTimes,
+    SetTagAppearance,
+    MoveTagMembers,
+    CreateFile,
+    ReplaceFile,
+    AppendFile,
+    DeleteFile,
+    SetFileFlags,
+    SetFileTimes,
+    TruncateFile,
+    ArchiveTag,
+    RestoreTag,
+    RenameTag,
+}
+
+impl HistoryOperation {
+    fn to_byte(self) -> u8 {
+        return match self {
+            HistoryOperation::WriteBootArea => 1,
+            HistoryOperation::SetNamePolicy => 2,
+            HistoryOperation::CreateTag => 3,
+            HistoryOperation::DeleteTag => 4,
+            HistoryOperation::ApplyTag => 5,
+            HistoryOperation::RemoveTag => 6,
+            HistoryOperation::CompactTag => 7,
+            HistoryOperation::SetTagTimes => 8,
+            HistoryOperation::CreateFile => 9,
+            HistoryOperation::ReplaceFile => 10,
+            HistoryOperation::AppendFile => 11,
+            HistoryOperation::DeleteFile => 12,
+            HistoryOperation::SetFileFlags => 13,
+            HistoryOperation::SetFileTimes => 14,
+            HistoryOperation::SetTagAppearance => 15,
+            HistoryOperation::MoveTagMembers => 16,
+            HistoryOperation::TruncateFile => 17,
+            HistoryOperation::ArchiveTag => 18,
+            HistoryOperation::RestoreTag => 19,
+            HistoryOperation::RenameTag => 20,
+        };
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        return match byte {
+            1 => Some(HistoryOperation::WriteBootArea),
+            2 => Some(HistoryOperation::SetNamePolicy),
+            3 => Some(HistoryOperation::CreateTag),
+            4 => Some(HistoryOperation::DeleteTag),
+            5 => Some(HistoryOperation::ApplyTag),
+            6 => Some(HistoryOperation::RemoveTag),
+            7 => Some(HistoryOperation::CompactTag),
+            8 => Some(HistoryOperation::SetTagTimes),
+            9 => Some(HistoryOperation::CreateFile),
+            10 => Some(HistoryOperation::ReplaceFile),
+            11 => Some(HistoryOperation::AppendFile),
+            12 => Some(HistoryOperation::DeleteFile),
+            13 => Some(HistoryOperation::SetFileFlags),
+            14 => Some(HistoryOperation::SetFileTimes),
+            15 => Some(HistoryOperation::SetTagAppearance),
+            16 => Some(HistoryOperation::MoveTagMembers),
+            17 => Some(HistoryOperation::TruncateFile),
+            18 => Some(HistoryOperation::ArchiveTag),
+            19 => Some(HistoryOperation::RestoreTag),
+            20 => Some(HistoryOperation::RenameTag),
+            _ => None,
+        };
+    }
+}
+
+impl Display for HistoryOperation {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let name = match self {
+            HistoryOperation::WriteBootArea => "write-boot-area",
+            HistoryOperation::SetNamePolicy => "set-name-policy",
+            HistoryOperation::CreateTag => "create-tag",
+            HistoryOperation::DeleteTag => "delete-tag",
+            HistoryOperation::ArchiveTag => "archive-tag",
+            HistoryOperation::RestoreTag => "restore-tag",
+            HistoryOperation::RenameTag => "rename-tag",
+            HistoryOperation::ApplyTag => "apply-tag",
+            HistoryOperation::RemoveTag => "remove-tag",
+            HistoryOperation::CompactTag => "compact-tag",
+            HistoryOperation::SetTagTimes => "set-tag-times",
+            HistoryOperation::SetTagAppearance => "set-tag-appearance",
+            HistoryOperation::MoveTagMembers => "move-tag-members",
+            HistoryOperation::CreateFile => "create-file",
+            HistoryOperation::ReplaceFile => "replace-file",
+            HistoryOperation::AppendFile => "append-file",
+            HistoryOperation::TruncateFile => "truncate-file",
+            HistoryOperation::DeleteFile => "delete-file",
+            HistoryOperation::SetFileFlags => "set-file-flags",
+            HistoryOperation::SetFileTimes => "set-file-times",
+        };
+
+        return write!(f, "{}", name);
+    }
+}
+
+/// One change recorded in the history region, who made it, when and to what. Length of 128 bytes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HistoryRecord {
+    /// Counts up from 1 with every record written, an empty slot has a sequence of 0.
+    sequence: u64,
+    time: u64,
+    operation: HistoryOperation,
+    user_id: u32,
+    user_name: [u8; MAX_USER_NAME_LENGTH],
+    /// The index of the tag or file changed.
+    subject: u64,
+    /// The index of the file a tag was applied to or removed from.
+    target: u64,
+    /// The name of the subject when the change was made, truncated to 64 bytes.
+    name: [u8; MAX_SUBJECT_NAME_LENGTH],
+    checksum: u8,
+}
+
+impl HistoryRecord {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        sequence: u64,
+        time: Timestamp,
+        operation: HistoryOperation,
+        user_id: u32,
+        user_name: &str,
+        subject: u64,
+        target: Option<u64>,
+        name: &str,
+    ) -> Self {
+        let mut record = Self {
+            sequence,
+            time: timestamp_to_disk(time),
+            operation,
+            user_id,
+            user_name: truncated(user_name),
+            subject,
+            target: target.unwrap_or(NO_TARGET),
+            name: truncated(name),
+            checksum: 0,
+        };
+
+        record.set_checksum();
+
+        return record;
+    }
+
+    pub const fn size() -> u64 {
+        return 128;
+    }
+
+    pub fn sequence(&self) -> u64 {
+        return self.sequence;
+    }
+
+    pub fn time(&self) -> Timestamp {
+        return disk_to_timestamp(self.time);
+    }
+
+    /// Converts the time read from a disk older than format version 1 to the current encoding.
+    pub(crate) fn convert_legacy_time(&mut self) {
+        self.time = legacy_time_to_disk(self.time);
+        self.set_checksum();
+    }
+
+    pub fn operation(&self) -> HistoryOperation {
+        return self.operation;
+    }
+
+    pub fn user_id(&self) -> u32 {
+        return self.user_id;
+    }
+
+    pub fn user_name(&self) -> String {
+        return from_padded(&self.user_name);
+    }
+
+    /// The index of the tag or file changed, the tag for `ApplyTag` and `RemoveTag`.
+    pub fn subject(&self) -> u64 {
+        return self.subject;
+    }
+
+    /// The index of the file for `ApplyTag` and `RemoveTag`, the destination tag for `MoveTagMembers`.
+    pub fn target(&self) -> Option<u64> {
+        return match self.target {
+            NO_TARGET => None,
+            target => Some(target),
+        };
+    }
+
+    /// The name of the subject when the change was made, truncated to 64 bytes.
+    pub fn name(&self) -> String {
+        return from_padded(&self.name);
+    }
+}
+
+/// Copies as much of a string as fits into a null padded array without splitting a character.
+fn truncated<const N: usize>(string: &str) -> [u8; N] {
+    let mut length = core::cmp::min(string.len(), N);
+
+    while !string.is_char_boundary(length) {
+        length -= 1;
+    }
+
+    let mut bytes = [0u8; N];
+    bytes[..length].copy_from_slice(&string.as_bytes()[..length]);
+
+    return bytes;
+}
+
+fn from_padded(bytes: &[u8]) -> String {
+    let length = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+
+    return String::from_utf8_lossy(&bytes[..length]).into_owned();
+}
+
+impl ByteSerializable for HistoryRecord {
+    type BytesArrayType = [u8; 128];
+
+    fn to_bytes(&self) -> Self::BytesArrayType {
+        let mut bytes = [0u8; 128];
+        let mut offset = 0;
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.sequence);
+        offset += 8;
+        LittleEndian::write_u64(&mut bytes[offset..], self.time);
+        offset += 8;
+
+        bytes[offset] = self.operation.to_byte();
+        offset += 1;
+
+        LittleEndian::write_u32(&mut bytes[offset..], self.user_id);
+        offset += 4;
+        bytes[offset..offset + MAX_USER_NAME_LENGTH].copy_from_slice(&self.user_name);
+        offset += MAX_USER_NAME_LENGTH;
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.subject);
+        offset += 8;
+        LittleEndian::write_u64(&mut bytes[offset..], self.target);
+        offset += 8;
+        bytes[offset..offset + MAX_SUBJECT_NAME_LENGTH].copy_from_slice(&self.name);
+
+        // bytes 117 to 126 are reserved
+
+        bytes[127] = self.checksum;
+
+        return bytes;
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self>
+    where
+        Self: core::marker::Sized,
+    {
+        if bytes.len() < Self::size() as usize {
+            return None;
+        }
+
+        let mut offset = 0;
+        let mut user_name = [0u8; MAX_USER_NAME_LENGTH];
+        let mut name = [0u8; MAX_SUBJECT_NAME_LENGTH];
+
+        let sequence = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+        let time = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+
+        let operation = HistoryOperation::from_byte(bytes[offset])?;
+        offset += 1;
+
+        let user_id = LittleEndian::read_u32(&bytes[offset..]);
+        offset += 4;
+        user_name.copy_from_slice(&bytes[offset..offset + MAX_USER_NAME_LENGTH]);
+        offset += MAX_USER_NAME_LENGTH;
+
+        let subject = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+        let target = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+        name.copy_from_slice(&bytes[offset..offset + MAX_SUBJECT_NAME_LENGTH]);
+
+        let record = Self {
+            sequence,
+            time,
+            operation,
+            user_id,
+            user_name,
+            subject,
+            target,
+            name,
+            checksum: bytes[127],
+        };
+
+        if record.perform_checksum() {
+            return Some(record);
+        } else {
+            return None;
+        }
+    }
+
+    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
+        return bytes;
+    }
+}
+
+impl Checksum for HistoryRecord {
+    fn set_checksum(&mut self) {
+        self.checksum = 0; // For the purpose of calculation
+        self.checksum = self.calculate_checksum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::disk_to_timestamp;
+
+    #[test]
+    fn test_round_trip() {
+        let record = HistoryRecord::new(
+            7,
+            disk_to_timestamp(1_000),
+            HistoryOperation::ApplyTag,
+            1000,
+            "alice",
+            3,
+            Some(12),
+            "work",
+        );
+
+        let parsed = HistoryRecord::from_bytes(&record.to_bytes()).unwrap();
+
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.user_name(), "alice");
+        assert_eq!(parsed.name(), "work");
+        assert_eq!(parsed.target(), Some(12));
+        assert_eq!(parsed.time(), disk_to_timestamp(1_000));
+    }
+
+    #[test]
+    fn test_truncated_names() {
+        let record = HistoryRecord::new(
+            1,
+            disk_to_timestamp(0),
+            HistoryOperation::CreateFile,
+            0,
+            "a_user_name_that_is_too_long",
+            0,
+            None,
+            &"é".repeat(40),
+        );
+
+        assert_eq!(record.user_name(), "a_user_name_that");
+        assert_eq!(record.name(), "é".repeat(32));
+        assert_eq!(record.target(), None);
+    }
+
+    #[test]
+    fn test_empty_slot_and_corruption() {
+        // An empty slot has no operation so it never parses as a record
+        assert_eq!(HistoryRecord::from_bytes(&[0u8; 128]), None);
+
+        let record = HistoryRecord::new(
+            1,
+            disk_to_timestamp(0),
+            HistoryOperation::DeleteTag,
+            0,
+            "",
+            4,
+            None,
+            "old",
+        );
+
+        let mut bytes = record.to_bytes();
+        bytes[60] ^= 0x10;
+
+        assert_eq!(HistoryRecord::from_bytes(&bytes), None);
+    }
+}
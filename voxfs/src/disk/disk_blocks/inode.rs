@@ -1,13 +1,59 @@
+use crate::checksum_trait::UnverifiedRecord;
+use crate::manager::{disk_to_timestamp, legacy_time_to_disk, timestamp_to_disk, Timestamp};
 use crate::ByteSerializable;
 use crate::Checksum;
 use alloc::string::String;
 use alloc::{vec, vec::Vec};
 use byteorder::{ByteOrder, LittleEndian};
-use chrono::{DateTime, TimeZone, Utc};
 
-const MAX_INODE_NAME_LENGTH: usize = 125;
 const INODE_EXTENT_COUNT: usize = 5;
 
+/// The kind of file an inode represents, stored in the lowest 2 bits of the flags.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Regular,
+    /// A symbolic link, its contents are the path it points to.
+    Symlink,
+    /// A character or block device, its device number is stored in place of the extents and it has no contents.
+    Device,
+    /// A socket or other special file without contents.
+    Socket,
+}
+
+impl FileType {
+    fn to_bits(self) -> u8 {
+        return match self {
+            FileType::Regular => 0,
+            FileType::Symlink => 1,
+            FileType::Device => 2,
+            FileType::Socket => 3,
+        };
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        return match bits & 0b11 {
+            0 => FileType::Regular,
+            1 => FileType::Symlink,
+            2 => FileType::Device,
+            _ => FileType::Socket,
+        };
+    }
+
+    /// Whether files of this type store their contents in data blocks.
+    pub fn has_contents(self) -> bool {
+        return match self {
+            FileType::Regular | FileType::Symlink => true,
+            FileType::Device | FileType::Socket => false,
+        };
+    }
+}
+
+impl Default for FileType {
+    fn default() -> Self {
+        return FileType::Regular;
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(packed)]
 /// The possible flags for a file.
@@ -17,7 +63,11 @@ pub struct INodeFlags {
     read: bool,
     write: bool,
     execute: bool,
-    // reserved: [bool; 4],  There are 4 reserved bits for future use
+    /// The file's contents can only be added to.
+    append_only: bool,
+    /// The file can't be modified or deleted.
+    immutable: bool,
+    file_type: FileType,
 }
 
 #[derive(Copy, Clone)]
@@ -26,16 +76,16 @@ pub struct INode {
     /// Index, a unique number representing this inode's location in the inode map
     index: u64,
     /// name, 125 bytes constant filled with null bytes otherwise
-    name: [char; MAX_INODE_NAME_LENGTH],
+    name: [char; Self::MAX_NAME_LENGTH],
     /// size in bytes, this is the actual size NOT the on disk size.
     size: u64,
-    /// flags (v,r,w,e), bits 5 - 8 are reserved
+    /// flags (v,r,w,e,a,i) from the highest bit down, with the file type in the lowest 2 bits
     flags: INodeFlags,
-    /// access time, nano seconds since unix epoch
+    /// access time, as stored by timestamp_to_disk
     access_time: u64,
-    /// modified time, nano seconds since unix epoch
+    /// modified time, as stored by timestamp_to_disk
     modified_time: u64,
-    /// creation time, nano seconds since unix epoch
+    /// creation time, as stored by timestamp_to_disk
     creation_time: u64,
     /// checksum, sum of the bytes with wrapping addition must be zero.
     checksum: u8,
@@ -79,16 +129,94 @@ impl INodeFlags {
             read,
             write,
             execute,
+            append_only: false,
+            immutable: false,
+            file_type: FileType::Regular,
         };
     }
 
+    /// Flags for a regular file that can be read and written but not executed, the same as the default.
+    pub fn read_write() -> Self {
+        return Self::new(true, true, true, false);
+    }
+
+    /// Flags for a regular file that can be read but not written or executed.
+    pub fn read_only() -> Self {
+        return Self::new(true, true, false, false);
+    }
+
     pub fn from_u8(n: u8) -> Self {
         let valid = ((n >> 7) & 1) == 1;
         let read = ((n >> 6) & 1) == 1;
         let write = ((n >> 5) & 1) == 1;
         let execute = ((n >> 4) & 1) == 1;
 
-        return Self::new(valid, read, write, execute);
+        return Self::new(valid, read, write, execute)
+            .with_append_only(((n >> 3) & 1) == 1)
+            .with_immutable(((n >> 2) & 1) == 1)
+            .with_file_type(FileType::from_bits(n));
+    }
+
+    pub fn with_file_type(mut self, file_type: FileType) -> Self {
+        self.file_type = file_type;
+
+        return self;
+    }
+
+    pub fn file_type(&self) -> FileType {
+        return self.file_type;
+    }
+
+    pub fn with_read(mut self, read: bool) -> Self {
+        self.read = read;
+
+        return self;
+    }
+
+    pub fn with_write(mut self, write: bool) -> Self {
+        self.write = write;
+
+        return self;
+    }
+
+    pub fn with_execute(mut self, execute: bool) -> Self {
+        self.execute = execute;
+
+        return self;
+    }
+
+    pub fn read(&self) -> bool {
+        return self.read;
+    }
+
+    pub fn write(&self) -> bool {
+        return self.write;
+    }
+
+    pub fn execute(&self) -> bool {
+        return self.execute;
+    }
+
+    pub fn with_append_only(mut self, append_only: bool) -> Self {
+        self.append_only = append_only;
+
+        return self;
+    }
+
+    pub fn with_immutable(mut self, immutable: bool) -> Self {
+        self.immutable = immutable;
+
+        return self;
+    }
+
+    /// Whether the file's contents can only be appended to, it can't be replaced or deleted.
+    pub fn append_only(&self) -> bool {
+        return self.append_only;
+    }
+
+    /// Whether the file can't be appended to, replaced or deleted.
+    pub fn immutable(&self) -> bool {
+        return self.immutable;
     }
 
     pub fn to_u8(&self) -> u8 {
@@ -110,6 +238,16 @@ impl INodeFlags {
             res |= 1 << 4;
         }
 
+        if self.append_only {
+            res |= 1 << 3;
+        }
+
+        if self.immutable {
+            res |= 1 << 2;
+        }
+
+        res |= self.file_type.to_bits();
+
         return res;
     }
 }
@@ -120,23 +258,45 @@ impl Default for INodeFlags {
     }
 }
 
+impl core::fmt::Display for INodeFlags {
+    /// Formats the flags like file permissions, e.g. "rwx-" or "rw--". The last character is 'i' for an
+    /// immutable file, otherwise 'a' for an append only file.
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let read = if self.read { 'r' } else { '-' };
+        let write = if self.write { 'w' } else { '-' };
+        let execute = if self.execute { 'x' } else { '-' };
+        let protection = if self.immutable {
+            'i'
+        } else if self.append_only {
+            'a'
+        } else {
+            '-'
+        };
+
+        return write!(f, "{}{}{}{}", read, write, execute, protection);
+    }
+}
+
 impl INode {
+    /// The most characters a file name can have, longer names are cut short.
+    pub const MAX_NAME_LENGTH: usize = 125;
+
     pub fn new(
         index: u64,
         str_name: &str,
         size: u64,
         flags: INodeFlags,
-        access_time: DateTime<Utc>,
-        modified_time: DateTime<Utc>,
-        creation_time: DateTime<Utc>,
+        access_time: Timestamp,
+        modified_time: Timestamp,
+        creation_time: Timestamp,
         indirect_pointer: u64,
         num_extents: u8,
         blocks: [Extent; INODE_EXTENT_COUNT],
     ) -> Self {
-        let mut name: [char; MAX_INODE_NAME_LENGTH] = ['\0'; MAX_INODE_NAME_LENGTH];
+        let mut name: [char; Self::MAX_NAME_LENGTH] = ['\0'; Self::MAX_NAME_LENGTH];
 
         for (i, c) in str_name.chars().enumerate() {
-            if i >= MAX_INODE_NAME_LENGTH {
+            if i >= Self::MAX_NAME_LENGTH {
                 break;
             }
 
@@ -148,9 +308,9 @@ impl INode {
             name,
             size,
             flags,
-            access_time: access_time.timestamp_nanos() as u64,
-            modified_time: modified_time.timestamp_nanos() as u64,
-            creation_time: creation_time.timestamp_nanos() as u64,
+            access_time: timestamp_to_disk(access_time),
+            modified_time: timestamp_to_disk(modified_time),
+            creation_time: timestamp_to_disk(creation_time),
             checksum: 0,
             indirect_block: indirect_pointer,
             num_extents,
@@ -162,7 +322,8 @@ impl INode {
         return res;
     }
 
-    pub(crate) const fn size() -> u64 {
+    /// The size of an inode on the disk.
+    pub const fn size() -> u64 {
         return 256;
     }
 
@@ -231,16 +392,54 @@ impl INode {
         return self.size;
     }
 
-    pub fn access_time(&self) -> DateTime<Utc> {
-        return Utc.timestamp_nanos(self.access_time as i64);
+    pub fn access_time(&self) -> Timestamp {
+        return disk_to_timestamp(self.access_time);
+    }
+
+    pub fn modified_time(&self) -> Timestamp {
+        return disk_to_timestamp(self.modified_time);
     }
 
-    pub fn modified_time(&self) -> DateTime<Utc> {
-        return Utc.timestamp_nanos(self.modified_time as i64);
+    pub fn creation_time(&self) -> Timestamp {
+        return disk_to_timestamp(self.creation_time);
     }
 
-    pub fn creation_time(&self) -> DateTime<Utc> {
-        return Utc.timestamp_nanos(self.creation_time as i64);
+    pub fn flags(&self) -> INodeFlags {
+        return self.flags;
+    }
+
+    /// The device number of a device file.
+    pub fn rdev(&self) -> Option<u64> {
+        if self.flags.file_type() != FileType::Device {
+            return None;
+        }
+
+        return Some(self.blocks[0].start);
+    }
+
+    pub(crate) fn set_flags(&mut self, flags: INodeFlags) {
+        self.flags = flags;
+        self.set_checksum();
+    }
+
+    pub(crate) fn set_times(
+        &mut self,
+        creation_time: Timestamp,
+        modified_time: Timestamp,
+        access_time: Timestamp,
+    ) {
+        self.creation_time = timestamp_to_disk(creation_time);
+        self.modified_time = timestamp_to_disk(modified_time);
+        self.access_time = timestamp_to_disk(access_time);
+        self.set_checksum();
+    }
+
+    /// Converts the times read from a disk older than format version 1 to the current encoding.
+    pub(crate) fn convert_legacy_times(&mut self) {
+        self.access_time = legacy_time_to_disk(self.access_time);
+        self.modified_time = legacy_time_to_disk(self.modified_time);
+        self.creation_time = legacy_time_to_disk(self.creation_time);
+        self.set_checksum();
     }
 
     pub(crate) fn increase_file_size(&mut self, amount: u64) {
@@ -299,7 +498,7 @@ impl ByteSerializable for INode {
             bytes[offset + i] = *c as u8;
         }
 
-        offset += MAX_INODE_NAME_LENGTH;
+        offset += Self::MAX_NAME_LENGTH;
 
         LittleEndian::write_u64(&mut bytes[offset..], self.size);
         offset += 8;
@@ -335,6 +534,22 @@ impl ByteSerializable for INode {
 
     /// Performs the checksum check.
     fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let res = Self::from_bytes_unverified(bytes)?;
+
+        if res.perform_checksum() {
+            return Some(res);
+        } else {
+            return None;
+        }
+    }
+
+    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
+        return bytes;
+    }
+}
+
+impl UnverifiedRecord for INode {
+    fn from_bytes_unverified(bytes: &[u8]) -> Option<Self> {
         if bytes.len() < 256 {
             return None;
         }
@@ -342,7 +557,7 @@ impl ByteSerializable for INode {
         let mut offset = 0;
 
         let index: u64;
-        let mut name = ['\0'; MAX_INODE_NAME_LENGTH];
+        let mut name = ['\0'; Self::MAX_NAME_LENGTH];
         let size: u64;
         let flags: INodeFlags;
         let access_time: u64;
@@ -357,12 +572,12 @@ impl ByteSerializable for INode {
         offset += 8;
 
         let mut i = 0;
-        for ch in &bytes[offset..offset + MAX_INODE_NAME_LENGTH] {
+        for ch in &bytes[offset..offset + Self::MAX_NAME_LENGTH] {
             name[i] = *ch as char;
             i += 1;
         }
 
-        offset += MAX_INODE_NAME_LENGTH;
+        offset += Self::MAX_NAME_LENGTH;
 
         size = LittleEndian::read_u64(&bytes[offset..]);
         offset += 8;
@@ -388,6 +603,10 @@ impl ByteSerializable for INode {
         num_extents = bytes[offset];
         offset += 1;
 
+        if num_extents as usize > INODE_EXTENT_COUNT {
+            return None;
+        }
+
         let mut i = 0;
         for _ in (offset..256).step_by(16) {
             blocks[i].start = LittleEndian::read_u64(&bytes[offset..]);
@@ -412,15 +631,7 @@ impl ByteSerializable for INode {
             blocks,
         };
 
-        if s.perform_checksum() {
-            return Some(s);
-        } else {
-            return None;
-        }
-    }
-
-    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
-        return bytes;
+        return Some(s);
     }
 }
 
@@ -428,7 +639,7 @@ impl core::cmp::PartialEq for INode {
     fn eq(&self, other: &Self) -> bool {
         let mut name_identical = true;
 
-        for i in 0..MAX_INODE_NAME_LENGTH {
+        for i in 0..Self::MAX_NAME_LENGTH {
             if other.name[i] != self.name[i] {
                 name_identical = false;
 
@@ -452,7 +663,8 @@ impl core::cmp::PartialEq for INode {
 
 impl core::fmt::Debug for INode {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        let name_str: alloc::string::String = self.name.iter().collect();
+        let name_str: alloc::string::String =
+            self.name.iter().take_while(|c| **c != '\0').collect();
         return f
             .debug_struct("INode")
             .field("index", &self.index)
@@ -605,6 +817,22 @@ impl ByteSerializable for IndirectINode {
 
     // Performs a checksum check
     fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let res = Self::from_bytes_unverified(bytes)?;
+
+        if res.perform_checksum() {
+            return Some(res);
+        } else {
+            return None;
+        }
+    }
+
+    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
+        return bytes;
+    }
+}
+
+impl UnverifiedRecord for IndirectINode {
+    fn from_bytes_unverified(bytes: &[u8]) -> Option<Self> {
         if bytes.len() < Self::NON_EXPANDABLE_SIZE as usize {
             return None;
         }
@@ -629,6 +857,11 @@ impl ByteSerializable for IndirectINode {
         num_extents = LittleEndian::read_u16(&bytes[offset..]);
         offset += 2;
 
+        // The extents must fit in the provided bytes
+        if bytes.len() < offset + num_extents as usize * Extent::size() as usize {
+            return None;
+        }
+
         for _ in 0..num_extents {
             let start = LittleEndian::read_u64(&bytes[offset..]);
             offset += 8;
@@ -647,15 +880,7 @@ impl ByteSerializable for IndirectINode {
             maximum_extents: 0,
         };
 
-        if res.perform_checksum() {
-            return Some(res);
-        } else {
-            return None;
-        }
-    }
-
-    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
-        return bytes;
+        return Some(res);
     }
 }
 
@@ -669,6 +894,15 @@ impl Checksum for IndirectINode {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::manager::{timestamp_from_unix, MAX_TIMESTAMP_SECONDS, MIN_TIMESTAMP_SECONDS};
+    use chrono::DateTime;
+
+    /// Parses an RFC 2822 date into a timestamp regardless of the `timestamps` feature.
+    fn time(date: &str) -> Timestamp {
+        let time = DateTime::parse_from_rfc2822(date).unwrap();
+
+        return timestamp_from_unix(time.timestamp(), time.timestamp_subsec_nanos());
+    }
 
     mod flags {
         use super::*;
@@ -684,6 +918,81 @@ mod tests {
             let flags = INodeFlags::new(true, false, true, false);
             assert_eq!(flags, INodeFlags::from_u8(0b1010_0000));
         }
+
+        #[test]
+        fn test_append_only_and_immutable() {
+            let flags = INodeFlags::new(true, true, true, false).with_append_only(true);
+            assert_eq!(flags.to_u8(), 0b1110_1000);
+            assert!(INodeFlags::from_u8(0b1110_1000).append_only());
+
+            let flags = INodeFlags::new(true, true, false, false).with_immutable(true);
+            assert_eq!(flags.to_u8(), 0b1100_0100);
+            assert!(INodeFlags::from_u8(0b1100_0100).immutable());
+            assert!(!INodeFlags::from_u8(0b1100_0100).append_only());
+        }
+
+        #[test]
+        fn test_file_type() {
+            assert_eq!(
+                INodeFlags::from_u8(0b1100_0000).file_type(),
+                FileType::Regular
+            );
+
+            for file_type in [
+                FileType::Regular,
+                FileType::Symlink,
+                FileType::Device,
+                FileType::Socket,
+            ]
+            .iter()
+            {
+                let flags = INodeFlags::new(true, true, true, false)
+                    .with_immutable(true)
+                    .with_file_type(*file_type);
+
+                assert_eq!(INodeFlags::from_u8(flags.to_u8()), flags);
+            }
+
+            assert_eq!(
+                INodeFlags::default()
+                    .with_file_type(FileType::Socket)
+                    .to_u8(),
+                0b1110_0011
+            );
+        }
+
+        #[test]
+        fn test_builders_and_display() {
+            assert_eq!(INodeFlags::read_write(), INodeFlags::default());
+            assert_eq!(
+                INodeFlags::read_write().with_execute(true),
+                INodeFlags::new(true, true, true, true)
+            );
+            assert_eq!(
+                INodeFlags::read_only(),
+                INodeFlags::new(true, true, false, false)
+            );
+            assert!(!INodeFlags::read_write().with_read(false).read());
+
+            assert_eq!(
+                format!("{}", INodeFlags::read_write().with_execute(true)),
+                "rwx-"
+            );
+            assert_eq!(format!("{}", INodeFlags::read_write()), "rw--");
+            assert_eq!(
+                format!("{}", INodeFlags::read_only().with_append_only(true)),
+                "r--a"
+            );
+            assert_eq!(
+                format!(
+                    "{}",
+                    INodeFlags::read_only()
+                        .with_append_only(true)
+                        .with_immutable(true)
+                ),
+                "r--i"
+            );
+        }
     }
 
     mod inode {
@@ -700,15 +1009,9 @@ mod tests {
                 "new file",
                 246,
                 INodeFlags::new(true, true, false, false),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:09 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Thu, 19 Feb 2015 23:16:09 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Fri, 20 Feb 2015 23:16:09 +0000").unwrap(),
-                ),
+                time("Wed, 18 Feb 2015 23:16:09 +0000"),
+                time("Thu, 19 Feb 2015 23:16:09 +0000"),
+                time("Fri, 20 Feb 2015 23:16:09 +0000"),
                 0,
                 1,
                 blocks,
@@ -728,15 +1031,9 @@ mod tests {
                 "name",
                 246,
                 INodeFlags::new(true, true, false, false),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:09 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:10 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:11 +0000").unwrap(),
-                ),
+                time("Wed, 18 Feb 2015 23:16:09 +0000"),
+                time("Wed, 18 Feb 2015 23:16:10 +0000"),
+                time("Wed, 18 Feb 2015 23:16:11 +0000"),
                 0,
                 1,
                 blocks,
@@ -756,38 +1053,29 @@ mod tests {
             comp[133] = 246; // Size
             comp[141] = 0b1100_0000; // Flags
 
-            // Access Time: 0x13c4228c8058fa00
-            comp[142] = 0x00;
-            comp[143] = 0xfa;
-            comp[144] = 0x58;
-            comp[145] = 0x80;
-            comp[146] = 0x8c;
-            comp[147] = 0x22;
-            comp[148] = 0xc4;
-            comp[149] = 0x13;
-
-            // Modified Time: 0x13c4228cbbf3c400
-            comp[150] = 0x00;
-            comp[151] = 0xc4;
-            comp[152] = 0xf3;
-            comp[153] = 0xbb;
-            comp[154] = 0x8c;
-            comp[155] = 0x22;
-            comp[156] = 0xc4;
-            comp[157] = 0x13;
-
-            // Creation Time: 0x13c4228cf78e8e00
-            comp[158] = 0x00;
-            comp[159] = 0x8e;
-            comp[160] = 0x8e;
-            comp[161] = 0xf7;
-            comp[162] = 0x8c;
-            comp[163] = 0x22;
-            comp[164] = 0xc4;
-            comp[165] = 0x13;
+            // Access Time: 0x3539474e40000000
+            comp[145] = 0x40;
+            comp[146] = 0x4e;
+            comp[147] = 0x47;
+            comp[148] = 0x39;
+            comp[149] = 0x35;
+
+            // Modified Time: 0x3539474e80000000
+            comp[153] = 0x80;
+            comp[154] = 0x4e;
+            comp[155] = 0x47;
+            comp[156] = 0x39;
+            comp[157] = 0x35;
+
+            // Creation Time: 0x3539474ec0000000
+            comp[161] = 0xc0;
+            comp[162] = 0x4e;
+            comp[163] = 0x47;
+            comp[164] = 0x39;
+            comp[165] = 0x35;
 
             // Checksum
-            comp[166] = 35;
+            comp[166] = 128;
 
             // indirect pointer
             comp[167] = 0;
@@ -805,124 +1093,26 @@ mod tests {
         }
 
         #[test]
-        fn test_from_bytes() {
-            let comp = {
-                let mut comp = [0u8; 256];
-
-                comp[0] = 1; // Index
-
-                comp[8] = b'n'; // Name
-                comp[9] = b'a';
-                comp[10] = b'm';
-                comp[11] = b'e';
-
-                comp[133] = 246; // Size
-                comp[141] = 0b1100_0000; // Flags
-
-                // Access Time: 0x13c4228c8058fa00
-                comp[142] = 0x00;
-                comp[143] = 0xfa;
-                comp[144] = 0x58;
-                comp[145] = 0x80;
-                comp[146] = 0x8c;
-                comp[147] = 0x22;
-                comp[148] = 0xc4;
-                comp[149] = 0x13;
-
-                // Modified Time: 0x13c4228cbbf3c400
-                comp[150] = 0x00;
-                comp[151] = 0xc4;
-                comp[152] = 0xf3;
-                comp[153] = 0xbb;
-                comp[154] = 0x8c;
-                comp[155] = 0x22;
-                comp[156] = 0xc4;
-                comp[157] = 0x13;
-
-                // Creation Time: 0x13c4228cf78e8e00
-                comp[158] = 0x00;
-                comp[159] = 0x8e;
-                comp[160] = 0x8e;
-                comp[161] = 0xf7;
-                comp[162] = 0x8c;
-                comp[163] = 0x22;
-                comp[164] = 0xc4;
-                comp[165] = 0x13;
-
-                // Checksum
-                comp[166] = 35;
-
-                // indirect pointer
-                comp[167] = 0;
-
-                comp[175] = 0x1; // Size
-
-                // blocks
-                comp[176] = 0x2;
-
-                comp[184] = 0x56;
-                comp[185] = 0x34;
-                comp[186] = 0x12;
-
-                comp
-            };
-
-            let mut blocks = [Extent::zeroed(); 5];
-            blocks[0].start = 0x2;
-            blocks[0].end = 0x123456;
-
+        fn test_from_bytes_too_many_extents() {
             let node = INode::new(
                 1,
                 "name",
                 246,
                 INodeFlags::new(true, true, false, false),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:09 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:10 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:11 +0000").unwrap(),
-                ),
+                time("Wed, 18 Feb 2015 23:16:09 +0000"),
+                time("Thu, 19 Feb 2015 23:16:10 +0000"),
+                time("Fri, 20 Feb 2015 23:16:11 +0000"),
                 0,
                 1,
-                blocks,
+                [Extent::zeroed(); 5],
             );
 
-            assert!(node.perform_checksum());
-
-            assert_eq!(INode::from_bytes(&comp).unwrap(), node);
-        }
-
-        #[test]
-        fn test_to_from_bytes() {
-            let mut blocks = [Extent::zeroed(); 5];
-            blocks[0].start = 0x2;
-            blocks[0].end = 0x123456;
-
-            let node = INode::new(
-                1,
-                "name",
-                246,
-                INodeFlags::new(true, true, false, false),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Wed, 18 Feb 2015 23:16:09 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Thu, 19 Feb 2015 23:16:10 +0000").unwrap(),
-                ),
-                DateTime::from(
-                    DateTime::parse_from_rfc2822("Fri, 20 Feb 2015 23:16:11 +0000").unwrap(),
-                ),
-                0,
-                1,
-                blocks,
-            );
-
-            assert!(node.perform_checksum());
+            // Raise the extent count past the 5 available and keep the checksum valid
+            let mut bytes = node.to_bytes();
+            bytes[175] = bytes[175].wrapping_add(5);
+            bytes[166] = bytes[166].wrapping_sub(5);
 
-            assert_eq!(INode::from_bytes(&node.to_bytes()).unwrap(), node);
+            assert_eq!(INode::from_bytes(&bytes), None);
         }
     }
 
@@ -988,84 +1178,125 @@ mod tests {
         }
 
         #[test]
-        fn test_from_bytes() {
-            let comp = {
-                let mut comp = vec![0u8; IndirectINode::NON_EXPANDABLE_SIZE as usize];
-
-                // Checksum
-                comp[0] = 84;
-
-                // Reserved
-                comp[1] = 0;
-
-                // Next
-                comp[2] = 0x12;
-                comp[3] = 0xff;
-                comp[4] = 0xfe;
-
-                // Number of extents
-                comp[10] = 1;
+        fn test_from_bytes_truncated() {
+            let node = IndirectINode::new(vec![Extent::zeroed(); 2], 0xfeff12, 4096);
+            let bytes = node.to_bytes();
 
-                // Pointers
-                comp.push(0x12);
-                comp.push(0x73);
-                comp.push(0x62);
-                comp.push(0xdf);
-                comp.push(0);
-                comp.push(0);
-                comp.push(0);
-                comp.push(0);
+            assert_eq!(IndirectINode::from_bytes(&bytes[..bytes.len() - 8]), None);
+        }
+    }
 
-                comp.push(0x12);
-                comp.push(0x73);
-                comp.push(0x62);
-                comp.push(0xef);
-                comp.push(0);
-                comp.push(0);
-                comp.push(0);
-                comp.push(0);
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn arb_time() -> impl Strategy<Value = Timestamp> {
+            return (
+                MIN_TIMESTAMP_SECONDS..=MAX_TIMESTAMP_SECONDS,
+                0..1_000_000_000u32,
+            )
+                .prop_map(|(seconds, nanos)| timestamp_from_unix(seconds, nanos));
+        }
 
-                comp
-            };
+        fn arb_extent() -> impl Strategy<Value = Extent> {
+            return (any::<u64>(), any::<u64>()).prop_map(|(start, end)| Extent { start, end });
+        }
 
-            let extents = {
-                let mut pointers = vec![Extent::zeroed()];
+        fn arb_flags() -> impl Strategy<Value = INodeFlags> {
+            return (any::<(bool, bool, bool, bool, bool, bool)>(), 0..4u8).prop_map(
+                |((valid, read, write, execute, append_only, immutable), file_type)| {
+                    INodeFlags::new(valid, read, write, execute)
+                        .with_append_only(append_only)
+                        .with_immutable(immutable)
+                        .with_file_type(FileType::from_bits(file_type))
+                },
+            );
+        }
 
-                pointers[0].start = 0xdf627312;
-                pointers[0].end = 0xef627312;
+        fn arb_inode() -> impl Strategy<Value = INode> {
+            return (
+                any::<u64>(),
+                "[a-zA-Z0-9_. -]{0,125}",
+                any::<u64>(),
+                arb_flags(),
+                arb_time(),
+                arb_time(),
+                arb_time(),
+                any::<u64>(),
+                0..=INODE_EXTENT_COUNT as u8,
+                proptest::array::uniform5(arb_extent()),
+            )
+                .prop_map(
+                    |(
+                        index,
+                        name,
+                        size,
+                        flags,
+                        atime,
+                        mtime,
+                        ctime,
+                        indirect,
+                        num_extents,
+                        blocks,
+                    )| {
+                        INode::new(
+                            index,
+                            &name,
+                            size,
+                            flags,
+                            atime,
+                            mtime,
+                            ctime,
+                            indirect,
+                            num_extents,
+                            blocks,
+                        )
+                    },
+                );
+        }
 
-                pointers
-            };
+        fn arb_indirect_inode() -> impl Strategy<Value = IndirectINode> {
+            let maximum = IndirectINode::max_extents_for_blocksize(4096) as usize;
 
-            let node = IndirectINode::new(extents, 0xfeff12, 4096);
-            assert!(node.perform_checksum());
+            return (
+                proptest::collection::vec(arb_extent(), 0..=maximum),
+                any::<u64>(),
+            )
+                .prop_map(|(extents, next)| IndirectINode::new(extents, next, 4096));
+        }
 
-            let mut comp = IndirectINode::from_bytes(&comp).unwrap();
-            comp.maximum_extents = node.maximum_extents;
-            assert!(comp.perform_checksum());
+        proptest! {
+            #[test]
+            fn inode_round_trip(node in arb_inode()) {
+                prop_assert!(node.perform_checksum());
 
-            assert_eq!(node, comp);
-        }
+                let bytes = node.to_bytes();
+                let parsed = INode::from_bytes(&bytes).unwrap();
 
-        #[test]
-        fn test_to_from_bytes() {
-            let extents = {
-                let mut pointers = vec![Extent::zeroed()];
+                prop_assert_eq!(parsed, node);
+                prop_assert_eq!(parsed.to_bytes().to_vec(), bytes.to_vec());
+                prop_assert_eq!(parsed.calculate_checksum(), node.calculate_checksum());
+            }
 
-                pointers[0].start = 0xdf627312;
-                pointers[0].end = 0xef627312;
+            #[test]
+            fn inode_corruption_detected(node in arb_inode(), position in 0..256usize, change in 1..=255u8) {
+                let mut bytes = node.to_bytes();
+                bytes[position] = bytes[position].wrapping_add(change);
 
-                pointers
-            };
+                prop_assert_eq!(INode::from_bytes(&bytes), None);
+            }
 
-            let node = IndirectINode::new(extents, 0xfeff12, 4096);
-            assert!(node.perform_checksum());
+            #[test]
+            fn indirect_inode_round_trip(node in arb_indirect_inode()) {
+                prop_assert!(node.perform_checksum());
 
-            let mut comp = IndirectINode::from_bytes(&node.to_bytes()).unwrap();
-            assert!(comp.perform_checksum());
-            comp.maximum_extents = node.maximum_extents; // This is
+                let bytes = node.to_bytes();
+                let mut parsed = IndirectINode::from_bytes(&bytes).unwrap();
+                parsed.set_maximum_extents_blocksize(4096);
 
-            assert_eq!(comp, node);
+                prop_assert_eq!(&parsed, &node);
+                prop_assert_eq!(parsed.to_bytes(), bytes);
+            }
         }
     }
 }
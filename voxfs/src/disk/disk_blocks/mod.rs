@@ -1,7 +1,12 @@
+mod history_record;
 mod inode;
 mod super_block;
 mod tag_block;
 
-pub use inode::{Extent, INode, INodeFlags, IndirectINode};
-pub use super_block::SuperBlock;
-pub use tag_block::{IndirectTagBlock, TagBlock, TagFlags};
+pub use history_record::{HistoryOperation, HistoryRecord};
+pub use inode::{Extent, FileType, INode, INodeFlags, IndirectINode};
+pub use super_block::{
+    FilesystemState, NamePolicy, SuperBlock, BACKUP_SUPER_BLOCK_ADDRESS, CONTENT_HASH_SIZE,
+    DEFAULT_BYTES_PER_INODE, DEFAULT_INODES_PER_TAG, MAX_LABEL_LENGTH,
+};
+pub use tag_block::{IndirectTagBlock, TagBlock, TagFlags, MAX_TAG_COLOR};
@@ -1,10 +1,89 @@
 use super::{INode, TagBlock};
+use crate::manager::{disk_to_timestamp, legacy_time_to_disk, timestamp_to_disk, Timestamp};
 use crate::{ByteSerializable, Checksum};
+use alloc::string::String;
 use byteorder::{ByteOrder, LittleEndian};
 
-const CURRENT_VERSION: u8 = 0x00;
+/// Version 1 changed the encoding of times and version 2 gave tags their own colour and icon field, see
+/// `Disk::migrate`.
+const CURRENT_VERSION: u8 = 0x02;
 const MAGIC: u32 = 0xa1df5000;
-const BYTES_PER_INODE: u64 = 2048;
+/// The bytes of the disk for each tag or inode slot when formatting.
+pub const DEFAULT_BYTES_PER_INODE: u64 = 2048;
+/// How many of the slots are inodes for each tag when formatting.
+pub const DEFAULT_INODES_PER_TAG: u64 = 3;
+pub const MAX_LABEL_LENGTH: usize = 16;
+/// The size of the hash kept for each file when the filesystem stores content hashes.
+pub const CONTENT_HASH_SIZE: u64 = 32;
+
+/// The address of the copy of the super block, at the end of the first 512 bytes so it is inside the super block's
+/// block for every block size.
+pub const BACKUP_SUPER_BLOCK_ADDRESS: u64 = 384;
+/// The commit sequence is stored in 7 bytes.
+const MAX_COMMIT_SEQUENCE: u64 = (1 << 56) - 1;
+
+/// Feature bit set when the filesystem has a content hash region.
+const FEATURE_CONTENT_HASHES: u8 = 0b0000_0001;
+
+/// Whether the filesystem was closed after its last modification.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FilesystemState {
+    Clean,
+    Dirty,
+}
+
+impl FilesystemState {
+    fn to_byte(self) -> u8 {
+        return match self {
+            FilesystemState::Clean => 0,
+            FilesystemState::Dirty => 1,
+        };
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        return match byte {
+            0 => Some(FilesystemState::Clean),
+            1 => Some(FilesystemState::Dirty),
+            _ => None,
+        };
+    }
+}
+
+/// What happens when a file is created with the name of an existing file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NamePolicy {
+    /// Fail with `FileExistsWithName`.
+    Reject,
+    /// Create the file anyway, name lookups return the first file with the name.
+    Allow,
+    /// Append " (n)" to the name, before any extension, with the smallest n that makes it unique.
+    AutoSuffix,
+}
+
+impl NamePolicy {
+    fn to_byte(self) -> u8 {
+        return match self {
+            NamePolicy::Reject => 0,
+            NamePolicy::Allow => 1,
+            NamePolicy::AutoSuffix => 2,
+        };
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        return match byte {
+            0 => Some(NamePolicy::Reject),
+            1 => Some(NamePolicy::Allow),
+            2 => Some(NamePolicy::AutoSuffix),
+            _ => None,
+        };
+    }
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        return NamePolicy::Reject;
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SuperBlock {
@@ -28,25 +107,67 @@ pub struct SuperBlock {
     data_start_address: u64,
 
     checksum: u8,
-    reserved: [u8; 3],
+    /// The number of blocks reserved for a boot area directly after the super block's block.
+    boot_area_blocks: u16,
+    /// Set to dirty when the filesystem is first modified and back to clean when it is synced.
+    state: FilesystemState,
+
+    /// The time the filesystem was last opened for writing, as stored by timestamp_to_disk.
+    last_mount_time: u64,
+    /// The number of times the filesystem has been opened for writing.
+    mount_count: u32,
+
+    /// The address of the copy of the tag table, followed by the copy of the inode table. Zero if there is no mirror.
+    mirror_start_address: u64,
+
+    /// A unique identifier set when the filesystem is created.
+    uuid: [u8; 16],
+    /// A name for the filesystem, UTF-8 padded with null bytes.
+    label: [u8; MAX_LABEL_LENGTH],
+    /// How duplicate file names are handled.
+    name_policy: NamePolicy,
+    /// The number of blocks in the history region, which sits directly before the data blocks. Zero if there is no history.
+    history_blocks: u16,
+    /// Bits for the optional regions the filesystem was formatted with, see the `FEATURE_` constants.
+    features: u8,
+    /// The most characters a new file or tag name can have, 0 for only the limits of the records themselves.
+    max_name_length: u8,
+    /// Incremented every time the super block is written, to tell which of it and its backup is newer.
+    commit_sequence: u64,
 }
 
 impl SuperBlock {
     // Disk size should be all the available disk space for writing data. This should factor exclude the size of the superblock, maps and padding.
     // This method does not set values for addresses.
     pub fn new(block_size: u64, disk_size: u64) -> Self {
+        return Self::with_ratios(
+            block_size,
+            disk_size,
+            DEFAULT_BYTES_PER_INODE,
+            DEFAULT_INODES_PER_TAG,
+        );
+    }
+
+    /// Divides the disk into a tag or inode slot for every `bytes_per_inode` bytes, with `inodes_per_tag` inodes
+    /// for each tag. The block count is 0 if the tables would fill the disk.
+    pub fn with_ratios(
+        block_size: u64,
+        disk_size: u64,
+        bytes_per_inode: u64,
+        inodes_per_tag: u64,
+    ) -> Self {
         let total_block_count = disk_size / block_size;
-        let total_inodes = (total_block_count * block_size) / BYTES_PER_INODE;
+        let total_inodes = (total_block_count * block_size) / bytes_per_inode;
 
-        let mut inodes = (total_inodes / 4) * 3;
-        let mut tags = total_inodes / 4;
+        let mut tags = total_inodes / (inodes_per_tag + 1);
+        let mut inodes = tags * inodes_per_tag;
 
         inodes += (block_size - ((inodes * INode::size()) % block_size)) / INode::size(); // Add enough inodes to fill the blocks.
         tags += (block_size - ((tags * TagBlock::size()) % block_size)) / TagBlock::size(); // Add enough tags to fill the blocks.
 
         let available_data_blocks = total_block_count
-            - ((inodes * INode::size()) / block_size)
-            - ((tags * TagBlock::size()) / block_size);
+            .saturating_sub((inodes * INode::size()) / block_size)
+            .saturating_sub((tags * TagBlock::size()) / block_size);
 
         let mut new = Self {
             magic: MAGIC | (CURRENT_VERSION as u32),
@@ -58,7 +179,18 @@ impl SuperBlock {
             inode_start_address: 0,
             data_start_address: 0,
             checksum: 0,
-            reserved: [0u8; 3],
+            boot_area_blocks: 0,
+            state: FilesystemState::Clean,
+            last_mount_time: 0,
+            mount_count: 0,
+            mirror_start_address: 0,
+            uuid: [0u8; 16],
+            label: [0u8; MAX_LABEL_LENGTH],
+            name_policy: NamePolicy::Reject,
+            history_blocks: 0,
+            features: 0,
+            max_name_length: 0,
+            commit_sequence: 0,
         };
 
         new.set_checksum();
@@ -109,6 +241,243 @@ impl SuperBlock {
         return self.data_start_address;
     }
 
+    /// The number of blocks reserved for the boot area.
+    pub fn boot_area_blocks(&self) -> u16 {
+        return self.boot_area_blocks;
+    }
+
+    pub fn set_boot_area_blocks(&mut self, boot_area_blocks: u16) {
+        self.boot_area_blocks = boot_area_blocks;
+        self.set_checksum();
+    }
+
+    pub fn state(&self) -> FilesystemState {
+        return self.state;
+    }
+
+    pub fn set_state(&mut self, state: FilesystemState) {
+        self.state = state;
+        self.set_checksum();
+    }
+
+    /// The time the filesystem was last opened for writing.
+    pub fn last_mount_time(&self) -> Timestamp {
+        if self.has_legacy_times() {
+            return disk_to_timestamp(legacy_time_to_disk(self.last_mount_time));
+        }
+
+        return disk_to_timestamp(self.last_mount_time);
+    }
+
+    /// The number of times the filesystem has been opened for writing.
+    pub fn mount_count(&self) -> u32 {
+        return self.mount_count;
+    }
+
+    /// Records that the filesystem has been opened for writing at a time.
+    pub fn record_mount(&mut self, time: Timestamp) {
+        self.last_mount_time = timestamp_to_disk(time);
+        self.mount_count = self.mount_count.wrapping_add(1);
+        self.set_checksum();
+    }
+
+    /// The number of times the super block has been written since the filesystem was created.
+    pub fn commit_sequence(&self) -> u64 {
+        return self.commit_sequence;
+    }
+
+    /// Advances the commit sequence before the super block is written, wrapping once it no longer fits in its
+    /// 7 bytes.
+    pub fn record_commit(&mut self) {
+        self.commit_sequence = (self.commit_sequence + 1) & MAX_COMMIT_SEQUENCE;
+        self.set_checksum();
+    }
+
+    /// The address of the copy of the tag table, if the metadata is mirrored.
+    pub fn mirror_tag_start_address(&self) -> Option<u64> {
+        return match self.mirror_start_address {
+            0 => None,
+            address => Some(address),
+        };
+    }
+
+    /// The address of the copy of the inode table, if the metadata is mirrored.
+    pub fn mirror_inode_start_address(&self) -> Option<u64> {
+        return match self.mirror_start_address {
+            0 => None,
+            address => Some(address + self.blocks_for_tags() * self.block_size),
+        };
+    }
+
+    /// Set the address at which the copies of the tag and inode tables should be stored.
+    pub fn set_mirror_start_address(&mut self, mirror_start_address: u64) {
+        self.mirror_start_address = mirror_start_address;
+        self.set_checksum();
+    }
+
+    /// Takes the blocks needed for a copy of the tag and inode tables from the data blocks.
+    pub fn reserve_metadata_mirror(&mut self) {
+        let mirror_blocks = self.blocks_for_tags() + self.blocks_for_inodes();
+
+        self.block_count = self.block_count.saturating_sub(mirror_blocks);
+        self.set_checksum();
+    }
+
+    /// Takes the blocks for a history region from the data blocks, the data must then start after the region.
+    pub fn reserve_history(&mut self, blocks: u16) {
+        self.history_blocks = blocks;
+        self.block_count = self.block_count.saturating_sub(blocks as u64);
+        self.set_checksum();
+    }
+
+    pub fn history_blocks(&self) -> u16 {
+        return self.history_blocks;
+    }
+
+    /// The address of the history region, if the filesystem has one.
+    pub fn history_start_address(&self) -> Option<u64> {
+        return match self.history_blocks {
+            0 => None,
+            blocks => Some(self.data_start_address - blocks as u64 * self.block_size),
+        };
+    }
+
+    /// Takes the blocks for a content hash for every inode from the data blocks. The region sits directly before
+    /// the history, or the data blocks if there is no history.
+    pub fn reserve_content_hashes(&mut self) {
+        self.features |= FEATURE_CONTENT_HASHES;
+        self.block_count = self.block_count.saturating_sub(self.content_hash_blocks());
+        self.set_checksum();
+    }
+
+    /// Whether the filesystem keeps a content hash for each file.
+    pub fn has_content_hashes(&self) -> bool {
+        return self.features & FEATURE_CONTENT_HASHES != 0;
+    }
+
+    /// The number of blocks in the content hash region, zero if there is none.
+    pub fn content_hash_blocks(&self) -> u64 {
+        if !self.has_content_hashes() || self.block_size == 0 {
+            return 0;
+        }
+
+        return self
+            .inode_count
+            .saturating_mul(CONTENT_HASH_SIZE)
+            .div_ceil(self.block_size);
+    }
+
+    /// The address of the content hash region, if the filesystem has one. The hash of inode n is at
+    /// `n * CONTENT_HASH_SIZE` bytes into it.
+    pub fn content_hash_start_address(&self) -> Option<u64> {
+        if !self.has_content_hashes() {
+            return None;
+        }
+
+        let blocks = self.content_hash_blocks() + self.history_blocks as u64;
+
+        return Some(self.data_start_address - blocks * self.block_size);
+    }
+
+    /// The version of the filesystem format, stored in the low byte of the magic.
+    pub fn version(&self) -> u8 {
+        return (self.magic & 0xff) as u8;
+    }
+
+    /// Whether the filesystem was formatted with an older version, its records must be converted before they are
+    /// written.
+    pub fn needs_migration(&self) -> bool {
+        return self.version() < CURRENT_VERSION;
+    }
+
+    /// Whether the times were stored as nanoseconds since 1970, before version 1.
+    pub(crate) fn has_legacy_times(&self) -> bool {
+        return self.version() < 1;
+    }
+
+    /// Whether tags kept their colour and icon in their last member slot, before version 2.
+    pub(crate) fn has_legacy_tag_appearance(&self) -> bool {
+        return self.version() < 2;
+    }
+
+    /// Marks the filesystem as the current version once its records have been converted.
+    pub(crate) fn upgrade_version(&mut self) {
+        if self.has_legacy_times() {
+            self.last_mount_time = legacy_time_to_disk(self.last_mount_time);
+        }
+
+        self.magic = MAGIC | (CURRENT_VERSION as u32);
+        self.set_checksum();
+    }
+
+    pub fn uuid(&self) -> [u8; 16] {
+        return self.uuid;
+    }
+
+    pub fn set_uuid(&mut self, uuid: [u8; 16]) {
+        self.uuid = uuid;
+        self.set_checksum();
+    }
+
+    /// The filesystem's label, empty if it has none.
+    pub fn label(&self) -> String {
+        let length = self
+            .label
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(MAX_LABEL_LENGTH);
+
+        return String::from_utf8_lossy(&self.label[..length]).into_owned();
+    }
+
+    /// Sets the label, returning false if it is longer than `MAX_LABEL_LENGTH` bytes or contains a null byte.
+    pub fn set_label(&mut self, label: &str) -> bool {
+        if label.len() > MAX_LABEL_LENGTH || label.contains('\0') {
+            return false;
+        }
+
+        self.label = [0u8; MAX_LABEL_LENGTH];
+        self.label[..label.len()].copy_from_slice(label.as_bytes());
+        self.set_checksum();
+
+        return true;
+    }
+
+    pub fn name_policy(&self) -> NamePolicy {
+        return self.name_policy;
+    }
+
+    pub fn set_name_policy(&mut self, name_policy: NamePolicy) {
+        self.name_policy = name_policy;
+        self.set_checksum();
+    }
+
+    /// The most characters a new file or tag name can have, if the disk was given a limit shorter than the
+    /// `INode::MAX_NAME_LENGTH` and `TagBlock::MAX_NAME_LENGTH` the records can store.
+    pub fn max_name_length(&self) -> Option<usize> {
+        return match self.max_name_length {
+            0 => None,
+            length => Some(length as usize),
+        };
+    }
+
+    /// Sets the limit on new names, returning false if it is 0 or longer than `TagBlock::MAX_NAME_LENGTH`.
+    pub fn set_max_name_length(&mut self, max_name_length: Option<usize>) -> bool {
+        self.max_name_length = match max_name_length {
+            None => 0,
+            Some(length) if length > 0 && length <= TagBlock::MAX_NAME_LENGTH => length as u8,
+            Some(_) => return false,
+        };
+        self.set_checksum();
+
+        return true;
+    }
+
+    /// The address of the tag bitmap, which follows the super block's block and the boot area.
+    pub fn bitmap_start_address(&self) -> u64 {
+        return self.block_size * (1 + self.boot_area_blocks as u64);
+    }
+
     /// Set the address at which tags should be stored.
     pub fn set_tag_start_address(&mut self, tag_start_address: u64) {
         self.tag_start_address = tag_start_address;
@@ -127,19 +496,114 @@ impl SuperBlock {
         self.set_checksum();
     }
 
-    // Dead code since nothing should require this but for consistency it is provided.
-    #[allow(dead_code)]
+    /// Shrinks the data region to the given number of blocks, used to open a truncated image.
+    pub(crate) fn clamp_block_count(&mut self, block_count: u64) {
+        self.block_count = core::cmp::min(self.block_count, block_count);
+        self.set_checksum();
+    }
+
     /// The size of the superblock.
     pub fn size() -> u64 {
-        return 64; // 64 bytes
+        return 128; // 128 bytes
+    }
+
+    /// Checks that the magic is correct and that the layout described fits within a disk of the given size.
+    /// A corrupted super block can still have a valid checksum so this should be checked before using the layout.
+    pub fn is_layout_valid(&self, disk_size: u64) -> bool {
+        if self.magic & 0xffff_ff00 != MAGIC {
+            return false;
+        }
+
+        if self.block_size == 0 || self.block_size % 64 != 0 || self.block_size > disk_size {
+            return false;
+        }
+
+        let bitmap_start_address = match self
+            .block_size
+            .checked_mul(1 + self.boot_area_blocks as u64)
+        {
+            Some(a) => a,
+            None => return false,
+        };
+
+        if self.tag_start_address < bitmap_start_address
+            || self.inode_start_address < self.tag_start_address
+            || self.data_start_address < self.inode_start_address
+            || self.data_start_address > disk_size
+        {
+            return false;
+        }
+
+        // The tables must fit between their start addresses
+        let tags_size = match self.tag_count.checked_mul(TagBlock::size()) {
+            Some(s) => s,
+            None => return false,
+        };
+
+        let inodes_size = match self.inode_count.checked_mul(INode::size()) {
+            Some(s) => s,
+            None => return false,
+        };
+
+        // The mirror, if there is one, sits between the inode table and the data blocks
+        let inodes_end = match self.mirror_start_address {
+            0 => self.data_start_address,
+            address => address,
+        };
+
+        if inodes_end < self.inode_start_address
+            || inodes_end > self.data_start_address
+            || tags_size > self.inode_start_address - self.tag_start_address
+            || inodes_size > inodes_end - self.inode_start_address
+        {
+            return false;
+        }
+
+        // The history, if there is one, sits directly before the data blocks
+        let history_start = match (self.history_blocks as u64)
+            .checked_mul(self.block_size)
+            .and_then(|size| self.data_start_address.checked_sub(size))
+        {
+            Some(a) => a,
+            None => return false,
+        };
+
+        // The content hashes, if there are any, sit directly before the history
+        let metadata_end = match self
+            .content_hash_blocks()
+            .checked_mul(self.block_size)
+            .and_then(|size| history_start.checked_sub(size))
+        {
+            Some(a) => a,
+            None => return false,
+        };
+
+        if self.mirror_start_address != 0 {
+            let mirror_size = match tags_size.checked_add(inodes_size) {
+                Some(s) => s,
+                None => return false,
+            };
+
+            if self.mirror_start_address > metadata_end
+                || mirror_size > metadata_end - self.mirror_start_address
+            {
+                return false;
+            }
+        } else if metadata_end < self.inode_start_address
+            || inodes_size > metadata_end - self.inode_start_address
+        {
+            return false;
+        }
+
+        return self.block_count <= disk_size / self.block_size;
     }
 }
 
 impl ByteSerializable for SuperBlock {
-    type BytesArrayType = [u8; 64];
+    type BytesArrayType = [u8; 128];
 
     fn to_bytes(&self) -> Self::BytesArrayType {
-        let mut bytes = [0u8; 64];
+        let mut bytes = [0u8; 128];
         let mut offset = 0;
 
         LittleEndian::write_u32(&mut bytes[offset..], self.magic);
@@ -163,9 +627,41 @@ impl ByteSerializable for SuperBlock {
         offset += 8;
 
         bytes[offset] = self.checksum;
-        //offset += 1; // Increment if in further revisions data is added beyond this point
+        offset += 1;
+
+        LittleEndian::write_u16(&mut bytes[offset..], self.boot_area_blocks);
+        offset += 2;
+
+        bytes[offset] = self.state.to_byte();
+        offset += 1;
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.last_mount_time);
+        offset += 8;
+        LittleEndian::write_u32(&mut bytes[offset..], self.mount_count);
+        offset += 4;
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.mirror_start_address);
+        offset += 8;
+
+        bytes[offset..offset + 16].copy_from_slice(&self.uuid);
+        offset += 16;
+        bytes[offset..offset + MAX_LABEL_LENGTH].copy_from_slice(&self.label);
+        offset += MAX_LABEL_LENGTH;
+
+        bytes[offset] = self.name_policy.to_byte();
+        offset += 1;
 
-        // bytes 62, 63, 64 are reserved
+        LittleEndian::write_u16(&mut bytes[offset..], self.history_blocks);
+        offset += 2;
+
+        bytes[offset] = self.features;
+        offset += 1;
+
+        bytes[offset] = self.max_name_length;
+        offset += 1;
+
+        LittleEndian::write_uint(&mut bytes[offset..], self.commit_sequence, 7);
+        //offset += 7; // Increment if in further revisions data is added beyond this point
 
         return bytes;
     }
@@ -174,7 +670,7 @@ impl ByteSerializable for SuperBlock {
     where
         Self: core::marker::Sized,
     {
-        if bytes.len() < 40 {
+        if bytes.len() < Self::size() as usize {
             return None;
         }
 
@@ -193,6 +689,18 @@ impl ByteSerializable for SuperBlock {
         let data_start_address: u64;
 
         let checksum: u8;
+        let boot_area_blocks: u16;
+        let state: FilesystemState;
+        let last_mount_time: u64;
+        let mount_count: u32;
+        let mirror_start_address: u64;
+        let mut uuid = [0u8; 16];
+        let mut label = [0u8; MAX_LABEL_LENGTH];
+        let name_policy: NamePolicy;
+        let history_blocks: u16;
+        let features: u8;
+        let max_name_length: u8;
+        let commit_sequence: u64;
 
         magic = LittleEndian::read_u32(&bytes[offset..]);
         offset += 4;
@@ -215,7 +723,45 @@ impl ByteSerializable for SuperBlock {
         offset += 8;
 
         checksum = bytes[offset];
-        //offset += 1;  // Increment if in further revisions data is added beyond this point
+        offset += 1;
+
+        boot_area_blocks = LittleEndian::read_u16(&bytes[offset..]);
+        offset += 2;
+
+        state = FilesystemState::from_byte(bytes[offset])?;
+        offset += 1;
+
+        last_mount_time = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+        mount_count = LittleEndian::read_u32(&bytes[offset..]);
+        offset += 4;
+
+        mirror_start_address = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+
+        uuid.copy_from_slice(&bytes[offset..offset + 16]);
+        offset += 16;
+        label.copy_from_slice(&bytes[offset..offset + MAX_LABEL_LENGTH]);
+        offset += MAX_LABEL_LENGTH;
+
+        name_policy = NamePolicy::from_byte(bytes[offset])?;
+        offset += 1;
+
+        history_blocks = LittleEndian::read_u16(&bytes[offset..]);
+        offset += 2;
+
+        // A feature this version doesn't know about could change the layout
+        features = bytes[offset];
+        if features & !FEATURE_CONTENT_HASHES != 0 {
+            return None;
+        }
+        offset += 1;
+
+        max_name_length = bytes[offset];
+        offset += 1;
+
+        commit_sequence = LittleEndian::read_uint(&bytes[offset..], 7);
+        //offset += 7;  // Increment if in further revisions data is added beyond this point
 
         let res = Self {
             magic,
@@ -227,7 +773,18 @@ impl ByteSerializable for SuperBlock {
             inode_start_address,
             data_start_address,
             checksum,
-            reserved: [0u8; 3],
+            boot_area_blocks,
+            state,
+            last_mount_time,
+            mount_count,
+            mirror_start_address,
+            uuid,
+            label,
+            name_policy,
+            history_blocks,
+            features,
+            max_name_length,
+            commit_sequence,
         };
 
         if res.perform_checksum() {
@@ -272,8 +829,19 @@ mod tests {
                 tag_start_address: 0,
                 inode_start_address: 0,
                 data_start_address: 0,
-                checksum: 69,
-                reserved: [0u8; 3]
+                checksum: 67,
+                boot_area_blocks: 0,
+                state: FilesystemState::Clean,
+                last_mount_time: 0,
+                mount_count: 0,
+                mirror_start_address: 0,
+                uuid: [0u8; 16],
+                label: [0u8; MAX_LABEL_LENGTH],
+                name_policy: NamePolicy::Reject,
+                history_blocks: 0,
+                features: 0,
+                max_name_length: 0,
+                commit_sequence: 0,
             }
         );
 
@@ -288,10 +856,10 @@ mod tests {
         let block = SuperBlock::new(block_size, disk_size);
 
         let bytes = {
-            let mut res = [0u8; 64];
+            let mut res = [0u8; 128];
 
             // Magic
-            res[0] = 0x00;
+            res[0] = 0x02;
             res[1] = 0x50;
             res[2] = 0xdf;
             res[3] = 0xa1;
@@ -310,7 +878,7 @@ mod tests {
             // Block count
             res[28] = 218;
 
-            res[60] = 69;
+            res[60] = 67;
 
             res
         };
@@ -319,40 +887,307 @@ mod tests {
     }
 
     #[test]
-    fn test_from_bytes() {
+    fn test_uuid_and_label() {
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, 4096 * 250);
+
+        block.set_uuid([7u8; 16]);
+        assert!(block.set_label("backups"));
+        assert!(!block.set_label("a label that is too long"));
+        assert!(!block.set_label("null\0"));
+
+        let bytes = block.to_bytes();
+        assert_eq!(&bytes[84..100], &[7u8; 16]);
+        assert_eq!(&bytes[100..107], b"backups");
+
+        let read = SuperBlock::from_bytes(&bytes).unwrap();
+        assert_eq!(read.uuid(), [7u8; 16]);
+        assert_eq!(read.label(), "backups");
+        assert_eq!(read.version(), CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_name_policy() {
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, 4096 * 250);
+
+        block.set_name_policy(NamePolicy::AutoSuffix);
+
+        let mut bytes = block.to_bytes();
+        assert_eq!(bytes[116], 2);
+        assert_eq!(
+            SuperBlock::from_bytes(&bytes).unwrap().name_policy(),
+            NamePolicy::AutoSuffix
+        );
+
+        // An unknown policy is rejected even when the checksum matches
+        bytes[116] = 3;
+        bytes[60] = bytes[60].wrapping_sub(1);
+        assert_eq!(SuperBlock::from_bytes(&bytes), None);
+    }
+
+    #[test]
+    fn test_max_name_length() {
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, 4096 * 250);
+        assert_eq!(block.max_name_length(), None);
+
+        assert!(block.set_max_name_length(Some(64)));
+        assert_eq!(block.to_bytes()[120], 64);
+        assert_eq!(
+            SuperBlock::from_bytes(&block.to_bytes())
+                .unwrap()
+                .max_name_length(),
+            Some(64)
+        );
+
+        assert!(!block.set_max_name_length(Some(0)));
+        assert!(!block.set_max_name_length(Some(TagBlock::MAX_NAME_LENGTH + 1)));
+        assert_eq!(block.max_name_length(), Some(64));
+
+        assert!(block.set_max_name_length(None));
+        assert_eq!(block.to_bytes()[120], 0);
+    }
+
+    #[test]
+    fn test_from_bytes_short() {
+        let block = SuperBlock::new(DEFAULT_BLOCK_SIZE, 4096 * 250);
+
+        assert_eq!(SuperBlock::from_bytes(&block.to_bytes()[..50]), None);
+    }
+
+    #[test]
+    fn test_layout_valid() {
         let disk_size = 4096 * 250;
-        let block_size = DEFAULT_BLOCK_SIZE as u64;
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, disk_size);
+        block.set_tag_start_address(4096 * 2);
+        block.set_inode_start_address(4096 * 2 + block.blocks_for_tags() * 4096);
+        block
+            .set_data_start_address(block.inode_start_address() + block.blocks_for_inodes() * 4096);
+
+        assert!(block.is_layout_valid(disk_size));
+        assert!(!block.is_layout_valid(4096 * 10));
+
+        // A zeroed super block has a valid checksum but not a valid layout
+        let zeroed = SuperBlock::from_bytes(&[0u8; 128]).unwrap();
+        assert!(!zeroed.is_layout_valid(disk_size));
+    }
 
-        let block = SuperBlock::new(block_size, disk_size);
+    #[test]
+    fn test_mirror_layout_valid() {
+        let disk_size = 4096 * 250;
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, disk_size);
+        let block_count = block.block_count();
+        let table_blocks = block.blocks_for_tags() + block.blocks_for_inodes();
 
-        let bytes = {
-            let mut res = [0u8; 64];
+        block.reserve_metadata_mirror();
+        assert_eq!(block.block_count(), block_count - table_blocks);
 
-            // Magic
-            res[0] = 0x00;
-            res[1] = 0x50;
-            res[2] = 0xdf;
-            res[3] = 0xa1;
+        block.set_tag_start_address(4096 * 2);
+        block.set_inode_start_address(4096 * 2 + block.blocks_for_tags() * 4096);
+        block.set_mirror_start_address(
+            block.inode_start_address() + block.blocks_for_inodes() * 4096,
+        );
+        block.set_data_start_address(
+            block.mirror_tag_start_address().unwrap() + table_blocks * 4096,
+        );
 
-            // Block size
-            res[4] = 0x00;
-            res[5] = 0x10;
+        assert_eq!(
+            block.mirror_inode_start_address(),
+            Some(block.mirror_tag_start_address().unwrap() + block.blocks_for_tags() * 4096)
+        );
+        assert!(block.is_layout_valid(disk_size));
 
-            // Tag count
-            res[12] = 128;
+        // The mirror must not overlap the data blocks
+        block.set_data_start_address(block.data_start_address() - 4096);
+        assert!(!block.is_layout_valid(disk_size));
+    }
 
-            // Inode count
-            res[20] = 0x80;
-            res[21] = 0x01;
+    #[test]
+    fn test_history_layout_valid() {
+        let disk_size = 4096 * 250;
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, disk_size);
+        let block_count = block.block_count();
 
-            // Block count
-            res[28] = 218;
+        block.reserve_history(4);
+        assert_eq!(block.block_count(), block_count - 4);
 
-            res[60] = 69;
+        block.set_tag_start_address(4096 * 2);
+        block.set_inode_start_address(4096 * 2 + block.blocks_for_tags() * 4096);
+        let history_start = block.inode_start_address() + block.blocks_for_inodes() * 4096;
+        block.set_data_start_address(history_start + 4 * 4096);
 
-            res
-        };
+        assert_eq!(block.history_start_address(), Some(history_start));
+        assert!(block.is_layout_valid(disk_size));
+
+        let parsed = SuperBlock::from_bytes(&block.to_bytes()).unwrap();
+        assert_eq!(parsed.history_blocks(), 4);
+
+        // The history must not overlap the inode table
+        block.set_data_start_address(block.data_start_address() - 4096);
+        assert!(!block.is_layout_valid(disk_size));
+    }
+
+    #[test]
+    fn test_content_hash_layout_valid() {
+        let disk_size = 4096 * 250;
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, disk_size);
+        let block_count = block.block_count();
+
+        block.reserve_history(2);
+        block.reserve_content_hashes();
+        // 384 hashes of 32 bytes fill 3 blocks
+        assert_eq!(block.content_hash_blocks(), 3);
+        assert_eq!(block.block_count(), block_count - 5);
+
+        block.set_tag_start_address(4096 * 2);
+        block.set_inode_start_address(4096 * 2 + block.blocks_for_tags() * 4096);
+        let hash_start = block.inode_start_address() + block.blocks_for_inodes() * 4096;
+        block.set_data_start_address(hash_start + 5 * 4096);
+
+        assert_eq!(block.content_hash_start_address(), Some(hash_start));
+        assert_eq!(block.history_start_address(), Some(hash_start + 3 * 4096));
+        assert!(block.is_layout_valid(disk_size));
+
+        let mut bytes = block.to_bytes();
+        assert_eq!(bytes[119], FEATURE_CONTENT_HASHES);
+        assert!(SuperBlock::from_bytes(&bytes).unwrap().has_content_hashes());
+
+        // An unknown feature is rejected even when the checksum matches
+        bytes[119] |= 0b1000_0000;
+        bytes[60] = bytes[60].wrapping_sub(0b1000_0000);
+        assert_eq!(SuperBlock::from_bytes(&bytes), None);
+
+        // The hashes must not overlap the inode table
+        block.set_data_start_address(block.data_start_address() - 4096);
+        assert!(!block.is_layout_valid(disk_size));
+    }
+
+    #[test]
+    fn test_commit_sequence() {
+        let mut block = SuperBlock::new(DEFAULT_BLOCK_SIZE, 4096 * 250);
+        assert_eq!(block.commit_sequence(), 0);
+
+        block.record_commit();
+        block.record_commit();
+        assert_eq!(block.commit_sequence(), 2);
+        assert_eq!(
+            SuperBlock::from_bytes(&block.to_bytes())
+                .unwrap()
+                .commit_sequence(),
+            2
+        );
+
+        // The sequence wraps rather than overflowing its 7 bytes
+        block.commit_sequence = MAX_COMMIT_SEQUENCE;
+        block.record_commit();
+        assert_eq!(block.commit_sequence(), 0);
+        assert!(block.perform_checksum());
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn arb_super_block() -> impl Strategy<Value = SuperBlock> {
+            return (
+                any::<u8>(),
+                any::<u64>(),
+                any::<(u64, u64, u64)>(),
+                any::<(u64, u64, u64)>(),
+                any::<u16>(),
+                (any::<bool>(), any::<u64>(), any::<u32>(), any::<u64>()),
+                (
+                    any::<[u8; 16]>(),
+                    any::<[u8; MAX_LABEL_LENGTH]>(),
+                    0..3u8,
+                    any::<u16>(),
+                    any::<bool>(),
+                    any::<u8>(),
+                    0..=MAX_COMMIT_SEQUENCE,
+                ),
+            )
+                .prop_map(
+                    |(
+                        version,
+                        block_size,
+                        (tag_count, inode_count, block_count),
+                        (tag_start, inode_start, data_start),
+                        boot_area_blocks,
+                        (dirty, last_mount_time, mount_count, mirror_start_address),
+                        (
+                            uuid,
+                            label,
+                            name_policy,
+                            history_blocks,
+                            content_hashes,
+                            max_name_length,
+                            commit_sequence,
+                        ),
+                    )| {
+                        let mut block = SuperBlock {
+                            magic: MAGIC | (version as u32),
+                            block_size,
+                            tag_count,
+                            inode_count,
+                            block_count,
+                            tag_start_address: tag_start,
+                            inode_start_address: inode_start,
+                            data_start_address: data_start,
+                            checksum: 0,
+                            boot_area_blocks,
+                            state: if dirty {
+                                FilesystemState::Dirty
+                            } else {
+                                FilesystemState::Clean
+                            },
+                            last_mount_time,
+                            mount_count,
+                            mirror_start_address,
+                            uuid,
+                            label,
+                            name_policy: NamePolicy::from_byte(name_policy).unwrap(),
+                            history_blocks,
+                            features: if content_hashes {
+                                FEATURE_CONTENT_HASHES
+                            } else {
+                                0
+                            },
+                            max_name_length,
+                            commit_sequence,
+                        };
+
+                        block.set_checksum();
+
+                        block
+                    },
+                );
+        }
+
+        proptest! {
+            #[test]
+            fn super_block_round_trip(block in arb_super_block()) {
+                prop_assert!(block.perform_checksum());
+
+                let bytes = block.to_bytes();
+                let parsed = SuperBlock::from_bytes(&bytes).unwrap();
 
-        assert_eq!(SuperBlock::from_bytes(&bytes).unwrap(), block);
+                prop_assert_eq!(&parsed, &block);
+                prop_assert_eq!(parsed.to_bytes().to_vec(), bytes.to_vec());
+            }
+
+            #[test]
+            fn super_block_corruption_detected(block in arb_super_block(), position in 0..128usize, change in 1..=255u8) {
+                let mut bytes = block.to_bytes();
+                bytes[position] = bytes[position].wrapping_add(change);
+
+                prop_assert_eq!(SuperBlock::from_bytes(&bytes), None);
+            }
+
+            #[test]
+            fn new_super_block_checksum(block_size in (8..=256u64).prop_map(|n| n * 64), blocks in 16..65536u64) {
+                let block = SuperBlock::new(block_size, block_size * blocks);
+
+                prop_assert!(block.perform_checksum());
+                prop_assert_eq!(SuperBlock::from_bytes(&block.to_bytes()).unwrap(), block);
+            }
+        }
     }
 }
@@ -1,8 +1,15 @@
+use crate::checksum_trait::UnverifiedRecord;
+use crate::manager::{disk_to_timestamp, legacy_time_to_disk, timestamp_to_disk, Timestamp};
 use crate::{ByteSerializable, Checksum};
 use alloc::string::String;
 use alloc::vec::Vec;
 use byteorder::{ByteOrder, LittleEndian};
-use chrono::{DateTime, Utc};
+
+// A tag's colour and icon are stored as `icon << 32 | COLOR_SET | color`, 0 has neither.
+const COLOR_SET: u64 = 1 << 24;
+const COLOR_MASK: u64 = 0xff_ffff;
+/// The largest colour a tag can have, 0xRRGGBB.
+pub const MAX_TAG_COLOR: u32 = COLOR_MASK as u32;
 
 #[derive(Clone, Copy)]
 /// Length of 256 bytes
@@ -10,12 +17,14 @@ pub struct TagBlock {
     /// index, the index of the tag block in the map.
     index: u64,
     /// The name of this tag.
-    name: [char; 132],
+    name: [char; 124],
+    /// The colour and icon, see `COLOR_SET`. Before version 2 these were the last 8 characters of the name.
+    appearance: u64,
     /// Checksum
     checksum: u8,
     /// Flags
     flags: TagFlags,
-    /// creation time, nano seconds since unix epoch
+    /// creation time, as stored by timestamp_to_disk
     creation_time: u64,
     /// A pointer to a data block that contains more pointers to files, this should be an address not an index
     indirect: u64,
@@ -29,7 +38,9 @@ pub struct TagBlock {
 pub struct TagFlags {
     read: bool,
     write: bool,
-    // bits 3-8 are reserved
+    /// An archived tag is hidden from listings but keeps its members and name.
+    archived: bool,
+    // bits 4-8 are reserved
 }
 
 // Size of 1 block
@@ -54,13 +65,13 @@ pub struct IndirectTagBlock {
 
 impl TagBlock {
     pub const MAXIMUM_LOCAL_MEMBERS: u16 = 12;
-    pub const MAX_NAME_LENGTH: usize = 132;
+    pub const MAX_NAME_LENGTH: usize = 124;
 
     pub fn new(
         index: u64,
         name_str: &str,
         flags: TagFlags,
-        creation_time: DateTime<Utc>,
+        creation_time: Timestamp,
         indirect: u64,
         number_of_pointers: u16,
         members: [u64; 12],
@@ -69,7 +80,7 @@ impl TagBlock {
             index,
             name_str,
             flags,
-            creation_time.timestamp_nanos() as u64,
+            timestamp_to_disk(creation_time),
             indirect,
             number_of_pointers,
             members,
@@ -85,19 +96,10 @@ impl TagBlock {
         number_of_pointers: u16,
         members: [u64; 12],
     ) -> Self {
-        let mut name = ['\0'; Self::MAX_NAME_LENGTH];
-
-        for (i, ch) in name_str.chars().enumerate() {
-            if i >= Self::MAX_NAME_LENGTH {
-                break;
-            }
-
-            name[i] = ch;
-        }
-
         let mut res = Self {
             index,
-            name,
+            name: Self::name_from_str(name_str),
+            appearance: 0,
             checksum: 0,
             flags,
             creation_time,
@@ -111,6 +113,21 @@ impl TagBlock {
         return res;
     }
 
+    /// The stored form of a name, truncated to `MAX_NAME_LENGTH` characters and padded with nulls.
+    fn name_from_str(name_str: &str) -> [char; Self::MAX_NAME_LENGTH] {
+        let mut name = ['\0'; Self::MAX_NAME_LENGTH];
+
+        for (i, ch) in name_str.chars().enumerate() {
+            if i >= Self::MAX_NAME_LENGTH {
+                break;
+            }
+
+            name[i] = ch;
+        }
+
+        return name;
+    }
+
     pub fn size() -> u64 {
         return 256;
     }
@@ -156,8 +173,47 @@ impl TagBlock {
         return self.members[..self.number_of_pointers as usize].contains(member);
     }
 
+    /// The colour clients should show the tag in, as 0xRRGGBB.
+    pub fn color(&self) -> Option<u32> {
+        if self.appearance & COLOR_SET == 0 {
+            return None;
+        }
+
+        return Some((self.appearance & COLOR_MASK) as u32);
+    }
+
+    /// The character, usually an emoji, clients should show next to the tag.
+    pub fn icon(&self) -> Option<char> {
+        return match core::char::from_u32((self.appearance >> 32) as u32) {
+            Some('\0') | None => None,
+            Some(c) => Some(c),
+        };
+    }
+
+    /// Sets the colour and icon, failing if the colour is larger than `MAX_TAG_COLOR`.
+    pub(crate) fn set_appearance(&mut self, color: Option<u32>, icon: Option<char>) -> bool {
+        let mut appearance = 0;
+
+        if let Some(color) = color {
+            if color > MAX_TAG_COLOR {
+                return false;
+            }
+
+            appearance |= COLOR_SET | color as u64;
+        }
+
+        if let Some(icon) = icon {
+            appearance |= (icon as u64) << 32;
+        }
+
+        self.appearance = appearance;
+        self.set_checksum();
+
+        return true;
+    }
+
     pub fn append_member(&mut self, member: u64) -> bool {
-        if self.number_of_pointers == Self::MAXIMUM_LOCAL_MEMBERS {
+        if self.number_of_pointers >= Self::MAXIMUM_LOCAL_MEMBERS {
             return false;
         }
 
@@ -237,6 +293,51 @@ impl TagBlock {
 
         return self.name[..first_null_byte].iter().collect();
     }
+
+    pub fn flags(&self) -> TagFlags {
+        return self.flags;
+    }
+
+    pub fn creation_time(&self) -> Timestamp {
+        return disk_to_timestamp(self.creation_time);
+    }
+
+    pub(crate) fn set_name(&mut self, name: &str) {
+        self.name = Self::name_from_str(name);
+        self.set_checksum();
+    }
+
+    pub(crate) fn set_flags(&mut self, flags: TagFlags) {
+        self.flags = flags;
+        self.set_checksum();
+    }
+
+    pub(crate) fn set_creation_time(&mut self, creation_time: Timestamp) {
+        self.creation_time = timestamp_to_disk(creation_time);
+        self.set_checksum();
+    }
+
+    /// Converts the creation time read from a disk older than format version 1 to the current encoding.
+    pub(crate) fn convert_legacy_time(&mut self) {
+        self.creation_time = legacy_time_to_disk(self.creation_time);
+        self.set_checksum();
+    }
+
+    /// Moves the colour and icon read from a disk older than format version 2 out of the last member slot, where
+    /// they were kept while the tag held fewer members than that. What was read as the appearance was the end of
+    /// a name longer than `MAX_NAME_LENGTH`, which is dropped.
+    pub(crate) fn convert_legacy_appearance(&mut self) {
+        let last = Self::MAXIMUM_LOCAL_MEMBERS as usize - 1;
+
+        if self.number_of_pointers < Self::MAXIMUM_LOCAL_MEMBERS {
+            self.appearance = self.members[last];
+            self.members[last] = 0;
+        } else {
+            self.appearance = 0;
+        }
+
+        self.set_checksum();
+    }
 }
 
 impl ByteSerializable for TagBlock {
@@ -254,6 +355,9 @@ impl ByteSerializable for TagBlock {
             offset += 1;
         }
 
+        LittleEndian::write_u64(&mut res[offset..], self.appearance);
+        offset += 8;
+
         res[offset] = self.checksum;
         offset += 1;
 
@@ -277,16 +381,30 @@ impl ByteSerializable for TagBlock {
         return res;
     }
 
-    fn from_bytes(bytes: &[u8]) -> Option<Self>
-    where
-        Self: core::marker::Sized,
-    {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let res = Self::from_bytes_unverified(bytes)?;
+
+        if res.perform_checksum() {
+            return Some(res);
+        } else {
+            return None;
+        }
+    }
+
+    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
+        return bytes;
+    }
+}
+
+impl UnverifiedRecord for TagBlock {
+    fn from_bytes_unverified(bytes: &[u8]) -> Option<Self> {
         if bytes.len() < 256 {
             return None;
         }
 
         let index: u64;
         let mut name = ['\0'; Self::MAX_NAME_LENGTH];
+        let appearance: u64;
         let checksum: u8;
         let flags: TagFlags;
         let creation_time: u64;
@@ -305,6 +423,9 @@ impl ByteSerializable for TagBlock {
 
         offset += Self::MAX_NAME_LENGTH;
 
+        appearance = LittleEndian::read_u64(&bytes[offset..]);
+        offset += 8;
+
         checksum = bytes[offset];
         offset += 1;
         flags = TagFlags::from_u8(bytes[offset]);
@@ -325,6 +446,7 @@ impl ByteSerializable for TagBlock {
         let res = Self {
             index,
             name,
+            appearance,
             checksum,
             flags,
             creation_time,
@@ -333,16 +455,8 @@ impl ByteSerializable for TagBlock {
             members,
         };
 
-        if !res.perform_checksum() {
-            return None;
-        }
-
         return Some(res);
     }
-
-    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
-        return bytes;
-    }
 }
 
 impl Checksum for TagBlock {
@@ -354,12 +468,14 @@ impl Checksum for TagBlock {
 
 impl core::fmt::Debug for TagBlock {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        let name_str: alloc::string::String = self.name.iter().collect();
+        let name_str: alloc::string::String =
+            self.name.iter().take_while(|c| **c != '\0').collect();
         let members = self.members.to_vec();
         return f
             .debug_struct("TagBlock")
             .field("index", &self.index)
             .field("name", &name_str)
+            .field("appearance", &self.appearance)
             .field("checksum", &self.checksum)
             .field("flags", &self.flags)
             .field("creation_time", &self.creation_time)
@@ -404,6 +520,7 @@ impl core::cmp::PartialEq for TagBlock {
 
         return self.index == other.index
             && name_comp
+            && self.appearance == other.appearance
             && self.checksum == other.checksum
             && self.flags == other.flags
             && self.creation_time == other.creation_time
@@ -415,7 +532,51 @@ impl core::cmp::PartialEq for TagBlock {
 
 impl TagFlags {
     pub fn new(read: bool, write: bool) -> Self {
-        return Self { read, write };
+        return Self {
+            read,
+            write,
+            archived: false,
+        };
+    }
+
+    /// Flags for a tag that can be read and written, the same as the default.
+    pub fn read_write() -> Self {
+        return Self::new(true, true);
+    }
+
+    /// Flags for a tag that can be read but not written.
+    pub fn read_only() -> Self {
+        return Self::new(true, false);
+    }
+
+    pub fn with_read(mut self, read: bool) -> Self {
+        self.read = read;
+
+        return self;
+    }
+
+    pub fn with_write(mut self, write: bool) -> Self {
+        self.write = write;
+
+        return self;
+    }
+
+    pub fn with_archived(mut self, archived: bool) -> Self {
+        self.archived = archived;
+
+        return self;
+    }
+
+    pub fn read(&self) -> bool {
+        return self.read;
+    }
+
+    pub fn write(&self) -> bool {
+        return self.write;
+    }
+
+    pub fn archived(&self) -> bool {
+        return self.archived;
     }
 
     pub fn as_u8(&self) -> u8 {
@@ -429,14 +590,19 @@ impl TagFlags {
             result |= 0b0100_0000;
         }
 
+        if self.archived {
+            result |= 0b0010_0000;
+        }
+
         return result;
     }
 
     pub fn from_u8(n: u8) -> Self {
         let read = (n >> 7) & 1 == 1;
         let write = (n >> 6) & 1 == 1;
+        let archived = (n >> 5) & 1 == 1;
 
-        return Self::new(read, write);
+        return Self::new(read, write).with_archived(archived);
     }
 }
 
@@ -446,6 +612,16 @@ impl Default for TagFlags {
     }
 }
 
+impl core::fmt::Display for TagFlags {
+    /// Formats the flags like file permissions, e.g. "rw" or "r-".
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let read = if self.read { 'r' } else { '-' };
+        let write = if self.write { 'w' } else { '-' };
+
+        return write!(f, "{}{}", read, write);
+    }
+}
+
 impl IndirectTagBlock {
     /// The size in bytes of the fixed length elements within an indirect Tag.
     const NON_EXPANDABLE_SIZE: u64 = 8 + 1 + 1 + 8 + 2;
@@ -591,10 +767,23 @@ impl ByteSerializable for IndirectTagBlock {
         return bytes;
     }
 
-    fn from_bytes(bytes: &[u8]) -> Option<Self>
-    where
-        Self: core::marker::Sized,
-    {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let res = Self::from_bytes_unverified(bytes)?;
+
+        if res.perform_checksum() {
+            return Some(res);
+        } else {
+            return None;
+        }
+    }
+
+    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
+        return bytes;
+    }
+}
+
+impl UnverifiedRecord for IndirectTagBlock {
+    fn from_bytes_unverified(bytes: &[u8]) -> Option<Self> {
         if bytes.len() < Self::NON_EXPANDABLE_SIZE as usize {
             return None;
         }
@@ -623,6 +812,11 @@ impl ByteSerializable for IndirectTagBlock {
         number_of_members = LittleEndian::read_u16(&bytes[offset..]);
         offset += 2;
 
+        // The members must fit in the provided bytes
+        if bytes.len() < offset + number_of_members as usize * 8 {
+            return None;
+        }
+
         for _ in 0..number_of_members {
             members.push(LittleEndian::read_u64(&bytes[offset..]));
             offset += 8;
@@ -638,15 +832,7 @@ impl ByteSerializable for IndirectTagBlock {
             maximum_members: 0,
         };
 
-        if res.perform_checksum() {
-            return Some(res);
-        } else {
-            return None;
-        }
-    }
-
-    fn generic_bytes_rep(bytes: &Self::BytesArrayType) -> &[u8] {
-        return bytes;
+        return Some(res);
     }
 }
 
@@ -704,6 +890,21 @@ mod tests {
             assert_eq!(TagFlags::from_u8(0b1100_0000), flags);
         }
 
+        #[test]
+        fn test_builders_and_display() {
+            assert_eq!(TagFlags::read_write(), TagFlags::default());
+            assert_eq!(TagFlags::read_only(), TagFlags::new(true, false));
+            assert_eq!(
+                TagFlags::read_write().with_read(false),
+                TagFlags::new(false, true)
+            );
+            assert_eq!(TagFlags::read_only().with_write(true), TagFlags::default());
+
+            assert_eq!(format!("{}", TagFlags::read_write()), "rw");
+            assert_eq!(format!("{}", TagFlags::read_only()), "r-");
+            assert_eq!(format!("{}", TagFlags::new(false, false)), "--");
+        }
+
         #[test]
         fn test_from_u8_2() {
             let flags = TagFlags::new(false, true);
@@ -715,6 +916,16 @@ mod tests {
             let flags = TagFlags::new(true, false);
             assert_eq!(TagFlags::from_u8(0b1000_0000), flags);
         }
+
+        #[test]
+        fn test_archived() {
+            let flags = TagFlags::read_write().with_archived(true);
+            assert!(flags.archived());
+            assert!(!TagFlags::default().archived());
+            assert_eq!(flags.as_u8(), 0b1110_0000);
+            assert_eq!(TagFlags::from_u8(0b1110_0000), flags);
+            assert_eq!(format!("{}", flags), "rw");
+        }
     }
 
     mod tag_block {
@@ -747,6 +958,7 @@ mod tests {
                 TagBlock {
                     index: 0,
                     name: comp_name,
+                    appearance: 0,
                     checksum: 77,
                     flags: TagFlags::new(true, false),
                     creation_time: 0xad23132ad,
@@ -758,6 +970,100 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_appearance() {
+            let mut block = TagBlock::new_custom_creation_time(
+                0,
+                "files",
+                TagFlags::default(),
+                0,
+                0,
+                0,
+                [0u64; 12],
+            );
+            assert_eq!(block.color(), None);
+            assert_eq!(block.icon(), None);
+
+            assert!(block.set_appearance(Some(0), Some('🐱')));
+            assert_eq!(block.color(), Some(0));
+            assert_eq!(block.icon(), Some('🐱'));
+            assert!(block.perform_checksum());
+
+            // Every member slot is still available
+            for i in 0..12 {
+                assert!(block.append_member(i + 1));
+            }
+
+            let read = TagBlock::from_bytes(&block.to_bytes()).unwrap();
+            assert_eq!(read.color(), Some(0));
+            assert_eq!(read.icon(), Some('🐱'));
+            assert_eq!(read.member_at(11), 12);
+
+            assert!(!block.set_appearance(Some(MAX_TAG_COLOR + 1), None));
+            assert_eq!(block.color(), Some(0));
+
+            assert!(block.set_appearance(None, None));
+            assert_eq!(block.color(), None);
+            assert_eq!(block.icon(), None);
+        }
+
+        #[test]
+        fn test_convert_legacy_appearance() {
+            let mut members = [0u64; 12];
+            members[0] = 4;
+            members[11] = (('🐱' as u64) << 32) | COLOR_SET | 0xff0000;
+            let mut block = TagBlock::new_custom_creation_time(
+                0,
+                "files",
+                TagFlags::default(),
+                0,
+                0,
+                1,
+                members,
+            );
+
+            block.convert_legacy_appearance();
+            assert_eq!(block.color(), Some(0xff0000));
+            assert_eq!(block.icon(), Some('🐱'));
+            assert_eq!(block.members()[11], 0);
+            assert!(block.perform_checksum());
+
+            // A full block had no appearance, the bytes read as one were part of its name
+            let mut block = TagBlock::new_custom_creation_time(
+                0,
+                "files",
+                TagFlags::default(),
+                0,
+                0,
+                12,
+                [7u64; 12],
+            );
+            block.appearance = 0x6161_6161_6161_6161;
+
+            block.convert_legacy_appearance();
+            assert_eq!(block.color(), None);
+            assert_eq!(block.icon(), None);
+            assert_eq!(block.members()[11], 7);
+        }
+
+        #[test]
+        fn test_set_name() {
+            let mut block = TagBlock::new_custom_creation_time(
+                0,
+                "a longer name",
+                TagFlags::default(),
+                0,
+                0,
+                0,
+                [0u64; 12],
+            );
+
+            block.set_name("short");
+            assert_eq!(block.name_string(), "short");
+            assert!(block.same_name("short"));
+            assert!(block.perform_checksum());
+        }
+
         #[test]
         fn test_eq() {
             let mut members = [0u64; 12];
@@ -904,62 +1210,6 @@ mod tests {
             assert_eq!(comp_bytes.to_vec(), block.to_bytes().to_vec());
         }
 
-        #[test]
-        fn test_from_bytes() {
-            let mut members = [0u64; 12];
-            members[0] = 0x33;
-            let block = TagBlock::new_custom_creation_time(
-                1,
-                "files",
-                TagFlags::new(true, false),
-                0xbad23132ad,
-                0x1,
-                0x1,
-                members,
-            );
-
-            let bytes = {
-                let mut bytes = [0u8; 256];
-                bytes[0] = 0x1;
-
-                // name
-                bytes[8] = b'f';
-                bytes[9] = b'i';
-                bytes[10] = b'l';
-                bytes[11] = b'e';
-                bytes[12] = b's';
-
-                // checksum
-                bytes[140] = 155;
-
-                // Flags
-                bytes[141] = 0b1000_0000;
-
-                // creation_time
-                bytes[142] = 0xad;
-                bytes[143] = 0x32;
-                bytes[144] = 0x31;
-                bytes[145] = 0xd2;
-                bytes[146] = 0xba;
-
-                // indirect
-                bytes[150] = 0x1;
-
-                // number of pointers
-                bytes[158] = 0x1;
-
-                //members
-                bytes[160] = 0x33;
-
-                bytes
-            };
-
-            let comp = TagBlock::from_bytes(&bytes).unwrap();
-
-            assert!(comp.perform_checksum());
-            assert_eq!(comp, block);
-        }
-
         #[test]
         fn test_append_first() {
             let members = [0u64; 12];
@@ -1193,74 +1443,14 @@ mod tests {
         }
 
         #[test]
-        fn test_from_bytes() {
-            let mut members = Vec::new();
-            members.push(0x33);
-
-            let block = IndirectTagBlock::new(0xad44, members, 0x32, 4096);
-
-            let bytes = {
-                let mut bytes = Vec::new();
-
-                // Root
-                bytes.push(0x44);
-                bytes.push(0xad);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-
-                // Checksum
-                bytes.push(169);
-
-                // Reserved
-                bytes.push(0);
-
-                // next
-                bytes.push(0x32);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-
-                // Number of members
-                bytes.push(1);
-                bytes.push(0);
-
-                // Members
-                bytes.push(0x33);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-                bytes.push(0);
-
-                bytes
-            };
-
-            let mut res = IndirectTagBlock::from_bytes(&bytes).unwrap();
-            res.maximum_members = 509;
-
-            assert_eq!(block, res);
-        }
-
-        #[test]
-        fn test_to_from_bytes() {
-            let mut members = Vec::new();
-            members.push(0x33);
-
-            let block = IndirectTagBlock::new(0xad44, members, 0x32, 4096);
-            let mut res = IndirectTagBlock::from_bytes(&block.to_bytes()).unwrap();
-            res.maximum_members = 509;
+        fn test_from_bytes_truncated() {
+            let block = IndirectTagBlock::new(0xad44, vec![0x33, 0x34], 0x32, 4096);
+            let bytes = block.to_bytes();
 
-            assert_eq!(block, res,);
+            assert_eq!(
+                IndirectTagBlock::from_bytes(&bytes[..bytes.len() - 4]),
+                None
+            );
         }
 
         #[test]
@@ -1345,4 +1535,84 @@ mod tests {
             assert_eq!(block.number_of_members, 1);
         }
     }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn arb_tag_block() -> impl Strategy<Value = TagBlock> {
+            return (
+                any::<u64>(),
+                "[a-zA-Z0-9_. -]{0,132}",
+                any::<(bool, bool)>(),
+                any::<u64>(),
+                any::<u64>(),
+                0..=TagBlock::MAXIMUM_LOCAL_MEMBERS,
+                any::<[u64; 12]>(),
+            )
+                .prop_map(
+                    |(index, name, (read, write), creation_time, indirect, pointers, members)| {
+                        TagBlock::new_custom_creation_time(
+                            index,
+                            &name,
+                            TagFlags::new(read, write),
+                            creation_time,
+                            indirect,
+                            pointers,
+                            members,
+                        )
+                    },
+                );
+        }
+
+        fn arb_indirect_tag_block() -> impl Strategy<Value = IndirectTagBlock> {
+            let maximum = IndirectTagBlock::max_members_for_blocksize(4096) as usize;
+
+            return (
+                any::<u64>(),
+                proptest::collection::vec(any::<u64>(), 0..=maximum),
+                any::<u64>(),
+            )
+                .prop_map(|(root, members, next)| {
+                    IndirectTagBlock::new(root, members, next, 4096)
+                });
+        }
+
+        proptest! {
+            #[test]
+            fn tag_block_round_trip(block in arb_tag_block()) {
+                prop_assert!(block.perform_checksum());
+
+                let bytes = block.to_bytes();
+                let parsed = TagBlock::from_bytes(&bytes).unwrap();
+
+                prop_assert_eq!(parsed, block);
+                prop_assert_eq!(parsed.to_bytes().to_vec(), bytes.to_vec());
+                prop_assert_eq!(parsed.calculate_checksum(), block.calculate_checksum());
+            }
+
+            #[test]
+            fn tag_block_corruption_detected(block in arb_tag_block(), position in 0..256usize, change in 1..=255u8) {
+                // The reserved flag bits are not read so changing them is not a corruption
+                prop_assume!(position != 141);
+
+                let mut bytes = block.to_bytes();
+                bytes[position] = bytes[position].wrapping_add(change);
+
+                prop_assert_eq!(TagBlock::from_bytes(&bytes), None);
+            }
+
+            #[test]
+            fn indirect_tag_block_round_trip(block in arb_indirect_tag_block()) {
+                prop_assert!(block.perform_checksum());
+
+                let bytes = block.to_bytes();
+                let mut parsed = IndirectTagBlock::from_bytes(&bytes).unwrap();
+                parsed.maximum_members = block.maximum_members;
+
+                prop_assert_eq!(&parsed, &block);
+                prop_assert_eq!(parsed.to_bytes(), bytes);
+            }
+        }
+    }
 }
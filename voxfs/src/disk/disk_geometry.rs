@@ -0,0 +1,172 @@
+use super::SuperBlock;
+
+/// The layout of a disk, the sizes and counts it was formatted with and where each region starts.
+/// Addresses are in bytes from the start of the disk.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DiskGeometry {
+    block_size: u64,
+    tag_count: u64,
+    inode_count: u64,
+    block_count: u64,
+    boot_area_blocks: u64,
+    tag_bitmap_start: u64,
+    inode_bitmap_start: u64,
+    block_bitmap_start: u64,
+    bitmaps_end: u64,
+    tag_table_start: u64,
+    inode_table_start: u64,
+    mirror_tag_table_start: Option<u64>,
+    mirror_inode_table_start: Option<u64>,
+    content_hash_start: Option<u64>,
+    content_hash_blocks: u64,
+    history_start: Option<u64>,
+    history_blocks: u64,
+    data_start: u64,
+}
+
+impl DiskGeometry {
+    /// The number of blocks each bitmap takes is given since the super block doesn't store it.
+    pub(crate) fn new(
+        super_block: &SuperBlock,
+        blocks_for_tag_map: u64,
+        blocks_for_inode_map: u64,
+        blocks_for_block_map: u64,
+    ) -> Self {
+        let block_size = super_block.block_size();
+        let tag_bitmap_start = super_block.bitmap_start_address();
+        let inode_bitmap_start = tag_bitmap_start + blocks_for_tag_map * block_size;
+        let block_bitmap_start = inode_bitmap_start + blocks_for_inode_map * block_size;
+
+        return Self {
+            block_size,
+            tag_count: super_block.tag_count(),
+            inode_count: super_block.inode_count(),
+            block_count: super_block.block_count(),
+            boot_area_blocks: super_block.boot_area_blocks() as u64,
+            tag_bitmap_start,
+            inode_bitmap_start,
+            block_bitmap_start,
+            bitmaps_end: block_bitmap_start + blocks_for_block_map * block_size,
+            tag_table_start: super_block.tag_start_address(),
+            inode_table_start: super_block.inode_start_address(),
+            mirror_tag_table_start: super_block.mirror_tag_start_address(),
+            mirror_inode_table_start: super_block.mirror_inode_start_address(),
+            content_hash_start: super_block.content_hash_start_address(),
+            content_hash_blocks: super_block.content_hash_blocks(),
+            history_start: super_block.history_start_address(),
+            history_blocks: super_block.history_blocks() as u64,
+            data_start: super_block.data_start_address(),
+        };
+    }
+
+    #[inline]
+    pub fn block_size(&self) -> u64 {
+        return self.block_size;
+    }
+
+    /// The number of tag slots.
+    #[inline]
+    pub fn tag_count(&self) -> u64 {
+        return self.tag_count;
+    }
+
+    /// The number of inode slots.
+    #[inline]
+    pub fn inode_count(&self) -> u64 {
+        return self.inode_count;
+    }
+
+    /// The number of data blocks.
+    #[inline]
+    pub fn block_count(&self) -> u64 {
+        return self.block_count;
+    }
+
+    /// The boot area follows the super block's block, it is empty if this is 0.
+    #[inline]
+    pub fn boot_area_blocks(&self) -> u64 {
+        return self.boot_area_blocks;
+    }
+
+    #[inline]
+    pub fn tag_bitmap_start(&self) -> u64 {
+        return self.tag_bitmap_start;
+    }
+
+    #[inline]
+    pub fn inode_bitmap_start(&self) -> u64 {
+        return self.inode_bitmap_start;
+    }
+
+    #[inline]
+    pub fn block_bitmap_start(&self) -> u64 {
+        return self.block_bitmap_start;
+    }
+
+    /// The address just past the block bitmap.
+    #[inline]
+    pub fn bitmaps_end(&self) -> u64 {
+        return self.bitmaps_end;
+    }
+
+    #[inline]
+    pub fn tag_table_start(&self) -> u64 {
+        return self.tag_table_start;
+    }
+
+    #[inline]
+    pub fn inode_table_start(&self) -> u64 {
+        return self.inode_table_start;
+    }
+
+    /// The start of the copy of the tag table, if the metadata is mirrored.
+    #[inline]
+    pub fn mirror_tag_table_start(&self) -> Option<u64> {
+        return self.mirror_tag_table_start;
+    }
+
+    /// The start of the copy of the inode table, if the metadata is mirrored.
+    #[inline]
+    pub fn mirror_inode_table_start(&self) -> Option<u64> {
+        return self.mirror_inode_table_start;
+    }
+
+    /// The start of the content hash region, if the disk has one.
+    #[inline]
+    pub fn content_hash_start(&self) -> Option<u64> {
+        return self.content_hash_start;
+    }
+
+    #[inline]
+    pub fn content_hash_blocks(&self) -> u64 {
+        return self.content_hash_blocks;
+    }
+
+    /// The start of the history region, if the disk has one.
+    #[inline]
+    pub fn history_start(&self) -> Option<u64> {
+        return self.history_start;
+    }
+
+    #[inline]
+    pub fn history_blocks(&self) -> u64 {
+        return self.history_blocks;
+    }
+
+    #[inline]
+    pub fn data_start(&self) -> u64 {
+        return self.data_start;
+    }
+
+    /// The address just past the last data block.
+    #[inline]
+    pub fn data_end(&self) -> u64 {
+        return self.data_start + self.block_count * self.block_size;
+    }
+
+    /// The address of a data block.
+    #[inline]
+    pub fn data_block_address(&self, index: u64) -> u64 {
+        return self.data_start + index * self.block_size;
+    }
+}
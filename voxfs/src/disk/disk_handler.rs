@@ -15,4 +15,16 @@ pub trait DiskHandler<E: VoxFSErrorConvertible> {
 
     /// This should return the raw disk size.
     fn disk_size(&self) -> Result<u64, E>;
+
+    /// Persist any writes buffered by the handler, such as by syncing a file. Does nothing by default.
+    fn flush(&mut self) -> Result<(), E> {
+        return Ok(());
+    }
+
+    /// Ensure every write made before this call reaches the disk before any write made after it. Handlers that
+    /// reorder or cache writes, such as those backed by a device with a volatile write cache, should implement this
+    /// for the filesystem to survive a crash. Does nothing by default.
+    fn barrier(&mut self) -> Result<(), E> {
+        return Ok(());
+    }
 }
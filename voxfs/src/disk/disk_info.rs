@@ -1,4 +1,13 @@
-use crate::{Disk, VoxFSErrorConvertible};
+use crate::{Disk, Timestamp, VoxFSErrorConvertible};
+
+/// What a disk is projected to run out of first as files are added.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Exhaustion {
+    /// Inodes will run out before data blocks, there are too few inodes for files of the average size.
+    INodes,
+    /// Data blocks will run out before inodes.
+    DataBlocks,
+}
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct DiskInfo {
@@ -7,8 +16,15 @@ pub struct DiskInfo {
     number_of_files: u64,
     free_file_slots: u64,
     block_size: u64,
+    data_block_count: u64,
     free_block_count: u64,
     free_block_space: u64,
+    checksum_failures: u64,
+    recovered_records: u64,
+    opened_dirty: bool,
+    dirty: bool,
+    last_check_time: Option<Timestamp>,
+    max_name_length: Option<usize>,
 }
 
 impl DiskInfo {
@@ -19,8 +35,15 @@ impl DiskInfo {
             number_of_files: disk.number_of_files() as u64,
             free_file_slots: disk.free_file_slots() as u64,
             block_size: disk.block_size(),
+            data_block_count: disk.data_block_count(),
             free_block_count: disk.free_block_count() as u64,
             free_block_space: disk.free_block_space(),
+            checksum_failures: disk.checksum_failures(),
+            recovered_records: (disk.recovered_tags().len() + disk.recovered_inodes().len()) as u64,
+            opened_dirty: disk.opened_dirty(),
+            dirty: disk.is_dirty(),
+            last_check_time: disk.last_check_time(),
+            max_name_length: disk.max_name_length(),
         };
     }
 
@@ -49,6 +72,11 @@ impl DiskInfo {
         return self.block_size;
     }
 
+    #[inline]
+    pub fn data_block_count(&self) -> u64 {
+        return self.data_block_count;
+    }
+
     #[inline]
     pub fn free_block_count(&self) -> u64 {
         return self.free_block_count;
@@ -58,4 +86,63 @@ impl DiskInfo {
     pub fn free_block_space(&self) -> u64 {
         return self.free_block_space;
     }
+
+    /// The number of records found corrupted since the disk was opened, see `Disk::checksum_failures`.
+    #[inline]
+    pub fn checksum_failures(&self) -> u64 {
+        return self.checksum_failures;
+    }
+
+    /// The number of tags and inodes read from the mirror when the disk was opened because their primary copy was
+    /// corrupted.
+    #[inline]
+    pub fn recovered_records(&self) -> u64 {
+        return self.recovered_records;
+    }
+
+    /// Whether the filesystem was not closed cleanly before it was opened.
+    #[inline]
+    pub fn opened_dirty(&self) -> bool {
+        return self.opened_dirty;
+    }
+
+    /// Whether the filesystem has been modified and not yet synced.
+    #[inline]
+    pub fn dirty(&self) -> bool {
+        return self.dirty;
+    }
+
+    /// When a scrub or incremental check last covered the whole disk since it was opened.
+    #[inline]
+    pub fn last_check_time(&self) -> Option<Timestamp> {
+        return self.last_check_time;
+    }
+
+    /// The most characters a new file or tag name may have, if the disk was formatted with a limit.
+    #[inline]
+    pub fn max_name_length(&self) -> Option<usize> {
+        return self.max_name_length;
+    }
+
+    /// Which runs out first if files keep being added at the average number of blocks used by the current files,
+    /// None until there are files to take an average from.
+    pub fn projected_exhaustion(&self) -> Option<Exhaustion> {
+        if self.number_of_files == 0 {
+            return None;
+        }
+
+        let used_blocks = self.data_block_count - self.free_block_count;
+        let blocks_per_file = used_blocks / self.number_of_files;
+
+        // Mostly empty files only use up inodes
+        if blocks_per_file == 0 {
+            return Some(Exhaustion::INodes);
+        }
+
+        if self.free_file_slots < self.free_block_count / blocks_per_file {
+            return Some(Exhaustion::INodes);
+        } else {
+            return Some(Exhaustion::DataBlocks);
+        }
+    }
 }
@@ -0,0 +1,108 @@
+/// How much of a disk a set of new files would use, worked out before any of them are written so an import can be
+/// refused up front rather than failing part way through.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EstimateReport {
+    block_size: u64,
+    files: u64,
+    data_blocks: u64,
+    indirect_blocks: u64,
+    free_blocks: u64,
+    free_inodes: u64,
+    placed: bool,
+}
+
+impl EstimateReport {
+    pub(crate) fn new(
+        block_size: u64,
+        files: u64,
+        data_blocks: u64,
+        indirect_blocks: u64,
+        free_blocks: u64,
+        free_inodes: u64,
+        placed: bool,
+    ) -> Self {
+        return Self {
+            block_size,
+            files,
+            data_blocks,
+            indirect_blocks,
+            free_blocks,
+            free_inodes,
+            placed,
+        };
+    }
+
+    /// The number of files, each of which takes an inode slot.
+    #[inline]
+    pub fn files(&self) -> u64 {
+        return self.files;
+    }
+
+    /// The blocks holding the files' contents, each file rounded up to a whole block.
+    #[inline]
+    pub fn data_blocks(&self) -> u64 {
+        return self.data_blocks;
+    }
+
+    /// The indirect inode blocks needed by files split into more extents than fit in an inode. Only counted for
+    /// the files placed before the free blocks ran out.
+    #[inline]
+    pub fn indirect_blocks(&self) -> u64 {
+        return self.indirect_blocks;
+    }
+
+    #[inline]
+    pub fn required_blocks(&self) -> u64 {
+        return self.data_blocks + self.indirect_blocks;
+    }
+
+    #[inline]
+    pub fn required_bytes(&self) -> u64 {
+        return self.required_blocks() * self.block_size;
+    }
+
+    #[inline]
+    pub fn free_blocks(&self) -> u64 {
+        return self.free_blocks;
+    }
+
+    #[inline]
+    pub fn free_bytes(&self) -> u64 {
+        return self.free_blocks * self.block_size;
+    }
+
+    #[inline]
+    pub fn free_inodes(&self) -> u64 {
+        return self.free_inodes;
+    }
+
+    /// The blocks missing for every file to be written, zero when they fit.
+    #[inline]
+    pub fn missing_blocks(&self) -> u64 {
+        return self.required_blocks().saturating_sub(self.free_blocks);
+    }
+
+    /// Whether every file was given blocks and an inode slot. A file can still fail to be written if the disk
+    /// changes first.
+    pub fn fits(&self) -> bool {
+        return self.placed && self.files <= self.free_inodes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_report() {
+        let report = EstimateReport::new(4096, 3, 10, 1, 20, 2, true);
+        assert_eq!(report.required_blocks(), 11);
+        assert_eq!(report.required_bytes(), 11 * 4096);
+        assert_eq!(report.missing_blocks(), 0);
+        assert!(!report.fits()); // Not enough inode slots
+
+        let report = EstimateReport::new(4096, 1, 30, 0, 20, 2, false);
+        assert_eq!(report.missing_blocks(), 10);
+        assert!(!report.fits());
+    }
+}
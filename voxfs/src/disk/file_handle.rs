@@ -0,0 +1,29 @@
+/// An open file, used with `Disk::read_file_range` to read a file in pieces.
+/// The handle remembers where the last read ended so sequential reads can be detected and read ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHandle {
+    inode_index: u64,
+    next_offset: u64,
+}
+
+impl FileHandle {
+    pub(crate) fn new(inode_index: u64) -> Self {
+        return Self {
+            inode_index,
+            next_offset: 0,
+        };
+    }
+
+    pub fn inode_index(&self) -> u64 {
+        return self.inode_index;
+    }
+
+    /// The offset just after the end of the last read, where a sequential read would start.
+    pub fn next_offset(&self) -> u64 {
+        return self.next_offset;
+    }
+
+    pub(crate) fn set_next_offset(&mut self, next_offset: u64) {
+        self.next_offset = next_offset;
+    }
+}
@@ -0,0 +1,18 @@
+/// Controls when changes to the tag, inode and data block bitmaps are written to the disk.
+/// Anything other than `Immediate` leaves the bitmaps on disk out of date until they are flushed, so they
+/// are only consistent with the tables after `Disk::sync`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitmapFlushPolicy {
+    /// Write the bitmaps every time they change.
+    Immediate,
+    /// Only write the bitmaps when the disk is synced or dropped.
+    OnSync,
+    /// Write the bitmaps once every number of modifying operations, and when the disk is synced or dropped.
+    EveryNOperations(u32),
+}
+
+impl Default for BitmapFlushPolicy {
+    fn default() -> Self {
+        return BitmapFlushPolicy::Immediate;
+    }
+}
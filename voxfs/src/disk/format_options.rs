@@ -0,0 +1,104 @@
+use super::NamePolicy;
+use alloc::string::String;
+
+/// Options used when formatting a new filesystem.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormatOptions {
+    /// The size in bytes of each block, a power of two of at least `MIN_BLOCK_SIZE`. 4KiB if not given.
+    pub block_size: Option<u64>,
+    /// The size in bytes of the boot area reserved after the super block, rounded up to whole blocks.
+    pub boot_area_size: u64,
+    /// The size in bytes of the region recording each change to the filesystem, rounded up to whole blocks.
+    /// Zero for no history. Once full the oldest records are overwritten.
+    pub history_size: u64,
+    /// The bytes of the disk for each tag or inode slot, at least the size of an inode. Fewer slots leave more
+    /// space for data, suiting a few large files. `DEFAULT_BYTES_PER_INODE` if not given.
+    pub bytes_per_inode: Option<u64>,
+    /// How many of the slots are inodes for each tag, at least 1. `DEFAULT_INODES_PER_TAG` if not given.
+    pub inodes_per_tag: Option<u64>,
+    /// Whether to keep a second copy of the tag and inode tables to recover records that fail their checksum.
+    pub mirror_metadata: bool,
+    /// Whether to keep a hash of each file's contents, made by the `OSManager`'s content hasher.
+    pub content_hashes: bool,
+    /// The identifier to give the filesystem, one is derived from the creation time and disk size if not given.
+    pub uuid: Option<[u8; 16]>,
+    /// A name for the filesystem of up to `MAX_LABEL_LENGTH` bytes.
+    pub label: String,
+    /// What happens when a file is created with the name of an existing file.
+    pub name_policy: NamePolicy,
+    /// The most characters a new file or tag name may have, between 1 and `TagBlock::MAX_NAME_LENGTH`. Without a
+    /// limit longer names are cut short to fit their record.
+    pub max_name_length: Option<usize>,
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = Some(block_size);
+
+        return self;
+    }
+
+    pub fn with_boot_area_size(mut self, boot_area_size: u64) -> Self {
+        self.boot_area_size = boot_area_size;
+
+        return self;
+    }
+
+    pub fn with_history_size(mut self, history_size: u64) -> Self {
+        self.history_size = history_size;
+
+        return self;
+    }
+
+    pub fn with_bytes_per_inode(mut self, bytes_per_inode: u64) -> Self {
+        self.bytes_per_inode = Some(bytes_per_inode);
+
+        return self;
+    }
+
+    pub fn with_inodes_per_tag(mut self, inodes_per_tag: u64) -> Self {
+        self.inodes_per_tag = Some(inodes_per_tag);
+
+        return self;
+    }
+
+    pub fn with_metadata_mirror(mut self, mirror_metadata: bool) -> Self {
+        self.mirror_metadata = mirror_metadata;
+
+        return self;
+    }
+
+    pub fn with_content_hashes(mut self, content_hashes: bool) -> Self {
+        self.content_hashes = content_hashes;
+
+        return self;
+    }
+
+    pub fn with_uuid(mut self, uuid: [u8; 16]) -> Self {
+        self.uuid = Some(uuid);
+
+        return self;
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = String::from(label);
+
+        return self;
+    }
+
+    pub fn with_name_policy(mut self, name_policy: NamePolicy) -> Self {
+        self.name_policy = name_policy;
+
+        return self;
+    }
+
+    pub fn with_max_name_length(mut self, max_name_length: Option<usize>) -> Self {
+        self.max_name_length = max_name_length;
+
+        return self;
+    }
+}
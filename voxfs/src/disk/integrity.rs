@@ -0,0 +1,21 @@
+/// How much of what is read from a disk is checked against its checksums, set with `MountOptions::with_integrity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Integrity {
+    /// Only the super block is checked, for trusted images where opening quickly matters more. A corrupted tag,
+    /// inode or indirect block is used as read rather than failing or falling back to the mirror. `Disk::scrub`
+    /// still checks everything.
+    None,
+    /// The super block, tags, inodes and their indirect blocks are checked.
+    Metadata,
+    /// The metadata is checked and file contents are checked against their content hash, failing with
+    /// `VoxFSError::DataChecksumMismatch` rather than returning corrupted bytes. A whole file is checked before the
+    /// first range is read from it. Opening fails with `VoxFSError::NoContentHasher` if the disk keeps no content
+    /// hashes or the manager has no hasher.
+    Full,
+}
+
+impl Default for Integrity {
+    fn default() -> Self {
+        return Integrity::Metadata;
+    }
+}
@@ -0,0 +1,180 @@
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// A part of a disk covered by an integrity manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ManifestRegion {
+    /// The boot area reserved after the super block.
+    BootArea,
+    /// A tag's name, flags and the indices of the files it is applied to.
+    Tag(u64),
+    /// A file's name, size, flags and device number.
+    INode(u64),
+    /// The blocks of one of a file's extents as they are stored, including any bytes past the end of the file.
+    Extent { inode: u64, start: u64, end: u64 },
+}
+
+/// The hash of every part of a disk that a consumer of a distributed image cares about, made by
+/// `Disk::export_integrity_manifest`. Its text form lists the entries in a fixed order, one per line, so the same
+/// image always gives the same bytes to sign.
+///
+/// Access times, mount counts and free space are left out, so opening or reading an image doesn't change its
+/// manifest. Block positions are included, so a copy of the files made into another image has a different one.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntegrityManifest {
+    entries: Vec<(ManifestRegion, [u8; 32])>,
+}
+
+impl IntegrityManifest {
+    /// Builds a manifest from its entries in any order.
+    pub fn new(mut entries: Vec<(ManifestRegion, [u8; 32])>) -> Self {
+        entries.sort_by_key(|(region, _)| *region);
+
+        return Self { entries };
+    }
+
+    /// The entries sorted by region.
+    #[inline]
+    pub fn entries(&self) -> &[(ManifestRegion, [u8; 32])] {
+        return &self.entries;
+    }
+
+    /// The hash recorded for a region.
+    pub fn hash(&self, region: ManifestRegion) -> Option<[u8; 32]> {
+        return match self.entries.binary_search_by_key(&region, |(r, _)| *r) {
+            Ok(i) => Some(self.entries[i].1),
+            Err(_) => None,
+        };
+    }
+
+    /// The regions whose hashes differ between the manifests, or that only one of them has, sorted.
+    pub fn differences(&self, other: &IntegrityManifest) -> Vec<ManifestRegion> {
+        let mut regions: Vec<ManifestRegion> = self
+            .entries
+            .iter()
+            .filter(|(region, hash)| other.hash(*region) != Some(*hash))
+            .map(|(region, _)| *region)
+            .chain(
+                other
+                    .entries
+                    .iter()
+                    .filter(|(region, _)| self.hash(*region).is_none())
+                    .map(|(region, _)| *region),
+            )
+            .collect();
+
+        regions.sort();
+
+        return regions;
+    }
+
+    /// Parses the text form written by `Display`, returning None if any line is malformed.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut entries = Vec::new();
+
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (hash, fields) = fields.split_last()?;
+            let numbers = fields[1..]
+                .iter()
+                .map(|n| n.parse::<u64>().ok())
+                .collect::<Option<Vec<u64>>>()?;
+
+            let region = match (fields[0], numbers.as_slice()) {
+                ("boot", []) => ManifestRegion::BootArea,
+                ("tag", [index]) => ManifestRegion::Tag(*index),
+                ("inode", [index]) => ManifestRegion::INode(*index),
+                ("extent", [inode, start, end]) => ManifestRegion::Extent {
+                    inode: *inode,
+                    start: *start,
+                    end: *end,
+                },
+                _ => return None,
+            };
+
+            entries.push((region, parse_hash(hash)?));
+        }
+
+        return Some(Self::new(entries));
+    }
+}
+
+impl Display for IntegrityManifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (region, hash) in self.entries.iter() {
+            match region {
+                ManifestRegion::BootArea => write!(f, "boot")?,
+                ManifestRegion::Tag(index) => write!(f, "tag {}", index)?,
+                ManifestRegion::INode(index) => write!(f, "inode {}", index)?,
+                ManifestRegion::Extent { inode, start, end } => {
+                    write!(f, "extent {} {} {}", inode, start, end)?
+                }
+            }
+
+            write!(f, " ")?;
+
+            for b in hash.iter() {
+                write!(f, "{:02x}", b)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        return Ok(());
+    }
+}
+
+fn parse_hash(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut hash = [0u8; 32];
+
+    for (i, b) in hash.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    return Some(hash);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_text_round_trip() {
+        let manifest = IntegrityManifest::new(vec![
+            (
+                ManifestRegion::Extent {
+                    inode: 1,
+                    start: 4,
+                    end: 6,
+                },
+                [0xab; 32],
+            ),
+            (ManifestRegion::INode(1), [1; 32]),
+            (ManifestRegion::BootArea, [0; 32]),
+        ]);
+
+        let text = manifest.to_string();
+        assert!(text.starts_with("boot 0000"));
+        assert_eq!(text.lines().count(), 3);
+        assert_eq!(IntegrityManifest::from_text(&text), Some(manifest.clone()));
+
+        assert_eq!(IntegrityManifest::from_text("inode 1 abcd"), None);
+        assert_eq!(
+            IntegrityManifest::from_text(&text.replace("inode", "node")),
+            None
+        );
+
+        let mut changed = manifest.entries().to_vec();
+        changed[1].1 = [2; 32];
+        changed.push((ManifestRegion::Tag(0), [3; 32]));
+        assert_eq!(
+            manifest.differences(&IntegrityManifest::new(changed)),
+            [ManifestRegion::Tag(0), ManifestRegion::INode(1)]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// The names of each tag's members mapped to their inode indexes, so a tag served like a directory can be listed
+/// and searched without reading its indirect chain every time. A tag's listing is forgotten when its members change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingCache {
+    tags: BTreeMap<u64, BTreeMap<String, u64>>,
+}
+
+impl ListingCache {
+    pub fn new() -> Self {
+        return Self {
+            tags: BTreeMap::new(),
+        };
+    }
+
+    pub fn get(&self, tag: u64) -> Option<&BTreeMap<String, u64>> {
+        return self.tags.get(&tag);
+    }
+
+    pub fn insert(&mut self, tag: u64, listing: BTreeMap<String, u64>) {
+        self.tags.insert(tag, listing);
+    }
+
+    pub fn forget(&mut self, tag: u64) {
+        self.tags.remove(&tag);
+    }
+
+    pub fn clear(&mut self) {
+        self.tags.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forget() {
+        let mut cache = ListingCache::new();
+        let mut listing = BTreeMap::new();
+        listing.insert(String::from("a"), 1);
+
+        cache.insert(3, listing.clone());
+        cache.insert(4, listing.clone());
+        assert_eq!(cache.get(3), Some(&listing));
+
+        cache.forget(3);
+        assert_eq!(cache.get(3), None);
+        assert_eq!(cache.get(4), Some(&listing));
+
+        cache.clear();
+        assert_eq!(cache.get(4), None);
+    }
+}
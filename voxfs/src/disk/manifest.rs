@@ -0,0 +1,65 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// A description of a disk's tags and the names of the files each is applied to, made by `Disk::export_manifest`.
+/// It holds no file contents so it can be kept alongside the files, e.g. in version control, and re-applied with
+/// `Disk::apply_manifest` to an image rebuilt from them.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Sorted by name.
+    pub tags: Vec<ManifestTag>,
+}
+
+/// A tag in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestTag {
+    pub name: String,
+    pub read: bool,
+    pub write: bool,
+    /// The names of the files the tag is applied to, sorted.
+    pub files: Vec<String>,
+}
+
+/// What applying a manifest changed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ManifestReport {
+    created_tags: Vec<String>,
+    applied: u64,
+    missing_files: Vec<String>,
+}
+
+impl ManifestReport {
+    pub(crate) fn new() -> Self {
+        return Self::default();
+    }
+
+    pub(crate) fn tag_created(&mut self, name: &str) {
+        self.created_tags.push(String::from(name));
+    }
+
+    pub(crate) fn tag_applied(&mut self) {
+        self.applied += 1;
+    }
+
+    pub(crate) fn file_missing(&mut self, name: &str) {
+        if !self.missing_files.iter().any(|f| f == name) {
+            self.missing_files.push(String::from(name));
+        }
+    }
+
+    /// The names of the tags the disk didn't have.
+    pub fn created_tags(&self) -> &Vec<String> {
+        return &self.created_tags;
+    }
+
+    /// The number of tags applied to files, not counting those that were already applied.
+    pub fn applied(&self) -> u64 {
+        return self.applied;
+    }
+
+    /// The names of files in the manifest that aren't on the disk, their tags were skipped.
+    pub fn missing_files(&self) -> &Vec<String> {
+        return &self.missing_files;
+    }
+}
@@ -0,0 +1,28 @@
+use alloc::boxed::Box;
+use core::ops::{Deref, DerefMut};
+
+/// Either a borrow of a value or the value itself, so a disk can be given its handler and manager either way.
+pub enum MaybeOwned<'a, T: ?Sized> {
+    Borrowed(&'a mut T),
+    Owned(Box<T>),
+}
+
+impl<'a, T: ?Sized> Deref for MaybeOwned<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return match self {
+            MaybeOwned::Borrowed(t) => t,
+            MaybeOwned::Owned(t) => t,
+        };
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MaybeOwned<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        return match self {
+            MaybeOwned::Borrowed(t) => t,
+            MaybeOwned::Owned(t) => t,
+        };
+    }
+}
@@ -0,0 +1,130 @@
+use super::DiskHandler;
+use crate::VoxFSErrorConvertible;
+use alloc::vec::Vec;
+
+/// The errors a `MemoryDiskHandler` can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryDiskError {
+    /// A read or write went past the end of the disk.
+    OutOfBounds,
+}
+
+impl VoxFSErrorConvertible for MemoryDiskError {}
+
+impl core::fmt::Display for MemoryDiskError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return match self {
+            MemoryDiskError::OutOfBounds => write!(f, "Out of bounds disk access"),
+        };
+    }
+}
+
+/// A disk handler that keeps the whole disk in memory. Accesses outside the disk return an error rather than panicking,
+/// which makes it suitable for testing and fuzzing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryDiskHandler {
+    disk: Vec<u8>,
+}
+
+impl MemoryDiskHandler {
+    /// Constructs a new zeroed disk of a size in bytes.
+    pub fn new(disk_size: usize) -> Self {
+        return Self {
+            disk: vec![0u8; disk_size],
+        };
+    }
+
+    /// Constructs a disk from existing bytes, for example an image read from a file.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        return Self { disk: bytes };
+    }
+
+    /// The raw contents of the disk.
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.disk;
+    }
+
+    /// Consumes the handler and returns the raw contents of the disk.
+    pub fn into_bytes(self) -> Vec<u8> {
+        return self.disk;
+    }
+
+    /// Returns the range start..end if it lies within the disk.
+    fn checked_range(&self, start: u64, end: u64) -> Result<(usize, usize), MemoryDiskError> {
+        if start > end || end > self.disk.len() as u64 {
+            return Err(MemoryDiskError::OutOfBounds);
+        }
+
+        return Ok((start as usize, end as usize));
+    }
+}
+
+impl DiskHandler<MemoryDiskError> for MemoryDiskHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MemoryDiskError> {
+        let end = match location.checked_add(bytes.len() as u64) {
+            Some(e) => e,
+            None => return Err(MemoryDiskError::OutOfBounds),
+        };
+
+        let (start, end) = self.checked_range(location, end)?;
+        self.disk[start..end].copy_from_slice(bytes);
+
+        return Ok(());
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MemoryDiskError> {
+        let end = match location.checked_add(amount) {
+            Some(e) => e,
+            None => return Err(MemoryDiskError::OutOfBounds),
+        };
+
+        let (start, end) = self.checked_range(location, end)?;
+
+        return Ok(self.disk[start..end].to_vec());
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MemoryDiskError> {
+        let (start, end) = self.checked_range(start, end)?;
+
+        for byte in self.disk[start..end].iter_mut() {
+            *byte = 0;
+        }
+
+        return Ok(());
+    }
+
+    fn disk_size(&self) -> Result<u64, MemoryDiskError> {
+        return Ok(self.disk.len() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_write() {
+        let mut handler = MemoryDiskHandler::new(16);
+
+        handler.write_bytes(&vec![1, 2, 3], 4).unwrap();
+        assert_eq!(handler.read_bytes(3, 5).unwrap(), vec![0, 1, 2, 3, 0]);
+
+        handler.zero_range(4, 6).unwrap();
+        assert_eq!(handler.read_bytes(3, 5).unwrap(), vec![0, 0, 0, 3, 0]);
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let mut handler = MemoryDiskHandler::new(16);
+
+        assert_eq!(
+            handler.write_bytes(&vec![1, 2], 15),
+            Err(MemoryDiskError::OutOfBounds)
+        );
+        assert_eq!(
+            handler.read_bytes(u64::MAX, 2),
+            Err(MemoryDiskError::OutOfBounds)
+        );
+        assert_eq!(handler.zero_range(8, 17), Err(MemoryDiskError::OutOfBounds));
+    }
+}
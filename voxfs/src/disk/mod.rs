@@ -1,13 +1,70 @@
 // Disk layout:
-// 1024-bit padding block, super-block, inodes (10% of the disk is reserved for inodes),
-// tag table (10% of the disk is reserved for tags), data blocks ...
+// super-block (padded to a block), boot area (optional, a whole number of blocks), bitmaps,
+// tag table, inode table, tag and inode table mirror (optional), history (optional), data blocks ...
 
+#[cfg(feature = "access-stats")]
+mod access_heatmap;
+mod block_cache;
+mod capacity_report;
+mod chain_stats;
 mod disk;
 mod disk_blocks;
+mod disk_geometry;
 pub mod disk_handler;
 mod disk_info;
+mod estimate_report;
+mod file_handle;
+mod flush_policy;
+mod format_options;
+mod integrity;
+mod integrity_manifest;
+mod listing_cache;
+#[cfg(feature = "std")]
+mod manifest;
+mod maybe_owned;
+mod memory_disk_handler;
+mod mount_options;
+mod name_pattern;
+mod name_report;
+mod new_file_spec;
+mod op_context;
+mod open_report;
+mod overlay_disk;
+mod scrub_report;
+mod sort_order;
+mod tag_index;
+mod tag_query;
 
-pub use disk::{Disk, FileSize, FORBIDDEN_CHARACTERS};
-pub use disk_blocks::{INode, INodeFlags, IndirectINode, IndirectTagBlock, TagBlock, TagFlags};
+#[cfg(feature = "access-stats")]
+pub use access_heatmap::{AccessHeatmap, BlockAccess};
+pub use capacity_report::{CapacityReport, Usage};
+pub use chain_stats::{ChainStats, ChainUsage};
+pub use disk::{Disk, FileSize, FORBIDDEN_CHARACTERS, MIN_BLOCK_SIZE};
+pub use disk_blocks::{
+    Extent, FileType, FilesystemState, HistoryOperation, HistoryRecord, INode, INodeFlags,
+    IndirectINode, IndirectTagBlock, NamePolicy, SuperBlock, TagBlock, TagFlags,
+    BACKUP_SUPER_BLOCK_ADDRESS, CONTENT_HASH_SIZE, DEFAULT_BYTES_PER_INODE, DEFAULT_INODES_PER_TAG,
+    MAX_LABEL_LENGTH, MAX_TAG_COLOR,
+};
+pub use disk_geometry::DiskGeometry;
 pub use disk_handler::DiskHandler;
-pub use disk_info::DiskInfo;
+pub use disk_info::{DiskInfo, Exhaustion};
+pub use estimate_report::EstimateReport;
+pub use file_handle::FileHandle;
+pub use flush_policy::BitmapFlushPolicy;
+pub use format_options::FormatOptions;
+pub use integrity::Integrity;
+pub use integrity_manifest::{IntegrityManifest, ManifestRegion};
+#[cfg(feature = "std")]
+pub use manifest::{Manifest, ManifestReport, ManifestTag};
+pub use memory_disk_handler::{MemoryDiskError, MemoryDiskHandler};
+pub use mount_options::MountOptions;
+pub use name_pattern::{NamePattern, NamePatternError};
+pub use name_report::{DuplicateName, NameIssue, NameReport, OddName, LONGEST_NAMES};
+pub use new_file_spec::NewFileSpec;
+pub use op_context::OpContext;
+pub use open_report::{OpenReport, RecordKind, SkippedRecord};
+pub use overlay_disk::{OverlayDisk, DEFAULT_OVERLAY_BLOCK_SIZE};
+pub use scrub_report::{ScrubFailure, ScrubRegion, ScrubReport};
+pub use sort_order::SortOrder;
+pub use tag_query::{TagQuery, TagQueryError};
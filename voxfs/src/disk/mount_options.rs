@@ -0,0 +1,115 @@
+use super::Integrity;
+
+/// Options used when opening an existing filesystem with `Disk::open_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MountOptions {
+    /// Whether to refuse every modification with `VoxFSError::ReadOnly`. Nothing is written to the disk,
+    /// not even the mount count.
+    pub read_only: bool,
+    /// Whether to scrub the metadata after opening, failing with `VoxFSError::FailedCheckOnOpen` if it isn't clean.
+    pub check_on_open: bool,
+    /// Whether to read each tag's members only when they are first needed rather than all of them when opening.
+    pub lazy_load: bool,
+    /// Whether reading a file leaves its access time alone. Otherwise the access time is written when the disk syncs.
+    /// Always true for a read only disk.
+    pub noatime: bool,
+    /// The number of data blocks kept in memory, see `Disk::set_cache_size`.
+    pub cache_size: usize,
+    /// How much of what is read is checked against its checksums.
+    pub integrity: Integrity,
+    /// The most bytes an operation may allocate for the contents it reads, see `Disk::set_memory_budget`.
+    pub memory_budget: Option<u64>,
+    /// Whether to open an image shorter than its super block describes, which otherwise fails with
+    /// `VoxFSError::ImageTruncated`. The data region is shrunk to the blocks that remain and the disk is read only,
+    /// so the files that survived can be copied off it.
+    pub salvage: bool,
+    /// Whether deleting a file with handles open from `Disk::open_file` fails with `VoxFSError::FileBusy` until they
+    /// are closed with `Disk::close_file`, so a front end serving the file doesn't have it deleted underneath it.
+    pub busy_protection: bool,
+}
+
+impl MountOptions {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+
+        return self;
+    }
+
+    pub fn with_check_on_open(mut self, check_on_open: bool) -> Self {
+        self.check_on_open = check_on_open;
+
+        return self;
+    }
+
+    pub fn with_lazy_load(mut self, lazy_load: bool) -> Self {
+        self.lazy_load = lazy_load;
+
+        return self;
+    }
+
+    pub fn with_noatime(mut self, noatime: bool) -> Self {
+        self.noatime = noatime;
+
+        return self;
+    }
+
+    pub fn with_cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = cache_size;
+
+        return self;
+    }
+
+    pub fn with_integrity(mut self, integrity: Integrity) -> Self {
+        self.integrity = integrity;
+
+        return self;
+    }
+
+    /// Whether file contents are checked against their content hash as they are read, the same as
+    /// `Integrity::Full` when true and `Integrity::Metadata` when false.
+    pub fn with_verify_reads(self, verify_reads: bool) -> Self {
+        return self.with_integrity(match verify_reads {
+            true => Integrity::Full,
+            false => Integrity::Metadata,
+        });
+    }
+
+    pub fn with_memory_budget(mut self, memory_budget: Option<u64>) -> Self {
+        self.memory_budget = memory_budget;
+
+        return self;
+    }
+
+    pub fn with_salvage(mut self, salvage: bool) -> Self {
+        self.salvage = salvage;
+
+        return self;
+    }
+
+    pub fn with_busy_protection(mut self, busy_protection: bool) -> Self {
+        self.busy_protection = busy_protection;
+
+        return self;
+    }
+}
+
+impl Default for MountOptions {
+    /// The options `Disk::open_disk` uses.
+    fn default() -> Self {
+        return Self {
+            read_only: false,
+            check_on_open: false,
+            lazy_load: true,
+            noatime: true,
+            cache_size: 0,
+            integrity: Integrity::Metadata,
+            memory_budget: None,
+            salvage: false,
+            busy_protection: false,
+        };
+    }
+}
@@ -0,0 +1,241 @@
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// A shell style wildcard pattern over file names, used to search for files.
+///
+/// `*` matches any run of characters, `?` matches a single character and `[...]` matches one character from
+/// a set, which can contain ranges like `a-z` and be negated with a leading `!`. A `\` matches the character
+/// after it literally, e.g. `report-[0-9]*.log` or `\*important\*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePattern {
+    tokens: Vec<Token>,
+}
+
+/// Why a name pattern could not be parsed, with the character position the problem was found at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamePatternError {
+    position: usize,
+    reason: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    AnyCharacter,
+    AnyRun,
+    Set {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl NamePattern {
+    pub fn parse(pattern: &str) -> Result<Self, NamePatternError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut position = 0;
+
+        while position < chars.len() {
+            match chars[position] {
+                '*' => {
+                    // Consecutive stars match the same as one
+                    if tokens.last() != Some(&Token::AnyRun) {
+                        tokens.push(Token::AnyRun);
+                    }
+                }
+                '?' => tokens.push(Token::AnyCharacter),
+                '\\' => match chars.get(position + 1) {
+                    Some(c) => {
+                        tokens.push(Token::Literal(*c));
+                        position += 1;
+                    }
+                    None => {
+                        return Err(NamePatternError {
+                            position,
+                            reason: "Expected a character to escape",
+                        })
+                    }
+                },
+                '[' => {
+                    let (token, end) = parse_set(&chars, position)?;
+                    tokens.push(token);
+                    position = end;
+                }
+                c => tokens.push(Token::Literal(c)),
+            }
+
+            position += 1;
+        }
+
+        return Ok(Self { tokens });
+    }
+
+    /// Whether the whole of the name matches the pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        let mut token = 0;
+        let mut character = 0;
+
+        // Where to resume if the characters after the last star stop matching
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while character < name.len() {
+            match self.tokens.get(token) {
+                Some(Token::AnyRun) => {
+                    token += 1;
+                    backtrack = Some((token, character));
+                    continue;
+                }
+                Some(t) if t.matches(name[character]) => {
+                    token += 1;
+                    character += 1;
+                    continue;
+                }
+                _ => (),
+            }
+
+            match backtrack {
+                Some((after_star, start)) => {
+                    // Let the star take one more character
+                    token = after_star;
+                    character = start + 1;
+                    backtrack = Some((after_star, start + 1));
+                }
+                None => return false,
+            }
+        }
+
+        return self.tokens[token..].iter().all(|t| *t == Token::AnyRun);
+    }
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        return match self {
+            Token::Literal(l) => *l == c,
+            Token::AnyCharacter => true,
+            Token::AnyRun => false,
+            Token::Set { negated, ranges } => {
+                ranges.iter().any(|(low, high)| *low <= c && c <= *high) != *negated
+            }
+        };
+    }
+}
+
+/// Parses the set starting at the `[` at the position, returning it and the position of its `]`.
+fn parse_set(chars: &[char], start: usize) -> Result<(Token, usize), NamePatternError> {
+    let mut position = start + 1;
+    let negated = chars.get(position) == Some(&'!');
+
+    if negated {
+        position += 1;
+    }
+
+    let mut ranges = Vec::new();
+
+    loop {
+        let low = match chars.get(position) {
+            // A `]` straight after the opening bracket is part of the set
+            Some(']') if !ranges.is_empty() => break,
+            Some('\\') => {
+                position += 1;
+                match chars.get(position) {
+                    Some(c) => *c,
+                    None => break,
+                }
+            }
+            Some(c) => *c,
+            None => break,
+        };
+
+        let high = match (chars.get(position + 1), chars.get(position + 2)) {
+            (Some('-'), Some(h)) if *h != ']' => {
+                position += 2;
+                *h
+            }
+            _ => low,
+        };
+
+        if high < low {
+            return Err(NamePatternError {
+                position,
+                reason: "The range is backwards",
+            });
+        }
+
+        ranges.push((low, high));
+        position += 1;
+    }
+
+    if chars.get(position) != Some(&']') {
+        return Err(NamePatternError {
+            position: start,
+            reason: "Unterminated character set",
+        });
+    }
+
+    return Ok((Token::Set { negated, ranges }, position));
+}
+
+impl NamePatternError {
+    pub fn position(&self) -> usize {
+        return self.position;
+    }
+
+    pub fn reason(&self) -> &'static str {
+        return self.reason;
+    }
+}
+
+impl Display for NamePatternError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        return write!(f, "{} at position {}", self.reason, self.position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        return NamePattern::parse(pattern).unwrap().matches(name);
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*.log", "server.log"));
+        assert!(matches("*.log", ".log"));
+        assert!(!matches("*.log", "server.log.1"));
+        assert!(matches("*a*b*", "xxaxxbxx"));
+        assert!(!matches("*a*b", "xxbxxa"));
+        assert!(matches("file?", "file1"));
+        assert!(!matches("file?", "file"));
+        assert!(matches("**", ""));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+    }
+
+    #[test]
+    fn test_sets() {
+        assert!(matches("report-[0-9].txt", "report-7.txt"));
+        assert!(!matches("report-[0-9].txt", "report-x.txt"));
+        assert!(matches("[!a-c]*", "dog"));
+        assert!(!matches("[!a-c]*", "cat"));
+        assert!(matches("[]a]", "]"));
+        assert!(matches("[a-]", "-"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("[\\]]", "]"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(NamePattern::parse("a[bc").unwrap_err().position(), 1);
+        assert_eq!(NamePattern::parse("a\\").unwrap_err().position(), 1);
+        assert_eq!(NamePattern::parse("[z-a]").unwrap_err().position(), 3);
+    }
+}
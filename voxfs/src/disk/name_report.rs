@@ -0,0 +1,248 @@
+use super::disk::FORBIDDEN_CHARACTERS;
+use crate::INode;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The number of names listed by `NameReport::longest`.
+pub const LONGEST_NAMES: usize = 10;
+
+/// Why a file name could cause trouble when the files are shown through another interface, such as a mount or a
+/// web server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameIssue {
+    /// The name's bytes are not valid UTF-8, names are stored a byte per character so anything outside of ASCII
+    /// was stored as a single byte.
+    NonUtf8,
+    /// The name contains a control character such as a tab or newline.
+    ControlCharacter,
+    /// The name contains a character new names are refused for, written by an older tool or directly to the disk.
+    ForbiddenCharacter,
+    /// The name starts or ends with whitespace.
+    SurroundingWhitespace,
+    /// The name is empty, "." or "..", which most interfaces give another meaning.
+    Reserved,
+}
+
+impl NameIssue {
+    /// The issues with a name, in the order the variants are declared.
+    pub fn find(name: &str) -> Vec<NameIssue> {
+        let mut issues = Vec::new();
+        let bytes: Vec<u8> = name.chars().map(|c| c as u8).collect();
+
+        if core::str::from_utf8(&bytes).is_err() {
+            issues.push(NameIssue::NonUtf8);
+        }
+
+        if name.chars().any(|c| c.is_control()) {
+            issues.push(NameIssue::ControlCharacter);
+        }
+
+        if name.chars().any(|c| FORBIDDEN_CHARACTERS.contains(&c)) {
+            issues.push(NameIssue::ForbiddenCharacter);
+        }
+
+        if name.trim() != name {
+            issues.push(NameIssue::SurroundingWhitespace);
+        }
+
+        if name.is_empty() || name == "." || name == ".." {
+            issues.push(NameIssue::Reserved);
+        }
+
+        return issues;
+    }
+}
+
+/// A name, or with `NameReport::case_collisions` a name ignoring case, shared by more than one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateName {
+    name: String,
+    inodes: Vec<u64>,
+}
+
+impl DuplicateName {
+    /// The name of the file with the lowest index.
+    #[inline]
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// The indices of the files sharing the name, lowest first.
+    #[inline]
+    pub fn inodes(&self) -> &[u64] {
+        return &self.inodes;
+    }
+}
+
+/// A file whose name has at least one issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OddName {
+    index: u64,
+    name: String,
+    issues: Vec<NameIssue>,
+}
+
+impl OddName {
+    #[inline]
+    pub fn index(&self) -> u64 {
+        return self.index;
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    #[inline]
+    pub fn issues(&self) -> &[NameIssue] {
+        return &self.issues;
+    }
+}
+
+/// The names of every file on a disk, to find the ones to clean up before the files are shown through an interface
+/// that needs unique, printable names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameReport {
+    files: u64,
+    total_length: u64,
+    duplicates: Vec<DuplicateName>,
+    case_collisions: Vec<DuplicateName>,
+    longest: Vec<(u64, String)>,
+    odd_names: Vec<OddName>,
+}
+
+impl NameReport {
+    /// Builds the report from the inodes, which must be in index order.
+    pub(crate) fn new(inodes: &[INode]) -> Self {
+        let mut by_name: BTreeMap<String, Vec<(u64, String)>> = BTreeMap::new();
+        let mut by_lowercase: BTreeMap<String, Vec<(u64, String)>> = BTreeMap::new();
+        let mut total_length = 0;
+        let mut names = Vec::with_capacity(inodes.len());
+        let mut odd_names = Vec::new();
+
+        for inode in inodes {
+            let name = inode.name();
+            total_length += name.chars().count() as u64;
+
+            by_name
+                .entry(name.clone())
+                .or_default()
+                .push((inode.index(), name.clone()));
+            by_lowercase
+                .entry(name.to_lowercase())
+                .or_default()
+                .push((inode.index(), name.clone()));
+
+            let issues = NameIssue::find(&name);
+
+            if !issues.is_empty() {
+                odd_names.push(OddName {
+                    index: inode.index(),
+                    name: name.clone(),
+                    issues,
+                });
+            }
+
+            names.push((inode.index(), name));
+        }
+
+        // Names that only differ in case are reported once as collisions, exact duplicates are already listed
+        let case_collisions = shared(by_lowercase.into_values().filter(|files| {
+            return files.iter().any(|(_, name)| *name != files[0].1);
+        }));
+
+        // Longest first, ties in index order
+        names.sort_by_key(|(_, name)| core::cmp::Reverse(name.chars().count()));
+        names.truncate(LONGEST_NAMES);
+
+        return Self {
+            files: inodes.len() as u64,
+            total_length,
+            duplicates: shared(by_name.into_values()),
+            case_collisions,
+            longest: names,
+            odd_names,
+        };
+    }
+
+    #[inline]
+    pub fn files(&self) -> u64 {
+        return self.files;
+    }
+
+    /// The average length of a name in characters, rounded down.
+    pub fn average_length(&self) -> u64 {
+        if self.files == 0 {
+            return 0;
+        }
+
+        return self.total_length / self.files;
+    }
+
+    /// The names shared by more than one file, possible with `NamePolicy::Allow`. Lookups by name only find the
+    /// first of them.
+    #[inline]
+    pub fn duplicates(&self) -> &[DuplicateName] {
+        return &self.duplicates;
+    }
+
+    /// The names that are only unique because of their case, which clash on hosts that ignore case.
+    #[inline]
+    pub fn case_collisions(&self) -> &[DuplicateName] {
+        return &self.case_collisions;
+    }
+
+    /// The index and name of the longest names, up to `LONGEST_NAMES` of them, longest first.
+    #[inline]
+    pub fn longest(&self) -> &[(u64, String)] {
+        return &self.longest;
+    }
+
+    #[inline]
+    pub fn odd_names(&self) -> &[OddName] {
+        return &self.odd_names;
+    }
+
+    /// Whether no names are duplicated, collide or have an issue.
+    pub fn is_clean(&self) -> bool {
+        return self.duplicates.is_empty()
+            && self.case_collisions.is_empty()
+            && self.odd_names.is_empty();
+    }
+}
+
+/// Keeps the groups of more than one file as duplicates, named after their first file.
+fn shared(groups: impl Iterator<Item = Vec<(u64, String)>>) -> Vec<DuplicateName> {
+    let mut duplicates: Vec<DuplicateName> = groups
+        .filter(|files| files.len() > 1)
+        .map(|files| DuplicateName {
+            name: files[0].1.clone(),
+            inodes: files.iter().map(|(index, _)| *index).collect(),
+        })
+        .collect();
+
+    duplicates.sort_by_key(|d| d.inodes[0]);
+
+    return duplicates;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_issues() {
+        assert!(NameIssue::find("report.txt").is_empty());
+        assert!(NameIssue::find("naïve").contains(&NameIssue::NonUtf8));
+        assert_eq!(
+            NameIssue::find(" tab\t"),
+            [
+                NameIssue::ControlCharacter,
+                NameIssue::SurroundingWhitespace
+            ]
+        );
+        assert_eq!(NameIssue::find("a/b"), [NameIssue::ForbiddenCharacter]);
+        assert_eq!(NameIssue::find(".."), [NameIssue::Reserved]);
+    }
+}
@@ -0,0 +1,36 @@
+use super::INodeFlags;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A file to create with `Disk::import_batch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewFileSpec {
+    pub name: String,
+    pub flags: INodeFlags,
+    pub contents: Vec<u8>,
+    /// The indexes of the tags to apply to the file once every file in the batch has been written.
+    pub tags: Vec<u64>,
+}
+
+impl NewFileSpec {
+    pub fn new(name: &str, contents: Vec<u8>) -> Self {
+        return Self {
+            name: String::from(name),
+            flags: INodeFlags::default(),
+            contents,
+            tags: Vec::new(),
+        };
+    }
+
+    pub fn with_flags(mut self, flags: INodeFlags) -> Self {
+        self.flags = flags;
+
+        return self;
+    }
+
+    pub fn with_tag(mut self, tag_index: u64) -> Self {
+        self.tags.push(tag_index);
+
+        return self;
+    }
+}
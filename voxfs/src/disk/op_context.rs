@@ -0,0 +1,62 @@
+use crate::VoxFSError;
+
+/// Lets the caller of a long operation follow its progress and cancel it, e.g. to show a progress bar in a UI.
+/// Both callbacks are optional, `OpContext::new()` reports nothing and never cancels.
+#[derive(Clone, Copy, Default)]
+pub struct OpContext<'a> {
+    progress: Option<&'a dyn Fn(u64, u64)>,
+    cancelled: Option<&'a dyn Fn() -> bool>,
+}
+
+impl<'a> OpContext<'a> {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Called with the units of work done and the total as the operation goes.
+    pub fn with_progress(mut self, progress: &'a dyn Fn(u64, u64)) -> Self {
+        self.progress = Some(progress);
+        return self;
+    }
+
+    /// Checked between units of work, the operation stops with `VoxFSError::Cancelled` once it returns true.
+    /// Changes made before then are kept.
+    pub fn with_cancellation(mut self, cancelled: &'a dyn Fn() -> bool) -> Self {
+        self.cancelled = Some(cancelled);
+        return self;
+    }
+
+    pub fn report(&self, done: u64, total: u64) {
+        if let Some(progress) = self.progress {
+            progress(done, total);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return match self.cancelled {
+            Some(cancelled) => cancelled(),
+            None => false,
+        };
+    }
+
+    /// Reports the progress then stops the operation if it has been cancelled.
+    pub fn step<E>(&self, done: u64, total: u64) -> Result<(), VoxFSError<E>> {
+        self.report(done, total);
+
+        if self.is_cancelled() {
+            return Err(VoxFSError::Cancelled);
+        }
+
+        return Ok(());
+    }
+}
+
+impl<'a> core::fmt::Debug for OpContext<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return f
+            .debug_struct("OpContext")
+            .field("progress", &self.progress.is_some())
+            .field("cancelled", &self.cancelled.is_some())
+            .finish();
+    }
+}
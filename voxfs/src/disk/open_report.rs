@@ -0,0 +1,78 @@
+use crate::VoxFSError;
+use alloc::vec::Vec;
+
+/// The kind of on disk record that could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Tag,
+    INode,
+}
+
+/// A tag or inode that was skipped while opening a disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRecord<E> {
+    kind: RecordKind,
+    index: u64,
+    address: u64,
+    reason: VoxFSError<E>,
+}
+
+impl<E> SkippedRecord<E> {
+    pub fn kind(&self) -> RecordKind {
+        return self.kind;
+    }
+
+    /// The record's index in the tag or inode table.
+    pub fn index(&self) -> u64 {
+        return self.index;
+    }
+
+    /// The address of the record in the table.
+    pub fn address(&self) -> u64 {
+        return self.address;
+    }
+
+    /// Why it could not be read, either a corruption error or the error from the disk.
+    pub fn reason(&self) -> &VoxFSError<E> {
+        return &self.reason;
+    }
+}
+
+/// The records skipped by `Disk::open_disk_tolerant`. Their slots stay marked as used so they are not
+/// overwritten, leaving the damaged records and any data blocks they point to in place for recovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenReport<E> {
+    skipped: Vec<SkippedRecord<E>>,
+}
+
+impl<E> OpenReport<E> {
+    pub(crate) fn new() -> Self {
+        return Self {
+            skipped: Vec::new(),
+        };
+    }
+
+    pub(crate) fn skip(
+        &mut self,
+        kind: RecordKind,
+        index: u64,
+        address: u64,
+        reason: VoxFSError<E>,
+    ) {
+        self.skipped.push(SkippedRecord {
+            kind,
+            index,
+            address,
+            reason,
+        });
+    }
+
+    /// True if every record was read.
+    pub fn is_clean(&self) -> bool {
+        return self.skipped.is_empty();
+    }
+
+    pub fn skipped(&self) -> &Vec<SkippedRecord<E>> {
+        return &self.skipped;
+    }
+}
@@ -0,0 +1,212 @@
+use super::DiskHandler;
+use crate::VoxFSErrorConvertible;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// The size in bytes of the blocks an `OverlayDisk` copies from its base, unless another is given.
+pub const DEFAULT_OVERLAY_BLOCK_SIZE: u64 = 4_096;
+
+/// A disk handler that never writes to the disk beneath it. Each block written is copied from the base into memory
+/// and changed there, reads see those copies in place of the base. The changes can be written to the base with
+/// `commit` or thrown away with `discard`, so an image can be changed freely, e.g. to experiment or to run from
+/// media that should stay untouched.
+pub struct OverlayDisk<'a, E: VoxFSErrorConvertible> {
+    base: &'a mut dyn DiskHandler<E>,
+    block_size: u64,
+    // The changed blocks by their address on the base.
+    blocks: BTreeMap<u64, Vec<u8>>,
+}
+
+impl<'a, E: VoxFSErrorConvertible> OverlayDisk<'a, E> {
+    /// Constructs an overlay with no changes over a base disk.
+    pub fn new(base: &'a mut dyn DiskHandler<E>) -> Self {
+        return Self::with_block_size(base, DEFAULT_OVERLAY_BLOCK_SIZE);
+    }
+
+    /// Constructs an overlay that copies blocks of a size in bytes, which is at least 1.
+    pub fn with_block_size(base: &'a mut dyn DiskHandler<E>, block_size: u64) -> Self {
+        return Self {
+            base,
+            block_size: core::cmp::max(block_size, 1),
+            blocks: BTreeMap::new(),
+        };
+    }
+
+    /// Whether anything has been written since the overlay was created, committed or discarded.
+    pub fn is_modified(&self) -> bool {
+        return !self.blocks.is_empty();
+    }
+
+    /// The number of blocks held in memory.
+    pub fn modified_blocks(&self) -> usize {
+        return self.blocks.len();
+    }
+
+    /// Writes the changed blocks to the base and flushes it. The overlay is empty afterwards, if a write fails
+    /// every block is kept so the commit can be tried again.
+    pub fn commit(&mut self) -> Result<(), E> {
+        for (address, bytes) in self.blocks.iter() {
+            self.base.write_bytes(bytes, *address)?;
+        }
+
+        self.base.flush()?;
+        self.blocks.clear();
+
+        return Ok(());
+    }
+
+    /// Throws away the changed blocks, leaving the overlay the same as the base.
+    pub fn discard(&mut self) {
+        self.blocks.clear();
+    }
+
+    /// Returns the copy of the block at an address, reading it from the base if it hasn't been changed yet.
+    /// The last block is shorter if the disk doesn't end on a block boundary.
+    fn block_mut(&mut self, address: u64) -> Result<&mut Vec<u8>, E> {
+        if !self.blocks.contains_key(&address) {
+            let disk_size = self.base.disk_size()?;
+            let length = core::cmp::min(self.block_size, disk_size.saturating_sub(address));
+            let bytes = self.base.read_bytes(address, length)?;
+
+            self.blocks.insert(address, bytes);
+        }
+
+        return Ok(self.blocks.get_mut(&address).unwrap());
+    }
+
+    /// Replaces the bytes from start to end with the bytes given, or with zeroes if there are none.
+    fn write_range(&mut self, start: u64, end: u64, bytes: Option<&[u8]>) -> Result<(), E> {
+        // Reading the range from the base reports a write past its end without changing anything
+        if end > self.base.disk_size()? {
+            self.base.read_bytes(start, end - start)?;
+        }
+
+        let mut position = start;
+
+        while position < end {
+            let address = position - position % self.block_size;
+            let block = self.block_mut(address)?;
+
+            let offset = (position - address) as usize;
+            let length = core::cmp::min(end - position, block.len().saturating_sub(offset) as u64);
+
+            if length == 0 {
+                break;
+            }
+
+            let target = &mut block[offset..offset + length as usize];
+
+            match bytes {
+                Some(b) => {
+                    let source = (position - start) as usize;
+                    target.copy_from_slice(&b[source..source + length as usize]);
+                }
+                None => target.iter_mut().for_each(|byte| *byte = 0),
+            }
+
+            position += length;
+        }
+
+        return Ok(());
+    }
+}
+
+impl<'a, E: VoxFSErrorConvertible> DiskHandler<E> for OverlayDisk<'a, E> {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), E> {
+        return self.write_range(location, location + bytes.len() as u64, Some(bytes));
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, E> {
+        let end = location + amount;
+        let mut result = Vec::with_capacity(amount as usize);
+        let mut position = location;
+
+        while position < end {
+            let address = position - position % self.block_size;
+
+            match self.blocks.get(&address) {
+                Some(block) => {
+                    let offset = (position - address) as usize;
+                    let length = core::cmp::min(end - position, (block.len() - offset) as u64);
+
+                    if length == 0 {
+                        // Past the end of the disk, the base reports the error
+                        return self.base.read_bytes(position, end - position);
+                    }
+
+                    result.extend_from_slice(&block[offset..offset + length as usize]);
+                    position += length;
+                }
+                None => {
+                    // Read up to the next changed block in one go
+                    let next = match self.blocks.range(address..).next() {
+                        Some((a, _)) => core::cmp::min(*a, end),
+                        None => end,
+                    };
+
+                    result.extend(self.base.read_bytes(position, next - position)?);
+                    position = next;
+                }
+            }
+        }
+
+        return Ok(result);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), E> {
+        return self.write_range(start, end, None);
+    }
+
+    fn disk_size(&self) -> Result<u64, E> {
+        return self.base.disk_size();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryDiskError, MemoryDiskHandler};
+
+    #[test]
+    fn test_overlay_read_write() {
+        let mut base = MemoryDiskHandler::from_bytes((0..20).collect());
+
+        {
+            let mut overlay = OverlayDisk::with_block_size(&mut base, 8);
+
+            overlay.write_bytes(&vec![100, 101, 102], 6).unwrap();
+            overlay.zero_range(17, 20).unwrap();
+
+            assert_eq!(overlay.modified_blocks(), 3);
+            assert_eq!(
+                overlay.read_bytes(4, 16).unwrap(),
+                vec![4, 5, 100, 101, 102, 9, 10, 11, 12, 13, 14, 15, 16, 0, 0, 0]
+            );
+            assert_eq!(
+                overlay.write_bytes(&vec![1, 2], 19),
+                Err(MemoryDiskError::OutOfBounds)
+            );
+            assert_eq!(overlay.read_bytes(19, 1).unwrap(), vec![0]);
+
+            overlay.discard();
+            assert!(!overlay.is_modified());
+            assert_eq!(overlay.read_bytes(5, 3).unwrap(), vec![5, 6, 7]);
+        }
+
+        assert_eq!(base.as_bytes().to_vec(), (0..20).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_overlay_commit() {
+        let mut base = MemoryDiskHandler::new(16);
+
+        let mut overlay = OverlayDisk::with_block_size(&mut base, 4);
+        overlay.write_bytes(&vec![1, 2, 3], 3).unwrap();
+        overlay.commit().unwrap();
+        assert!(!overlay.is_modified());
+
+        assert_eq!(
+            base.as_bytes(),
+            &[0, 0, 0, 1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+}
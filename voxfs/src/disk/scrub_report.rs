@@ -0,0 +1,138 @@
+use crate::VoxFSError;
+use alloc::vec::Vec;
+
+/// A region of the disk checked by `Disk::scrub`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrubRegion {
+    SuperBlock,
+    TagTable,
+    INodeTable,
+    /// The mirrored copy of the tag table, if the disk has one.
+    TagMirror,
+    /// The mirrored copy of the inode table, if the disk has one.
+    INodeMirror,
+    IndirectTags,
+    IndirectINodes,
+}
+
+impl ScrubRegion {
+    /// Every region, in the order they are checked.
+    pub const ALL: [ScrubRegion; 7] = [
+        ScrubRegion::SuperBlock,
+        ScrubRegion::TagTable,
+        ScrubRegion::INodeTable,
+        ScrubRegion::TagMirror,
+        ScrubRegion::INodeMirror,
+        ScrubRegion::IndirectTags,
+        ScrubRegion::IndirectINodes,
+    ];
+}
+
+/// A record that failed its checksum or could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrubFailure<E> {
+    region: ScrubRegion,
+    index: u64,
+    address: u64,
+    reason: VoxFSError<E>,
+}
+
+impl<E> ScrubFailure<E> {
+    pub fn region(&self) -> ScrubRegion {
+        return self.region;
+    }
+
+    /// The index of the record, or of the tag or inode that owns an indirect block.
+    pub fn index(&self) -> u64 {
+        return self.index;
+    }
+
+    pub fn address(&self) -> u64 {
+        return self.address;
+    }
+
+    /// Either a corruption error or the error from the disk.
+    pub fn reason(&self) -> &VoxFSError<E> {
+        return &self.reason;
+    }
+
+    /// Whether `Disk::repair_scrub_failure` can rewrite the record. Indirect blocks have no copy in memory to
+    /// rewrite them from.
+    pub fn repairable(&self) -> bool {
+        return !matches!(
+            self.region,
+            ScrubRegion::IndirectTags | ScrubRegion::IndirectINodes
+        );
+    }
+}
+
+/// The result of `Disk::scrub`, the number of records checked in each region and any that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrubReport<E> {
+    checked: [u64; 7],
+    failures: Vec<ScrubFailure<E>>,
+    finished: bool,
+}
+
+impl<E> ScrubReport<E> {
+    pub(crate) fn new() -> Self {
+        return Self {
+            checked: [0; 7],
+            failures: Vec::new(),
+            finished: false,
+        };
+    }
+
+    pub(crate) fn pass(&mut self, region: ScrubRegion) {
+        self.checked[region as usize] += 1;
+    }
+
+    pub(crate) fn fail(
+        &mut self,
+        region: ScrubRegion,
+        index: u64,
+        address: u64,
+        reason: VoxFSError<E>,
+    ) {
+        self.checked[region as usize] += 1;
+        self.failures.push(ScrubFailure {
+            region,
+            index,
+            address,
+            reason,
+        });
+    }
+
+    pub(crate) fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Whether the check reached the end of the disk, always true for `Disk::scrub`.
+    pub fn reached_end(&self) -> bool {
+        return self.finished;
+    }
+
+    /// True if every record checked was intact.
+    pub fn is_clean(&self) -> bool {
+        return self.failures.is_empty();
+    }
+
+    /// The number of records checked in a region.
+    pub fn checked(&self, region: ScrubRegion) -> u64 {
+        return self.checked[region as usize];
+    }
+
+    /// The number of records in a region that failed.
+    pub fn failed(&self, region: ScrubRegion) -> u64 {
+        return self.failures.iter().filter(|f| f.region == region).count() as u64;
+    }
+
+    pub fn failures(&self) -> &Vec<ScrubFailure<E>> {
+        return &self.failures;
+    }
+
+    /// Takes the failures, e.g. to collect them from several calls to `Disk::check_incremental`.
+    pub fn into_failures(self) -> Vec<ScrubFailure<E>> {
+        return self.failures;
+    }
+}
@@ -0,0 +1,27 @@
+use super::INode;
+
+/// The order to list inodes in. Ties are broken by index so the order is always the same for the same files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Index,
+    Name,
+    /// The actual size of the file, smallest first.
+    Size,
+    /// The last modification time, oldest first.
+    ModifiedTime,
+}
+
+impl SortOrder {
+    /// Sorts inodes in place.
+    pub fn sort(self, inodes: &mut [INode]) {
+        match self {
+            SortOrder::Index => inodes.sort_unstable_by_key(|i| i.index()),
+            // Names are built from the stored characters so each is only built once
+            SortOrder::Name => inodes.sort_by_cached_key(|i| (i.name(), i.index())),
+            SortOrder::Size => inodes.sort_unstable_by_key(|i| (i.file_size(), i.index())),
+            SortOrder::ModifiedTime => {
+                inodes.sort_unstable_by_key(|i| (i.modified_time(), i.index()))
+            }
+        }
+    }
+}
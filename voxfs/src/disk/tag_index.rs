@@ -0,0 +1,212 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+/// The members of a tag and where its indirect chain ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagMembers {
+    members: BTreeSet<u64>,
+    // Indexes the tag still lists whose files no longer exist, left by a crash part way through deleting a file.
+    stale: BTreeSet<u64>,
+    // The address of the last indirect block and the number of members in it.
+    tail: Option<(u64, u16)>,
+}
+
+impl TagMembers {
+    pub fn new(members: BTreeSet<u64>, stale: BTreeSet<u64>, tail: Option<(u64, u16)>) -> Self {
+        return Self {
+            members,
+            stale,
+            tail,
+        };
+    }
+
+    pub fn contains(&self, member: u64) -> bool {
+        return self.members.contains(&member);
+    }
+
+    pub fn tail(&self) -> Option<(u64, u16)> {
+        return self.tail;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.members.len();
+    }
+}
+
+/// An in memory index of tag membership, so a tag can be checked and appended to without reading its whole
+/// indirect chain. Tags are loaded the first time they are needed and forgotten when their chain changes shape.
+/// The reverse mapping from an inode to its tags only covers the loaded tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagIndex {
+    tags: BTreeMap<u64, TagMembers>,
+    inodes: BTreeMap<u64, BTreeSet<u64>>,
+}
+
+impl TagIndex {
+    pub fn new() -> Self {
+        return Self {
+            tags: BTreeMap::new(),
+            inodes: BTreeMap::new(),
+        };
+    }
+
+    pub fn get(&self, tag: u64) -> Option<&TagMembers> {
+        return self.tags.get(&tag);
+    }
+
+    pub fn load(&mut self, tag: u64, members: TagMembers) {
+        self.forget(tag);
+
+        for member in members.members.iter() {
+            self.inodes.entry(*member).or_default().insert(tag);
+        }
+
+        self.tags.insert(tag, members);
+    }
+
+    /// The loaded tags that have the inode as a member.
+    pub fn tags_for(&self, inode: u64) -> Vec<u64> {
+        return match self.inodes.get(&inode) {
+            Some(tags) => tags.iter().cloned().collect(),
+            None => Vec::new(),
+        };
+    }
+
+    /// The loaded tags that still list an inode whose file no longer exists.
+    pub fn stale_tags(&self, inode: u64) -> Vec<u64> {
+        return self
+            .tags
+            .iter()
+            .filter(|(_, members)| members.stale.contains(&inode))
+            .map(|(tag, _)| *tag)
+            .collect();
+    }
+
+    /// Whether any loaded tag has the inode as a member.
+    pub fn is_tagged(&self, inode: u64) -> bool {
+        return self.inodes.contains_key(&inode);
+    }
+
+    /// Records a new member of a loaded tag along with where the chain now ends.
+    pub fn insert_member(&mut self, tag: u64, member: u64, tail: Option<(u64, u16)>) {
+        if let Some(entry) = self.tags.get_mut(&tag) {
+            entry.members.insert(member);
+            entry.tail = tail;
+
+            self.inodes.entry(member).or_default().insert(tag);
+        }
+    }
+
+    /// Records that a member was removed from a loaded tag, from the indirect block at the address if any.
+    pub fn remove_member(&mut self, tag: u64, member: u64, address: Option<u64>) {
+        if let Some(entry) = self.tags.get_mut(&tag) {
+            entry.members.remove(&member);
+            entry.stale.remove(&member);
+
+            if let (Some(address), Some((tail, count))) = (address, entry.tail) {
+                if address == tail {
+                    entry.tail = Some((tail, count - 1));
+                }
+            }
+
+            self.remove_reverse(tag, member);
+        }
+    }
+
+    /// Drops a tag so it is loaded from the disk again the next time it is needed.
+    pub fn forget(&mut self, tag: u64) {
+        if let Some(entry) = self.tags.remove(&tag) {
+            for member in entry.members.iter() {
+                self.remove_reverse(tag, *member);
+            }
+        }
+    }
+
+    fn remove_reverse(&mut self, tag: u64, member: u64) {
+        if let Some(tags) = self.inodes.get_mut(&member) {
+            tags.remove(&tag);
+
+            if tags.is_empty() {
+                self.inodes.remove(&member);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_tracking() {
+        let mut index = TagIndex::new();
+        index.insert_member(1, 5, None);
+        assert!(index.get(1).is_none());
+
+        index.load(
+            1,
+            TagMembers::new(BTreeSet::new(), BTreeSet::new(), Some((4096, 3))),
+        );
+        index.insert_member(1, 5, Some((4096, 4)));
+        assert!(index.get(1).unwrap().contains(5));
+
+        // Only removals from the tail block change its count
+        index.remove_member(1, 5, Some(8192));
+        assert_eq!(index.get(1).unwrap().tail(), Some((4096, 4)));
+        index.remove_member(1, 6, Some(4096));
+        assert_eq!(index.get(1).unwrap().tail(), Some((4096, 3)));
+        assert!(!index.get(1).unwrap().contains(5));
+
+        index.forget(1);
+        assert!(index.get(1).is_none());
+    }
+
+    #[test]
+    fn test_reverse_mapping() {
+        let mut index = TagIndex::new();
+
+        index.load(
+            1,
+            TagMembers::new(vec![10, 11].into_iter().collect(), BTreeSet::new(), None),
+        );
+        index.load(
+            2,
+            TagMembers::new(vec![11].into_iter().collect(), BTreeSet::new(), None),
+        );
+        assert_eq!(index.tags_for(11), vec![1, 2]);
+        assert!(!index.is_tagged(12));
+
+        index.insert_member(2, 10, None);
+        index.remove_member(1, 11, None);
+        assert_eq!(index.tags_for(10), vec![1, 2]);
+        assert_eq!(index.tags_for(11), vec![2]);
+
+        // Reloading replaces the old members
+        index.load(2, TagMembers::new(BTreeSet::new(), BTreeSet::new(), None));
+        assert_eq!(index.tags_for(10), vec![1]);
+
+        index.forget(1);
+        assert!(index.tags_for(10).is_empty());
+        assert!(!index.is_tagged(10));
+    }
+
+    #[test]
+    fn test_stale_members() {
+        let mut index = TagIndex::new();
+        let stale: BTreeSet<u64> = vec![12].into_iter().collect();
+
+        // Stale members are not counted or mapped back to their tags
+        index.load(
+            1,
+            TagMembers::new(vec![10].into_iter().collect(), stale, None),
+        );
+        assert_eq!(index.get(1).unwrap().len(), 1);
+        assert!(!index.get(1).unwrap().contains(12));
+        assert!(!index.is_tagged(12));
+        assert_eq!(index.stale_tags(12), vec![1]);
+
+        index.remove_member(1, 12, None);
+        assert!(index.stale_tags(12).is_empty());
+        assert_eq!(index.get(1).unwrap().len(), 1);
+    }
+}
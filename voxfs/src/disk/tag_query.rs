@@ -0,0 +1,274 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// A boolean expression over tag names, used to search for files.
+///
+/// Tags are combined with `&` (and), `|` (or) and `!` (not), with `!` binding tightest and `&` binding
+/// tighter than `|`. Parentheses group expressions and names containing spaces or operators can be quoted,
+/// e.g. `work & !(archived | "old projects")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagQuery {
+    Tag(String),
+    Not(Box<TagQuery>),
+    And(Box<TagQuery>, Box<TagQuery>),
+    Or(Box<TagQuery>, Box<TagQuery>),
+}
+
+/// Why a tag query could not be parsed, with the character position the problem was found at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagQueryError {
+    position: usize,
+    reason: &'static str,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl TagQuery {
+    pub fn parse(query: &str) -> Result<Self, TagQueryError> {
+        let mut parser = Parser {
+            chars: query.chars().collect(),
+            position: 0,
+        };
+
+        let result = parser.parse_or()?;
+        parser.skip_whitespace();
+
+        if parser.position != parser.chars.len() {
+            return Err(parser.error("Unexpected character"));
+        }
+
+        return Ok(result);
+    }
+
+    /// The names of the tags the query refers to, without duplicates.
+    pub fn tag_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+
+        return names;
+    }
+
+    fn collect_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            TagQuery::Tag(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            TagQuery::Not(query) => query.collect_names(names),
+            TagQuery::And(left, right) | TagQuery::Or(left, right) => {
+                left.collect_names(names);
+                right.collect_names(names);
+            }
+        }
+    }
+
+    /// Whether a file with the given tags matches the query.
+    pub fn matches<F: Fn(&str) -> bool>(&self, has_tag: &F) -> bool {
+        return match self {
+            TagQuery::Tag(name) => has_tag(name),
+            TagQuery::Not(query) => !query.matches(has_tag),
+            TagQuery::And(left, right) => left.matches(has_tag) && right.matches(has_tag),
+            TagQuery::Or(left, right) => left.matches(has_tag) || right.matches(has_tag),
+        };
+    }
+}
+
+impl TagQueryError {
+    pub fn position(&self) -> usize {
+        return self.position;
+    }
+
+    pub fn reason(&self) -> &'static str {
+        return self.reason;
+    }
+}
+
+impl Display for TagQueryError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        return write!(f, "{} at position {}", self.reason, self.position);
+    }
+}
+
+impl Parser {
+    fn error(&self, reason: &'static str) -> TagQueryError {
+        return TagQueryError {
+            position: self.position,
+            reason,
+        };
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.position).cloned();
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+
+            self.position += 1;
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<TagQuery, TagQueryError> {
+        let mut left = self.parse_and()?;
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() != Some('|') {
+                return Ok(left);
+            }
+
+            self.position += 1;
+            left = TagQuery::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<TagQuery, TagQueryError> {
+        let mut left = self.parse_not()?;
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() != Some('&') {
+                return Ok(left);
+            }
+
+            self.position += 1;
+            left = TagQuery::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<TagQuery, TagQueryError> {
+        self.skip_whitespace();
+
+        if self.peek() == Some('!') {
+            self.position += 1;
+            return Ok(TagQuery::Not(Box::new(self.parse_not()?)));
+        }
+
+        return self.parse_primary();
+    }
+
+    fn parse_primary(&mut self) -> Result<TagQuery, TagQueryError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let query = self.parse_or()?;
+                self.skip_whitespace();
+
+                if self.peek() != Some(')') {
+                    return Err(self.error("Expected a closing parenthesis"));
+                }
+
+                self.position += 1;
+                return Ok(query);
+            }
+            Some('"') => {
+                let start = self.position;
+                self.position += 1;
+                let mut name = String::new();
+
+                loop {
+                    match self.peek() {
+                        Some('"') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            self.position = start;
+                            return Err(self.error("Unterminated quoted tag name"));
+                        }
+                    }
+
+                    self.position += 1;
+                }
+
+                self.position += 1;
+
+                if name.is_empty() {
+                    self.position = start;
+                    return Err(self.error("Expected a tag name"));
+                }
+
+                return Ok(TagQuery::Tag(name));
+            }
+            _ => {
+                let mut name = String::new();
+
+                while let Some(c) = self.peek() {
+                    if c.is_whitespace() || "&|!()\"".contains(c) {
+                        break;
+                    }
+
+                    name.push(c);
+                    self.position += 1;
+                }
+
+                if name.is_empty() {
+                    return Err(self.error("Expected a tag name"));
+                }
+
+                return Ok(TagQuery::Tag(name));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> Box<TagQuery> {
+        return Box::new(TagQuery::Tag(String::from(name)));
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            TagQuery::parse("a | b & !c").unwrap(),
+            TagQuery::Or(
+                tag("a"),
+                Box::new(TagQuery::And(tag("b"), Box::new(TagQuery::Not(tag("c")))))
+            )
+        );
+
+        assert_eq!(
+            TagQuery::parse("(a | b) & c").unwrap(),
+            TagQuery::And(Box::new(TagQuery::Or(tag("a"), tag("b"))), tag("c"))
+        );
+    }
+
+    #[test]
+    fn test_quoted_names() {
+        let query = TagQuery::parse("\"old projects\" & !a&b").unwrap();
+
+        assert_eq!(query.tag_names(), vec!["old projects", "a", "b"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(TagQuery::parse("").unwrap_err().position(), 0);
+        assert_eq!(TagQuery::parse("a &").unwrap_err().position(), 3);
+        assert_eq!(TagQuery::parse("(a | b").unwrap_err().position(), 6);
+        assert_eq!(TagQuery::parse("a b").unwrap_err().position(), 2);
+        assert_eq!(TagQuery::parse("a & \"b").unwrap_err().position(), 4);
+    }
+
+    #[test]
+    fn test_matches() {
+        let query = TagQuery::parse("work & !(archived | old)").unwrap();
+
+        assert!(query.matches(&|t| t == "work"));
+        assert!(!query.matches(&|t| t == "work" || t == "old"));
+        assert!(!query.matches(&|_| false));
+    }
+}
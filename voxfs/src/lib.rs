@@ -1,19 +1,45 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
 #[allow(unused_imports)] // We use alloc's 'format!' macro but for some reason it raises a warning about an unused import.
 #[macro_use]
 extern crate alloc;
 
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
+#[cfg(feature = "alloc")]
 mod bitmap;
+#[cfg(feature = "alloc")]
 mod byte_serializable;
+#[cfg(feature = "alloc")]
 mod checksum_trait;
+#[cfg(feature = "alloc")]
 mod disk;
+#[cfg(feature = "alloc")]
 mod manager;
+#[cfg(feature = "alloc")]
+mod probe;
+pub mod raw;
+#[cfg(feature = "alloc")]
 mod utils;
+#[cfg(feature = "alloc")]
+pub mod volumes;
+#[cfg(feature = "alloc")]
 mod voxfs_error;
 
+#[cfg(feature = "alloc")]
 pub use byte_serializable::ByteSerializable;
+#[cfg(feature = "alloc")]
 pub use checksum_trait::Checksum;
+#[cfg(feature = "alloc")]
 pub use disk::*;
-pub use manager::OSManager;
+#[cfg(feature = "alloc")]
+pub use manager::{
+    timestamp_from_unix, timestamp_to_unix, ContentHasher, OSManager, Timestamp,
+    MAX_TIMESTAMP_SECONDS, MIN_TIMESTAMP_SECONDS,
+};
+#[cfg(feature = "alloc")]
+pub use probe::{probe, ProbeInfo};
+#[cfg(feature = "alloc")]
 pub use voxfs_error::{VoxFSError, VoxFSErrorConvertible};
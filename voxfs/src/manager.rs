@@ -1,8 +1,189 @@
-use chrono::DateTime;
-use chrono::Utc;
+use alloc::boxed::Box;
+use alloc::string::String;
+#[cfg(feature = "timestamps")]
+use chrono::{DateTime, TimeZone, Utc};
 use core::fmt::Debug;
 
+/// A point in time as used by inodes and tags. With the `timestamps` feature this is a `DateTime<Utc>`, without it
+/// this is the raw 64 bit time as stored on disk, made with `timestamp_from_unix`.
+#[cfg(feature = "timestamps")]
+pub type Timestamp = DateTime<Utc>;
+
+/// A point in time as used by inodes and tags. With the `timestamps` feature this is a `DateTime<Utc>`, without it
+/// this is the raw 64 bit time as stored on disk, made with `timestamp_from_unix`.
+#[cfg(not(feature = "timestamps"))]
+pub type Timestamp = u64;
+
+// Times are stored in 64 bits, the nanoseconds in the low 30 bits and the seconds since TIME_EPOCH_OFFSET seconds
+// before the unix epoch in the high 34 bits. Before format version 1 they were nanoseconds since the unix epoch,
+// which can't hold times before 1970 or after 2262.
+const NANOS_BITS: u32 = 30;
+const TIME_EPOCH_OFFSET: i64 = 1 << 31;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// The earliest time that can be stored, 1901-12-13 20:45:52 UTC, in seconds since the unix epoch.
+pub const MIN_TIMESTAMP_SECONDS: i64 = -TIME_EPOCH_OFFSET;
+/// The latest time that can be stored, 2446-05-10 22:38:55 UTC, in seconds since the unix epoch.
+pub const MAX_TIMESTAMP_SECONDS: i64 = (1 << (64 - NANOS_BITS)) - 1 - TIME_EPOCH_OFFSET;
+
 /// Provide OS specific methods
 pub trait OSManager: Debug {
-    fn current_time(&self) -> DateTime<Utc>;
+    fn current_time(&self) -> Timestamp;
+
+    /// The id of the user making changes, recorded in the history.
+    fn user_id(&self) -> u32 {
+        return 0;
+    }
+
+    /// The name of the user making changes, recorded in the history.
+    fn user_name(&self) -> String {
+        return String::new();
+    }
+
+    /// A hasher for the content hashes of a filesystem formatted with them, such as SHA-256 or BLAKE3. Without
+    /// one the hashes are cleared when files change and `Disk::file_hash` returns None.
+    fn content_hasher(&self) -> Option<Box<dyn ContentHasher>> {
+        return None;
+    }
+}
+
+/// Hashes the contents of a file a piece at a time, the pieces joined are the whole file.
+pub trait ContentHasher {
+    fn update(&mut self, bytes: &[u8]);
+
+    fn finish(self: Box<Self>) -> [u8; 32];
+}
+
+/// Makes a timestamp from the seconds since the unix epoch, negative before it, and the nanoseconds past that second.
+/// Times outside of `MIN_TIMESTAMP_SECONDS` to `MAX_TIMESTAMP_SECONDS` are clamped to the range.
+#[cfg(feature = "timestamps")]
+pub fn timestamp_from_unix(seconds: i64, nanos: u32) -> Timestamp {
+    let (seconds, nanos) = decode_time(encode_time(seconds, nanos));
+
+    return Utc.timestamp(seconds, nanos);
+}
+
+/// Makes a timestamp from the seconds since the unix epoch, negative before it, and the nanoseconds past that second.
+/// Times outside of `MIN_TIMESTAMP_SECONDS` to `MAX_TIMESTAMP_SECONDS` are clamped to the range.
+#[cfg(not(feature = "timestamps"))]
+pub fn timestamp_from_unix(seconds: i64, nanos: u32) -> Timestamp {
+    return encode_time(seconds, nanos);
+}
+
+/// The seconds since the unix epoch, negative before it, and the nanoseconds past that second.
+#[cfg(feature = "timestamps")]
+pub fn timestamp_to_unix(time: Timestamp) -> (i64, u32) {
+    return (time.timestamp(), time.timestamp_subsec_nanos());
+}
+
+/// The seconds since the unix epoch, negative before it, and the nanoseconds past that second.
+#[cfg(not(feature = "timestamps"))]
+pub fn timestamp_to_unix(time: Timestamp) -> (i64, u32) {
+    return decode_time(time);
+}
+
+/// Converts a timestamp into the time stored on disk, clamping it to the range that can be stored.
+pub(crate) fn timestamp_to_disk(time: Timestamp) -> u64 {
+    let (seconds, nanos) = timestamp_to_unix(time);
+
+    return encode_time(seconds, nanos);
+}
+
+/// Converts the time stored on disk into a timestamp.
+pub(crate) fn disk_to_timestamp(time: u64) -> Timestamp {
+    let (seconds, nanos) = decode_time(time);
+
+    return timestamp_from_unix(seconds, nanos);
+}
+
+/// Converts a time stored by a disk older than format version 1 into the current encoding. These were the
+/// nanoseconds since the epoch written as a u64, so times before 1970 wrapped around and are read back as an i64.
+pub(crate) fn legacy_time_to_disk(time: u64) -> u64 {
+    let nanos = time as i64;
+
+    return encode_time(
+        nanos.div_euclid(NANOS_PER_SECOND),
+        nanos.rem_euclid(NANOS_PER_SECOND) as u32,
+    );
+}
+
+fn encode_time(seconds: i64, nanos: u32) -> u64 {
+    if seconds < MIN_TIMESTAMP_SECONDS {
+        return 0;
+    } else if seconds > MAX_TIMESTAMP_SECONDS {
+        return encode_time(MAX_TIMESTAMP_SECONDS, NANOS_PER_SECOND as u32 - 1);
+    }
+
+    let nanos = core::cmp::min(nanos, NANOS_PER_SECOND as u32 - 1);
+
+    return ((seconds + TIME_EPOCH_OFFSET) as u64) << NANOS_BITS | nanos as u64;
+}
+
+fn decode_time(time: u64) -> (i64, u32) {
+    let seconds = (time >> NANOS_BITS) as i64 - TIME_EPOCH_OFFSET;
+    // A corrupted time can hold more nanoseconds than there are in a second
+    let nanos = core::cmp::min(
+        (time & ((1 << NANOS_BITS) - 1)) as u32,
+        NANOS_PER_SECOND as u32 - 1,
+    );
+
+    return (seconds, nanos);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_round_trip() {
+        for (seconds, nanos) in [
+            (0, 0),
+            (1_600_000_000, 123_456_789),
+            (-86_400 * 365 * 50, 5),
+            (MIN_TIMESTAMP_SECONDS, 0),
+            (MAX_TIMESTAMP_SECONDS, 999_999_999),
+            // Past the end of nanoseconds stored in a u64
+            (9_300_000_000, 1),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                decode_time(encode_time(*seconds, *nanos)),
+                (*seconds, *nanos)
+            );
+            assert_eq!(
+                timestamp_to_unix(disk_to_timestamp(encode_time(*seconds, *nanos))),
+                (*seconds, *nanos)
+            );
+        }
+
+        // Times sort in the same order as their encodings
+        assert!(encode_time(-1, 999_999_999) < encode_time(0, 0));
+    }
+
+    #[test]
+    fn test_time_clamped() {
+        assert_eq!(
+            decode_time(encode_time(i64::MIN, 10)),
+            (MIN_TIMESTAMP_SECONDS, 0)
+        );
+        assert_eq!(
+            decode_time(encode_time(i64::MAX, 0)),
+            (MAX_TIMESTAMP_SECONDS, 999_999_999)
+        );
+        assert_eq!(decode_time(encode_time(0, u32::MAX)), (0, 999_999_999));
+    }
+
+    #[test]
+    fn test_legacy_time() {
+        assert_eq!(
+            decode_time(legacy_time_to_disk(1_600_000_000_123_456_789)),
+            (1_600_000_000, 123_456_789)
+        );
+        // One nanosecond before the epoch was stored as u64::MAX
+        assert_eq!(
+            decode_time(legacy_time_to_disk(-1i64 as u64)),
+            (-1, 999_999_999)
+        );
+    }
 }
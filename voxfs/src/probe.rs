@@ -0,0 +1,63 @@
+use crate::disk::{FilesystemState, SuperBlock};
+use crate::{ByteSerializable, DiskHandler, VoxFSErrorConvertible};
+use alloc::string::String;
+
+/// The identifying details of a voxfs image, read by `probe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeInfo {
+    version: u8,
+    block_size: u64,
+    uuid: [u8; 16],
+    label: String,
+    state: FilesystemState,
+}
+
+impl ProbeInfo {
+    /// The version of the filesystem format.
+    pub fn version(&self) -> u8 {
+        return self.version;
+    }
+
+    pub fn block_size(&self) -> u64 {
+        return self.block_size;
+    }
+
+    pub fn uuid(&self) -> [u8; 16] {
+        return self.uuid;
+    }
+
+    /// The filesystem's label, empty if it has none.
+    pub fn label(&self) -> &str {
+        return &self.label;
+    }
+
+    /// Whether the filesystem was closed cleanly.
+    pub fn state(&self) -> FilesystemState {
+        return self.state;
+    }
+}
+
+/// Checks whether a disk holds a voxfs filesystem by reading only its super block.
+/// Returns None for anything that is not a valid voxfs image, including errors reading the disk.
+pub fn probe<E: VoxFSErrorConvertible>(handler: &dyn DiskHandler<E>) -> Option<ProbeInfo> {
+    let disk_size = handler.disk_size().ok()?;
+
+    if disk_size < SuperBlock::size() {
+        return None;
+    }
+
+    let bytes = handler.read_bytes(0, SuperBlock::size()).ok()?;
+    let super_block = SuperBlock::from_bytes(&bytes)?;
+
+    if !super_block.is_layout_valid(disk_size) {
+        return None;
+    }
+
+    return Some(ProbeInfo {
+        version: super_block.version(),
+        block_size: super_block.block_size(),
+        uuid: super_block.uuid(),
+        label: super_block.label(),
+        state: super_block.state(),
+    });
+}
@@ -0,0 +1,342 @@
+//! A minimal read only view of a voxfs image that never allocates, for boot loaders and other early environments
+//! without a heap. It reads the super block and inodes straight from the disk and streams file data into buffers
+//! provided by the caller. The block size is a const parameter so the only scratch space needed, one block for
+//! reading indirect inodes, lives on the stack.
+
+use byteorder::{ByteOrder, LittleEndian};
+use core::fmt::Display;
+
+const MAGIC: u32 = 0xa1df5000;
+const SUPER_BLOCK_SIZE: usize = 128;
+const INODE_SIZE: usize = 256;
+const MAX_INODE_NAME_LENGTH: usize = 125;
+const INODE_EXTENT_COUNT: usize = 5;
+const EXTENT_SIZE: usize = 16;
+const INDIRECT_INODE_HEADER_SIZE: usize = 11;
+
+/// Reads bytes from the underlying disk into a buffer.
+pub trait RawReader<E> {
+    /// Fills the buffer with the bytes starting at the location.
+    fn read_into(&self, location: u64, buffer: &mut [u8]) -> Result<(), E>;
+
+    /// The size of the disk in bytes.
+    fn disk_size(&self) -> Result<u64, E>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawError<E> {
+    CorruptedSuperBlock,
+    BlockSizeMismatch,
+    CouldNotFindINode,
+    CorruptedINode,
+    CorruptedIndirectINode,
+    DiskError(E),
+}
+
+impl<E: Display> Display for RawError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return match self {
+            RawError::CorruptedSuperBlock => write!(f, "CorruptedSuperBlock"),
+            RawError::BlockSizeMismatch => write!(f, "BlockSizeMismatch"),
+            RawError::CouldNotFindINode => write!(f, "CouldNotFindINode"),
+            RawError::CorruptedINode => write!(f, "CorruptedINode"),
+            RawError::CorruptedIndirectINode => write!(f, "CorruptedIndirectINode"),
+            RawError::DiskError(e) => write!(f, "Disk error: {}", e),
+        };
+    }
+}
+
+/// A run of data blocks, inclusive of both ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RawExtent {
+    start: u64,
+    end: u64,
+}
+
+/// The fields of an inode needed to locate and read its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawINode {
+    index: u64,
+    name: [u8; MAX_INODE_NAME_LENGTH],
+    size: u64,
+    indirect_block: u64,
+    num_extents: u8,
+    blocks: [RawExtent; INODE_EXTENT_COUNT],
+}
+
+impl RawINode {
+    /// Parses an inode, verifying its checksum.
+    fn from_bytes(bytes: &[u8; INODE_SIZE]) -> Option<Self> {
+        let mut sum = 0u8;
+
+        for b in bytes.iter() {
+            sum = sum.wrapping_add(*b);
+        }
+
+        if sum != 0 || bytes[175] as usize > INODE_EXTENT_COUNT {
+            return None;
+        }
+
+        let mut name = [0u8; MAX_INODE_NAME_LENGTH];
+        name.copy_from_slice(&bytes[8..8 + MAX_INODE_NAME_LENGTH]);
+
+        let mut blocks = [RawExtent { start: 0, end: 0 }; INODE_EXTENT_COUNT];
+
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let offset = 176 + i * EXTENT_SIZE;
+            block.start = LittleEndian::read_u64(&bytes[offset..]);
+            block.end = LittleEndian::read_u64(&bytes[offset + 8..]);
+        }
+
+        return Some(Self {
+            index: LittleEndian::read_u64(&bytes[0..]),
+            name,
+            size: LittleEndian::read_u64(&bytes[133..]),
+            indirect_block: LittleEndian::read_u64(&bytes[167..]),
+            num_extents: bytes[175],
+            blocks,
+        });
+    }
+
+    pub fn index(&self) -> u64 {
+        return self.index;
+    }
+
+    /// The name as stored on disk without the trailing padding.
+    pub fn name(&self) -> &[u8] {
+        let length = self
+            .name
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(MAX_INODE_NAME_LENGTH);
+
+        return &self.name[..length];
+    }
+
+    pub fn file_size(&self) -> u64 {
+        return self.size;
+    }
+}
+
+/// Tracks the progress of copying a range of a file into a buffer.
+struct Cursor<'b> {
+    offset: u64,
+    end: u64,
+    position: u64,
+    copied: usize,
+    buffer: &'b mut [u8],
+}
+
+/// A read only view of a voxfs image with a block size of `BLOCK_SIZE` bytes.
+pub struct RawDisk<'a, E, const BLOCK_SIZE: usize> {
+    reader: &'a dyn RawReader<E>,
+
+    inode_count: u64,
+    block_count: u64,
+    tag_count: u64,
+    boot_area_blocks: u64,
+    inode_start_address: u64,
+    data_start_address: u64,
+}
+
+impl<'a, E, const BLOCK_SIZE: usize> RawDisk<'a, E, BLOCK_SIZE> {
+    /// Reads and validates the super block.
+    pub fn open(reader: &'a dyn RawReader<E>) -> Result<Self, RawError<E>> {
+        let mut bytes = [0u8; SUPER_BLOCK_SIZE];
+        reader
+            .read_into(0, &mut bytes)
+            .map_err(RawError::DiskError)?;
+        let disk_size = reader.disk_size().map_err(RawError::DiskError)?;
+
+        let mut sum = 0u8;
+
+        for b in bytes.iter() {
+            sum = sum.wrapping_add(*b);
+        }
+
+        if sum != 0 || LittleEndian::read_u32(&bytes[0..]) & 0xffff_ff00 != MAGIC {
+            return Err(RawError::CorruptedSuperBlock);
+        }
+
+        if LittleEndian::read_u64(&bytes[4..]) != BLOCK_SIZE as u64 {
+            return Err(RawError::BlockSizeMismatch);
+        }
+
+        let disk = Self {
+            reader,
+            tag_count: LittleEndian::read_u64(&bytes[12..]),
+            inode_count: LittleEndian::read_u64(&bytes[20..]),
+            block_count: LittleEndian::read_u64(&bytes[28..]),
+            inode_start_address: LittleEndian::read_u64(&bytes[44..]),
+            data_start_address: LittleEndian::read_u64(&bytes[52..]),
+            boot_area_blocks: LittleEndian::read_u16(&bytes[61..]) as u64,
+        };
+
+        let inodes_end = disk
+            .inode_count
+            .checked_mul(INODE_SIZE as u64)
+            .and_then(|s| s.checked_add(disk.inode_start_address));
+
+        let inodes_fit = match inodes_end {
+            Some(end) => end <= disk.data_start_address && disk.data_start_address <= disk_size,
+            None => false,
+        };
+
+        if !inodes_fit || disk.block_count > disk_size / BLOCK_SIZE as u64 {
+            return Err(RawError::CorruptedSuperBlock);
+        }
+
+        return Ok(disk);
+    }
+
+    /// The number of inode slots on the disk.
+    pub fn inode_count(&self) -> u64 {
+        return self.inode_count;
+    }
+
+    /// Reads the inode at an index, failing if the slot is not in use.
+    pub fn inode(&self, index: u64) -> Result<RawINode, RawError<E>> {
+        if index >= self.inode_count {
+            return Err(RawError::CouldNotFindINode);
+        }
+
+        // The inode bitmap follows the super block, the boot area and the tag bitmap
+        let bits_per_block = BLOCK_SIZE as u64 * 8;
+        let tag_map_blocks = match self.tag_count % bits_per_block {
+            0 => self.tag_count / bits_per_block,
+            _ => self.tag_count / bits_per_block + 1,
+        };
+        let bitmap_address =
+            BLOCK_SIZE as u64 * (1 + self.boot_area_blocks + tag_map_blocks) + (index / 64) * 8;
+
+        let mut word = [0u8; 8];
+        self.read(bitmap_address, &mut word)?;
+
+        if (LittleEndian::read_u64(&word) >> (index % 64)) & 1 == 0 {
+            return Err(RawError::CouldNotFindINode);
+        }
+
+        let mut bytes = [0u8; INODE_SIZE];
+        self.read(
+            self.inode_start_address + index * INODE_SIZE as u64,
+            &mut bytes,
+        )?;
+
+        return match RawINode::from_bytes(&bytes) {
+            Some(inode) => Ok(inode),
+            None => Err(RawError::CorruptedINode),
+        };
+    }
+
+    /// Copies the file's contents starting at an offset into the buffer, returning how many bytes were copied.
+    /// This is fewer than the buffer's length only when the end of the file is reached.
+    pub fn read_file(
+        &self,
+        inode: &RawINode,
+        offset: u64,
+        buffer: &mut [u8],
+    ) -> Result<usize, RawError<E>> {
+        if offset >= inode.size {
+            return Ok(0);
+        }
+
+        let mut cursor = Cursor {
+            offset,
+            end: core::cmp::min(inode.size, offset.saturating_add(buffer.len() as u64)),
+            position: 0,
+            copied: 0,
+            buffer,
+        };
+
+        for extent in inode.blocks[..inode.num_extents as usize].iter() {
+            if self.copy_extent(*extent, &mut cursor)? {
+                return Ok(cursor.copied);
+            }
+        }
+
+        let mut next = inode.indirect_block;
+        let mut block = [0u8; BLOCK_SIZE];
+        let mut visited = 0;
+
+        while next != 0 {
+            // Guard against a chain that loops back on itself
+            visited += 1;
+
+            if visited > self.block_count || next < self.data_start_address {
+                return Err(RawError::CorruptedIndirectINode);
+            }
+
+            self.read(next, &mut block)?;
+
+            let num_extents = LittleEndian::read_u16(&block[10..]) as usize;
+            let length = INDIRECT_INODE_HEADER_SIZE + num_extents * EXTENT_SIZE;
+
+            if length > BLOCK_SIZE {
+                return Err(RawError::CorruptedIndirectINode);
+            }
+
+            let mut sum = 0u8;
+
+            for b in block[..length].iter() {
+                sum = sum.wrapping_add(*b);
+            }
+
+            if sum != 0 {
+                return Err(RawError::CorruptedIndirectINode);
+            }
+
+            for i in 0..num_extents {
+                let extent_offset = INDIRECT_INODE_HEADER_SIZE + i * EXTENT_SIZE;
+                let extent = RawExtent {
+                    start: LittleEndian::read_u64(&block[extent_offset..]),
+                    end: LittleEndian::read_u64(&block[extent_offset + 8..]),
+                };
+
+                if self.copy_extent(extent, &mut cursor)? {
+                    return Ok(cursor.copied);
+                }
+            }
+
+            next = LittleEndian::read_u64(&block[2..]);
+        }
+
+        // The extents did not cover the file's size
+        return Err(RawError::CorruptedINode);
+    }
+
+    /// Copies the part of an extent that overlaps the cursor's range, returning true once the end is reached.
+    fn copy_extent(&self, extent: RawExtent, cursor: &mut Cursor) -> Result<bool, RawError<E>> {
+        if extent.end < extent.start || extent.end >= self.block_count {
+            return Err(RawError::CorruptedINode);
+        }
+
+        let extent_size = (extent.end - extent.start + 1) * BLOCK_SIZE as u64;
+        let extent_end = cursor.position + extent_size;
+
+        if extent_end > cursor.offset {
+            let from = core::cmp::max(cursor.offset, cursor.position);
+            let to = core::cmp::min(cursor.end, extent_end);
+            let address = self.data_start_address
+                + extent.start * BLOCK_SIZE as u64
+                + (from - cursor.position);
+            let length = (to - from) as usize;
+
+            self.read(
+                address,
+                &mut cursor.buffer[cursor.copied..cursor.copied + length],
+            )?;
+            cursor.copied += length;
+        }
+
+        cursor.position = extent_end;
+
+        return Ok(cursor.position >= cursor.end);
+    }
+
+    fn read(&self, location: u64, buffer: &mut [u8]) -> Result<(), RawError<E>> {
+        return self
+            .reader
+            .read_into(location, buffer)
+            .map_err(RawError::DiskError);
+    }
+}
@@ -14,10 +14,43 @@ pub fn rightmost_set_bit(n: u64) -> usize {
     return index;
 }
 
+/// Derives a version 4 style UUID from a seed. There is no source of randomness without std so the seed should
+/// be something that differs between filesystems, such as the creation time.
+pub fn generate_uuid(seed: u64) -> [u8; 16] {
+    // splitmix64
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        return z ^ (z >> 31);
+    };
+
+    let mut uuid = [0u8; 16];
+    uuid[..8].copy_from_slice(&next().to_le_bytes());
+    uuid[8..].copy_from_slice(&next().to_le_bytes());
+
+    // Set the version and variant bits
+    uuid[6] = (uuid[6] & 0x0f) | 0x40;
+    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+
+    return uuid;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    pub fn test_generate_uuid() {
+        let uuid = generate_uuid(1);
+
+        assert_eq!(uuid[6] >> 4, 4);
+        assert_eq!(uuid[8] >> 6, 0b10);
+        assert_ne!(uuid, generate_uuid(2));
+    }
+
     #[test]
     pub fn test_set_bit_1() {
         let n = 0b01001;
@@ -0,0 +1,379 @@
+//! An optional volume table that splits a single image into several independent voxfs filesystems.
+//!
+//! The table occupies the first `VOLUME_TABLE_SIZE` bytes of the image and records a name, start and size for each
+//! volume. Every volume starts on a block boundary and is formatted and opened like a whole disk through a
+//! `VolumeHandler`, which translates addresses and keeps accesses inside the volume.
+//!
+//! Table layout:
+//! magic (4 bytes), volume count (2 bytes), checksum (1 byte), reserved (1 byte), then the entries.
+//! Each entry is a nul padded name (32 bytes), the start address (8 bytes) and the size in bytes (8 bytes).
+
+use crate::{DiskHandler, VoxFSErrorConvertible};
+use alloc::string::String;
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LittleEndian};
+use core::fmt::Display;
+
+const MAGIC: u32 = 0xa1df7600;
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 48;
+
+/// The size in bytes reserved for the table at the start of the image. Volumes are aligned to this size.
+pub const VOLUME_TABLE_SIZE: u64 = 4_096;
+/// The maximum length in bytes of a volume's name.
+pub const MAX_VOLUME_NAME_LENGTH: usize = 32;
+/// The maximum number of volumes a table can hold.
+pub const MAX_VOLUMES: usize = (VOLUME_TABLE_SIZE as usize - HEADER_SIZE) / ENTRY_SIZE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeError<E> {
+    /// The image does not start with a valid volume table.
+    CorruptedVolumeTable,
+    InvalidVolumeName,
+    VolumeExistsWithName(String),
+    CouldNotFindVolume(String),
+    TooManyVolumes,
+    /// The volumes do not fit within the image.
+    NotEnoughSpace,
+    /// A read or write went past the end of the volume.
+    OutOfBounds,
+    DiskError(E),
+}
+
+impl<E: VoxFSErrorConvertible> VoxFSErrorConvertible for VolumeError<E> {}
+
+impl<E: Display> Display for VolumeError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return match self {
+            VolumeError::CorruptedVolumeTable => write!(f, "CorruptedVolumeTable"),
+            VolumeError::InvalidVolumeName => write!(f, "InvalidVolumeName"),
+            VolumeError::VolumeExistsWithName(n) => write!(f, "VolumeExistsWithName({})", n),
+            VolumeError::CouldNotFindVolume(n) => write!(f, "CouldNotFindVolume({})", n),
+            VolumeError::TooManyVolumes => write!(f, "TooManyVolumes"),
+            VolumeError::NotEnoughSpace => write!(f, "NotEnoughSpace"),
+            VolumeError::OutOfBounds => write!(f, "OutOfBounds"),
+            VolumeError::DiskError(e) => write!(f, "Disk error: {}", e),
+        };
+    }
+}
+
+/// An entry in the volume table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Volume {
+    name: String,
+    start: u64,
+    size: u64,
+}
+
+impl Volume {
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// The address of the volume's first byte within the image.
+    pub fn start(&self) -> u64 {
+        return self.start;
+    }
+
+    /// The size of the volume in bytes.
+    pub fn size(&self) -> u64 {
+        return self.size;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeTable {
+    volumes: Vec<Volume>,
+}
+
+impl VolumeTable {
+    /// Lays out volumes with the given names and sizes one after another and writes the table to the disk.
+    /// Sizes are rounded down to a multiple of `VOLUME_TABLE_SIZE`. The volumes still need to be formatted.
+    pub fn create<E: VoxFSErrorConvertible>(
+        handler: &mut dyn DiskHandler<E>,
+        volumes: &[(&str, u64)],
+    ) -> Result<Self, VolumeError<E>> {
+        if volumes.len() > MAX_VOLUMES {
+            return Err(VolumeError::TooManyVolumes);
+        }
+
+        let disk_size = handler.disk_size().map_err(VolumeError::DiskError)?;
+        let mut table = Self {
+            volumes: Vec::with_capacity(volumes.len()),
+        };
+        let mut start = VOLUME_TABLE_SIZE;
+
+        for (name, size) in volumes.iter() {
+            if !Self::is_valid_name(name) {
+                return Err(VolumeError::InvalidVolumeName);
+            }
+
+            if table.volume(name).is_some() {
+                return Err(VolumeError::VolumeExistsWithName(String::from(*name)));
+            }
+
+            let size = size - size % VOLUME_TABLE_SIZE;
+
+            if size == 0 || start > disk_size || disk_size - start < size {
+                return Err(VolumeError::NotEnoughSpace);
+            }
+
+            table.volumes.push(Volume {
+                name: String::from(*name),
+                start,
+                size,
+            });
+
+            start += size;
+        }
+
+        handler
+            .write_bytes(&table.to_bytes(), 0)
+            .map_err(VolumeError::DiskError)?;
+
+        return Ok(table);
+    }
+
+    /// Reads the volume table from the start of the disk.
+    pub fn read<E: VoxFSErrorConvertible>(
+        handler: &dyn DiskHandler<E>,
+    ) -> Result<Self, VolumeError<E>> {
+        let disk_size = handler.disk_size().map_err(VolumeError::DiskError)?;
+
+        if disk_size < VOLUME_TABLE_SIZE {
+            return Err(VolumeError::CorruptedVolumeTable);
+        }
+
+        let bytes = handler
+            .read_bytes(0, VOLUME_TABLE_SIZE)
+            .map_err(VolumeError::DiskError)?;
+
+        let table = match Self::from_bytes(&bytes) {
+            Some(t) => t,
+            None => return Err(VolumeError::CorruptedVolumeTable),
+        };
+
+        for volume in table.volumes.iter() {
+            if volume.start < VOLUME_TABLE_SIZE
+                || volume.start > disk_size
+                || volume.size > disk_size - volume.start
+            {
+                return Err(VolumeError::CorruptedVolumeTable);
+            }
+        }
+
+        return Ok(table);
+    }
+
+    pub fn volumes(&self) -> &Vec<Volume> {
+        return &self.volumes;
+    }
+
+    pub fn volume(&self, name: &str) -> Option<&Volume> {
+        return self.volumes.iter().find(|v| v.name == name);
+    }
+
+    /// Returns a handler that exposes the volume with a name as a whole disk.
+    pub fn open<'a, E: VoxFSErrorConvertible>(
+        &self,
+        handler: &'a mut dyn DiskHandler<E>,
+        name: &str,
+    ) -> Result<VolumeHandler<'a, E>, VolumeError<E>> {
+        return match self.volume(name) {
+            Some(volume) => Ok(VolumeHandler {
+                handler,
+                start: volume.start,
+                size: volume.size,
+            }),
+            None => Err(VolumeError::CouldNotFindVolume(String::from(name))),
+        };
+    }
+
+    fn is_valid_name(name: &str) -> bool {
+        return !name.is_empty() && name.len() <= MAX_VOLUME_NAME_LENGTH && !name.contains('\0');
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; VOLUME_TABLE_SIZE as usize];
+
+        LittleEndian::write_u32(&mut bytes[0..], MAGIC);
+        LittleEndian::write_u16(&mut bytes[4..], self.volumes.len() as u16);
+
+        for (i, volume) in self.volumes.iter().enumerate() {
+            let offset = HEADER_SIZE + i * ENTRY_SIZE;
+            let name = volume.name.as_bytes();
+
+            bytes[offset..offset + name.len()].copy_from_slice(name);
+            LittleEndian::write_u64(&mut bytes[offset + 32..], volume.start);
+            LittleEndian::write_u64(&mut bytes[offset + 40..], volume.size);
+        }
+
+        let mut sum = 0u8;
+
+        for b in bytes.iter() {
+            sum = sum.wrapping_add(*b);
+        }
+
+        bytes[6] = 0u8.wrapping_sub(sum);
+
+        return bytes;
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut sum = 0u8;
+
+        for b in bytes.iter() {
+            sum = sum.wrapping_add(*b);
+        }
+
+        let count = LittleEndian::read_u16(&bytes[4..]) as usize;
+
+        if sum != 0 || LittleEndian::read_u32(&bytes[0..]) != MAGIC || count > MAX_VOLUMES {
+            return None;
+        }
+
+        let mut volumes = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let offset = HEADER_SIZE + i * ENTRY_SIZE;
+            let raw_name = &bytes[offset..offset + MAX_VOLUME_NAME_LENGTH];
+            let length = raw_name
+                .iter()
+                .position(|b| *b == 0)
+                .unwrap_or(MAX_VOLUME_NAME_LENGTH);
+
+            let name = match core::str::from_utf8(&raw_name[..length]) {
+                Ok(n) => String::from(n),
+                Err(_) => return None,
+            };
+
+            volumes.push(Volume {
+                name,
+                start: LittleEndian::read_u64(&bytes[offset + 32..]),
+                size: LittleEndian::read_u64(&bytes[offset + 40..]),
+            });
+        }
+
+        return Some(Self { volumes });
+    }
+}
+
+/// Exposes a single volume of an image as a whole disk. Addresses are relative to the start of the volume.
+pub struct VolumeHandler<'a, E: VoxFSErrorConvertible> {
+    handler: &'a mut dyn DiskHandler<E>,
+    start: u64,
+    size: u64,
+}
+
+impl<'a, E: VoxFSErrorConvertible> VolumeHandler<'a, E> {
+    /// Returns the address within the image of the range start..end if it lies within the volume.
+    fn translate(&self, start: u64, end: Option<u64>) -> Result<u64, VolumeError<E>> {
+        return match end {
+            Some(end) if start <= end && end <= self.size => Ok(self.start + start),
+            _ => Err(VolumeError::OutOfBounds),
+        };
+    }
+}
+
+impl<'a, E: VoxFSErrorConvertible> DiskHandler<VolumeError<E>> for VolumeHandler<'a, E> {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), VolumeError<E>> {
+        let address = self.translate(location, location.checked_add(bytes.len() as u64))?;
+
+        return self
+            .handler
+            .write_bytes(bytes, address)
+            .map_err(VolumeError::DiskError);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, VolumeError<E>> {
+        let address = self.translate(location, location.checked_add(amount))?;
+
+        return self
+            .handler
+            .read_bytes(address, amount)
+            .map_err(VolumeError::DiskError);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), VolumeError<E>> {
+        let address = self.translate(start, Some(end))?;
+
+        return self
+            .handler
+            .zero_range(address, address + (end - start))
+            .map_err(VolumeError::DiskError);
+    }
+
+    fn disk_size(&self) -> Result<u64, VolumeError<E>> {
+        return Ok(self.size);
+    }
+
+    fn flush(&mut self) -> Result<(), VolumeError<E>> {
+        return self.handler.flush().map_err(VolumeError::DiskError);
+    }
+
+    fn barrier(&mut self) -> Result<(), VolumeError<E>> {
+        return self.handler.barrier().map_err(VolumeError::DiskError);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryDiskError, MemoryDiskHandler};
+
+    #[test]
+    fn test_table_round_trip() {
+        let mut handler = MemoryDiskHandler::new(VOLUME_TABLE_SIZE as usize * 8);
+        let table =
+            VolumeTable::create(&mut handler, &[("a", 4096 * 3), ("b", 4096 * 4 + 10)]).unwrap();
+
+        assert_eq!(table.volume("b").unwrap().start(), 4096 * 4);
+        assert_eq!(table.volume("b").unwrap().size(), 4096 * 4);
+        assert_eq!(VolumeTable::read(&handler).unwrap(), table);
+    }
+
+    #[test]
+    fn test_create_errors() {
+        let mut handler = MemoryDiskHandler::new(VOLUME_TABLE_SIZE as usize * 4);
+
+        assert_eq!(
+            VolumeTable::create(&mut handler, &[("a", 4096 * 4)]),
+            Err(VolumeError::NotEnoughSpace)
+        );
+        assert_eq!(
+            VolumeTable::create(&mut handler, &[("a", 4096), ("a", 4096)]),
+            Err(VolumeError::VolumeExistsWithName(String::from("a")))
+        );
+        assert_eq!(
+            VolumeTable::create(&mut handler, &[("", 4096)]),
+            Err(VolumeError::InvalidVolumeName)
+        );
+        assert_eq!(
+            VolumeTable::read(&handler),
+            Err(VolumeError::<MemoryDiskError>::CorruptedVolumeTable)
+        );
+    }
+
+    #[test]
+    fn test_handler_bounds() {
+        let mut handler = MemoryDiskHandler::new(VOLUME_TABLE_SIZE as usize * 4);
+        let table = VolumeTable::create(&mut handler, &[("a", 4096), ("b", 4096)]).unwrap();
+
+        {
+            let mut volume = table.open(&mut handler, "a").unwrap();
+
+            volume.write_bytes(&vec![7u8; 4], 4092).unwrap();
+            assert_eq!(
+                volume.write_bytes(&vec![7u8; 4], 4093),
+                Err(VolumeError::OutOfBounds)
+            );
+            assert_eq!(volume.read_bytes(4097, 0), Err(VolumeError::OutOfBounds));
+        }
+
+        assert_eq!(
+            handler.read_bytes(4096 * 2 - 4, 8).unwrap(),
+            vec![7, 7, 7, 7, 0, 0, 0, 0]
+        );
+        assert!(table.open(&mut handler, "c").is_err());
+    }
+}
@@ -53,9 +53,109 @@ pub enum VoxFSError<E> {
     FileExistsWithName(String),
     MoreNamesThanTagsProvided,
     NoTagsWithNames(Vec<String>),
+    InvalidBootAreaSize,
+    InvalidLabel,
+    InvalidMaxNameLength,
+    FileIsAppendOnly,
+    FileIsImmutable,
+    InvalidFileType,
+    InvalidHistorySize,
+    CorruptedHistoryRecord,
+    InvalidMetadataRatio,
+    Cancelled,
+    ReadOnly,
+    FailedCheckOnOpen,
+    InvalidChunkSize,
+    InvalidConcatenation,
+    MigrationRequired,
+    InvalidTagColor,
+    SameSourceAndDestinationTag,
+    MemoryBudgetExceeded,
+    FileBusy,
+    NoContentHasher,
+    NotRepairable,
+    DataChecksumMismatch {
+        inode: u64,
+    },
+    AddressOutOfRegion {
+        address: u64,
+        length: u64,
+    },
+    /// The disk is shorter than the metadata and allocated blocks the super block describes, `expected` is the
+    /// size in bytes needed to hold them.
+    ImageTruncated {
+        expected: u64,
+        actual: u64,
+    },
+    BlockOutOfRange(u64),
     DiskError(E),
 }
 
+impl<E> VoxFSError<E> {
+    /// A code for the error that stays the same when its wording changes, for scripts and issue reports to refer
+    /// to. Codes are never reused or renumbered, a new variant takes the next unused number.
+    pub fn code(&self) -> &'static str {
+        use VoxFSError::*;
+
+        return match self {
+            InvalidBlockSize => "E0001",
+            NoFreeInode => "E0002",
+            NoFreeTag => "E0003",
+            CorruptedTag => "E0004",
+            CorruptedIndirectTag => "E0005",
+            CorruptedINode => "E0006",
+            CorruptedSuperBlock => "E0007",
+            NotEnoughFreeDataBlocks => "E0008",
+            BlockAlreadyAllocated => "E0009",
+            CouldNotFindTag => "E0010",
+            TagAlreadyAppliedToINode => "E0011",
+            FailedToAppendToNewTag => "E0012",
+            InternalIndexLocationError => "E0013",
+            FailedIndirectTagAppend => "E0014",
+            FailedToFreeTag => "E0015",
+            FailedToFreeBlock => "E0016",
+            FailedToFreeINode => "E0017",
+            TagNotAppliedToINode => "E0018",
+            CorruptedIndirectINode => "E0019",
+            CouldNotFindINode => "E0020",
+            FailedToSetBitmapBit => "E0021",
+            ExpectedIndirectNode => "E0022",
+            InvalidTagName => "E0023",
+            TagExistsWithName(_) => "E0024",
+            InvalidFileName => "E0025",
+            FileExistsWithName(_) => "E0026",
+            MoreNamesThanTagsProvided => "E0027",
+            NoTagsWithNames(_) => "E0028",
+            InvalidBootAreaSize => "E0029",
+            InvalidLabel => "E0030",
+            InvalidMaxNameLength => "E0031",
+            FileIsAppendOnly => "E0032",
+            FileIsImmutable => "E0033",
+            InvalidFileType => "E0034",
+            InvalidHistorySize => "E0035",
+            CorruptedHistoryRecord => "E0036",
+            InvalidMetadataRatio => "E0037",
+            Cancelled => "E0038",
+            ReadOnly => "E0039",
+            FailedCheckOnOpen => "E0040",
+            InvalidChunkSize => "E0041",
+            InvalidConcatenation => "E0042",
+            MigrationRequired => "E0043",
+            InvalidTagColor => "E0044",
+            SameSourceAndDestinationTag => "E0045",
+            MemoryBudgetExceeded => "E0046",
+            FileBusy => "E0047",
+            NoContentHasher => "E0048",
+            DataChecksumMismatch { .. } => "E0049",
+            AddressOutOfRegion { .. } => "E0050",
+            ImageTruncated { .. } => "E0051",
+            BlockOutOfRange(_) => "E0052",
+            DiskError(_) => "E0053",
+            NotRepairable => "E0054",
+        };
+    }
+}
+
 impl<E: Display> core::fmt::Display for VoxFSError<E> {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         use VoxFSError::*;
@@ -78,6 +178,16 @@ impl<E: Display> core::fmt::Display for VoxFSError<E> {
             }
             FileExistsWithName(n) => write!(f, "FileExistsWithName({})", n),
             TagExistsWithName(n) => write!(f, "TagExistsWithName({})", n),
+            BlockOutOfRange(i) => write!(f, "BlockOutOfRange({})", i),
+            DataChecksumMismatch { inode } => write!(f, "DataChecksumMismatch(inode {})", inode),
+            AddressOutOfRegion { address, length } => {
+                write!(f, "AddressOutOfRegion({} bytes at {:#x})", length, address)
+            }
+            ImageTruncated { expected, actual } => write!(
+                f,
+                "ImageTruncated(expected {} bytes, found {})",
+                expected, actual
+            ),
             _ => write!(
                 f,
                 "{}",
@@ -108,7 +218,28 @@ impl<E: Display> core::fmt::Display for VoxFSError<E> {
                         ExpectedIndirectNode,
                         InvalidTagName,
                         InvalidFileName,
-                        MoreNamesThanTagsProvided
+                        MoreNamesThanTagsProvided,
+                        InvalidBootAreaSize,
+                        InvalidLabel,
+                        InvalidMaxNameLength,
+                        FileIsAppendOnly,
+                        FileIsImmutable,
+                        InvalidFileType,
+                        InvalidHistorySize,
+                        CorruptedHistoryRecord,
+                        InvalidMetadataRatio,
+                        Cancelled,
+                        ReadOnly,
+                        FailedCheckOnOpen,
+                        InvalidChunkSize,
+                        InvalidConcatenation,
+                        MigrationRequired,
+                        InvalidTagColor,
+                        SameSourceAndDestinationTag,
+                        MemoryBudgetExceeded,
+                        FileBusy,
+                        NoContentHasher,
+                        NotRepairable
                     ]
                 )
             ),
@@ -120,6 +251,7 @@ impl<E: Display> core::fmt::Display for VoxFSError<E> {
 mod tests {
     use crate::VoxFSError;
     use alloc::string::String;
+    use alloc::vec::Vec;
 
     #[derive(Debug)]
     struct DummyError;
@@ -163,4 +295,113 @@ mod tests {
         ]);
         assert_eq!("No tags with names: test, test, test", format!("{}", err));
     }
+
+    #[test]
+    fn test_fmt_6() {
+        let err: VoxFSError<DummyError> = VoxFSError::DataChecksumMismatch { inode: 3 };
+        assert_eq!("DataChecksumMismatch(inode 3)", format!("{}", err));
+    }
+
+    #[test]
+    fn test_fmt_7() {
+        let err: VoxFSError<DummyError> = VoxFSError::AddressOutOfRegion {
+            address: 0x3f80,
+            length: 256,
+        };
+        assert_eq!(
+            "AddressOutOfRegion(256 bytes at 0x3f80)",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_fmt_8() {
+        let err: VoxFSError<DummyError> = VoxFSError::ImageTruncated {
+            expected: 8192,
+            actual: 4096,
+        };
+        assert_eq!(
+            "ImageTruncated(expected 8192 bytes, found 4096)",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_codes() {
+        use VoxFSError::*;
+
+        let errors: Vec<VoxFSError<DummyError>> = vec![
+            InvalidBlockSize,
+            NoFreeInode,
+            NoFreeTag,
+            CorruptedTag,
+            CorruptedIndirectTag,
+            CorruptedINode,
+            CorruptedSuperBlock,
+            NotEnoughFreeDataBlocks,
+            BlockAlreadyAllocated,
+            CouldNotFindTag,
+            TagAlreadyAppliedToINode,
+            FailedToAppendToNewTag,
+            InternalIndexLocationError,
+            FailedIndirectTagAppend,
+            FailedToFreeTag,
+            FailedToFreeBlock,
+            FailedToFreeINode,
+            TagNotAppliedToINode,
+            CorruptedIndirectINode,
+            CouldNotFindINode,
+            FailedToSetBitmapBit,
+            ExpectedIndirectNode,
+            InvalidTagName,
+            TagExistsWithName(String::new()),
+            InvalidFileName,
+            FileExistsWithName(String::new()),
+            MoreNamesThanTagsProvided,
+            NoTagsWithNames(Vec::new()),
+            InvalidBootAreaSize,
+            InvalidLabel,
+            InvalidMaxNameLength,
+            FileIsAppendOnly,
+            FileIsImmutable,
+            InvalidFileType,
+            InvalidHistorySize,
+            CorruptedHistoryRecord,
+            InvalidMetadataRatio,
+            Cancelled,
+            ReadOnly,
+            FailedCheckOnOpen,
+            InvalidChunkSize,
+            InvalidConcatenation,
+            MigrationRequired,
+            InvalidTagColor,
+            SameSourceAndDestinationTag,
+            MemoryBudgetExceeded,
+            FileBusy,
+            NoContentHasher,
+            NotRepairable,
+            DataChecksumMismatch { inode: 0 },
+            AddressOutOfRegion {
+                address: 0,
+                length: 0,
+            },
+            ImageTruncated {
+                expected: 0,
+                actual: 0,
+            },
+            BlockOutOfRange(0),
+            DiskError(DummyError),
+        ];
+        let mut codes: Vec<&str> = errors.iter().map(|e| e.code()).collect();
+
+        // Scripts depend on these never changing
+        assert_eq!(codes[0], "E0001");
+        assert_eq!(VoxFSError::<DummyError>::FileBusy.code(), "E0047");
+        assert_eq!(DiskError(DummyError).code(), "E0053");
+
+        assert!(codes.iter().all(|c| c.len() == 5 && c.starts_with('E')));
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+    }
 }
@@ -1,6 +1,8 @@
 extern crate voxfs;
-use chrono::{DateTime, Utc};
-use voxfs::{DiskHandler, OSManager, VoxFSErrorConvertible};
+use chrono::Utc;
+use std::cell::Cell;
+use std::rc::Rc;
+use voxfs::{ContentHasher, DiskHandler, OSManager, Timestamp, VoxFSErrorConvertible};
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Error {}
@@ -12,6 +14,7 @@ pub struct Handler {
 }
 
 impl Handler {
+    #[allow(dead_code)]
     pub fn new(disk_size: usize) -> Self {
         return Self {
             disk: vec![0u8; disk_size],
@@ -52,6 +55,33 @@ impl DiskHandler<Error> for Handler {
     }
 }
 
+/// Wraps a handler, counting the reads that reach the disk.
+#[allow(dead_code)]
+pub struct CountingHandler {
+    pub disk: Handler,
+    pub reads: Rc<Cell<usize>>,
+}
+
+impl DiskHandler<Error> for CountingHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), Error> {
+        return self.disk.write_bytes(bytes, location);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, Error> {
+        self.reads.set(self.reads.get() + 1);
+
+        return self.disk.read_bytes(location, amount);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), Error> {
+        return self.disk.zero_range(start, end);
+    }
+
+    fn disk_size(&self) -> Result<u64, Error> {
+        return self.disk.disk_size();
+    }
+}
+
 #[derive(Debug)]
 pub struct Manager {}
 
@@ -62,7 +92,74 @@ impl Manager {
 }
 
 impl OSManager for Manager {
-    fn current_time(&self) -> DateTime<Utc> {
+    #[cfg(feature = "timestamps")]
+    fn current_time(&self) -> Timestamp {
         return Utc::now();
     }
+
+    #[cfg(not(feature = "timestamps"))]
+    fn current_time(&self) -> Timestamp {
+        let now = Utc::now();
+
+        return voxfs::timestamp_from_unix(now.timestamp(), now.timestamp_subsec_nanos());
+    }
+}
+
+/// Hashes a file by adding up its bytes, enough to tell the contents of test files apart.
+#[derive(Default)]
+pub struct SumHasher {
+    sums: [u8; 24],
+    length: u64,
+}
+
+impl ContentHasher for SumHasher {
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            let slot = (self.length % 24) as usize;
+            self.sums[slot] = self.sums[slot].wrapping_add(*byte);
+            self.length += 1;
+        }
+    }
+
+    fn finish(self: Box<Self>) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash[..24].copy_from_slice(&self.sums);
+        // Never all zeroes, which would read as no hash
+        hash[24..].copy_from_slice(&(self.length + 1).to_le_bytes());
+
+        return hash;
+    }
+}
+
+/// The hash `HashingManager` gives the contents.
+#[allow(dead_code)]
+pub fn sum_hash(contents: &[u8]) -> [u8; 32] {
+    let mut hasher = Box::new(SumHasher::default());
+    hasher.update(contents);
+
+    return hasher.finish();
+}
+
+/// A manager with a content hasher.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct HashingManager {}
+
+impl OSManager for HashingManager {
+    fn current_time(&self) -> Timestamp {
+        return Manager::new().current_time();
+    }
+
+    fn content_hasher(&self) -> Option<Box<dyn ContentHasher>> {
+        return Some(Box::new(SumHasher::default()));
+    }
+}
+
+/// Sets the checksum byte of a record so its bytes sum to zero.
+#[allow(dead_code)]
+pub fn fix_checksum(record: &mut [u8], checksum_offset: usize) {
+    record[checksum_offset] = 0;
+
+    let sum = record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    record[checksum_offset] = 0u8.wrapping_sub(sum);
 }
@@ -0,0 +1,39 @@
+#![cfg(feature = "access-stats")]
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_access_heatmap() {
+    let mut handler = Handler::new(4096 * 100);
+    let mut manager = Manager::new();
+
+    let file = {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        disk.create_new_file("file", INodeFlags::default(), vec![3u8; 4096 * 2])
+            .unwrap()
+    };
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let geometry = disk.geometry();
+    let heatmap = disk.access_heatmap();
+
+    // Opening reads the super block and the metadata but none of the data blocks
+    assert_eq!(heatmap.block_size(), 4096);
+    assert!(heatmap.block(0).reads > 0);
+    assert!(heatmap.block(geometry.tag_table_start() / 4096).reads > 0);
+
+    let data_start = geometry.data_start() / 4096;
+    assert_eq!(heatmap.range_accesses(data_start, u64::MAX).total(), 0);
+
+    disk.reset_access_heatmap();
+    disk.read_file(file.index()).unwrap();
+    disk.read_file(file.index()).unwrap();
+
+    let heatmap = disk.access_heatmap();
+    assert_eq!(heatmap.range_accesses(data_start, u64::MAX).reads, 4);
+    assert_eq!(heatmap.max_accesses(), 2);
+    assert_eq!(heatmap.range_accesses(0, data_start - 1).total(), 0);
+}
@@ -29,6 +29,8 @@ fn test_append_no_new_block() {
 
     disk.append_file_bytes(node_index, &b.to_vec()).unwrap();
 
+    drop(disk);
+
     assert_eq!(
         handler.dump_disk()[32768..32768 + file_contents.len()].to_vec(),
         file_contents
@@ -62,6 +64,8 @@ fn test_append_new_block() {
 
     assert_eq!(disk.read_file(node_index).unwrap(), file_contents);
 
+    drop(disk);
+
     assert_eq!(
         handler.dump_disk()[32768..32768 + file_contents.len()].to_vec(),
         file_contents
@@ -0,0 +1,111 @@
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, TagFlags, VoxFSError};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_capacity_report() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let report = disk.capacity_report();
+    assert_eq!(report.tags().used(), 1); // The root tag
+    assert_eq!(report.inodes().used(), 0);
+    assert_eq!(report.blocks().used(), 0);
+    assert_eq!(report.inodes().total(), disk.free_file_slots() as u64);
+    assert_eq!(report.blocks().total(), disk.data_block_count());
+
+    disk.create_new_file("file", INodeFlags::default(), vec![1u8; 5000])
+        .unwrap();
+
+    let report = disk.capacity_report();
+    assert_eq!(report.inodes().used(), 1);
+    assert_eq!(report.blocks().used(), 2);
+
+    assert!(disk.is_data_block_allocated(0));
+    assert!(disk.is_data_block_allocated(1));
+    assert!(!disk.is_data_block_allocated(2));
+    assert!(!disk.is_data_block_allocated(disk.data_block_count()));
+    assert_eq!(
+        report.inodes().free(),
+        report.inodes().total() - report.inodes().used()
+    );
+}
+
+#[test]
+fn test_tag_table_full() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let total = disk.capacity_report().tags().total();
+
+    for i in 1..total {
+        disk.create_new_tag(&format!("tag_{}", i), TagFlags::default())
+            .unwrap();
+    }
+
+    let report = disk.capacity_report();
+    assert!(report.tags().is_full());
+    assert_eq!(report.tags().percent_used(), 100);
+    assert!(report.any_above(100));
+    assert!(!report.inodes().is_full());
+
+    // Running out of tags is reported as such rather than as running out of inodes
+    assert_eq!(
+        disk.create_new_tag("one_more", TagFlags::default()).err(),
+        Some(VoxFSError::NoFreeTag)
+    );
+}
+
+#[test]
+fn test_estimate_required_blocks() {
+    let mut handler = Handler::new(4096 * 200);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    // Leave ten gaps of 5 blocks so a large file is split into more extents than fit in its inode
+    let mut gaps = Vec::new();
+
+    for i in 0..10 {
+        gaps.push(
+            disk.create_new_file(&format!("gap_{}", i), flags, vec![0; 5 * 4096])
+                .unwrap(),
+        );
+        disk.create_new_file(&format!("keep_{}", i), flags, vec![0; 10])
+            .unwrap();
+    }
+
+    let rest = disk.free_block_count();
+    disk.create_new_file("fill", flags, vec![0; (rest - 4) * 4096])
+        .unwrap();
+
+    for gap in gaps {
+        disk.delete_file(gap.index()).unwrap();
+    }
+
+    let report = disk.estimate_required_blocks(&[4096 * 35, 1]);
+    assert_eq!(report.files(), 2);
+    assert_eq!(report.data_blocks(), 36);
+    assert_eq!(report.indirect_blocks(), 1);
+    assert_eq!(report.free_blocks(), disk.free_block_count() as u64);
+    assert!(report.fits());
+
+    let free = disk.free_block_count() as u64;
+    let contents: Vec<u8> = (0..4096 * 35).map(|i| (i / 4096) as u8).collect();
+    let large = disk.create_new_file("large", flags, contents.clone()).unwrap();
+    disk.create_new_file("small", flags, vec![1]).unwrap();
+
+    assert_eq!(free - disk.free_block_count() as u64, report.required_blocks());
+    assert_eq!(disk.read_file(large.index()).unwrap(), contents);
+
+    let report = disk.estimate_required_blocks(&[4096 * 4, 4096 * 100]);
+    assert_eq!(report.required_bytes(), 104 * 4096);
+    assert_eq!(report.missing_blocks(), 104 - report.free_blocks());
+    assert!(!report.fits());
+}
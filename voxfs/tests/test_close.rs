@@ -0,0 +1,199 @@
+extern crate voxfs;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use voxfs::{
+    ByteSerializable, Disk, DiskHandler, INodeFlags, SuperBlock, VoxFSError,
+    BACKUP_SUPER_BLOCK_ADDRESS,
+};
+
+mod common;
+use common::*;
+
+/// Wraps a handler, counting flushes and optionally failing them.
+struct FlushHandler {
+    disk: Handler,
+    flushes: Rc<Cell<usize>>,
+    fail: bool,
+}
+
+impl DiskHandler<Error> for FlushHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), Error> {
+        return self.disk.write_bytes(bytes, location);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, Error> {
+        return self.disk.read_bytes(location, amount);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), Error> {
+        return self.disk.zero_range(start, end);
+    }
+
+    fn disk_size(&self) -> Result<u64, Error> {
+        return self.disk.disk_size();
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.fail {
+            return Err(Error {});
+        }
+
+        self.flushes.set(self.flushes.get() + 1);
+
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Write(u64),
+    Barrier,
+}
+
+/// Wraps a handler, recording the location of each write and each barrier in order.
+struct BarrierHandler {
+    disk: Handler,
+    events: Rc<RefCell<Vec<Event>>>,
+}
+
+impl DiskHandler<Error> for BarrierHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), Error> {
+        self.events.borrow_mut().push(Event::Write(location));
+
+        return self.disk.write_bytes(bytes, location);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, Error> {
+        return self.disk.read_bytes(location, amount);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), Error> {
+        return self.disk.zero_range(start, end);
+    }
+
+    fn disk_size(&self) -> Result<u64, Error> {
+        return self.disk.disk_size();
+    }
+
+    fn barrier(&mut self) -> Result<(), Error> {
+        self.events.borrow_mut().push(Event::Barrier);
+
+        return Ok(());
+    }
+}
+
+#[test]
+fn test_close() {
+    let flushes = Rc::new(Cell::new(0));
+    let mut handler = FlushHandler {
+        disk: Handler::new(4096 * 30),
+        flushes: flushes.clone(),
+        fail: false,
+    };
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    disk.create_new_file("test_file", INodeFlags::default(), vec![1u8; 100])
+        .unwrap();
+    disk.close().unwrap();
+
+    assert_eq!(flushes.get(), 1);
+
+    // Nothing was written so there is nothing to flush
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(!disk.opened_dirty());
+    disk.close().unwrap();
+    drop(Disk::open_disk(&mut handler, &mut manager).unwrap());
+
+    assert_eq!(flushes.get(), 1);
+
+    // Dropping flushes too
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    disk.create_new_file("other_file", INodeFlags::default(), Vec::new())
+        .unwrap();
+    drop(disk);
+
+    assert_eq!(flushes.get(), 2);
+    assert!(!Disk::open_disk(&mut handler, &mut manager)
+        .unwrap()
+        .opened_dirty());
+}
+
+#[test]
+fn test_close_reports_flush_errors() {
+    let mut handler = FlushHandler {
+        disk: Handler::new(4096 * 30),
+        flushes: Rc::new(Cell::new(0)),
+        fail: true,
+    };
+    let mut manager = Manager::new();
+
+    let disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    assert_eq!(disk.close(), Err(VoxFSError::DiskError(Error {})));
+}
+
+#[test]
+fn test_barriers() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let mut handler = BarrierHandler {
+        disk: Handler::new(4096 * 30),
+        events: events.clone(),
+    };
+    let mut manager = Manager::new();
+
+    Disk::make_new_filesystem(&mut handler, &mut manager)
+        .unwrap()
+        .close()
+        .unwrap();
+
+    let super_block =
+        SuperBlock::from_bytes(&handler.disk.disk[..SuperBlock::size() as usize]).unwrap();
+    let inode_start = super_block.inode_start_address();
+    let data_start = super_block.data_start_address();
+
+    events.borrow_mut().clear();
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    disk.create_new_file("test_file", INodeFlags::default(), vec![1u8; 100])
+        .unwrap();
+    disk.close().unwrap();
+
+    let events = events.borrow();
+
+    // The super block is marked dirty before anything else is written and clean after everything else, each time
+    // writing the backup before the primary
+    let backup = BACKUP_SUPER_BLOCK_ADDRESS;
+    assert_eq!(
+        events[..4],
+        [
+            Event::Write(backup),
+            Event::Barrier,
+            Event::Write(0),
+            Event::Barrier
+        ]
+    );
+    assert_eq!(
+        events[events.len() - 4..],
+        [
+            Event::Barrier,
+            Event::Write(backup),
+            Event::Barrier,
+            Event::Write(0)
+        ]
+    );
+
+    // The file's data is ordered before the inode that points to it
+    let data = events
+        .iter()
+        .position(|e| *e == Event::Write(data_start))
+        .unwrap();
+    let inode = events
+        .iter()
+        .position(|e| match e {
+            Event::Write(location) => *location >= inode_start && *location < data_start,
+            Event::Barrier => false,
+        })
+        .unwrap();
+    assert!(data < inode);
+    assert!(events[data..inode].contains(&Event::Barrier));
+}
@@ -0,0 +1,109 @@
+extern crate voxfs;
+use voxfs::{Disk, FileType, FormatOptions, INodeFlags};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_no_content_hashes() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = HashingManager {};
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![1, 2, 3])
+        .unwrap();
+
+    assert!(!disk.has_content_hashes());
+    assert_eq!(disk.file_hash(file.index()).unwrap(), None);
+    assert!(disk.file_hash(file.index() + 1).is_err());
+}
+
+#[test]
+fn test_content_hashes_follow_writes() {
+    let mut handler = Handler::new(4096 * 60);
+    let mut manager = HashingManager {};
+    let flags = INodeFlags::default();
+    let options = FormatOptions::new()
+        .with_content_hashes(true)
+        .with_history_size(4096);
+
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    assert!(disk.has_content_hashes());
+
+    let first = disk.create_new_file("first", flags, vec![7; 5000]).unwrap();
+    let second = disk.create_new_file("second", flags, vec![9; 10]).unwrap();
+    assert_eq!(
+        disk.file_hash(first.index()).unwrap(),
+        Some(sum_hash(&[7; 5000]))
+    );
+
+    let mut appended = vec![9; 10];
+    appended.extend_from_slice(&[3; 9000]);
+    disk.append_file_bytes(second.index(), &vec![3; 9000])
+        .unwrap();
+    assert_eq!(
+        disk.file_hash(second.index()).unwrap(),
+        Some(sum_hash(&appended))
+    );
+
+    disk.replace_file("first", flags, vec![1, 2]).unwrap();
+    assert_eq!(
+        disk.file_hash(first.index()).unwrap(),
+        Some(sum_hash(&[1, 2]))
+    );
+
+    let joined = disk
+        .concat_files(&[first.index(), second.index()], "joined")
+        .unwrap();
+    let mut contents = vec![1, 2];
+    contents.extend_from_slice(&appended);
+    assert_eq!(
+        disk.file_hash(joined.index()).unwrap(),
+        Some(sum_hash(&contents))
+    );
+
+    let socket = disk.create_special("socket", FileType::Socket, 0).unwrap();
+    assert_eq!(disk.file_hash(socket.index()).unwrap(), Some(sum_hash(&[])));
+
+    // A file reusing a deleted file's slot doesn't inherit its hash
+    disk.delete_file(socket.index()).unwrap();
+    let reused = disk.create_new_file("reused", flags, vec![4]).unwrap();
+    assert_eq!(reused.index(), socket.index());
+    assert_eq!(
+        disk.file_hash(reused.index()).unwrap(),
+        Some(sum_hash(&[4]))
+    );
+
+    disk.close().unwrap();
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.has_content_hashes());
+    assert_eq!(
+        disk.file_hash(joined.index()).unwrap(),
+        Some(sum_hash(&contents))
+    );
+    assert!(disk.scrub().unwrap().is_clean());
+}
+
+#[test]
+fn test_content_hash_cleared_without_hasher() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut hashing = HashingManager {};
+    let options = FormatOptions::new().with_content_hashes(true);
+
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut hashing, options).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![5; 100])
+        .unwrap();
+    assert!(disk.file_hash(file.index()).unwrap().is_some());
+    disk.close().unwrap();
+
+    // A hash that can't be updated is cleared rather than left stale
+    let mut manager = Manager::new();
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    disk.append_file_bytes(file.index(), &vec![6; 100]).unwrap();
+    assert_eq!(disk.file_hash(file.index()).unwrap(), None);
+}
@@ -0,0 +1,350 @@
+extern crate voxfs;
+use std::collections::BTreeMap;
+use voxfs::{Disk, DiskHandler, INodeFlags, TagFlags, VoxFSError};
+
+mod common;
+use common::*;
+
+const DISK_SIZE: usize = 4096 * 60;
+
+/// A handler that simulates power loss by failing every write after a number of writes have completed.
+/// The failed write and all writes after it never reach the disk.
+struct FaultHandler {
+    disk: Vec<u8>,
+    writes_allowed: Option<usize>,
+    writes: usize,
+}
+
+impl FaultHandler {
+    fn new(disk: Vec<u8>, writes_allowed: Option<usize>) -> Self {
+        return Self {
+            disk,
+            writes_allowed,
+            writes: 0,
+        };
+    }
+
+    /// Records a write, returning an error if the power has been lost.
+    fn write_boundary(&mut self) -> Result<(), Error> {
+        if let Some(allowed) = self.writes_allowed {
+            if self.writes >= allowed {
+                return Err(Error {});
+            }
+        }
+
+        self.writes += 1;
+
+        return Ok(());
+    }
+}
+
+impl DiskHandler<Error> for FaultHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), Error> {
+        self.write_boundary()?;
+
+        let location = location as usize;
+        self.disk[location..location + bytes.len()].copy_from_slice(bytes);
+
+        return Ok(());
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, Error> {
+        let location = location as usize;
+        let amount = amount as usize;
+
+        return Ok(self.disk[location..location + amount].to_vec());
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), Error> {
+        self.write_boundary()?;
+
+        for i in start..end {
+            self.disk[i as usize] = 0;
+        }
+
+        return Ok(());
+    }
+
+    fn disk_size(&self) -> Result<u64, Error> {
+        return Ok(self.disk.len() as u64);
+    }
+}
+
+/// The visible state of a filesystem, files by name with their contents and tags by name with their members.
+#[derive(Debug, PartialEq)]
+struct State {
+    files: BTreeMap<String, Vec<u8>>,
+    tags: BTreeMap<String, Vec<String>>,
+}
+
+/// Opens an image and checks that everything reachable from the tags and inodes can be read.
+/// Returns the visible state if the image is consistent.
+fn check(image: &[u8]) -> Result<State, VoxFSError<Error>> {
+    let mut handler = Handler {
+        disk: image.to_vec(),
+    };
+    let mut manager = Manager::new();
+    let disk = Disk::open_disk(&mut handler, &mut manager)?;
+
+    let mut files = BTreeMap::new();
+
+    for inode in disk.list_inodes() {
+        let contents = disk.read_file(inode.index())?;
+
+        if contents.len() as u64 != inode.file_size() {
+            return Err(VoxFSError::CorruptedINode);
+        }
+
+        files.insert(inode.name(), contents);
+    }
+
+    let mut tags = BTreeMap::new();
+
+    for tag in disk.list_tags() {
+        let mut members: Vec<String> = disk
+            .list_nodes_with_tag(tag.index())?
+            .iter()
+            .map(|n| n.name())
+            .collect();
+        members.sort();
+
+        tags.insert(tag.name_string(), members);
+    }
+
+    return Ok(State { files, tags });
+}
+
+/// Runs an operation against an image, interrupting it at every write boundary including the sync that marks it clean.
+/// After each interruption the image is reopened and checked and its state must match either the state before or
+/// after the operation.
+fn assert_crash_consistent<F>(image: Vec<u8>, operation: F)
+where
+    F: Fn(&mut Disk<Error>) -> Result<(), VoxFSError<Error>>,
+{
+    let old_state = check(&image).expect("The starting image is not consistent");
+
+    // Run the operation to completion to count the writes and find the new state
+    let (total_writes, completed) = run_with_fault(&image, None, &operation);
+    let new_state = check(&completed).expect("The completed operation is not consistent");
+
+    for allowed in 0..total_writes {
+        let (_, interrupted) = run_with_fault(&image, Some(allowed), &operation);
+
+        match check(&interrupted) {
+            Ok(state) => assert!(
+                state == old_state || state == new_state,
+                "Interrupted after {} of {} writes, a partial state is visible: {:?}",
+                allowed,
+                total_writes,
+                state
+            ),
+            Err(e) => panic!(
+                "Interrupted after {} of {} writes, the image is inconsistent: {:?}",
+                allowed, total_writes, e
+            ),
+        }
+    }
+}
+
+/// Runs an operation against an image and syncs it, losing power after a number of writes if one is given.
+/// Returns the number of writes made and the image left behind.
+fn run_with_fault<F>(image: &[u8], writes_allowed: Option<usize>, operation: &F) -> (usize, Vec<u8>)
+where
+    F: Fn(&mut Disk<Error>) -> Result<(), VoxFSError<Error>>,
+{
+    let mut handler = FaultHandler::new(image.to_vec(), writes_allowed);
+    let mut manager = Manager::new();
+    {
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        let result = operation(&mut disk).and_then(|_| disk.sync());
+        assert_eq!(result.is_err(), writes_allowed.is_some());
+    }
+
+    return (handler.writes, handler.disk);
+}
+
+/// Builds an image with a small file, a large file using an indirect inode and a tag applied to both.
+fn populated_image() -> Vec<u8> {
+    let mut handler = Handler::new(DISK_SIZE);
+    let mut manager = Manager::new();
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+        let small = disk
+            .create_new_file(
+                "small_file",
+                INodeFlags::new(true, true, true, false),
+                b"Some file contents".to_vec(),
+            )
+            .unwrap();
+
+        let large = disk
+            .create_new_file(
+                "large_file",
+                INodeFlags::new(true, true, true, false),
+                vec![0x5a; 4096 * 6],
+            )
+            .unwrap();
+
+        let tag = disk
+            .create_new_tag("tagged", TagFlags::new(true, true))
+            .unwrap();
+
+        disk.apply_tag(tag.index(), small.index()).unwrap();
+        disk.apply_tag(tag.index(), large.index()).unwrap();
+    }
+
+    return handler.dump_disk();
+}
+
+#[test]
+fn test_crash_create_file() {
+    assert_crash_consistent(populated_image(), |disk| {
+        disk.create_new_file(
+            "new_file",
+            INodeFlags::new(true, true, true, false),
+            vec![0x11; 100],
+        )?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_create_large_file() {
+    assert_crash_consistent(populated_image(), |disk| {
+        disk.create_new_file(
+            "new_large_file",
+            INodeFlags::new(true, true, true, false),
+            vec![0x22; 4096 * 7],
+        )?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_append_file() {
+    assert_crash_consistent(populated_image(), |disk| {
+        let index = disk.inode_with_name("small_file").unwrap();
+        disk.append_file_bytes(index, &vec![0x33; 4096 * 2])?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_delete_file() {
+    let delete = |disk: &mut Disk<Error>| {
+        let index = disk.inode_with_name("large_file").unwrap();
+        disk.delete_file(index)?;
+
+        return Ok(());
+    };
+
+    assert_crash_consistent(populated_image(), delete);
+
+    // The inode is freed before the tag is rewritten, so a crash between them leaves the tag listing a free index.
+    // A new file given that index must not inherit the tag.
+    let image = populated_image();
+    let (total_writes, _) = run_with_fault(&image, None, &delete);
+
+    for allowed in 0..total_writes {
+        let (_, interrupted) = run_with_fault(&image, Some(allowed), &delete);
+        let mut handler = Handler { disk: interrupted };
+        let mut manager = Manager::new();
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        let tag = disk.tag_with_name("tagged").unwrap();
+
+        assert_eq!(
+            disk.tag_member_count(tag).unwrap(),
+            disk.list_nodes_with_tag(tag).unwrap().len() as u64,
+            "Interrupted after {} of {} writes, the tag counts a deleted file",
+            allowed,
+            total_writes
+        );
+
+        let file = disk
+            .create_new_file("new_file", INodeFlags::default(), vec![0x44; 10])
+            .unwrap();
+
+        assert!(
+            disk.tags_for_inode(file.index()).unwrap().is_empty(),
+            "Interrupted after {} of {} writes, the new file inherited a tag",
+            allowed,
+            total_writes
+        );
+        disk.close().unwrap();
+
+        // The stale index is gone from the disk, not just from memory
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        assert!(disk.tags_for_inode(file.index()).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn test_crash_create_tag() {
+    assert_crash_consistent(populated_image(), |disk| {
+        disk.create_new_tag("new_tag", TagFlags::new(true, true))?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_delete_tag() {
+    assert_crash_consistent(populated_image(), |disk| {
+        let index = disk.tag_with_name("tagged").unwrap();
+        disk.delete_tag(index)?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_apply_tag() {
+    assert_crash_consistent(populated_image(), |disk| {
+        let index = disk.inode_with_name("small_file").unwrap();
+        disk.apply_tag(0, index)?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_remove_tag() {
+    assert_crash_consistent(populated_image(), |disk| {
+        let tag = disk.tag_with_name("tagged").unwrap();
+        let index = disk.inode_with_name("large_file").unwrap();
+        disk.remove_tag_from_inode(tag, index)?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_replace_file() {
+    assert_crash_consistent(populated_image(), |disk| {
+        disk.replace_file(
+            "small_file",
+            INodeFlags::new(true, true, true, false),
+            vec![0x44; 4096 * 2],
+        )?;
+
+        return Ok(());
+    });
+}
+
+#[test]
+fn test_crash_replace_large_file() {
+    assert_crash_consistent(populated_image(), |disk| {
+        disk.replace_file(
+            "large_file",
+            INodeFlags::new(true, true, true, false),
+            b"Shorter contents".to_vec(),
+        )?;
+
+        return Ok(());
+    });
+}
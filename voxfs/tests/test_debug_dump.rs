@@ -0,0 +1,58 @@
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, TagFlags};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_debug_dump() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let file = disk
+        .create_new_file("notes.txt", INodeFlags::read_write(), vec![7u8; 5000])
+        .unwrap();
+    let tag = disk.create_new_tag("work", TagFlags::read_write()).unwrap();
+    disk.apply_tag(tag.index(), file.index()).unwrap();
+
+    let mut dump = String::new();
+    disk.debug_dump(&mut dump).unwrap();
+
+    assert!(dump.starts_with("super block\n  version: "));
+    assert!(dump.contains("\nbitmaps\n  tags: 2 of "));
+    assert!(dump.contains("\n  inodes: 1 of "));
+    assert!(dump.contains(&format!(
+        "\ninode {}\n  name: \"notes.txt\"\n  type: Regular\n  flags: rw--\n  size: 5000\n",
+        file.index()
+    )));
+    assert!(dump.contains(&format!(
+        "\ntag {}\n  name: \"work\"\n  flags: rw\n  members: {}\n",
+        tag.index(),
+        file.index()
+    )));
+
+    // Members past those kept in the tag are listed with the indirect block holding them
+    let many = disk.create_new_tag("many", TagFlags::read_write()).unwrap();
+    for i in 0..13 {
+        let inode = disk
+            .create_new_file(&format!("file {}", i), INodeFlags::read_write(), vec![])
+            .unwrap();
+        disk.apply_tag(many.index(), inode.index()).unwrap();
+    }
+
+    let mut dump = String::new();
+    disk.debug_dump(&mut dump).unwrap();
+    let many_dump = dump
+        .split(&format!("tag {}\n", many.index()))
+        .nth(1)
+        .unwrap();
+    assert!(many_dump.contains("\n  indirect 0x"));
+    assert!(!dump.contains("error"));
+
+    // The dump only depends on the disk's contents
+    let mut again = String::new();
+    disk.debug_dump(&mut again).unwrap();
+    assert_eq!(dump, again);
+}
@@ -1,5 +1,7 @@
 extern crate voxfs;
-use voxfs::{Disk, INodeFlags, TagFlags};
+use std::cell::Cell;
+use std::rc::Rc;
+use voxfs::{Disk, FormatOptions, INodeFlags, MountOptions, TagFlags, VoxFSError};
 
 mod common;
 use common::*;
@@ -165,3 +167,122 @@ fn test_delete_file_tags() {
     disk.delete_file(index).unwrap();
     assert_eq!(disk.list_nodes_with_tag(tag_index).unwrap().len(), 2);
 }
+
+#[test]
+fn test_delete_file_only_touches_its_tags() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: Handler::new(4096 * 1000),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let mut nodes = Vec::new();
+
+    for i in 0..20 {
+        nodes.push(
+            disk.create_new_file(&format!("test_file_{}", i), flags, Vec::new())
+                .unwrap(),
+        );
+    }
+
+    // Every tag has an indirect block, holding the first file
+    let mut tags = Vec::new();
+
+    for i in 0..30 {
+        let tag = disk
+            .create_new_tag(&format!("tag_{}", i), TagFlags::default())
+            .unwrap();
+
+        for node in nodes[1..14].iter() {
+            disk.apply_tag(tag.index(), node.index()).unwrap();
+        }
+
+        disk.apply_tag(tag.index(), nodes[0].index()).unwrap();
+        tags.push(tag);
+    }
+
+    disk.apply_tag(tags[0].index(), nodes[15].index()).unwrap();
+
+    // The first deletion builds the index
+    disk.delete_file(nodes[16].index()).unwrap();
+
+    let before = reads.get();
+    disk.delete_file(nodes[15].index()).unwrap();
+    assert!(reads.get() - before <= 2);
+
+    disk.delete_file(nodes[0].index()).unwrap();
+
+    for tag in tags.iter() {
+        let members = disk.list_nodes_with_tag(tag.index()).unwrap();
+
+        assert_eq!(members.len(), 13);
+        assert!(!members.contains(&nodes[0]));
+        assert!(!members.contains(&nodes[15]));
+    }
+}
+
+#[test]
+fn test_delete_busy_file() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk.create_new_file("file", flags, vec![1; 10]).unwrap();
+    let other = disk.create_new_file("other", flags, vec![2; 10]).unwrap();
+
+    // Without protection open handles don't stop a delete
+    disk.open_file(other.index()).unwrap();
+    disk.delete_file(other.index()).unwrap();
+    assert_eq!(disk.open_count(other.index()), 0);
+    disk.close().unwrap();
+
+    let options = MountOptions::new().with_busy_protection(true);
+    let mut disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+
+    let first = disk.open_file(file.index()).unwrap();
+    let second = disk.open_file(file.index()).unwrap();
+    assert_eq!(disk.open_count(file.index()), 2);
+    assert_eq!(disk.delete_file(file.index()), Err(VoxFSError::FileBusy));
+
+    disk.close_file(first);
+    assert_eq!(disk.delete_file(file.index()), Err(VoxFSError::FileBusy));
+
+    disk.close_file(second);
+    disk.close_file(second);
+    assert_eq!(disk.open_count(file.index()), 0);
+    disk.delete_file(file.index()).unwrap();
+    assert!(disk.list_inodes().is_empty());
+}
+
+#[test]
+fn test_internal_reads_leave_files_closed() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = HashingManager {};
+    let flags = INodeFlags::default();
+    let options = FormatOptions::new().with_content_hashes(true);
+
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    let first = disk.create_new_file("first", flags, vec![1; 10]).unwrap();
+    let second = disk.create_new_file("second", flags, vec![2; 10]).unwrap();
+    let joined = disk
+        .concat_files(&[first.index(), second.index()], "joined")
+        .unwrap();
+    let parts = disk.split_file(joined.index(), 8).unwrap();
+
+    for index in [
+        first.index(),
+        second.index(),
+        joined.index(),
+        parts[0].index(),
+    ]
+    .iter()
+    {
+        assert_eq!(disk.open_count(*index), 0);
+    }
+}
@@ -0,0 +1,133 @@
+extern crate voxfs;
+use voxfs::{Disk, FileType, INodeFlags, VoxFSError};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_append_only_file() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false).with_append_only(true);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let inode = disk
+        .create_new_file("log", flags, b"first\n".to_vec())
+        .unwrap();
+    assert!(inode.flags().append_only());
+
+    disk.append_file_bytes(inode.index(), &b"second\n".to_vec())
+        .unwrap();
+    assert_eq!(
+        disk.read_file(inode.index()).unwrap(),
+        b"first\nsecond\n".to_vec()
+    );
+
+    assert_eq!(
+        disk.replace_file("log", flags, Vec::new()),
+        Err(VoxFSError::FileIsAppendOnly)
+    );
+    assert_eq!(
+        disk.delete_file(inode.index()),
+        Err(VoxFSError::FileIsAppendOnly)
+    );
+
+    drop(disk);
+
+    // The flag is kept on disk
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.list_inodes()[0].flags(), flags);
+
+    disk.set_file_flags(inode.index(), INodeFlags::default())
+        .unwrap();
+    disk.delete_file(inode.index()).unwrap();
+    assert_eq!(disk.number_of_files(), 0);
+}
+
+#[test]
+fn test_immutable_file() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, false, false).with_immutable(true);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let inode = disk
+        .create_new_file("firmware", flags, vec![0xab; 5000])
+        .unwrap();
+    let free_blocks = disk.free_block_count();
+
+    assert_eq!(
+        disk.append_file_bytes(inode.index(), &vec![0u8; 10]),
+        Err(VoxFSError::FileIsImmutable)
+    );
+    assert_eq!(
+        disk.replace_file("firmware", INodeFlags::default(), Vec::new()),
+        Err(VoxFSError::FileIsImmutable)
+    );
+    assert_eq!(
+        disk.delete_file(inode.index()),
+        Err(VoxFSError::FileIsImmutable)
+    );
+
+    assert_eq!(disk.read_file(inode.index()).unwrap(), vec![0xab; 5000]);
+    assert_eq!(disk.free_block_count(), free_blocks);
+}
+
+#[test]
+fn test_special_files() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let free_blocks = disk.free_block_count();
+
+    let device = disk
+        .create_special("sda", FileType::Device, 0x0801)
+        .unwrap();
+    let socket = disk.create_special("sock", FileType::Socket, 7).unwrap();
+    let link = disk
+        .create_new_file(
+            "link",
+            INodeFlags::default().with_file_type(FileType::Symlink),
+            b"sda".to_vec(),
+        )
+        .unwrap();
+
+    // Only the symlink has contents
+    assert_eq!(disk.free_block_count(), free_blocks - 1);
+
+    assert_eq!(
+        disk.create_special("file", FileType::Regular, 0),
+        Err(VoxFSError::InvalidFileType)
+    );
+    assert_eq!(
+        disk.create_new_file(
+            "device",
+            INodeFlags::default().with_file_type(FileType::Device),
+            Vec::new()
+        ),
+        Err(VoxFSError::InvalidFileType)
+    );
+    assert_eq!(
+        disk.append_file_bytes(device.index(), &vec![0u8; 10]),
+        Err(VoxFSError::InvalidFileType)
+    );
+
+    drop(disk);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let inodes = disk.list_inodes();
+
+    assert_eq!(inodes, vec![device, socket, link]);
+    assert_eq!(inodes[0].flags().file_type(), FileType::Device);
+    assert_eq!(inodes[0].rdev(), Some(0x0801));
+    assert_eq!(inodes[1].flags().file_type(), FileType::Socket);
+    assert_eq!(inodes[1].rdev(), None);
+    assert_eq!(inodes[2].flags().file_type(), FileType::Symlink);
+    assert_eq!(disk.read_file(link.index()).unwrap(), b"sda".to_vec());
+    assert!(disk.read_file(device.index()).unwrap().is_empty());
+
+    disk.delete_file(device.index()).unwrap();
+    disk.delete_file(socket.index()).unwrap();
+    assert_eq!(disk.free_block_count(), free_blocks - 1);
+}
@@ -1,5 +1,9 @@
 extern crate voxfs;
-use voxfs::{Disk, INodeFlags, OSManager, TagBlock, TagFlags};
+use voxfs::{
+    ByteSerializable, Disk, FilesystemState, FormatOptions, INodeFlags, Integrity,
+    MemoryDiskHandler, MountOptions, NamePolicy, OSManager, RecordKind, SuperBlock, TagBlock,
+    TagFlags, VoxFSError, BACKUP_SUPER_BLOCK_ADDRESS,
+};
 
 mod common;
 use common::*;
@@ -635,3 +639,498 @@ fn test_fsopen_large_file_deleted() {
 
     assert_eq!(available_blocks, disk.available_data_blocks());
 }
+
+#[test]
+fn test_open_unformatted() {
+    // A zeroed first block has a valid checksum but must still be rejected
+    let mut handler = MemoryDiskHandler::new(4096 * 30);
+    let mut manager = Manager::new();
+
+    assert_eq!(
+        Disk::open_disk(&mut handler, &mut manager).err(),
+        Some(VoxFSError::CorruptedSuperBlock)
+    );
+}
+
+#[test]
+fn test_clean_and_dirty_state() {
+    let mut handler = MemoryDiskHandler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        disk.create_new_file("first", flags, vec![1u8; 100])
+            .unwrap();
+    }
+
+    // Dropping the disk marks it clean
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(!disk.opened_dirty());
+    assert_eq!(disk.mount_count(), 1);
+
+    // Simulate a crash by never dropping the disk after modifying it
+    disk.create_new_file("second", flags, vec![2u8; 100])
+        .unwrap();
+    core::mem::forget(disk);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.opened_dirty());
+    assert_eq!(disk.mount_count(), 2);
+
+    disk.create_new_file("third", flags, vec![3u8; 100])
+        .unwrap();
+    disk.sync().unwrap();
+    assert_eq!(disk.mount_count(), 3);
+    drop(disk);
+
+    // Opening without modifying the disk does not count as a mount
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(!disk.opened_dirty());
+    assert_eq!(disk.mount_count(), 3);
+}
+
+#[test]
+fn test_mirror_recovery() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_metadata_mirror(true);
+    let inode;
+    let tag;
+
+    {
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+        inode = disk
+            .create_new_file(
+                "file",
+                INodeFlags::new(true, true, true, false),
+                vec![7u8; 5000],
+            )
+            .unwrap();
+        tag = disk
+            .create_new_tag("tag", TagFlags::new(true, true))
+            .unwrap();
+        disk.apply_tag(tag.index(), inode.index()).unwrap();
+    }
+
+    // Corrupt the primary copies of the inode and the tag
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + inode.index() * 256; // Inodes are 256 bytes
+    let tag_address = super_block.tag_start_address() + tag.index() * TagBlock::size();
+    bytes[inode_address as usize + 10] ^= 0xff;
+    bytes[tag_address as usize + 10] ^= 0xff;
+
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+
+    {
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+        assert_eq!(disk.recovered_inodes(), &vec![inode.index()]);
+        assert_eq!(disk.recovered_tags(), &vec![tag.index()]);
+        assert_eq!(disk.read_file(inode.index()).unwrap(), vec![7u8; 5000]);
+        assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap().len(), 1);
+
+        // Writing the records again repairs the primary copies
+        disk.append_file_bytes(inode.index(), &vec![8u8; 10])
+            .unwrap();
+        disk.remove_tag_from_inode(tag.index(), inode.index())
+            .unwrap();
+    }
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.recovered_inodes().is_empty());
+    assert!(disk.recovered_tags().is_empty());
+}
+
+#[test]
+fn test_corrupted_inode_without_mirror() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let inode;
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        inode = disk
+            .create_new_file(
+                "file",
+                INodeFlags::new(true, true, true, false),
+                vec![7u8; 10],
+            )
+            .unwrap();
+    }
+
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + inode.index() * 256; // Inodes are 256 bytes
+    bytes[inode_address as usize + 10] ^= 0xff;
+
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+    assert_eq!(
+        Disk::open_disk(&mut handler, &mut manager).err(),
+        Some(VoxFSError::CorruptedINode)
+    );
+}
+
+#[test]
+fn test_integrity_levels() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let inode = {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        disk.create_new_file("file", INodeFlags::default(), vec![7u8; 10])
+            .unwrap()
+    };
+
+    // Change the inode's name without updating its checksum
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + inode.index() * 256;
+    bytes[inode_address as usize + 10] = b'x';
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+
+    let options = MountOptions::new().with_integrity(Integrity::Metadata);
+    assert_eq!(
+        Disk::open_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::CorruptedINode)
+    );
+
+    // Without checking the inode is used as read
+    let options = MountOptions::new().with_integrity(Integrity::None);
+    let disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    assert_eq!(disk.integrity(), Integrity::None);
+    assert!(!disk.verifies_reads());
+    assert_eq!(disk.list_inodes()[0].name(), "fixe");
+    assert_eq!(disk.read_file(inode.index()).unwrap(), vec![7u8; 10]);
+    assert!(!disk.scrub().unwrap().is_clean());
+    disk.close().unwrap();
+
+    let options = MountOptions::new().with_integrity(Integrity::Full);
+    assert_eq!(options, MountOptions::new().with_verify_reads(true));
+    assert_eq!(
+        MountOptions::new().with_verify_reads(false).integrity,
+        Integrity::Metadata
+    );
+}
+
+#[test]
+fn test_backup_super_block() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![3u8; 10])
+        .unwrap();
+    disk.close().unwrap();
+
+    let size = SuperBlock::size() as usize;
+    let backup = BACKUP_SUPER_BLOCK_ADDRESS as usize;
+    let stale = handler.as_bytes()[..size].to_vec();
+    assert_eq!(&handler.as_bytes()[backup..backup + size], &stale[..]);
+
+    // Each write of the super block advances the sequence
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let sequence = disk.commit_sequence();
+    assert!(!disk.recovered_super_block());
+    disk.set_name_policy(NamePolicy::Allow).unwrap();
+    disk.close().unwrap();
+
+    // A primary left behind by an interrupted write is older than the backup
+    let mut bytes = handler.into_bytes();
+    bytes[..size].copy_from_slice(&stale);
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.recovered_super_block());
+    assert!(disk.commit_sequence() > sequence);
+    assert_eq!(disk.name_policy(), NamePolicy::Allow);
+    drop(disk);
+
+    // A corrupted primary falls back to the backup and is repaired by the next write
+    let mut bytes = handler.into_bytes();
+    bytes[8] ^= 0xff;
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.recovered_super_block());
+    assert_eq!(disk.read_file(file.index()).unwrap(), vec![3u8; 10]);
+    disk.delete_file(file.index()).unwrap();
+    disk.close().unwrap();
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(!disk.recovered_super_block());
+    assert!(disk.list_inodes().is_empty());
+    drop(disk);
+
+    let mut bytes = handler.into_bytes();
+    bytes[8] ^= 0xff;
+    bytes[backup + 8] ^= 0xff;
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+    assert_eq!(
+        Disk::open_disk(&mut handler, &mut manager).err(),
+        Some(VoxFSError::CorruptedSuperBlock)
+    );
+}
+
+#[test]
+fn test_probe() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+
+    assert_eq!(voxfs::probe(&handler), None);
+
+    let options = FormatOptions::new()
+        .with_uuid([3u8; 16])
+        .with_label("photos");
+    Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+    let info = voxfs::probe(&handler).unwrap();
+    assert_eq!(info.uuid(), [3u8; 16]);
+    assert_eq!(info.label(), "photos");
+    assert_eq!(info.block_size(), 4096);
+    assert_eq!(info.version(), 2);
+    assert_eq!(info.state(), FilesystemState::Clean);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.label(), "photos");
+    assert_eq!(disk.uuid(), [3u8; 16]);
+    drop(disk);
+
+    // Anything else is rejected, including a disk too small to hold a super block
+    assert_eq!(voxfs::probe(&MemoryDiskHandler::new(64)), None);
+    let mut bytes = handler.into_bytes();
+    bytes[20] ^= 0xff;
+    assert_eq!(voxfs::probe(&MemoryDiskHandler::from_bytes(bytes)), None);
+}
+
+#[test]
+fn test_generated_uuid_and_invalid_label() {
+    let mut first = MemoryDiskHandler::new(4096 * 100);
+    let mut second = MemoryDiskHandler::new(4096 * 101);
+    let mut manager = Manager::new();
+
+    let uuid = Disk::make_new_filesystem(&mut first, &mut manager)
+        .unwrap()
+        .uuid();
+    assert_ne!(uuid, [0u8; 16]);
+    assert_ne!(
+        Disk::make_new_filesystem(&mut second, &mut manager)
+            .unwrap()
+            .uuid(),
+        uuid
+    );
+
+    let options = FormatOptions::new().with_label("a label that is too long");
+    assert_eq!(
+        Disk::make_new_filesystem_with_options(&mut second, &mut manager, options).err(),
+        Some(VoxFSError::InvalidLabel)
+    );
+}
+
+#[test]
+fn test_open_tolerant() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+    let (damaged, intact, tag);
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        damaged = disk
+            .create_new_file("damaged", flags, vec![1u8; 10])
+            .unwrap();
+        intact = disk
+            .create_new_file("intact", flags, vec![2u8; 10])
+            .unwrap();
+        tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+    }
+
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + damaged.index() * 256; // Inodes are 256 bytes
+    let tag_address = super_block.tag_start_address() + tag.index() * TagBlock::size();
+    bytes[inode_address as usize + 10] ^= 0xff;
+    bytes[tag_address as usize + 10] ^= 0xff;
+
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+    assert_eq!(
+        Disk::open_disk(&mut handler, &mut manager).err(),
+        Some(VoxFSError::CorruptedTag)
+    );
+
+    let (mut disk, report) = Disk::open_disk_tolerant(&mut handler, &mut manager).unwrap();
+
+    assert!(!report.is_clean());
+    let skipped: Vec<(RecordKind, u64, u64)> = report
+        .skipped()
+        .iter()
+        .map(|r| (r.kind(), r.index(), r.address()))
+        .collect();
+    assert_eq!(
+        skipped,
+        vec![
+            (RecordKind::Tag, tag.index(), tag_address),
+            (RecordKind::INode, damaged.index(), inode_address)
+        ]
+    );
+    assert_eq!(report.skipped()[1].reason(), &VoxFSError::CorruptedINode);
+
+    // The rest of the disk is usable and the damaged slots are not reused
+    assert_eq!(disk.read_file(intact.index()).unwrap(), vec![2u8; 10]);
+    assert!(disk.inode_with_name("damaged").is_none());
+
+    let new = disk.create_new_file("new", flags, vec![3u8; 10]).unwrap();
+    assert_ne!(new.index(), damaged.index());
+    assert_eq!(disk.read_file(intact.index()).unwrap(), vec![2u8; 10]);
+}
+
+#[test]
+fn test_open_with_options() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+    let file;
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        let tag = disk.create_new_tag("work", TagFlags::default()).unwrap();
+        file = disk.create_new_file("file", flags, vec![1u8; 100]).unwrap();
+        disk.apply_tag(tag.index(), file.index()).unwrap();
+
+        let created = file.creation_time();
+        disk.set_file_times(file.index(), created, created, created)
+            .unwrap();
+    }
+
+    // A read only disk refuses modifications and writes nothing, even when closed
+    let before = handler.as_bytes().to_vec();
+    let options = MountOptions::new()
+        .with_read_only(true)
+        .with_noatime(false)
+        .with_lazy_load(false)
+        .with_check_on_open(true)
+        .with_cache_size(8);
+    let mut disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+
+    assert!(disk.is_read_only());
+    assert_eq!(disk.read_file(file.index()).unwrap(), vec![1u8; 100]);
+    assert_eq!(
+        disk.create_new_file("other", flags, vec![2u8; 10]).err(),
+        Some(VoxFSError::ReadOnly)
+    );
+    assert_eq!(
+        disk.write_boot_area(&vec![]).err(),
+        Some(VoxFSError::ReadOnly)
+    );
+    disk.close().unwrap();
+    assert_eq!(handler.as_bytes().to_vec(), before);
+
+    // The default options leave the access time alone, without noatime it is written when the disk syncs
+    let disk =
+        Disk::open_with_options(&mut handler, &mut manager, MountOptions::default()).unwrap();
+    disk.read_file(file.index()).unwrap();
+    disk.close().unwrap();
+    assert_eq!(handler.as_bytes().to_vec(), before);
+
+    let options = MountOptions::new().with_noatime(false);
+    let disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    disk.read_file(file.index()).unwrap();
+    disk.close().unwrap();
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let inode = disk.list_inodes()[0];
+    assert!(inode.access_time() > file.creation_time());
+    assert_eq!(inode.modified_time(), file.creation_time());
+    assert!(!disk.verifies_reads());
+    disk.close().unwrap();
+
+    // Verifying reads needs content hashes to check them against
+    let options = MountOptions::new().with_verify_reads(true);
+    assert_eq!(
+        Disk::open_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::NoContentHasher)
+    );
+}
+
+#[test]
+fn test_check_on_open() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_metadata_mirror(true);
+
+    let file = {
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+        disk.create_new_file("file", INodeFlags::default(), vec![1u8; 10])
+            .unwrap()
+    };
+
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + file.index() * 256; // Inodes are 256 bytes
+    bytes[inode_address as usize + 10] ^= 0xff;
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+
+    // The mirror lets the disk open but a scrub still finds the damaged record
+    assert!(Disk::open_disk(&mut handler, &mut manager).is_ok());
+
+    let options = MountOptions::new().with_check_on_open(true);
+    assert_eq!(
+        Disk::open_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::FailedCheckOnOpen)
+    );
+}
+
+#[test]
+fn test_truncated_image() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let kept = disk
+        .create_new_file("kept", flags, vec![1u8; 8000])
+        .unwrap();
+    disk.create_new_file("lost", flags, vec![2u8; 4096 * 10])
+        .unwrap();
+    disk.close().unwrap();
+
+    let bytes = handler.into_bytes();
+    let data_start = SuperBlock::from_bytes(&bytes).unwrap().data_start_address();
+    let truncated = |length: u64| MemoryDiskHandler::from_bytes(bytes[..length as usize].to_vec());
+
+    // Losing free blocks leaves every file readable
+    let mut handler = truncated(data_start + 4096 * 12);
+    assert!(Disk::open_disk(&mut handler, &mut manager).is_ok());
+
+    let mut handler = truncated(data_start + 4096 * 5);
+    assert_eq!(
+        Disk::open_disk(&mut handler, &mut manager).err(),
+        Some(VoxFSError::ImageTruncated {
+            expected: data_start + 4096 * 12,
+            actual: data_start + 4096 * 5,
+        })
+    );
+
+    let options = MountOptions::new().with_salvage(true);
+    let mut disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    assert!(disk.is_read_only());
+    assert_eq!(disk.data_block_count(), 5);
+    assert_eq!(disk.read_file(kept.index()).unwrap(), vec![1u8; 8000]);
+    assert_eq!(
+        disk.create_new_file("new", flags, vec![3]).err(),
+        Some(VoxFSError::ReadOnly)
+    );
+    drop(disk);
+
+    // Nothing can be salvaged without the tables
+    let mut handler = truncated(data_start - 4096);
+    assert_eq!(
+        Disk::open_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::ImageTruncated {
+            expected: data_start,
+            actual: data_start - 4096,
+        })
+    );
+}
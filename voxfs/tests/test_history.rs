@@ -0,0 +1,126 @@
+extern crate voxfs;
+use voxfs::{
+    Disk, FormatOptions, HistoryOperation, HistoryRecord, INodeFlags, TagFlags, VoxFSError,
+};
+
+mod common;
+use common::*;
+
+fn format_with_history(handler: &mut Handler, manager: &mut Manager, history_size: u64) {
+    let options = FormatOptions::new().with_history_size(history_size);
+
+    Disk::make_new_filesystem_with_options(handler, manager, options).unwrap();
+}
+
+#[test]
+fn test_no_history_by_default() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    disk.create_new_file("a", INodeFlags::default(), vec![1, 2, 3])
+        .unwrap();
+
+    assert!(!disk.has_history());
+    assert_eq!(disk.history_size(), 0);
+    assert!(disk.history().unwrap().is_empty());
+}
+
+#[test]
+fn test_records_changes() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    format_with_history(&mut handler, &mut manager, 4096);
+
+    {
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        assert!(disk.has_history());
+        assert_eq!(disk.history_size(), 4096);
+
+        let tag = disk.create_new_tag("work", TagFlags::default()).unwrap();
+        let file = disk
+            .create_new_file("notes", INodeFlags::default(), vec![0u8; 10])
+            .unwrap();
+        disk.apply_tag(tag.index(), file.index()).unwrap();
+        disk.append_file_bytes(file.index(), &vec![1u8; 5]).unwrap();
+    }
+
+    // The sequence carries on after reopening
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let file = disk.inode_with_name("notes").unwrap();
+    disk.delete_file(file).unwrap();
+
+    let history = disk.history().unwrap();
+    let operations: Vec<HistoryOperation> = history.iter().map(|r| r.operation()).collect();
+
+    // The tag removed by the deletion is not recorded separately
+    assert_eq!(
+        operations,
+        vec![
+            HistoryOperation::CreateTag,
+            HistoryOperation::CreateFile,
+            HistoryOperation::ApplyTag,
+            HistoryOperation::AppendFile,
+            HistoryOperation::DeleteFile,
+        ]
+    );
+
+    let sequences: Vec<u64> = history.iter().map(|r| r.sequence()).collect();
+    assert_eq!(sequences, vec![1, 2, 3, 4, 5]);
+
+    assert_eq!(history[2].name(), "work");
+    assert_eq!(history[2].target(), Some(file));
+    assert_eq!(history[4].name(), "notes");
+    assert_eq!(history[4].subject(), file);
+    assert_eq!(history[4].target(), None);
+}
+
+#[test]
+fn test_oldest_records_overwritten() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    format_with_history(&mut handler, &mut manager, 1);
+
+    let capacity = 4096 / HistoryRecord::size();
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+    for i in 0..capacity + 3 {
+        disk.create_new_tag(&format!("tag{}", i), TagFlags::default())
+            .unwrap();
+    }
+
+    let history = disk.history().unwrap();
+
+    assert_eq!(history.len() as u64, capacity);
+    assert_eq!(history.first().unwrap().sequence(), 4);
+    assert_eq!(history.first().unwrap().name(), "tag3");
+    assert_eq!(history.last().unwrap().sequence(), capacity + 3);
+}
+
+#[test]
+fn test_history_leaves_data_space() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+
+    let disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let blocks = disk.available_data_blocks();
+    drop(disk);
+
+    let mut handler = Handler::new(4096 * 400);
+    format_with_history(&mut handler, &mut manager, 8192 + 1);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.history_size(), 4096 * 3);
+    assert_eq!(disk.available_data_blocks(), blocks - 3);
+}
+
+#[test]
+fn test_invalid_history_size() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_history_size(4096 * (u16::MAX as u64 + 1));
+
+    let result = Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options);
+
+    assert_eq!(result.err(), Some(VoxFSError::InvalidHistorySize));
+}
@@ -0,0 +1,114 @@
+extern crate voxfs;
+use voxfs::{
+    Disk, FormatOptions, INodeFlags, IntegrityManifest, ManifestRegion, TagFlags, VoxFSError,
+};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_integrity_manifest() {
+    let mut handler = Handler::new(4096 * 60);
+    let mut manager = HashingManager {};
+    let flags = INodeFlags::default();
+    let options = FormatOptions::new().with_boot_area_size(4096);
+
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    disk.write_boot_area(&vec![0xeb; 16]).unwrap();
+    let tag = disk
+        .create_new_tag("firmware", TagFlags::default())
+        .unwrap();
+    let first = disk.create_new_file("first", flags, vec![1; 9000]).unwrap();
+    let second = disk.create_new_file("second", flags, vec![2; 100]).unwrap();
+    disk.apply_tag(tag.index(), first.index()).unwrap();
+
+    let manifest = disk.export_integrity_manifest().unwrap();
+    let regions: Vec<ManifestRegion> = manifest.entries().iter().map(|(r, _)| *r).collect();
+    assert_eq!(regions[0], ManifestRegion::BootArea);
+    assert!(regions.contains(&ManifestRegion::Tag(tag.index())));
+    assert!(regions.contains(&ManifestRegion::INode(first.index())));
+    assert!(regions.contains(&ManifestRegion::Extent {
+        inode: first.index(),
+        start: 0,
+        end: 2
+    }));
+    assert!(disk.verify_against_manifest(&manifest).unwrap().is_empty());
+
+    // Reading doesn't change the manifest, and it survives being written out as text
+    disk.read_file(first.index()).unwrap();
+    let text = manifest.to_string();
+    let parsed = IntegrityManifest::from_text(&text).unwrap();
+    assert!(disk.verify_against_manifest(&parsed).unwrap().is_empty());
+
+    disk.append_file_bytes(second.index(), &vec![3; 10])
+        .unwrap();
+    assert_eq!(
+        disk.verify_against_manifest(&manifest).unwrap(),
+        [
+            ManifestRegion::INode(second.index()),
+            ManifestRegion::Extent {
+                inode: second.index(),
+                start: 3,
+                end: 3
+            }
+        ]
+    );
+
+    disk.delete_file(first.index()).unwrap();
+    let differences = disk.verify_against_manifest(&manifest).unwrap();
+    assert!(differences.contains(&ManifestRegion::Tag(tag.index())));
+    assert!(differences.contains(&ManifestRegion::INode(first.index())));
+
+    let current = disk.export_integrity_manifest().unwrap();
+    assert!(current.hash(ManifestRegion::INode(first.index())).is_none());
+    assert!(disk.verify_against_manifest(&current).unwrap().is_empty());
+}
+
+#[test]
+fn test_integrity_manifest_non_ascii_names() {
+    // U+0101 and U+0201 only differ above U+00FF
+    let manifests: Vec<(IntegrityManifest, u64, u64)> = ["\u{101}", "\u{201}"]
+        .iter()
+        .map(|name| {
+            let mut handler = Handler::new(4096 * 40);
+            let mut manager = HashingManager {};
+
+            let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+            let tag = disk.create_new_tag(name, TagFlags::default()).unwrap();
+            let file = disk
+                .create_new_file(name, INodeFlags::default(), vec![1; 10])
+                .unwrap();
+
+            return (
+                disk.export_integrity_manifest().unwrap(),
+                tag.index(),
+                file.index(),
+            );
+        })
+        .collect();
+
+    let (first, tag, file) = &manifests[0];
+    let (second, _, _) = &manifests[1];
+    assert_ne!(
+        first.hash(ManifestRegion::Tag(*tag)),
+        second.hash(ManifestRegion::Tag(*tag))
+    );
+    assert_ne!(
+        first.hash(ManifestRegion::INode(*file)),
+        second.hash(ManifestRegion::INode(*file))
+    );
+}
+
+#[test]
+fn test_integrity_manifest_without_hasher() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    match disk.export_integrity_manifest() {
+        Err(VoxFSError::NoContentHasher) => {}
+        r => panic!("Expected NoContentHasher, got {:?}", r),
+    }
+}
@@ -1,5 +1,7 @@
 extern crate voxfs;
-use voxfs::{Disk, INodeFlags, TagFlags};
+use std::cell::Cell;
+use std::rc::Rc;
+use voxfs::{Disk, INodeFlags, NameIssue, NamePattern, NamePolicy, SortOrder, TagFlags};
 
 mod common;
 use common::*;
@@ -227,3 +229,185 @@ fn test_list_inodes_with_tags_2() {
             .unwrap()
     );
 }
+
+#[test]
+fn test_list_inodes_sorted() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+
+    for (name, size) in [("c", 30), ("a", 10), ("d", 5), ("b", 20)].iter() {
+        let node = disk.create_new_file(name, flags, vec![0u8; *size]).unwrap();
+        disk.apply_tag(tag.index(), node.index()).unwrap();
+    }
+
+    // Reuse the slot of a deleted file so the index order is not the creation order
+    disk.delete_file(disk.inode_with_name("c").unwrap())
+        .unwrap();
+    let node = disk.create_new_file("e", flags, vec![0u8; 15]).unwrap();
+    disk.apply_tag(tag.index(), node.index()).unwrap();
+
+    let names =
+        |inodes: Vec<voxfs::INode>| inodes.iter().map(|i| i.name()).collect::<Vec<String>>();
+
+    assert_eq!(
+        names(disk.list_inodes_sorted(SortOrder::Index)),
+        vec!["e", "a", "d", "b"]
+    );
+    assert_eq!(
+        names(disk.list_inodes_sorted(SortOrder::Name)),
+        vec!["a", "b", "d", "e"]
+    );
+    assert_eq!(
+        names(disk.list_inodes_sorted(SortOrder::Size)),
+        vec!["d", "a", "e", "b"]
+    );
+    assert_eq!(
+        names(
+            disk.list_nodes_with_tag_sorted(tag.index(), SortOrder::Name)
+                .unwrap()
+        ),
+        vec!["a", "b", "d", "e"]
+    );
+
+    let by_time = disk.list_inodes_sorted(SortOrder::ModifiedTime);
+    assert_eq!(by_time.len(), 4);
+    assert!(by_time
+        .windows(2)
+        .all(|w| w[0].modified_time() <= w[1].modified_time()));
+}
+
+#[test]
+fn test_list_pages() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: Handler::new(4096 * 100),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+
+    for i in 0..40 {
+        let node = disk
+            .create_new_file(&format!("file_{}", i), flags, Vec::new())
+            .unwrap();
+        disk.apply_tag(tag.index(), node.index()).unwrap();
+    }
+
+    let all = disk.list_inodes();
+    assert_eq!(disk.list_inodes_page(0, 10), all[..10].to_vec());
+    assert_eq!(disk.list_inodes_page(35, 10), all[35..].to_vec());
+    assert!(disk.list_inodes_page(40, 10).is_empty());
+
+    // The first page fits in the tag block, so no indirect blocks are read
+    let before = reads.get();
+    assert_eq!(
+        disk.list_nodes_with_tag_page(tag.index(), 0, 10).unwrap(),
+        all[..10].to_vec()
+    );
+    assert_eq!(reads.get(), before);
+
+    assert_eq!(
+        disk.list_nodes_with_tag_page(tag.index(), 10, 10).unwrap(),
+        all[10..20].to_vec()
+    );
+    assert_eq!(
+        disk.list_nodes_with_tag_page(tag.index(), 35, 10).unwrap(),
+        all[35..].to_vec()
+    );
+    assert!(disk
+        .list_nodes_with_tag_page(tag.index(), 0, 0)
+        .unwrap()
+        .is_empty());
+    assert!(disk
+        .list_nodes_with_tag_page(tag.index() + 1, 0, 10)
+        .is_err());
+}
+
+#[test]
+fn test_find_inodes() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    for name in ["b.log", "notes.txt", "a.log", "a.log.1", "report-7.txt"].iter() {
+        disk.create_new_file(name, INodeFlags::default(), vec![1])
+            .unwrap();
+    }
+
+    let names = |pattern: &str| -> Vec<String> {
+        return disk
+            .find_inodes(&NamePattern::parse(pattern).unwrap())
+            .iter()
+            .map(|i| i.name())
+            .collect();
+    };
+
+    // Results are in index order, which is creation order here
+    assert_eq!(names("*.log"), vec!["b.log", "a.log"]);
+    assert_eq!(names("*.txt"), vec!["notes.txt", "report-7.txt"]);
+    assert_eq!(names("report-[0-9].txt"), vec!["report-7.txt"]);
+    assert_eq!(names("?.log*").len(), 3);
+    assert!(names("*.md").is_empty());
+}
+
+#[test]
+fn test_name_report() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    assert!(disk.name_report().is_clean());
+
+    disk.set_name_policy(NamePolicy::Allow).unwrap();
+
+    let mut indices = Vec::new();
+
+    for name in [
+        "notes",
+        "notes",
+        "Notes",
+        "naïve",
+        " spaced",
+        "a_much_longer_name",
+    ]
+    .iter()
+    {
+        indices.push(
+            disk.create_new_file(name, INodeFlags::default(), vec![1u8; 10])
+                .unwrap()
+                .index(),
+        );
+    }
+
+    let report = disk.name_report();
+    assert_eq!(report.files(), 6);
+    assert!(!report.is_clean());
+
+    assert_eq!(report.duplicates().len(), 1);
+    assert_eq!(report.duplicates()[0].name(), "notes");
+    assert_eq!(report.duplicates()[0].inodes(), &indices[..2]);
+
+    assert_eq!(report.case_collisions().len(), 1);
+    assert_eq!(report.case_collisions()[0].inodes(), &indices[..3]);
+
+    assert_eq!(
+        report.longest()[0],
+        (indices[5], "a_much_longer_name".to_string())
+    );
+
+    let odd = report.odd_names();
+    assert_eq!(odd.len(), 2);
+    assert_eq!(
+        (odd[0].index(), odd[0].issues()),
+        (indices[3], &[NameIssue::NonUtf8][..])
+    );
+    assert_eq!(odd[1].issues(), [NameIssue::SurroundingWhitespace]);
+}
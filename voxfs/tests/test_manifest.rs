@@ -0,0 +1,127 @@
+#![cfg(feature = "std")]
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, Manifest, ManifestTag, TagFlags};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_export_manifest() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let work = disk.create_new_tag("work", TagFlags::default()).unwrap();
+    let archived = disk
+        .create_new_tag("archived", TagFlags::new(true, false))
+        .unwrap();
+
+    for name in ["notes", "budget", "old"].iter() {
+        let file = disk
+            .create_new_file(name, INodeFlags::default(), vec![1u8; 4])
+            .unwrap();
+
+        if *name != "old" {
+            disk.apply_tag(work.index(), file.index()).unwrap();
+        } else {
+            disk.apply_tag(archived.index(), file.index()).unwrap();
+        }
+    }
+
+    let manifest = disk.export_manifest().unwrap();
+
+    assert_eq!(
+        manifest,
+        Manifest {
+            tags: vec![
+                ManifestTag {
+                    name: "archived".to_string(),
+                    read: true,
+                    write: false,
+                    files: vec!["old".to_string()],
+                },
+                ManifestTag {
+                    name: "root".to_string(),
+                    read: true,
+                    write: true,
+                    files: vec![],
+                },
+                ManifestTag {
+                    name: "work".to_string(),
+                    read: TagFlags::default().read(),
+                    write: TagFlags::default().write(),
+                    files: vec!["budget".to_string(), "notes".to_string()],
+                },
+            ]
+        }
+    );
+
+    let json = serde_json::to_string(&manifest).unwrap();
+    assert_eq!(serde_json::from_str::<Manifest>(&json).unwrap(), manifest);
+}
+
+#[test]
+fn test_apply_manifest() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let work = disk.create_new_tag("work", TagFlags::default()).unwrap();
+    let notes = disk
+        .create_new_file("notes", INodeFlags::default(), vec![1u8; 4])
+        .unwrap();
+    disk.create_new_file("budget", INodeFlags::default(), vec![2u8; 4])
+        .unwrap();
+    disk.apply_tag(work.index(), notes.index()).unwrap();
+
+    let manifest = Manifest {
+        tags: vec![
+            ManifestTag {
+                name: "archived".to_string(),
+                read: true,
+                write: false,
+                files: vec!["budget".to_string(), "gone".to_string()],
+            },
+            ManifestTag {
+                name: "work".to_string(),
+                read: true,
+                write: true,
+                files: vec!["budget".to_string(), "notes".to_string()],
+            },
+        ],
+    };
+
+    let report = disk.apply_manifest(&manifest).unwrap();
+
+    assert_eq!(report.created_tags(), &vec!["archived".to_string()]);
+    assert_eq!(report.applied(), 2);
+    assert_eq!(report.missing_files(), &vec!["gone".to_string()]);
+
+    let archived = disk.tag_with_name("archived").unwrap();
+    let tag = disk
+        .list_tags()
+        .into_iter()
+        .find(|t| t.index() == archived)
+        .unwrap();
+    assert!(tag.flags().read());
+    assert!(!tag.flags().write());
+
+    // Applying it again changes nothing
+    let report = disk.apply_manifest(&manifest).unwrap();
+    assert!(report.created_tags().is_empty());
+    assert_eq!(report.applied(), 0);
+
+    // A new filesystem has a root tag
+    let mut expected = manifest.clone();
+    expected.tags[0].files = vec!["budget".to_string()];
+    expected.tags.insert(
+        1,
+        ManifestTag {
+            name: "root".to_string(),
+            read: true,
+            write: true,
+            files: vec![],
+        },
+    );
+    assert_eq!(disk.export_manifest().unwrap(), expected);
+}
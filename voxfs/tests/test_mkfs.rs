@@ -1,5 +1,8 @@
 extern crate voxfs;
-use voxfs::{ByteSerializable, Disk, DiskHandler, OSManager, TagBlock, TagFlags};
+use voxfs::{
+    ByteSerializable, Disk, DiskHandler, Exhaustion, FormatOptions, INode, INodeFlags, OSManager,
+    TagBlock, TagFlags, VoxFSError,
+};
 
 mod common;
 use common::*;
@@ -23,6 +26,7 @@ fn test_create_new_disk() {
         Disk::make_new_filesystem_with_root(&mut handler, &mut manager, root_tag.clone()).unwrap();
 
     let tags = disk.list_tags();
+    drop(disk);
 
     let mut tag_bitmap_bits = vec![0u8; 4096];
     tag_bitmap_bits[0] = 0b1;
@@ -38,3 +42,257 @@ fn test_create_new_disk() {
     assert_eq!(tags.len(), 1);
     assert_eq!(tags[0], root_tag);
 }
+
+#[test]
+fn test_boot_area() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    let boot_image: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+
+    {
+        let options = FormatOptions::new().with_boot_area_size(boot_image.len() as u64);
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+        // The boot area is rounded up to whole blocks
+        assert_eq!(disk.boot_area_size(), 4096 * 3);
+        assert_eq!(
+            disk.write_boot_area(&vec![0u8; 4096 * 3 + 1]),
+            Err(VoxFSError::InvalidBootAreaSize)
+        );
+
+        disk.write_boot_area(&boot_image).unwrap();
+        disk.create_new_file(
+            "file",
+            INodeFlags::new(true, true, true, false),
+            vec![0x5a; 5000],
+        )
+        .unwrap();
+    }
+
+    // The boot area sits between the super block and the tag bitmap
+    assert_eq!(handler.read_bytes(4096, 10_000).unwrap(), boot_image);
+    assert_eq!(handler.read_bytes(4096 * 4, 1).unwrap(), vec![0b1]);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let mut expected = boot_image.clone();
+    expected.resize(4096 * 3, 0);
+
+    assert_eq!(disk.read_boot_area().unwrap(), expected);
+
+    let index = disk.inode_with_name("file").unwrap();
+    assert_eq!(disk.read_file(index).unwrap(), vec![0x5a; 5000]);
+    disk.close().unwrap();
+
+    // Writing the boot area marks the disk dirty like any other modification
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(!disk.is_dirty());
+    disk.write_boot_area(&boot_image).unwrap();
+    assert!(disk.is_dirty());
+}
+
+#[test]
+fn test_boot_area_too_large() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_boot_area_size(4096 * 400);
+
+    assert_eq!(
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::InvalidBootAreaSize)
+    );
+}
+
+#[test]
+fn test_block_size() {
+    for block_size in [512, 8192].iter() {
+        let mut handler = Handler::new(4096 * 400);
+        let mut manager = Manager::new();
+        let options = FormatOptions::new().with_block_size(*block_size);
+
+        {
+            let mut disk =
+                Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options)
+                    .unwrap();
+            assert_eq!(disk.block_size(), *block_size);
+
+            let file = disk
+                .create_new_file("file", INodeFlags::default(), vec![0x5a; 20_000])
+                .unwrap();
+            let tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+            disk.apply_tag(tag.index(), file.index()).unwrap();
+        }
+
+        let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        assert_eq!(disk.block_size(), *block_size);
+
+        let index = disk.inode_with_name("file").unwrap();
+        assert_eq!(disk.read_file(index).unwrap(), vec![0x5a; 20_000]);
+
+        let tag = disk.tag_with_name("tag").unwrap();
+        assert_eq!(disk.list_nodes_with_tag(tag).unwrap().len(), 1);
+    }
+}
+
+#[test]
+fn test_invalid_block_size() {
+    for block_size in [0, 256, 1000, 6144].iter() {
+        let mut handler = Handler::new(4096 * 400);
+        let mut manager = Manager::new();
+        let options = FormatOptions::new().with_block_size(*block_size);
+
+        let result = Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options);
+
+        assert_eq!(result.err(), Some(VoxFSError::InvalidBlockSize));
+    }
+}
+
+#[test]
+fn test_metadata_ratios() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+
+    // A slot for every 2KiB, three inodes for each tag, rounded up to fill the blocks of the tables
+    let disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let info = disk.disk_info();
+    assert_eq!(info.number_of_files() + info.free_file_slots(), 608);
+    assert_eq!(info.number_of_tags() + info.free_tag_slots(), 208);
+    let default_blocks = disk.data_block_count();
+    drop(disk);
+
+    let mut handler = Handler::new(4096 * 400);
+    let options = FormatOptions::new()
+        .with_bytes_per_inode(8192)
+        .with_inodes_per_tag(7);
+
+    {
+        let disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+        let info = disk.disk_info();
+        assert_eq!(info.number_of_files() + info.free_file_slots(), 176);
+        assert_eq!(info.number_of_tags() + info.free_tag_slots(), 32);
+        assert!(disk.data_block_count() > default_blocks);
+    }
+
+    // The counts are stored in the super block
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.free_file_slots(), 176);
+}
+
+#[test]
+fn test_invalid_metadata_ratio() {
+    let options = [
+        FormatOptions::new().with_inodes_per_tag(0),
+        FormatOptions::new().with_bytes_per_inode(INode::size() - 1),
+        // The tables would fill the whole disk
+        FormatOptions::new().with_bytes_per_inode(INode::size()),
+    ];
+
+    for options in options.iter() {
+        let mut handler = Handler::new(4096 * 400);
+        let mut manager = Manager::new();
+
+        let result =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options.clone());
+
+        assert_eq!(result.err(), Some(VoxFSError::InvalidMetadataRatio));
+    }
+}
+
+#[test]
+fn test_projected_exhaustion() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_bytes_per_inode(16384);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+    assert_eq!(disk.disk_info().projected_exhaustion(), None);
+
+    // There are about four data blocks for each inode, so files of a block run out of inodes first
+
+    for i in 0..3 {
+        disk.create_new_file(
+            &format!("small_{}", i),
+            INodeFlags::default(),
+            vec![1u8; 10],
+        )
+        .unwrap();
+    }
+
+    assert_eq!(
+        disk.disk_info().projected_exhaustion(),
+        Some(Exhaustion::INodes)
+    );
+
+    for i in 0..3 {
+        disk.create_new_file(
+            &format!("large_{}", i),
+            INodeFlags::default(),
+            vec![1u8; 4096 * 80],
+        )
+        .unwrap();
+    }
+
+    assert_eq!(
+        disk.disk_info().projected_exhaustion(),
+        Some(Exhaustion::DataBlocks)
+    );
+}
+
+#[test]
+fn test_geometry() {
+    let mut handler = Handler::new(4096 * 400);
+    let mut manager = Manager::new();
+
+    let disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let geometry = disk.geometry();
+
+    assert_eq!(geometry.block_size(), 4096);
+    assert_eq!(geometry.boot_area_blocks(), 0);
+    assert_eq!(geometry.tag_bitmap_start(), 4096);
+    assert_eq!(geometry.inode_bitmap_start(), 8192);
+    assert_eq!(geometry.block_bitmap_start(), 12288);
+    assert_eq!(geometry.bitmaps_end(), 0x4000);
+    assert_eq!(geometry.tag_table_start(), 0x4000);
+    assert_eq!(geometry.mirror_tag_table_start(), None);
+    assert_eq!(geometry.history_start(), None);
+    assert_eq!(geometry.block_count(), disk.data_block_count());
+    assert_eq!(
+        geometry.inode_table_start(),
+        0x4000 + geometry.tag_count() * TagBlock::size()
+    );
+    assert_eq!(
+        geometry.data_start(),
+        geometry.inode_table_start() + geometry.inode_count() * INode::size()
+    );
+    drop(disk);
+
+    let options = FormatOptions::new()
+        .with_boot_area_size(4096 * 2)
+        .with_metadata_mirror(true)
+        .with_history_size(4096);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    let geometry = disk.geometry();
+    let tag_table_size = geometry.inode_table_start() - geometry.tag_table_start();
+
+    assert_eq!(geometry.boot_area_blocks(), 2);
+    assert_eq!(geometry.tag_bitmap_start(), 4096 * 3);
+    assert_eq!(geometry.history_blocks(), 1);
+    assert_eq!(
+        geometry.mirror_inode_table_start(),
+        Some(geometry.mirror_tag_table_start().unwrap() + tag_table_size)
+    );
+    assert_eq!(geometry.history_start(), Some(geometry.data_start() - 4096));
+
+    // The first file is written to the first data block
+    disk.create_new_file("file", INodeFlags::default(), vec![9u8; 10])
+        .unwrap();
+    assert_eq!(
+        disk.handler()
+            .read_bytes(geometry.data_block_address(0), 10)
+            .unwrap(),
+        vec![9u8; 10]
+    );
+}
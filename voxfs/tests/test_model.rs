@@ -0,0 +1,258 @@
+extern crate voxfs;
+use proptest::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use voxfs::{Disk, INodeFlags, MemoryDiskError, MemoryDiskHandler, TagFlags};
+
+mod common;
+use common::Manager;
+
+const DISK_SIZE: usize = 4096 * 1024;
+const FILE_NAMES: usize = 20;
+const TAG_NAMES: usize = 4;
+
+#[derive(Debug, Clone)]
+enum Operation {
+    CreateFile(usize, usize),
+    AppendFile(usize, usize),
+    TruncateFile(usize, usize),
+    DeleteFile(usize),
+    CreateTag(usize),
+    DeleteTag(usize),
+    ApplyTag(usize, usize),
+    RemoveTag(usize, usize),
+}
+
+fn file_name(i: usize) -> String {
+    return format!("file_{}", i);
+}
+
+fn tag_name(i: usize) -> String {
+    return format!("tag_{}", i);
+}
+
+/// Generates file contents that differ between files and operations so misplaced blocks are noticed.
+fn contents(seed: usize, size: usize) -> Vec<u8> {
+    return (0..size).map(|i| (i * 31 + seed * 7) as u8).collect();
+}
+
+/// The reference model, files by name with their contents and tags by name with the names of their members.
+#[derive(Debug, Default, PartialEq)]
+struct Model {
+    files: BTreeMap<String, Vec<u8>>,
+    tags: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Model {
+    fn new() -> Self {
+        let mut model = Self::default();
+        model.tags.insert("root".to_string(), BTreeSet::new());
+
+        return model;
+    }
+
+    /// Applies an operation to the model, returning whether it should succeed.
+    fn apply(&mut self, operation: &Operation) -> bool {
+        match operation {
+            Operation::CreateFile(f, size) => {
+                let name = file_name(*f);
+
+                if self.files.contains_key(&name) {
+                    return false;
+                }
+
+                self.files.insert(name, contents(*f, *size));
+            }
+            Operation::AppendFile(f, size) => match self.files.get_mut(&file_name(*f)) {
+                Some(file) => file.extend(contents(*f + 1, *size)),
+                None => return false,
+            },
+            Operation::TruncateFile(f, size) => match self.files.get_mut(&file_name(*f)) {
+                Some(file) => file.resize(*size, 0),
+                None => return false,
+            },
+            Operation::DeleteFile(f) => {
+                let name = file_name(*f);
+
+                if self.files.remove(&name).is_none() {
+                    return false;
+                }
+
+                for members in self.tags.values_mut() {
+                    members.remove(&name);
+                }
+            }
+            Operation::CreateTag(t) => {
+                let name = tag_name(*t);
+
+                if self.tags.contains_key(&name) {
+                    return false;
+                }
+
+                self.tags.insert(name, BTreeSet::new());
+            }
+            Operation::DeleteTag(t) => {
+                if self.tags.remove(&tag_name(*t)).is_none() {
+                    return false;
+                }
+            }
+            Operation::ApplyTag(t, f) => {
+                let name = file_name(*f);
+
+                if !self.files.contains_key(&name) {
+                    return false;
+                }
+
+                match self.tags.get_mut(&tag_name(*t)) {
+                    Some(members) => return members.insert(name),
+                    None => return false,
+                }
+            }
+            Operation::RemoveTag(t, f) => match self.tags.get_mut(&tag_name(*t)) {
+                Some(members) => return members.remove(&file_name(*f)),
+                None => return false,
+            },
+        }
+
+        return true;
+    }
+}
+
+/// Applies an operation to the disk, returning whether it succeeded.
+fn apply(disk: &mut Disk<MemoryDiskError>, operation: &Operation) -> bool {
+    let flags = INodeFlags::new(true, true, true, false);
+
+    return match operation {
+        Operation::CreateFile(f, size) => disk
+            .create_new_file(&file_name(*f), flags, contents(*f, *size))
+            .is_ok(),
+        Operation::AppendFile(f, size) => match disk.inode_with_name(&file_name(*f)) {
+            Some(index) => disk
+                .append_file_bytes(index, &contents(*f + 1, *size))
+                .is_ok(),
+            None => false,
+        },
+        Operation::TruncateFile(f, size) => match disk.inode_with_name(&file_name(*f)) {
+            Some(index) => disk.truncate_file(index, *size as u64).is_ok(),
+            None => false,
+        },
+        Operation::DeleteFile(f) => match disk.inode_with_name(&file_name(*f)) {
+            Some(index) => disk.delete_file(index).is_ok(),
+            None => false,
+        },
+        Operation::CreateTag(t) => disk
+            .create_new_tag(&tag_name(*t), TagFlags::new(true, true))
+            .is_ok(),
+        Operation::DeleteTag(t) => match disk.tag_with_name(&tag_name(*t)) {
+            Some(index) => disk.delete_tag(index).is_ok(),
+            None => false,
+        },
+        Operation::ApplyTag(t, f) => {
+            match (
+                disk.tag_with_name(&tag_name(*t)),
+                disk.inode_with_name(&file_name(*f)),
+            ) {
+                (Some(tag), Some(inode)) => disk.apply_tag(tag, inode).is_ok(),
+                _ => false,
+            }
+        }
+        Operation::RemoveTag(t, f) => {
+            match (
+                disk.tag_with_name(&tag_name(*t)),
+                disk.inode_with_name(&file_name(*f)),
+            ) {
+                (Some(tag), Some(inode)) => disk.remove_tag_from_inode(tag, inode).is_ok(),
+                _ => false,
+            }
+        }
+    };
+}
+
+/// Reads the observable state of the disk in the same form as the model.
+fn observe(disk: &Disk<MemoryDiskError>) -> Model {
+    let mut model = Model::default();
+
+    for inode in disk.list_inodes() {
+        model
+            .files
+            .insert(inode.name(), disk.read_file(inode.index()).unwrap());
+    }
+
+    for tag in disk.list_tags() {
+        let members = disk
+            .list_nodes_with_tag(tag.index())
+            .unwrap()
+            .iter()
+            .map(|n| n.name())
+            .collect();
+
+        model.tags.insert(tag.name_string(), members);
+    }
+
+    return model;
+}
+
+fn arb_operation() -> impl Strategy<Value = Operation> {
+    let file = 0..FILE_NAMES;
+    let tag = 0..TAG_NAMES;
+    let size = 1..4096 * 8usize;
+
+    return prop_oneof![
+        3 => (file.clone(), size.clone()).prop_map(|(f, s)| Operation::CreateFile(f, s)),
+        2 => (file.clone(), size).prop_map(|(f, s)| Operation::AppendFile(f, s)),
+        2 => (file.clone(), 0..4096 * 12usize).prop_map(|(f, s)| Operation::TruncateFile(f, s)),
+        1 => file.clone().prop_map(Operation::DeleteFile),
+        1 => tag.clone().prop_map(Operation::CreateTag),
+        1 => tag.clone().prop_map(Operation::DeleteTag),
+        4 => (tag.clone(), file.clone()).prop_map(|(t, f)| Operation::ApplyTag(t, f)),
+        2 => (tag, file).prop_map(|(t, f)| Operation::RemoveTag(t, f)),
+    ];
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_model(operations in proptest::collection::vec(arb_operation(), 1..60)) {
+        let mut handler = MemoryDiskHandler::new(DISK_SIZE);
+        let mut manager = Manager::new();
+        let mut model = Model::new();
+        let free_blocks;
+        let used_blocks;
+
+        {
+            let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+            free_blocks = disk.available_data_blocks();
+
+            for operation in operations.iter() {
+                let expected = model.apply(operation);
+                prop_assert_eq!(apply(&mut disk, operation), expected, "{:?}", operation);
+                prop_assert_eq!(&observe(&disk), &model, "{:?}", operation);
+            }
+
+            used_blocks = free_blocks - disk.available_data_blocks();
+        }
+
+        // The state written to the disk must match after reopening
+        let mut handler = MemoryDiskHandler::from_bytes(handler.into_bytes());
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        prop_assert_eq!(&observe(&disk), &model);
+        prop_assert_eq!(free_blocks - disk.available_data_blocks(), used_blocks);
+
+        // Removing everything must return every block
+        for name in model.files.keys() {
+            let index = disk.inode_with_name(name).unwrap();
+            disk.delete_file(index).unwrap();
+        }
+
+        for name in model.tags.keys() {
+            if name != "root" {
+                let index = disk.tag_with_name(name).unwrap();
+                disk.delete_tag(index).unwrap();
+            }
+        }
+
+        prop_assert!(disk.list_inodes().is_empty());
+        prop_assert_eq!(disk.list_tags().len(), 1);
+        prop_assert_eq!(disk.available_data_blocks(), free_blocks);
+    }
+}
@@ -1,5 +1,10 @@
 extern crate voxfs;
-use voxfs::{Disk, INodeFlags};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use voxfs::{
+    BitmapFlushPolicy, Disk, DiskHandler, FormatOptions, INodeFlags, NamePolicy, NewFileSpec,
+    TagBlock, TagFlags, VoxFSError,
+};
 
 mod common;
 use common::*;
@@ -32,6 +37,8 @@ fn test_create_new_file() {
     )
     .unwrap();
 
+    drop(disk);
+
     assert_eq!(
         handler.dump_disk()[32768..32768 + file_contents.len()].to_vec(),
         file_contents
@@ -71,3 +78,562 @@ fn test_file_size() {
     assert_eq!(file_size.actual_size, 4096 * 6 + 33);
     assert_eq!(file_size.physical_size, 4096 * 7);
 }
+
+/// Records the location and size of every write so the batching of writes can be checked.
+struct WriteLogHandler {
+    disk: Handler,
+    writes: Rc<RefCell<Vec<(u64, usize)>>>,
+}
+
+impl DiskHandler<Error> for WriteLogHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), Error> {
+        self.writes.borrow_mut().push((location, bytes.len()));
+
+        return self.disk.write_bytes(bytes, location);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, Error> {
+        return self.disk.read_bytes(location, amount);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), Error> {
+        return self.disk.zero_range(start, end);
+    }
+
+    fn disk_size(&self) -> Result<u64, Error> {
+        return self.disk.disk_size();
+    }
+}
+
+#[test]
+fn test_create_new_file_coalesces_writes() {
+    let writes = Rc::new(RefCell::new(Vec::new()));
+    let mut handler = WriteLogHandler {
+        disk: Handler::new(4096 * 100),
+        writes: writes.clone(),
+    };
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+    let contents: Vec<u8> = (0..4096 * 20 + 10).map(|i| i as u8).collect();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    // Only the file's data is larger than a block
+    writes.borrow_mut().clear();
+    let first = disk
+        .create_new_file("first", flags, contents.clone())
+        .unwrap()
+        .index();
+    let data_writes: Vec<usize> = writes
+        .borrow()
+        .iter()
+        .filter(|(_, size)| *size > 4096)
+        .map(|(_, size)| *size)
+        .collect();
+    assert_eq!(data_writes, vec![4096 * 20 + 10]);
+
+    disk.set_max_io_size(4096 * 8 + 100);
+    writes.borrow_mut().clear();
+    let second = disk
+        .create_new_file("second", flags, contents.clone())
+        .unwrap()
+        .index();
+    let data_writes: Vec<usize> = writes
+        .borrow()
+        .iter()
+        .filter(|(_, size)| *size > 4096)
+        .map(|(_, size)| *size)
+        .collect();
+    assert_eq!(data_writes, vec![4096 * 8, 4096 * 8, 4096 * 4 + 10]);
+
+    assert_eq!(disk.read_file(first).unwrap(), contents);
+    assert_eq!(disk.read_file(second).unwrap(), contents);
+}
+
+#[test]
+fn test_bitmap_flush_policy() {
+    let writes = Rc::new(RefCell::new(Vec::new()));
+    let mut handler = WriteLogHandler {
+        disk: Handler::new(4096 * 100),
+        writes: writes.clone(),
+    };
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    // The tag bitmap is the first thing after the super block so each flush writes there
+    let bitmap_flushes = |writes: &Rc<RefCell<Vec<(u64, usize)>>>| {
+        return writes
+            .borrow()
+            .iter()
+            .filter(|(location, _)| *location == 4096)
+            .count();
+    };
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        disk.set_bitmap_flush_policy(BitmapFlushPolicy::OnSync)
+            .unwrap();
+
+        writes.borrow_mut().clear();
+        for i in 0..10 {
+            disk.create_new_file(&format!("file_{}", i), flags, vec![i as u8; 5000])
+                .unwrap();
+        }
+        assert_eq!(bitmap_flushes(&writes), 0);
+
+        disk.sync().unwrap();
+        assert_eq!(bitmap_flushes(&writes), 1);
+
+        disk.set_bitmap_flush_policy(BitmapFlushPolicy::EveryNOperations(4))
+            .unwrap();
+
+        writes.borrow_mut().clear();
+        for i in 10..20 {
+            disk.create_new_file(&format!("file_{}", i), flags, vec![i as u8; 5000])
+                .unwrap();
+        }
+        assert_eq!(bitmap_flushes(&writes), 2);
+
+        // The last two files are written when the disk is dropped
+    }
+
+    assert_eq!(bitmap_flushes(&writes), 3);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+    // If the bitmaps were stale the new file would overwrite existing data
+    disk.create_new_file("last", flags, vec![0xff; 5000])
+        .unwrap();
+
+    for i in 0..20 {
+        let index = disk.inode_with_name(&format!("file_{}", i)).unwrap();
+        assert_eq!(disk.read_file(index).unwrap(), vec![i as u8; 5000]);
+    }
+}
+
+#[test]
+fn test_file_size_is_cached() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: Handler::new(4096 * 200),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let (file, expected) = {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+        // Interleave two files so the first has more than 5 extents and needs an indirect inode
+        let file = disk
+            .create_new_file("file", flags, vec![1u8; 4096])
+            .unwrap()
+            .index();
+        let other = disk
+            .create_new_file("other", flags, vec![2u8; 4096])
+            .unwrap()
+            .index();
+
+        for _ in 0..6 {
+            disk.append_file_bytes(file, &vec![1u8; 4096]).unwrap();
+            disk.append_file_bytes(other, &vec![2u8; 4096]).unwrap();
+        }
+
+        // Known from the allocations without reading anything
+        let before = reads.get();
+        let size = disk.file_size(file).unwrap();
+        assert_eq!(reads.get(), before);
+        assert_eq!(size.physical_size, 4096 * 7);
+
+        (file, size.physical_size)
+    };
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+    // The indirect inodes were walked when the disk was opened
+    let before = reads.get();
+    assert_eq!(disk.file_size(file).unwrap().physical_size, expected);
+    assert_eq!(reads.get(), before);
+}
+
+#[test]
+fn test_name_policy() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.name_policy(), NamePolicy::Reject);
+
+    disk.create_new_file("photo.jpg", flags, Vec::new())
+        .unwrap();
+
+    match disk.create_new_file("photo.jpg", flags, Vec::new()) {
+        Err(VoxFSError::FileExistsWithName(name)) => assert_eq!(name, "photo.jpg"),
+        r => panic!("Expected FileExistsWithName, got {:?}", r.map(|i| i.name())),
+    }
+
+    disk.set_name_policy(NamePolicy::AutoSuffix).unwrap();
+    assert_eq!(
+        disk.create_new_file("photo.jpg", flags, Vec::new())
+            .unwrap()
+            .name(),
+        "photo (1).jpg"
+    );
+    assert_eq!(
+        disk.create_new_file("photo.jpg", flags, Vec::new())
+            .unwrap()
+            .name(),
+        "photo (2).jpg"
+    );
+
+    disk.create_new_file(".hidden", flags, Vec::new()).unwrap();
+    assert_eq!(
+        disk.create_new_file(".hidden", flags, Vec::new())
+            .unwrap()
+            .name(),
+        ".hidden (1)"
+    );
+
+    drop(disk);
+
+    // The policy is kept in the super block
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.name_policy(), NamePolicy::AutoSuffix);
+
+    disk.set_name_policy(NamePolicy::Allow).unwrap();
+    disk.create_new_file("photo.jpg", flags, Vec::new())
+        .unwrap();
+    assert_eq!(
+        disk.list_inodes()
+            .iter()
+            .filter(|i| i.name() == "photo.jpg")
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_name_policy_format_option() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let options = FormatOptions::new().with_name_policy(NamePolicy::Allow);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+    disk.create_new_file("test_file", flags, Vec::new())
+        .unwrap();
+    disk.create_new_file("test_file", flags, Vec::new())
+        .unwrap();
+    assert_eq!(disk.number_of_files(), 2);
+}
+
+#[test]
+fn test_set_times() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let (old, new, tag);
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        old = disk
+            .create_new_file("old", INodeFlags::default(), vec![1])
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        new = disk
+            .create_new_file("new", INodeFlags::default(), vec![2])
+            .unwrap();
+        tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+
+        assert_ne!(old.creation_time(), new.creation_time());
+
+        let updated = disk
+            .set_file_times(
+                new.index(),
+                old.creation_time(),
+                old.creation_time(),
+                new.creation_time(),
+            )
+            .unwrap();
+        assert_eq!(updated.creation_time(), old.creation_time());
+        assert_eq!(updated.modified_time(), old.creation_time());
+        assert_eq!(updated.access_time(), new.creation_time());
+
+        disk.set_tag_creation_time(tag.index(), old.creation_time())
+            .unwrap();
+
+        assert_eq!(
+            disk.set_file_times(
+                100,
+                old.creation_time(),
+                old.creation_time(),
+                old.creation_time()
+            )
+            .err(),
+            Some(VoxFSError::CouldNotFindINode)
+        );
+        assert_eq!(
+            disk.set_tag_creation_time(100, old.creation_time()).err(),
+            Some(VoxFSError::CouldNotFindTag)
+        );
+    }
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let reopened = disk
+        .list_inodes()
+        .into_iter()
+        .find(|i| i.index() == new.index())
+        .unwrap();
+    assert_eq!(reopened.creation_time(), old.creation_time());
+    assert_eq!(reopened.modified_time(), old.creation_time());
+    assert_eq!(reopened.access_time(), new.creation_time());
+
+    let tag = disk
+        .list_tags()
+        .into_iter()
+        .find(|t| t.index() == tag.index())
+        .unwrap();
+    assert_eq!(tag.creation_time(), old.creation_time());
+}
+
+#[test]
+fn test_import_batch() {
+    let mut handler = Handler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let tag;
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        tag = disk
+            .create_new_tag("imported", TagFlags::default())
+            .unwrap();
+
+        let batch = vec![
+            NewFileSpec::new("first", vec![1u8; 5000]).with_tag(tag.index()),
+            NewFileSpec::new("second", vec![2u8; 10]).with_tag(tag.index()),
+            NewFileSpec::new("third", Vec::new()),
+        ];
+        let created = disk.import_batch(&batch).unwrap();
+
+        assert_eq!(created.len(), 3);
+        assert_eq!(disk.read_file(created[0].index()).unwrap(), vec![1u8; 5000]);
+        assert_eq!(disk.read_file(created[1].index()).unwrap(), vec![2u8; 10]);
+        assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap().len(), 2);
+    }
+
+    let before = handler.dump_disk();
+
+    {
+        let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+        let free_blocks = disk.free_block_count();
+
+        // The first files fit but the last doesn't, so none are created
+        let batch = vec![
+            NewFileSpec::new("small", vec![3u8; 10]),
+            NewFileSpec::new("large", vec![4u8; 4096 * 100]),
+        ];
+        assert_eq!(
+            disk.import_batch(&batch).err(),
+            Some(VoxFSError::NotEnoughFreeDataBlocks)
+        );
+
+        let batch = vec![
+            NewFileSpec::new("small", vec![3u8; 10]),
+            NewFileSpec::new("small", vec![3u8; 10]),
+        ];
+        assert_eq!(
+            disk.import_batch(&batch).err(),
+            Some(VoxFSError::FileExistsWithName("small".to_string()))
+        );
+
+        let batch = vec![NewFileSpec::new("small", vec![3u8; 10]).with_tag(tag.index() + 50)];
+        assert_eq!(
+            disk.import_batch(&batch).err(),
+            Some(VoxFSError::CouldNotFindTag)
+        );
+
+        assert_eq!(disk.number_of_files(), 3);
+        assert_eq!(disk.free_block_count(), free_blocks);
+    }
+
+    assert_eq!(handler.dump_disk(), before);
+}
+
+#[test]
+fn test_create_new_file_in_tag() {
+    let mut handler = Handler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+
+    // Leave a free block at the start of the data blocks
+    let other = disk.create_new_file("other", flags, vec![1u8; 10]).unwrap();
+    let first = disk
+        .create_new_file_in_tag("first", flags, vec![2u8; 10], tag.index())
+        .unwrap();
+    disk.delete_file(other.index()).unwrap();
+    assert!(disk.is_data_block_allocated(1));
+
+    // Files with the tag go after its members rather than in the first free block
+    let second = disk
+        .create_new_file_in_tag("second", flags, vec![3u8; 10], tag.index())
+        .unwrap();
+    assert!(!disk.is_data_block_allocated(0));
+    assert!(disk.is_data_block_allocated(2));
+    assert_eq!(
+        disk.list_nodes_with_tag(tag.index()).unwrap(),
+        vec![first, second]
+    );
+
+    let batch = vec![NewFileSpec::new("third", vec![4u8; 10]).with_tag(tag.index())];
+    disk.import_batch(&batch).unwrap();
+    assert!(!disk.is_data_block_allocated(0));
+    assert!(disk.is_data_block_allocated(3));
+
+    // Files without a tag still use the first free blocks
+    disk.create_new_file("untagged", flags, vec![5u8; 10])
+        .unwrap();
+    assert!(disk.is_data_block_allocated(0));
+
+    assert_eq!(
+        disk.create_new_file_in_tag("missing", flags, Vec::new(), tag.index() + 50)
+            .err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+    assert_eq!(disk.read_file(second.index()).unwrap(), vec![3u8; 10]);
+}
+
+#[test]
+fn test_tag_placement_is_cached() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: Handler::new(4096 * 200),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+
+    // A member with an indirect inode, whose extents can only be found by reading it
+    let file = disk
+        .create_new_file_in_tag("file", flags, vec![1u8; 4096], tag.index())
+        .unwrap()
+        .index();
+    let other = disk
+        .create_new_file("other", flags, vec![2u8; 4096])
+        .unwrap()
+        .index();
+
+    for _ in 0..6 {
+        disk.append_file_bytes(file, &vec![1u8; 4096]).unwrap();
+        disk.append_file_bytes(other, &vec![2u8; 4096]).unwrap();
+    }
+
+    disk.invalidate_caches();
+    disk.create_new_file_in_tag("first", flags, vec![3u8; 10], tag.index())
+        .unwrap();
+
+    // The members' extents are only read for the first file placed with the tag
+    let before = reads.get();
+    let second = disk
+        .create_new_file_in_tag("second", flags, vec![4u8; 10], tag.index())
+        .unwrap();
+    assert_eq!(reads.get(), before);
+    assert!(disk.is_data_block_allocated(15));
+    assert_eq!(disk.read_file(second.index()).unwrap(), vec![4u8; 10]);
+}
+
+#[test]
+fn test_create_new_file_in_tag_failure() {
+    let mut handler = Handler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+    Disk::make_new_filesystem(&mut handler, &mut manager)
+        .unwrap()
+        .close()
+        .unwrap();
+
+    // The last data blocks of a disk this small lie past its end, room is added so the disk can be filled
+    handler.disk.resize(4096 * 110, 0);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+
+    for i in 0..TagBlock::MAXIMUM_LOCAL_MEMBERS {
+        disk.create_new_file_in_tag(&format!("file {}", i), flags, Vec::new(), tag.index())
+            .unwrap();
+    }
+
+    // An empty file needs no blocks but the tag needs one for an indirect block
+    let space = disk.free_block_space();
+    disk.create_new_file("filler", flags, vec![1u8; space as usize])
+        .unwrap();
+    assert_eq!(
+        disk.create_new_file_in_tag("extra", flags, Vec::new(), tag.index())
+            .err(),
+        Some(VoxFSError::NotEnoughFreeDataBlocks)
+    );
+
+    // The file isn't left behind without the tag
+    assert_eq!(disk.inode_with_name("extra"), None);
+    assert_eq!(
+        disk.list_inodes().len(),
+        TagBlock::MAXIMUM_LOCAL_MEMBERS as usize + 1
+    );
+}
+
+#[test]
+fn test_max_name_length() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+
+    let options = FormatOptions::new()
+        .with_name_policy(NamePolicy::AutoSuffix)
+        .with_max_name_length(Some(8));
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    assert_eq!(disk.max_name_length(), Some(8));
+    assert_eq!(disk.disk_info().max_name_length(), Some(8));
+
+    disk.create_new_file("12345678", flags, vec![1]).unwrap();
+    assert_eq!(
+        disk.create_new_file("123456789", flags, vec![1]).err(),
+        Some(VoxFSError::InvalidFileName)
+    );
+    assert_eq!(
+        disk.create_new_tag("long_tag_name", TagFlags::default())
+            .err(),
+        Some(VoxFSError::InvalidTagName)
+    );
+
+    // A suffix that would take the name past the limit can't be added
+    assert_eq!(
+        disk.create_new_file("12345678", flags, vec![2]).err(),
+        Some(VoxFSError::FileExistsWithName("12345678".to_string()))
+    );
+    assert_eq!(
+        disk.create_new_file("a.b", flags, vec![3]).unwrap().name(),
+        "a.b"
+    );
+    assert_eq!(
+        disk.create_new_file("a.b", flags, vec![3]).unwrap().name(),
+        "a (1).b"
+    );
+    disk.close().unwrap();
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.max_name_length(), Some(8));
+    drop(disk);
+
+    let mut handler = Handler::new(4096 * 30);
+    let options = FormatOptions::new().with_max_name_length(Some(TagBlock::MAX_NAME_LENGTH + 1));
+    assert_eq!(
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::InvalidMaxNameLength)
+    );
+}
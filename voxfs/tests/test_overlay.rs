@@ -0,0 +1,60 @@
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, MemoryDiskHandler, OverlayDisk, TagFlags};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_overlay_disk() {
+    let mut base = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+
+    Disk::make_new_filesystem(&mut base, &mut manager)
+        .unwrap()
+        .close()
+        .unwrap();
+    let formatted = base.as_bytes().to_vec();
+
+    {
+        let mut overlay = OverlayDisk::new(&mut base);
+
+        {
+            let mut disk = Disk::open_disk(&mut overlay, &mut manager).unwrap();
+            let tag = disk.create_new_tag("work", TagFlags::default()).unwrap();
+            let file = disk
+                .create_new_file("notes", INodeFlags::default(), vec![7u8; 10_000])
+                .unwrap();
+            disk.apply_tag(tag.index(), file.index()).unwrap();
+        }
+
+        // The changes are seen through the overlay
+        {
+            let disk = Disk::open_disk(&mut overlay, &mut manager).unwrap();
+            let file = disk.inode_with_name("notes").unwrap();
+            assert_eq!(disk.read_file(file).unwrap(), vec![7u8; 10_000]);
+        }
+
+        assert!(overlay.is_modified());
+        overlay.discard();
+
+        let disk = Disk::open_disk(&mut overlay, &mut manager).unwrap();
+        assert!(disk.inode_with_name("notes").is_none());
+    }
+
+    assert_eq!(base.as_bytes().to_vec(), formatted);
+
+    {
+        let mut overlay = OverlayDisk::new(&mut base);
+        Disk::open_disk(&mut overlay, &mut manager)
+            .unwrap()
+            .create_new_file("kept", INodeFlags::default(), vec![1u8; 10])
+            .unwrap();
+
+        overlay.commit().unwrap();
+    }
+
+    let disk = Disk::open_disk(&mut base, &mut manager).unwrap();
+    let file = disk.inode_with_name("kept").unwrap();
+    assert_eq!(disk.read_file(file).unwrap(), vec![1u8; 10]);
+    assert!(!disk.opened_dirty());
+}
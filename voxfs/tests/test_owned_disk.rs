@@ -0,0 +1,80 @@
+extern crate voxfs;
+use voxfs::{
+    Disk, FormatOptions, INodeFlags, MemoryDiskError, MemoryDiskHandler, MountOptions, VoxFSError,
+};
+
+mod common;
+use common::*;
+
+/// An application keeping its disk for as long as it runs.
+struct Library {
+    disk: Disk<'static, 'static, MemoryDiskError>,
+}
+
+impl Library {
+    fn add(&mut self, name: &str, contents: &[u8]) -> u64 {
+        return self
+            .disk
+            .create_new_file(name, INodeFlags::default(), contents.to_vec())
+            .unwrap()
+            .index();
+    }
+
+    /// Copies the image out through the disk's handler.
+    fn image(&mut self) -> Vec<u8> {
+        self.disk.sync().unwrap();
+        let handler = self.disk.handler();
+
+        return handler.read_bytes(0, handler.disk_size().unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn test_owned_disk() {
+    let disk = Disk::make_new_filesystem_owned(
+        Box::new(MemoryDiskHandler::new(4096 * 40)),
+        Box::new(Manager::new()),
+        FormatOptions::new().with_label("library"),
+    )
+    .unwrap();
+    let mut library = Library { disk };
+
+    let first = library.add("first", b"first contents");
+    library.add("second", &[2; 5000]);
+    assert_eq!(
+        library.disk.read_file(first).unwrap(),
+        b"first contents".to_vec()
+    );
+
+    let image = library.image();
+    drop(library);
+
+    let disk = Disk::open_owned(
+        Box::new(MemoryDiskHandler::from_bytes(image)),
+        Box::new(Manager::new()),
+        MountOptions::new().with_read_only(true),
+    )
+    .unwrap();
+    let library = Library { disk };
+
+    assert!(library.disk.is_read_only());
+    assert_eq!(library.disk.label(), "library");
+    assert_eq!(library.disk.number_of_files(), 2);
+    assert_eq!(
+        library.disk.read_file(first).unwrap(),
+        b"first contents".to_vec()
+    );
+}
+
+#[test]
+fn test_owned_disk_errors() {
+    match Disk::open_owned(
+        Box::new(MemoryDiskHandler::new(4096 * 40)),
+        Box::new(Manager::new()),
+        MountOptions::new(),
+    ) {
+        Err(VoxFSError::CorruptedSuperBlock) => (),
+        Err(e) => panic!("Unexpected error {:?}", e),
+        Ok(_) => panic!("Opened a disk that was never formatted"),
+    }
+}
@@ -0,0 +1,238 @@
+extern crate voxfs;
+use voxfs::raw::{RawDisk, RawError, RawReader};
+use voxfs::{Disk, FormatOptions, INodeFlags};
+
+mod common;
+use common::*;
+
+const DISK_SIZE: usize = 4096 * 300;
+
+struct SliceReader<'a> {
+    disk: &'a [u8],
+}
+
+impl RawReader<Error> for SliceReader<'_> {
+    fn read_into(&self, location: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        let location = location as usize;
+
+        match self.disk.get(location..location + buffer.len()) {
+            Some(bytes) => buffer.copy_from_slice(bytes),
+            None => return Err(Error {}),
+        }
+
+        return Ok(());
+    }
+
+    fn disk_size(&self) -> Result<u64, Error> {
+        return Ok(self.disk.len() as u64);
+    }
+}
+
+fn contents(size: usize) -> Vec<u8> {
+    return (0..size).map(|i| (i * 7 + i / 4096) as u8).collect();
+}
+
+/// Builds an image with a small file at index 0 and at index 1 a large file split over more extents than fit
+/// in the inode, so part of it is found through an indirect inode.
+fn image() -> Vec<u8> {
+    let mut handler = Handler::new(DISK_SIZE);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        disk.create_new_file("small", flags, contents(100)).unwrap();
+
+        // Leave single block holes so the large file is fragmented
+        let mut fillers = Vec::new();
+
+        for i in 0..16 {
+            let inode = disk
+                .create_new_file(&format!("filler_{}", i), flags, vec![0xff; 10])
+                .unwrap();
+            fillers.push(inode.index());
+        }
+
+        let large = disk
+            .create_new_file("placeholder", flags, vec![0xff; 10])
+            .unwrap();
+        disk.delete_file(large.index()).unwrap();
+
+        for index in fillers.iter().step_by(2) {
+            disk.delete_file(*index).unwrap();
+        }
+
+        let large = disk
+            .create_new_file("large", flags, contents(4096 * 12 + 123))
+            .unwrap();
+        assert_eq!(large.index(), 1);
+    }
+
+    return handler.dump_disk();
+}
+
+#[test]
+fn test_read_small_file() {
+    let image = image();
+    let reader = SliceReader { disk: &image };
+    let disk = RawDisk::<Error, 4096>::open(&reader).unwrap();
+
+    let inode = disk.inode(0).unwrap();
+    assert_eq!(inode.name(), b"small");
+    assert_eq!(inode.file_size(), 100);
+
+    let mut buffer = [0u8; 256];
+    assert_eq!(disk.read_file(&inode, 0, &mut buffer).unwrap(), 100);
+    assert_eq!(&buffer[..100], contents(100).as_slice());
+}
+
+#[test]
+fn test_stream_large_file() {
+    let image = image();
+    let reader = SliceReader { disk: &image };
+    let disk = RawDisk::<Error, 4096>::open(&reader).unwrap();
+
+    let inode = disk.inode(1).unwrap();
+    assert_eq!(inode.name(), b"large");
+
+    // Use a buffer size that doesn't line up with the blocks
+    let mut buffer = [0u8; 1000];
+    let mut read = Vec::new();
+
+    loop {
+        let n = disk
+            .read_file(&inode, read.len() as u64, &mut buffer)
+            .unwrap();
+
+        if n == 0 {
+            break;
+        }
+
+        read.extend_from_slice(&buffer[..n]);
+    }
+
+    assert_eq!(read, contents(4096 * 12 + 123));
+}
+
+#[test]
+fn test_read_at_offset() {
+    let image = image();
+    let reader = SliceReader { disk: &image };
+    let disk = RawDisk::<Error, 4096>::open(&reader).unwrap();
+
+    let inode = disk.inode(1).unwrap();
+    let expected = contents(4096 * 12 + 123);
+
+    let mut buffer = [0u8; 5000];
+    assert_eq!(disk.read_file(&inode, 4090, &mut buffer).unwrap(), 5000);
+    assert_eq!(&buffer[..], &expected[4090..9090]);
+
+    assert_eq!(
+        disk.read_file(&inode, expected.len() as u64 - 10, &mut buffer)
+            .unwrap(),
+        10
+    );
+    assert_eq!(&buffer[..10], &expected[expected.len() - 10..]);
+
+    assert_eq!(
+        disk.read_file(&inode, expected.len() as u64, &mut buffer)
+            .unwrap(),
+        0
+    );
+}
+
+#[test]
+fn test_free_inode() {
+    let image = image();
+    let reader = SliceReader { disk: &image };
+    let disk = RawDisk::<Error, 4096>::open(&reader).unwrap();
+
+    // Every other filler was deleted and the large file reused the first free slot
+    assert!(disk.inode(2).is_ok());
+    assert_eq!(disk.inode(3), Err(RawError::CouldNotFindINode));
+    assert_eq!(
+        disk.inode(disk.inode_count()),
+        Err(RawError::CouldNotFindINode)
+    );
+}
+
+#[test]
+fn test_open_errors() {
+    let mut image = image();
+
+    let reader = SliceReader { disk: &image };
+    assert_eq!(
+        RawDisk::<Error, 512>::open(&reader).err(),
+        Some(RawError::BlockSizeMismatch)
+    );
+
+    image[20] ^= 0x40;
+    let reader = SliceReader { disk: &image };
+    assert_eq!(
+        RawDisk::<Error, 4096>::open(&reader).err(),
+        Some(RawError::CorruptedSuperBlock)
+    );
+
+    let reader = SliceReader { disk: &[0u8; 32] };
+    assert_eq!(
+        RawDisk::<Error, 4096>::open(&reader).err(),
+        Some(RawError::DiskError(Error {}))
+    );
+}
+
+#[test]
+fn test_boot_area() {
+    let mut handler = Handler::new(DISK_SIZE);
+    let mut manager = Manager::new();
+
+    {
+        let options = FormatOptions::new().with_boot_area_size(4096 * 2);
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+        disk.create_new_file(
+            "kernel",
+            INodeFlags::new(true, true, true, false),
+            contents(5000),
+        )
+        .unwrap();
+    }
+
+    let image = handler.dump_disk();
+    let reader = SliceReader { disk: &image };
+    let disk = RawDisk::<Error, 4096>::open(&reader).unwrap();
+
+    let inode = disk.inode(0).unwrap();
+    assert_eq!(inode.name(), b"kernel");
+    assert_eq!(disk.inode(1), Err(RawError::CouldNotFindINode));
+
+    let mut buffer = [0u8; 6000];
+    assert_eq!(disk.read_file(&inode, 0, &mut buffer).unwrap(), 5000);
+    assert_eq!(&buffer[..5000], contents(5000).as_slice());
+}
+
+#[test]
+fn test_protected_file() {
+    let mut handler = Handler::new(DISK_SIZE);
+    let mut manager = Manager::new();
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        disk.create_new_file(
+            "firmware",
+            INodeFlags::new(true, true, false, false)
+                .with_append_only(true)
+                .with_immutable(true),
+            contents(100),
+        )
+        .unwrap();
+    }
+
+    let image = handler.dump_disk();
+    let reader = SliceReader { disk: &image };
+    let disk = RawDisk::<Error, 4096>::open(&reader).unwrap();
+
+    // The flag bits are covered by the checksum
+    let inode = disk.inode(0).unwrap();
+    assert_eq!(inode.name(), b"firmware");
+    assert_eq!(inode.file_size(), 100);
+}
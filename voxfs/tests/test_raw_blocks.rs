@@ -0,0 +1,64 @@
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, VoxFSError};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_read_block() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    disk.create_new_file("file", INodeFlags::default(), vec![7u8; 5000])
+        .unwrap();
+
+    assert_eq!(disk.read_block(0).unwrap(), vec![7u8; 4096]);
+    assert_eq!(&disk.read_block(1).unwrap()[..904], &[7u8; 904][..]);
+    assert_eq!(disk.read_block(2).unwrap(), vec![0u8; 4096]);
+
+    let count = disk.data_block_count();
+    assert_eq!(
+        disk.read_block(count).err(),
+        Some(VoxFSError::BlockOutOfRange(count))
+    );
+}
+
+#[cfg(feature = "dangerous")]
+#[test]
+fn test_write_block_unchecked() {
+    use voxfs::MountOptions;
+
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![7u8; 10])
+        .unwrap();
+    disk.close().unwrap();
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.write_block_unchecked(1, &vec![0u8; 4097]).is_err());
+    assert_eq!(
+        disk.write_block_unchecked(disk.data_block_count(), &[1])
+            .err(),
+        Some(VoxFSError::BlockOutOfRange(disk.data_block_count()))
+    );
+    assert!(!disk.is_dirty());
+
+    disk.write_block_unchecked(0, &[1, 2, 3]).unwrap();
+    assert!(disk.is_dirty());
+
+    let mut contents = vec![1u8, 2, 3];
+    contents.extend_from_slice(&[7u8; 7]);
+    assert_eq!(disk.read_file(file.index()).unwrap(), contents);
+    drop(disk);
+
+    let options = MountOptions::new().with_read_only(true);
+    let mut disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    assert_eq!(
+        disk.write_block_unchecked(0, &[1]).err(),
+        Some(VoxFSError::ReadOnly)
+    );
+}
@@ -1,5 +1,10 @@
 extern crate voxfs;
-use voxfs::{Disk, INodeFlags};
+use std::cell::Cell;
+use std::rc::Rc;
+use voxfs::{
+    Disk, DiskHandler, FormatOptions, INodeFlags, Integrity, MemoryDiskError, MemoryDiskHandler,
+    MountOptions, VoxFSError,
+};
 
 mod common;
 use common::*;
@@ -52,3 +57,338 @@ fn test_read_large_file() {
 
     assert_eq!(read_contents[..file_contents.len()].to_vec(), file_contents);
 }
+
+/// Counts the reads that reach the disk so the effect of the cache can be measured.
+struct CountingHandler {
+    disk: MemoryDiskHandler,
+    reads: Rc<Cell<usize>>,
+}
+
+impl DiskHandler<MemoryDiskError> for CountingHandler {
+    fn write_bytes(&mut self, bytes: &Vec<u8>, location: u64) -> Result<(), MemoryDiskError> {
+        return self.disk.write_bytes(bytes, location);
+    }
+
+    fn read_bytes(&self, location: u64, amount: u64) -> Result<Vec<u8>, MemoryDiskError> {
+        self.reads.set(self.reads.get() + 1);
+
+        return self.disk.read_bytes(location, amount);
+    }
+
+    fn zero_range(&mut self, start: u64, end: u64) -> Result<(), MemoryDiskError> {
+        return self.disk.zero_range(start, end);
+    }
+
+    fn disk_size(&self) -> Result<u64, MemoryDiskError> {
+        return self.disk.disk_size();
+    }
+}
+
+/// Creates a file with the extents [0], [2, 3] and [5, 6] by interleaving it with another file.
+fn fragmented_file(disk: &mut Disk<MemoryDiskError>) -> (u64, Vec<u8>) {
+    let flags = INodeFlags::new(true, true, true, false);
+    let contents: Vec<u8> = (0..4096 * 5).map(|i| (i / 7) as u8).collect();
+
+    let file = disk
+        .create_new_file("file", flags, contents[..4096].to_vec())
+        .unwrap()
+        .index();
+    let other = disk
+        .create_new_file("other", flags, vec![1u8; 4096])
+        .unwrap()
+        .index();
+
+    disk.append_file_bytes(file, &contents[4096..4096 * 3].to_vec())
+        .unwrap();
+    disk.append_file_bytes(other, &vec![1u8; 4096]).unwrap();
+    disk.append_file_bytes(file, &contents[4096 * 3..].to_vec())
+        .unwrap();
+
+    return (file, contents);
+}
+
+#[test]
+fn test_read_file_range() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let (file, contents) = fragmented_file(&mut disk);
+    let mut handle = disk.open_file(file).unwrap();
+
+    for (offset, length) in [(0, 10), (4090, 20), (100, 4096 * 3), (4096 * 5 - 5, 100)].iter() {
+        let end = std::cmp::min(offset + length, contents.len());
+
+        assert_eq!(
+            disk.read_file_range(&mut handle, *offset as u64, *length as u64)
+                .unwrap(),
+            contents[*offset..end].to_vec()
+        );
+    }
+
+    assert!(disk
+        .read_file_range(&mut handle, 4096 * 5, 10)
+        .unwrap()
+        .is_empty());
+    assert!(disk.open_file(file + 10).is_err());
+}
+
+#[test]
+fn test_read_ahead() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: MemoryDiskHandler::new(4096 * 100),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let (file, contents) = fragmented_file(&mut disk);
+
+    disk.set_cache_size(16);
+    disk.set_read_ahead(true);
+
+    let mut handle = disk.open_file(file).unwrap();
+
+    // The first extent is read and the second is prefetched
+    let start = reads.get();
+    assert_eq!(
+        disk.read_file_range(&mut handle, 0, 4096).unwrap(),
+        contents[..4096].to_vec()
+    );
+    assert_eq!(reads.get(), start + 2);
+
+    // The second extent comes from the cache while the third is prefetched with one read
+    assert_eq!(
+        disk.read_file_range(&mut handle, 4096, 4096 * 2).unwrap(),
+        contents[4096..4096 * 3].to_vec()
+    );
+    assert_eq!(reads.get(), start + 3);
+
+    assert_eq!(
+        disk.read_file_range(&mut handle, 4096 * 3, 4096 * 2)
+            .unwrap(),
+        contents[4096 * 3..].to_vec()
+    );
+    assert_eq!(reads.get(), start + 3);
+
+    // Writes replace the cached blocks
+    disk.append_file_bytes(file, &vec![9u8; 10]).unwrap();
+    let mut expected = contents.clone();
+    expected.extend_from_slice(&[9u8; 10]);
+    assert_eq!(disk.read_file(file).unwrap(), expected);
+}
+
+#[test]
+fn test_no_read_ahead_when_not_sequential() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: MemoryDiskHandler::new(4096 * 100),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let (file, contents) = fragmented_file(&mut disk);
+
+    disk.set_cache_size(16);
+    disk.set_read_ahead(true);
+
+    let mut handle = disk.open_file(file).unwrap();
+
+    let start = reads.get();
+    assert_eq!(
+        disk.read_file_range(&mut handle, 10, 100).unwrap(),
+        contents[10..110].to_vec()
+    );
+    assert_eq!(reads.get(), start + 1);
+
+    // Reading the first block again hits the cache
+    assert_eq!(
+        disk.read_file_range(&mut handle, 0, 4096).unwrap(),
+        contents[..4096].to_vec()
+    );
+    assert_eq!(reads.get(), start + 1);
+}
+
+#[test]
+fn test_read_file_bytes_fragmented() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let (file, contents) = fragmented_file(&mut disk);
+
+    for length in [1, 4095, 4096, 4097, 4096 * 4 + 1].iter() {
+        assert_eq!(
+            disk.read_file_bytes(file, *length as u64).unwrap(),
+            contents[..*length].to_vec()
+        );
+    }
+
+    assert_eq!(disk.read_file(file).unwrap(), contents);
+}
+
+#[test]
+fn test_verify_reads() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = HashingManager {};
+    let options = FormatOptions::new().with_content_hashes(true);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    let (file, contents) = fragmented_file(&mut disk);
+    disk.close().unwrap();
+
+    // Verifying reads the whole file to hash it but returns the same bytes
+    let options = MountOptions::new().with_integrity(Integrity::Full);
+    let disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    assert_eq!(disk.read_file_bytes(file, 4097).unwrap(), contents[..4097]);
+    assert_eq!(disk.read_file(file).unwrap(), contents);
+
+    let mut handle = disk.open_file(file).unwrap();
+    assert_eq!(
+        disk.read_file_range(&mut handle, 4096, 10).unwrap(),
+        contents[4096..4106].to_vec()
+    );
+}
+
+#[test]
+fn test_verify_reads_corrupted_block() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = HashingManager {};
+    let options = FormatOptions::new().with_content_hashes(true);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![7u8; 5000])
+        .unwrap()
+        .index();
+    let geometry = disk.geometry();
+    disk.close().unwrap();
+
+    // The first file is written to the first data block
+    handler
+        .write_bytes(&vec![8u8; 10], geometry.data_block_address(0))
+        .unwrap();
+
+    // Without verifying, the corrupted bytes are returned as read
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.read_file_bytes(file, 10).unwrap(), vec![8u8; 10]);
+    disk.close().unwrap();
+
+    let options = MountOptions::new().with_integrity(Integrity::Full);
+    let disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    let mismatch = Some(VoxFSError::DataChecksumMismatch { inode: file });
+    assert_eq!(disk.read_file(file).err(), mismatch);
+    assert_eq!(disk.read_file_bytes(file, 10).err(), mismatch);
+
+    // Reading a range from the undamaged end of the file still checks all of it
+    let mut handle = disk.open_file(file).unwrap();
+    assert_eq!(disk.read_file_range(&mut handle, 4096, 10).err(), mismatch);
+}
+
+#[test]
+fn test_verify_reads_option() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = HashingManager {};
+    let options = FormatOptions::new().with_content_hashes(true);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![7u8; 100])
+        .unwrap()
+        .index();
+    let geometry = disk.geometry();
+    disk.close().unwrap();
+
+    let options = MountOptions::new().with_verify_reads(true);
+    let disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    assert!(disk.verifies_reads());
+    assert_eq!(disk.read_file(file).unwrap(), vec![7u8; 100]);
+    disk.close().unwrap();
+
+    handler
+        .write_bytes(&vec![8u8; 10], geometry.data_block_address(0))
+        .unwrap();
+
+    let options = MountOptions::new().with_verify_reads(true);
+    let disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    assert_eq!(
+        disk.read_file(file).err(),
+        Some(VoxFSError::DataChecksumMismatch { inode: file })
+    );
+}
+
+#[test]
+fn test_verify_reads_needs_content_hashes() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut hashing = HashingManager {};
+    let disk = Disk::make_new_filesystem(&mut handler, &mut hashing).unwrap();
+    disk.close().unwrap();
+
+    // A disk without content hashes has nothing to check the contents against
+    let options = MountOptions::new().with_integrity(Integrity::Full);
+    assert_eq!(
+        Disk::open_with_options(&mut handler, &mut hashing, options).err(),
+        Some(VoxFSError::NoContentHasher)
+    );
+
+    let mut handler = Handler::new(4096 * 40);
+    let options = FormatOptions::new().with_content_hashes(true);
+    let disk = Disk::make_new_filesystem_with_options(&mut handler, &mut hashing, options).unwrap();
+    disk.close().unwrap();
+
+    // Nor can a manager without a hasher
+    let mut manager = Manager::new();
+    let options = MountOptions::new().with_integrity(Integrity::Full);
+    assert_eq!(
+        Disk::open_with_options(&mut handler, &mut manager, options).err(),
+        Some(VoxFSError::NoContentHasher)
+    );
+}
+
+#[test]
+fn test_memory_budget() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let (file, contents) = fragmented_file(&mut disk);
+
+    // Room for two blocks of contents and the block being read
+    disk.set_memory_budget(Some(4096 * 3));
+    assert_eq!(disk.memory_budget(), Some(4096 * 3));
+
+    assert_eq!(
+        disk.read_file(file).err(),
+        Some(VoxFSError::MemoryBudgetExceeded)
+    );
+    assert_eq!(
+        disk.split_file(file, 4096).err(),
+        Some(VoxFSError::MemoryBudgetExceeded)
+    );
+
+    // The file can still be read in pieces that fit
+    let mut handle = disk.open_file(file).unwrap();
+    let mut read = Vec::new();
+
+    while read.len() < contents.len() {
+        read.extend(
+            disk.read_file_range(&mut handle, read.len() as u64, 4096 * 2)
+                .unwrap(),
+        );
+    }
+
+    assert_eq!(read, contents);
+    assert_eq!(
+        disk.read_file_range(&mut handle, 0, 4096 * 3).err(),
+        Some(VoxFSError::MemoryBudgetExceeded)
+    );
+
+    disk.set_memory_budget(None);
+    assert_eq!(disk.read_file(file).unwrap(), contents);
+    disk.close().unwrap();
+
+    let disk = Disk::open_with_options(
+        &mut handler,
+        &mut manager,
+        MountOptions::new().with_memory_budget(Some(4096)),
+    )
+    .unwrap();
+    assert_eq!(disk.memory_budget(), Some(4096));
+}
@@ -0,0 +1,77 @@
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, TagFlags, VoxFSError};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_replace_file() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let original = disk
+        .create_new_file("test_file", flags, vec![1u8; 4096 * 3])
+        .unwrap();
+    let tag = disk
+        .create_new_tag("tag", TagFlags::new(true, true))
+        .unwrap();
+    disk.apply_tag(tag.index(), original.index()).unwrap();
+
+    let free_blocks = disk.free_block_count();
+
+    let replaced = disk
+        .replace_file(
+            "test_file",
+            INodeFlags::new(true, true, false, false),
+            b"Replaced".to_vec(),
+        )
+        .unwrap();
+
+    assert_eq!(replaced.index(), original.index());
+    assert_eq!(replaced.name(), "test_file");
+    assert_eq!(replaced.creation_time(), original.creation_time());
+    assert_eq!(replaced.file_size(), 8);
+    assert_eq!(
+        disk.read_file(original.index()).unwrap(),
+        b"Replaced".to_vec()
+    );
+
+    // The old blocks are freed
+    assert_eq!(disk.free_block_count(), free_blocks + 2);
+    assert_eq!(
+        disk.file_size(original.index()).unwrap().physical_size,
+        4096
+    );
+
+    drop(disk);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let inodes = disk.list_inodes();
+
+    assert_eq!(inodes.len(), 1);
+    assert_eq!(inodes[0], replaced);
+    assert_eq!(
+        disk.read_file(replaced.index()).unwrap(),
+        b"Replaced".to_vec()
+    );
+    assert_eq!(
+        disk.list_nodes_with_tag(tag.index()).unwrap(),
+        vec![replaced]
+    );
+}
+
+#[test]
+fn test_replace_missing_file() {
+    let mut handler = Handler::new(4096 * 30);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    assert!(matches!(
+        disk.replace_file("missing", INodeFlags::default(), Vec::new()),
+        Err(VoxFSError::CouldNotFindINode)
+    ));
+}
@@ -0,0 +1,298 @@
+extern crate voxfs;
+use std::cell::{Cell, RefCell};
+use voxfs::{
+    ByteSerializable, Disk, FormatOptions, INodeFlags, MemoryDiskHandler, OpContext, ScrubRegion,
+    SuperBlock, TagBlock, TagFlags, VoxFSError,
+};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_scrub() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_metadata_mirror(true);
+    let (inode, tag);
+
+    {
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+        inode = disk
+            .create_new_file("file", INodeFlags::default(), vec![7u8; 10])
+            .unwrap();
+        disk.create_new_file("other", INodeFlags::default(), vec![8u8; 10])
+            .unwrap();
+        tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+
+        // Enough members to need an indirect tag block
+        for i in 0..13 {
+            let member = disk
+                .create_new_file(&format!("member_{}", i), INodeFlags::default(), vec![1])
+                .unwrap();
+            disk.apply_tag(tag.index(), member.index()).unwrap();
+        }
+
+        let report = disk.scrub().unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.checked(ScrubRegion::SuperBlock), 1);
+        assert_eq!(report.checked(ScrubRegion::INodeTable), 15);
+        assert_eq!(report.checked(ScrubRegion::INodeMirror), 15);
+        assert_eq!(
+            report.checked(ScrubRegion::TagTable),
+            disk.number_of_tags() as u64
+        );
+        assert_eq!(report.checked(ScrubRegion::IndirectTags), 1);
+        assert_eq!(report.checked(ScrubRegion::IndirectINodes), 0);
+    }
+
+    // Corrupt the primary copies of the inode and the tag, the mirror lets the disk open
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + inode.index() * 256; // Inodes are 256 bytes
+    let tag_address = super_block.tag_start_address() + tag.index() * TagBlock::size();
+    bytes[inode_address as usize + 10] ^= 0xff;
+    bytes[tag_address as usize + 10] ^= 0xff;
+
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let report = disk.scrub().unwrap();
+
+    assert!(!report.is_clean());
+    assert_eq!(report.failed(ScrubRegion::INodeTable), 1);
+    assert_eq!(report.failed(ScrubRegion::TagTable), 1);
+    assert_eq!(report.failed(ScrubRegion::INodeMirror), 0);
+    assert_eq!(report.failed(ScrubRegion::TagMirror), 0);
+
+    let failures: Vec<(ScrubRegion, u64, u64)> = report
+        .failures()
+        .iter()
+        .map(|f| (f.region(), f.index(), f.address()))
+        .collect();
+    assert_eq!(
+        failures,
+        vec![
+            (ScrubRegion::TagTable, tag.index(), tag_address),
+            (ScrubRegion::INodeTable, inode.index(), inode_address)
+        ]
+    );
+    assert_eq!(report.failures()[1].reason(), &VoxFSError::CorruptedINode);
+}
+
+#[test]
+fn test_scrub_progress_and_cancellation() {
+    let mut handler = Handler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    for i in 0..5 {
+        disk.create_new_file(&format!("file_{}", i), INodeFlags::default(), vec![1])
+            .unwrap();
+    }
+
+    // The root tag and five files are each counted in the tables and again for their indirect blocks
+    let updates = RefCell::new(Vec::new());
+    let progress = |done, total| updates.borrow_mut().push((done, total));
+    let context = OpContext::new().with_progress(&progress);
+
+    assert!(disk.scrub_with_context(&context).unwrap().is_clean());
+    assert_eq!(updates.borrow().len(), 12);
+    assert_eq!(updates.borrow().last(), Some(&(12, 12)));
+
+    let checks = Cell::new(0);
+    let cancelled = || {
+        checks.set(checks.get() + 1);
+        return checks.get() > 3;
+    };
+    let context = OpContext::new().with_cancellation(&cancelled);
+
+    assert_eq!(
+        disk.scrub_with_context(&context).err(),
+        Some(VoxFSError::Cancelled)
+    );
+    assert_eq!(checks.get(), 4);
+}
+
+#[test]
+fn test_check_incremental() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_metadata_mirror(true);
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+    let tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+    for i in 0..20 {
+        let file = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), vec![1])
+            .unwrap();
+        disk.apply_tag(tag.index(), file.index()).unwrap();
+    }
+
+    let full = disk.scrub().unwrap();
+    assert!(full.reached_end());
+
+    // Small budgets take several calls to cover what a full scrub does
+    let mut calls = 0;
+    let mut checked = [0u64; 7];
+
+    loop {
+        let report = disk.check_incremental(4).unwrap();
+        assert!(report.is_clean());
+        calls += 1;
+
+        for (i, region) in ScrubRegion::ALL.iter().enumerate() {
+            checked[i] += report.checked(*region);
+        }
+
+        if report.reached_end() {
+            break;
+        }
+    }
+
+    assert!(calls > 5);
+    for (i, region) in ScrubRegion::ALL.iter().enumerate() {
+        assert_eq!(checked[i], full.checked(*region));
+    }
+
+    // The next pass starts over, and a stored cursor can be restored
+    assert_eq!(disk.check_cursor(), 0);
+    disk.check_incremental(4).unwrap();
+    let cursor = disk.check_cursor();
+    assert!(cursor > 0 && cursor < disk.check_length());
+
+    disk.set_check_cursor(0);
+    assert_eq!(
+        disk.check_incremental(0)
+            .unwrap()
+            .checked(ScrubRegion::SuperBlock),
+        1
+    );
+
+    disk.set_check_cursor(cursor);
+    assert_eq!(
+        disk.check_incremental(0)
+            .unwrap()
+            .checked(ScrubRegion::SuperBlock),
+        0
+    );
+
+    // A large budget covers the rest in one call
+    assert!(disk.check_incremental(u64::MAX).unwrap().reached_end());
+}
+
+#[test]
+fn test_health_metrics() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_metadata_mirror(true);
+
+    let inode = {
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+        let inode = disk
+            .create_new_file("file", INodeFlags::default(), vec![7u8; 10])
+            .unwrap();
+
+        let info = disk.disk_info();
+        assert_eq!(info.checksum_failures(), 0);
+        assert_eq!(info.recovered_records(), 0);
+        assert!(info.last_check_time().is_none());
+        assert!(info.dirty());
+
+        inode
+    };
+
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + inode.index() * 256; // Inodes are 256 bytes
+    bytes[inode_address as usize + 10] ^= 0xff;
+
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+    let info = disk.disk_info();
+    assert_eq!(info.checksum_failures(), 1);
+    assert_eq!(info.recovered_records(), 1);
+    assert!(!info.opened_dirty());
+    assert!(!info.dirty());
+
+    // The scrub finds the primary copy again
+    disk.scrub().unwrap();
+    let info = disk.disk_info();
+    assert_eq!(info.checksum_failures(), 2);
+    assert!(info.last_check_time().is_some());
+
+    disk.create_new_file("other", INodeFlags::default(), vec![1u8; 10])
+        .unwrap();
+    assert!(disk.disk_info().dirty());
+}
+
+#[test]
+fn test_repair_scrub_failure() {
+    let mut handler = MemoryDiskHandler::new(4096 * 100);
+    let mut manager = Manager::new();
+    let options = FormatOptions::new().with_metadata_mirror(true);
+    let (inode, tag);
+
+    {
+        let mut disk =
+            Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+
+        inode = disk
+            .create_new_file("file", INodeFlags::default(), vec![7u8; 10])
+            .unwrap();
+        tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+
+        // Enough members to need an indirect tag block
+        for i in 0..13 {
+            let member = disk
+                .create_new_file(&format!("member_{}", i), INodeFlags::default(), vec![1])
+                .unwrap();
+            disk.apply_tag(tag.index(), member.index()).unwrap();
+        }
+    }
+
+    // Corrupt the primary inode, the mirrored tag and the indirect tag block
+    let mut bytes = handler.into_bytes();
+    let super_block = SuperBlock::from_bytes(&bytes[..SuperBlock::size() as usize]).unwrap();
+    let inode_address = super_block.inode_start_address() + inode.index() * 256; // Inodes are 256 bytes
+    let mirror_address =
+        super_block.mirror_tag_start_address().unwrap() + tag.index() * TagBlock::size();
+    bytes[inode_address as usize + 10] ^= 0xff;
+    bytes[mirror_address as usize + 10] ^= 0xff;
+
+    let mut handler = MemoryDiskHandler::from_bytes(bytes);
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let indirect = disk
+        .list_tags()
+        .iter()
+        .find(|t| t.index() == tag.index())
+        .unwrap()
+        .indirect_pointer()
+        .unwrap();
+    disk.handler()
+        .write_bytes(&vec![0xffu8; 8], indirect + 20)
+        .unwrap();
+
+    let report = disk.scrub().unwrap();
+    assert_eq!(report.failures().len(), 3);
+
+    for failure in report.failures() {
+        if failure.region() == ScrubRegion::IndirectTags {
+            assert!(!failure.repairable());
+            assert_eq!(
+                disk.repair_scrub_failure(failure).err(),
+                Some(VoxFSError::NotRepairable)
+            );
+        } else {
+            assert!(failure.repairable());
+            disk.repair_scrub_failure(failure).unwrap();
+        }
+    }
+
+    let report = disk.scrub().unwrap();
+    assert_eq!(report.failures().len(), 1);
+    assert_eq!(report.failed(ScrubRegion::IndirectTags), 1);
+}
@@ -0,0 +1,119 @@
+extern crate voxfs;
+use voxfs::{Disk, INodeFlags, VoxFSError};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_split_file() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, false, false);
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let contents: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+    let file = disk
+        .create_new_file("video", flags, contents.clone())
+        .unwrap();
+
+    let chunks = disk.split_file(file.index(), 4096).unwrap();
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].name(), "video.000");
+    assert_eq!(chunks[2].name(), "video.002");
+    assert_eq!(chunks[2].file_size(), 10_000 - 4096 * 2);
+    assert_eq!(chunks[1].flags(), flags);
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let end = core::cmp::min((i + 1) * 4096, contents.len());
+        assert_eq!(
+            disk.read_file(chunk.index()).unwrap(),
+            contents[i * 4096..end].to_vec()
+        );
+    }
+
+    // The original is kept
+    assert_eq!(disk.read_file(file.index()).unwrap(), contents);
+
+    let empty = disk.create_new_file("empty", flags, vec![]).unwrap();
+    let chunks = disk.split_file(empty.index(), 100).unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].file_size(), 0);
+
+    assert_eq!(
+        disk.split_file(file.index(), 0),
+        Err(VoxFSError::InvalidChunkSize)
+    );
+
+    // Nothing is created when the chunks don't fit
+    let other = disk.create_new_file("other", flags, contents).unwrap();
+    let files = disk.number_of_files();
+    assert_eq!(
+        disk.split_file(other.index(), 1),
+        Err(VoxFSError::NoFreeInode)
+    );
+    assert_eq!(disk.number_of_files(), files);
+}
+
+#[test]
+fn test_concat_files() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let contents: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+    {
+        let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+        let file = disk
+            .create_new_file("video", INodeFlags::default(), contents.clone())
+            .unwrap();
+        let chunks = disk.split_file(file.index(), 4096).unwrap();
+        disk.delete_file(file.index()).unwrap();
+
+        // Every chunk but the last fills its blocks, so they are all reused
+        let free_blocks = disk.free_block_count();
+        let indexes: Vec<u64> = chunks.iter().map(|c| c.index()).collect();
+        let joined = disk.concat_files(&indexes, "joined").unwrap();
+
+        assert_eq!(disk.free_block_count(), free_blocks);
+        assert_eq!(joined.file_size(), 10_000);
+        assert_eq!(disk.read_file(joined.index()).unwrap(), contents);
+        assert_eq!(disk.number_of_files(), 1);
+        assert!(disk.inode_with_name("video.000").is_none());
+
+        // A file that doesn't fill its last block means the files after it are copied
+        let first = disk
+            .create_new_file("first", INodeFlags::default(), vec![1u8; 10])
+            .unwrap();
+        let free_blocks = disk.free_block_count();
+        let joined = disk
+            .concat_files(&[first.index(), joined.index()], "all")
+            .unwrap();
+
+        let mut expected = vec![1u8; 10];
+        expected.extend(contents.iter());
+        assert_eq!(disk.read_file(joined.index()).unwrap(), expected);
+        // The first file's block is freed, the rest are copied to as many blocks as they are freed from
+        assert_eq!(disk.free_block_count(), free_blocks + 1);
+        assert_eq!(disk.number_of_files(), 1);
+    }
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let joined = disk.inode_with_name("all").unwrap();
+    assert_eq!(disk.read_file(joined).unwrap().len(), 10_010);
+    assert!(disk.scrub().unwrap().is_clean());
+
+    assert_eq!(
+        disk.concat_files(&[], "none"),
+        Err(VoxFSError::InvalidConcatenation)
+    );
+    assert_eq!(
+        disk.concat_files(&[joined, joined], "twice"),
+        Err(VoxFSError::InvalidConcatenation)
+    );
+    assert_eq!(
+        disk.concat_files(&[joined, 1000], "missing"),
+        Err(VoxFSError::CouldNotFindINode)
+    );
+}
@@ -1,5 +1,7 @@
 extern crate voxfs;
-use voxfs::{Disk, INodeFlags, TagFlags, VoxFSError};
+use std::cell::Cell;
+use std::rc::Rc;
+use voxfs::{Disk, INodeFlags, TagFlags, TagQuery, VoxFSError};
 
 mod common;
 use common::*;
@@ -691,3 +693,821 @@ fn test_tag_with_names_fail_2() {
         ])
         .is_err());
 }
+
+#[test]
+fn test_compact_tag() {
+    let mut handler = Handler::new(4096 * 2000);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let custom_tag = disk
+        .create_new_tag("compact", TagFlags::new(true, true))
+        .unwrap();
+
+    let mut comp_nodes = Vec::new();
+
+    // 12 local members, a full indirect block of 509 and a second indirect block of 100
+    for i in 0..621 {
+        comp_nodes.push(
+            disk.create_new_file(
+                &format!("test_file_{}", i),
+                INodeFlags::new(true, true, true, false),
+                vec![1u8; 10],
+            )
+            .unwrap(),
+        );
+
+        disk.apply_tag(custom_tag.index(), comp_nodes[i].index())
+            .unwrap();
+    }
+
+    // Already packed so nothing changes
+    assert_eq!(disk.compact_tag(custom_tag.index()).unwrap(), 0);
+
+    // Leave gaps in the tag block and the first indirect block without emptying either
+    for _ in 0..5 {
+        disk.remove_tag_from_inode(custom_tag.index(), comp_nodes.remove(0).index())
+            .unwrap();
+    }
+
+    for _ in 0..200 {
+        disk.remove_tag_from_inode(custom_tag.index(), comp_nodes.remove(7).index())
+            .unwrap();
+    }
+
+    let available = disk.available_data_blocks();
+    let members = disk.list_nodes_with_tag(custom_tag.index()).unwrap();
+    members.iter().for_each(|m| assert!(comp_nodes.contains(m)));
+    assert_eq!(members.len(), comp_nodes.len());
+
+    assert_eq!(disk.compact_tag(custom_tag.index()).unwrap(), 1);
+    assert_eq!(disk.available_data_blocks(), available + 1);
+    assert_eq!(
+        disk.list_nodes_with_tag(custom_tag.index()).unwrap(),
+        members
+    );
+    assert_eq!(disk.compact_tag(custom_tag.index()).unwrap(), 0);
+
+    // The compacted tag survives reopening and can still be modified
+    drop(disk);
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(
+        disk.list_nodes_with_tag(custom_tag.index()).unwrap(),
+        members
+    );
+
+    disk.remove_tag_from_inode(custom_tag.index(), members[100].index())
+        .unwrap();
+    disk.apply_tag(custom_tag.index(), members[100].index())
+        .unwrap();
+    assert_eq!(
+        disk.list_nodes_with_tag(custom_tag.index()).unwrap().len(),
+        members.len()
+    );
+
+    assert_eq!(
+        disk.compact_tag(100).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+}
+
+#[test]
+fn test_apply_tag_reads_only_the_tail() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: Handler::new(4096 * 2000),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let custom_tag = disk
+        .create_new_tag("fast", TagFlags::new(true, true))
+        .unwrap();
+
+    let mut nodes = Vec::new();
+
+    for i in 0..1100 {
+        nodes.push(
+            disk.create_new_file(
+                &format!("test_file_{}", i),
+                INodeFlags::new(true, true, true, false),
+                Vec::new(),
+            )
+            .unwrap(),
+        );
+    }
+
+    // Fill the tag block and two indirect blocks
+    for node in nodes[..1030].iter() {
+        disk.apply_tag(custom_tag.index(), node.index()).unwrap();
+    }
+
+    // Each apply reads at most the last indirect block, however long the chain is
+    for node in nodes[1030..].iter() {
+        let before = reads.get();
+        disk.apply_tag(custom_tag.index(), node.index()).unwrap();
+        assert!(reads.get() - before <= 1);
+    }
+
+    assert_eq!(
+        disk.list_nodes_with_tag(custom_tag.index()).unwrap().len(),
+        1100
+    );
+
+    // Members in the indirect blocks are still found once the tag block has room again
+    disk.remove_tag_from_inode(custom_tag.index(), nodes[0].index())
+        .unwrap();
+    assert_eq!(
+        disk.apply_tag(custom_tag.index(), nodes[600].index()).err(),
+        Some(VoxFSError::TagAlreadyAppliedToINode)
+    );
+    disk.apply_tag(custom_tag.index(), nodes[0].index())
+        .unwrap();
+
+    // Reopening rebuilds the index from the disk
+    drop(disk);
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(
+        disk.apply_tag(custom_tag.index(), nodes[1099].index())
+            .err(),
+        Some(VoxFSError::TagAlreadyAppliedToINode)
+    );
+    assert_eq!(
+        disk.list_nodes_with_tag(custom_tag.index()).unwrap().len(),
+        1100
+    );
+}
+
+#[test]
+fn test_tags_for_inode() {
+    let mut handler = Handler::new(4096 * 2000);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let large = disk.create_new_tag("large", TagFlags::default()).unwrap();
+    let small = disk.create_new_tag("small", TagFlags::default()).unwrap();
+    let unused = disk.create_new_tag("unused", TagFlags::default()).unwrap();
+
+    let mut nodes = Vec::new();
+
+    // Enough members for the large tag to need indirect blocks
+    for i in 0..600 {
+        let node = disk
+            .create_new_file(&format!("test_file_{}", i), flags, Vec::new())
+            .unwrap();
+        disk.apply_tag(large.index(), node.index()).unwrap();
+        nodes.push(node);
+    }
+
+    disk.apply_tag(small.index(), nodes[0].index()).unwrap();
+    disk.apply_tag(small.index(), nodes[599].index()).unwrap();
+
+    let names = |tags: Vec<voxfs::TagBlock>| {
+        let mut names: Vec<String> = tags.iter().map(|t| t.name_string()).collect();
+        names.sort();
+        return names;
+    };
+
+    assert_eq!(
+        names(disk.tags_for_inode(nodes[599].index()).unwrap()),
+        vec!["large", "small"]
+    );
+    assert_eq!(
+        names(disk.tags_for_inode(nodes[300].index()).unwrap()),
+        vec!["large"]
+    );
+
+    // The index follows later changes
+    disk.remove_tag_from_inode(large.index(), nodes[599].index())
+        .unwrap();
+    disk.apply_tag(unused.index(), nodes[300].index()).unwrap();
+    disk.delete_tag(small.index()).unwrap();
+
+    assert!(disk.tags_for_inode(nodes[599].index()).unwrap().is_empty());
+    assert_eq!(
+        names(disk.tags_for_inode(nodes[300].index()).unwrap()),
+        vec!["large", "unused"]
+    );
+
+    assert_eq!(
+        disk.tags_for_inode(10_000).err(),
+        Some(VoxFSError::CouldNotFindINode)
+    );
+}
+
+#[test]
+fn test_tag_member_count() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("count", TagFlags::default()).unwrap();
+    assert_eq!(disk.tag_member_count(tag.index()).unwrap(), 0);
+
+    // Enough members to need an indirect block
+    for i in 0..21 {
+        let inode = disk
+            .create_new_file(
+                &format!("test_file_{}", i),
+                INodeFlags::default(),
+                vec![1, 2, 3],
+            )
+            .unwrap();
+
+        disk.apply_tag(tag.index(), inode.index()).unwrap();
+    }
+
+    assert_eq!(disk.tag_member_count(tag.index()).unwrap(), 21);
+
+    assert_eq!(
+        disk.tag_member_count(tag.index() + 1).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+}
+
+#[test]
+fn test_tag_appearance() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+    let mut members = Vec::new();
+
+    // Exactly enough members to fill the tag itself
+    for i in 0..12 {
+        let inode = disk
+            .create_new_file(&format!("photo_{}", i), INodeFlags::default(), vec![])
+            .unwrap();
+
+        disk.apply_tag(tag.index(), inode.index()).unwrap();
+        members.push(inode);
+    }
+
+    assert_eq!(tag.color(), None);
+
+    // The colour doesn't take a member slot
+    let tag = disk.set_tag_color(tag.index(), Some(0x3366ff)).unwrap();
+    assert_eq!(tag.color(), Some(0x3366ff));
+    assert_eq!(tag.number_of_pointers(), 12);
+    assert!(tag.indirect_pointer().is_none());
+
+    let tag = disk.set_tag_icon(tag.index(), Some('📷')).unwrap();
+    assert_eq!(tag.color(), Some(0x3366ff));
+    assert_eq!(tag.icon(), Some('📷'));
+
+    assert_eq!(
+        disk.set_tag_color(tag.index(), Some(0x1000000)).err(),
+        Some(VoxFSError::InvalidTagColor)
+    );
+    assert_eq!(
+        disk.set_tag_icon(tag.index() + 1, None).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+
+    drop(disk);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let tag = disk.list_tags()[1];
+    assert_eq!(tag.color(), Some(0x3366ff));
+    assert_eq!(tag.icon(), Some('📷'));
+    assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap(), members);
+}
+
+#[test]
+fn test_migrate_legacy_tag_appearance() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+    let file = disk
+        .create_new_file("photo", INodeFlags::default(), vec![])
+        .unwrap();
+    disk.apply_tag(tag.index(), file.index()).unwrap();
+    let tag_address = disk.geometry().tag_table_start() + tag.index() * 256;
+    disk.close().unwrap();
+
+    // Rewrite the disk as version 1, which kept the colour and icon in the last member slot and used the bytes
+    // of the appearance for the end of the name
+    let record = &mut handler.disk[tag_address as usize..tag_address as usize + 256];
+    let legacy = ((('📷' as u64) << 32) | (1 << 24) | 0x3366ff).to_le_bytes();
+    record[248..256].copy_from_slice(&legacy);
+    fix_checksum(record, 140);
+
+    handler.disk[0] = 1;
+    fix_checksum(&mut handler.disk[..128], 60);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.needs_migration());
+    assert_eq!(disk.list_tags()[1].color(), Some(0x3366ff));
+
+    disk.migrate().unwrap();
+    disk.close().unwrap();
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let tag = disk.list_tags()[1];
+    assert_eq!(tag.name_string(), "photos");
+    assert_eq!(tag.color(), Some(0x3366ff));
+    assert_eq!(tag.icon(), Some('📷'));
+    assert_eq!(tag.members()[11], 0);
+    assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap(), vec![file]);
+}
+
+#[test]
+fn test_query_tags() {
+    let mut handler = Handler::new(4096 * 30); // Disk size of 120 KiB
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let work = disk.create_new_tag("work", TagFlags::default()).unwrap();
+    let archived = disk
+        .create_new_tag("archived", TagFlags::default())
+        .unwrap();
+
+    for i in 0..20 {
+        let inode = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), vec![1])
+            .unwrap();
+
+        if i % 2 == 0 {
+            disk.apply_tag(work.index(), inode.index()).unwrap();
+        }
+
+        if i % 3 == 0 {
+            disk.apply_tag(archived.index(), inode.index()).unwrap();
+        }
+    }
+
+    let names = |query: &str, disk: &mut Disk<common::Error>| -> Vec<String> {
+        return disk
+            .query_tags(&TagQuery::parse(query).unwrap())
+            .unwrap()
+            .iter()
+            .map(|i| i.name())
+            .collect();
+    };
+
+    let expected = |f: &dyn Fn(usize) -> bool| -> Vec<String> {
+        return (0..20)
+            .filter(|i| f(*i))
+            .map(|i| format!("file_{}", i))
+            .collect();
+    };
+
+    assert_eq!(names("work", &mut disk), expected(&|i| i % 2 == 0));
+    assert_eq!(
+        names("work & !archived", &mut disk),
+        expected(&|i| i % 2 == 0 && i % 3 != 0)
+    );
+    assert_eq!(
+        names("work | archived", &mut disk),
+        expected(&|i| i % 2 == 0 || i % 3 == 0)
+    );
+    assert_eq!(
+        names("!(work | archived)", &mut disk),
+        expected(&|i| i % 2 != 0 && i % 3 != 0)
+    );
+
+    assert_eq!(
+        disk.query_tags(&TagQuery::parse("work & missing").unwrap())
+            .err(),
+        Some(VoxFSError::NoTagsWithNames(vec!["missing".to_string()]))
+    );
+}
+
+#[test]
+fn test_list_untagged_inodes() {
+    let mut handler = Handler::new(4096 * 200);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("tagged", TagFlags::default()).unwrap();
+    assert!(disk.list_untagged_inodes().unwrap().is_empty());
+
+    let mut nodes = Vec::new();
+
+    // Enough members for the tag to need an indirect block
+    for i in 0..30 {
+        let inode = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), vec![1])
+            .unwrap();
+
+        if i % 2 == 0 {
+            disk.apply_tag(tag.index(), inode.index()).unwrap();
+        }
+
+        nodes.push(inode);
+    }
+
+    let untagged = |disk: &mut Disk<common::Error>| -> Vec<u64> {
+        return disk
+            .list_untagged_inodes()
+            .unwrap()
+            .iter()
+            .map(|i| i.index())
+            .collect();
+    };
+
+    let odd: Vec<u64> = nodes.iter().skip(1).step_by(2).map(|i| i.index()).collect();
+    assert_eq!(untagged(&mut disk), odd);
+
+    // The list follows later changes to the tag
+    disk.remove_tag_from_inode(tag.index(), nodes[0].index())
+        .unwrap();
+    disk.apply_tag(tag.index(), nodes[1].index()).unwrap();
+
+    let mut expected = odd[1..].to_vec();
+    expected.insert(0, nodes[0].index());
+    assert_eq!(untagged(&mut disk), expected);
+
+    disk.delete_tag(tag.index()).unwrap();
+
+    assert_eq!(untagged(&mut disk).len(), nodes.len());
+}
+
+#[test]
+fn test_merge_tags() {
+    let mut handler = Handler::new(4096 * 2000);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let src = disk.create_new_tag("src", TagFlags::default()).unwrap();
+    let dst = disk.create_new_tag("dst", TagFlags::default()).unwrap();
+
+    let mut nodes = Vec::new();
+
+    // Both tags need indirect blocks and share the members 400 to 599
+    for i in 0..1000 {
+        let node = disk
+            .create_new_file(
+                &format!("test_file_{}", i),
+                INodeFlags::default(),
+                Vec::new(),
+            )
+            .unwrap();
+
+        if i < 600 {
+            disk.apply_tag(src.index(), node.index()).unwrap();
+        }
+
+        if i >= 400 {
+            disk.apply_tag(dst.index(), node.index()).unwrap();
+        }
+
+        nodes.push(node);
+    }
+
+    assert_eq!(
+        disk.merge_tags(src.index(), src.index()).err(),
+        Some(VoxFSError::SameSourceAndDestinationTag)
+    );
+
+    let available = disk.available_data_blocks();
+    assert_eq!(disk.merge_tags(src.index(), dst.index()).unwrap(), 400);
+
+    assert_eq!(disk.tag_with_name("src"), None);
+    assert_eq!(disk.tag_member_count(dst.index()).unwrap(), 1000);
+    // The source's two indirect blocks are freed and the destination's two hold every member
+    assert_eq!(disk.available_data_blocks(), available + 2);
+
+    drop(disk);
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+
+    let mut members = disk.list_nodes_with_tag(dst.index()).unwrap();
+    members.sort_by_key(|m| m.index());
+    assert_eq!(members, nodes);
+
+    // The merged chain can still be modified
+    disk.remove_tag_from_inode(dst.index(), nodes[0].index())
+        .unwrap();
+    disk.apply_tag(dst.index(), nodes[0].index()).unwrap();
+    assert_eq!(disk.tag_member_count(dst.index()).unwrap(), 1000);
+}
+
+#[test]
+fn test_move_members() {
+    let mut handler = Handler::new(4096 * 200);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let src = disk.create_new_tag("src", TagFlags::default()).unwrap();
+    let dst = disk.create_new_tag("dst", TagFlags::default()).unwrap();
+
+    let mut nodes = Vec::new();
+
+    for i in 0..30 {
+        let node = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), Vec::new())
+            .unwrap();
+        disk.apply_tag(src.index(), node.index()).unwrap();
+        nodes.push(node);
+    }
+
+    disk.apply_tag(dst.index(), nodes[1].index()).unwrap();
+
+    let odd =
+        |i: &voxfs::INode| i.name().trim_start_matches("file_").parse::<u64>().unwrap() % 2 == 1;
+    assert_eq!(
+        disk.move_members(src.index(), dst.index(), &odd).unwrap(),
+        14
+    );
+
+    let names = |disk: &mut Disk<common::Error>, tag: u64| -> Vec<String> {
+        let mut names: Vec<String> = disk
+            .list_nodes_with_tag(tag)
+            .unwrap()
+            .iter()
+            .map(|i| i.name())
+            .collect();
+        names.sort();
+        return names;
+    };
+
+    let expected = |remainder: usize| -> Vec<String> {
+        let mut names: Vec<String> = (0..30)
+            .filter(|i| i % 2 == remainder)
+            .map(|i| format!("file_{}", i))
+            .collect();
+        names.sort();
+        return names;
+    };
+
+    assert_eq!(names(&mut disk, src.index()), expected(0));
+    assert_eq!(names(&mut disk, dst.index()), expected(1));
+    assert_eq!(
+        disk.tags_for_inode(nodes[3].index()).unwrap(),
+        vec![disk.list_tags()[2]]
+    );
+
+    // Moving nothing leaves both tags as they were
+    assert_eq!(
+        disk.move_members(src.index(), dst.index(), &|_| false)
+            .unwrap(),
+        0
+    );
+    assert_eq!(disk.tag_member_count(src.index()).unwrap(), 15);
+
+    assert_eq!(
+        disk.move_members(src.index(), 100, &|_| true).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+}
+
+#[test]
+fn test_chain_stats() {
+    let mut handler = Handler::new(4096 * 2000);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+
+    let tag = disk
+        .create_new_tag("chained", TagFlags::new(true, true))
+        .unwrap();
+
+    let mut nodes = Vec::new();
+
+    // 12 local members, a full indirect block of 509 and a second indirect block of 100
+    for i in 0..621 {
+        let node = disk
+            .create_new_file(
+                &format!("test_file_{}", i),
+                INodeFlags::new(true, true, true, false),
+                vec![1u8; 10],
+            )
+            .unwrap();
+
+        disk.apply_tag(tag.index(), node.index()).unwrap();
+        nodes.push(node);
+    }
+
+    let stats = disk.chain_stats().unwrap();
+    assert_eq!(stats.inodes().len(), 621);
+    assert!(stats.inodes().iter().all(|c| c.indirect_blocks() == 0));
+    assert_eq!(stats.indirect_blocks(), 2);
+    assert_eq!(stats.reclaimable_blocks(), 0);
+
+    // Emptying most of the first indirect block leaves room for the second's members
+    for node in nodes[12..212].iter() {
+        disk.remove_tag_from_inode(tag.index(), node.index())
+            .unwrap();
+    }
+
+    let stats = disk.chain_stats().unwrap();
+    let chain = stats
+        .tags()
+        .iter()
+        .find(|c| c.index() == tag.index())
+        .unwrap();
+    assert_eq!(chain.name(), "chained");
+    assert_eq!(chain.indirect_blocks(), 2);
+    assert_eq!(chain.entries(), 409);
+    assert_eq!(chain.fill_percent(), 409 * 100 / (509 * 2));
+    assert_eq!(chain.reclaimable_blocks(), 1);
+    assert_eq!(stats.compactable().count(), 1);
+
+    disk.compact_tag(tag.index()).unwrap();
+
+    let stats = disk.chain_stats().unwrap();
+    assert_eq!(stats.indirect_blocks(), 1);
+    assert_eq!(stats.compactable().count(), 0);
+}
+
+#[test]
+fn test_tag_listing_cache() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handler = CountingHandler {
+        disk: Handler::new(4096 * 200),
+        reads: reads.clone(),
+    };
+    let mut manager = Manager::new();
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("served", TagFlags::default()).unwrap();
+
+    // Enough members for the tag to need indirect blocks
+    let mut files = Vec::new();
+
+    for i in 0..20 {
+        let file = disk
+            .create_new_file(&format!("file {:02}", i), INodeFlags::default(), vec![i])
+            .unwrap();
+        disk.apply_tag(tag.index(), file.index()).unwrap();
+        files.push(file);
+    }
+
+    reads.set(0);
+    let listing = disk.tag_listing(tag.index()).unwrap();
+    assert_eq!(listing.len(), 20);
+    assert_eq!(listing[3], ("file 03".to_string(), files[3].index()));
+    assert!(reads.get() > 0);
+
+    // Later lookups don't read the chain again
+    reads.set(0);
+    assert_eq!(
+        disk.lookup_in_tag(tag.index(), "file 17").unwrap(),
+        Some(files[17].index())
+    );
+    assert_eq!(disk.lookup_in_tag(tag.index(), "missing").unwrap(), None);
+    assert_eq!(disk.tag_listing(tag.index()).unwrap(), listing);
+    assert_eq!(reads.get(), 0);
+
+    // Changing the members replaces the listing
+    disk.remove_tag_from_inode(tag.index(), files[17].index())
+        .unwrap();
+    assert_eq!(disk.lookup_in_tag(tag.index(), "file 17").unwrap(), None);
+
+    disk.delete_file(files[2].index()).unwrap();
+    let extra = disk
+        .create_new_file_in_tag("extra", INodeFlags::default(), Vec::new(), tag.index())
+        .unwrap();
+    assert_eq!(
+        disk.lookup_in_tag(tag.index(), "extra").unwrap(),
+        Some(extra.index())
+    );
+    assert_eq!(disk.lookup_in_tag(tag.index(), "file 02").unwrap(), None);
+    assert_eq!(disk.tag_listing(tag.index()).unwrap().len(), 19);
+
+    disk.invalidate_caches();
+    reads.set(0);
+    assert_eq!(disk.tag_listing(tag.index()).unwrap().len(), 19);
+    assert!(reads.get() > 0);
+
+    disk.delete_tag(tag.index()).unwrap();
+    assert_eq!(
+        disk.tag_listing(tag.index()).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+}
+
+#[test]
+fn test_delete_tag_returning_members() {
+    let mut handler = Handler::new(4096 * 200);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("holiday", TagFlags::default()).unwrap();
+
+    let mut expected = Vec::new();
+
+    for i in 0..20 {
+        let node = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), Vec::new())
+            .unwrap();
+
+        if i % 3 != 0 {
+            disk.apply_tag(tag.index(), node.index()).unwrap();
+            expected.push(node.index());
+        }
+    }
+
+    expected.sort();
+
+    assert_eq!(
+        disk.delete_tag_returning_members(tag.index()).unwrap(),
+        expected
+    );
+    assert_eq!(disk.tag_with_name("holiday"), None);
+    assert_eq!(
+        disk.delete_tag_returning_members(tag.index()).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+}
+
+#[test]
+fn test_archive_tag() {
+    let mut handler = Handler::new(4096 * 200);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("old", TagFlags::read_only()).unwrap();
+
+    let mut members = Vec::new();
+
+    for i in 0..15 {
+        let node = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), Vec::new())
+            .unwrap();
+        disk.apply_tag(tag.index(), node.index()).unwrap();
+        members.push(node);
+    }
+
+    let free_blocks = disk.available_data_blocks();
+    let archived = disk.archive_tag(tag.index()).unwrap();
+    assert!(archived.flags().archived());
+    assert!(!archived.flags().write());
+    assert_eq!(disk.list_archived_tags(), vec![archived]);
+    assert_eq!(disk.available_data_blocks(), free_blocks);
+
+    // The name stays reserved while the tag is archived
+    assert_eq!(disk.tag_with_name("old"), Some(tag.index()));
+    assert!(disk.create_new_tag("old", TagFlags::default()).is_err());
+
+    // The flag and the members survive reopening
+    drop(disk);
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.list_archived_tags(), vec![archived]);
+    assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap(), members);
+
+    let restored = disk.restore_tag(tag.index()).unwrap();
+    assert!(!restored.flags().archived());
+    assert_eq!(restored.flags(), TagFlags::read_only());
+    assert!(disk.list_archived_tags().is_empty());
+    assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap(), members);
+
+    assert_eq!(
+        disk.archive_tag(100).err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+}
+
+#[test]
+fn test_rename_tag() {
+    let mut handler = Handler::new(4096 * 200);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let tag = disk.create_new_tag("photos", TagFlags::default()).unwrap();
+    disk.create_new_tag("music", TagFlags::default()).unwrap();
+
+    let mut members = Vec::new();
+
+    for i in 0..20 {
+        let node = disk
+            .create_new_file(&format!("file_{}", i), INodeFlags::default(), Vec::new())
+            .unwrap();
+        disk.apply_tag(tag.index(), node.index()).unwrap();
+        members.push(node);
+    }
+
+    let renamed = disk.rename_tag(tag.index(), "pictures").unwrap();
+    assert_eq!(renamed.name_string(), "pictures");
+    assert_eq!(renamed.index(), tag.index());
+    assert_eq!(disk.tag_with_name("photos"), None);
+    assert_eq!(disk.tag_with_name("pictures"), Some(tag.index()));
+
+    assert_eq!(
+        disk.rename_tag(tag.index(), "music").err(),
+        Some(VoxFSError::TagExistsWithName(String::from("music")))
+    );
+    assert_eq!(
+        disk.rename_tag(tag.index(), "bad/name").err(),
+        Some(VoxFSError::InvalidTagName)
+    );
+    assert_eq!(
+        disk.rename_tag(100, "other").err(),
+        Some(VoxFSError::CouldNotFindTag)
+    );
+
+    // Renaming a tag to its own name is allowed
+    disk.rename_tag(tag.index(), "pictures").unwrap();
+
+    drop(disk);
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.tag_with_name("pictures"), Some(tag.index()));
+    assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap(), members);
+}
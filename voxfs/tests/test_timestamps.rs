@@ -0,0 +1,106 @@
+#![cfg(feature = "timestamps")]
+extern crate voxfs;
+use chrono::{TimeZone, Utc};
+use voxfs::{
+    probe, timestamp_from_unix, Disk, INode, INodeFlags, VoxFSError, MAX_TIMESTAMP_SECONDS,
+    MIN_TIMESTAMP_SECONDS,
+};
+
+mod common;
+use common::*;
+
+fn find_inode(disk: &Disk<Error>, index: u64) -> INode {
+    return disk
+        .list_inodes()
+        .into_iter()
+        .find(|inode| inode.index() == index)
+        .unwrap();
+}
+
+#[test]
+fn test_times_outside_nanosecond_range() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("old", INodeFlags::read_write(), vec![1, 2, 3])
+        .unwrap();
+
+    let moon_landing = Utc.ymd(1969, 7, 20).and_hms_nano(20, 17, 40, 500);
+    let far_future = Utc.ymd(2300, 1, 1).and_hms(0, 0, 0);
+    let before_range = Utc.ymd(1800, 1, 1).and_hms(0, 0, 0);
+
+    disk.set_file_times(file.index(), moon_landing, far_future, before_range)
+        .unwrap();
+    disk.close().unwrap();
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let inode = find_inode(&disk, file.index());
+
+    assert_eq!(inode.creation_time(), moon_landing);
+    assert_eq!(inode.modified_time(), far_future);
+    // Times outside of the range are clamped to it
+    assert_eq!(
+        inode.access_time(),
+        timestamp_from_unix(MIN_TIMESTAMP_SECONDS, 0)
+    );
+    assert_eq!(
+        timestamp_from_unix(i64::MAX, 0),
+        timestamp_from_unix(MAX_TIMESTAMP_SECONDS, 999_999_999)
+    );
+}
+
+#[test]
+fn test_migrate_legacy_times() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("old", INodeFlags::read_write(), vec![1, 2, 3])
+        .unwrap();
+
+    let time = Utc.ymd(1960, 3, 4).and_hms_nano(5, 6, 7, 8);
+    disk.set_file_times(file.index(), time, time, time).unwrap();
+    let inode_address = disk.geometry().inode_table_start() + file.index() * 256;
+    disk.close().unwrap();
+
+    // Rewrite the disk as version 0, which stored times as nanoseconds since the epoch in a u64
+    let legacy = (time.timestamp_nanos() as u64).to_le_bytes();
+    let record = &mut handler.disk[inode_address as usize..inode_address as usize + 256];
+    for offset in [142, 150, 158].iter() {
+        record[*offset..*offset + 8].copy_from_slice(&legacy);
+    }
+    fix_checksum(record, 166);
+
+    handler.disk[0] = 0;
+    fix_checksum(&mut handler.disk[..128], 60);
+
+    assert_eq!(probe(&handler).unwrap().version(), 0);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert!(disk.needs_migration());
+
+    // Times are converted as they are read but nothing can be written until the disk is migrated
+    assert_eq!(find_inode(&disk, file.index()).creation_time(), time);
+    assert_eq!(disk.read_file(file.index()).unwrap(), vec![1, 2, 3]);
+    assert_eq!(
+        disk.create_new_file("new", INodeFlags::read_write(), vec![])
+            .err(),
+        Some(VoxFSError::MigrationRequired)
+    );
+
+    disk.migrate().unwrap();
+    assert!(!disk.needs_migration());
+    disk.create_new_file("new", INodeFlags::read_write(), vec![])
+        .unwrap();
+    disk.close().unwrap();
+
+    assert_eq!(probe(&handler).unwrap().version(), 2);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    let inode = find_inode(&disk, file.index());
+    assert_eq!(inode.creation_time(), time);
+    assert_eq!(inode.access_time(), time);
+}
@@ -0,0 +1,227 @@
+extern crate voxfs;
+use voxfs::{
+    Disk, FormatOptions, HistoryOperation, INodeFlags, MountOptions, TagFlags, VoxFSError,
+};
+
+mod common;
+use common::*;
+
+#[test]
+fn test_truncate_file() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let contents: Vec<u8> = (0..4096 * 3).map(|i| (i % 251) as u8).collect();
+    let file = disk
+        .create_new_file("file", flags, contents.clone())
+        .unwrap();
+    let tag = disk.create_new_tag("tag", TagFlags::default()).unwrap();
+    disk.apply_tag(tag.index(), file.index()).unwrap();
+    let free_blocks = disk.free_block_count();
+
+    let truncated = disk.truncate_file(file.index(), 5000).unwrap();
+    assert_eq!(truncated.file_size(), 5000);
+    assert_eq!(truncated.creation_time(), file.creation_time());
+    assert_eq!(disk.read_file(file.index()).unwrap(), contents[..5000]);
+    assert_eq!(disk.free_block_count(), free_blocks + 1);
+    assert_eq!(
+        disk.file_size(file.index()).unwrap().physical_size,
+        4096 * 2
+    );
+
+    // The old bytes past the end don't come back when the file grows again
+    disk.append_file_bytes(file.index(), &vec![9; 10]).unwrap();
+    let mut expected = contents[..5000].to_vec();
+    expected.extend_from_slice(&[9; 10]);
+    assert_eq!(disk.read_file(file.index()).unwrap(), expected);
+
+    disk.truncate_file(file.index(), 6000).unwrap();
+    expected.resize(6000, 0);
+    assert_eq!(disk.read_file(file.index()).unwrap(), expected);
+
+    disk.truncate_file(file.index(), 0).unwrap();
+    assert!(disk.read_file(file.index()).unwrap().is_empty());
+    assert_eq!(disk.free_block_count(), free_blocks + 3);
+    assert_eq!(disk.list_nodes_with_tag(tag.index()).unwrap().len(), 1);
+
+    drop(disk);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.list_inodes()[0].file_size(), 0);
+    assert_eq!(disk.free_block_count(), free_blocks + 3);
+}
+
+#[test]
+fn test_truncate_file_with_indirect_inodes() {
+    let mut handler = Handler::new(4096 * 60);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::default();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let start_free = disk.free_block_count();
+    let mut contents = vec![0u8; 4096];
+    let file = disk
+        .create_new_file("file", flags, contents.clone())
+        .unwrap();
+
+    // Files between each append leave the file in separate extents, more than its inode can hold
+    for i in 1..10u8 {
+        disk.create_new_file(&format!("filler {}", i), flags, vec![i])
+            .unwrap();
+        disk.append_file_bytes(file.index(), &vec![i; 4096])
+            .unwrap();
+        contents.extend_from_slice(&vec![i; 4096]);
+    }
+
+    let free_blocks = disk.free_block_count();
+    // 10 data blocks, 9 fillers and at least one indirect inode
+    assert!(start_free - free_blocks > 19);
+
+    disk.truncate_file(file.index(), 4096 * 2 + 1).unwrap();
+    assert_eq!(
+        disk.read_file(file.index()).unwrap(),
+        contents[..4096 * 2 + 1]
+    );
+    assert_eq!(start_free - disk.free_block_count(), 3 + 9);
+
+    drop(disk);
+
+    let mut disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(
+        disk.read_file(file.index()).unwrap(),
+        contents[..4096 * 2 + 1]
+    );
+
+    disk.delete_file(file.index()).unwrap();
+    assert_eq!(start_free - disk.free_block_count(), 9);
+}
+
+#[test]
+fn test_truncate_file_to_empty_then_grow() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![1; 5000])
+        .unwrap();
+    let free_blocks = disk.free_block_count();
+
+    disk.truncate_file(file.index(), 0).unwrap();
+    let grown = disk.truncate_file(file.index(), 6000).unwrap();
+    assert_eq!(grown.file_size(), 6000);
+    assert_eq!(disk.read_file(file.index()).unwrap(), vec![0; 6000]);
+    assert_eq!(disk.free_block_count(), free_blocks);
+
+    drop(disk);
+
+    let disk = Disk::open_disk(&mut handler, &mut manager).unwrap();
+    assert_eq!(disk.read_file(file.index()).unwrap(), vec![0; 6000]);
+}
+
+#[test]
+fn test_truncate_file_to_empty_then_append() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![1; 100])
+        .unwrap();
+
+    disk.truncate_file(file.index(), 0).unwrap();
+    disk.append_file_bytes(file.index(), &vec![2; 10]).unwrap();
+    assert_eq!(disk.read_file(file.index()).unwrap(), vec![2; 10]);
+
+    disk.append_file_bytes(file.index(), &vec![3; 4096])
+        .unwrap();
+    let mut expected = vec![2; 10];
+    expected.extend_from_slice(&[3; 4096]);
+    assert_eq!(disk.read_file(file.index()).unwrap(), expected);
+    assert_eq!(
+        disk.file_size(file.index()).unwrap().physical_size,
+        4096 * 2
+    );
+}
+
+#[test]
+fn test_truncate_file_errors() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = Manager::new();
+
+    let mut disk = Disk::make_new_filesystem(&mut handler, &mut manager).unwrap();
+    let immutable = disk
+        .create_new_file(
+            "immutable",
+            INodeFlags::default().with_immutable(true),
+            vec![1; 100],
+        )
+        .unwrap();
+    let append_only = disk
+        .create_new_file(
+            "append only",
+            INodeFlags::default().with_append_only(true),
+            vec![1; 100],
+        )
+        .unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![1; 100])
+        .unwrap();
+
+    assert_eq!(
+        disk.truncate_file(immutable.index(), 10),
+        Err(VoxFSError::FileIsImmutable)
+    );
+    assert_eq!(
+        disk.truncate_file(append_only.index(), 10),
+        Err(VoxFSError::FileIsAppendOnly)
+    );
+    assert_eq!(
+        disk.truncate_file(file.index() + 10, 10),
+        Err(VoxFSError::CouldNotFindINode)
+    );
+    disk.close().unwrap();
+
+    let options = MountOptions::new().with_busy_protection(true);
+    let mut disk = Disk::open_with_options(&mut handler, &mut manager, options).unwrap();
+    let handle = disk.open_file(file.index()).unwrap();
+    assert_eq!(
+        disk.truncate_file(file.index(), 10),
+        Err(VoxFSError::FileBusy)
+    );
+
+    disk.close_file(handle);
+    assert_eq!(
+        disk.truncate_file(file.index(), 10).unwrap().file_size(),
+        10
+    );
+}
+
+#[test]
+fn test_truncate_file_hash_and_history() {
+    let mut handler = Handler::new(4096 * 40);
+    let mut manager = HashingManager {};
+    let options = FormatOptions::new()
+        .with_content_hashes(true)
+        .with_history_size(4096);
+
+    let mut disk =
+        Disk::make_new_filesystem_with_options(&mut handler, &mut manager, options).unwrap();
+    let file = disk
+        .create_new_file("file", INodeFlags::default(), vec![7; 5000])
+        .unwrap();
+
+    disk.truncate_file(file.index(), 30).unwrap();
+    assert_eq!(
+        disk.file_hash(file.index()).unwrap(),
+        Some(sum_hash(&[7; 30]))
+    );
+    assert_eq!(disk.open_count(file.index()), 0);
+
+    let history = disk.history().unwrap();
+    let last = history.last().unwrap();
+    assert_eq!(last.operation(), HistoryOperation::TruncateFile);
+    assert_eq!(last.subject(), file.index());
+}
@@ -0,0 +1,60 @@
+extern crate voxfs;
+use voxfs::volumes::{VolumeError, VolumeTable, VOLUME_TABLE_SIZE};
+use voxfs::{Disk, INodeFlags, MemoryDiskHandler};
+
+mod common;
+use common::Manager;
+
+#[test]
+fn test_independent_volumes() {
+    let mut handler = MemoryDiskHandler::new(VOLUME_TABLE_SIZE as usize + 4096 * 400);
+    let mut manager = Manager::new();
+    let flags = INodeFlags::new(true, true, true, false);
+
+    let table = VolumeTable::create(
+        &mut handler,
+        &[("first", 4096 * 200), ("second", 4096 * 200)],
+    )
+    .unwrap();
+
+    for (name, contents) in [("first", vec![1u8; 10_000]), ("second", vec![2u8; 5_000])].iter() {
+        let mut volume = table.open(&mut handler, name).unwrap();
+        let mut disk = Disk::make_new_filesystem(&mut volume, &mut manager).unwrap();
+
+        disk.create_new_file(name, flags, contents.clone()).unwrap();
+    }
+
+    // Reopen from the raw bytes to make sure everything needed was written
+    let mut handler = MemoryDiskHandler::from_bytes(handler.into_bytes());
+    let table = VolumeTable::read(&handler).unwrap();
+
+    let names: Vec<&str> = table.volumes().iter().map(|v| v.name()).collect();
+    assert_eq!(names, vec!["first", "second"]);
+
+    {
+        let mut volume = table.open(&mut handler, "first").unwrap();
+        let disk = Disk::open_disk(&mut volume, &mut manager).unwrap();
+
+        assert_eq!(disk.list_inodes().len(), 1);
+        let index = disk.inode_with_name("first").unwrap();
+        assert_eq!(disk.read_file(index).unwrap(), vec![1u8; 10_000]);
+    }
+
+    let mut volume = table.open(&mut handler, "second").unwrap();
+    let disk = Disk::open_disk(&mut volume, &mut manager).unwrap();
+
+    assert_eq!(disk.list_inodes().len(), 1);
+    let index = disk.inode_with_name("second").unwrap();
+    assert_eq!(disk.read_file(index).unwrap(), vec![2u8; 5_000]);
+}
+
+#[test]
+fn test_missing_volume() {
+    let mut handler = MemoryDiskHandler::new(VOLUME_TABLE_SIZE as usize + 4096 * 100);
+    let table = VolumeTable::create(&mut handler, &[("only", 4096 * 100)]).unwrap();
+
+    assert_eq!(
+        table.open(&mut handler, "other").err(),
+        Some(VolumeError::CouldNotFindVolume(String::from("other")))
+    );
+}